@@ -0,0 +1,166 @@
+/**
+ * Helpers shared by the CLI scripts.
+ */
+
+import * as anchor from "@coral-xyz/anchor";
+import { BN, Program } from "@coral-xyz/anchor";
+import { AccountInfo, Connection, PublicKey } from "@solana/web3.js";
+import { readFileSync } from "fs";
+import { join } from "path";
+import { parse } from "smol-toml";
+import { KeypairSigner, Signer } from "./signer";
+
+export const DELEGATION_PROGRAM = new PublicKey(
+  "DELeGGvXpWV2fqJUhqcF5ZSYMS4JTLjteaAMARRSaeSh"
+);
+
+const CLUSTERS_TOML = join(__dirname, "..", "clusters.toml");
+
+export interface Cluster {
+  /** ER RPC URL by validator pubkey */
+  erUrls: Map<string, string>;
+}
+
+/** Cluster `name` from clusters.toml. */
+export function loadCluster(name: string): Cluster {
+  const toml: any = parse(readFileSync(CLUSTERS_TOML, "utf-8"));
+  const cluster = toml.clusters?.[name];
+  if (!cluster) {
+    throw new Error(`Cluster ${name} is not in clusters.toml`);
+  }
+  return {
+    erUrls: new Map(
+      (cluster.validators ?? []).map((v: any) => [v.pubkey, v.er_rpc_url])
+    ),
+  };
+}
+
+export function vaultAddress(vaultId: BN, programId: PublicKey): PublicKey {
+  return PublicKey.findProgramAddressSync(
+    [Buffer.from("obscura_vault"), vaultId.toArrayLike(Buffer, "le", 8)],
+    programId
+  )[0];
+}
+
+/** PDA of the program from string and raw seeds. */
+export function pda(
+  programId: PublicKey,
+  ...seeds: (string | Buffer)[]
+): PublicKey {
+  return PublicKey.findProgramAddressSync(
+    seeds.map((seed) => (typeof seed === "string" ? Buffer.from(seed) : seed)),
+    programId
+  )[0];
+}
+
+/**
+ * Allowlist, integrator denylist and compliance program an outbound
+ * transfer from `vault` (decoded as `state`) has to pass, or null where
+ * the vault has none.
+ */
+export async function complianceAccounts(
+  connection: Connection,
+  program: Program,
+  vault: PublicKey,
+  state: any
+): Promise<{
+  recipientAllowlist: PublicKey | null;
+  denylist: PublicKey | null;
+  complianceProgram: PublicKey | null;
+}> {
+  const recipientAllowlist = state.allowlistEnabled
+    ? pda(program.programId, "obscura_allowlist", vault.toBuffer())
+    : null;
+  if (state.integrator.equals(PublicKey.default)) {
+    return { recipientAllowlist, denylist: null, complianceProgram: null };
+  }
+  const denylist = pda(
+    program.programId,
+    "obscura_denylist",
+    state.integrator.toBuffer()
+  );
+  let complianceProgram: PublicKey | null = null;
+  const list = await connection.getAccountInfo(denylist);
+  if (list) {
+    const decoded: any = program.coder.accounts.decode("Denylist", list.data);
+    if (!decoded.complianceProgram.equals(PublicKey.default)) {
+      complianceProgram = decoded.complianceProgram;
+    }
+  }
+  return { recipientAllowlist, denylist, complianceProgram };
+}
+
+/** JSON-safe copy of a decoded account: u64s as decimal strings. */
+export function plain(value: any): unknown {
+  if (BN.isBN(value)) return value.toString();
+  if (value instanceof PublicKey) return value.toBase58();
+  if (Array.isArray(value)) return value.map(plain);
+  if (value && typeof value === "object") {
+    return Object.fromEntries(
+      Object.entries(value).map(([k, v]) => [k, plain(v)])
+    );
+  }
+  return value;
+}
+
+/**
+ * Authenticated TEE endpoint for the wallet or signer, by signing the
+ * validator's challenge (as the ephemeral rollups SDK's getAuthToken
+ * does).
+ */
+export async function teeUrl(
+  erUrl: string,
+  wallet: anchor.Wallet | Signer
+): Promise<string> {
+  const signer =
+    "signMessage" in wallet ? wallet : new KeypairSigner(wallet.payer);
+  const pubkey = signer.publicKey.toBase58();
+  const challengeResponse = await fetch(
+    `${erUrl}/auth/challenge?pubkey=${pubkey}`
+  );
+  const { challenge, error } = await challengeResponse.json();
+  if (!challengeResponse.ok || typeof challenge !== "string") {
+    throw new Error(
+      error || `TEE challenge failed: ${challengeResponse.status}`
+    );
+  }
+
+  const signature = await signer.signMessage(Buffer.from(challenge, "utf-8"));
+  const loginResponse = await fetch(`${erUrl}/auth/login`, {
+    method: "POST",
+    headers: { "Content-Type": "application/json" },
+    body: JSON.stringify({
+      pubkey,
+      challenge,
+      signature: anchor.utils.bytes.bs58.encode(signature),
+    }),
+  });
+  const login = await loginResponse.json();
+  if (!loginResponse.ok || typeof login.token !== "string") {
+    throw new Error(login.error || `TEE login failed: ${loginResponse.status}`);
+  }
+  return `${erUrl}?token=${encodeURIComponent(login.token)}`;
+}
+
+/** ER endpoint for reading a vault: the TEE, authenticated, if private. */
+export async function erConnection(
+  erUrl: string,
+  isPrivate: boolean,
+  wallet: anchor.Wallet | Signer
+): Promise<Connection> {
+  const endpoint = isPrivate ? await teeUrl(erUrl, wallet) : erUrl;
+  return new Connection(endpoint, "confirmed");
+}
+
+export async function getAccounts(
+  connection: Connection,
+  addresses: PublicKey[]
+): Promise<(AccountInfo<Buffer> | null)[]> {
+  const infos: (AccountInfo<Buffer> | null)[] = [];
+  for (let i = 0; i < addresses.length; i += 100) {
+    infos.push(
+      ...(await connection.getMultipleAccountsInfo(addresses.slice(i, i + 100)))
+    );
+  }
+  return infos;
+}
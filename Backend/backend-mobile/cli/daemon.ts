@@ -0,0 +1,1017 @@
+/**
+ * obscurad: payment API for app backends.
+ *
+ *   npm run daemon -- --config <daemon.toml>
+ *
+ *   POST /v1/payments        {"vaultId": "7", "recipient": "<pubkey>",
+ *                             "amount": "<lamports>"}
+ *   GET  /v1/payments/<key>  outcome of the payment sent with that key
+ *   POST /v1/quotes          {"operation": "withdraw", "vaultId": "7",
+ *                             "amount": "<lamports>"}
+ *   GET  /health
+ *
+ * A payment is a private_transfer from the vault on the ER of its
+ * validator (through the TEE for a private vault), signed by the daemon
+ * key as the vault owner, a session key or a transfer-scoped permission
+ * holder.  The daemon key can be a keypair file, an AWS KMS key or a
+ * remote signing service (cli/signer.ts).
+ *
+ * Every payment request needs an `Idempotency-Key` header: a retry with
+ * the same key and body returns the first outcome (with
+ * `Idempotent-Replayed: true`) instead of sending again, also after a
+ * restart (cli/idempotency.ts).  The same key with another body is
+ * refused with 422, and 409 while its payment is still landing.
+ *
+ * With `[[tenants]]` configured, one daemon serves several apps: each
+ * tenant authenticates with its own API key (`Authorization: Bearer`),
+ * signs and reads vaults with its own key, only reaches the vaults listed
+ * for it, is rate limited on its own and has its own idempotency keys, so
+ * no tenant can see another's payments or decrypt its private vaults.
+ * Without tenants the API is unauthenticated and `signer` (by default
+ * ANCHOR_WALLET) is the daemon key.
+ *
+ * A fee quote is what an operation would cost on top of its amount: the
+ * vault's protocol fee, the network fee with an estimated priority fee,
+ * and rent for accounts it creates.  It is signed with `quote_signer`
+ * over "obscura-fee-quote:" followed by the quote JSON, exactly as
+ * returned, and expires after `quote_ttl` seconds, so the app can check
+ * a quote relayed by its backend before showing a total.
+ *
+ * `[[webhooks]]` watch a tenant's vaults and deliver signed events to
+ * the app backend (cli/webhooks.ts).
+ *
+ * `[telemetry]`, off by default, reports how many vaults used each
+ * feature per period with differential-privacy noise, and never a vault,
+ * tenant or amount (cli/telemetry.ts).
+ *
+ * Logs are JSON lines (cli/log.ts) carrying the request's correlation
+ * ID: the caller's `X-Request-Id` if it sends a usable one, a new one
+ * otherwise, returned in the response's `X-Request-Id` either way.
+ *
+ * Uses PROGRAM_ID for the deployed program and the IDL from `anchor
+ * build`.  daemon.example.toml documents the configuration.
+ */
+
+import * as anchor from "@coral-xyz/anchor";
+import { BN, Program } from "@coral-xyz/anchor";
+import {
+  AccountInfo,
+  Connection,
+  PublicKey,
+  SendTransactionError,
+} from "@solana/web3.js";
+import { createHash, randomUUID, timingSafeEqual } from "crypto";
+import { readFileSync } from "fs";
+import { join } from "path";
+import { createServer, IncomingMessage, ServerResponse } from "http";
+import { parse } from "smol-toml";
+import * as dotenv from "dotenv";
+import {
+  Cluster,
+  DELEGATION_PROGRAM,
+  complianceAccounts,
+  erConnection,
+  loadCluster,
+  pda,
+  vaultAddress,
+} from "./common";
+import { IdempotencyStore, PaymentRecord } from "./idempotency";
+import { logger, withCorrelation } from "./log";
+import { defaultSigner, loadSigner, SignerWallet } from "./signer";
+import { Telemetry, TelemetryConfig } from "./telemetry";
+import {
+  EVENT_TYPES,
+  EventType,
+  VaultReading,
+  Webhook,
+  Webhooks,
+} from "./webhooks";
+
+dotenv.config();
+
+// Generated by `anchor build`
+const idl = require("../target/idl/obscura_per.json");
+const log = logger("obscurad");
+
+const USAGE = "Usage: npm run daemon -- --config <daemon.toml>";
+/** Largest request body accepted */
+const MAX_BODY = 64 * 1024;
+const POLL_INTERVAL_MS = 1000;
+/** Prefix of the message a fee quote's signature covers */
+const QUOTE_SIGNING_PREFIX = "obscura-fee-quote:";
+/** Lamports per signature */
+const BASE_FEE = 5_000;
+
+/** How each quotable operation runs */
+const QUOTE_OPERATIONS: Record<
+  string,
+  { onL1: boolean; computeUnits: number; outflow: boolean }
+> = {
+  // ER transactions pay no network fees.
+  private_transfer: { onL1: false, computeUnits: 80_000, outflow: true },
+  withdraw: { onL1: true, computeUnits: 80_000, outflow: true },
+  deposit: { onL1: true, computeUnits: 40_000, outflow: false },
+  create_vault: { onL1: true, computeUnits: 40_000, outflow: false },
+};
+
+interface TenantConfig {
+  name: string;
+  /** SHA-256 (hex) of the tenant's API key */
+  apiKeySha256: string;
+  /** Signer of the tenant's payments (see cli/signer.ts) */
+  signer: string;
+  /** Vault IDs the tenant may pay from */
+  vaults: string[];
+  /** Payment API requests allowed per minute */
+  requestsPerMinute: number;
+}
+
+interface DaemonConfig {
+  cluster: string;
+  rpcUrl: string;
+  /** Daemon key without tenants; ANCHOR_WALLET by default */
+  signer?: string;
+  host: string;
+  port: number;
+  stateDir: string;
+  /** Seconds an idempotency key is remembered */
+  idempotencyTtl: number;
+  tenants: TenantConfig[];
+  webhooks: Webhook[];
+  /** Seconds between readings of the vaults webhooks watch */
+  webhookPollInterval: number;
+  /** Signer of fee quotes (see cli/signer.ts); no quotes without one */
+  quoteSigner?: string;
+  /** Seconds a fee quote is valid */
+  quoteTtl: number;
+  /** Noised usage counters (see cli/telemetry.ts); off when null */
+  telemetry: TelemetryConfig | null;
+}
+
+/** One app served by the daemon, isolated from the others. */
+interface Tenant {
+  name: string;
+  keyHash: Buffer | null;
+  wallet: SignerWallet;
+  program: Program;
+  /** Vault IDs the tenant may pay from; null for any */
+  vaults: Set<string> | null;
+  limiter: RateLimiter | null;
+  store: IdempotencyStore;
+  /** ER connections by endpoint, reused while they can read */
+  ers: Map<string, Connection>;
+}
+
+interface Payment {
+  vaultId: string;
+  recipient: string;
+  amount: string;
+}
+
+class HttpError extends Error {
+  constructor(public status: number, message: string) {
+    super(message);
+  }
+}
+
+/** Token bucket of `perMinute` requests a minute, in bursts of as many. */
+class RateLimiter {
+  private tokens: number;
+  private refilledAt = Date.now();
+
+  constructor(private perMinute: number) {
+    this.tokens = perMinute;
+  }
+
+  /** Take a token; returns 0, or the ms until one is available. */
+  take(): number {
+    const now = Date.now();
+    this.tokens = Math.min(
+      this.perMinute,
+      this.tokens + ((now - this.refilledAt) * this.perMinute) / 60_000
+    );
+    this.refilledAt = now;
+    if (this.tokens >= 1) {
+      this.tokens--;
+      return 0;
+    }
+    return Math.ceil(((1 - this.tokens) * 60_000) / this.perMinute);
+  }
+}
+
+function parseArgs(argv: string[]): string {
+  let config: string | null = null;
+  for (let i = 0; i < argv.length; i++) {
+    switch (argv[i]) {
+      case "--config":
+        config = argv[++i];
+        break;
+      default:
+        throw new Error(`Unknown option ${argv[i]}\n${USAGE}`);
+    }
+  }
+  if (config === null) {
+    throw new Error(USAGE);
+  }
+  return config;
+}
+
+function number(table: any, key: string, fallback: number): number {
+  const value = table?.[key] ?? fallback;
+  if (typeof value !== "number" || !Number.isFinite(value) || value < 0) {
+    throw new Error(`daemon config: ${key} must be a non-negative number`);
+  }
+  return value;
+}
+
+function loadTenant(table: any): TenantConfig {
+  const { name, api_key_sha256, signer, vaults } = table ?? {};
+  if (typeof name !== "string" || !/^[\w-]{1,64}$/.test(name)) {
+    throw new Error("daemon config: tenant name must be 1-64 of [A-Za-z0-9_-]");
+  }
+  if (
+    typeof api_key_sha256 !== "string" ||
+    !/^[0-9a-f]{64}$/i.test(api_key_sha256)
+  ) {
+    throw new Error(`daemon config: tenant ${name} needs api_key_sha256`);
+  }
+  if (typeof signer !== "string") {
+    throw new Error(`daemon config: tenant ${name} needs a signer`);
+  }
+  if (
+    !Array.isArray(vaults) ||
+    !vaults.every((v) => typeof v === "string" && /^\d{1,20}$/.test(v))
+  ) {
+    throw new Error(
+      `daemon config: tenant ${name} needs vaults, a list of vault IDs`
+    );
+  }
+  return {
+    name,
+    apiKeySha256: api_key_sha256.toLowerCase(),
+    signer,
+    vaults: vaults.map((v: string) => new BN(v).toString()),
+    requestsPerMinute: number(table, "requests_per_minute", 60),
+  };
+}
+
+function loadWebhook(table: any, tenants: TenantConfig[]): Webhook {
+  const { url, secret, vaults, events, low_balance } = table ?? {};
+  if (typeof url !== "string" || !/^https?:\/\//.test(url)) {
+    throw new Error("daemon config: webhook url must be http(s)");
+  }
+  if (typeof secret !== "string" || secret.length < 16) {
+    throw new Error(`daemon config: webhook ${url} needs a 16+ char secret`);
+  }
+  const tenant = table.tenant ?? "default";
+  const allowed = tenants.length
+    ? tenants.find((t) => t.name === tenant)?.vaults
+    : null;
+  if (tenants.length && !allowed) {
+    throw new Error(`daemon config: webhook ${url} has no known tenant`);
+  }
+  if (
+    !Array.isArray(vaults) ||
+    !vaults.every((v) => typeof v === "string" && /^\d{1,20}$/.test(v))
+  ) {
+    throw new Error(`daemon config: webhook ${url} needs vaults`);
+  }
+  const ids = vaults.map((v: string) => new BN(v).toString());
+  if (allowed && ids.some((id: string) => !allowed.includes(id))) {
+    throw new Error(
+      `daemon config: webhook ${url} watches vaults outside its tenant`
+    );
+  }
+  const types: string[] = events ?? [...EVENT_TYPES];
+  if (!types.every((e) => (EVENT_TYPES as readonly string[]).includes(e))) {
+    throw new Error(
+      `daemon config: webhook events must be among ${EVENT_TYPES.join(", ")}`
+    );
+  }
+  if (
+    low_balance !== undefined &&
+    !(typeof low_balance === "string" && /^\d{1,20}$/.test(low_balance))
+  ) {
+    throw new Error("daemon config: low_balance must be a lamport string");
+  }
+  return {
+    url,
+    secret,
+    tenant,
+    vaults: new Set(ids),
+    events: new Set(types as EventType[]),
+    lowBalance: low_balance === undefined ? null : new BN(low_balance),
+  };
+}
+
+function loadTelemetry(section: any): TelemetryConfig | null {
+  if (!section || section.enabled !== true) return null;
+  const { endpoint } = section;
+  if (typeof endpoint !== "string" || !endpoint.startsWith("https://")) {
+    throw new Error("daemon config: telemetry needs an https endpoint");
+  }
+  const epsilon = section.epsilon ?? 1;
+  if (typeof epsilon !== "number" || !(epsilon > 0 && epsilon <= 10)) {
+    throw new Error("daemon config: telemetry epsilon must be in (0, 10]");
+  }
+  const period = number(section, "period", 86_400);
+  if (period < 3_600) {
+    throw new Error("daemon config: telemetry period must be at least 3600");
+  }
+  return { endpoint, epsilon, period };
+}
+
+function loadConfig(path: string): DaemonConfig {
+  const toml: any = parse(readFileSync(path, "utf-8"));
+  if (typeof toml.cluster !== "string" || typeof toml.rpc_url !== "string") {
+    throw new Error("daemon config: cluster and rpc_url are required");
+  }
+  const tenants: TenantConfig[] = (toml.tenants ?? []).map(loadTenant);
+  const names = new Set(tenants.map((t) => t.name));
+  const keys = new Set(tenants.map((t) => t.apiKeySha256));
+  if (names.size !== tenants.length || keys.size !== tenants.length) {
+    throw new Error("daemon config: tenant names and API keys must be unique");
+  }
+  return {
+    cluster: toml.cluster,
+    rpcUrl: toml.rpc_url,
+    signer: typeof toml.signer === "string" ? toml.signer : undefined,
+    host: typeof toml.host === "string" ? toml.host : "127.0.0.1",
+    port: number(toml, "port", 8787),
+    stateDir: typeof toml.state_dir === "string" ? toml.state_dir : "obscurad",
+    idempotencyTtl: number(toml, "idempotency_ttl", 86_400),
+    tenants,
+    webhooks: (toml.webhooks ?? []).map((w: any) => loadWebhook(w, tenants)),
+    webhookPollInterval: number(toml, "webhook_poll_interval", 5),
+    quoteSigner:
+      typeof toml.quote_signer === "string" ? toml.quote_signer : undefined,
+    quoteTtl: number(toml, "quote_ttl", 30),
+    telemetry: loadTelemetry(toml.telemetry),
+  };
+}
+
+const sha256 = (data: string) => createHash("sha256").update(data).digest();
+
+function describe(err: unknown): string {
+  return err instanceof Error ? err.message : String(err);
+}
+
+const sleep = (ms: number) => new Promise((resolve) => setTimeout(resolve, ms));
+
+async function readJson(req: IncomingMessage): Promise<any> {
+  const chunks: Buffer[] = [];
+  let length = 0;
+  for await (const chunk of req) {
+    length += chunk.length;
+    if (length > MAX_BODY) throw new HttpError(413, "Request too large");
+    chunks.push(chunk);
+  }
+  try {
+    return JSON.parse(Buffer.concat(chunks).toString("utf-8"));
+  } catch {
+    throw new HttpError(400, "Body must be JSON");
+  }
+}
+
+/** Validated payment request, in canonical form for hashing. */
+function parsePayment(body: any): Payment {
+  const { vaultId, recipient, amount } = body ?? {};
+  if (typeof vaultId !== "string" || !/^\d{1,20}$/.test(vaultId)) {
+    throw new HttpError(400, "vaultId must be a decimal string");
+  }
+  if (typeof amount !== "string" || !/^[1-9]\d{0,19}$/.test(amount)) {
+    throw new HttpError(400, "amount must be a positive decimal string");
+  }
+  try {
+    return {
+      vaultId: new BN(vaultId).toString(),
+      recipient: new PublicKey(recipient).toBase58(),
+      amount: new BN(amount).toString(),
+    };
+  } catch {
+    throw new HttpError(400, "recipient must be a base58 public key");
+  }
+}
+
+async function main() {
+  const config = loadConfig(parseArgs(process.argv.slice(2)));
+  const cluster: Cluster = loadCluster(config.cluster);
+  const l1 = new Connection(config.rpcUrl, "confirmed");
+  const programId = new PublicKey(process.env.PROGRAM_ID || idl.address);
+  const telemetry = config.telemetry ? new Telemetry(config.telemetry) : null;
+
+  function openTenant(
+    name: string,
+    wallet: SignerWallet,
+    stateDir: string,
+    settings?: TenantConfig
+  ): Tenant {
+    const provider = new anchor.AnchorProvider(l1, wallet, {
+      commitment: "confirmed",
+    });
+    return {
+      name,
+      keyHash: settings ? Buffer.from(settings.apiKeySha256, "hex") : null,
+      wallet,
+      program: new Program({ ...idl, address: programId.toBase58() }, provider),
+      vaults: settings ? new Set(settings.vaults) : null,
+      limiter: settings ? new RateLimiter(settings.requestsPerMinute) : null,
+      store: new IdempotencyStore(stateDir, config.idempotencyTtl * 1000),
+      ers: new Map(),
+    };
+  }
+
+  const tenants: Tenant[] = [];
+  for (const t of config.tenants) {
+    const wallet = new SignerWallet(await loadSigner(t.signer));
+    tenants.push(openTenant(t.name, wallet, join(config.stateDir, t.name), t));
+  }
+  if (!tenants.length) {
+    const wallet = new SignerWallet(await defaultSigner(config.signer));
+    tenants.push(openTenant("default", wallet, config.stateDir));
+  }
+
+  /** The tenant whose API key authorizes `req`. */
+  function authenticate(req: IncomingMessage): Tenant {
+    if (!config.tenants.length) return tenants[0];
+    const match = req.headers.authorization?.match(/^Bearer (\S+)$/);
+    if (!match) throw new HttpError(401, "An API key is required");
+    const hash = sha256(match[1]);
+    // Compare against every tenant, so timing doesn't reveal which matched.
+    let found: Tenant | null = null;
+    for (const t of tenants) {
+      if (timingSafeEqual(hash, t.keyHash!)) found = t;
+    }
+    if (!found) throw new HttpError(401, "Invalid API key");
+    return found;
+  }
+
+  /** The ER a delegated vault is on, and its state there. */
+  async function locateVault(
+    tenant: Tenant,
+    vault: PublicKey,
+    l1Info: AccountInfo<Buffer>
+  ) {
+    const { program, wallet } = tenant;
+    const committed: any = program.coder.accounts.decode(
+      "VaultState",
+      l1Info.data
+    );
+    const endpoint = cluster.erUrls.get(
+      committed.delegateValidator.toBase58()
+    );
+    if (!endpoint) {
+      throw new HttpError(
+        503,
+        `Validator ${committed.delegateValidator} is not in clusters.toml`
+      );
+    }
+    const isPrivate = committed.isPrivate as boolean;
+    const cacheKey = `${isPrivate ? "tee" : "er"} ${endpoint}`;
+    let er = tenant.ers.get(cacheKey);
+    if (!er) {
+      er = await erConnection(endpoint, isPrivate, wallet);
+      tenant.ers.set(cacheKey, er);
+    }
+    let erInfo: AccountInfo<Buffer> | null = null;
+    try {
+      erInfo = await er.getAccountInfo(vault);
+    } finally {
+      // A TEE token may have expired: log in again next time.
+      if (!erInfo) tenant.ers.delete(cacheKey);
+    }
+    if (!erInfo) {
+      throw new HttpError(403, "Vault is not readable by the daemon key");
+    }
+    const state: any = program.coder.accounts.decode("VaultState", erInfo.data);
+    return { er, endpoint, isPrivate, state };
+  }
+
+  /** Sign a private_transfer of `payment`, ready to send. */
+  async function buildPayment(tenant: Tenant, payment: Payment) {
+    const { program, wallet } = tenant;
+    if (tenant.vaults && !tenant.vaults.has(payment.vaultId)) {
+      throw new HttpError(404, "Vault not found");
+    }
+    const vault = vaultAddress(new BN(payment.vaultId), programId);
+    const l1Info = await l1.getAccountInfo(vault);
+    if (!l1Info) throw new HttpError(404, "Vault not found");
+    if (!l1Info.owner.equals(DELEGATION_PROGRAM)) {
+      throw new HttpError(409, "Vault is not delegated");
+    }
+    const { er, endpoint, isPrivate, state } = await locateVault(
+      tenant,
+      vault,
+      l1Info
+    );
+
+    const signer = wallet.publicKey;
+    let sessionKey: PublicKey | null = null;
+    let permission: PublicKey | null = null;
+    if (!state.owner.equals(signer)) {
+      const session = pda(
+        programId,
+        "obscura_session_key",
+        vault.toBuffer(),
+        signer.toBuffer()
+      );
+      if (await er.getAccountInfo(session)) {
+        sessionKey = session;
+      } else {
+        permission = pda(
+          programId,
+          "obscura_permission",
+          vault.toBuffer(),
+          signer.toBuffer()
+        );
+      }
+    }
+    const history = pda(programId, "obscura_history", vault.toBuffer());
+
+    const tx = await program.methods
+      .privateTransfer(new BN(payment.amount), new PublicKey(payment.recipient))
+      .accountsPartial({
+        vault,
+        owner: signer,
+        historyView: (await er.getAccountInfo(history)) ? history : null,
+        cosigner: null,
+        permission,
+        sessionKey,
+        ...(await complianceAccounts(er, program, vault, state)),
+        savingsVault: state.roundUp.increment.isZero()
+          ? null
+          : state.roundUp.savingsVault,
+        splitVault:
+          state.split.bps === 0 || state.split.inbound
+            ? null
+            : state.split.splitVault,
+      })
+      .transaction();
+    const { blockhash, lastValidBlockHeight } = await er.getLatestBlockhash(
+      "confirmed"
+    );
+    tx.feePayer = signer;
+    tx.recentBlockhash = blockhash;
+    const signed = await wallet.signTransaction(tx);
+    return {
+      er,
+      endpoint,
+      isPrivate,
+      signed,
+      signature: anchor.utils.bytes.bs58.encode(signed.signature!),
+      lastValidBlockHeight,
+    };
+  }
+
+  /** Wait until a submitted payment has landed or can no longer land. */
+  async function settle(
+    tenant: Tenant,
+    record: PaymentRecord,
+    connection?: Connection
+  ): Promise<PaymentRecord> {
+    const { store } = tenant;
+    const er =
+      connection ??
+      (await erConnection(record.endpoint!, !!record.private, tenant.wallet));
+    for (;;) {
+      const { value } = await er.getSignatureStatuses([record.signature!], {
+        searchTransactionHistory: true,
+      });
+      const status = value[0];
+      if (status?.err) {
+        return store.update(record.key, {
+          state: "failed",
+          status: 422,
+          response: {
+            key: record.key,
+            state: "failed",
+            signature: record.signature,
+            error: JSON.stringify(status.err),
+          },
+        });
+      }
+      if (
+        status?.confirmationStatus === "confirmed" ||
+        status?.confirmationStatus === "finalized"
+      ) {
+        return store.update(record.key, {
+          state: "done",
+          status: 200,
+          response: {
+            key: record.key,
+            state: "done",
+            signature: record.signature,
+          },
+        });
+      }
+      const height = await er.getBlockHeight("confirmed");
+      if (height > record.lastValidBlockHeight!) {
+        return store.update(record.key, {
+          state: "failed",
+          status: 504,
+          response: {
+            key: record.key,
+            state: "failed",
+            signature: record.signature,
+            error: "Expired before landing; nothing was sent",
+          },
+        });
+      }
+      await sleep(POLL_INTERVAL_MS);
+    }
+  }
+
+  /** Settle `record`, retrying while the endpoint can't be reached. */
+  async function settleInBackground(tenant: Tenant, record: PaymentRecord) {
+    for (;;) {
+      try {
+        const settled = await settle(tenant, record);
+        log.info("Payment resolved", {
+          tenant: tenant.name,
+          key: record.key,
+          state: settled.state,
+        });
+        return;
+      } catch (err) {
+        log.warn("Resolving payment failed, retrying", {
+          tenant: tenant.name,
+          key: record.key,
+          error: describe(err),
+        });
+        await sleep(POLL_INTERVAL_MS * 10);
+      }
+    }
+  }
+
+  function respond(
+    res: ServerResponse,
+    record: PaymentRecord,
+    replayed: boolean
+  ) {
+    res.writeHead(record.status!, {
+      "Content-Type": "application/json",
+      ...(replayed ? { "Idempotent-Replayed": "true" } : {}),
+    });
+    res.end(JSON.stringify(record.response));
+  }
+
+  async function pay(
+    tenant: Tenant,
+    req: IncomingMessage,
+    res: ServerResponse
+  ) {
+    const { store } = tenant;
+    const key = req.headers["idempotency-key"];
+    if (typeof key !== "string" || !/^[\x21-\x7e]{1,255}$/.test(key)) {
+      throw new HttpError(
+        400,
+        "An Idempotency-Key header of 1-255 printable characters is required"
+      );
+    }
+    const payment = parsePayment(await readJson(req));
+    const requestHash = createHash("sha256")
+      .update(JSON.stringify(payment))
+      .digest("hex");
+
+    const begun = store.begin(key, requestHash);
+    switch (begun.kind) {
+      case "mismatch":
+        throw new HttpError(
+          422,
+          "Idempotency-Key was already used for a different request"
+        );
+      case "in-progress":
+        throw new HttpError(409, "A payment with this key is in progress");
+      case "replay":
+        telemetry?.record("payment_replays", `tenant:${tenant.name}`);
+        return respond(res, begun.record, true);
+    }
+
+    let built;
+    try {
+      built = await buildPayment(tenant, payment);
+    } catch (err) {
+      store.release(key);
+      throw err;
+    }
+    // Recorded before sending: after a crash the signature tells whether
+    // this payment landed.
+    let record = store.update(key, {
+      state: "submitted",
+      signature: built.signature,
+      endpoint: built.endpoint,
+      private: built.isPrivate,
+      lastValidBlockHeight: built.lastValidBlockHeight,
+    });
+    const vault = vaultAddress(new BN(payment.vaultId), programId);
+    log.info("Sending payment", { tenant: tenant.name, key, vault });
+    telemetry?.record("payments", vault);
+    if (built.isPrivate) telemetry?.record("private_vault_payments", vault);
+    try {
+      await built.er.sendRawTransaction(built.signed.serialize());
+    } catch (err) {
+      // Otherwise it may have reached the validator: the signature tells.
+      if (err instanceof SendTransactionError) {
+        record = store.update(key, {
+          state: "failed",
+          status: 422,
+          response: {
+            key,
+            state: "failed",
+            signature: built.signature,
+            error: describe(err),
+          },
+        });
+        return respond(res, record, false);
+      }
+    }
+    try {
+      respond(res, await settle(tenant, record, built.er), false);
+    } catch (err) {
+      log.warn("Resolving payment failed, continuing in the background", {
+        tenant: tenant.name,
+        key,
+        error: describe(err),
+      });
+      settleInBackground(tenant, record);
+      res.writeHead(202, { "Content-Type": "application/json" });
+      res.end(
+        JSON.stringify({ key, state: "submitted", signature: built.signature })
+      );
+    }
+  }
+
+  const quoteSigner = config.quoteSigner
+    ? await loadSigner(config.quoteSigner)
+    : null;
+
+  /** The vault's state, from its ER while it is delegated. */
+  async function vaultState(tenant: Tenant, vault: PublicKey) {
+    const l1Info = await l1.getAccountInfo(vault);
+    if (!l1Info) throw new HttpError(404, "Vault not found");
+    const delegated = l1Info.owner.equals(DELEGATION_PROGRAM);
+    const state: any = delegated
+      ? (await locateVault(tenant, vault, l1Info)).state
+      : tenant.program.coder.accounts.decode("VaultState", l1Info.data);
+    return { delegated, state };
+  }
+
+  /** Signed quote of what an operation would cost (POST /v1/quotes). */
+  async function quote(tenant: Tenant, body: any) {
+    if (!quoteSigner) throw new HttpError(404, "Fee quotes are not enabled");
+    const { operation, vaultId, amount } = body ?? {};
+    const op = QUOTE_OPERATIONS[operation];
+    if (!op) {
+      throw new HttpError(
+        400,
+        `operation must be one of ${Object.keys(QUOTE_OPERATIONS).join(", ")}`
+      );
+    }
+    if (typeof amount !== "string" || !/^\d{1,20}$/.test(amount)) {
+      throw new HttpError(400, "amount must be a decimal string");
+    }
+    const lamports = new BN(amount);
+    const { program } = tenant;
+
+    let vault: PublicKey | null = null;
+    let state: any = null;
+    if (operation !== "create_vault") {
+      if (typeof vaultId !== "string" || !/^\d{1,20}$/.test(vaultId)) {
+        throw new HttpError(400, "vaultId must be a decimal string");
+      }
+      const id = new BN(vaultId).toString();
+      if (tenant.vaults && !tenant.vaults.has(id)) {
+        throw new HttpError(404, "Vault not found");
+      }
+      vault = vaultAddress(new BN(id), programId);
+      state = (await vaultState(tenant, vault)).state;
+    }
+
+    const protocolFee = op.outflow
+      ? lamports.muln(state.billing.feeBps).divn(10_000)
+      : new BN(0);
+    let networkFee = new BN(0);
+    let priorityFee = new BN(0);
+    if (op.onL1) {
+      networkFee = new BN(BASE_FEE);
+      // 75th percentile of recent prices for the vault's writes
+      const recent = await l1.getRecentPrioritizationFees(
+        vault ? { lockedWritableAccounts: [vault] } : undefined
+      );
+      const prices = recent
+        .map((f) => f.prioritizationFee)
+        .sort((a, b) => a - b);
+      const price = prices[Math.floor(prices.length * 0.75)] ?? 0;
+      priorityFee = new BN(Math.ceil((price * op.computeUnits) / 1_000_000));
+    }
+    let rent = new BN(0);
+    if (operation === "create_vault") {
+      rent = new BN(
+        await l1.getMinimumBalanceForRentExemption(
+          program.account.vaultState.size
+        )
+      );
+    } else if (operation === "withdraw") {
+      const threshold: BN = state.withdrawalLimit.timelockThreshold;
+      if (!threshold.isZero() && lamports.gt(threshold)) {
+        // Queued as a PendingWithdrawal, refunded when it executes
+        rent = new BN(
+          await l1.getMinimumBalanceForRentExemption(
+            program.account.pendingWithdrawal.size
+          )
+        );
+      }
+    }
+
+    telemetry?.record("fee_quotes", vault ?? `tenant:${tenant.name}`);
+    const now = Math.floor(Date.now() / 1000);
+    const quoted = JSON.stringify({
+      version: 1,
+      id: randomUUID(),
+      operation,
+      vaultId: vault ? new BN(vaultId).toString() : null,
+      amount: lamports.toString(),
+      protocolFee: protocolFee.toString(),
+      networkFee: networkFee.toString(),
+      priorityFee: priorityFee.toString(),
+      rent: rent.toString(),
+      totalCost: protocolFee
+        .add(networkFee)
+        .add(priorityFee)
+        .add(rent)
+        .toString(),
+      quotedAt: now,
+      expiresAt: now + config.quoteTtl,
+    });
+    const signature = await quoteSigner.signMessage(
+      Buffer.from(QUOTE_SIGNING_PREFIX + quoted, "utf-8")
+    );
+    return {
+      quote: quoted,
+      signature: anchor.utils.bytes.bs58.encode(signature),
+      signer: quoteSigner.publicKey.toBase58(),
+    };
+  }
+
+  async function handle(req: IncomingMessage, res: ServerResponse) {
+    const json = (status: number, body: unknown) => {
+      res.writeHead(status, { "Content-Type": "application/json" });
+      res.end(JSON.stringify(body));
+    };
+    try {
+      const url = new URL(req.url ?? "/", "http://localhost");
+      const lookup = url.pathname.match(/^\/v1\/payments\/([^/]+)$/);
+      if (req.method === "GET" && url.pathname === "/health") {
+        return json(200, { status: "ok" });
+      }
+      const tenant = authenticate(req);
+      const wait = tenant.limiter?.take() ?? 0;
+      if (wait > 0) {
+        res.setHeader("Retry-After", Math.ceil(wait / 1000));
+        throw new HttpError(429, "Rate limit exceeded");
+      }
+      if (req.method === "POST" && url.pathname === "/v1/payments") {
+        return await pay(tenant, req, res);
+      }
+      if (req.method === "POST" && url.pathname === "/v1/quotes") {
+        return json(200, await quote(tenant, await readJson(req)));
+      }
+      if (req.method === "GET" && lookup) {
+        const record = tenant.store.get(decodeURIComponent(lookup[1]));
+        if (!record) throw new HttpError(404, "Unknown idempotency key");
+        if (record.state === "done" || record.state === "failed") {
+          return respond(res, record, true);
+        }
+        return json(202, { key: record.key, state: record.state });
+      }
+      throw new HttpError(404, "Not found");
+    } catch (err) {
+      if (res.headersSent) {
+        res.end();
+        return;
+      }
+      if (!(err instanceof HttpError)) {
+        log.error("Request failed", {
+          method: req.method,
+          path: req.url,
+          error: describe(err),
+        });
+      }
+      json(err instanceof HttpError ? err.status : 500, {
+        error: describe(err),
+      });
+    }
+  }
+
+  const server = createServer((req, res) => {
+    const requested = req.headers["x-request-id"];
+    const cid =
+      typeof requested === "string" && /^[\w.:-]{1,64}$/.test(requested)
+        ? requested
+        : randomUUID();
+    res.setHeader("X-Request-Id", cid);
+    withCorrelation(() => handle(req, res), cid);
+  });
+
+  const webhooks = new Webhooks(config.webhooks, (_tenant, vaultId) =>
+    telemetry?.record(
+      "webhook_events",
+      vaultAddress(new BN(vaultId), programId)
+    )
+  );
+  const byName = new Map(tenants.map((t) => [t.name, t]));
+
+  /** The vault as a webhook sees it, wherever it currently is. */
+  async function readVault(
+    tenant: Tenant,
+    vault: PublicKey
+  ): Promise<VaultReading> {
+    const { delegated, state } = await vaultState(tenant, vault);
+    return {
+      delegated,
+      balance: state.balance,
+      lastActivity: state.lastActivity,
+    };
+  }
+
+  async function pollWebhooks() {
+    for (const { tenant, vaultId } of webhooks.watched) {
+      const vault = vaultAddress(new BN(vaultId), programId);
+      try {
+        const reading = await readVault(byName.get(tenant)!, vault);
+        webhooks.observe(tenant, vaultId, reading);
+      } catch (err) {
+        log.warn("Reading a watched vault failed", {
+          tenant,
+          vault,
+          error: describe(err),
+        });
+      }
+    }
+    setTimeout(pollWebhooks, config.webhookPollInterval * 1000);
+  }
+
+  /** Report IntentReceipts paid into watched vaults as InvoicePaid. */
+  function watchInvoices() {
+    const receipt = idl.accounts.find((a: any) => a.name === "IntentReceipt");
+    const seen = new Set<string>();
+    for (const { tenant, vaultId } of webhooks.watched) {
+      const vault = vaultAddress(new BN(vaultId), programId);
+      l1.onProgramAccountChange(
+        programId,
+        ({ accountId, accountInfo }) => {
+          if (accountInfo.lamports === 0 || seen.has(accountId.toBase58())) {
+            return;
+          }
+          seen.add(accountId.toBase58());
+          const paid: any = byName
+            .get(tenant)!
+            .program.coder.accounts.decode("IntentReceipt", accountInfo.data);
+          webhooks.invoicePaid(tenant, vaultId, {
+            receipt: accountId.toBase58(),
+            payerVault: paid.payerVault.toBase58(),
+            reference: Buffer.from(paid.reference).toString("hex"),
+            amount: paid.amount.toString(),
+            paidAt: paid.paidAt.toNumber(),
+          });
+        },
+        {
+          commitment: "confirmed",
+          filters: [
+            {
+              memcmp: {
+                offset: 0,
+                bytes: anchor.utils.bytes.bs58.encode(receipt.discriminator),
+              },
+            },
+            // IntentReceipt::recipient_vault, after payer_vault
+            { memcmp: { offset: 8 + 32, bytes: vault.toBase58() } },
+          ],
+        }
+      );
+    }
+  }
+
+  for (const t of tenants) {
+    for (const record of t.store.unresolved()) {
+      withCorrelation(() => settleInBackground(t, record));
+    }
+  }
+  if (config.webhooks.length) {
+    pollWebhooks();
+    watchInvoices();
+  }
+  server.listen(config.port, config.host, () => {
+    log.info("Listening", {
+      host: config.host,
+      port: config.port,
+      tenants: config.tenants.map((t) => t.name),
+      ...(config.tenants.length ? {} : { key: tenants[0].wallet.publicKey }),
+    });
+  });
+}
+
+main().catch((err) => {
+  log.error(describe(err));
+  process.exit(1);
+});
@@ -0,0 +1,362 @@
+/**
+ * Export a vault's full state into a passphrase-encrypted local file.
+ *
+ *   npm run export-state -- --vault-id <id> [--er-url <url>] [--out <file>]
+ *   npm run export-state -- --verify <file>
+ *
+ * Run it before force-undelegating or migrating a vault, so there is a
+ * verified backup of what the ER holds if settlement goes wrong.  Every
+ * account keyed by the vault (the vault's ledger and policy, its history
+ * view, token balances, permissions, allowlist, escrows, ...) is read
+ * from the ER while delegated, through an auth token on the TEE so
+ * private state comes out decrypted, and from L1 otherwise.
+ *
+ * The export is encrypted with AES-256-GCM under a scrypt key derived
+ * from OBSCURA_EXPORT_PASSPHRASE, then read back and checked before the
+ * command succeeds.  --verify decrypts and re-checks an existing export.
+ *
+ * Uses ANCHOR_PROVIDER_URL / ANCHOR_WALLET like `anchor test` (the wallet
+ * must own the vault or hold a read permission on it), PROGRAM_ID for
+ * the deployed program, and the IDL from `anchor build`.
+ */
+
+import * as anchor from "@coral-xyz/anchor";
+import { BN, Program } from "@coral-xyz/anchor";
+import { AccountInfo, PublicKey } from "@solana/web3.js";
+import {
+  createCipheriv,
+  createDecipheriv,
+  createHash,
+  randomBytes,
+  scryptSync,
+} from "crypto";
+import { readFileSync, writeFileSync } from "fs";
+import * as dotenv from "dotenv";
+import {
+  DELEGATION_PROGRAM,
+  erConnection,
+  getAccounts,
+  plain,
+  vaultAddress,
+} from "./common";
+
+dotenv.config();
+
+// Generated by `anchor build`
+const idl = require("../target/idl/obscura_per.json");
+
+const USAGE =
+  "Usage: npm run export-state -- --vault-id <id> [--er-url <url>] " +
+  "[--out <file>] | --verify <file>";
+
+const EXPORT_FORMAT = "obscura-state-export";
+const EXPORT_VERSION = 1;
+const MIN_PASSPHRASE_LENGTH = 12;
+/** scrypt cost; ~100 ms and 32 MiB per derivation */
+const SCRYPT = { N: 2 ** 15, r: 8, p: 1, maxmem: 64 * 1024 * 1024 };
+
+interface Options {
+  vaultId: BN | null;
+  erUrl: string | null;
+  out: string | null;
+  verify: string | null;
+}
+
+interface ExportedAccount {
+  address: string;
+  /** IDL account type */
+  account: string;
+  /** Where the data was read: the ER for delegated accounts, else L1 */
+  source: "er" | "l1";
+  lamports: number;
+  data: string;
+  sha256: string;
+  decoded: unknown;
+}
+
+interface Snapshot {
+  programId: string;
+  vault: string;
+  exportedAt: string;
+  l1Slot: number;
+  erUrl: string | null;
+  accounts: ExportedAccount[];
+}
+
+interface ExportFile {
+  format: string;
+  version: number;
+  kdf: { name: "scrypt"; N: number; r: number; p: number; salt: string };
+  cipher: "aes-256-gcm";
+  iv: string;
+  tag: string;
+  ciphertext: string;
+}
+
+function parseArgs(argv: string[]): Options {
+  const options: Options = {
+    vaultId: null,
+    erUrl: null,
+    out: null,
+    verify: null,
+  };
+  for (let i = 0; i < argv.length; i++) {
+    switch (argv[i]) {
+      case "--vault-id":
+        options.vaultId = new BN(argv[++i]);
+        break;
+      case "--er-url":
+        options.erUrl = argv[++i].replace(/\/$/, "");
+        break;
+      case "--out":
+        options.out = argv[++i];
+        break;
+      case "--verify":
+        options.verify = argv[++i];
+        break;
+      default:
+        throw new Error(`Unknown option ${argv[i]}\n${USAGE}`);
+    }
+  }
+  if ((options.vaultId === null) === (options.verify === null)) {
+    throw new Error(USAGE);
+  }
+  return options;
+}
+
+function passphrase(): string {
+  const value = process.env.OBSCURA_EXPORT_PASSPHRASE || "";
+  if (value.length < MIN_PASSPHRASE_LENGTH) {
+    throw new Error(
+      "Set OBSCURA_EXPORT_PASSPHRASE to at least " +
+        `${MIN_PASSPHRASE_LENGTH} characters`
+    );
+  }
+  return value;
+}
+
+function sha256(data: Buffer): string {
+  return createHash("sha256").update(data).digest("hex");
+}
+
+/** IDL accounts whose first field is the vault they belong to. */
+function vaultKeyedAccounts(): { name: string; discriminator: number[] }[] {
+  return idl.accounts.filter((account: any) => {
+    const type = idl.types.find((t: any) => t.name === account.name);
+    const first = type?.type.fields?.[0];
+    return first?.name === "vault" && first.type === "pubkey";
+  });
+}
+
+function encrypt(snapshot: Snapshot, secret: string): ExportFile {
+  const salt = randomBytes(16);
+  const iv = randomBytes(12);
+  const key = scryptSync(secret, salt, 32, SCRYPT);
+  const cipher = createCipheriv("aes-256-gcm", key, iv);
+  const ciphertext = Buffer.concat([
+    cipher.update(JSON.stringify(snapshot), "utf-8"),
+    cipher.final(),
+  ]);
+  return {
+    format: EXPORT_FORMAT,
+    version: EXPORT_VERSION,
+    kdf: {
+      name: "scrypt",
+      N: SCRYPT.N,
+      r: SCRYPT.r,
+      p: SCRYPT.p,
+      salt: salt.toString("base64"),
+    },
+    cipher: "aes-256-gcm",
+    iv: iv.toString("base64"),
+    tag: cipher.getAuthTag().toString("base64"),
+    ciphertext: ciphertext.toString("base64"),
+  };
+}
+
+function decrypt(file: ExportFile, secret: string): Snapshot {
+  if (file.format !== EXPORT_FORMAT || file.version !== EXPORT_VERSION) {
+    throw new Error("Not a supported Obscura state export");
+  }
+  const { N, r, p } = file.kdf;
+  const key = scryptSync(secret, Buffer.from(file.kdf.salt, "base64"), 32, {
+    N,
+    r,
+    p,
+    maxmem: SCRYPT.maxmem,
+  });
+  const decipher = createDecipheriv(
+    "aes-256-gcm",
+    key,
+    Buffer.from(file.iv, "base64")
+  );
+  decipher.setAuthTag(Buffer.from(file.tag, "base64"));
+  try {
+    const plaintext = Buffer.concat([
+      decipher.update(Buffer.from(file.ciphertext, "base64")),
+      decipher.final(),
+    ]);
+    return JSON.parse(plaintext.toString("utf-8"));
+  } catch {
+    throw new Error("Wrong passphrase or corrupted export");
+  }
+}
+
+/**
+ * Check every account in `snapshot` against its hash and re-decode it
+ * with the IDL.  Returns the vault's decoded state.
+ */
+function verify(snapshot: Snapshot, program: Program): any {
+  let vault: any = null;
+  for (const entry of snapshot.accounts) {
+    const data = Buffer.from(entry.data, "base64");
+    const decoded = plain(program.coder.accounts.decode(entry.account, data));
+    if (
+      sha256(data) !== entry.sha256 ||
+      JSON.stringify(decoded) !== JSON.stringify(entry.decoded)
+    ) {
+      throw new Error(`Export is inconsistent at ${entry.address}`);
+    }
+    if (entry.address === snapshot.vault) vault = decoded;
+  }
+  if (!vault) {
+    throw new Error("Export does not contain the vault");
+  }
+  return vault;
+}
+
+function summarize(snapshot: Snapshot, vault: any) {
+  const counts = new Map<string, number>();
+  for (const { account } of snapshot.accounts) {
+    counts.set(account, (counts.get(account) || 0) + 1);
+  }
+  console.log(`Vault ${snapshot.vault} exported at ${snapshot.exportedAt}`);
+  console.log(`  balance ${vault.balance} lamports, nonce ${vault.nonce}`);
+  console.log(`  L1 slot ${snapshot.l1Slot}, ER ${snapshot.erUrl ?? "-"}`);
+  for (const [account, count] of counts) {
+    console.log(`  ${account}: ${count}`);
+  }
+}
+
+async function main() {
+  const options = parseArgs(process.argv.slice(2));
+  const secret = passphrase();
+  const provider = anchor.AnchorProvider.env();
+  const connection = provider.connection;
+  const programId = new PublicKey(process.env.PROGRAM_ID || idl.address);
+  const program = new Program(
+    { ...idl, address: programId.toBase58() },
+    provider
+  );
+
+  if (options.verify) {
+    const file = JSON.parse(readFileSync(options.verify, "utf-8"));
+    const snapshot = decrypt(file, secret);
+    summarize(snapshot, verify(snapshot, program));
+    console.log("Export verified");
+    return;
+  }
+
+  const vaultId = options.vaultId!;
+  const vault = vaultAddress(vaultId, programId);
+  const l1Slot = await connection.getSlot();
+  const vaultInfo = await connection.getAccountInfo(vault);
+  if (!vaultInfo) {
+    throw new Error(`Vault ${vaultId.toString()} does not exist`);
+  }
+
+  // Every vault-keyed account, wherever it lives; delegated ones keep
+  // their last committed data on L1 under the delegation program.
+  const found: {
+    address: PublicKey;
+    account: string;
+    l1: AccountInfo<Buffer>;
+  }[] = [{ address: vault, account: "VaultState", l1: vaultInfo }];
+  for (const { name, discriminator } of vaultKeyedAccounts()) {
+    for (const owner of [programId, DELEGATION_PROGRAM]) {
+      const accounts = await connection.getProgramAccounts(owner, {
+        filters: [
+          {
+            memcmp: {
+              offset: 0,
+              bytes: anchor.utils.bytes.bs58.encode(discriminator),
+            },
+          },
+          { memcmp: { offset: 8, bytes: vault.toBase58() } },
+        ],
+      });
+      for (const { pubkey, account } of accounts) {
+        found.push({ address: pubkey, account: name, l1: account });
+      }
+    }
+  }
+
+  const delegated = found.filter(({ l1 }) =>
+    l1.owner.equals(DELEGATION_PROGRAM)
+  );
+  let erInfos: (AccountInfo<Buffer> | null)[] = [];
+  if (delegated.length > 0) {
+    if (!options.erUrl) {
+      throw new Error(
+        "The vault is delegated; pass --er-url (the validator's " +
+          "er_rpc_url in clusters.toml)"
+      );
+    }
+    const state: any = program.coder.accounts.decode(
+      "VaultState",
+      vaultInfo.data
+    );
+    erInfos = await getAccounts(
+      await erConnection(
+        options.erUrl,
+        state.isPrivate,
+        provider.wallet as anchor.Wallet
+      ),
+      delegated.map(({ address }) => address)
+    );
+  }
+
+  const accounts: ExportedAccount[] = found.map(({ address, account, l1 }) => {
+    const index = delegated.findIndex((d) => d.address.equals(address));
+    const info = index >= 0 ? erInfos[index] : l1;
+    if (!info) {
+      throw new Error(
+        `${account} ${address.toBase58()} is not readable on the ER by ` +
+          `${provider.wallet.publicKey.toBase58()}`
+      );
+    }
+    return {
+      address: address.toBase58(),
+      account,
+      source: index >= 0 ? "er" : "l1",
+      lamports: info.lamports,
+      data: info.data.toString("base64"),
+      sha256: sha256(info.data),
+      decoded: plain(program.coder.accounts.decode(account, info.data)),
+    };
+  });
+
+  const snapshot: Snapshot = {
+    programId: programId.toBase58(),
+    vault: vault.toBase58(),
+    exportedAt: new Date().toISOString(),
+    l1Slot,
+    erUrl: delegated.length > 0 ? options.erUrl : null,
+    accounts,
+  };
+  const out =
+    options.out || `vault-${vaultId.toString()}-${Date.now()}.obscura-export`;
+  writeFileSync(out, JSON.stringify(encrypt(snapshot, secret)), {
+    mode: 0o600,
+  });
+
+  // Read the file back before declaring the backup good.
+  const written = decrypt(JSON.parse(readFileSync(out, "utf-8")), secret);
+  summarize(written, verify(written, program));
+  console.log(`Export written and verified: ${out}`);
+}
+
+main().catch((err) => {
+  console.error(err.message || err);
+  process.exit(1);
+});
@@ -0,0 +1,86 @@
+/**
+ * Generate the mobile client's program error table from the IDL.
+ *
+ *   npm run gen-errors -- [--out <file>]
+ *
+ * Reads the `errors` table of the IDL from `anchor build` and writes it as
+ * a TypeScript module, by default over
+ * Frontend/mobile-app/obscura-react-native/src/api/programErrors.generated.ts.
+ * Re-run after adding or rewording an `ObscuraError` variant.
+ */
+
+import { writeFileSync } from "fs";
+import * as path from "path";
+
+// Generated by `anchor build`
+const idl = require("../target/idl/obscura_per.json");
+
+const USAGE = "Usage: npm run gen-errors -- [--out <file>]";
+
+const DEFAULT_OUT = path.resolve(
+  __dirname,
+  "../../../Frontend/mobile-app/obscura-react-native/src/api",
+  "programErrors.generated.ts"
+);
+
+const HEADER = `/**
+ * Program error table
+ * Generated from the obscura_per IDL by \`npm run gen-errors\` in
+ * Backend/backend-mobile; do not edit by hand.
+ */
+
+export interface ProgramErrorEntry {
+  name: string;
+  msg: string;
+}
+
+`;
+
+interface IdlError {
+  code: number;
+  name: string;
+  msg?: string;
+}
+
+function parseArgs(argv: string[]): { out: string } {
+  let out = DEFAULT_OUT;
+  for (let i = 0; i < argv.length; i++) {
+    switch (argv[i]) {
+      case "--out":
+        out = path.resolve(argv[++i] ?? "");
+        break;
+      default:
+        throw new Error(`Unknown argument ${argv[i]}\n${USAGE}`);
+    }
+  }
+  return { out };
+}
+
+function render(errors: IdlError[]): string {
+  const entries = errors.map(
+    (error) =>
+      `  ${error.code}: { name: ${JSON.stringify(error.name)}, ` +
+      `msg: ${JSON.stringify(error.msg ?? "")} },`
+  );
+  return (
+    HEADER +
+    "export const PROGRAM_ERRORS: Record<number, ProgramErrorEntry> = {\n" +
+    entries.join("\n") +
+    "\n};\n"
+  );
+}
+
+async function main() {
+  const { out } = parseArgs(process.argv.slice(2));
+  const errors: IdlError[] = idl.errors ?? [];
+  if (errors.length === 0) {
+    throw new Error("IDL has no errors table; run `anchor build` first");
+  }
+  writeFileSync(out, render(errors));
+  console.log(`Wrote ${errors.length} program errors to ${out}`);
+}
+
+main().catch((err) => {
+  console.error(err.message || err);
+  process.exit(1);
+});
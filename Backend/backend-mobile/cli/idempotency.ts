@@ -0,0 +1,161 @@
+/**
+ * Idempotency keys for the daemon's payment API.
+ *
+ * Each key is recorded, with a hash of its request, before anything is
+ * sent, and again with the transaction signature before that
+ * transaction goes out, in an append-only log fsynced on every write.
+ * A retried request with the same key therefore gets the first
+ * request's outcome instead of sending a second transfer, also across
+ * daemon restarts: a payment still `submitted` at startup is resolved
+ * from its signature's status before the key accepts anything else.  A
+ * key still `pending` never had a transaction sent and is released.
+ */
+
+import {
+  closeSync,
+  existsSync,
+  fsyncSync,
+  mkdirSync,
+  openSync,
+  readFileSync,
+  renameSync,
+  writeSync,
+} from "fs";
+import { join } from "path";
+
+export type PaymentState = "pending" | "submitted" | "done" | "failed";
+
+export interface PaymentRecord {
+  key: string;
+  /** SHA-256 of the request the key was first used with */
+  requestHash: string;
+  state: PaymentState;
+  /** Set once the transaction is signed */
+  signature?: string;
+  /** Where the transaction was sent, to resolve it after a restart */
+  endpoint?: string;
+  private?: boolean;
+  lastValidBlockHeight?: number;
+  /** HTTP status and body returned for the key once done or failed */
+  status?: number;
+  response?: unknown;
+  /** Unix timestamps (ms) */
+  createdAt: number;
+  updatedAt: number;
+}
+
+export type Begin =
+  | { kind: "new"; record: PaymentRecord }
+  | { kind: "replay"; record: PaymentRecord }
+  | { kind: "in-progress"; record: PaymentRecord }
+  | { kind: "mismatch"; record: PaymentRecord };
+
+export class IdempotencyStore {
+  private records = new Map<string, PaymentRecord>();
+  private fd: number;
+
+  /** Open the log in `dir`, dropping keys older than `ttlMs`. */
+  constructor(dir: string, private ttlMs: number) {
+    mkdirSync(dir, { recursive: true });
+    const path = join(dir, "idempotency.jsonl");
+    if (existsSync(path)) {
+      for (const line of readFileSync(path, "utf-8").split("\n")) {
+        if (!line.trim()) continue;
+        try {
+          const record = JSON.parse(line);
+          if (record.released || record.state === "pending") {
+            this.records.delete(record.key);
+          } else {
+            this.records.set(record.key, record);
+          }
+        } catch {
+          // A torn final line from a crash mid-write.
+        }
+      }
+    }
+    this.prune();
+
+    // Compact: rewrite the live records, then append to the new log.
+    const compacted = `${path}.tmp`;
+    const out = openSync(compacted, "w");
+    for (const record of this.records.values()) {
+      writeSync(out, JSON.stringify(record) + "\n");
+    }
+    fsyncSync(out);
+    closeSync(out);
+    renameSync(compacted, path);
+    this.fd = openSync(path, "a");
+  }
+
+  private prune() {
+    const cutoff = Date.now() - this.ttlMs;
+    for (const [key, record] of this.records) {
+      if (record.createdAt < cutoff && record.state !== "submitted") {
+        this.records.delete(key);
+      }
+    }
+  }
+
+  private persist(record: PaymentRecord) {
+    this.records.set(record.key, record);
+    writeSync(this.fd, JSON.stringify(record) + "\n");
+    fsyncSync(this.fd);
+  }
+
+  get(key: string): PaymentRecord | undefined {
+    return this.records.get(key);
+  }
+
+  /** Payments sent but not yet known to have landed or expired. */
+  unresolved(): PaymentRecord[] {
+    return [...this.records.values()].filter((r) => r.state === "submitted");
+  }
+
+  /** Claim `key` for a request hashing to `requestHash`. */
+  begin(key: string, requestHash: string): Begin {
+    this.prune();
+    const existing = this.records.get(key);
+    if (existing) {
+      if (existing.requestHash !== requestHash) {
+        return { kind: "mismatch", record: existing };
+      }
+      if (existing.state === "done" || existing.state === "failed") {
+        return { kind: "replay", record: existing };
+      }
+      return { kind: "in-progress", record: existing };
+    }
+    const now = Date.now();
+    const record: PaymentRecord = {
+      key,
+      requestHash,
+      state: "pending",
+      createdAt: now,
+      updatedAt: now,
+    };
+    this.persist(record);
+    return { kind: "new", record };
+  }
+
+  /** Update `key`'s record; call before the step it describes. */
+  update(key: string, changes: Partial<PaymentRecord>): PaymentRecord {
+    const record = {
+      ...this.records.get(key)!,
+      ...changes,
+      updatedAt: Date.now(),
+    };
+    this.persist(record);
+    return record;
+  }
+
+  /**
+   * Forget a key whose transaction was never sent (e.g. it could not be
+   * built), so the same request can be retried with it.
+   */
+  release(key: string) {
+    if (this.records.get(key)?.state === "pending") {
+      this.records.delete(key);
+      writeSync(this.fd, JSON.stringify({ key, released: true }) + "\n");
+      fsyncSync(this.fd);
+    }
+  }
+}
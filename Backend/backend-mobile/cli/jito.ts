@@ -0,0 +1,83 @@
+/**
+ * Jito bundle submission for L1 settlement and exit transactions.
+ *
+ * A transaction sent as a bundle goes straight to the Jito block engine
+ * instead of through RPC gossip, so nothing sees it before it lands and
+ * it can't be front-run.  The block engine only takes bundles that tip
+ * one of its tip accounts; the tip is a transfer appended to the
+ * transaction itself, so it is only paid if the transaction lands.
+ * Landing is confirmed by signature on the usual L1 connection.
+ */
+
+import {
+  PublicKey,
+  SystemProgram,
+  Transaction,
+  TransactionInstruction,
+  VersionedTransaction,
+} from "@solana/web3.js";
+
+export interface JitoConfig {
+  /** Block engine, e.g. https://mainnet.block-engine.jito.wtf */
+  blockEngineUrl: string;
+  /** Lamports tipped per bundle */
+  tipLamports: number;
+}
+
+const REQUEST_TIMEOUT_MS = 10_000;
+
+let tipAccounts: { url: string; accounts: PublicKey[] } | null = null;
+
+async function call(url: string, method: string, params: unknown[]) {
+  const response = await fetch(`${url.replace(/\/+$/, "")}/api/v1/bundles`, {
+    method: "POST",
+    headers: { "Content-Type": "application/json" },
+    body: JSON.stringify({ jsonrpc: "2.0", id: 1, method, params }),
+    signal: AbortSignal.timeout(REQUEST_TIMEOUT_MS),
+  });
+  const body = await response.json().catch(() => ({}));
+  if (!response.ok || body.error) {
+    throw new Error(
+      `Jito ${method}: ${body.error?.message ?? `HTTP ${response.status}`}`
+    );
+  }
+  return body.result;
+}
+
+/** A tip to a random tip account of the block engine. */
+export async function tipInstruction(
+  config: JitoConfig,
+  payer: PublicKey
+): Promise<TransactionInstruction> {
+  if (tipAccounts?.url !== config.blockEngineUrl) {
+    const accounts: string[] = await call(
+      config.blockEngineUrl,
+      "getTipAccounts",
+      []
+    );
+    tipAccounts = {
+      url: config.blockEngineUrl,
+      accounts: accounts.map((a) => new PublicKey(a)),
+    };
+  }
+  const { accounts } = tipAccounts;
+  return SystemProgram.transfer({
+    fromPubkey: payer,
+    toPubkey: accounts[Math.floor(Math.random() * accounts.length)],
+    lamports: config.tipLamports,
+  });
+}
+
+/** Send signed transactions, tip included, as one bundle; its ID. */
+export async function sendBundle(
+  config: JitoConfig,
+  transactions: (Transaction | VersionedTransaction)[]
+): Promise<string> {
+  const encoded = transactions.map((tx) =>
+    Buffer.from(tx.serialize()).toString("base64")
+  );
+  return call(config.blockEngineUrl, "sendBundle", [
+    encoded,
+    { encoding: "base64" },
+  ]);
+}
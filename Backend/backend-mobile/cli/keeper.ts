@@ -0,0 +1,603 @@
+/**
+ * Obscura keeper.
+ *
+ *   npm run keeper -- --config <keeper.toml> [--write-snapshot <file>]
+ *
+ * Every `scan_interval` seconds the keeper looks for due work and hands
+ * it to the job scheduler (cli/scheduler.ts), highest priority first:
+ *
+ *   1. commits   — checkpoint each vault in `vaults.commit` with
+ *                  commit_vault_state every `vaults.commit_interval`
+ *                  seconds (the keeper key needs a DelegatedAuthority
+ *                  with IX_COMMIT_VAULT_STATE on each vault)
+ *   2. sweeps    — undelegate inactive vaults with sweep_inactive_vault
+ *                  when `vaults.sweep` is set (see README "Inactivity
+ *                  Sweep"; the keeper key must be `ProgramConfig.keeper`)
+ *   3. payments  — execute due scheduled transfers whose tip is at least
+ *                  `tips.min_scheduled_transfer_tip`, and process due
+ *                  subscriptions
+ *
+ * Commits and sweeps go to the ER of the vault's validator (from
+ * clusters.toml), payments to the L1 endpoints in `[[rpc]]`, or with
+ * `[jito]` to a Jito block engine as tipped bundles (cli/jito.ts).
+ *
+ * Delegated vaults, scheduled transfers and subscriptions are tracked in
+ * memory (cli/snapshot.ts): loaded once at start, followed through
+ * account notifications and reloaded every `snapshot.refresh_interval`.
+ * With `snapshot.file` set, the keeper starts from that file, if present,
+ * and rewrites it on every reload; `--write-snapshot <file>` only writes
+ * one and exits, e.g. to publish it for other keepers.
+ *
+ * SIGHUP reloads the config file (and clusters.toml) without a restart:
+ * watched vaults, endpoints, limits, tips and backoff change for the next
+ * scan while queued and running jobs carry on.  A config that fails to
+ * load is reported and the running one kept; the signer is only read at
+ * startup.  SIGINT or SIGTERM stops scanning and exits once running jobs
+ * have finished.
+ *
+ * Logs are JSON lines, one correlation ID per job (cli/log.ts).
+ *
+ * Uses `signer` (cli/signer.ts; by default ANCHOR_WALLET) as the keeper
+ * key, PROGRAM_ID for the deployed program, and the IDL from `anchor
+ * build`.  keeper.example.toml documents the configuration.
+ */
+
+import * as anchor from "@coral-xyz/anchor";
+import { BN, Program } from "@coral-xyz/anchor";
+import {
+  ComputeBudgetProgram,
+  Connection,
+  PublicKey,
+  Transaction,
+} from "@solana/web3.js";
+import { existsSync, readFileSync } from "fs";
+import { parse } from "smol-toml";
+import * as dotenv from "dotenv";
+import {
+  DELEGATION_PROGRAM,
+  complianceAccounts,
+  erConnection,
+  loadCluster,
+  pda,
+  vaultAddress,
+} from "./common";
+import { JitoConfig, sendBundle, tipInstruction } from "./jito";
+import { logger } from "./log";
+import { Backoff, JobEvent, Priority, Scheduler } from "./scheduler";
+import { defaultSigner, SignerWallet } from "./signer";
+import { AccountSet, Source } from "./snapshot";
+
+dotenv.config();
+
+// Generated by `anchor build`
+const idl = require("../target/idl/obscura_per.json");
+const log = logger("keeper");
+
+const USAGE =
+  "Usage: npm run keeper -- --config <keeper.toml> [--write-snapshot <file>]";
+
+interface Options {
+  config: string | null;
+  writeSnapshot: string | null;
+}
+
+interface Endpoint {
+  url: string;
+  concurrency: number;
+}
+
+interface KeeperConfig {
+  cluster: string;
+  /** Keeper key (see cli/signer.ts); ANCHOR_WALLET by default */
+  signer?: string;
+  /** Seconds between scans */
+  scanInterval: number;
+  rpc: Endpoint[];
+  /** Concurrency limit of each ER endpoint */
+  erConcurrency: number;
+  commitVaults: BN[];
+  /** Seconds between commits of one vault */
+  commitInterval: number;
+  sweep: boolean;
+  minScheduledTransferTip: number;
+  /** Micro-lamports per compute unit on L1 transactions */
+  priorityFee: number;
+  /** Send L1 payments as Jito bundles (cli/jito.ts); null for RPC */
+  jito: JitoConfig | null;
+  backoff: Backoff;
+  /** Snapshot file to start from and keep refreshed */
+  snapshotFile: string | null;
+  /** Seconds between full reloads of the tracked accounts */
+  snapshotRefresh: number;
+}
+
+function parseArgs(argv: string[]): Options {
+  const options: Options = { config: null, writeSnapshot: null };
+  for (let i = 0; i < argv.length; i++) {
+    switch (argv[i]) {
+      case "--config":
+        options.config = argv[++i];
+        break;
+      case "--write-snapshot":
+        options.writeSnapshot = argv[++i];
+        break;
+      default:
+        throw new Error(`Unknown option ${argv[i]}\n${USAGE}`);
+    }
+  }
+  if (options.config === null) {
+    throw new Error(USAGE);
+  }
+  return options;
+}
+
+function number(table: any, key: string, fallback?: number): number {
+  const value = table?.[key] ?? fallback;
+  if (typeof value !== "number" || !Number.isFinite(value) || value < 0) {
+    throw new Error(`keeper config: ${key} must be a non-negative number`);
+  }
+  return value;
+}
+
+function loadJito(table: any): JitoConfig | null {
+  if (!table) return null;
+  if (typeof table.block_engine_url !== "string") {
+    throw new Error("keeper config: [jito] needs block_engine_url");
+  }
+  const tipLamports = number(table, "tip_lamports", 10_000);
+  if (tipLamports < 1_000) {
+    throw new Error("keeper config: jito tip_lamports must be at least 1000");
+  }
+  return { blockEngineUrl: table.block_engine_url, tipLamports };
+}
+
+function loadConfig(path: string): KeeperConfig {
+  const toml: any = parse(readFileSync(path, "utf-8"));
+  if (typeof toml.cluster !== "string") {
+    throw new Error("keeper config: cluster must name a clusters.toml entry");
+  }
+  const rpc: Endpoint[] = (toml.rpc ?? []).map((endpoint: any) => ({
+    url: String(endpoint.url),
+    concurrency: number(endpoint, "concurrency", 4),
+  }));
+  if (rpc.length === 0) {
+    throw new Error("keeper config: at least one [[rpc]] endpoint is needed");
+  }
+  return {
+    cluster: toml.cluster,
+    signer: typeof toml.signer === "string" ? toml.signer : undefined,
+    scanInterval: number(toml, "scan_interval", 30),
+    rpc,
+    erConcurrency: number(toml.er, "concurrency", 2),
+    commitVaults: (toml.vaults?.commit ?? []).map((id: number) => new BN(id)),
+    commitInterval: number(toml.vaults, "commit_interval", 600),
+    sweep: toml.vaults?.sweep === true,
+    minScheduledTransferTip: number(toml.tips, "min_scheduled_transfer_tip", 0),
+    priorityFee: number(toml.tips, "priority_fee", 0),
+    jito: loadJito(toml.jito),
+    backoff: {
+      baseMs: number(toml.backoff, "base_ms", 500),
+      maxMs: number(toml.backoff, "max_ms", 60_000),
+      maxAttempts: number(toml.backoff, "max_attempts", 8),
+    },
+    snapshotFile:
+      typeof toml.snapshot?.file === "string" ? toml.snapshot.file : null,
+    snapshotRefresh: number(toml.snapshot, "refresh_interval", 3600),
+  };
+}
+
+function source(owner: PublicKey, type: string): Source {
+  const account = idl.accounts.find((a: any) => a.name === type);
+  return { owner, type, discriminator: account.discriminator };
+}
+
+function describe(err: unknown): string {
+  return err instanceof Error ? err.message : String(err);
+}
+
+function connections(config: KeeperConfig) {
+  return config.rpc.map(({ url }) => ({
+    url,
+    connection: new Connection(url, "confirmed"),
+  }));
+}
+
+async function main() {
+  const options = parseArgs(process.argv.slice(2));
+  const configPath = options.config!;
+  let config = loadConfig(configPath);
+  let cluster = loadCluster(config.cluster);
+  let l1 = connections(config);
+  const wallet = new SignerWallet(await defaultSigner(config.signer));
+  const provider = new anchor.AnchorProvider(l1[0].connection, wallet, {
+    commitment: "confirmed",
+  });
+  const programId = new PublicKey(process.env.PROGRAM_ID || idl.address);
+  const program = new Program(
+    { ...idl, address: programId.toBase58() },
+    provider
+  );
+  const programConfig = pda(programId, "obscura_config");
+
+  // Delegated vaults keep their last committed data on L1 under the
+  // delegation program.
+  const accounts = new AccountSet(programId, [
+    source(DELEGATION_PROGRAM, "VaultState"),
+    source(programId, "ScheduledTransfer"),
+    source(programId, "Subscription"),
+  ]);
+  if (options.writeSnapshot) {
+    await accounts.load(l1[0].connection);
+    accounts.write(options.writeSnapshot);
+    log.info("Wrote snapshot", {
+      file: options.writeSnapshot,
+      accounts: accounts.size,
+      slot: accounts.slot,
+    });
+    return;
+  }
+
+  const scheduler = new Scheduler((event: JobEvent) => {
+    switch (event.kind) {
+      case "done":
+        break;
+      case "retry":
+        log.warn("Job failed, retrying", {
+          job: event.job.key,
+          attempt: event.attempt,
+          delayMs: Math.round(event.delayMs),
+          error: describe(event.error),
+        });
+        break;
+      case "dropped":
+        log.error("Job failed, giving up", {
+          job: event.job.key,
+          error: describe(event.error),
+        });
+        break;
+    }
+  });
+  const configureScheduler = () =>
+    scheduler.configure(
+      config.backoff,
+      config.erConcurrency,
+      new Map(config.rpc.map(({ url, concurrency }) => [url, concurrency]))
+    );
+  configureScheduler();
+
+  let next = 0;
+  const nextL1 = () => l1[next++ % l1.length];
+
+  /**
+   * Sign and send `tx`, throwing if it fails.  With `jito`, it goes to the
+   * block engine as a bundle, tip appended, instead of to `connection`.
+   */
+  async function send(
+    connection: Connection,
+    tx: Transaction,
+    priorityFee: number,
+    jito: JitoConfig | null = null
+  ): Promise<string> {
+    if (priorityFee > 0) {
+      tx.instructions.unshift(
+        ComputeBudgetProgram.setComputeUnitPrice({ microLamports: priorityFee })
+      );
+    }
+    if (jito) tx.add(await tipInstruction(jito, wallet.publicKey));
+    const { blockhash, lastValidBlockHeight } =
+      await connection.getLatestBlockhash("confirmed");
+    tx.feePayer = wallet.publicKey;
+    tx.recentBlockhash = blockhash;
+    const signed = await wallet.signTransaction(tx);
+    let signature: string;
+    if (jito) {
+      signature = anchor.utils.bytes.bs58.encode(signed.signature!);
+      log.debug("Sending bundle", {
+        bundle: await sendBundle(jito, [signed]),
+        signature,
+      });
+    } else {
+      signature = await connection.sendRawTransaction(signed.serialize());
+    }
+    const { value } = await connection.confirmTransaction(
+      { signature, blockhash, lastValidBlockHeight },
+      "confirmed"
+    );
+    if (value.err) {
+      throw new Error(`${signature} failed: ${JSON.stringify(value.err)}`);
+    }
+    return signature;
+  }
+
+  const lastCommit = new Map<string, number>();
+
+  function scanCommits(connection: Connection) {
+    return Promise.all(
+      config.commitVaults.map(async (vaultId) => {
+        const vault = vaultAddress(vaultId, programId);
+        const key = `commit:${vault.toBase58()}`;
+        const due = (lastCommit.get(key) ?? 0) + config.commitInterval * 1000;
+        if (due > Date.now()) return;
+
+        const info = await connection.getAccountInfo(vault);
+        if (!info?.owner.equals(DELEGATION_PROGRAM)) return;
+        const state: any = program.coder.accounts.decode(
+          "VaultState",
+          info.data
+        );
+        const erUrl = cluster.erUrls.get(state.delegateValidator.toBase58());
+        if (!erUrl) {
+          log.warn("Vault is on a validator not in clusters.toml", {
+            vault,
+            validator: state.delegateValidator,
+          });
+          return;
+        }
+        const delegatedAuthority = pda(
+          programId,
+          "obscura_authority",
+          vault.toBuffer(),
+          wallet.publicKey.toBuffer()
+        );
+
+        scheduler.submit({
+          key,
+          priority: Priority.Commit,
+          endpoint: erUrl,
+          run: async () => {
+            const er = await erConnection(erUrl, state.isPrivate, wallet);
+            const tx = await program.methods
+              .commitVaultState()
+              .accountsPartial({
+                vault,
+                owner: wallet.publicKey,
+                delegatedAuthority,
+              })
+              .transaction();
+            const signature = await send(er, tx, 0);
+            lastCommit.set(key, Date.now());
+            log.info("Committed vault", { vault, signature });
+          },
+        });
+      })
+    );
+  }
+
+  function scanSweeps() {
+    for (const { pubkey: vault, data } of accounts.of("VaultState")) {
+      const state: any = program.coder.accounts.decode("VaultState", data);
+      const erUrl = cluster.erUrls.get(state.delegateValidator.toBase58());
+      if (!erUrl) continue;
+
+      scheduler.submit({
+        key: `sweep:${vault.toBase58()}`,
+        priority: Priority.ForceUndelegate,
+        endpoint: erUrl,
+        run: async () => {
+          const er = await erConnection(erUrl, state.isPrivate, wallet);
+          const check = await program.methods
+            .checkUndelegateEligibility()
+            .accountsPartial({ vault, config: programConfig })
+            .transaction();
+          check.feePayer = wallet.publicKey;
+          const { value } = await er.simulateTransaction(check);
+          if (value.err) {
+            throw new Error(`eligibility check: ${JSON.stringify(value.err)}`);
+          }
+          const returned = value.returnData?.data[0];
+          if (!returned || Buffer.from(returned, "base64")[0] !== 1) return;
+
+          const tx = await program.methods
+            .sweepInactiveVault()
+            .accountsPartial({
+              vault,
+              config: programConfig,
+              keeper: wallet.publicKey,
+            })
+            .transaction();
+          const signature = await send(er, tx, 0);
+          log.info("Swept inactive vault", { vault, signature });
+        },
+      });
+    }
+  }
+
+  function scanPayments(connection: Connection, url: string) {
+    const now = Math.floor(Date.now() / 1000);
+
+    for (const { pubkey, data } of accounts.of("ScheduledTransfer")) {
+      const transfer: any = program.coder.accounts.decode(
+        "ScheduledTransfer",
+        data
+      );
+      if (
+        transfer.executeAfter.toNumber() > now ||
+        transfer.tip.toNumber() < config.minScheduledTransferTip
+      ) {
+        continue;
+      }
+      scheduler.submit({
+        key: `scheduled:${pubkey.toBase58()}`,
+        priority: Priority.ScheduledPayment,
+        endpoint: url,
+        run: async () => {
+          if (!(await connection.getAccountInfo(pubkey))) {
+            accounts.forget(pubkey);
+            return;
+          }
+          const tx = await program.methods
+            .executeScheduledTransfer()
+            .accountsPartial({
+              scheduledTransfer: pubkey,
+              recipient: transfer.recipient,
+              owner: transfer.owner,
+              cranker: wallet.publicKey,
+            })
+            .transaction();
+          const signature = await send(
+            connection,
+            tx,
+            config.priorityFee,
+            config.jito
+          );
+          accounts.forget(pubkey);
+          log.info("Executed scheduled transfer", {
+            scheduledTransfer: pubkey,
+            signature,
+          });
+        },
+      });
+    }
+
+    for (const { pubkey, data } of accounts.of("Subscription")) {
+      const subscription: any = program.coder.accounts.decode(
+        "Subscription",
+        data
+      );
+      if (subscription.nextDue.toNumber() > now) continue;
+      scheduler.submit({
+        key: `subscription:${pubkey.toBase58()}`,
+        priority: Priority.ScheduledPayment,
+        endpoint: url,
+        run: async () => {
+          const info = await connection.getAccountInfo(subscription.vault);
+          // Delegated vaults pay once they are back on L1.
+          if (!info?.owner.equals(programId)) return;
+          const vault: any = program.coder.accounts.decode(
+            "VaultState",
+            info.data
+          );
+          const compliance = await complianceAccounts(
+            connection,
+            program,
+            subscription.vault,
+            vault
+          );
+          const tx = await program.methods
+            .processSubscription()
+            .accountsPartial({
+              vault: subscription.vault,
+              subscription: pubkey,
+              recipient: subscription.recipient,
+              ...compliance,
+            })
+            .transaction();
+          const signature = await send(
+            connection,
+            tx,
+            config.priorityFee,
+            config.jito
+          );
+          log.info("Processed subscription", {
+            subscription: pubkey,
+            signature,
+          });
+        },
+      });
+    }
+  }
+
+  let scanning = false;
+  async function scan() {
+    if (scanning) return;
+    scanning = true;
+    const { url, connection } = nextL1();
+    try {
+      await scanCommits(connection);
+      if (config.sweep) scanSweeps();
+      scanPayments(connection, url);
+    } catch (err) {
+      log.error("Scan failed", { error: describe(err) });
+    } finally {
+      scanning = false;
+    }
+  }
+
+  let refreshTimer: NodeJS.Timeout | undefined;
+  /** Reload the tracked accounts in full and refresh the snapshot file. */
+  async function refresh() {
+    try {
+      await accounts.load(nextL1().connection);
+      if (config.snapshotFile) accounts.write(config.snapshotFile);
+      log.info("Refreshed accounts", {
+        accounts: accounts.size,
+        slot: accounts.slot,
+      });
+    } catch (err) {
+      log.error("Account refresh failed", { error: describe(err) });
+    }
+    refreshTimer = setTimeout(refresh, config.snapshotRefresh * 1000);
+  }
+
+  accounts.subscribe(l1[0].connection);
+  if (config.snapshotFile && existsSync(config.snapshotFile)) {
+    accounts.read(config.snapshotFile);
+    log.info("Loaded snapshot", {
+      file: config.snapshotFile,
+      accounts: accounts.size,
+      slot: accounts.slot,
+    });
+    if (await accounts.catchUp(l1[0].connection)) {
+      refreshTimer = setTimeout(refresh, config.snapshotRefresh * 1000);
+    } else {
+      // The RPC can't send just the changes: work from the snapshot
+      // while a full reload runs.
+      refresh();
+    }
+  } else {
+    await refresh();
+  }
+
+  let timer = setInterval(scan, config.scanInterval * 1000);
+
+  const reload = () => {
+    let reloaded: KeeperConfig;
+    try {
+      reloaded = loadConfig(configPath);
+      cluster = loadCluster(reloaded.cluster);
+    } catch (err) {
+      log.error("Reload failed, keeping the running config", {
+        error: describe(err),
+      });
+      return;
+    }
+    if (reloaded.scanInterval !== config.scanInterval) {
+      clearInterval(timer);
+      timer = setInterval(scan, reloaded.scanInterval * 1000);
+    }
+    config = reloaded;
+    l1 = connections(config);
+    configureScheduler();
+    log.info("Reloaded config", {
+      file: configPath,
+      pending: scheduler.pending,
+    });
+  };
+  process.on("SIGHUP", reload);
+
+  const shutdown = () => {
+    clearInterval(timer);
+    clearTimeout(refreshTimer);
+    scheduler.stop();
+    const wait = setInterval(() => {
+      if (scheduler.pending === 0) {
+        clearInterval(wait);
+        process.exit(0);
+      }
+    }, 200);
+  };
+  process.on("SIGINT", shutdown);
+  process.on("SIGTERM", shutdown);
+
+  log.info("Keeper started", {
+    keeper: wallet.publicKey,
+    cluster: config.cluster,
+    scanInterval: config.scanInterval,
+  });
+  await scan();
+}
+
+main().catch((err) => {
+  log.error(describe(err));
+  process.exit(1);
+});
@@ -0,0 +1,109 @@
+/**
+ * Structured logging for the daemon and keeper.
+ *
+ * Each entry is one JSON line on stdout with `time`, `level`,
+ * `component`, `msg`, the correlation ID (`cid`) of the request or job it
+ * was written in, and the entry's fields.  Accounts are logged by
+ * reference, the first 16 hex digits of the SHA-256 of their base58
+ * address, so entries about one vault can be followed without the log
+ * saying whose vault it is: public keys in fields, and base58 keys in
+ * messages (e.g. from RPC errors), are replaced by their reference
+ * unless LOG_LEVEL=debug.  LOG_LEVEL (debug, info, warn or error)
+ * defaults to info.
+ */
+
+import { BN } from "@coral-xyz/anchor";
+import { PublicKey } from "@solana/web3.js";
+import { AsyncLocalStorage } from "async_hooks";
+import { createHash, randomUUID } from "crypto";
+
+export type Level = "debug" | "info" | "warn" | "error";
+
+const LEVELS: Record<Level, number> = {
+  debug: 10,
+  info: 20,
+  warn: 30,
+  error: 40,
+};
+
+const minimum: Level =
+  (process.env.LOG_LEVEL as Level) in LEVELS
+    ? (process.env.LOG_LEVEL as Level)
+    : "info";
+const raw = minimum === "debug";
+
+/** A base58 string of public key length */
+const BASE58_KEY = /\b[1-9A-HJ-NP-Za-km-z]{32,44}\b/g;
+
+const correlation = new AsyncLocalStorage<string>();
+
+/** Reference logged in place of an account's address. */
+export function ref(key: PublicKey | string): string {
+  const address = typeof key === "string" ? key : key.toBase58();
+  return createHash("sha256").update(address).digest("hex").slice(0, 16);
+}
+
+/** Run `fn` with `cid` (a new one by default) as its correlation ID. */
+export function withCorrelation<T>(fn: () => T, cid = randomUUID()): T {
+  return correlation.run(cid, fn);
+}
+
+export function correlationId(): string | undefined {
+  return correlation.getStore();
+}
+
+function redact(value: unknown): unknown {
+  if (value instanceof PublicKey) {
+    return raw ? value.toBase58() : `ref:${ref(value)}`;
+  }
+  if (BN.isBN(value)) return value.toString();
+  if (typeof value === "string") {
+    return raw ? value : value.replace(BASE58_KEY, (key) => `ref:${ref(key)}`);
+  }
+  if (value instanceof Error) return redact(value.message);
+  if (Array.isArray(value)) return value.map(redact);
+  if (value && typeof value === "object") {
+    return Object.fromEntries(
+      Object.entries(value).map(([k, v]) => [k, redact(v)])
+    );
+  }
+  return value;
+}
+
+export class Logger {
+  constructor(private component: string) {}
+
+  debug(msg: string, fields: Record<string, unknown> = {}) {
+    this.write("debug", msg, fields);
+  }
+
+  info(msg: string, fields: Record<string, unknown> = {}) {
+    this.write("info", msg, fields);
+  }
+
+  warn(msg: string, fields: Record<string, unknown> = {}) {
+    this.write("warn", msg, fields);
+  }
+
+  error(msg: string, fields: Record<string, unknown> = {}) {
+    this.write("error", msg, fields);
+  }
+
+  private write(level: Level, msg: string, fields: Record<string, unknown>) {
+    if (LEVELS[level] < LEVELS[minimum]) return;
+    const cid = correlationId();
+    const entry = {
+      time: new Date().toISOString(),
+      level,
+      component: this.component,
+      msg: redact(msg),
+      ...(cid ? { cid } : {}),
+      ...(redact(fields) as Record<string, unknown>),
+    };
+    process.stdout.write(JSON.stringify(entry) + "\n");
+  }
+}
+
+export function logger(component: string): Logger {
+  return new Logger(component);
+}
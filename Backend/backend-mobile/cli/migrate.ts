@@ -0,0 +1,500 @@
+/**
+ * Migrate a plain wallet into a fresh Obscura vault.
+ *
+ *   npm run migrate -- [--vault-id <id>] [--mint <mint>]... [--all-tokens]
+ *                      [--nfts] [--keep <lamports>] [--dry-run]
+ *
+ * Creates the vault, moves the selected SPL / Token-2022 balances and
+ * Metaplex NFTs into it, unwraps wSOL, then deposits the remaining SOL
+ * minus --keep.  Instructions are packed into as few transactions as fit.
+ * --dry-run prints the plan without sending anything.
+ *
+ * Uses ANCHOR_PROVIDER_URL / ANCHOR_WALLET like `anchor test`, PROGRAM_ID
+ * for the deployed program, and the IDL from `anchor build`.
+ */
+
+import * as anchor from "@coral-xyz/anchor";
+import { BN, Program } from "@coral-xyz/anchor";
+import {
+  ASSOCIATED_TOKEN_PROGRAM_ID,
+  NATIVE_MINT,
+  TOKEN_2022_PROGRAM_ID,
+  TOKEN_PROGRAM_ID,
+  createCloseAccountInstruction,
+  getAssociatedTokenAddressSync,
+} from "@solana/spl-token";
+import {
+  ComputeBudgetProgram,
+  LAMPORTS_PER_SOL,
+  PublicKey,
+  SYSVAR_INSTRUCTIONS_PUBKEY,
+  SystemProgram,
+  Transaction,
+  TransactionInstruction,
+} from "@solana/web3.js";
+import { randomBytes } from "crypto";
+import * as dotenv from "dotenv";
+
+dotenv.config();
+
+// Generated by `anchor build`
+const idl = require("../target/idl/obscura_per.json");
+
+const USAGE =
+  "Usage: npm run migrate -- [--vault-id <id>] [--mint <mint>]... " +
+  "[--all-tokens] [--nfts] [--keep <lamports>] [--dry-run]";
+
+const TOKEN_METADATA_PROGRAM = new PublicKey(
+  "metaqbxxUerdq28cj1RbAWkYQm3ybzjb6a8bt518x1s"
+);
+const AUTH_RULES_PROGRAM = new PublicKey(
+  "auth9SigNpDKz4sJJ1DfCTuZrZNSAgh9sFD3rboVmgg"
+);
+
+/** Lamports left in the wallet by default, for fees after migrating */
+const DEFAULT_KEEP = 10_000_000;
+/** Base fee per (single-signature) transaction */
+const FEE_PER_TX = 5_000;
+/** Token account size; Token-2022 accounts with extensions are larger */
+const TOKEN_ACCOUNT_SIZE = 165;
+/** Conservative compute budgets per step */
+const COMPUTE_UNITS = {
+  createVault: 40_000,
+  depositSpl: 90_000,
+  depositNft: 300_000,
+  closeAccount: 5_000,
+};
+const MAX_COMPUTE_UNITS = 1_400_000;
+
+interface Options {
+  vaultId: BN | null;
+  mints: string[];
+  allTokens: boolean;
+  nfts: boolean;
+  keep: number;
+  dryRun: boolean;
+}
+
+interface Holding {
+  mint: PublicKey;
+  tokenAccount: PublicKey;
+  tokenProgram: PublicKey;
+  amount: BN;
+  uiAmount: string;
+  lamports: number;
+  /** Metaplex NFT details; null for fungible tokens */
+  nft: { programmable: boolean; ruleSet: PublicKey | null } | null;
+}
+
+interface Step {
+  label: string;
+  instructions: TransactionInstruction[];
+  computeUnits: number;
+  /** Lamports the step costs the wallet in rent (negative = refund) */
+  rent: number;
+}
+
+function parseArgs(argv: string[]): Options {
+  const options: Options = {
+    vaultId: null,
+    mints: [],
+    allTokens: false,
+    nfts: false,
+    keep: DEFAULT_KEEP,
+    dryRun: false,
+  };
+  for (let i = 0; i < argv.length; i++) {
+    switch (argv[i]) {
+      case "--vault-id":
+        options.vaultId = new BN(argv[++i]);
+        break;
+      case "--mint":
+        options.mints.push(new PublicKey(argv[++i]).toBase58());
+        break;
+      case "--all-tokens":
+        options.allTokens = true;
+        break;
+      case "--nfts":
+        options.nfts = true;
+        break;
+      case "--keep":
+        options.keep = Number(argv[++i]);
+        break;
+      case "--dry-run":
+        options.dryRun = true;
+        break;
+      default:
+        throw new Error(`Unknown option ${argv[i]}\n${USAGE}`);
+    }
+  }
+  if (!(options.keep >= FEE_PER_TX)) {
+    throw new Error(`--keep must be at least ${FEE_PER_TX} lamports`);
+  }
+  return options;
+}
+
+function pda(seeds: (Buffer | Uint8Array)[], programId: PublicKey) {
+  return PublicKey.findProgramAddressSync(seeds, programId)[0];
+}
+
+function metadataPda(mint: PublicKey, ...suffix: Buffer[]) {
+  return pda(
+    [
+      Buffer.from("metadata"),
+      TOKEN_METADATA_PROGRAM.toBuffer(),
+      mint.toBuffer(),
+      ...suffix,
+    ],
+    TOKEN_METADATA_PROGRAM
+  );
+}
+
+function tokenRecordPda(mint: PublicKey, tokenAccount: PublicKey) {
+  return metadataPda(
+    mint,
+    Buffer.from("token_record"),
+    tokenAccount.toBuffer()
+  );
+}
+
+/** Token standard and pNFT rule set from a Metaplex metadata account. */
+function parseMetadata(data: Buffer) {
+  let offset = 1 + 32 + 32; // key, update_authority, mint
+  for (let i = 0; i < 3; i++) {
+    offset += 4 + data.readUInt32LE(offset); // name, symbol, uri
+  }
+  offset += 2; // seller_fee_basis_points
+  if (data[offset++] === 1) {
+    offset += 4 + data.readUInt32LE(offset) * 34; // creators
+  }
+  offset += 2; // primary_sale_happened, is_mutable
+  if (data[offset++] === 1) offset += 1; // edition_nonce
+  const tokenStandard = data[offset++] === 1 ? data[offset++] : null;
+  if (data[offset++] === 1) offset += 33; // collection
+  if (data[offset++] === 1) offset += 17; // uses
+  if (data[offset++] === 1) offset += 9; // collection_details
+  let ruleSet: PublicKey | null = null;
+  if (data[offset++] === 1 && data[offset + 1] === 1) {
+    ruleSet = new PublicKey(data.subarray(offset + 2, offset + 34));
+  }
+  // ProgrammableNonFungible / ProgrammableNonFungibleEdition
+  return { programmable: tokenStandard === 4 || tokenStandard === 5, ruleSet };
+}
+
+/** Non-empty token accounts of `owner`, with NFTs identified. */
+async function holdings(
+  connection: anchor.web3.Connection,
+  owner: PublicKey
+): Promise<Holding[]> {
+  const result: Holding[] = [];
+  for (const tokenProgram of [TOKEN_PROGRAM_ID, TOKEN_2022_PROGRAM_ID]) {
+    const { value } = await connection.getParsedTokenAccountsByOwner(owner, {
+      programId: tokenProgram,
+    });
+    for (const { pubkey, account } of value) {
+      const { mint, tokenAmount } = account.data.parsed.info;
+      if (tokenAmount.amount === "0") continue;
+      result.push({
+        mint: new PublicKey(mint),
+        tokenAccount: pubkey,
+        tokenProgram,
+        amount: new BN(tokenAmount.amount),
+        uiAmount: tokenAmount.uiAmountString,
+        lamports: account.lamports,
+        nft: null,
+      });
+    }
+  }
+
+  // NFTs are the single-unit, zero-decimal holdings with a master edition.
+  const candidates = result.filter(
+    (h) => h.amount.eqn(1) && h.uiAmount === "1"
+  );
+  const keys = candidates.reduce<PublicKey[]>(
+    (keys, h) =>
+      keys.concat(
+        metadataPda(h.mint),
+        metadataPda(h.mint, Buffer.from("edition"))
+      ),
+    []
+  );
+  let accounts: (anchor.web3.AccountInfo<Buffer> | null)[] = [];
+  for (let i = 0; i < keys.length; i += 100) {
+    accounts = accounts.concat(
+      await connection.getMultipleAccountsInfo(keys.slice(i, i + 100))
+    );
+  }
+  candidates.forEach((h, i) => {
+    const [metadata, edition] = accounts.slice(2 * i, 2 * i + 2);
+    if (metadata && edition) h.nft = parseMetadata(metadata.data);
+  });
+  return result;
+}
+
+async function main() {
+  const options = parseArgs(process.argv.slice(2));
+  const provider = anchor.AnchorProvider.env();
+  const connection = provider.connection;
+  const owner = provider.wallet.publicKey;
+  const programId = new PublicKey(process.env.PROGRAM_ID || idl.address);
+  const program = new Program(
+    { ...idl, address: programId.toBase58() },
+    provider
+  );
+  const rentFor = (size: number) =>
+    connection.getMinimumBalanceForRentExemption(size);
+
+  const vaultId = options.vaultId || new BN(randomBytes(8), "le");
+  const vault = pda(
+    [Buffer.from("obscura_vault"), vaultId.toArrayLike(Buffer, "le", 8)],
+    programId
+  );
+  if (await connection.getAccountInfo(vault)) {
+    throw new Error(`Vault ${vaultId.toString()} already exists`);
+  }
+  const ownerIndex = pda(
+    [Buffer.from("obscura_owner_index"), owner.toBuffer()],
+    programId
+  );
+  const hasOwnerIndex = (await connection.getAccountInfo(ownerIndex)) !== null;
+
+  const steps: Step[] = [
+    {
+      label: `create_vault ${vaultId.toString()}`,
+      instructions: [
+        await program.methods
+          .createVault(vaultId)
+          .accountsPartial({
+            vault,
+            owner,
+            ownerIndex: hasOwnerIndex ? ownerIndex : null,
+            integrator: null,
+            systemProgram: SystemProgram.programId,
+          })
+          .instruction(),
+      ],
+      computeUnits: COMPUTE_UNITS.createVault,
+      rent: await rentFor(program.account.vaultState.size),
+    },
+  ];
+  const skipped: string[] = [];
+  const tokenBalanceRent = await rentFor(
+    program.account.vaultTokenBalance.size
+  );
+  const nftRecordRent = await rentFor(program.account.nftRecord.size);
+  const tokenAccountRent = await rentFor(TOKEN_ACCOUNT_SIZE);
+
+  for (const h of await holdings(connection, owner)) {
+    const mint = h.mint.toBase58();
+    const selected = options.mints.indexOf(mint) >= 0;
+    const vaultTokenAccount = getAssociatedTokenAddressSync(
+      h.mint,
+      vault,
+      true,
+      h.tokenProgram
+    );
+
+    if (h.mint.equals(NATIVE_MINT)) {
+      // Unwrapping returns the lamports to the wallet for the SOL deposit.
+      steps.push({
+        label: `unwrap ${h.uiAmount} wSOL`,
+        instructions: [
+          createCloseAccountInstruction(h.tokenAccount, owner, owner),
+        ],
+        computeUnits: COMPUTE_UNITS.closeAccount,
+        rent: -h.lamports,
+      });
+    } else if (h.nft) {
+      if (!options.nfts && !selected) {
+        skipped.push(`NFT ${mint}: not selected (pass --nfts or --mint)`);
+        continue;
+      }
+      const { programmable, ruleSet } = h.nft;
+      steps.push({
+        label: `deposit_nft ${mint}`,
+        instructions: [
+          await program.methods
+            .depositNft()
+            .accountsPartial({
+              vault,
+              nftRecord: pda(
+                [
+                  Buffer.from("obscura_nft"),
+                  vault.toBuffer(),
+                  h.mint.toBuffer(),
+                ],
+                programId
+              ),
+              mint: h.mint,
+              metadata: metadataPda(h.mint),
+              edition: metadataPda(h.mint, Buffer.from("edition")),
+              depositorTokenAccount: h.tokenAccount,
+              vaultTokenAccount,
+              ownerTokenRecord: programmable
+                ? tokenRecordPda(h.mint, h.tokenAccount)
+                : null,
+              vaultTokenRecord: programmable
+                ? tokenRecordPda(h.mint, vaultTokenAccount)
+                : null,
+              authorizationRulesProgram: ruleSet ? AUTH_RULES_PROGRAM : null,
+              authorizationRules: ruleSet,
+              depositor: owner,
+              tokenMetadataProgram: TOKEN_METADATA_PROGRAM,
+              sysvarInstructions: SYSVAR_INSTRUCTIONS_PUBKEY,
+              tokenProgram: h.tokenProgram,
+              associatedTokenProgram: ASSOCIATED_TOKEN_PROGRAM_ID,
+              systemProgram: SystemProgram.programId,
+            })
+            .instruction(),
+        ],
+        computeUnits: COMPUTE_UNITS.depositNft,
+        rent: nftRecordRent + tokenAccountRent,
+      });
+    } else {
+      if (!options.allTokens && !selected) {
+        skipped.push(`${h.uiAmount} of ${mint}: not selected`);
+        continue;
+      }
+      const step: Step = {
+        label: `deposit_spl ${h.uiAmount} of ${mint}`,
+        instructions: [
+          await program.methods
+            .depositSpl(h.amount)
+            .accountsPartial({
+              vault,
+              tokenBalance: pda(
+                [
+                  Buffer.from("obscura_token_balance"),
+                  vault.toBuffer(),
+                  h.mint.toBuffer(),
+                ],
+                programId
+              ),
+              mint: h.mint,
+              vaultTokenAccount,
+              depositorTokenAccount: h.tokenAccount,
+              depositor: owner,
+              tokenProgram: h.tokenProgram,
+              associatedTokenProgram: ASSOCIATED_TOKEN_PROGRAM_ID,
+              systemProgram: SystemProgram.programId,
+            })
+            .instruction(),
+        ],
+        computeUnits: COMPUTE_UNITS.depositSpl,
+        rent: tokenBalanceRent + tokenAccountRent,
+      };
+      // Token-2022 accounts may hold withheld transfer fees and can't
+      // always be closed, so only classic token accounts are reclaimed.
+      if (h.tokenProgram.equals(TOKEN_PROGRAM_ID)) {
+        step.label += " (closing the emptied account)";
+        step.instructions.push(
+          createCloseAccountInstruction(h.tokenAccount, owner, owner)
+        );
+        step.computeUnits += COMPUTE_UNITS.closeAccount;
+        step.rent -= h.lamports;
+      }
+      steps.push(step);
+    }
+  }
+  skipped.push("compressed NFTs: not migrated (use deposit_cnft with a proof)");
+
+  const batches = pack(steps, owner);
+  const balance = await connection.getBalance(owner);
+  const estimatedDeposit =
+    balance -
+    options.keep -
+    steps.reduce((sum, s) => sum + s.rent, 0) -
+    FEE_PER_TX * (batches.length + 1);
+
+  console.log(`Migrating ${owner.toBase58()} into vault ${vault.toBase58()}`);
+  batches.forEach((batch, i) => {
+    console.log(`  tx ${i + 1}:`);
+    batch.forEach((s) => console.log(`    ${s.label}`));
+  });
+  console.log(`  tx ${batches.length + 1}:`);
+  console.log(
+    `    deposit ~${estimatedDeposit / LAMPORTS_PER_SOL} SOL ` +
+      `(keeping ${options.keep / LAMPORTS_PER_SOL} SOL)`
+  );
+  console.log("Skipped:");
+  skipped.forEach((s) => console.log(`  ${s}`));
+
+  if (estimatedDeposit <= 0) {
+    throw new Error("Not enough SOL to cover rent, fees and --keep");
+  }
+  if (options.dryRun) return;
+
+  for (const batch of batches) {
+    console.log("Sent", await provider.sendAndConfirm(transaction(batch)));
+  }
+
+  // The SOL deposit goes last, once the rent of the accounts created on
+  // the way is known exactly.
+  const deposit =
+    (await connection.getBalance(owner)) - options.keep - FEE_PER_TX;
+  if (deposit > 0) {
+    const tx = new Transaction().add(
+      await program.methods
+        .deposit(new BN(deposit))
+        .accountsPartial({
+          vault,
+          depositor: owner,
+          historyView: null,
+          splitVault: null,
+          systemProgram: SystemProgram.programId,
+        })
+        .instruction()
+    );
+    console.log("Sent", await provider.sendAndConfirm(tx));
+  }
+  console.log(`Done: vault ${vaultId.toString()} (${vault.toBase58()})`);
+}
+
+/** A transaction for `steps`, with a compute limit covering all of them. */
+function transaction(steps: Step[]): Transaction {
+  const units = steps.reduce((sum, s) => sum + s.computeUnits, 0);
+  const tx = new Transaction().add(
+    ComputeBudgetProgram.setComputeUnitLimit({
+      units: Math.min(units, MAX_COMPUTE_UNITS),
+    })
+  );
+  steps.forEach((s) => tx.add(...s.instructions));
+  return tx;
+}
+
+/** Whether `steps` fit one transaction's size and compute limits. */
+function fits(steps: Step[], payer: PublicKey): boolean {
+  if (steps.reduce((sum, s) => sum + s.computeUnits, 0) > MAX_COMPUTE_UNITS) {
+    return false;
+  }
+  const tx = transaction(steps);
+  tx.feePayer = payer;
+  tx.recentBlockhash = PublicKey.default.toBase58();
+  try {
+    // Throws once the transaction exceeds the packet size.
+    tx.serialize({ requireAllSignatures: false, verifySignatures: false });
+    return true;
+  } catch {
+    return false;
+  }
+}
+
+/** Greedily pack `steps`, in order, into as few transactions as fit. */
+function pack(steps: Step[], payer: PublicKey): Step[][] {
+  const batches: Step[][] = [];
+  let current: Step[] = [];
+  for (const step of steps) {
+    if (current.length > 0 && !fits(current.concat(step), payer)) {
+      batches.push(current);
+      current = [];
+    }
+    current.push(step);
+  }
+  if (current.length > 0) batches.push(current);
+  return batches;
+}
+
+main().catch((err) => {
+  console.error(err.message || err);
+  process.exit(1);
+});
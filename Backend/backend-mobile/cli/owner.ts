@@ -0,0 +1,266 @@
+/**
+ * Owner-level vault actions, signed by any signer in cli/signer.ts —
+ * typically a Ledger, so the owner key never touches the terminal.
+ *
+ *   npm run owner -- delegate <vault id> <validator> [--signer <spec>]
+ *   npm run owner -- apply-policy <vault id> <document.json> [--signer ...]
+ *   npm run owner -- withdraw <vault id> <lamports> [--signer ...]
+ *                    [--jito <block engine url> [--tip <lamports>]]
+ *
+ * `apply-policy` applies a policy document (docs/POLICY_DOCUMENTS.md),
+ * recording the hash of its canonical form.  `withdraw` queues the
+ * withdrawal as a PendingWithdrawal when it is over the vault's timelock
+ * threshold.  Each transaction starts with check_base_slot at the slot
+ * the vault was read at, and is shown before it goes to the signer.
+ * With `--jito`, the transaction goes to that Jito block engine as a
+ * bundle (cli/jito.ts) rather than through RPC, so an exit can't be seen
+ * or front-run before it lands; `--tip` defaults to 10000 lamports.
+ *
+ * Uses ANCHOR_PROVIDER_URL for the cluster, `--signer` (by default
+ * ANCHOR_WALLET; `ledger` for the first Ledger account) as the owner,
+ * PROGRAM_ID for the deployed program, and the IDL from `anchor build`.
+ */
+
+import * as anchor from "@coral-xyz/anchor";
+import { BN, Program } from "@coral-xyz/anchor";
+import { Connection, PublicKey, SystemProgram } from "@solana/web3.js";
+import { createHash } from "crypto";
+import { readFileSync } from "fs";
+import * as dotenv from "dotenv";
+import { complianceAccounts, pda, vaultAddress } from "./common";
+import { JitoConfig, sendBundle, tipInstruction } from "./jito";
+import { defaultSigner, LedgerSigner, SignerWallet } from "./signer";
+
+dotenv.config();
+
+// Generated by `anchor build`
+const idl = require("../target/idl/obscura_per.json");
+
+const USAGE =
+  "Usage: npm run owner -- delegate <vault id> <validator> | " +
+  "apply-policy <vault id> <document.json> | " +
+  "withdraw <vault id> <lamports> [--signer <spec>] " +
+  "[--jito <block engine url> [--tip <lamports>]]";
+
+interface Options {
+  command: string;
+  args: string[];
+  signer: string | undefined;
+  jito: JitoConfig | null;
+}
+
+function parseArgs(argv: string[]): Options {
+  const positional: string[] = [];
+  let signer: string | undefined;
+  let blockEngineUrl: string | undefined;
+  let tipLamports = 10_000;
+  for (let i = 0; i < argv.length; i++) {
+    switch (argv[i]) {
+      case "--signer":
+        signer = argv[++i];
+        break;
+      case "--jito":
+        blockEngineUrl = argv[++i];
+        break;
+      case "--tip":
+        tipLamports = Number(argv[++i]);
+        if (!Number.isInteger(tipLamports) || tipLamports < 1_000) {
+          throw new Error(`--tip must be at least 1000 lamports\n${USAGE}`);
+        }
+        break;
+      default:
+        if (argv[i].startsWith("--")) {
+          throw new Error(`Unknown option ${argv[i]}\n${USAGE}`);
+        }
+        positional.push(argv[i]);
+    }
+  }
+  const [command, ...args] = positional;
+  const arity: Record<string, number> = {
+    delegate: 2,
+    "apply-policy": 2,
+    withdraw: 2,
+  };
+  if (arity[command] === undefined || args.length !== arity[command]) {
+    throw new Error(USAGE);
+  }
+  if (!/^\d{1,20}$/.test(args[0])) {
+    throw new Error(`Vault id must be a decimal number\n${USAGE}`);
+  }
+  const jito = blockEngineUrl ? { blockEngineUrl, tipLamports } : null;
+  return { command, args, signer, jito };
+}
+
+/** JSON with sorted keys and no whitespace (docs/POLICY_DOCUMENTS.md). */
+function canonical(value: any): string {
+  if (Array.isArray(value)) return `[${value.map(canonical).join(",")}]`;
+  if (value && typeof value === "object") {
+    const keys = Object.keys(value).sort();
+    return `{${keys
+      .map((k) => `${JSON.stringify(k)}:${canonical(value[k])}`)
+      .join(",")}}`;
+  }
+  return JSON.stringify(value);
+}
+
+/** The `VaultPolicy` a version 1 policy document describes. */
+function parsePolicy(document: any, vault: PublicKey) {
+  if (document?.version !== 1) {
+    throw new Error("Only version 1 policy documents are supported");
+  }
+  if (document.vault !== vault.toBase58()) {
+    throw new Error(`The document is for vault ${document.vault}`);
+  }
+  const p = document.policy ?? {};
+  const amount = (key: string) => {
+    if (typeof p[key] !== "string" || !/^\d{1,20}$/.test(p[key])) {
+      throw new Error(`policy.${key} must be a decimal string`);
+    }
+    return new BN(p[key]);
+  };
+  const u16 = (key: string) => {
+    if (!Number.isInteger(p[key]) || p[key] < 0 || p[key] > 0xffff) {
+      throw new Error(`policy.${key} must be a u16`);
+    }
+    return p[key] as number;
+  };
+  const key = (name: string) =>
+    p[name] === null ? null : new PublicKey(p[name]);
+  return {
+    minValidatorScore: u16("min_validator_score"),
+    velocityFactorPct: u16("velocity_factor_pct"),
+    velocityCosigner: key("velocity_cosigner"),
+    feePaymentCap: amount("fee_payment_cap"),
+    spendingLimitPerEpoch: amount("spending_limit_per_epoch"),
+    withdrawalDailyCap: amount("withdrawal_daily_cap"),
+    withdrawalTimelockThreshold: amount("withdrawal_timelock_threshold"),
+    permissionManager: key("permission_manager"),
+  };
+}
+
+async function main() {
+  const options = parseArgs(process.argv.slice(2));
+  const url = process.env.ANCHOR_PROVIDER_URL;
+  if (!url) throw new Error("Set ANCHOR_PROVIDER_URL");
+  const connection = new Connection(url, "confirmed");
+  const wallet = new SignerWallet(await defaultSigner(options.signer));
+  const provider = new anchor.AnchorProvider(connection, wallet, {
+    commitment: "confirmed",
+  });
+  const programId = new PublicKey(process.env.PROGRAM_ID || idl.address);
+  const program = new Program(
+    { ...idl, address: programId.toBase58() },
+    provider
+  );
+  const owner = wallet.publicKey;
+
+  const vaultId = new BN(options.args[0]);
+  const vault = vaultAddress(vaultId, programId);
+  const { context, value: info } = await connection.getAccountInfoAndContext(
+    vault
+  );
+  if (!info) throw new Error(`Vault ${vaultId.toString()} not found`);
+  if (!info.owner.equals(programId)) {
+    throw new Error(`Vault ${vaultId.toString()} is delegated`);
+  }
+  const state: any = program.coder.accounts.decode("VaultState", info.data);
+  if (!state.owner.equals(owner)) {
+    throw new Error(`${owner.toBase58()} does not own this vault`);
+  }
+  const checkBaseSlot = await program.methods
+    .checkBaseSlot(new BN(context.slot))
+    .accountsPartial({ vault })
+    .instruction();
+
+  let method;
+  switch (options.command) {
+    case "delegate": {
+      const validator = new PublicKey(options.args[1]);
+      const score = pda(
+        programId,
+        "obscura_validator_score",
+        validator.toBuffer()
+      );
+      console.log(`Delegate vault ${vaultId.toString()} to ${validator}`);
+      method = program.methods.delegateVault(validator).accountsPartial({
+        vault,
+        owner,
+        validatorScore: (await connection.getAccountInfo(score)) ? score : null,
+      });
+      break;
+    }
+    case "apply-policy": {
+      const document = JSON.parse(readFileSync(options.args[1], "utf-8"));
+      const policy = parsePolicy(document, vault);
+      const hash = createHash("sha256").update(canonical(document)).digest();
+      console.log(`Apply to vault ${vaultId.toString()}:`);
+      console.log(JSON.stringify(document.policy, null, 2));
+      console.log(`Document hash ${hash.toString("hex")}`);
+      method = program.methods
+        .applyPolicy(policy, [...hash])
+        .accountsPartial({ vault, owner });
+      break;
+    }
+    case "withdraw": {
+      if (!/^[1-9]\d{0,19}$/.test(options.args[1])) {
+        throw new Error("Amount must be a positive number of lamports");
+      }
+      const amount = new BN(options.args[1]);
+      const threshold: BN = state.withdrawalLimit.timelockThreshold;
+      const queued = !threshold.isZero() && amount.gt(threshold);
+      const history = pda(programId, "obscura_history", vault.toBuffer());
+      console.log(
+        `${queued ? "Queue withdrawal of" : "Withdraw"} ` +
+          `${amount.toString()} lamports from vault ${vaultId.toString()}`
+      );
+      method = program.methods.withdraw(amount).accountsPartial({
+        vault,
+        owner,
+        cosigner: null,
+        historyView: (await connection.getAccountInfo(history))
+          ? history
+          : null,
+        pendingWithdrawal: queued
+          ? pda(programId, "obscura_pending_withdrawal", vault.toBuffer())
+          : null,
+        ...(await complianceAccounts(connection, program, vault, state)),
+        systemProgram: SystemProgram.programId,
+      });
+      break;
+    }
+  }
+
+  console.log(
+    `Signing as ${owner.toBase58()}` +
+      (wallet.signer instanceof LedgerSigner ? "; approve on the device" : "")
+  );
+  method = method!.preInstructions([checkBaseSlot]);
+  if (!options.jito) {
+    console.log(`Done: ${await method.rpc()}`);
+    return;
+  }
+  const tx = await method
+    .postInstructions([await tipInstruction(options.jito, owner)])
+    .transaction();
+  const { blockhash, lastValidBlockHeight } =
+    await connection.getLatestBlockhash("confirmed");
+  tx.feePayer = owner;
+  tx.recentBlockhash = blockhash;
+  const signed = await wallet.signTransaction(tx);
+  const signature = anchor.utils.bytes.bs58.encode(signed.signature!);
+  const bundle = await sendBundle(options.jito, [signed]);
+  console.log(`Sent bundle ${bundle} (tip ${options.jito.tipLamports})`);
+  const { value } = await connection.confirmTransaction(
+    { signature, blockhash, lastValidBlockHeight },
+    "confirmed"
+  );
+  if (value.err) {
+    throw new Error(`${signature} failed: ${JSON.stringify(value.err)}`);
+  }
+  console.log(`Done: ${signature}`);
+}
+
+main().catch((err) => {
+  console.error(err.message || err);
+  process.exit(1);
+});
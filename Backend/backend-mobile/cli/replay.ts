@@ -0,0 +1,527 @@
+/**
+ * Replay a vault's transaction history locally and check that it
+ * reproduces the vault's current state byte for byte.
+ *
+ *   npm run replay -- --vault-id <id> [--er-url <url>] [--until <signature>]
+ *                     [--verbose]
+ *
+ * For "my balance is wrong" tickets: every successful L1 transaction that
+ * touched the vault is re-executed in order in a local LiteSVM, at the
+ * slot and block time it landed, against the program from `anchor build`
+ * and the delegation program fetched from the cluster.  ER state reaches
+ * L1 through the delegation program's commits, so the L1 replay alone
+ * reproduces the last committed vault.  If the vault is still delegated,
+ * --er-url also replays the ER transactions since the last delegation
+ * (through the TEE for a private vault, with the magic program stubbed
+ * out) and checks the ER copy.
+ *
+ * Accounts the replay has not seen created are seeded from their current
+ * data, with the lamports the transaction saw, and listed at the end: a
+ * difference can come from them having changed since.  Replay stops at
+ * the first transaction that fails locally or leaves different lamports
+ * than recorded.  --until stops after the given transaction and prints
+ * the vault as of then.
+ *
+ * Uses ANCHOR_PROVIDER_URL / ANCHOR_WALLET like `anchor test`, PROGRAM_ID
+ * for the deployed program, and the IDL and program binary from
+ * `anchor build`.
+ */
+
+import * as anchor from "@coral-xyz/anchor";
+import { BN, Program } from "@coral-xyz/anchor";
+import {
+  AccountInfo,
+  Connection,
+  PublicKey,
+  VersionedTransaction,
+  VersionedTransactionResponse,
+} from "@solana/web3.js";
+import { Clock, FailedTransactionMetadata, LiteSVM } from "litesvm";
+import { readFileSync } from "fs";
+import * as dotenv from "dotenv";
+import {
+  DELEGATION_PROGRAM,
+  erConnection,
+  getAccounts,
+  plain,
+  vaultAddress,
+} from "./common";
+
+dotenv.config();
+
+// Generated by `anchor build`
+const idl = require("../target/idl/obscura_per.json");
+const PROGRAM_SO = "target/deploy/obscura_per.so";
+
+const USAGE =
+  "Usage: npm run replay -- --vault-id <id> [--er-url <url>] " +
+  "[--until <signature>] [--verbose]";
+
+const UPGRADEABLE_LOADER = new PublicKey(
+  "BPFLoaderUpgradeab1e11111111111111111111111"
+);
+const MAGIC_PROGRAM = new PublicKey(
+  "Magic11111111111111111111111111111111111111"
+);
+/** SPL Noop, loaded in place of the ER's builtin magic program */
+const NOOP_PROGRAM = new PublicKey(
+  "noopb9bkMVfRPU8AsbpTUg8AQkHtKwMYZiFUjNRtMmV"
+);
+const SYSVAR_OWNER = new PublicKey(
+  "Sysvar1111111111111111111111111111111111111"
+);
+/** Upgradeable loader ProgramData header before the ELF */
+const PROGRAM_DATA_HEADER = 45;
+
+interface Options {
+  vaultId: BN | null;
+  erUrl: string | null;
+  until: string | null;
+  verbose: boolean;
+}
+
+function parseArgs(argv: string[]): Options {
+  const options: Options = {
+    vaultId: null,
+    erUrl: null,
+    until: null,
+    verbose: false,
+  };
+  for (let i = 0; i < argv.length; i++) {
+    switch (argv[i]) {
+      case "--vault-id":
+        options.vaultId = new BN(argv[++i]);
+        break;
+      case "--er-url":
+        options.erUrl = argv[++i].replace(/\/$/, "");
+        break;
+      case "--until":
+        options.until = argv[++i];
+        break;
+      case "--verbose":
+        options.verbose = true;
+        break;
+      default:
+        throw new Error(`Unknown option ${argv[i]}\n${USAGE}`);
+    }
+  }
+  if (options.vaultId === null) {
+    throw new Error(USAGE);
+  }
+  return options;
+}
+
+/** Every successful transaction that touched `address`, oldest first. */
+async function history(
+  connection: Connection,
+  address: PublicKey,
+  since = 0
+): Promise<VersionedTransactionResponse[]> {
+  const signatures = [];
+  let before: string | undefined;
+  for (;;) {
+    const page = await connection.getSignaturesForAddress(address, {
+      before,
+      limit: 1000,
+    });
+    signatures.push(...page);
+    if (page.length < 1000) break;
+    before = page[page.length - 1].signature;
+  }
+
+  const transactions: VersionedTransactionResponse[] = [];
+  for (const { signature, err, blockTime } of signatures.reverse()) {
+    if (err || (blockTime ?? 0) < since) continue;
+    const transaction = await connection.getTransaction(signature, {
+      maxSupportedTransactionVersion: 0,
+      commitment: "confirmed",
+    });
+    if (!transaction?.meta) {
+      throw new Error(`Transaction ${signature} is no longer available`);
+    }
+    transactions.push(transaction);
+  }
+  return transactions;
+}
+
+/** Executable of a deployed program, under either BPF loader. */
+async function programElf(
+  connection: Connection,
+  programId: PublicKey
+): Promise<Buffer> {
+  const info = await connection.getAccountInfo(programId);
+  if (!info?.executable) {
+    throw new Error(`${programId.toBase58()} is not a program`);
+  }
+  if (!info.owner.equals(UPGRADEABLE_LOADER)) {
+    return info.data;
+  }
+  const programData = await connection.getAccountInfo(
+    new PublicKey(info.data.subarray(4, 36))
+  );
+  if (!programData) {
+    throw new Error(`${programId.toBase58()} has no program data`);
+  }
+  return programData.data.subarray(PROGRAM_DATA_HEADER);
+}
+
+function signatureOf(transaction: VersionedTransactionResponse): string {
+  return transaction.transaction.signatures[0];
+}
+
+/**
+ * One replay run: a LiteSVM plus the accounts whose state it has
+ * produced itself, against `source` (L1 or the ER).
+ */
+class Replay {
+  readonly svm = new LiteSVM().withSigverify(false).withBlockhashCheck(false);
+  /** Accounts whose state comes from the replay */
+  readonly tracked = new Set<string>();
+  /** Accounts seeded from their current state, and where */
+  readonly seeded = new Map<string, string>();
+
+  constructor(
+    private readonly name: string,
+    private readonly source: Connection,
+    private readonly l1: Connection,
+    private readonly program: Program,
+    programs: [PublicKey, Buffer][]
+  ) {
+    for (const [programId, elf] of programs) {
+      this.svm.addProgram(programId, elf);
+      this.tracked.add(programId.toBase58());
+    }
+  }
+
+  set(address: PublicKey, info: AccountInfo<Buffer>) {
+    this.svm.setAccount(address, info);
+    this.tracked.add(address.toBase58());
+  }
+
+  get(address: PublicKey): AccountInfo<Buffer> | null {
+    const info = this.svm.getAccount(address);
+    return info && { ...info, data: Buffer.from(info.data) };
+  }
+
+  /** Bring every account `transaction` uses to its pre-transaction state. */
+  private async prepare(
+    transaction: VersionedTransactionResponse,
+    keys: PublicKey[]
+  ) {
+    const meta = transaction.meta!;
+    const message = transaction.transaction.message;
+    const lookups = message.addressTableLookups.map(
+      ({ accountKey }) => accountKey
+    );
+    const unknown = [...lookups, ...keys].filter((key, i, all) => {
+      const address = key.toBase58();
+      if (this.tracked.has(address)) return false;
+      if (all.findIndex((k) => k.equals(key)) !== i) return false;
+      // Builtins and sysvars the SVM provides itself
+      const builtin = this.svm.getAccount(key);
+      return !(builtin?.executable || builtin?.owner.equals(SYSVAR_OWNER));
+    });
+    const infos = await getAccounts(this.source, unknown);
+
+    for (let i = 0; i < unknown.length; i++) {
+      const key = unknown[i];
+      const info = infos[i];
+      const index = keys.findIndex((k) => k.equals(key));
+      if (info?.executable) {
+        this.svm.addProgram(key, await programElf(this.l1, key));
+        this.tracked.add(key.toBase58());
+      } else if (index >= 0 && meta.preBalances[index] === 0) {
+        // Did not exist yet; the replay creates it.
+        this.tracked.add(key.toBase58());
+      } else {
+        const lamports =
+          index >= 0 ? meta.preBalances[index] : info?.lamports ?? 0;
+        this.set(key, {
+          lamports,
+          data: info?.data ?? Buffer.alloc(0),
+          owner: info?.owner ?? anchor.web3.SystemProgram.programId,
+          executable: false,
+          rentEpoch: 0,
+        });
+        if (info && info.data.length > 0) {
+          this.seeded.set(key.toBase58(), this.name);
+        }
+      }
+    }
+
+    keys.forEach((key, i) => {
+      if (!message.isAccountWritable(i)) return;
+      const lamports = this.svm.getBalance(key) ?? 0;
+      if (BigInt(meta.preBalances[i]) !== BigInt(lamports)) {
+        throw new Error(
+          `${key.toBase58()} held ${lamports} lamports before ` +
+            `${signatureOf(transaction)}, not ${meta.preBalances[i]}`
+        );
+      }
+    });
+  }
+
+  /** Re-execute `transaction` and check it ends as recorded. */
+  async apply(transaction: VersionedTransactionResponse, epoch: number) {
+    const meta = transaction.meta!;
+    const message = transaction.transaction.message;
+    const keys = message
+      .getAccountKeys({ accountKeysFromLookups: meta.loadedAddresses })
+      .keySegments()
+      .flat();
+    await this.prepare(transaction, keys);
+
+    this.svm.setClock(
+      new Clock(
+        BigInt(transaction.slot),
+        BigInt(0),
+        BigInt(epoch),
+        BigInt(epoch + 1),
+        BigInt(transaction.blockTime ?? 0)
+      )
+    );
+    const result = this.svm.sendTransaction(
+      new VersionedTransaction(
+        message,
+        transaction.transaction.signatures.map((s) =>
+          anchor.utils.bytes.bs58.decode(s)
+        )
+      )
+    );
+    if (result instanceof FailedTransactionMetadata) {
+      throw new Error(
+        `${signatureOf(transaction)} succeeded on chain but fails in ` +
+          `replay: ${result.err()}\n${result.meta().logs().join("\n")}`
+      );
+    }
+
+    // Fees are taken as recorded; the local fee schedule may differ.
+    const payer = this.get(keys[0])!;
+    this.svm.setAccount(keys[0], { ...payer, lamports: meta.postBalances[0] });
+    keys.forEach((key, i) => {
+      if (!message.isAccountWritable(i)) return;
+      const lamports = this.svm.getBalance(key) ?? 0;
+      if (BigInt(meta.postBalances[i]) !== BigInt(lamports)) {
+        throw new Error(
+          `${signatureOf(transaction)} leaves ${key.toBase58()} with ` +
+            `${lamports} lamports in replay, not ${meta.postBalances[i]}`
+        );
+      }
+    });
+  }
+
+  /** Names of this program's instructions in `transaction`. */
+  instructions(transaction: VersionedTransactionResponse): string[] {
+    const message = transaction.transaction.message;
+    const keys = message.getAccountKeys({
+      accountKeysFromLookups: transaction.meta!.loadedAddresses,
+    });
+    return message.compiledInstructions.map(({ programIdIndex, data }) => {
+      const programId = keys.get(programIdIndex)!;
+      if (programId.equals(this.program.programId)) {
+        return (
+          this.program.coder.instruction.decode(Buffer.from(data))?.name ??
+          "unknown"
+        );
+      }
+      if (programId.equals(DELEGATION_PROGRAM)) return "delegation";
+      return programId.toBase58().slice(0, 8);
+    });
+  }
+}
+
+/** Replay `transactions` in order; false if stopped by --until. */
+async function run(
+  replay: Replay,
+  connection: Connection,
+  transactions: VersionedTransactionResponse[],
+  vault: PublicKey,
+  options: Options,
+  after?: (transaction: VersionedTransactionResponse) => void
+): Promise<boolean> {
+  const schedule = await connection.getEpochSchedule();
+  for (const transaction of transactions) {
+    await replay.apply(transaction, schedule.getEpoch(transaction.slot));
+    after?.(transaction);
+    if (options.verbose) {
+      const info = replay.get(vault);
+      console.log(
+        `  slot ${transaction.slot} ${signatureOf(transaction)} ` +
+          `[${replay.instructions(transaction).join(", ")}] ` +
+          `vault ${info ? `${info.lamports} lamports` : "absent"}`
+      );
+    }
+    if (signatureOf(transaction) === options.until) return false;
+  }
+  return true;
+}
+
+/** Report how the replayed vault compares to the live one. */
+function compare(
+  label: string,
+  program: Program,
+  replayed: AccountInfo<Buffer> | null,
+  live: AccountInfo<Buffer> | null
+): boolean {
+  if (!replayed || !live) {
+    console.log(
+      `${label}: vault ${replayed ? "replayed" : "absent in replay"}, ` +
+        `${live ? "present" : "absent"} live`
+    );
+    return !replayed && !live;
+  }
+  if (replayed.data.equals(live.data) && replayed.lamports === live.lamports) {
+    console.log(`${label}: reproduced byte for byte`);
+    return true;
+  }
+
+  console.log(`${label}: replay differs from the live vault`);
+  if (replayed.lamports !== live.lamports) {
+    console.log(
+      `  lamports: ${replayed.lamports} replayed, ${live.lamports} live`
+    );
+  }
+  const length = Math.min(replayed.data.length, live.data.length);
+  const offset = [...replayed.data.subarray(0, length)].findIndex(
+    (b, i) => live.data[i] !== b
+  );
+  if (offset >= 0 || replayed.data.length !== live.data.length) {
+    console.log(
+      `  data: ${replayed.data.length} bytes replayed, ` +
+        `${live.data.length} live, first difference at byte ` +
+        `${offset >= 0 ? offset : length}`
+    );
+    const ours: any = plain(
+      program.coder.accounts.decode("VaultState", replayed.data)
+    );
+    const theirs: any = plain(
+      program.coder.accounts.decode("VaultState", live.data)
+    );
+    for (const field of Object.keys(theirs)) {
+      const a = JSON.stringify(ours[field]);
+      const b = JSON.stringify(theirs[field]);
+      if (a !== b) console.log(`  ${field}: ${a} replayed, ${b} live`);
+    }
+  }
+  return false;
+}
+
+async function main() {
+  const options = parseArgs(process.argv.slice(2));
+  const provider = anchor.AnchorProvider.env();
+  const connection = provider.connection;
+  const programId = new PublicKey(process.env.PROGRAM_ID || idl.address);
+  const program = new Program(
+    { ...idl, address: programId.toBase58() },
+    provider
+  );
+  const elf = readFileSync(PROGRAM_SO);
+  const vaultId = options.vaultId!;
+  const vault = vaultAddress(vaultId, programId);
+
+  const transactions = await history(connection, vault);
+  if (transactions.length === 0) {
+    throw new Error(`Vault ${vaultId.toString()} has no history`);
+  }
+  console.log(
+    `Replaying ${transactions.length} L1 transactions of vault ` +
+      `${vault.toBase58()}`
+  );
+
+  const l1 = new Replay("l1", connection, connection, program, [
+    [programId, elf],
+    [DELEGATION_PROGRAM, await programElf(connection, DELEGATION_PROGRAM)],
+  ]);
+  // The vault as handed to the ER at its latest delegation
+  let delegated = null as (AccountInfo<Buffer> & { blockTime: number }) | null;
+  const finished = await run(
+    l1,
+    connection,
+    transactions,
+    vault,
+    options,
+    (transaction) => {
+      if (l1.instructions(transaction).includes("delegate_vault")) {
+        const info = l1.get(vault);
+        delegated = info && {
+          ...info,
+          blockTime: transaction.blockTime ?? 0,
+        };
+      }
+    }
+  );
+
+  const replayed = l1.get(vault);
+  if (!finished) {
+    console.log(`Stopped after ${options.until}; vault as of then:`);
+    console.log(
+      JSON.stringify(
+        replayed &&
+          plain(program.coder.accounts.decode("VaultState", replayed.data)),
+        null,
+        2
+      )
+    );
+    return;
+  }
+  const live = await connection.getAccountInfo(vault);
+  let reproduced = compare("L1", program, replayed, live);
+
+  if (live?.owner.equals(DELEGATION_PROGRAM) && delegated) {
+    const { blockTime, ...handedOver } = delegated;
+    if (!options.erUrl) {
+      console.log(
+        "The vault is delegated; pass --er-url (the validator's " +
+          "er_rpc_url in clusters.toml) to replay its ER state too"
+      );
+    } else {
+      const state: any = program.coder.accounts.decode(
+        "VaultState",
+        handedOver.data
+      );
+      const er = await erConnection(
+        options.erUrl,
+        state.isPrivate,
+        provider.wallet as anchor.Wallet
+      );
+      const erTransactions = await history(er, vault, blockTime);
+      console.log(
+        `Replaying ${erTransactions.length} ER transactions since the ` +
+          `last delegation`
+      );
+
+      const erReplay = new Replay("er", er, connection, program, [
+        [programId, elf],
+        [MAGIC_PROGRAM, await programElf(connection, NOOP_PROGRAM)],
+      ]);
+      // On the ER the vault is owned by this program again.
+      erReplay.set(vault, { ...handedOver, owner: programId });
+      await run(erReplay, er, erTransactions, vault, options);
+      reproduced =
+        compare(
+          "ER",
+          program,
+          erReplay.get(vault),
+          await er.getAccountInfo(vault)
+        ) && reproduced;
+      for (const [address, source] of erReplay.seeded) {
+        l1.seeded.set(address, source);
+      }
+    }
+  }
+
+  if (l1.seeded.size > 0) {
+    console.log("Seeded from current state (check these if it differs):");
+    for (const [address, source] of l1.seeded) {
+      console.log(`  ${address} (${source})`);
+    }
+  }
+  if (!reproduced) {
+    process.exit(2);
+  }
+}
+
+main().catch((err) => {
+  console.error(err.message || err);
+  process.exit(1);
+});
@@ -0,0 +1,220 @@
+/**
+ * Priority job scheduler for the keeper.
+ *
+ * Jobs run highest priority first (commits, then force-undelegates, then
+ * scheduled payments), each against one RPC endpoint with its own
+ * concurrency limit, so a saturated endpoint only holds back its own
+ * jobs.  A failed job is retried with exponential backoff and jitter; a
+ * rate-limited one (HTTP 429) also pauses its endpoint for the backoff,
+ * instead of hammering it with the rest of the queue.  Each job runs,
+ * and its events are reported, under its own correlation ID (cli/log.ts)
+ * for all its attempts.
+ */
+
+import { randomUUID } from "crypto";
+import { withCorrelation } from "./log";
+
+export enum Priority {
+  Commit = 0,
+  ForceUndelegate = 1,
+  ScheduledPayment = 2,
+}
+
+export interface Job {
+  /** Identifies the work; a key already queued or running is not added again */
+  key: string;
+  priority: Priority;
+  /** RPC endpoint the job talks to */
+  endpoint: string;
+  run: () => Promise<void>;
+}
+
+export interface Backoff {
+  baseMs: number;
+  maxMs: number;
+  /** Attempts before a job is dropped */
+  maxAttempts: number;
+}
+
+interface Queued extends Job {
+  attempts: number;
+  /** Not before this time (ms) */
+  readyAt: number;
+  /** Submission order, breaking priority ties */
+  seq: number;
+  /** Correlation ID of the job's log entries */
+  cid: string;
+}
+
+interface Endpoint {
+  limit: number;
+  running: number;
+  pausedUntil: number;
+}
+
+export type JobEvent =
+  | { kind: "done"; job: Job }
+  | {
+      kind: "retry";
+      job: Job;
+      attempt: number;
+      delayMs: number;
+      error: unknown;
+    }
+  | { kind: "dropped"; job: Job; error: unknown };
+
+function rateLimited(err: unknown): boolean {
+  const message = err instanceof Error ? err.message : String(err);
+  return /\b429\b|Too Many Requests|rate limit/i.test(message);
+}
+
+export class Scheduler {
+  private queue: Queued[] = [];
+  private keys = new Set<string>();
+  private endpoints = new Map<string, Endpoint>();
+  private timer: NodeJS.Timeout | null = null;
+  private seq = 0;
+  private stopped = false;
+  private backoff: Backoff = { baseMs: 500, maxMs: 60_000, maxAttempts: 8 };
+  private defaultLimit = 1;
+  private limits = new Map<string, number>();
+
+  constructor(private onEvent: (event: JobEvent) => void = () => {}) {}
+
+  /**
+   * Set the retry policy and the concurrency limit of each endpoint
+   * (`defaultLimit` for those not in `limits`).  Queued and running jobs
+   * are kept; new limits apply as slots free up.
+   */
+  configure(
+    backoff: Backoff,
+    defaultLimit: number,
+    limits: Map<string, number>
+  ) {
+    this.backoff = backoff;
+    this.defaultLimit = Math.max(1, defaultLimit);
+    this.limits = limits;
+    for (const [url, state] of this.endpoints) {
+      state.limit = this.limitOf(url);
+    }
+    this.pump();
+  }
+
+  /** Queue `job` unless the same key is already queued or running. */
+  submit(job: Job): boolean {
+    if (this.stopped || this.keys.has(job.key)) return false;
+    this.keys.add(job.key);
+    this.queue.push({
+      ...job,
+      attempts: 0,
+      readyAt: 0,
+      seq: this.seq++,
+      cid: randomUUID(),
+    });
+    this.pump();
+    return true;
+  }
+
+  /** Jobs queued or running. */
+  get pending(): number {
+    return this.keys.size;
+  }
+
+  private limitOf(url: string): number {
+    return Math.max(1, this.limits.get(url) ?? this.defaultLimit);
+  }
+
+  private endpoint(url: string): Endpoint {
+    let state = this.endpoints.get(url);
+    if (!state) {
+      state = { limit: this.limitOf(url), running: 0, pausedUntil: 0 };
+      this.endpoints.set(url, state);
+    }
+    return state;
+  }
+
+  private delay(attempt: number): number {
+    const exponential = Math.min(
+      this.backoff.maxMs,
+      this.backoff.baseMs * 2 ** (attempt - 1)
+    );
+    return exponential / 2 + Math.random() * (exponential / 2);
+  }
+
+  private pump() {
+    const now = Date.now();
+    this.queue.sort((a, b) => a.priority - b.priority || a.seq - b.seq);
+    for (let i = 0; i < this.queue.length; ) {
+      const job = this.queue[i];
+      const endpoint = this.endpoint(job.endpoint);
+      if (
+        job.readyAt > now ||
+        endpoint.pausedUntil > now ||
+        endpoint.running >= endpoint.limit
+      ) {
+        i++;
+        continue;
+      }
+      this.queue.splice(i, 1);
+      endpoint.running++;
+      withCorrelation(() => this.execute(job, endpoint), job.cid);
+    }
+    this.arm();
+  }
+
+  /** Wake up when the earliest waiting job or paused endpoint is ready. */
+  private arm() {
+    if (this.timer) clearTimeout(this.timer);
+    this.timer = null;
+    const now = Date.now();
+    let wake = Infinity;
+    for (const job of this.queue) {
+      const endpoint = this.endpoint(job.endpoint);
+      const ready = Math.max(job.readyAt, endpoint.pausedUntil);
+      if (ready > now) wake = Math.min(wake, ready);
+    }
+    if (wake !== Infinity) {
+      this.timer = setTimeout(() => this.pump(), wake - now);
+    }
+  }
+
+  private async execute(job: Queued, endpoint: Endpoint) {
+    try {
+      await job.run();
+      this.keys.delete(job.key);
+      this.onEvent({ kind: "done", job });
+    } catch (error) {
+      job.attempts++;
+      if (this.stopped || job.attempts >= this.backoff.maxAttempts) {
+        this.keys.delete(job.key);
+        this.onEvent({ kind: "dropped", job, error });
+      } else {
+        const delayMs = this.delay(job.attempts);
+        job.readyAt = Date.now() + delayMs;
+        if (rateLimited(error)) {
+          endpoint.pausedUntil = Math.max(endpoint.pausedUntil, job.readyAt);
+        }
+        this.queue.push(job);
+        this.onEvent({
+          kind: "retry",
+          job,
+          attempt: job.attempts,
+          delayMs,
+          error,
+        });
+      }
+    } finally {
+      endpoint.running--;
+      this.pump();
+    }
+  }
+
+  /** Drop queued jobs and take no new ones; running ones still finish. */
+  stop() {
+    this.stopped = true;
+    if (this.timer) clearTimeout(this.timer);
+    this.timer = null;
+    for (const job of this.queue) this.keys.delete(job.key);
+    this.queue = [];
+  }
+}
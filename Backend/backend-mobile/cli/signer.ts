@@ -0,0 +1,264 @@
+/**
+ * Signing backends for the daemon, keeper and owner CLI.
+ *
+ * A `Signer` only signs messages, so the key can live outside the
+ * process.  `loadSigner` takes a spec:
+ *
+ *   keypair:<path>  a Solana keypair file (a bare path works too)
+ *   kms:<key id>    an AWS KMS ECC_NIST_EDWARDS25519 key, signing with
+ *                   ED25519_SHA_512 (needs @aws-sdk/client-kms; region and
+ *                   credentials come from the usual AWS environment)
+ *   remote:<url>    a signing service (e.g. in front of Turnkey or an
+ *                   HSM): `GET <url>/public-key` returns {"publicKey":
+ *                   "<base58>"} and `POST <url>/sign` with {"message":
+ *                   "<base64>"} returns {"signature": "<base64>"}, with
+ *                   SIGNER_TOKEN, if set, as a bearer token
+ *   ledger[:<path>] the Solana app on a Ledger over USB, at derivation
+ *                   path <path> (44'/501' by default, as for the Solana
+ *                   CLI's usb://ledger); needs @ledgerhq/hw-app-solana
+ *                   and @ledgerhq/hw-transport-node-hid.  It signs
+ *                   transactions only, each approved on the device, so
+ *                   it can't log in to a TEE.
+ *
+ * Signatures from KMS, remote and Ledger signers are checked against the public
+ * key before use.  `SignerWallet` signs transactions with a `Signer`
+ * wherever an Anchor wallet is expected.
+ */
+
+import {
+  Keypair,
+  PublicKey,
+  Transaction,
+  VersionedTransaction,
+} from "@solana/web3.js";
+import { createPrivateKey, createPublicKey, sign, verify } from "crypto";
+import { readFileSync } from "fs";
+
+export interface Signer {
+  publicKey: PublicKey;
+  /** Ed25519 signature of `message` */
+  signMessage(message: Uint8Array): Promise<Uint8Array>;
+}
+
+/** PKCS#8 DER prefix of a raw Ed25519 private key seed */
+const ED25519_PKCS8_PREFIX = Buffer.from(
+  "302e020100300506032b657004220420",
+  "hex"
+);
+/** SubjectPublicKeyInfo DER prefix of a raw Ed25519 public key */
+const ED25519_SPKI_PREFIX = Buffer.from("302a300506032b6570032100", "hex");
+
+export class KeypairSigner implements Signer {
+  readonly publicKey: PublicKey;
+
+  constructor(private keypair: Keypair) {
+    this.publicKey = keypair.publicKey;
+  }
+
+  static fromFile(path: string): KeypairSigner {
+    const secret = JSON.parse(readFileSync(path, "utf-8"));
+    return new KeypairSigner(Keypair.fromSecretKey(Uint8Array.from(secret)));
+  }
+
+  async signMessage(message: Uint8Array): Promise<Uint8Array> {
+    const key = createPrivateKey({
+      key: Buffer.concat([
+        ED25519_PKCS8_PREFIX,
+        this.keypair.secretKey.slice(0, 32),
+      ]),
+      format: "der",
+      type: "pkcs8",
+    });
+    return sign(null, message, key);
+  }
+}
+
+/** Fails unless `signature` is `publicKey`'s signature of `message`. */
+function checkSignature(
+  publicKey: PublicKey,
+  message: Uint8Array,
+  signature: Uint8Array
+): Uint8Array {
+  const key = createPublicKey({
+    key: Buffer.concat([ED25519_SPKI_PREFIX, publicKey.toBuffer()]),
+    format: "der",
+    type: "spki",
+  });
+  if (signature.length !== 64 || !verify(null, message, key, signature)) {
+    throw new Error(`Signer returned an invalid signature for ${publicKey}`);
+  }
+  return signature;
+}
+
+function optional(name: string): any {
+  try {
+    return require(name);
+  } catch {
+    throw new Error(`This signer needs ${name}: npm install ${name}`);
+  }
+}
+
+export class KmsSigner implements Signer {
+  private constructor(
+    private kms: any,
+    private client: any,
+    private keyId: string,
+    readonly publicKey: PublicKey
+  ) {}
+
+  static async open(keyId: string): Promise<KmsSigner> {
+    const kms = optional("@aws-sdk/client-kms");
+    const client = new kms.KMSClient({});
+    const key = await client.send(
+      new kms.GetPublicKeyCommand({ KeyId: keyId })
+    );
+    if (key.KeySpec !== "ECC_NIST_EDWARDS25519") {
+      throw new Error(`KMS key ${keyId} is ${key.KeySpec}, not Ed25519`);
+    }
+    // The raw key ends the SubjectPublicKeyInfo.
+    const raw = Buffer.from(key.PublicKey).subarray(-32);
+    return new KmsSigner(kms, client, keyId, new PublicKey(raw));
+  }
+
+  async signMessage(message: Uint8Array): Promise<Uint8Array> {
+    const { Signature } = await this.client.send(
+      new this.kms.SignCommand({
+        KeyId: this.keyId,
+        Message: message,
+        MessageType: "RAW",
+        SigningAlgorithm: "ED25519_SHA_512",
+      })
+    );
+    return checkSignature(this.publicKey, message, Signature);
+  }
+}
+
+export class RemoteSigner implements Signer {
+  private constructor(private url: string, readonly publicKey: PublicKey) {}
+
+  private static headers(): Record<string, string> {
+    const token = process.env.SIGNER_TOKEN;
+    return {
+      "Content-Type": "application/json",
+      ...(token ? { Authorization: `Bearer ${token}` } : {}),
+    };
+  }
+
+  static async open(url: string): Promise<RemoteSigner> {
+    const base = url.replace(/\/+$/, "");
+    const response = await fetch(`${base}/public-key`, {
+      headers: RemoteSigner.headers(),
+    });
+    const body = await response.json();
+    if (!response.ok || typeof body.publicKey !== "string") {
+      throw new Error(`Remote signer: public key: HTTP ${response.status}`);
+    }
+    return new RemoteSigner(base, new PublicKey(body.publicKey));
+  }
+
+  async signMessage(message: Uint8Array): Promise<Uint8Array> {
+    const response = await fetch(`${this.url}/sign`, {
+      method: "POST",
+      headers: RemoteSigner.headers(),
+      body: JSON.stringify({
+        message: Buffer.from(message).toString("base64"),
+      }),
+    });
+    const body = await response.json();
+    if (!response.ok || typeof body.signature !== "string") {
+      throw new Error(
+        `Remote signer: sign: ${body.error ?? `HTTP ${response.status}`}`
+      );
+    }
+    const signature = Buffer.from(body.signature, "base64");
+    return checkSignature(this.publicKey, message, signature);
+  }
+}
+
+export class LedgerSigner implements Signer {
+  private constructor(
+    private app: any,
+    private path: string,
+    readonly publicKey: PublicKey
+  ) {}
+
+  static async open(path = "44'/501'"): Promise<LedgerSigner> {
+    const Transport = optional("@ledgerhq/hw-transport-node-hid").default;
+    const Solana = optional("@ledgerhq/hw-app-solana").default;
+    const app = new Solana(await Transport.create());
+    const { address } = await app.getAddress(path);
+    return new LedgerSigner(app, path, new PublicKey(address));
+  }
+
+  /** Sign a transaction message once it is approved on the device. */
+  async signMessage(message: Uint8Array): Promise<Uint8Array> {
+    const { signature } = await this.app.signTransaction(
+      this.path,
+      Buffer.from(message)
+    );
+    return checkSignature(this.publicKey, message, signature);
+  }
+}
+
+/** The signer a spec (see above) names. */
+export async function loadSigner(spec: string): Promise<Signer> {
+  if (spec === "ledger") return LedgerSigner.open();
+  const colon = spec.indexOf(":");
+  const scheme = colon > 0 ? spec.slice(0, colon) : "";
+  const target = spec.slice(colon + 1);
+  switch (scheme) {
+    case "ledger":
+      return LedgerSigner.open(target);
+    case "kms":
+      return KmsSigner.open(target);
+    case "remote":
+      return RemoteSigner.open(target);
+    case "keypair":
+      return KeypairSigner.fromFile(target);
+    default:
+      return KeypairSigner.fromFile(spec);
+  }
+}
+
+/** The signer in `spec`, or ANCHOR_WALLET's keypair without one. */
+export async function defaultSigner(spec?: string): Promise<Signer> {
+  if (spec) return loadSigner(spec);
+  const wallet = process.env.ANCHOR_WALLET;
+  if (!wallet) throw new Error("Set ANCHOR_WALLET or configure a signer");
+  return KeypairSigner.fromFile(wallet);
+}
+
+/** Anchor wallet, and `Signer`, signing through `signer`. */
+export class SignerWallet implements Signer {
+  constructor(readonly signer: Signer) {}
+
+  get publicKey(): PublicKey {
+    return this.signer.publicKey;
+  }
+
+  signMessage(message: Uint8Array): Promise<Uint8Array> {
+    return this.signer.signMessage(message);
+  }
+
+  async signTransaction<T extends Transaction | VersionedTransaction>(
+    tx: T
+  ): Promise<T> {
+    if (tx instanceof VersionedTransaction) {
+      tx.addSignature(
+        this.publicKey,
+        await this.signMessage(tx.message.serialize())
+      );
+    } else {
+      const signature = await this.signMessage(tx.serializeMessage());
+      tx.addSignature(this.publicKey, Buffer.from(signature));
+    }
+    return tx;
+  }
+
+  async signAllTransactions<T extends Transaction | VersionedTransaction>(
+    txs: T[]
+  ): Promise<T[]> {
+    for (const tx of txs) await this.signTransaction(tx);
+    return txs;
+  }
+}
@@ -0,0 +1,256 @@
+/**
+ * In-memory set of program accounts for the keeper, with cold start from
+ * a snapshot instead of rescanning or replaying history.
+ *
+ * `load` fetches every account of the tracked types with
+ * getProgramAccounts, one type at a time, paged through
+ * getProgramAccountsV2 where the RPC provides it.  `write` saves the set
+ * as a snapshot file and `read` starts from one: it is then brought up to
+ * date with only the accounts changed since its slot where the RPC can
+ * filter on that, or refreshed in full in the background otherwise.
+ * `subscribe` keeps the set current from program account notifications.
+ */
+
+import * as anchor from "@coral-xyz/anchor";
+import {
+  AccountInfo,
+  Connection,
+  GetProgramAccountsFilter,
+  PublicKey,
+} from "@solana/web3.js";
+import { readFileSync, renameSync, writeFileSync } from "fs";
+
+export const SNAPSHOT_VERSION = 1;
+const PAGE_SIZE = 5_000;
+
+/** Accounts of IDL type `type` owned by `owner`. */
+export interface Source {
+  owner: PublicKey;
+  type: string;
+  discriminator: number[];
+}
+
+export interface TrackedAccount {
+  pubkey: PublicKey;
+  type: string;
+  data: Buffer;
+}
+
+interface Page {
+  accounts: { pubkey: string; data: string }[];
+  /** Key of the next page; null on the last one */
+  next: string | null;
+}
+
+interface SnapshotFile {
+  version: number;
+  programId: string;
+  /** Slot the accounts were read at */
+  slot: number;
+  accounts: { pubkey: string; owner: string; type: string; data: string }[];
+}
+
+function filter(source: Source): GetProgramAccountsFilter {
+  return {
+    memcmp: {
+      offset: 0,
+      bytes: anchor.utils.bytes.bs58.encode(source.discriminator),
+    },
+  };
+}
+
+/** One getProgramAccountsV2 call; null if the RPC doesn't have it. */
+async function pageV2(
+  connection: Connection,
+  source: Source,
+  paginationKey: string | null,
+  changedSinceSlot: number | null
+): Promise<Page | null> {
+  const response = await fetch(connection.rpcEndpoint, {
+    method: "POST",
+    headers: { "Content-Type": "application/json" },
+    body: JSON.stringify({
+      jsonrpc: "2.0",
+      id: 1,
+      method: "getProgramAccountsV2",
+      params: [
+        source.owner.toBase58(),
+        {
+          encoding: "base64",
+          commitment: "confirmed",
+          filters: [filter(source)],
+          limit: PAGE_SIZE,
+          ...(paginationKey ? { paginationKey } : {}),
+          ...(changedSinceSlot !== null ? { changedSinceSlot } : {}),
+        },
+      ],
+    }),
+  });
+  if (response.status === 429) {
+    throw new Error("getProgramAccountsV2: HTTP 429 Too Many Requests");
+  }
+  const body = await response.json();
+  if (body.error) {
+    if (body.error.code === -32601) return null;
+    throw new Error(`getProgramAccountsV2: ${body.error.message}`);
+  }
+  return {
+    accounts: body.result.accounts.map((a: any) => ({
+      pubkey: a.pubkey,
+      data: a.account.data[0],
+    })),
+    next: body.result.paginationKey ?? null,
+  };
+}
+
+export class AccountSet {
+  private accounts = new Map<string, TrackedAccount>();
+  /** Slot the set is known to be current at */
+  slot = 0;
+
+  constructor(private programId: PublicKey, private sources: Source[]) {}
+
+  get size(): number {
+    return this.accounts.size;
+  }
+
+  /** Tracked accounts of IDL type `type`. */
+  of(type: string): TrackedAccount[] {
+    return [...this.accounts.values()].filter((a) => a.type === type);
+  }
+
+  /** Drop an account known to be closed. */
+  forget(pubkey: PublicKey) {
+    this.accounts.delete(pubkey.toBase58());
+  }
+
+  private apply(source: Source, pubkey: PublicKey, data: Buffer | null) {
+    if (data === null) {
+      this.accounts.delete(pubkey.toBase58());
+    } else {
+      this.accounts.set(pubkey.toBase58(), { pubkey, type: source.type, data });
+    }
+  }
+
+  private async fetch(
+    connection: Connection,
+    source: Source,
+    changedSinceSlot: number | null
+  ): Promise<boolean> {
+    let paginationKey: string | null = null;
+    do {
+      const page = await pageV2(
+        connection,
+        source,
+        paginationKey,
+        changedSinceSlot
+      );
+      if (page === null) {
+        if (changedSinceSlot !== null) return false;
+        const all = await connection.getProgramAccounts(source.owner, {
+          filters: [filter(source)],
+        });
+        for (const { pubkey, account } of all) {
+          this.apply(source, pubkey, account.data);
+        }
+        return true;
+      }
+      for (const { pubkey, data } of page.accounts) {
+        this.apply(source, new PublicKey(pubkey), Buffer.from(data, "base64"));
+      }
+      paginationKey = page.next;
+    } while (paginationKey);
+    return true;
+  }
+
+  /** Replace the set with every account of the tracked types. */
+  async load(connection: Connection) {
+    const slot = await connection.getSlot("confirmed");
+    const previous = this.accounts;
+    this.accounts = new Map();
+    try {
+      for (const source of this.sources) {
+        await this.fetch(connection, source, null);
+      }
+    } catch (err) {
+      this.accounts = previous;
+      throw err;
+    }
+    this.slot = slot;
+  }
+
+  /**
+   * Fetch the accounts changed since the set's slot.  Returns false if
+   * the RPC can't filter on that; the set then needs a full `load`.
+   * Closed accounts are only dropped by `subscribe` or a full load.
+   */
+  async catchUp(connection: Connection): Promise<boolean> {
+    const slot = await connection.getSlot("confirmed");
+    for (const source of this.sources) {
+      if (!(await this.fetch(connection, source, this.slot))) return false;
+    }
+    this.slot = slot;
+    return true;
+  }
+
+  /** Follow changes to tracked accounts; returns the subscription IDs. */
+  subscribe(connection: Connection): number[] {
+    return this.sources.map((source) =>
+      connection.onProgramAccountChange(
+        source.owner,
+        ({ accountId, accountInfo }, { slot }) => {
+          const open = (info: AccountInfo<Buffer>) =>
+            info.lamports > 0 && info.owner.equals(source.owner);
+          this.apply(
+            source,
+            accountId,
+            open(accountInfo) ? accountInfo.data : null
+          );
+          this.slot = Math.max(this.slot, slot);
+        },
+        { commitment: "confirmed", filters: [filter(source)] }
+      )
+    );
+  }
+
+  /** Save the set as a snapshot file (atomically). */
+  write(path: string) {
+    const sources = new Map(this.sources.map((s) => [s.type, s]));
+    const snapshot: SnapshotFile = {
+      version: SNAPSHOT_VERSION,
+      programId: this.programId.toBase58(),
+      slot: this.slot,
+      accounts: [...this.accounts.values()].map(({ pubkey, type, data }) => ({
+        pubkey: pubkey.toBase58(),
+        owner: sources.get(type)!.owner.toBase58(),
+        type,
+        data: data.toString("base64"),
+      })),
+    };
+    writeFileSync(`${path}.tmp`, JSON.stringify(snapshot));
+    renameSync(`${path}.tmp`, path);
+  }
+
+  /** Replace the set with a snapshot file's accounts. */
+  read(path: string) {
+    const snapshot: SnapshotFile = JSON.parse(readFileSync(path, "utf-8"));
+    if (snapshot.version !== SNAPSHOT_VERSION) {
+      throw new Error(`Unsupported snapshot version ${snapshot.version}`);
+    }
+    if (snapshot.programId !== this.programId.toBase58()) {
+      throw new Error(`Snapshot is for program ${snapshot.programId}`);
+    }
+    const sources = new Map(this.sources.map((s) => [s.type, s]));
+    this.accounts = new Map();
+    for (const account of snapshot.accounts) {
+      const source = sources.get(account.type);
+      if (!source || source.owner.toBase58() !== account.owner) continue;
+      this.apply(
+        source,
+        new PublicKey(account.pubkey),
+        Buffer.from(account.data, "base64")
+      );
+    }
+    this.slot = snapshot.slot;
+  }
+}
@@ -0,0 +1,111 @@
+/**
+ * Opt-in usage telemetry for the daemon, with differential privacy.
+ *
+ * Each counter is the number of distinct vaults (or, where no vault is
+ * involved, tenants) that used a feature in a reporting period, so one
+ * vault changes a counter by at most 1.  At the end of a period each
+ * counter gets Laplace noise of scale `counters / epsilon` and the noisy
+ * counts are POSTed to the endpoint, which spends at most `epsilon` of
+ * privacy budget per period.  The period's true counts are then
+ * discarded; they are never logged or sent.
+ *
+ * Vaults are only held, for the period, as HMACs under a key drawn at
+ * its start, and reports carry no vault, tenant, amount or instance
+ * identity.  Off unless `[telemetry]` sets `enabled = true`.
+ */
+
+import { PublicKey } from "@solana/web3.js";
+import { createHmac, randomBytes } from "crypto";
+import { logger } from "./log";
+
+const log = logger("telemetry");
+
+export const FEATURES = [
+  "payments",
+  "private_vault_payments",
+  "payment_replays",
+  "fee_quotes",
+  "webhook_events",
+] as const;
+
+export type Feature = (typeof FEATURES)[number];
+
+export const TELEMETRY_VERSION = 1;
+
+const REPORT_TIMEOUT_MS = 10_000;
+
+export interface TelemetryConfig {
+  endpoint: string;
+  /** Privacy budget spent per period, across all counters */
+  epsilon: number;
+  /** Seconds per reporting period */
+  period: number;
+}
+
+/** A Laplace(0, scale) sample. */
+export function laplace(scale: number): number {
+  // Uniform in (-0.5, 0.5) from 53 random bits, never exactly -0.5
+  const bits = randomBytes(8).readBigUInt64BE() >> BigInt(11);
+  const u = (Number(bits) + 0.5) / 2 ** 53 - 0.5;
+  return -scale * Math.sign(u) * Math.log(1 - 2 * Math.abs(u));
+}
+
+export class Telemetry {
+  private key = randomBytes(32);
+  private seen = new Map<Feature, Set<string>>();
+  private started = Math.floor(Date.now() / 1000);
+
+  constructor(private config: TelemetryConfig) {
+    setInterval(() => this.report(), config.period * 1000).unref();
+  }
+
+  /**
+   * `unit` used `feature` in this period: a vault, or a string naming
+   * what stands in for one (e.g. "tenant:<name>").
+   */
+  record(feature: Feature, unit: PublicKey | string) {
+    const id = typeof unit === "string" ? unit : unit.toBase58();
+    let units = this.seen.get(feature);
+    if (!units) {
+      units = new Set();
+      this.seen.set(feature, units);
+    }
+    units.add(createHmac("sha256", this.key).update(id).digest("hex"));
+  }
+
+  private async report() {
+    const scale = FEATURES.length / this.config.epsilon;
+    const counters = Object.fromEntries(
+      FEATURES.map((feature) => {
+        const count = this.seen.get(feature)?.size ?? 0;
+        return [feature, Math.max(0, Math.round(count + laplace(scale)))];
+      })
+    );
+    const body = JSON.stringify({
+      version: TELEMETRY_VERSION,
+      periodStart: this.started,
+      period: this.config.period,
+      epsilon: this.config.epsilon,
+      counters,
+    });
+    this.key = randomBytes(32);
+    this.seen.clear();
+    this.started = Math.floor(Date.now() / 1000);
+
+    // Not retried: a lost report is only a gap in the series.
+    try {
+      const response = await fetch(this.config.endpoint, {
+        method: "POST",
+        headers: { "Content-Type": "application/json" },
+        body,
+        signal: AbortSignal.timeout(REPORT_TIMEOUT_MS),
+      });
+      if (!response.ok) throw new Error(`HTTP ${response.status}`);
+      log.debug("Sent telemetry report");
+    } catch (err) {
+      log.warn("Telemetry report failed", {
+        error: err instanceof Error ? err.message : String(err),
+      });
+    }
+  }
+}
@@ -0,0 +1,198 @@
+/**
+ * Show what a delegated vault's ER state has that L1 does not yet.
+ *
+ *   npm run vault-diff -- --vault-id <id> --er-url <url> [--json]
+ *
+ * Reads the vault from L1 (its last commit) and from the ER (through an
+ * auth token on the TEE for a private vault), decodes both with the IDL
+ * and prints every field that differs, with its IDL type, down to nested
+ * struct fields and array elements.  --json prints the differences as a
+ * JSON array instead.
+ *
+ * Uses ANCHOR_PROVIDER_URL / ANCHOR_WALLET like `anchor test` (the wallet
+ * must own the vault or hold a read permission on it for a private
+ * vault), PROGRAM_ID for the deployed program, and the IDL from
+ * `anchor build`.
+ */
+
+import * as anchor from "@coral-xyz/anchor";
+import { BN, Program } from "@coral-xyz/anchor";
+import { PublicKey } from "@solana/web3.js";
+import * as dotenv from "dotenv";
+import {
+  DELEGATION_PROGRAM,
+  erConnection,
+  plain,
+  vaultAddress,
+} from "./common";
+
+dotenv.config();
+
+// Generated by `anchor build`
+const idl = require("../target/idl/obscura_per.json");
+
+const USAGE =
+  "Usage: npm run vault-diff -- --vault-id <id> --er-url <url> [--json]";
+
+interface Options {
+  vaultId: BN | null;
+  erUrl: string | null;
+  json: boolean;
+}
+
+interface Change {
+  path: string;
+  type: string;
+  l1: unknown;
+  er: unknown;
+}
+
+function parseArgs(argv: string[]): Options {
+  const options: Options = { vaultId: null, erUrl: null, json: false };
+  for (let i = 0; i < argv.length; i++) {
+    switch (argv[i]) {
+      case "--vault-id":
+        options.vaultId = new BN(argv[++i]);
+        break;
+      case "--er-url":
+        options.erUrl = argv[++i].replace(/\/$/, "");
+        break;
+      case "--json":
+        options.json = true;
+        break;
+      default:
+        throw new Error(`Unknown option ${argv[i]}\n${USAGE}`);
+    }
+  }
+  if (options.vaultId === null || options.erUrl === null) {
+    throw new Error(USAGE);
+  }
+  return options;
+}
+
+/** Rust-style name of an IDL type, e.g. `Option<u64>` or `[u8; 32]`. */
+function typeName(type: any): string {
+  if (typeof type === "string") return type;
+  if (type.defined) return type.defined.name;
+  if (type.option) return `Option<${typeName(type.option)}>`;
+  if (type.vec) return `Vec<${typeName(type.vec)}>`;
+  if (type.array) return `[${typeName(type.array[0])}; ${type.array[1]}]`;
+  return JSON.stringify(type);
+}
+
+/** Collect the differences between two decoded values of IDL `type`. */
+function diff(type: any, l1: any, er: any, path: string, out: Change[]) {
+  const leaf = () => {
+    if (JSON.stringify(l1) !== JSON.stringify(er)) {
+      out.push({ path, type: typeName(type), l1, er });
+    }
+  };
+
+  if (typeof type === "object" && type.defined) {
+    const defined = idl.types.find((t: any) => t.name === type.defined.name);
+    if (defined?.type.kind === "struct" && defined.type.fields?.[0]?.name) {
+      for (const field of defined.type.fields) {
+        const name = field.name.replace(/_(\w)/g, (_: string, c: string) =>
+          c.toUpperCase()
+        );
+        diff(field.type, l1[name], er[name], `${path}.${name}`, out);
+      }
+      return;
+    }
+    return leaf();
+  }
+  if (typeof type === "object" && (type.array || type.vec)) {
+    const inner = type.array ? type.array[0] : type.vec;
+    if (l1.length !== er.length || typeof inner === "string") {
+      return leaf();
+    }
+    for (let i = 0; i < l1.length; i++) {
+      diff(inner, l1[i], er[i], `${path}[${i}]`, out);
+    }
+    return;
+  }
+  if (typeof type === "object" && type.option && l1 !== null && er !== null) {
+    return diff(type.option, l1, er, path, out);
+  }
+  leaf();
+}
+
+function show(value: unknown): string {
+  return typeof value === "string" ? value : JSON.stringify(value);
+}
+
+async function main() {
+  const options = parseArgs(process.argv.slice(2));
+  const provider = anchor.AnchorProvider.env();
+  const connection = provider.connection;
+  const programId = new PublicKey(process.env.PROGRAM_ID || idl.address);
+  const program = new Program(
+    { ...idl, address: programId.toBase58() },
+    provider
+  );
+
+  const vaultId = options.vaultId!;
+  const vault = vaultAddress(vaultId, programId);
+  const l1Info = await connection.getAccountInfo(vault);
+  if (!l1Info) {
+    throw new Error(`Vault ${vaultId.toString()} does not exist`);
+  }
+  if (!l1Info.owner.equals(DELEGATION_PROGRAM)) {
+    console.log(`Vault ${vaultId.toString()} is not delegated; L1 is current`);
+    return;
+  }
+
+  const l1: any = plain(
+    program.coder.accounts.decode("VaultState", l1Info.data)
+  );
+  const er = await erConnection(
+    options.erUrl!,
+    l1.isPrivate,
+    provider.wallet as anchor.Wallet
+  );
+  const erInfo = await er.getAccountInfo(vault);
+  if (!erInfo) {
+    throw new Error(
+      `Vault ${vaultId.toString()} is not readable on the ER by ` +
+        provider.wallet.publicKey.toBase58()
+    );
+  }
+
+  const changes: Change[] = [];
+  if (l1Info.lamports !== erInfo.lamports) {
+    changes.push({
+      path: "lamports",
+      type: "u64",
+      l1: l1Info.lamports,
+      er: erInfo.lamports,
+    });
+  }
+  const decoded = plain(
+    program.coder.accounts.decode("VaultState", erInfo.data)
+  );
+  diff({ defined: { name: "VaultState" } }, l1, decoded, "", changes);
+  for (const change of changes) {
+    change.path = change.path.replace(/^\./, "");
+  }
+
+  if (options.json) {
+    console.log(JSON.stringify(changes, null, 2));
+    return;
+  }
+  if (changes.length === 0) {
+    console.log(`Vault ${vaultId.toString()}: ER and L1 agree`);
+    return;
+  }
+  console.log(
+    `Vault ${vaultId.toString()}: ${changes.length} uncommitted ` +
+      `field${changes.length === 1 ? "" : "s"}`
+  );
+  for (const { path, type, l1: before, er: after } of changes) {
+    console.log(`  ${path} (${type}): ${show(before)} -> ${show(after)}`);
+  }
+}
+
+main().catch((err) => {
+  console.error(err.message || err);
+  process.exit(1);
+});
@@ -0,0 +1,214 @@
+/**
+ * Webhooks from the daemon on vault events.
+ *
+ * The daemon polls each vault a webhook watches and passes its readings
+ * to `observe`, and subscribes to new IntentReceipts paid into it for
+ * `invoicePaid`.  From those come:
+ *
+ *   PaymentReceived     the vault's balance went up (net of anything paid
+ *                       out between two polls)
+ *   InvoicePaid         a payment intent was fulfilled into the vault
+ *   LowBalance          the balance fell below the webhook's low_balance
+ *   ForcedUndelegation  the vault left its ER without its owner
+ *                       undelegating it (a keeper sweep)
+ *
+ * Each event is POSTed as JSON to the webhooks subscribed to it, signed
+ * with `Obscura-Signature: t=<unix time>,v1=<hex HMAC-SHA256 of
+ * "<t>.<body>" under the webhook's secret>`, and retried with backoff
+ * until the endpoint answers 2xx.  Events are not persisted: the first
+ * reading of a vault after a start only sets its baseline, so changes
+ * while the daemon is down are not reported.  Deliveries are logged
+ * with the event ID as their correlation ID.
+ */
+
+import { BN } from "@coral-xyz/anchor";
+import { createHmac, randomUUID } from "crypto";
+import { logger, withCorrelation } from "./log";
+
+const log = logger("webhooks");
+
+export const EVENT_TYPES = [
+  "PaymentReceived",
+  "InvoicePaid",
+  "LowBalance",
+  "ForcedUndelegation",
+] as const;
+
+export type EventType = (typeof EVENT_TYPES)[number];
+
+const DELIVERY_TIMEOUT_MS = 10_000;
+const MAX_ATTEMPTS = 10;
+const BASE_DELAY_MS = 1_000;
+const MAX_DELAY_MS = 10 * 60_000;
+
+export interface Webhook {
+  url: string;
+  /** HMAC key the receiver verifies deliveries with */
+  secret: string;
+  /** Tenant whose vaults the webhook watches */
+  tenant: string;
+  /** Vault IDs watched */
+  vaults: Set<string>;
+  events: Set<EventType>;
+  /** LowBalance threshold in lamports (null = no LowBalance events) */
+  lowBalance: BN | null;
+}
+
+export interface VaultEvent {
+  /** Unique per event and webhook; the same across retries */
+  id: string;
+  type: EventType;
+  vaultId: string;
+  /** Unix timestamp (s) the event was seen */
+  createdAt: number;
+  data: Record<string, unknown>;
+}
+
+/** What the events are derived from, read by the daemon. */
+export interface VaultReading {
+  delegated: boolean;
+  balance: BN;
+  lastActivity: BN;
+}
+
+/** The `v1` value of `Obscura-Signature` for `body` sent at `timestamp`. */
+export function signature(
+  secret: string,
+  timestamp: number,
+  body: string
+): string {
+  return createHmac("sha256", secret)
+    .update(`${timestamp}.${body}`)
+    .digest("hex");
+}
+
+const sleep = (ms: number) => new Promise((resolve) => setTimeout(resolve, ms));
+
+export class Webhooks {
+  private readings = new Map<string, VaultReading>();
+
+  /** `onEvent` is told of each event emitted, e.g. for telemetry. */
+  constructor(
+    private hooks: Webhook[],
+    private onEvent?: (tenant: string, vaultId: string) => void
+  ) {}
+
+  /** Each watched (tenant, vault ID) pair, once. */
+  get watched(): { tenant: string; vaultId: string }[] {
+    const pairs = new Map<string, { tenant: string; vaultId: string }>();
+    for (const hook of this.hooks) {
+      for (const vaultId of hook.vaults) {
+        pairs.set(`${hook.tenant}/${vaultId}`, {
+          tenant: hook.tenant,
+          vaultId,
+        });
+      }
+    }
+    return [...pairs.values()];
+  }
+
+  /** Compare a fresh reading of a vault with the last one. */
+  observe(tenant: string, vaultId: string, reading: VaultReading) {
+    const key = `${tenant}/${vaultId}`;
+    const before = this.readings.get(key);
+    this.readings.set(key, reading);
+    if (!before) return;
+
+    for (const hook of this.subscribed(tenant, vaultId)) {
+      if (reading.balance.gt(before.balance)) {
+        this.emit(hook, "PaymentReceived", vaultId, {
+          amount: reading.balance.sub(before.balance).toString(),
+          balance: reading.balance.toString(),
+        });
+      }
+      const threshold = hook.lowBalance;
+      if (
+        threshold &&
+        before.balance.gte(threshold) &&
+        reading.balance.lt(threshold)
+      ) {
+        this.emit(hook, "LowBalance", vaultId, {
+          balance: reading.balance.toString(),
+          threshold: threshold.toString(),
+        });
+      }
+      // The owner's undelegate_vault records activity; a sweep doesn't.
+      if (
+        before.delegated &&
+        !reading.delegated &&
+        reading.lastActivity.eq(before.lastActivity)
+      ) {
+        this.emit(hook, "ForcedUndelegation", vaultId, {
+          lastActivity: reading.lastActivity.toString(),
+        });
+      }
+    }
+  }
+
+  /** A payment intent was fulfilled into the vault. */
+  invoicePaid(tenant: string, vaultId: string, data: Record<string, unknown>) {
+    for (const hook of this.subscribed(tenant, vaultId)) {
+      this.emit(hook, "InvoicePaid", vaultId, data);
+    }
+  }
+
+  private subscribed(tenant: string, vaultId: string): Webhook[] {
+    return this.hooks.filter(
+      (hook) => hook.tenant === tenant && hook.vaults.has(vaultId)
+    );
+  }
+
+  private emit(
+    hook: Webhook,
+    type: EventType,
+    vaultId: string,
+    data: Record<string, unknown>
+  ) {
+    if (!hook.events.has(type)) return;
+    this.onEvent?.(hook.tenant, vaultId);
+    const event: VaultEvent = {
+      id: randomUUID(),
+      type,
+      vaultId,
+      createdAt: Math.floor(Date.now() / 1000),
+      data,
+    };
+    withCorrelation(() => this.deliver(hook, event), event.id);
+  }
+
+  private async deliver(hook: Webhook, event: VaultEvent) {
+    const body = JSON.stringify(event);
+    for (let attempt = 1; ; attempt++) {
+      const timestamp = Math.floor(Date.now() / 1000);
+      const v1 = signature(hook.secret, timestamp, body);
+      try {
+        const response = await fetch(hook.url, {
+          method: "POST",
+          headers: {
+            "Content-Type": "application/json",
+            "Obscura-Event-Id": event.id,
+            "Obscura-Signature": `t=${timestamp},v1=${v1}`,
+          },
+          body,
+          signal: AbortSignal.timeout(DELIVERY_TIMEOUT_MS),
+        });
+        if (response.ok) {
+          log.info("Delivered webhook", { type: event.type, url: hook.url });
+          return;
+        }
+        throw new Error(`HTTP ${response.status}`);
+      } catch (err) {
+        const reason = err instanceof Error ? err.message : String(err);
+        if (attempt >= MAX_ATTEMPTS) {
+          log.error("Webhook delivery failed, dropping the event", {
+            type: event.type,
+            url: hook.url,
+            error: reason,
+          });
+          return;
+        }
+        await sleep(Math.min(MAX_DELAY_MS, BASE_DELAY_MS * 2 ** (attempt - 1)));
+      }
+    }
+  }
+}
@@ -0,0 +1,88 @@
+//! MagicBlock Access Control program CPI.
+//!
+//! The TEE validator enforces PER visibility from the Access Control
+//! program's permission accounts, not from our `PermissionState`.  Every
+//! read-scoped grant is therefore mirrored there: one ACL permission per
+//! (vault, member), created and closed with the vault PDA as signer.
+//! `PermissionState` stays the source of truth for scopes, expiry and
+//! suspension; the ACL entry exists exactly while read access is active.
+
+use anchor_lang::prelude::*;
+use anchor_lang::solana_program::hash::hash;
+use anchor_lang::solana_program::instruction::{AccountMeta, Instruction};
+use anchor_lang::solana_program::program::invoke_signed;
+
+use crate::ObscuraError;
+
+/// Access Control program id (`ACCESS_CONTROL_PROGRAM`)
+pub const ID: Pubkey = pubkey!("ACLseoPoyC3cBqoUtkbjZ4aDrkurZW86v19pXz2XQnp1");
+
+/// ACL permission PDA seed prefix
+pub const PERMISSION_SEED: &[u8] = b"permission";
+
+/// ACL permission account granting `member` read access to `vault`.
+pub fn permission_address(vault: &Pubkey, member: &Pubkey) -> Pubkey {
+    Pubkey::find_program_address(&[PERMISSION_SEED, vault.as_ref(), member.as_ref()], &ID).0
+}
+
+/// Accounts shared by the ACL CPIs.
+pub struct AclAccounts<'a, 'info> {
+    /// The Access Control program
+    pub program: &'a AccountInfo<'info>,
+    /// ACL permission PDA for (vault, member)
+    pub permission: &'a AccountInfo<'info>,
+    /// The vault PDA, signing as the permission's delegated account
+    pub vault: &'a AccountInfo<'info>,
+    /// Pays rent on grant, receives it back on revoke
+    pub payer: &'a AccountInfo<'info>,
+    /// System program
+    pub system_program: &'a AccountInfo<'info>,
+}
+
+/// Grant `member` read access to the vault in the ACL program.
+pub fn grant(accounts: &AclAccounts, member: Pubkey, vault_seeds: &[&[&[u8]]]) -> Result<()> {
+    invoke(accounts, "create_permission", member, vault_seeds)
+}
+
+/// Revoke `member`'s read access, closing the ACL permission.
+pub fn revoke(accounts: &AclAccounts, member: Pubkey, vault_seeds: &[&[&[u8]]]) -> Result<()> {
+    invoke(accounts, "close_permission", member, vault_seeds)
+}
+
+fn invoke(
+    accounts: &AclAccounts,
+    instruction: &str,
+    member: Pubkey,
+    vault_seeds: &[&[&[u8]]],
+) -> Result<()> {
+    require_keys_eq!(
+        accounts.permission.key(),
+        permission_address(accounts.vault.key, &member),
+        ObscuraError::InvalidAclPermission
+    );
+
+    let mut data = hash(format!("global:{}", instruction).as_bytes()).to_bytes()[..8].to_vec();
+    data.extend_from_slice(member.as_ref());
+
+    invoke_signed(
+        &Instruction {
+            program_id: ID,
+            accounts: vec![
+                AccountMeta::new(accounts.permission.key(), false),
+                AccountMeta::new_readonly(accounts.vault.key(), true),
+                AccountMeta::new(accounts.payer.key(), accounts.payer.is_signer),
+                AccountMeta::new_readonly(accounts.system_program.key(), false),
+            ],
+            data,
+        },
+        &[
+            accounts.permission.clone(),
+            accounts.vault.clone(),
+            accounts.payer.clone(),
+            accounts.system_program.clone(),
+            accounts.program.clone(),
+        ],
+        vault_seeds,
+    )?;
+    Ok(())
+}
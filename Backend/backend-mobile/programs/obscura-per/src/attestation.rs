@@ -0,0 +1,87 @@
+//! Counterparty rating attestations.
+//!
+//! After an escrow (`execute_conditional_transfer`) completes with a
+//! `RatingTicket`, each party may rate the other once with
+//! `rate_counterparty`.  The rating is minted as a compressed NFT into the
+//! governance-configured `ProgramConfig::attestation_tree`, through the
+//! same Bubblegum CPI and accounts as receipts (see `receipt`), with the
+//! tree delegate PDA as its owner so it can't be moved or burned.
+//!
+//! The leaf URI is `obscura:rating:<subject>:<reference hex>:<rating>`,
+//! where `<rating>` is the score for a public rating or `s` and the hex
+//! commitment for a sealed one, and the rater is the leaf's (unverified)
+//! creator.  The reference matches the escrow's receipts, so a reader can
+//! tell which trade a rating is about.  A sealed rating only commits to
+//! SHA-256(score || salt): the rater's client reveals the opening to keys
+//! holding `SCOPE_REPUTATION` on their vault, who check it against the
+//! commitment.
+
+use anchor_lang::prelude::*;
+use mpl_bubblegum::types::{Creator, MetadataArgs, TokenProgramVersion, TokenStandard};
+
+use crate::receipt::mint_leaves;
+use crate::Rating;
+
+/// Rating NFT name.
+pub const RATING_NAME: &str = "Obscura Rating";
+
+/// Rating NFT symbol.
+pub const RATING_SYMBOL: &str = "OBSA";
+
+/// Rating URI prefix; subject, reference and rating follow.
+pub const RATING_URI_PREFIX: &str = "obscura:rating:";
+
+fn push_hex(uri: &mut String, bytes: &[u8]) {
+    for byte in bytes {
+        uri.push_str(&format!("{:02x}", byte));
+    }
+}
+
+/// Rating URI for `subject`'s `rating` in the trade `reference`.
+pub fn rating_uri(subject: &Pubkey, reference: &[u8; 32], rating: &Rating) -> String {
+    let mut uri = String::with_capacity(RATING_URI_PREFIX.len() + 176);
+    uri.push_str(RATING_URI_PREFIX);
+    uri.push_str(&subject.to_string());
+    uri.push(':');
+    push_hex(&mut uri, reference);
+    uri.push(':');
+    match rating {
+        Rating::Public { score } => uri.push_str(&score.to_string()),
+        Rating::Sealed { commitment } => {
+            uri.push('s');
+            push_hex(&mut uri, commitment);
+        }
+    }
+    uri
+}
+
+/// Mint `rater`'s `rating` of `subject` using the first
+/// `receipt::ACCOUNTS` of `accounts`, with the attestation tree as the
+/// merkle tree.
+pub fn mint_attestation<'info>(
+    accounts: &[AccountInfo<'info>],
+    rater: &Pubkey,
+    subject: &Pubkey,
+    reference: &[u8; 32],
+    rating: &Rating,
+) -> Result<()> {
+    let metadata = MetadataArgs {
+        name: RATING_NAME.to_string(),
+        symbol: RATING_SYMBOL.to_string(),
+        uri: rating_uri(subject, reference, rating),
+        seller_fee_basis_points: 0,
+        primary_sale_happened: true,
+        is_mutable: false,
+        edition_nonce: None,
+        token_standard: Some(TokenStandard::NonFungible),
+        collection: None,
+        uses: None,
+        token_program_version: TokenProgramVersion::Original,
+        creators: vec![Creator {
+            address: *rater,
+            verified: false,
+            share: 100,
+        }],
+    };
+    mint_leaves(accounts, |config| config.attestation_tree, &[], metadata)
+}
@@ -0,0 +1,45 @@
+//! Compliance hook CPI.
+//!
+//! Integrators with regulatory requirements can attach a compliance
+//! program to their `Denylist`.  Outbound transfers from vaults created
+//! through the integrator call its `check_transfer` instruction once the
+//! recipient has cleared the denylist; the program blocks a transfer by
+//! failing.  The hook only ever receives read-only, non-signer metas, so
+//! it can inspect accounts but never move anything.
+
+use anchor_lang::prelude::*;
+use anchor_lang::solana_program::hash::hash;
+use anchor_lang::solana_program::instruction::{AccountMeta, Instruction};
+use anchor_lang::solana_program::program::invoke;
+
+/// Call `program`'s `check_transfer(vault, recipient, amount)` over
+/// `accounts` (the transfer instruction's remaining accounts).
+pub fn check_transfer<'info>(
+    program: &AccountInfo<'info>,
+    accounts: &[AccountInfo<'info>],
+    vault: Pubkey,
+    recipient: Pubkey,
+    amount: u64,
+) -> Result<()> {
+    let mut data = hash(b"global:check_transfer").to_bytes()[..8].to_vec();
+    data.extend_from_slice(vault.as_ref());
+    data.extend_from_slice(recipient.as_ref());
+    data.extend_from_slice(&amount.to_le_bytes());
+
+    let metas = accounts
+        .iter()
+        .map(|account| AccountMeta::new_readonly(account.key(), false))
+        .collect();
+
+    let mut infos = accounts.to_vec();
+    infos.push(program.clone());
+    invoke(
+        &Instruction {
+            program_id: program.key(),
+            accounts: metas,
+            data,
+        },
+        &infos,
+    )?;
+    Ok(())
+}
@@ -0,0 +1,77 @@
+//! Delegation program adapter.
+//!
+//! Each validator registry entry records the MagicBlock delegation SDK
+//! version its validator speaks.  Vaults remember the version they were
+//! delegated under, so a vault delegated through an older delegation
+//! program can still be resolved to that program after the ecosystem
+//! migrates to a new one.
+
+use anchor_lang::prelude::*;
+use anchor_lang::solana_program::pubkey;
+use ephemeral_rollups_sdk::cr::commit_and_undelegate_accounts;
+use ephemeral_rollups_sdk::ephem::commit_accounts;
+
+use crate::ObscuraError;
+
+/// Delegation SDK version used for new delegations.
+pub const CURRENT_SDK_VERSION: u8 = 1;
+
+/// Delegation program for SDK version 1 (`ephemeral-rollups-sdk` 0.x).
+pub const DELEGATION_PROGRAM_V1: Pubkey = pubkey!("DELeGGvXpWV2fqJUhqcF5ZSYMS4JTLjteaAMARRSaeSh");
+
+/// Resolve the delegation program for a given SDK version.
+pub fn delegation_program_id(sdk_version: u8) -> Result<Pubkey> {
+    match sdk_version {
+        1 => Ok(DELEGATION_PROGRAM_V1),
+        _ => err!(ObscuraError::UnsupportedSdkVersion),
+    }
+}
+
+/// Whether `sdk_version` can still be used for new delegations.
+///
+/// Older versions stay resolvable through `delegation_program_id` so
+/// existing delegations can be committed and undelegated, but new
+/// delegations always go through the program the SDK CPI targets.
+pub fn accepts_new_delegations(sdk_version: u8) -> bool {
+    sdk_version == CURRENT_SDK_VERSION
+}
+
+/// Commit `accounts` to L1 through the delegation program of
+/// `sdk_version`, leaving them delegated.
+pub fn commit<'a, 'info>(
+    sdk_version: u8,
+    payer: &'a AccountInfo<'info>,
+    accounts: Vec<&'a AccountInfo<'info>>,
+    magic_context: &'a AccountInfo<'info>,
+    magic_program: &'a AccountInfo<'info>,
+) -> Result<()> {
+    match sdk_version {
+        1 => Ok(commit_accounts(
+            payer,
+            accounts,
+            magic_context,
+            magic_program,
+        )?),
+        _ => err!(ObscuraError::UnsupportedSdkVersion),
+    }
+}
+
+/// Commit `accounts` to L1 and undelegate them through the delegation
+/// program of `sdk_version`.
+pub fn commit_and_undelegate<'a, 'info>(
+    sdk_version: u8,
+    payer: &'a AccountInfo<'info>,
+    accounts: Vec<&'a AccountInfo<'info>>,
+    magic_context: &'a AccountInfo<'info>,
+    magic_program: &'a AccountInfo<'info>,
+) -> Result<()> {
+    match sdk_version {
+        1 => Ok(commit_and_undelegate_accounts(
+            payer,
+            accounts,
+            magic_context,
+            magic_program,
+        )?),
+        _ => err!(ObscuraError::UnsupportedSdkVersion),
+    }
+}
@@ -0,0 +1,100 @@
+//! DEX adapter CPI.
+//!
+//! Limit orders go through a governance-configured adapter program
+//! (`ProgramConfig::dex_adapter`), typically a thin wrapper over Phoenix or
+//! OpenBook.  The client builds the adapter instruction; the program
+//! forwards it with the vault PDA as signer and measures what actually
+//! left and came back to the vault's token accounts, so the vault ledger
+//! never relies on the adapter's own accounting.
+//!
+//! Since the vault PDA signs, the adapter could move any vault token
+//! account it is given.  Only the order's own token accounts may be
+//! passed, and none of them may lose more than the order accounts for.
+
+use anchor_lang::prelude::*;
+use anchor_lang::solana_program::instruction::{AccountMeta, Instruction};
+use anchor_lang::solana_program::program::invoke_signed;
+use anchor_spl::token::spl_token;
+use anchor_spl::token_2022::spl_token_2022::{self, extension::StateWithExtensions};
+
+use crate::ObscuraError;
+
+/// Invoke `adapter` with `data` over `accounts`, signing for `vault`.
+pub fn invoke_adapter<'info>(
+    adapter: &AccountInfo<'info>,
+    vault: &Pubkey,
+    accounts: &[AccountInfo<'info>],
+    data: Vec<u8>,
+    signer_seeds: &[&[&[u8]]],
+) -> Result<()> {
+    let metas = accounts
+        .iter()
+        .map(|account| AccountMeta {
+            pubkey: account.key(),
+            is_signer: account.is_signer || account.key == vault,
+            is_writable: account.is_writable,
+        })
+        .collect();
+
+    let mut infos = accounts.to_vec();
+    infos.push(adapter.clone());
+    invoke_signed(
+        &Instruction {
+            program_id: adapter.key(),
+            accounts: metas,
+            data,
+        },
+        &infos,
+        signer_seeds,
+    )?;
+    Ok(())
+}
+
+/// Balances of the vault-owned token accounts among `accounts`, failing
+/// if any of them is not in `allowed`.
+pub fn vault_token_balances(
+    accounts: &[AccountInfo],
+    vault: &Pubkey,
+    allowed: &[Pubkey],
+) -> Result<Vec<(Pubkey, u64)>> {
+    let mut balances = Vec::new();
+    for account in accounts {
+        if let Some(amount) = vault_token_amount(account, vault)? {
+            require!(
+                allowed.contains(account.key),
+                ObscuraError::DexAccountNotAllowed
+            );
+            balances.push((account.key(), amount));
+        }
+    }
+    Ok(balances)
+}
+
+/// Fail if any account in `before` holds less than it did, or no longer
+/// belongs to the vault, after the adapter ran.
+pub fn require_no_outflow(
+    accounts: &[AccountInfo],
+    vault: &Pubkey,
+    before: &[(Pubkey, u64)],
+) -> Result<()> {
+    for (key, amount) in before {
+        let after = match accounts.iter().find(|account| account.key == key) {
+            Some(account) => vault_token_amount(account, vault)?.unwrap_or(0),
+            None => 0,
+        };
+        require!(after >= *amount, ObscuraError::DexAdapterMismatch);
+    }
+    Ok(())
+}
+
+/// Balance of `account` if it is a token account owned by `vault`.
+fn vault_token_amount(account: &AccountInfo, vault: &Pubkey) -> Result<Option<u64>> {
+    if *account.owner != spl_token::ID && *account.owner != spl_token_2022::ID {
+        return Ok(None);
+    }
+    let data = account.try_borrow_data()?;
+    let Ok(state) = StateWithExtensions::<spl_token_2022::state::Account>::unpack(&data) else {
+        return Ok(None);
+    };
+    Ok((state.base.owner == *vault).then_some(state.base.amount))
+}
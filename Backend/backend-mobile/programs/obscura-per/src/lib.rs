@@ -1,26 +1,589 @@
 use anchor_lang::prelude::*;
+use anchor_lang::solana_program::hash::hashv;
+use anchor_lang::solana_program::instruction::{get_stack_height, TRANSACTION_LEVEL_STACK_HEIGHT};
+use anchor_lang::solana_program::pubkey;
+use anchor_lang::solana_program::sysvar::instructions::load_instruction_at_checked;
+use anchor_spl::associated_token::{get_associated_token_address_with_program_id, AssociatedToken};
+use anchor_spl::token::spl_token::native_mint;
+use anchor_spl::token_interface::{
+    close_account, sync_native, CloseAccount, Mint, SyncNative, TokenAccount, TokenInterface,
+};
 use ephemeral_rollups_sdk::cpi::delegate_account;
-use ephemeral_rollups_sdk::cr::commit_and_undelegate_accounts;
 use ephemeral_rollups_sdk::ephem::commit_accounts;
+use mpl_bubblegum::instructions::TransferCpiBuilder;
+use mpl_bubblegum::utils::get_asset_id;
+use mpl_token_metadata::instructions::TransferV1CpiBuilder;
+use pyth_solana_receiver_sdk::price_update::PriceUpdateV2;
+
+pub mod access_control;
+pub mod attestation;
+pub mod compliance;
+pub mod delegation;
+pub mod dex;
+pub mod receipt;
+pub mod token;
 
 declare_id!("YOUR_PROGRAM_ID");
 
+// Disclosure contact and build metadata, readable from the deployed
+// binary with `solana-verify` / `query-security-txt`.
+#[cfg(not(feature = "no-entrypoint"))]
+solana_security_txt::security_txt! {
+    name: "Obscura PER",
+    project_url: "https://github.com/daemon-protocol/obscura_app",
+    contacts: "link:https://github.com/daemon-protocol/obscura_app/security/advisories/new",
+    policy: "https://github.com/daemon-protocol/obscura_app/security/policy",
+    preferred_languages: "en",
+    source_code: "https://github.com/daemon-protocol/obscura_app",
+    source_release: concat!("obscura-per-v", env!("CARGO_PKG_VERSION"))
+}
+
 // ---------------------------------------------------------------------------
 // Constants
+//
+// Everything clients need to derive PDAs or size accounts is marked
+// `#[constant]` so it lands in the IDL; TypeScript / Kotlin clients should
+// read these from the IDL rather than copy the byte strings.
 // ---------------------------------------------------------------------------
 
 /// MagicBlock Delegation Program
+#[constant]
 pub const DELEGATION_PROGRAM: &str = "DELeGGvXpWV2fqJUhqcF5ZSYMS4JTLjteaAMARRSaeSh";
 
-/// MagicBlock Access Control Program (for PER visibility restrictions)
+/// MagicBlock Access Control Program (for PER visibility restrictions;
+/// `access_control::ID`)
+#[constant]
 pub const ACCESS_CONTROL_PROGRAM: &str = "ACLseoPoyC3cBqoUtkbjZ4aDrkurZW86v19pXz2XQnp1";
 
 /// Vault seed prefix
+#[constant]
 pub const VAULT_SEED: &[u8] = b"obscura_vault";
 
 /// Permission seed prefix
+#[constant]
 pub const PERMISSION_SEED: &[u8] = b"obscura_permission";
 
+/// MagicBlock devnet ER validator (Asia)
+#[constant]
+pub const ASIA_VALIDATOR: Pubkey = pubkey!("MAS1Dt9qreoRMQ14YQuhg8UTZMMzDdKhmkZMECCzk57");
+
+/// MagicBlock devnet ER validator (EU)
+#[constant]
+pub const EU_VALIDATOR: Pubkey = pubkey!("MEUGGrYPxKk17hCr7wpT6s8dtNokZj5U2L57vjYMS8e");
+
+/// MagicBlock devnet ER validator (US)
+#[constant]
+pub const US_VALIDATOR: Pubkey = pubkey!("MUS3hc9TCw4cGC12vHNoYcCGzJG1txjgQLZWVoeNHNd");
+
+/// TEE validator for Private ER (PER)
+#[constant]
+pub const TEE_VALIDATOR: Pubkey = pubkey!("FnE6VJT5QNZdedZPnCoLsARgBwoE6DeJNjBs2H1gySXA");
+
+/// Allocated size of a `VaultState` account (discriminator included)
+#[constant]
+pub const VAULT_STATE_SIZE: usize = 8 + VaultState::INIT_SPACE;
+
+/// Allocated size of a `PermissionState` account (discriminator included)
+#[constant]
+pub const PERMISSION_STATE_SIZE: usize = 8 + PermissionState::INIT_SPACE;
+
+/// Program config seed (singleton)
+#[constant]
+pub const CONFIG_SEED: &[u8] = b"obscura_config";
+
+/// Validator registry entry seed prefix
+#[constant]
+pub const VALIDATOR_SEED: &[u8] = b"obscura_validator";
+
+/// Mandatory public delay between a validator being marked defunct and
+/// governance force-settling the vaults delegated to it (7 days).
+#[constant]
+pub const FORCE_SETTLE_DELAY: i64 = 7 * 24 * 60 * 60;
+
+/// Queued config change seed prefix
+#[constant]
+pub const CONFIG_CHANGE_SEED: &[u8] = b"obscura_config_change";
+
+/// Parameter vote record seed prefix
+#[constant]
+pub const VOTE_RECORD_SEED: &[u8] = b"obscura_vote";
+
+/// Delay between governance queueing a config change and it becoming
+/// executable, during which vault owners vote on it (3 days)
+#[constant]
+pub const CONFIG_CHANGE_DELAY: i64 = 3 * 24 * 60 * 60;
+
+/// Vault age (days) at which a parameter vote reaches full weight
+#[constant]
+pub const MAX_VOTE_WEIGHT: u64 = 365;
+
+/// Weight against a queued config change (vault-days) at which it is
+/// vetoed, if it also outweighs the weight for it
+#[constant]
+pub const VETO_WEIGHT: u64 = 100_000;
+
+/// Period a `RateCard`'s delegation fee is quoted per (30 days)
+#[constant]
+pub const RATE_CARD_PERIOD: i64 = 30 * 24 * 60 * 60;
+
+/// Shortest owner inactivity governance may set as the auto-undelegate
+/// threshold (7 days), so owners who are merely offline for a few days
+/// are not swept out of the ER.
+#[constant]
+pub const MIN_INACTIVITY_UNDELEGATE_AFTER: i64 = 7 * 24 * 60 * 60;
+
+/// Validator score seed prefix
+#[constant]
+pub const VALIDATOR_SCORE_SEED: &[u8] = b"obscura_validator_score";
+
+/// Maximum validator score (basis points)
+#[constant]
+pub const MAX_VALIDATOR_SCORE: u16 = 10_000;
+
+/// Oldest latest-observation (seconds) a `ValidatorScore` may have to
+/// satisfy a vault's `min_validator_score` policy
+#[constant]
+pub const MAX_VALIDATOR_SCORE_AGE: i64 = 24 * 60 * 60;
+
+/// Commit latency at or below which a validator takes no latency penalty
+#[constant]
+pub const SCORE_LATENCY_TARGET_MS: u32 = 50;
+
+/// History view seed prefix
+#[constant]
+pub const HISTORY_SEED: &[u8] = b"obscura_history";
+
+/// Number of calendar months kept in a `HistoryView` ring
+#[constant]
+pub const HISTORY_MONTHS: usize = 12;
+
+/// Number of amount buckets in a `HistoryView` (decades of SOL)
+#[constant]
+pub const HISTORY_AMOUNT_BUCKETS: usize = 5;
+
+/// Registered device key seed prefix
+#[constant]
+pub const DEVICE_SEED: &[u8] = b"obscura_device";
+
+/// Session handoff seed prefix
+#[constant]
+pub const HANDOFF_SEED: &[u8] = b"obscura_handoff";
+
+/// Maximum encrypted session bundle length (bytes)
+#[constant]
+pub const MAX_SESSION_BUNDLE_LEN: usize = 512;
+
+/// How long a posted session handoff can be picked up (10 minutes)
+#[constant]
+pub const SESSION_HANDOFF_TTL: i64 = 10 * 60;
+
+/// Delegated authority seed prefix
+#[constant]
+pub const AUTHORITY_SEED: &[u8] = b"obscura_authority";
+
+/// Capability record seed prefix
+#[constant]
+pub const CAPABILITY_SEED: &[u8] = b"obscura_capability";
+
+/// Longest a capability may stay valid (24 hours)
+#[constant]
+pub const MAX_CAPABILITY_TTL: i64 = 24 * 60 * 60;
+
+/// `DelegatedAuthority::allowed_instructions` bit: `commit_vault_state`
+#[constant]
+pub const IX_COMMIT_VAULT_STATE: u64 = 1 << 0;
+
+/// `DelegatedAuthority::allowed_instructions` bit: `heartbeat`
+#[constant]
+pub const IX_HEARTBEAT: u64 = 1 << 1;
+
+/// `DelegatedAuthority::allowed_instructions` bit: `undelegate_vault`
+#[constant]
+pub const IX_UNDELEGATE_VAULT: u64 = 1 << 2;
+
+/// All instructions that can currently be delegated
+pub const IX_DELEGABLE: u64 = IX_COMMIT_VAULT_STATE | IX_HEARTBEAT | IX_UNDELEGATE_VAULT;
+
+/// `SessionKey::allowed_instructions` bit: `private_transfer`
+#[constant]
+pub const IX_PRIVATE_TRANSFER: u64 = 1 << 3;
+
+/// All instructions that currently accept a session key
+pub const IX_SESSIONABLE: u64 = IX_PRIVATE_TRANSFER;
+
+/// Session key seed prefix
+#[constant]
+pub const SESSION_KEY_SEED: &[u8] = b"obscura_session_key";
+
+/// Longest a session key may stay valid (7 days)
+#[constant]
+pub const MAX_SESSION_KEY_TTL: i64 = 7 * 24 * 60 * 60;
+
+/// `PermissionState::scopes` bit: read vault state in the PER
+#[constant]
+pub const SCOPE_READ: u8 = 1 << 0;
+
+/// `PermissionState::scopes` bit: initiate `private_transfer`
+#[constant]
+pub const SCOPE_TRANSFER: u8 = 1 << 1;
+
+/// `PermissionState::scopes` bit: administrative instructions
+#[constant]
+pub const SCOPE_ADMIN: u8 = 1 << 2;
+
+/// `PermissionState::scopes` bit: see the openings of the owner's sealed
+/// counterparty ratings (disclosed by the owner's client)
+#[constant]
+pub const SCOPE_REPUTATION: u8 = 1 << 3;
+
+/// All defined permission scopes
+pub const SCOPE_ALL: u8 = SCOPE_READ | SCOPE_TRANSFER | SCOPE_ADMIN | SCOPE_REPUTATION;
+
+/// Maximum permissions created by one `create_permissions_batch`
+#[constant]
+pub const MAX_PERMISSION_BATCH: usize = 10;
+
+/// Maximum transfers in one `private_transfer_batch`
+#[constant]
+pub const MAX_TRANSFER_BATCH: usize = 16;
+
+/// Maximum number of sub-operations in one `execute_intent`
+#[constant]
+pub const MAX_INTENT_OPS: usize = 8;
+
+/// Maximum memo length (bytes) in an intent
+#[constant]
+pub const MAX_MEMO_LEN: usize = 128;
+
+/// Fee statement seed prefix
+#[constant]
+pub const FEE_STATEMENT_SEED: &[u8] = b"obscura_fee_statement";
+
+/// Basis-point denominator for fee rates
+#[constant]
+pub const BPS_DENOMINATOR: u64 = 10_000;
+
+/// Integrator (third-party app) registration seed prefix
+#[constant]
+pub const INTEGRATOR_SEED: &[u8] = b"obscura_integrator";
+
+/// Maximum `rebalance_own_vaults` calls out of one vault per UTC day
+#[constant]
+pub const MAX_REBALANCES_PER_DAY: u8 = 24;
+
+/// Program release number, bumped with every deployed upgrade.  Vaults
+/// in strict upgrade mode must acknowledge each new release.
+#[constant]
+pub const PROGRAM_VERSION: u32 = 1;
+
+/// Delay before a receive-only vault can send again (48 hours)
+#[constant]
+pub const RECEIVE_ONLY_UNLOCK_DELAY: i64 = 48 * 60 * 60;
+
+/// Delay before a velocity override takes effect, and how long it then
+/// stays valid (24 hours each)
+#[constant]
+pub const VELOCITY_OVERRIDE_DELAY: i64 = 24 * 60 * 60;
+
+/// Delay before a raised or removed spending limit takes effect (48 hours)
+#[constant]
+pub const SPENDING_LIMIT_RAISE_DELAY: i64 = 48 * 60 * 60;
+
+/// Length of the rolling withdrawal window (24 hours)
+#[constant]
+pub const WITHDRAWAL_WINDOW: i64 = 24 * 60 * 60;
+
+/// Delay before a withdrawal-limit override takes effect, and how long it
+/// then stays valid (24 hours each)
+#[constant]
+pub const WITHDRAWAL_OVERRIDE_DELAY: i64 = 24 * 60 * 60;
+
+/// Pending withdrawal seed prefix
+#[constant]
+pub const PENDING_WITHDRAWAL_SEED: &[u8] = b"obscura_pending_withdrawal";
+
+/// Delay before a withdrawal over the timelock threshold can be executed
+/// (48 hours)
+#[constant]
+pub const WITHDRAWAL_TIMELOCK_DELAY: i64 = 48 * 60 * 60;
+
+/// Recipient allowlist seed prefix
+#[constant]
+pub const RECIPIENT_ALLOWLIST_SEED: &[u8] = b"obscura_allowlist";
+
+/// Maximum recipients on a vault's allowlist
+#[constant]
+pub const MAX_ALLOWED_RECIPIENTS: usize = 16;
+
+/// Integrator denylist seed prefix
+#[constant]
+pub const DENYLIST_SEED: &[u8] = b"obscura_denylist";
+
+/// Maximum recipients on an integrator's denylist
+#[constant]
+pub const MAX_DENIED_RECIPIENTS: usize = 64;
+
+/// NFT custody record seed prefix
+#[constant]
+pub const NFT_SEED: &[u8] = b"obscura_nft";
+
+/// Compressed NFT custody record seed prefix
+#[constant]
+pub const CNFT_SEED: &[u8] = b"obscura_cnft";
+
+/// Relayer reimbursement ledger seed prefix
+#[constant]
+pub const RELAYER_LEDGER_SEED: &[u8] = b"obscura_relayer";
+
+/// How long an owner can dispute settled relayer fees (48 hours)
+#[constant]
+pub const RELAYER_DISPUTE_WINDOW: i64 = 48 * 60 * 60;
+
+/// Contact-hash claim registry seed prefix
+#[constant]
+pub const CONTACT_SEED: &[u8] = b"obscura_contact";
+
+/// Payment intent receipt seed prefix
+#[constant]
+pub const INTENT_RECEIPT_SEED: &[u8] = b"obscura_intent_receipt";
+
+/// Receipt cNFT tree delegate seed
+#[constant]
+pub const RECEIPT_AUTHORITY_SEED: &[u8] = b"obscura_receipt_authority";
+
+/// Payment intent envelope version `fulfill_intent` accepts
+#[constant]
+pub const PAYMENT_INTENT_VERSION: u8 = 1;
+
+/// Savings goal seed prefix
+#[constant]
+pub const GOAL_SEED: &[u8] = b"obscura_goal";
+
+/// Safety fund seed
+#[constant]
+pub const SAFETY_FUND_SEED: &[u8] = b"obscura_safety_fund";
+
+/// Fee for breaking a savings goal early, in basis points (5%)
+#[constant]
+pub const GOAL_BREAK_FEE_BPS: u64 = 500;
+
+/// Vault metadata seed prefix
+#[constant]
+pub const METADATA_SEED: &[u8] = b"obscura_metadata";
+
+/// Maximum vault label length (bytes)
+#[constant]
+pub const MAX_LABEL_LEN: usize = 32;
+
+/// Maximum encrypted memo length (bytes)
+#[constant]
+pub const MAX_ENCRYPTED_MEMO_LEN: usize = 256;
+
+/// Owner → vault index seed prefix
+#[constant]
+pub const OWNER_INDEX_SEED: &[u8] = b"obscura_owner_index";
+
+/// Maximum vaults listed in one `OwnerIndex`
+#[constant]
+pub const MAX_INDEXED_VAULTS: usize = 32;
+
+/// Program version info seed
+#[constant]
+pub const VERSION_SEED: &[u8] = b"obscura_version";
+
+/// Conditional transfer seed prefix
+#[constant]
+pub const CONDITIONAL_TRANSFER_SEED: &[u8] = b"obscura_conditional";
+
+/// Evidence locker seed prefix
+#[constant]
+pub const EVIDENCE_SEED: &[u8] = b"obscura_evidence";
+
+/// Rating ticket seed prefix
+#[constant]
+pub const RATING_TICKET_SEED: &[u8] = b"obscura_rating_ticket";
+
+/// How long after an escrow completes its parties may rate each other
+#[constant]
+pub const RATING_WINDOW: i64 = 30 * 24 * 60 * 60;
+
+/// Highest public rating score (the lowest is 1)
+#[constant]
+pub const MAX_RATING_SCORE: u8 = 5;
+
+/// Maximum entries in one `EvidenceLocker`
+#[constant]
+pub const MAX_EVIDENCE_ENTRIES: usize = 8;
+
+/// Maximum encrypted evidence blob length (bytes)
+#[constant]
+pub const MAX_EVIDENCE_LEN: usize = 256;
+
+/// Scheduled transfer seed prefix
+#[constant]
+pub const SCHEDULED_TRANSFER_SEED: &[u8] = b"obscura_scheduled";
+
+/// Lamports escrowed with each scheduled transfer and paid to whoever
+/// cranks `execute_scheduled_transfer` (two base signature fees)
+#[constant]
+pub const SCHEDULED_TRANSFER_TIP: u64 = 10_000;
+
+/// Cranker stats seed prefix
+#[constant]
+pub const CRANKER_SEED: &[u8] = b"obscura_cranker";
+
+/// Cranks by the same caller closer together than this halve its tip,
+/// compounding; the withheld share goes back to the transfer owner
+#[constant]
+pub const CRANK_COOLDOWN: i64 = 60;
+
+/// Subscription seed prefix
+#[constant]
+pub const SUBSCRIPTION_SEED: &[u8] = b"obscura_subscription";
+
+/// Shortest billing interval a subscription may have (1 day)
+#[constant]
+pub const MIN_SUBSCRIPTION_INTERVAL: i64 = 24 * 60 * 60;
+
+/// Payment stream seed prefix
+#[constant]
+pub const STREAM_SEED: &[u8] = b"obscura_stream";
+
+/// Oldest Pyth price (seconds) a price condition accepts
+#[constant]
+pub const PRICE_MAX_AGE: u64 = 60;
+
+/// Limit order seed prefix
+#[constant]
+pub const LIMIT_ORDER_SEED: &[u8] = b"obscura_limit_order";
+
+/// Per-mint token ledger seed prefix
+#[constant]
+pub const TOKEN_BALANCE_SEED: &[u8] = b"obscura_token_balance";
+
+/// `ProgramConfig::feature_flags` bit: vault-funded limit orders
+/// (bits 0-2 are unassigned)
+#[constant]
+pub const FEATURE_LIMIT_ORDERS: u64 = 1 << 3;
+
+/// Guardian set seed prefix
+#[constant]
+pub const GUARDIAN_SEED: &[u8] = b"obscura_guardians";
+
+/// Maximum guardians per vault
+#[constant]
+pub const MAX_GUARDIANS: usize = 8;
+
+/// Challenge period between guardians reaching their threshold and the
+/// owner rotation taking effect, during which the owner can cancel (72
+/// hours).  An unapproved request can be replaced after as long again.
+#[constant]
+pub const RECOVERY_CHALLENGE_PERIOD: i64 = 72 * 60 * 60;
+
+/// Shortest inactivity period after which a beneficiary may claim a
+/// vault (30 days), so a holiday away from the app never hands it over
+#[constant]
+pub const MIN_INHERITANCE_PERIOD: i64 = 30 * 24 * 60 * 60;
+
+/// Multisig owner set seed prefix
+#[constant]
+pub const VAULT_OWNERS_SEED: &[u8] = b"obscura_vault_owners";
+
+/// Multisig action proposal seed prefix
+#[constant]
+pub const ACTION_SEED: &[u8] = b"obscura_action";
+
+/// Maximum signers in a `VaultOwners` set
+#[constant]
+pub const MAX_VAULT_OWNERS: usize = 10;
+
+/// Maximum instruction data length (bytes) of a proposed action
+#[constant]
+pub const MAX_ACTION_DATA_LEN: usize = 512;
+
+/// Maximum accounts of a proposed action
+#[constant]
+pub const MAX_ACTION_ACCOUNTS: usize = 16;
+
+/// Shared expense group seed prefix
+#[constant]
+pub const GROUP_SEED: &[u8] = b"obscura_group";
+
+/// Maximum member vaults per expense group (participant sets are `u8`
+/// bitmaps)
+#[constant]
+pub const MAX_GROUP_MEMBERS: usize = 8;
+
+/// Point-of-sale session seed prefix
+#[constant]
+pub const POS_SESSION_SEED: &[u8] = b"obscura_pos";
+
+/// Longest a point-of-sale session accepts payments (24 hours)
+#[constant]
+pub const MAX_POS_SESSION_DURATION: i64 = 24 * 60 * 60;
+
+/// Maximum vaults reported by one `inspect_vaults` (keeps the report
+/// within the 1 KiB return-data limit)
+#[constant]
+pub const MAX_INSPECTED_VAULTS: usize = 8;
+
+/// `VaultHealth::policy_flags` bit: receive-only mode
+#[constant]
+pub const HEALTH_RECEIVE_ONLY: u16 = 1 << 0;
+
+/// `VaultHealth::policy_flags` bit: receive-only unlock requested
+#[constant]
+pub const HEALTH_UNLOCK_PENDING: u16 = 1 << 1;
+
+/// `VaultHealth::policy_flags` bit: strict upgrade mode
+#[constant]
+pub const HEALTH_STRICT_UPGRADE: u16 = 1 << 2;
+
+/// `VaultHealth::policy_flags` bit: strict upgrade mode and the running
+/// release is not yet acknowledged (outbound instructions fail)
+#[constant]
+pub const HEALTH_UPGRADE_UNACKNOWLEDGED: u16 = 1 << 3;
+
+/// `VaultHealth::policy_flags` bit: velocity step-up policy set
+#[constant]
+pub const HEALTH_VELOCITY_POLICY: u16 = 1 << 4;
+
+/// `VaultHealth::policy_flags` bit: custodial vault
+#[constant]
+pub const HEALTH_CUSTODIAL: u16 = 1 << 5;
+
+/// `VaultHealth::policy_flags` bit: on invoiced billing
+#[constant]
+pub const HEALTH_INVOICED: u16 = 1 << 6;
+
+/// `VaultHealth::policy_flags` bit: per-epoch spending limit set
+#[constant]
+pub const HEALTH_SPENDING_LIMIT: u16 = 1 << 7;
+
+/// `VaultHealth::policy_flags` bit: 24h withdrawal limit set
+#[constant]
+pub const HEALTH_WITHDRAWAL_LIMIT: u16 = 1 << 8;
+
+/// `VaultHealth::policy_flags` bit: large withdrawals are timelocked
+#[constant]
+pub const HEALTH_WITHDRAWAL_TIMELOCK: u16 = 1 << 9;
+
+/// `VaultHealth::policy_flags` bit: recipient allowlist mode is on
+#[constant]
+pub const HEALTH_RECIPIENT_ALLOWLIST: u16 = 1 << 10;
+
+/// `VaultHealth::policy_flags` bit: the vault is frozen
+#[constant]
+pub const HEALTH_FROZEN: u16 = 1 << 11;
+
+/// `VaultHealth::policy_flags` bit: a beneficiary is set
+#[constant]
+pub const HEALTH_BENEFICIARY: u16 = 1 << 12;
+
+/// `VaultHealth::policy_flags` bit: outbound transfers need a co-signer
+#[constant]
+pub const HEALTH_COSIGNER: u16 = 1 << 13;
+
 // ---------------------------------------------------------------------------
 // Program
 // ---------------------------------------------------------------------------
@@ -56,6 +619,38 @@ pub mod obscura_per {
         vault.last_activity = Clock::get()?.unix_timestamp;
         vault.nonce = 0;
         vault.is_private = false;
+        vault.min_validator_score = 0;
+        vault.delegation_sdk_version = 0;
+        vault.billing = FeeLedger::default();
+        vault.velocity = VelocityPolicy::default();
+        vault.min_accepted_program_version = 0;
+        vault.fee_payment_cap = 0;
+        vault.token_balances = 0;
+        vault.rebalance_day = 0;
+        vault.rebalances_today = 0;
+        vault.receive_only = false;
+        vault.receive_only_unlock_at = 0;
+        vault.custody_claimant = Pubkey::default();
+        vault.locked_balance = 0;
+        vault.round_up = RoundUpRule::default();
+        vault.split = SplitRule::default();
+        vault.permission_manager = Pubkey::default();
+        vault.integrator = ctx.accounts.integrator_key();
+        vault.spending_limit = SpendingLimit::default();
+        vault.withdrawal_limit = WithdrawalLimit::default();
+        vault.last_settled_slot = 0;
+        vault.policy_hash = [0; 32];
+        vault.allowlist_enabled = false;
+        vault.frozen = false;
+        vault.freeze_guardian = Pubkey::default();
+        vault.beneficiary = Pubkey::default();
+        vault.inheritance_period = 0;
+        vault.cosigner = Pubkey::default();
+        vault.operator_fees = OperatorFees::default();
+
+        if let Some(owner_index) = ctx.accounts.owner_index.as_mut() {
+            owner_index.insert(vault_id)?;
+        }
 
         msg!(
             "Vault created: id={}, owner={}",
@@ -65,6 +660,117 @@ pub mod obscura_per {
         Ok(())
     }
 
+    /// Create a vault controlled by an operator on behalf of a user who
+    /// does not have a wallet yet.
+    ///
+    /// `claimant` is a key generated on the user's device at sign-up.
+    /// Whoever holds it can call `claim_full_custody` at any time; the
+    /// operator cannot change or revoke it.
+    pub fn create_custodial_vault(
+        ctx: Context<CreateVault>,
+        vault_id: u64,
+        claimant: Pubkey,
+    ) -> Result<()> {
+        require!(claimant != Pubkey::default(), ObscuraError::InvalidClaimant);
+
+        let now = Clock::get()?.unix_timestamp;
+        let vault = &mut ctx.accounts.vault;
+        vault.set_inner(VaultState {
+            owner: ctx.accounts.owner.key(),
+            vault_id,
+            created_at: now,
+            last_activity: now,
+            custody_claimant: claimant,
+            integrator: ctx.accounts.integrator_key(),
+            ..Default::default()
+        });
+        if let Some(owner_index) = ctx.accounts.owner_index.as_mut() {
+            owner_index.insert(vault_id)?;
+        }
+
+        msg!(
+            "Custodial vault created: id={}, operator={}",
+            vault_id,
+            ctx.accounts.owner.key()
+        );
+        Ok(())
+    }
+
+    /// Take over a custodial vault.  One-way: the claimant's `new_owner`
+    /// becomes the owner and the vault can never become custodial again.
+    ///
+    /// Works whether or not the vault is delegated, so the operator
+    /// cannot block it.  Operator-set payment policies are reset; the new
+    /// owner should review delegated authorities and relayer ledgers.
+    pub fn claim_full_custody(ctx: Context<ClaimFullCustody>) -> Result<()> {
+        let vault = &mut ctx.accounts.vault;
+        require!(
+            vault.custody_claimant != Pubkey::default(),
+            ObscuraError::NotCustodial
+        );
+        require_keys_eq!(
+            vault.custody_claimant,
+            ctx.accounts.claimant.key(),
+            ObscuraError::Unauthorized
+        );
+
+        let operator = vault.owner;
+        vault.hand_over_custody(ctx.accounts.new_owner.key());
+        vault.last_activity = Clock::get()?.unix_timestamp;
+
+        msg!(
+            "Vault {} custody claimed: {} -> {}",
+            vault.vault_id,
+            operator,
+            vault.owner
+        );
+        Ok(())
+    }
+
+    /// Link a custodial vault to a salted hash of a phone number or
+    /// email, so senders can pay "a contact" before they have a wallet.
+    ///
+    /// The vault's claimant must be this registry entry's PDA; it is then
+    /// claimed with `claim_contact_vault`.
+    pub fn register_contact_claim(
+        ctx: Context<RegisterContactClaim>,
+        contact_hash: [u8; 32],
+    ) -> Result<()> {
+        let claim = &mut ctx.accounts.contact_claim;
+        claim.contact_hash = contact_hash;
+        claim.vault = ctx.accounts.vault.key();
+        claim.created_at = Clock::get()?.unix_timestamp;
+
+        msg!(
+            "Contact claim registered for vault {}",
+            ctx.accounts.vault.vault_id
+        );
+        Ok(())
+    }
+
+    /// Claim a contact-registered vault.  The claim attestor co-signs
+    /// after verifying the recipient controls the contact identifier.
+    pub fn claim_contact_vault(ctx: Context<ClaimContactVault>) -> Result<()> {
+        let vault = &mut ctx.accounts.vault;
+        require_keys_eq!(
+            vault.custody_claimant,
+            ctx.accounts.contact_claim.key(),
+            ObscuraError::NotCustodial
+        );
+
+        let operator = vault.owner;
+        vault.hand_over_custody(ctx.accounts.new_owner.key());
+        vault.last_activity = Clock::get()?.unix_timestamp;
+
+        msg!(
+            "Vault {} claimed via contact: {} -> {}",
+            vault.vault_id,
+            operator,
+            vault.owner
+        );
+        Ok(())
+    }
+
     /// Delegate vault to an Ephemeral Rollup validator.
     ///
     /// After delegation the account lives on the ER validator and
@@ -72,17 +778,59 @@ pub mod obscura_per {
     ///
     /// For **Private ER (PER)**, pass the TEE validator pubkey:
     /// `FnE6VJT5QNZdedZPnCoLsARgBwoE6DeJNjBs2H1gySXA`
+    ///
+    /// The validator must be in the registry and not defunct.
     pub fn delegate_vault(ctx: Context<DelegateVault>, validator: Pubkey) -> Result<()> {
+        ctx.accounts.vault.require_acknowledged_upgrade()?;
+
+        // Enforce the owner's minimum validator score policy, if any.
+        if ctx.accounts.vault.min_validator_score > 0 {
+            let score = ctx
+                .accounts
+                .validator_score
+                .as_ref()
+                .ok_or(ObscuraError::ValidatorScoreTooLow)?;
+            require_keys_eq!(score.validator, validator, ObscuraError::InvalidValidator);
+            require!(
+                Clock::get()?.unix_timestamp - score.last_observed_at <= MAX_VALIDATOR_SCORE_AGE,
+                ObscuraError::StaleValidatorScore
+            );
+            require!(
+                score.score >= ctx.accounts.vault.min_validator_score,
+                ObscuraError::ValidatorScoreTooLow
+            );
+        }
+
+        // Only registered, live validators; the registry pins the
+        // delegation SDK version each one speaks.
+        let validator_info = &ctx.accounts.validator_info;
+        require!(!validator_info.is_defunct, ObscuraError::ValidatorDefunct);
+        let sdk_version = validator_info.sdk_version;
+        require!(
+            delegation::accepts_new_delegations(sdk_version),
+            ObscuraError::UnsupportedSdkVersion
+        );
+
+        // The owner accepts the operator's current rate card by delegating;
+        // fees from an earlier delegation must be settled first.
+        require!(
+            ctx.accounts.vault.operator_fees.owed == 0,
+            ObscuraError::OperatorFeesOutstanding
+        );
+        let rate_card = ctx.accounts.validator_info.rate_card;
+
         // Record delegation metadata *before* we hand off to the SDK,
         // because after delegation the account is owned by the ER validator.
+        let clock = Clock::get()?;
         let vault = &mut ctx.accounts.vault;
         vault.is_delegated = true;
         vault.delegate_validator = validator;
-        vault.last_activity = Clock::get()?.unix_timestamp;
+        vault.delegation_sdk_version = sdk_version;
+        vault.last_activity = clock.unix_timestamp;
+        vault.last_settled_slot = clock.slot;
+        vault.operator_fees.start(&rate_card, clock.unix_timestamp);
 
-        // Known TEE validator for PER
-        const TEE_VALIDATOR: &str = "FnE6VJT5QNZdedZPnCoLsARgBwoE6DeJNjBs2H1gySXA";
-        if validator.to_string() == TEE_VALIDATOR {
+        if validator == TEE_VALIDATOR {
             vault.is_private = true;
             msg!("Delegating to TEE validator for Private ER");
         }
@@ -110,51 +858,195 @@ pub mod obscura_per {
     /// This instruction runs on the ER validator and benefits from
     /// sub-second finality.  When finished it commits the updated state
     /// back to L1 and undelegates the account in a single atomic step.
+    ///
+    /// Signed by the owner, a transfer-scoped permission holder, or a
+    /// session key passed as `session_key`.
     pub fn private_transfer(
         ctx: Context<PrivateTransfer>,
         amount: u64,
         recipient: Pubkey,
     ) -> Result<()> {
-        let vault = &mut ctx.accounts.vault;
+        private_transfers(ctx, &[BatchTransfer { recipient, amount }])
+    }
 
-        require!(vault.is_delegated, ObscuraError::NotDelegated);
-        require!(vault.balance >= amount, ObscuraError::InsufficientBalance);
+    /// Execute several private transfers inside the Ephemeral Rollup and
+    /// commit + undelegate once for all of them, instead of once per
+    /// transfer.
+    ///
+    /// Takes the same accounts as `private_transfer`.  A session key must
+    /// allow the batch total.
+    pub fn private_transfer_batch(
+        ctx: Context<PrivateTransfer>,
+        transfers: Vec<BatchTransfer>,
+    ) -> Result<()> {
         require!(
-            vault.owner == ctx.accounts.owner.key(),
-            ObscuraError::Unauthorized
+            !transfers.is_empty() && transfers.len() <= MAX_TRANSFER_BATCH,
+            ObscuraError::InvalidTransferBatch
         );
+        private_transfers(ctx, &transfers)
+    }
 
-        // Execute transfer logic
-        vault.balance = vault.balance.checked_sub(amount).unwrap();
-        vault.nonce += 1;
-        vault.last_activity = Clock::get()?.unix_timestamp;
-
-        msg!(
-            "Private transfer: {} lamports to {}, nonce={}",
-            amount,
-            recipient,
-            vault.nonce
+    /// Apply a list of sub-operations atomically inside the ER.
+    ///
+    /// Either every operation applies or none does, so a multi-leg app
+    /// action (e.g. pay + attach memo) can't half-apply.  Transfer legs
+    /// pass the same outbound checks as `private_transfer`.  The vault
+    /// stays delegated; checkpoint with `commit_vault_state` as usual.
+    pub fn execute_intent(ctx: Context<ExecuteIntent>, ops: Vec<IntentOp>) -> Result<()> {
+        require!(
+            !ops.is_empty() && ops.len() <= MAX_INTENT_OPS,
+            ObscuraError::InvalidIntent
         );
 
-        // Commit state back to L1 and undelegate in one step.
-        // The `#[commit]` macro on `PrivateTransfer` wires up the
-        // `magic_context` and `magic_program` accounts automatically.
-        ctx.accounts.commit_and_undelegate_vault()?;
+        let cosigner = ctx.accounts.cosigner.as_ref().map(|c| c.key());
+        let vault = &mut ctx.accounts.vault;
+        require!(vault.is_delegated, ObscuraError::NotDelegated);
+        if ops.iter().any(|op| matches!(op, IntentOp::Transfer { .. })) {
+            vault.require_outbound_allowed()?;
+            vault.require_acknowledged_upgrade()?;
+            vault.require_cosigned(cosigner)?;
+        }
+
+        let clock = Clock::get()?;
+        let now = clock.unix_timestamp;
+        for op in &ops {
+            match op {
+                IntentOp::Transfer { amount, recipient } => {
+                    vault.require_allowed_recipient(
+                        ctx.accounts.recipient_allowlist.as_deref(),
+                        recipient,
+                    )?;
+                    require_compliant(
+                        vault,
+                        ctx.accounts.denylist.as_ref(),
+                        ctx.accounts.compliance_program.as_ref(),
+                        ctx.remaining_accounts,
+                        recipient,
+                        *amount,
+                    )?;
+                    vault.velocity.observe(now, *amount, cosigner)?;
+                    vault.spending_limit.record(clock.epoch, now, *amount)?;
+                    vault.record_transfer(*amount)?;
+                    vault.billing.accrue(now, *amount)?;
+                    let skimmed =
+                        skim_round_up(vault, ctx.accounts.savings_vault.as_mut(), *amount)?;
+                    let split = apply_split(
+                        vault,
+                        ctx.accounts.split_vault.as_mut(),
+                        FlowDirection::Outflow,
+                        *amount,
+                    )?;
+                    msg!(
+                        "Intent transfer: {} lamports to {}, nonce={}, round-up={}, split={}",
+                        amount,
+                        recipient,
+                        vault.nonce,
+                        skimmed,
+                        split
+                    );
+                }
+                IntentOp::Memo { memo } => {
+                    require!(memo.len() <= MAX_MEMO_LEN, ObscuraError::InvalidIntent);
+                    msg!("Intent memo: {}", memo);
+                }
+                IntentOp::GrantPermission {
+                    permitted,
+                    scopes,
+                    expires_at,
+                } => {
+                    let permission = ctx
+                        .accounts
+                        .permission
+                        .as_mut()
+                        .ok_or(ObscuraError::InvalidIntent)?;
+                    require_keys_eq!(
+                        permission.permitted,
+                        *permitted,
+                        ObscuraError::InvalidIntent
+                    );
+                    // Read scope needs an Access Control entry, which
+                    // only `create_permission` maintains.
+                    require!(
+                        !permission.has_acl_entry() && scopes & SCOPE_READ == 0,
+                        ObscuraError::InvalidScopes
+                    );
+                    **permission = PermissionState::grant(
+                        vault.key(),
+                        *permitted,
+                        ctx.accounts.owner.key(),
+                        *scopes,
+                        *expires_at,
+                        now,
+                    )?;
+                    msg!("Intent grant: scopes={:#x} to {}", scopes, permitted);
+                }
+            }
+        }
+        vault.last_activity = now;
 
+        msg!(
+            "Intent with {} ops applied to vault {}",
+            ops.len(),
+            vault.vault_id
+        );
         Ok(())
     }
 
     /// Commit current vault state to L1 without undelegating.
     ///
     /// Useful for periodic checkpoints while keeping the account
-    /// delegated for continued fast execution.
-    pub fn commit_vault_state(ctx: Context<CommitState>) -> Result<()> {
+    /// delegated for continued fast execution.  Delegated companion
+    /// accounts (`HistoryView`, `VaultTokenBalance`) can be passed as
+    /// `remaining_accounts`; only those changed since their last commit
+    /// are committed along with the vault, so large vaults don't pay to
+    /// settle their whole account family every time.
+    pub fn commit_vault_state<'info>(
+        ctx: Context<'_, '_, 'info, 'info, CommitState<'info>>,
+    ) -> Result<()> {
+        require_owner_or_delegate(
+            &ctx.accounts.vault,
+            &ctx.accounts.owner.key(),
+            ctx.accounts.delegated_authority.as_deref(),
+            IX_COMMIT_VAULT_STATE,
+        )?;
+
+        let vault_key = ctx.accounts.vault.key();
+        let mut dirty = Vec::new();
+        for info in ctx.remaining_accounts {
+            let changed = match take_dirty::<HistoryView>(info, &vault_key)? {
+                Some(changed) => changed,
+                None => take_dirty::<VaultTokenBalance>(info, &vault_key)?
+                    .ok_or(ObscuraError::InvalidCompanion)?,
+            };
+            if changed {
+                dirty.push(info);
+            }
+        }
+
+        let now = Clock::get()?.unix_timestamp;
         let vault = &mut ctx.accounts.vault;
-        vault.last_activity = Clock::get()?.unix_timestamp;
+        vault.last_activity = now;
+        vault.operator_fees.accrue(now, 1)?;
 
-        msg!("Committing vault {} state to L1", vault.vault_id);
+        msg!(
+            "Committing vault {} state to L1 with {} of {} companions",
+            vault.vault_id,
+            dirty.len(),
+            ctx.remaining_accounts.len()
+        );
 
-        ctx.accounts.commit_vault()?;
+        let sdk_version = vault.delegation_sdk_version;
+        ctx.accounts.vault.exit(&crate::ID)?;
+        let vault_info = ctx.accounts.vault.to_account_info();
+        let mut accounts = vec![&vault_info];
+        accounts.extend(dirty);
+        delegation::commit(
+            sdk_version,
+            &ctx.accounts.owner,
+            accounts,
+            &ctx.accounts.magic_context,
+            &ctx.accounts.magic_program,
+        )?;
 
         Ok(())
     }
@@ -164,16 +1056,50 @@ pub mod obscura_per {
     /// After undelegation the account is a normal Solana account again
     /// and the ER validator no longer has authority over it.
     pub fn undelegate_vault(ctx: Context<UndelegateVault>) -> Result<()> {
+        require_owner_or_delegate(
+            &ctx.accounts.vault,
+            &ctx.accounts.owner.key(),
+            ctx.accounts.delegated_authority.as_deref(),
+            IX_UNDELEGATE_VAULT,
+        )?;
+
+        let now = Clock::get()?.unix_timestamp;
         let vault = &mut ctx.accounts.vault;
         vault.is_delegated = false;
         vault.delegate_validator = Pubkey::default();
         vault.is_private = false;
-        vault.last_activity = Clock::get()?.unix_timestamp;
+        vault.last_activity = now;
+        vault.operator_fees.stop(now)?;
 
         msg!("Undelegating vault {}", vault.vault_id);
 
-        ctx.accounts.commit_and_undelegate_vault()?;
+        let sdk_version = vault.delegation_sdk_version;
+        ctx.accounts.vault.exit(&crate::ID)?;
+        delegation::commit_and_undelegate(
+            sdk_version,
+            &ctx.accounts.owner,
+            vec![&ctx.accounts.vault.to_account_info()],
+            &ctx.accounts.magic_context,
+            &ctx.accounts.magic_program,
+        )?;
+
+        Ok(())
+    }
 
+    /// Fail unless `base_slot` — the L1 slot the client read the vault at
+    /// when building the transaction — is not older than the vault's
+    /// `last_settled_slot`.
+    ///
+    /// Clients put this first in every L1 transaction on a vault, so a
+    /// transaction signed against pre-delegation state (e.g. held back
+    /// with a durable nonce) fails as a whole once the vault has been
+    /// through the ER, instead of acting on a ledger it no longer
+    /// matches.
+    pub fn check_base_slot(ctx: Context<CheckBaseSlot>, base_slot: u64) -> Result<()> {
+        require!(
+            base_slot >= ctx.accounts.vault.last_settled_slot,
+            ObscuraError::StaleBaseSlot
+        );
         Ok(())
     }
 
@@ -196,30 +1122,73 @@ pub mod obscura_per {
             ],
         )?;
 
-        vault.balance = vault.balance.checked_add(amount).unwrap();
-        vault.last_activity = Clock::get()?.unix_timestamp;
+        let now = Clock::get()?.unix_timestamp;
+        vault.credit(amount)?;
+        let split = apply_split(
+            vault,
+            ctx.accounts.split_vault.as_mut(),
+            FlowDirection::Inflow,
+            amount,
+        )?;
+        vault.last_activity = now;
+        if let Some(history_view) = ctx.accounts.history_view.as_mut() {
+            history_view.record(now, FlowDirection::Inflow, amount);
+        }
 
-        msg!("Deposited {} lamports into vault {}", amount, vault.vault_id);
+        msg!(
+            "Deposited {} lamports into vault {}, split={}",
+            amount,
+            vault.vault_id,
+            split
+        );
         Ok(())
     }
 
     /// Withdraw SOL from the vault.
+    ///
+    /// Passing `pending_withdrawal` queues the withdrawal instead, to be
+    /// executed after `WITHDRAWAL_TIMELOCK_DELAY`; this is required for
+    /// amounts over the vault's timelock threshold.
     pub fn withdraw(ctx: Context<Withdraw>, amount: u64) -> Result<()> {
+        let cosigner = ctx.accounts.cosigner.as_ref().map(|c| c.key());
         let vault = &mut ctx.accounts.vault;
 
         require!(!vault.is_delegated, ObscuraError::AccountDelegated);
         require!(vault.balance >= amount, ObscuraError::InsufficientBalance);
+        vault.require_outbound_allowed()?;
+        vault.require_acknowledged_upgrade()?;
+        vault.require_cosigned(cosigner)?;
         require!(
             vault.owner == ctx.accounts.owner.key(),
             ObscuraError::Unauthorized
         );
+        vault.require_allowed_recipient(
+            ctx.accounts.recipient_allowlist.as_deref(),
+            &ctx.accounts.owner.key(),
+        )?;
+        require_compliant(
+            vault,
+            ctx.accounts.denylist.as_ref(),
+            ctx.accounts.compliance_program.as_ref(),
+            ctx.remaining_accounts,
+            &ctx.accounts.owner.key(),
+            amount,
+        )?;
 
-        // Transfer SOL from vault PDA to owner
-        **vault.to_account_info().try_borrow_mut_lamports()? -= amount;
-        **ctx.accounts.owner.try_borrow_mut_lamports()? += amount;
-
-        vault.balance = vault.balance.checked_sub(amount).unwrap();
-        vault.last_activity = Clock::get()?.unix_timestamp;
+        if let Some(pending) = ctx.accounts.pending_withdrawal.as_mut() {
+            return queue_withdrawal(vault, pending, amount);
+        }
+        require!(
+            !vault.withdrawal_limit.requires_timelock(amount),
+            ObscuraError::WithdrawalTimelocked
+        );
+
+        pay_out_withdrawal(
+            vault,
+            &ctx.accounts.owner.to_account_info(),
+            ctx.accounts.history_view.as_mut(),
+            amount,
+        )?;
 
         msg!(
             "Withdrew {} lamports from vault {}",
@@ -229,19 +1198,210 @@ pub mod obscura_per {
         Ok(())
     }
 
+    /// Shuffle funds between two undelegated vaults with the same owner.
+    ///
+    /// Funds never leave the owner's control, so no fees accrue; each
+    /// source vault is limited to `MAX_REBALANCES_PER_DAY` calls.
+    ///
+    /// `to_vault` counts as the recipient for the outbound checks, which
+    /// run as for `internal_transfer`.  The destination must also be at
+    /// least as strict as the source: spending limit, withdrawal cap and
+    /// timelock threshold no looser, the same co-signer, and, if the
+    /// source is in allowlist mode, allowlist mode with no recipient the
+    /// source's allowlist lacks.  Otherwise a stolen owner key could move
+    /// funds into a fresh vault without policies and withdraw at once.
+    pub fn rebalance_own_vaults(ctx: Context<RebalanceOwnVaults>, amount: u64) -> Result<()> {
+        let cosigner = ctx.accounts.cosigner.as_ref().map(|c| c.key());
+        let from_vault = &mut ctx.accounts.from_vault;
+        let to_vault = &mut ctx.accounts.to_vault;
+
+        require!(
+            !from_vault.is_delegated && !to_vault.is_delegated,
+            ObscuraError::AccountDelegated
+        );
+        require!(
+            from_vault.balance >= amount,
+            ObscuraError::InsufficientBalance
+        );
+        from_vault.require_outbound_allowed()?;
+        from_vault.require_acknowledged_upgrade()?;
+        from_vault.require_cosigned(cosigner)?;
+        let from_allowlist = ctx.accounts.recipient_allowlist.as_deref();
+        from_vault.require_allowed_recipient(from_allowlist, &to_vault.key())?;
+        require_compliant(
+            from_vault,
+            ctx.accounts.denylist.as_ref(),
+            ctx.accounts.compliance_program.as_ref(),
+            ctx.remaining_accounts,
+            &to_vault.key(),
+            amount,
+        )?;
+        require!(
+            !from_vault.withdrawal_limit.requires_timelock(amount),
+            ObscuraError::WithdrawalTimelocked
+        );
+
+        let allowlist_as_strict = !from_vault.allowlist_enabled
+            || (to_vault.allowlist_enabled
+                && ctx
+                    .accounts
+                    .to_recipient_allowlist
+                    .as_deref()
+                    .zip(from_allowlist)
+                    .is_some_and(|(to, from)| {
+                        to.recipients.iter().all(|r| from.recipients.contains(r))
+                    }));
+        require!(
+            to_vault
+                .spending_limit
+                .at_least_as_strict(&from_vault.spending_limit)
+                && to_vault
+                    .withdrawal_limit
+                    .at_least_as_strict(&from_vault.withdrawal_limit)
+                && (from_vault.cosigner == Pubkey::default()
+                    || to_vault.cosigner == from_vault.cosigner)
+                && allowlist_as_strict,
+            ObscuraError::WeakerDestinationPolicy
+        );
+
+        let clock = Clock::get()?;
+        let now = clock.unix_timestamp;
+        let day = (now / 86_400) as u32;
+        if from_vault.rebalance_day != day {
+            from_vault.rebalance_day = day;
+            from_vault.rebalances_today = 0;
+        }
+        require!(
+            from_vault.rebalances_today < MAX_REBALANCES_PER_DAY,
+            ObscuraError::RebalanceLimitExceeded
+        );
+        from_vault.rebalances_today += 1;
+        from_vault.velocity.observe(now, amount, cosigner)?;
+        from_vault.spending_limit.record(clock.epoch, now, amount)?;
+        from_vault.withdrawal_limit.record(now, amount)?;
+
+        from_vault.debit(amount)?;
+        to_vault.credit(amount)?;
+        from_vault.last_activity = now;
+        to_vault.last_activity = now;
+
+        **from_vault.to_account_info().try_borrow_mut_lamports()? -= amount;
+        **to_vault.to_account_info().try_borrow_mut_lamports()? += amount;
+
+        msg!(
+            "Rebalanced {} lamports from vault {} to vault {}",
+            amount,
+            from_vault.vault_id,
+            to_vault.vault_id
+        );
+        Ok(())
+    }
+
+    /// Move lamports directly between two vaults, atomically.
+    ///
+    /// Replaces the withdraw → system transfer → deposit round trip, which
+    /// routes funds through the owner's wallet and links the two vaults.
+    /// Both vaults must be on L1, or both delegated to the same ER: there
+    /// the move only changes ER state, and reaches L1 when each vault is
+    /// next committed (`commit_vault_state` or `undelegate_vault`).
+    ///
+    /// `to_vault` counts as the recipient for the outbound checks, which
+    /// run as for `private_transfer`; amounts past the withdrawal
+    /// timelock threshold are refused.
+    pub fn internal_transfer(ctx: Context<InternalTransfer>, amount: u64) -> Result<()> {
+        let cosigner = ctx.accounts.cosigner.as_ref().map(|c| c.key());
+        let from_vault = &mut ctx.accounts.from_vault;
+        let to_vault = &mut ctx.accounts.to_vault;
+
+        require!(
+            from_vault.delegate_validator == to_vault.delegate_validator,
+            ObscuraError::InvalidValidator
+        );
+        require!(
+            from_vault.balance >= amount,
+            ObscuraError::InsufficientBalance
+        );
+        from_vault.require_outbound_allowed()?;
+        from_vault.require_acknowledged_upgrade()?;
+        from_vault.require_cosigned(cosigner)?;
+        from_vault.require_allowed_recipient(
+            ctx.accounts.recipient_allowlist.as_deref(),
+            &to_vault.key(),
+        )?;
+        require_compliant(
+            from_vault,
+            ctx.accounts.denylist.as_ref(),
+            ctx.accounts.compliance_program.as_ref(),
+            ctx.remaining_accounts,
+            &to_vault.key(),
+            amount,
+        )?;
+        require!(
+            !from_vault.withdrawal_limit.requires_timelock(amount),
+            ObscuraError::WithdrawalTimelocked
+        );
+
+        let clock = Clock::get()?;
+        let now = clock.unix_timestamp;
+        from_vault.velocity.observe(now, amount, cosigner)?;
+        from_vault.spending_limit.record(clock.epoch, now, amount)?;
+        from_vault.withdrawal_limit.record(now, amount)?;
+        move_to_vault(from_vault, to_vault, amount)?;
+        from_vault.billing.accrue(now, amount)?;
+        from_vault.last_activity = now;
+        to_vault.last_activity = now;
+
+        msg!(
+            "Internal transfer of {} lamports from vault {} to vault {}",
+            amount,
+            from_vault.vault_id,
+            to_vault.vault_id
+        );
+        Ok(())
+    }
+
     /// Create a permission entry for Access Control (PER visibility).
     ///
     /// Only accounts with a valid permission PDA can read the vault
-    /// state when it is delegated to a TEE validator.
+    /// state when it is delegated to a TEE validator.  `scopes` (`SCOPE_*`
+    /// bits) limits what the grant covers, e.g. read-only visibility for
+    /// one key and transfer initiation for another.  With `expires_at`
+    /// the grant lapses at that time and anyone can close it.
+    ///
+    /// Read-scoped grants are mirrored into the Access Control program,
+    /// which is what the TEE validator enforces.  An expired grant stays
+    /// visible there until `close_expired_permission` runs.
     pub fn create_permission(
         ctx: Context<CreatePermission>,
         permitted_pubkey: Pubkey,
+        scopes: u8,
+        expires_at: Option<i64>,
     ) -> Result<()> {
-        let permission = &mut ctx.accounts.permission;
-        permission.vault = ctx.accounts.vault.key();
-        permission.permitted = permitted_pubkey;
-        permission.granted_by = ctx.accounts.owner.key();
-        permission.granted_at = Clock::get()?.unix_timestamp;
+        require_permission_authority(&ctx.accounts.vault, &ctx.accounts.owner.key(), scopes)?;
+        let permission = PermissionState::grant(
+            ctx.accounts.vault.key(),
+            permitted_pubkey,
+            ctx.accounts.owner.key(),
+            scopes,
+            expires_at,
+            Clock::get()?.unix_timestamp,
+        )?;
+        if permission.has_acl_entry() {
+            let vault_id = ctx.accounts.vault.vault_id.to_le_bytes();
+            let bump = [ctx.bumps.vault];
+            access_control::grant(
+                &access_control::AclAccounts {
+                    program: &ctx.accounts.access_control_program,
+                    permission: &ctx.accounts.acl_permission,
+                    vault: &ctx.accounts.vault.to_account_info(),
+                    payer: &ctx.accounts.owner,
+                    system_program: &ctx.accounts.system_program,
+                },
+                permitted_pubkey,
+                &[&[VAULT_SEED, &vault_id, &bump]],
+            )?;
+        }
+        ctx.accounts.permission.set_inner(permission);
 
         msg!(
             "Permission granted: {} can access vault {}",
@@ -250,202 +1410,11245 @@ pub mod obscura_per {
         );
         Ok(())
     }
-}
 
-// ---------------------------------------------------------------------------
-// Accounts (instruction contexts)
-// ---------------------------------------------------------------------------
+    /// Grant the same permission to several keys in one transaction.
+    ///
+    /// For each key in `permitted`, in order, its permission PDA and
+    /// Access Control permission are passed as a pair of remaining
+    /// accounts.
+    pub fn create_permissions_batch<'info>(
+        ctx: Context<'_, '_, 'info, 'info, CreatePermissionsBatch<'info>>,
+        permitted: Vec<Pubkey>,
+        scopes: u8,
+        expires_at: Option<i64>,
+    ) -> Result<()> {
+        require!(
+            !permitted.is_empty()
+                && permitted.len() <= MAX_PERMISSION_BATCH
+                && permitted.len() * 2 == ctx.remaining_accounts.len(),
+            ObscuraError::InvalidPermissionBatch
+        );
+        require_permission_authority(&ctx.accounts.vault, &ctx.accounts.owner.key(), scopes)?;
 
-#[derive(Accounts)]
-#[instruction(vault_id: u64)]
-pub struct CreateVault<'info> {
-    #[account(
-        init,
-        payer = owner,
-        space = 8 + VaultState::INIT_SPACE,
-        seeds = [VAULT_SEED, &vault_id.to_le_bytes()],
-        bump,
-    )]
-    pub vault: Account<'info, VaultState>,
+        let vault = ctx.accounts.vault.key();
+        let owner = ctx.accounts.owner.key();
+        let now = Clock::get()?.unix_timestamp;
+        let rent = Rent::get()?.minimum_balance(PERMISSION_STATE_SIZE);
+        let vault_id = ctx.accounts.vault.vault_id.to_le_bytes();
+        let vault_bump = [ctx.bumps.vault];
+        for (permitted_pubkey, pair) in permitted.iter().zip(ctx.remaining_accounts.chunks(2)) {
+            let (account, acl_permission) = (&pair[0], &pair[1]);
+            let (address, bump) = Pubkey::find_program_address(
+                &[PERMISSION_SEED, vault.as_ref(), permitted_pubkey.as_ref()],
+                &crate::ID,
+            );
+            require_keys_eq!(account.key(), address, ObscuraError::InvalidPermissionBatch);
+            require!(account.data_is_empty(), ObscuraError::PermissionExists);
 
-    #[account(mut)]
-    pub owner: Signer<'info>,
+            anchor_lang::system_program::create_account(
+                CpiContext::new_with_signer(
+                    ctx.accounts.system_program.to_account_info(),
+                    anchor_lang::system_program::CreateAccount {
+                        from: ctx.accounts.owner.to_account_info(),
+                        to: account.clone(),
+                    },
+                    &[&[
+                        PERMISSION_SEED,
+                        vault.as_ref(),
+                        permitted_pubkey.as_ref(),
+                        &[bump],
+                    ]],
+                ),
+                rent,
+                PERMISSION_STATE_SIZE as u64,
+                &crate::ID,
+            )?;
 
-    pub system_program: Program<'info, System>,
-}
+            let permission =
+                PermissionState::grant(vault, *permitted_pubkey, owner, scopes, expires_at, now)?;
+            permission.try_serialize(&mut &mut account.try_borrow_mut_data()?[..])?;
 
-/// Delegate a vault to an ER validator.
-///
-/// The `#[delegate]` attribute from `ephemeral-rollups-sdk` automatically
-/// adds the delegation program accounts and generates a
-/// `delegate_vault(&self, ...)` helper on the context.
-#[delegate]
-#[derive(Accounts)]
-pub struct DelegateVault<'info> {
-    #[account(
-        mut,
-        seeds = [VAULT_SEED, &vault.vault_id.to_le_bytes()],
-        bump,
-        del,  // marks this account for delegation
-    )]
-    pub vault: Account<'info, VaultState>,
+            if permission.has_acl_entry() {
+                access_control::grant(
+                    &access_control::AclAccounts {
+                        program: &ctx.accounts.access_control_program,
+                        permission: acl_permission,
+                        vault: &ctx.accounts.vault.to_account_info(),
+                        payer: &ctx.accounts.owner,
+                        system_program: &ctx.accounts.system_program,
+                    },
+                    *permitted_pubkey,
+                    &[&[VAULT_SEED, &vault_id, &vault_bump]],
+                )?;
+            }
+        }
 
-    #[account(mut)]
-    pub owner: Signer<'info>,
-}
+        msg!(
+            "Permissions granted: {} keys can access vault {}",
+            permitted.len(),
+            vault
+        );
+        Ok(())
+    }
 
-/// Private transfer within the ephemeral rollup, then commit + undelegate.
-///
-/// The `#[commit]` attribute auto-adds `magic_context` and `magic_program`
-/// accounts and generates `commit_and_undelegate_vault(&self)`.
-#[commit]
-#[derive(Accounts)]
-pub struct PrivateTransfer<'info> {
-    #[account(
-        mut,
-        seeds = [VAULT_SEED, &vault.vault_id.to_le_bytes()],
-        bump,
-        com,  // marks this account for commit
-    )]
-    pub vault: Account<'info, VaultState>,
+    /// Temporarily suspend a permission without closing it (owner only).
+    ///
+    /// Keeps the PDA — and its rent and grant history — so the permission
+    /// can be restored later, e.g. for an employee on leave.
+    pub fn suspend_permission(ctx: Context<UpdatePermission>) -> Result<()> {
+        require!(
+            !ctx.accounts.permission.suspended,
+            ObscuraError::PermissionSuspended
+        );
+        if ctx.accounts.permission.has_acl_entry() {
+            ctx.accounts.sync_acl(ctx.bumps.vault, false)?;
+        }
 
-    #[account(mut)]
-    pub owner: Signer<'info>,
-}
+        let permission = &mut ctx.accounts.permission;
+        permission.suspended = true;
+        permission.suspended_at = Clock::get()?.unix_timestamp;
 
-/// Commit vault state to L1 without undelegating.
-#[commit]
-#[derive(Accounts)]
-pub struct CommitState<'info> {
-    #[account(
-        mut,
-        seeds = [VAULT_SEED, &vault.vault_id.to_le_bytes()],
-        bump,
-        com,
-    )]
-    pub vault: Account<'info, VaultState>,
+        msg!(
+            "Permission suspended: {} on vault {}",
+            permission.permitted,
+            permission.vault
+        );
+        Ok(())
+    }
+
+    /// Lift a suspension placed by `suspend_permission` (owner only).
+    pub fn restore_permission(ctx: Context<UpdatePermission>) -> Result<()> {
+        let permission = &mut ctx.accounts.permission;
+        require!(permission.suspended, ObscuraError::PermissionNotSuspended);
+        permission.suspended = false;
+        permission.suspended_at = 0;
+        if ctx.accounts.permission.has_acl_entry() {
+            ctx.accounts.sync_acl(ctx.bumps.vault, true)?;
+        }
+
+        let permission = &ctx.accounts.permission;
+
+        msg!(
+            "Permission restored: {} on vault {}",
+            permission.permitted,
+            permission.vault
+        );
+        Ok(())
+    }
+
+    /// Revoke a permission by closing its PDA, refunding the rent to the
+    /// granter.  The owner can revoke any permission; the permission
+    /// manager only read-only ones.
+    ///
+    /// The Access Control permission is closed in the same instruction,
+    /// so the TEE validator stops serving reads immediately.
+    pub fn revoke_permission(ctx: Context<RevokePermission>) -> Result<()> {
+        require_permission_authority(
+            &ctx.accounts.vault,
+            &ctx.accounts.owner.key(),
+            ctx.accounts.permission.scopes,
+        )?;
+        if ctx.accounts.permission.has_acl_entry() {
+            let vault_id = ctx.accounts.vault.vault_id.to_le_bytes();
+            let bump = [ctx.bumps.vault];
+            access_control::revoke(
+                &access_control::AclAccounts {
+                    program: &ctx.accounts.access_control_program,
+                    permission: &ctx.accounts.acl_permission,
+                    vault: &ctx.accounts.vault.to_account_info(),
+                    payer: &ctx.accounts.granted_by,
+                    system_program: &ctx.accounts.system_program,
+                },
+                ctx.accounts.permission.permitted,
+                &[&[VAULT_SEED, &vault_id, &bump]],
+            )?;
+        }
+
+        msg!(
+            "Permission revoked: {} on vault {}",
+            ctx.accounts.permission.permitted,
+            ctx.accounts.vault.key()
+        );
+        Ok(())
+    }
+
+    /// Close an expired permission (permissionless), refunding the rent
+    /// to the granter.
+    pub fn close_expired_permission(ctx: Context<CloseExpiredPermission>) -> Result<()> {
+        let permission = &ctx.accounts.permission;
+        require!(
+            permission.is_expired(Clock::get()?.unix_timestamp),
+            ObscuraError::PermissionNotExpired
+        );
+        if permission.has_acl_entry() {
+            let vault_id = ctx.accounts.vault.vault_id.to_le_bytes();
+            let bump = [ctx.bumps.vault];
+            access_control::revoke(
+                &access_control::AclAccounts {
+                    program: &ctx.accounts.access_control_program,
+                    permission: &ctx.accounts.acl_permission,
+                    vault: &ctx.accounts.vault.to_account_info(),
+                    payer: &ctx.accounts.granted_by,
+                    system_program: &ctx.accounts.system_program,
+                },
+                permission.permitted,
+                &[&[VAULT_SEED, &vault_id, &bump]],
+            )?;
+        }
+
+        msg!(
+            "Expired permission closed: {} on vault {}",
+            permission.permitted,
+            permission.vault
+        );
+        Ok(())
+    }
+
+    /// Appoint (or with `None`, remove) a permission manager: a key that
+    /// may grant and revoke read-only permissions but cannot move funds.
+    pub fn set_permission_manager(
+        ctx: Context<SetVaultPolicy>,
+        permission_manager: Option<Pubkey>,
+    ) -> Result<()> {
+        let vault = &mut ctx.accounts.vault;
+        vault.permission_manager = permission_manager.unwrap_or_default();
+        vault.last_activity = Clock::get()?.unix_timestamp;
+
+        msg!(
+            "Vault {} permission manager set to {}",
+            vault.vault_id,
+            vault.permission_manager
+        );
+        Ok(())
+    }
+
+    /// The vault's full policy, as exported to a policy document.
+    ///
+    /// Clients serialize this to canonical JSON (see
+    /// `docs/POLICY_DOCUMENTS.md`) for review and versioning outside the
+    /// app.  A spending limit raise still in its delay is reported at its
+    /// pending amount.
+    pub fn export_policy(ctx: Context<ExportPolicy>) -> Result<VaultPolicy> {
+        Ok(ctx.accounts.vault.policy())
+    }
+
+    /// Apply a reviewed policy document in one step (owner only).
+    ///
+    /// Every field is validated before any is written, so the vault never
+    /// ends up half-way between two policies.  The same rules as the
+    /// individual setters apply: spending limit raises wait out
+    /// `SPENDING_LIMIT_RAISE_DELAY`, and loosening the withdrawal limit
+    /// needs a matured override.  `document_hash` — the sha256 of the
+    /// canonical document — is recorded so auditors can tie the vault to
+    /// the signed document it was configured from.
+    pub fn apply_policy(
+        ctx: Context<SetVaultPolicy>,
+        policy: VaultPolicy,
+        document_hash: [u8; 32],
+    ) -> Result<()> {
+        require_valid_min_score(policy.min_validator_score)?;
+
+        let vault = &mut ctx.accounts.vault;
+        require!(!vault.is_delegated, ObscuraError::AccountDelegated);
+
+        let now = Clock::get()?.unix_timestamp;
+        let mut velocity = vault.velocity;
+        velocity.configure(policy.velocity_factor_pct, policy.velocity_cosigner)?;
+        let mut withdrawal_limit = vault.withdrawal_limit;
+        withdrawal_limit.configure(
+            policy.withdrawal_daily_cap,
+            policy.withdrawal_timelock_threshold,
+            now,
+        )?;
+
+        vault.velocity = velocity;
+        vault.withdrawal_limit = withdrawal_limit;
+        vault
+            .spending_limit
+            .set(policy.spending_limit_per_epoch, now);
+        vault.min_validator_score = policy.min_validator_score;
+        vault.fee_payment_cap = policy.fee_payment_cap;
+        vault.permission_manager = policy.permission_manager.unwrap_or_default();
+        vault.policy_hash = document_hash;
+        vault.last_activity = now;
+
+        msg!("Vault {} policy document applied", vault.vault_id);
+        Ok(())
+    }
+
+    // -----------------------------------------------------------------------
+    // Delegated authorities
+    // -----------------------------------------------------------------------
+
+    /// Grant `delegate` the right to call a whitelist of instructions on
+    /// the vault (owner only).
+    ///
+    /// `allowed_instructions` is a bitmask of `IX_*` constants.  Funds can
+    /// never be moved through a delegated authority, which makes this the
+    /// way to give a keeper the minimum power it needs.  Granting again
+    /// replaces the whitelist.
+    pub fn grant_delegated_authority(
+        ctx: Context<GrantDelegatedAuthority>,
+        delegate: Pubkey,
+        allowed_instructions: u64,
+    ) -> Result<()> {
+        require!(
+            allowed_instructions != 0 && allowed_instructions & !IX_DELEGABLE == 0,
+            ObscuraError::InvalidInstructionScope
+        );
+
+        ctx.accounts.vault.require_acknowledged_upgrade()?;
+
+        let authority = &mut ctx.accounts.delegated_authority;
+        authority.vault = ctx.accounts.vault.key();
+        authority.delegate = delegate;
+        authority.allowed_instructions = allowed_instructions;
+        authority.granted_at = Clock::get()?.unix_timestamp;
+
+        msg!(
+            "Delegated authority {} on vault {}: instructions={:#x}",
+            delegate,
+            ctx.accounts.vault.vault_id,
+            allowed_instructions
+        );
+        Ok(())
+    }
+
+    /// Revoke a delegated authority and reclaim its rent (owner only).
+    pub fn revoke_delegated_authority(ctx: Context<RevokeDelegatedAuthority>) -> Result<()> {
+        msg!(
+            "Delegated authority {} revoked on vault {}",
+            ctx.accounts.delegated_authority.delegate,
+            ctx.accounts.vault.vault_id
+        );
+        Ok(())
+    }
+
+    /// Authorize `key` to sign a whitelist of instructions on the vault
+    /// until `expires_at`, moving at most `max_amount` lamports per call
+    /// (owner only).
+    ///
+    /// Lets the mobile app keep a short-lived key in memory for ER
+    /// transactions instead of the main key.  `allowed_instructions` is a
+    /// bitmask of `IX_*` constants within `IX_SESSIONABLE`; the vault's
+    /// velocity and spending limits still apply on top.  Creating a
+    /// session key again for the same `key` replaces it.
+    pub fn create_session_key(
+        ctx: Context<CreateSessionKey>,
+        key: Pubkey,
+        expires_at: i64,
+        max_amount: u64,
+        allowed_instructions: u64,
+    ) -> Result<()> {
+        let now = Clock::get()?.unix_timestamp;
+        require!(
+            allowed_instructions != 0
+                && allowed_instructions & !IX_SESSIONABLE == 0
+                && expires_at > now
+                && expires_at <= now + MAX_SESSION_KEY_TTL
+                && key != ctx.accounts.vault.owner,
+            ObscuraError::InvalidSessionKey
+        );
+
+        ctx.accounts.session_key.set_inner(SessionKey {
+            vault: ctx.accounts.vault.key(),
+            key,
+            expires_at,
+            max_amount,
+            allowed_instructions,
+            created_at: now,
+        });
+
+        msg!(
+            "Session key {} on vault {} until {}: instructions={:#x}, max={}",
+            key,
+            ctx.accounts.vault.vault_id,
+            expires_at,
+            allowed_instructions,
+            max_amount
+        );
+        Ok(())
+    }
+
+    /// Revoke a session key and reclaim its rent (owner only).
+    pub fn revoke_session_key(ctx: Context<RevokeSessionKey>) -> Result<()> {
+        msg!(
+            "Session key {} revoked on vault {}",
+            ctx.accounts.session_key.key,
+            ctx.accounts.vault.vault_id
+        );
+        Ok(())
+    }
+
+    /// Mint a one-time capability letting `service` perform `verb` on
+    /// `object` for up to `max_amount` lamports until `expires_at` (owner
+    /// only), e.g. "top up my game account up to 0.05 SOL for 1 hour".
+    ///
+    /// Minting stands in for the co-signer's approval of every later
+    /// execution, so a vault with a co-signer needs it here.
+    pub fn mint_capability(
+        ctx: Context<MintCapability>,
+        capability_id: u64,
+        service: Pubkey,
+        verb: CapabilityVerb,
+        object: Pubkey,
+        max_amount: u64,
+        expires_at: i64,
+    ) -> Result<()> {
+        let now = Clock::get()?.unix_timestamp;
+        require!(
+            max_amount > 0
+                && expires_at > now
+                && expires_at <= now + MAX_CAPABILITY_TTL
+                && object != ctx.accounts.vault.key(),
+            ObscuraError::InvalidCapability
+        );
+        ctx.accounts
+            .vault
+            .require_cosigned(ctx.accounts.cosigner.as_ref().map(|c| c.key()))?;
+
+        ctx.accounts.capability.set_inner(Capability {
+            vault: ctx.accounts.vault.key(),
+            service,
+            capability_id,
+            verb,
+            object,
+            max_amount,
+            expires_at,
+            created_at: now,
+        });
+
+        msg!(
+            "Capability {} minted on vault {} for {}, max={} until {}",
+            capability_id,
+            ctx.accounts.vault.vault_id,
+            service,
+            max_amount,
+            expires_at
+        );
+        Ok(())
+    }
+
+    /// Consume a capability (its service only), moving `amount` out of
+    /// the vault as the capability describes.  The record is closed, so
+    /// it can be used once.
+    ///
+    /// The vault's outbound policies apply as for any other transfer,
+    /// except that the co-signer approved at `mint_capability`; a
+    /// velocity step-up can't be satisfied here.  Payouts count against
+    /// the withdrawal cap, and amounts past the withdrawal timelock
+    /// threshold are refused.
+    pub fn execute_capability(ctx: Context<ExecuteCapability>, amount: u64) -> Result<()> {
+        let capability = &ctx.accounts.capability;
+        let clock = Clock::get()?;
+        let now = clock.unix_timestamp;
+        require!(now < capability.expires_at, ObscuraError::CapabilityExpired);
+        require!(
+            amount > 0 && amount <= capability.max_amount,
+            ObscuraError::CapabilityAmountExceeded
+        );
+
+        let recipient = match capability.verb {
+            CapabilityVerb::Transfer => {
+                require!(
+                    ctx.accounts.recipient_vault.is_none(),
+                    ObscuraError::InvalidCapability
+                );
+                ctx.accounts
+                    .recipient
+                    .as_ref()
+                    .ok_or(ObscuraError::InvalidCapability)?
+                    .to_account_info()
+            }
+            CapabilityVerb::TopUpVault => ctx
+                .accounts
+                .recipient_vault
+                .as_ref()
+                .ok_or(ObscuraError::InvalidCapability)?
+                .to_account_info(),
+        };
+        require_keys_eq!(
+            recipient.key(),
+            capability.object,
+            ObscuraError::InvalidCapability
+        );
+
+        let vault = &mut ctx.accounts.vault;
+        require!(!vault.is_delegated, ObscuraError::AccountDelegated);
+        vault.require_outbound_allowed()?;
+        vault.require_acknowledged_upgrade()?;
+        vault.require_allowed_recipient(
+            ctx.accounts.recipient_allowlist.as_deref(),
+            &capability.object,
+        )?;
+        require_compliant(
+            vault,
+            ctx.accounts.denylist.as_ref(),
+            ctx.accounts.compliance_program.as_ref(),
+            ctx.remaining_accounts,
+            &capability.object,
+            amount,
+        )?;
+        require!(
+            !vault.withdrawal_limit.requires_timelock(amount),
+            ObscuraError::WithdrawalTimelocked
+        );
+        vault.velocity.observe(now, amount, None)?;
+        vault.spending_limit.record(clock.epoch, now, amount)?;
+        vault.withdrawal_limit.record(now, amount)?;
+        vault.record_transfer(amount)?;
+        vault.billing.accrue(now, amount)?;
+        vault.last_activity = now;
+
+        **vault.to_account_info().try_borrow_mut_lamports()? -= amount;
+        **recipient.try_borrow_mut_lamports()? += amount;
+        if capability.verb == CapabilityVerb::TopUpVault {
+            if let Some(recipient_vault) = ctx.accounts.recipient_vault.as_mut() {
+                recipient_vault.credit(amount)?;
+            }
+        }
+
+        msg!(
+            "Capability {} executed on vault {}: {} lamports to {}",
+            capability.capability_id,
+            vault.vault_id,
+            amount,
+            capability.object
+        );
+        Ok(())
+    }
+
+    /// Revoke an unused capability and reclaim its rent (owner only).
+    pub fn revoke_capability(ctx: Context<RevokeCapability>) -> Result<()> {
+        msg!(
+            "Capability {} revoked on vault {}",
+            ctx.accounts.capability.capability_id,
+            ctx.accounts.vault.vault_id
+        );
+        Ok(())
+    }
+
+    /// Create the caller's `OwnerIndex`, listing their vault ids so
+    /// clients can enumerate them with a single account fetch.
+    pub fn init_owner_index(ctx: Context<InitOwnerIndex>) -> Result<()> {
+        let owner_index = &mut ctx.accounts.owner_index;
+        owner_index.owner = ctx.accounts.owner.key();
+        owner_index.vault_ids = Vec::new();
+
+        msg!("Owner index created for {}", owner_index.owner);
+        Ok(())
+    }
+
+    /// Add one of the caller's existing vaults to their `OwnerIndex`
+    /// (e.g. vaults created before the index, or claimed from custody).
+    pub fn index_vault(ctx: Context<IndexVault>) -> Result<()> {
+        let vault_id = ctx.accounts.vault.vault_id;
+        ctx.accounts.owner_index.insert(vault_id)?;
+
+        msg!("Vault {} added to owner index", vault_id);
+        Ok(())
+    }
+
+    /// Remove a vault id from the caller's `OwnerIndex`.
+    pub fn unindex_vault(ctx: Context<UpdateOwnerIndex>, vault_id: u64) -> Result<()> {
+        ctx.accounts.owner_index.remove(vault_id);
+
+        msg!("Vault {} removed from owner index", vault_id);
+        Ok(())
+    }
+
+    /// Set the vault's label and owner-encrypted memo.
+    ///
+    /// Stored in a sibling `VaultMetadata` PDA so `VaultState` stays
+    /// fixed-size.  The memo is opaque to the program; clients encrypt it
+    /// to the owner before sending.
+    pub fn set_vault_metadata(
+        ctx: Context<SetVaultMetadata>,
+        label: String,
+        encrypted_memo: Vec<u8>,
+    ) -> Result<()> {
+        require!(
+            label.len() <= MAX_LABEL_LEN && encrypted_memo.len() <= MAX_ENCRYPTED_MEMO_LEN,
+            ObscuraError::MetadataTooLong
+        );
+
+        let metadata = &mut ctx.accounts.metadata;
+        metadata.vault = ctx.accounts.vault.key();
+        metadata.label = label;
+        metadata.encrypted_memo = encrypted_memo;
+        metadata.updated_at = Clock::get()?.unix_timestamp;
+
+        msg!("Metadata updated for vault {}", ctx.accounts.vault.vault_id);
+        Ok(())
+    }
+
+    // -----------------------------------------------------------------------
+    // Devices & liveness
+    // -----------------------------------------------------------------------
+
+    /// Register a device key for the vault (owner only).
+    ///
+    /// Device keys can prove liveness via `heartbeat` but cannot move funds.
+    pub fn register_device(ctx: Context<RegisterDevice>, device: Pubkey) -> Result<()> {
+        let entry = &mut ctx.accounts.device_key;
+        entry.vault = ctx.accounts.vault.key();
+        entry.device = device;
+        entry.registered_at = Clock::get()?.unix_timestamp;
+
+        msg!(
+            "Device {} registered for vault {}",
+            device,
+            ctx.accounts.vault.vault_id
+        );
+        Ok(())
+    }
+
+    /// Remove a registered device key and reclaim its rent (owner only).
+    pub fn remove_device(ctx: Context<RemoveDevice>) -> Result<()> {
+        msg!(
+            "Device {} removed from vault {}",
+            ctx.accounts.device_key.device,
+            ctx.accounts.vault.vault_id
+        );
+        Ok(())
+    }
+
+    /// Prove the owner is still around without moving funds.
+    ///
+    /// Callable by the owner, a registered device key or a delegated
+    /// authority holding `IX_HEARTBEAT`; only bumps `last_activity`, which
+    /// the inactivity-based features read.  Works on L1 or inside the ER,
+    /// wherever the vault currently lives.
+    pub fn heartbeat(ctx: Context<Heartbeat>) -> Result<()> {
+        let authority = ctx.accounts.authority.key();
+        let is_device = ctx
+            .accounts
+            .device_key
+            .as_ref()
+            .is_some_and(|d| d.device == authority);
+        if !is_device {
+            require_owner_or_delegate(
+                &ctx.accounts.vault,
+                &authority,
+                ctx.accounts.delegated_authority.as_deref(),
+                IX_HEARTBEAT,
+            )?;
+        }
+
+        let vault = &mut ctx.accounts.vault;
+        vault.last_activity = Clock::get()?.unix_timestamp;
+
+        msg!("Heartbeat for vault {}", vault.vault_id);
+        Ok(())
+    }
+
+    /// Post a session bundle, encrypted to `to_device`, for that device
+    /// to pick up ("continue on tablet").
+    ///
+    /// Both devices must be registered to the vault.  There is one
+    /// handoff slot per target device; it can be picked up for
+    /// `SESSION_HANDOFF_TTL`.
+    pub fn post_session_handoff(ctx: Context<PostSessionHandoff>, bundle: Vec<u8>) -> Result<()> {
+        require!(
+            !bundle.is_empty() && bundle.len() <= MAX_SESSION_BUNDLE_LEN,
+            ObscuraError::InvalidSessionBundle
+        );
+
+        let now = Clock::get()?.unix_timestamp;
+        ctx.accounts.handoff.set_inner(SessionHandoff {
+            vault: ctx.accounts.vault.key(),
+            from_device: ctx.accounts.from_device.key(),
+            to_device: ctx.accounts.to_device_key.device,
+            bundle,
+            posted_at: now,
+            expires_at: now + SESSION_HANDOFF_TTL,
+        });
+
+        msg!(
+            "Session handoff posted on vault {}: {} -> {}",
+            ctx.accounts.vault.vault_id,
+            ctx.accounts.from_device.key(),
+            ctx.accounts.to_device_key.device
+        );
+        Ok(())
+    }
+
+    /// Acknowledge a picked-up session handoff (target device only),
+    /// closing it and refunding the rent to the posting device.
+    pub fn acknowledge_session_handoff(ctx: Context<AcknowledgeSessionHandoff>) -> Result<()> {
+        let handoff = &ctx.accounts.handoff;
+        require!(
+            Clock::get()?.unix_timestamp < handoff.expires_at,
+            ObscuraError::SessionHandoffExpired
+        );
+
+        msg!(
+            "Session handoff acknowledged on vault {}: {} -> {}",
+            ctx.accounts.vault.vault_id,
+            handoff.from_device,
+            handoff.to_device
+        );
+        Ok(())
+    }
+
+    /// Withdraw a session handoff: the posting device can at any time,
+    /// anyone once it has expired.  Rent goes back to the posting device.
+    pub fn close_session_handoff(ctx: Context<CloseSessionHandoff>) -> Result<()> {
+        let handoff = &ctx.accounts.handoff;
+        require!(
+            ctx.accounts.closer.key() == handoff.from_device
+                || Clock::get()?.unix_timestamp >= handoff.expires_at,
+            ObscuraError::SessionHandoffActive
+        );
+
+        msg!(
+            "Session handoff closed on vault {}: {} -> {}",
+            handoff.vault,
+            handoff.from_device,
+            handoff.to_device
+        );
+        Ok(())
+    }
+
+    // -----------------------------------------------------------------------
+    // History view
+    // -----------------------------------------------------------------------
+
+    /// Create the vault's read-only history view.
+    ///
+    /// The view only holds per-month counts of inflows and outflows by
+    /// coarse amount bucket — no amounts, counterparties or timestamps —
+    /// so it can be shared with compliance viewers instead of the vault.
+    /// `deposit`, `withdraw` and `private_transfer` update it whenever it
+    /// is passed alongside the vault.
+    pub fn init_history_view(ctx: Context<InitHistoryView>) -> Result<()> {
+        let history_view = &mut ctx.accounts.history_view;
+        history_view.vault = ctx.accounts.vault.key();
+        history_view.months = [HistoryMonth::default(); HISTORY_MONTHS];
+
+        msg!(
+            "History view created for vault {}",
+            ctx.accounts.vault.vault_id
+        );
+        Ok(())
+    }
+
+    /// Delegate the history view to the same validator as its vault.
+    ///
+    /// Call this before `delegate_vault` (in the same transaction) so the
+    /// view is private under PER, readable only by keys permitted on it,
+    /// and can be updated by `private_transfer` inside the ER.
+    pub fn delegate_history_view(
+        ctx: Context<DelegateHistoryView>,
+        validator: Pubkey,
+    ) -> Result<()> {
+        let vault_key = ctx.accounts.vault.key();
+
+        msg!(
+            "Delegating history view of vault {} to validator {}",
+            ctx.accounts.vault.vault_id,
+            validator
+        );
+
+        ctx.accounts.delegate_history_view(
+            &ctx.accounts.owner,
+            &[HISTORY_SEED, vault_key.as_ref()],
+            validator,
+        )?;
+
+        Ok(())
+    }
+
+    // -----------------------------------------------------------------------
+    // Governance
+    // -----------------------------------------------------------------------
+
+    /// Initialize the singleton program config.
+    ///
+    /// Only the program's upgrade authority may call this, which stops a
+    /// front-runner from installing their own governance key.
+    pub fn initialize_config(ctx: Context<InitializeConfig>, governance: Pubkey) -> Result<()> {
+        let config = &mut ctx.accounts.config;
+        config.governance = governance;
+        config.keeper = Pubkey::default();
+        config.claim_attestor = Pubkey::default();
+        config.dex_adapter = Pubkey::default();
+        config.feature_flags = 0;
+        config.inactivity_undelegate_after = 0;
+        config.receipt_tree = Pubkey::default();
+        config.attestation_tree = Pubkey::default();
+
+        msg!("Program config initialized: governance={}", governance);
+        Ok(())
+    }
+
+    /// Set the keeper allowed to submit validator observations (governance only).
+    pub fn set_keeper(ctx: Context<UpdateConfig>, keeper: Pubkey) -> Result<()> {
+        ctx.accounts.config.keeper = keeper;
+
+        msg!("Keeper set to {}", keeper);
+        Ok(())
+    }
+
+    /// Set the operator key that attests contact-identifier ownership
+    /// for `claim_contact_vault`.
+    pub fn set_claim_attestor(ctx: Context<UpdateConfig>, claim_attestor: Pubkey) -> Result<()> {
+        ctx.accounts.config.claim_attestor = claim_attestor;
+
+        msg!("Claim attestor set to {}", claim_attestor);
+        Ok(())
+    }
+
+    /// Set the DEX adapter program that limit orders are routed through.
+    pub fn set_dex_adapter(ctx: Context<UpdateConfig>, dex_adapter: Pubkey) -> Result<()> {
+        ctx.accounts.config.dex_adapter = dex_adapter;
+
+        msg!("DEX adapter set to {}", dex_adapter);
+        Ok(())
+    }
+
+    /// Set how long an owner may be inactive before the keeper can sweep
+    /// their vault out of the ER (0 disables the sweep).
+    pub fn set_inactivity_undelegate_after(
+        ctx: Context<UpdateConfig>,
+        inactivity_undelegate_after: i64,
+    ) -> Result<()> {
+        require!(
+            inactivity_undelegate_after == 0
+                || inactivity_undelegate_after >= MIN_INACTIVITY_UNDELEGATE_AFTER,
+            ObscuraError::InvalidInactivityThreshold
+        );
+        ctx.accounts.config.inactivity_undelegate_after = inactivity_undelegate_after;
+
+        msg!(
+            "Inactivity undelegate threshold set to {}s",
+            inactivity_undelegate_after
+        );
+        Ok(())
+    }
+
+    /// Set the Bubblegum tree that escrow and invoice receipts are minted
+    /// into (default = receipts disabled).  Its tree delegate must be the
+    /// `RECEIPT_AUTHORITY_SEED` PDA.
+    pub fn set_receipt_tree(ctx: Context<UpdateConfig>, receipt_tree: Pubkey) -> Result<()> {
+        ctx.accounts.config.receipt_tree = receipt_tree;
+
+        msg!("Receipt tree set to {}", receipt_tree);
+        Ok(())
+    }
+
+    /// Set the Bubblegum tree counterparty ratings are minted into
+    /// (default = ratings disabled).  Its tree delegate must be the
+    /// `RECEIPT_AUTHORITY_SEED` PDA.
+    pub fn set_attestation_tree(
+        ctx: Context<UpdateConfig>,
+        attestation_tree: Pubkey,
+    ) -> Result<()> {
+        ctx.accounts.config.attestation_tree = attestation_tree;
+
+        msg!("Attestation tree set to {}", attestation_tree);
+        Ok(())
+    }
+
+    /// Queue a config change (governance only).  It becomes executable
+    /// after `CONFIG_CHANGE_DELAY`, unless vault owners veto it with
+    /// `vote_on_config_change` in the meantime.
+    pub fn queue_config_change(
+        ctx: Context<QueueConfigChange>,
+        change_id: u64,
+        change: ConfigChange,
+    ) -> Result<()> {
+        change.validate()?;
+
+        let now = Clock::get()?.unix_timestamp;
+        let queued = &mut ctx.accounts.config_change;
+        queued.change_id = change_id;
+        queued.change = change;
+        queued.queued_at = now;
+        queued.executable_at = now + CONFIG_CHANGE_DELAY;
+        queued.weight_for = 0;
+        queued.weight_against = 0;
+        queued.voters = 0;
+
+        msg!(
+            "Config change {} queued, executable at {}",
+            change_id,
+            queued.executable_at
+        );
+        Ok(())
+    }
+
+    /// Vote for or against a queued config change with a vault, once per
+    /// vault.  The vote weighs the vault's age in days when the change
+    /// was queued, up to `MAX_VOTE_WEIGHT`, so vaults opened to vote on a
+    /// change don't count.
+    pub fn vote_on_config_change(ctx: Context<VoteOnConfigChange>, support: bool) -> Result<()> {
+        let vault = &ctx.accounts.vault;
+        let queued = &mut ctx.accounts.config_change;
+        require!(
+            Clock::get()?.unix_timestamp < queued.executable_at,
+            ObscuraError::VotingClosed
+        );
+        let weight = vote_weight(vault.created_at, queued.queued_at);
+        require!(weight > 0, ObscuraError::NotEligibleToVote);
+
+        if support {
+            queued.weight_for = queued.weight_for.saturating_add(weight);
+        } else {
+            queued.weight_against = queued.weight_against.saturating_add(weight);
+        }
+        queued.voters = queued.voters.saturating_add(1);
+
+        let record = &mut ctx.accounts.vote_record;
+        record.config_change = queued.key();
+        record.vault = vault.key();
+        record.voter = ctx.accounts.owner.key();
+        record.support = support;
+        record.weight = weight;
+
+        msg!(
+            "Vault {} voted {} config change {} with weight {}",
+            vault.vault_id,
+            if support { "for" } else { "against" },
+            queued.change_id,
+            weight
+        );
+        Ok(())
+    }
+
+    /// Apply a queued config change once its delay has passed
+    /// (permissionless), unless the vote tally vetoed it: at least
+    /// `VETO_WEIGHT` against and more against than for.
+    pub fn execute_config_change(ctx: Context<ExecuteConfigChange>) -> Result<()> {
+        let queued = &ctx.accounts.config_change;
+        require!(
+            Clock::get()?.unix_timestamp >= queued.executable_at,
+            ObscuraError::ConfigChangeTimelocked
+        );
+        require!(!queued.is_vetoed(), ObscuraError::ConfigChangeVetoed);
+
+        ctx.accounts.config.apply(&queued.change);
+
+        msg!(
+            "Config change {} executed ({} for, {} against, {} voters)",
+            queued.change_id,
+            queued.weight_for,
+            queued.weight_against,
+            queued.voters
+        );
+        Ok(())
+    }
+
+    /// Drop a queued config change without applying it (governance only).
+    pub fn cancel_config_change(ctx: Context<CancelConfigChange>) -> Result<()> {
+        msg!(
+            "Config change {} cancelled",
+            ctx.accounts.config_change.change_id
+        );
+        Ok(())
+    }
+
+    /// Close a vote record once its config change was executed or
+    /// cancelled, refunding its rent to the voter (permissionless).
+    pub fn close_vote_record(ctx: Context<CloseVoteRecord>) -> Result<()> {
+        require!(
+            ctx.accounts.config_change.data_is_empty(),
+            ObscuraError::ConfigChangeOpen
+        );
+
+        msg!(
+            "Vote record of vault {} closed",
+            ctx.accounts.vote_record.vault
+        );
+        Ok(())
+    }
+
+    /// Create the safety fund that receives early-break fees.
+    pub fn init_safety_fund(ctx: Context<InitSafetyFund>) -> Result<()> {
+        ctx.accounts.safety_fund.total_received = 0;
+
+        msg!("Safety fund initialized");
+        Ok(())
+    }
+
+    /// Replace the cluster's feature flag bitfield (governance only).
+    ///
+    /// Experimental instructions check their `FEATURE_*` bit, so they can
+    /// ship dark and be enabled per cluster without a separate deploy.
+    pub fn set_feature_flags(ctx: Context<UpdateConfig>, feature_flags: u64) -> Result<()> {
+        let config = &mut ctx.accounts.config;
+        let previous = config.feature_flags;
+        config.feature_flags = feature_flags;
+
+        msg!(
+            "Feature flags changed: {:#x} -> {:#x}",
+            previous,
+            feature_flags
+        );
+        Ok(())
+    }
+
+    /// Record the deployed release in the `ProgramVersionInfo` PDA
+    /// (governance only; run after every deploy).
+    ///
+    /// Clients compare it with the version they were built against to
+    /// detect skew and gate UI features on `features_hash`.
+    pub fn publish_program_version(
+        ctx: Context<PublishProgramVersion>,
+        major: u16,
+        minor: u16,
+        patch: u16,
+        features_hash: [u8; 32],
+    ) -> Result<()> {
+        let info = &mut ctx.accounts.version_info;
+        require!(
+            (major, minor, patch) > (info.major, info.minor, info.patch),
+            ObscuraError::VersionNotIncreasing
+        );
+
+        info.major = major;
+        info.minor = minor;
+        info.patch = patch;
+        info.release = PROGRAM_VERSION;
+        info.upgrade_slot = ctx.accounts.program_data.slot;
+        info.features_hash = features_hash;
+        info.published_at = Clock::get()?.unix_timestamp;
+
+        msg!(
+            "Program version {}.{}.{} (release {}) deployed at slot {}",
+            major,
+            minor,
+            patch,
+            PROGRAM_VERSION,
+            info.upgrade_slot
+        );
+        Ok(())
+    }
+
+    /// Add a validator to the registry (governance only).
+    ///
+    /// `sdk_version` selects the delegation program the validator speaks;
+    /// see the `delegation` adapter module.
+    pub fn register_validator(
+        ctx: Context<RegisterValidator>,
+        validator: Pubkey,
+        sdk_version: u8,
+    ) -> Result<()> {
+        delegation::delegation_program_id(sdk_version)?;
+
+        let entry = &mut ctx.accounts.validator_info;
+        entry.validator = validator;
+        entry.sdk_version = sdk_version;
+        entry.is_defunct = false;
+        entry.defunct_at = 0;
+        entry.registered_at = Clock::get()?.unix_timestamp;
+        entry.rate_card = RateCard::default();
+
+        msg!("Validator registered: {}", validator);
+        Ok(())
+    }
+
+    /// Move a registered validator to another delegation SDK version
+    /// (governance only).
+    ///
+    /// Vaults already delegated keep the version they were delegated under.
+    pub fn set_validator_sdk_version(
+        ctx: Context<UpdateValidatorInfo>,
+        sdk_version: u8,
+    ) -> Result<()> {
+        delegation::delegation_program_id(sdk_version)?;
+
+        let entry = &mut ctx.accounts.validator_info;
+        entry.sdk_version = sdk_version;
+
+        msg!(
+            "Validator {} moved to delegation SDK v{}",
+            entry.validator,
+            sdk_version
+        );
+        Ok(())
+    }
+
+    /// Publish the validator's rate card (signed by the validator key).
+    ///
+    /// Vaults accept the card in force when they delegate and keep those
+    /// rates until they undelegate, so a new card only applies to new
+    /// delegations.
+    pub fn set_rate_card(ctx: Context<SetRateCard>, rate_card: RateCard) -> Result<()> {
+        require!(
+            rate_card.payout != Pubkey::default()
+                || (rate_card.commit_fee == 0 && rate_card.monthly_delegation_fee == 0),
+            ObscuraError::InvalidRateCard
+        );
+        let entry = &mut ctx.accounts.validator_info;
+        entry.rate_card = rate_card;
+
+        msg!(
+            "Validator {} rate card: {} per commit, {} per {}s delegated, paid to {}",
+            entry.validator,
+            rate_card.commit_fee,
+            rate_card.monthly_delegation_fee,
+            RATE_CARD_PERIOD,
+            rate_card.payout
+        );
+        Ok(())
+    }
+
+    /// Pay the operator fees a vault owes out of its balance
+    /// (permissionless, on L1).  Pays what the unlocked balance covers.
+    pub fn settle_operator_fees(ctx: Context<SettleOperatorFees>) -> Result<()> {
+        let vault = &mut ctx.accounts.vault;
+        require!(!vault.is_delegated, ObscuraError::AccountDelegated);
+        let amount = vault
+            .operator_fees
+            .owed
+            .min(vault.balance.saturating_sub(vault.locked_balance));
+        require!(amount > 0, ObscuraError::NoOperatorFees);
+
+        vault.balance -= amount;
+        vault.operator_fees.owed -= amount;
+
+        **vault.to_account_info().try_borrow_mut_lamports()? -= amount;
+        **ctx.accounts.payout.try_borrow_mut_lamports()? += amount;
+
+        msg!(
+            "Vault {} paid {} lamports of operator fees, {} still owed",
+            vault.vault_id,
+            amount,
+            vault.operator_fees.owed
+        );
+        Ok(())
+    }
+
+    /// Mark a registered validator as defunct (governance only).
+    ///
+    /// This starts the public `FORCE_SETTLE_DELAY` window.  Vaults delegated
+    /// to the validator can only be force-settled once it has elapsed,
+    /// giving the operator and users time to react.
+    pub fn mark_validator_defunct(ctx: Context<UpdateValidatorInfo>) -> Result<()> {
+        let entry = &mut ctx.accounts.validator_info;
+        require!(!entry.is_defunct, ObscuraError::ValidatorDefunct);
+
+        let now = Clock::get()?.unix_timestamp;
+        entry.is_defunct = true;
+        entry.defunct_at = now;
+
+        msg!(
+            "Validator {} marked defunct; force settlement possible after {}",
+            entry.validator,
+            now + FORCE_SETTLE_DELAY
+        );
+        Ok(())
+    }
+
+    /// Force-settle a vault stranded on a defunct validator (governance only).
+    ///
+    /// Clears the vault's delegation metadata so the owner can withdraw on
+    /// L1 again.  The vault account must already be owned by this program
+    /// (i.e. released by the delegation program), and the validator it was
+    /// delegated to must have been defunct for at least `FORCE_SETTLE_DELAY`.
+    ///
+    /// This does not take a vault back from the delegation program: its
+    /// `undelegate` must be signed by the validator, and it has no
+    /// escape hatch this program could CPI into, so a vault still owned by
+    /// the delegation program under a dead validator fails here with
+    /// `AccountOwnedByWrongProgram` until MagicBlock releases it.
+    pub fn admin_force_settle(ctx: Context<AdminForceSettle>) -> Result<()> {
+        let entry = &ctx.accounts.validator_info;
+        let vault = &mut ctx.accounts.vault;
+
+        require!(vault.is_delegated, ObscuraError::NotDelegated);
+        require!(entry.is_defunct, ObscuraError::ValidatorNotDefunct);
+
+        let clock = Clock::get()?;
+        let now = clock.unix_timestamp;
+        require!(
+            now >= entry.defunct_at + FORCE_SETTLE_DELAY,
+            ObscuraError::ForceSettleDelayActive
+        );
+
+        vault.is_delegated = false;
+        vault.delegate_validator = Pubkey::default();
+        vault.is_private = false;
+        vault.last_activity = now;
+        vault.last_settled_slot = clock.slot;
+        vault.operator_fees.clear_rates();
+
+        msg!(
+            "Vault {} force-settled from defunct validator {}",
+            vault.vault_id,
+            entry.validator
+        );
+        Ok(())
+    }
+
+    /// Whether `sweep_inactive_vault` would currently accept the vault.
+    ///
+    /// The keeper simulates this against every delegated vault to find
+    /// the ones to sweep.
+    pub fn check_undelegate_eligibility(ctx: Context<CheckUndelegateEligibility>) -> Result<bool> {
+        let now = Clock::get()?.unix_timestamp;
+        Ok(ctx
+            .accounts
+            .vault
+            .inactive_for(now, ctx.accounts.config.inactivity_undelegate_after))
+    }
+
+    /// Undelegate a vault whose owner has been inactive for longer than
+    /// `ProgramConfig::inactivity_undelegate_after` (keeper only; runs in
+    /// the ER).
+    ///
+    /// Bounds how much state a long-term validator compromise can reach:
+    /// abandoned vaults are settled back to L1 instead of staying on the
+    /// validator indefinitely.  Funds do not move and `last_activity` is
+    /// left as is; the owner can delegate again at any time.
+    pub fn sweep_inactive_vault(ctx: Context<SweepInactiveVault>) -> Result<()> {
+        let now = Clock::get()?.unix_timestamp;
+        let vault = &mut ctx.accounts.vault;
+        require!(
+            vault.inactive_for(now, ctx.accounts.config.inactivity_undelegate_after),
+            ObscuraError::VaultNotInactive
+        );
+
+        vault.is_delegated = false;
+        vault.delegate_validator = Pubkey::default();
+        vault.is_private = false;
+        vault.operator_fees.stop(now)?;
+
+        msg!(
+            "Sweeping inactive vault {} (last active {})",
+            vault.vault_id,
+            vault.last_activity
+        );
+
+        let sdk_version = vault.delegation_sdk_version;
+        ctx.accounts.vault.exit(&crate::ID)?;
+        delegation::commit_and_undelegate(
+            sdk_version,
+            &ctx.accounts.keeper,
+            vec![&ctx.accounts.vault.to_account_info()],
+            &ctx.accounts.magic_context,
+            &ctx.accounts.magic_program,
+        )?;
+
+        Ok(())
+    }
+
+    // -----------------------------------------------------------------------
+    // Invoiced billing
+    // -----------------------------------------------------------------------
+
+    /// Put a vault on invoiced billing at `fee_bps` (0 turns it off).
+    ///
+    /// Requires both governance and the vault owner.  Fees are only
+    /// recorded — nothing is deducted from the vault — and are billed
+    /// from the `FeeStatement`s produced by `close_fee_statement`.
+    pub fn set_invoiced_billing(ctx: Context<SetInvoicedBilling>, fee_bps: u16) -> Result<()> {
+        require!(
+            fee_bps as u64 <= BPS_DENOMINATOR,
+            ObscuraError::InvalidFeeRate
+        );
+
+        let vault = &mut ctx.accounts.vault;
+        require!(!vault.is_delegated, ObscuraError::AccountDelegated);
+        vault.billing.fee_bps = fee_bps;
+
+        msg!(
+            "Vault {} invoiced billing set to {} bps",
+            vault.vault_id,
+            fee_bps
+        );
+        Ok(())
+    }
+
+    /// Close out the vault's oldest finished billing month into a
+    /// `FeeStatement` PDA (governance only).
+    ///
+    /// Statements are keyed by (vault, month) and never change once
+    /// written, so operator and customer reconcile against the same
+    /// numbers.  If a month was missed, its fees roll into the next
+    /// statement closed.
+    ///
+    /// For vaults created through a registered integrator, the
+    /// integrator's share of the statement is accrued for the next
+    /// `settle_integrator_fees`.
+    pub fn close_fee_statement(ctx: Context<CloseFeeStatement>, period: u32) -> Result<()> {
+        let vault = &mut ctx.accounts.vault;
+        require!(!vault.is_delegated, ObscuraError::AccountDelegated);
+        require!(
+            vault.integrator == Pubkey::default() || ctx.accounts.integrator.is_some(),
+            ObscuraError::InvalidIntegrator
+        );
+
+        let now = Clock::get()?.unix_timestamp;
+        vault.billing.roll(now);
+        require!(
+            vault.billing.pending_ops > 0 && vault.billing.pending_period == period,
+            ObscuraError::NoFeesToClose
+        );
+
+        let statement = &mut ctx.accounts.fee_statement;
+        statement.vault = vault.key();
+        statement.period = period;
+        statement.fee_bps = vault.billing.fee_bps;
+        statement.fees = vault.billing.pending_fees;
+        statement.operations = vault.billing.pending_ops;
+        statement.closed_at = now;
+        statement.integrator = vault.integrator;
+        statement.integrator_share = match ctx.accounts.integrator.as_mut() {
+            Some(integrator) => integrator.accrue(statement.fees)?,
+            None => 0,
+        };
+
+        vault.billing.pending_period = 0;
+        vault.billing.pending_fees = 0;
+        vault.billing.pending_ops = 0;
+
+        msg!(
+            "Fee statement closed: vault {} period {} fees={} ops={} integrator_share={}",
+            vault.vault_id,
+            period,
+            statement.fees,
+            statement.operations,
+            statement.integrator_share
+        );
+        Ok(())
+    }
+
+    /// Register a third-party app under `app_id` with a `share_bps`
+    /// revenue share of the protocol fees billed to vaults created
+    /// through it.
+    ///
+    /// Requires both governance and the integrator's authority, whose
+    /// `payout_vault` receives the monthly settlements.
+    pub fn register_integrator(
+        ctx: Context<RegisterIntegrator>,
+        app_id: u64,
+        share_bps: u16,
+    ) -> Result<()> {
+        require!(
+            share_bps as u64 <= BPS_DENOMINATOR,
+            ObscuraError::InvalidFeeRate
+        );
+
+        ctx.accounts.integrator.set_inner(Integrator {
+            app_id,
+            authority: ctx.accounts.authority.key(),
+            payout_vault: ctx.accounts.payout_vault.key(),
+            share_bps,
+            accrued: 0,
+            settled_period: 0,
+            total_paid: 0,
+            registered_at: Clock::get()?.unix_timestamp,
+        });
+
+        msg!(
+            "Integrator registered: app {} share={} bps payout vault {}",
+            app_id,
+            share_bps,
+            ctx.accounts.payout_vault.vault_id
+        );
+        Ok(())
+    }
+
+    /// Change an integrator's revenue share (governance only).  Applies
+    /// to statements closed from now on; 0 stops further accrual.
+    pub fn set_integrator_share(ctx: Context<UpdateIntegrator>, share_bps: u16) -> Result<()> {
+        require!(
+            share_bps as u64 <= BPS_DENOMINATOR,
+            ObscuraError::InvalidFeeRate
+        );
+
+        let integrator = &mut ctx.accounts.integrator;
+        integrator.share_bps = share_bps;
+
+        msg!(
+            "Integrator {} share set to {} bps",
+            integrator.app_id,
+            share_bps
+        );
+        Ok(())
+    }
+
+    /// Pay an integrator's accrued revenue share into its payout vault
+    /// (governance only, at most once per calendar month).
+    ///
+    /// Invoiced fees are collected off-chain, so governance funds the
+    /// payout; the vault is credited like a deposit.
+    pub fn settle_integrator_fees(ctx: Context<SettleIntegratorFees>) -> Result<()> {
+        let now = Clock::get()?.unix_timestamp;
+        let period = month_index(now);
+        let integrator = &mut ctx.accounts.integrator;
+        require!(
+            integrator.accrued > 0 && integrator.settled_period != period,
+            ObscuraError::NoIntegratorFees
+        );
+
+        let payout_vault = &mut ctx.accounts.payout_vault;
+        require!(!payout_vault.is_delegated, ObscuraError::AccountDelegated);
+
+        let amount = integrator.accrued;
+        let ix = anchor_lang::solana_program::system_instruction::transfer(
+            &ctx.accounts.governance.key(),
+            &payout_vault.key(),
+            amount,
+        );
+        anchor_lang::solana_program::program::invoke(
+            &ix,
+            &[
+                ctx.accounts.governance.to_account_info(),
+                payout_vault.to_account_info(),
+                ctx.accounts.system_program.to_account_info(),
+            ],
+        )?;
+        payout_vault.credit(amount)?;
+        payout_vault.last_activity = now;
+
+        integrator.accrued = 0;
+        integrator.settled_period = period;
+        integrator.total_paid = integrator.total_paid.saturating_add(amount);
+
+        msg!(
+            "Integrator {} paid {} lamports into vault {}",
+            integrator.app_id,
+            amount,
+            payout_vault.vault_id
+        );
+        Ok(())
+    }
+
+    /// Block `recipient` for vaults created through the integrator
+    /// (integrator authority only).  Checked by `private_transfer`,
+    /// `withdraw` and `execute_pending_withdrawal`.
+    pub fn add_denied_recipient(ctx: Context<UpdateDenylist>, recipient: Pubkey) -> Result<()> {
+        let denylist = &mut ctx.accounts.denylist;
+        require!(
+            !denylist.recipients.contains(&recipient)
+                && denylist.recipients.len() < MAX_DENIED_RECIPIENTS,
+            ObscuraError::InvalidDenylist
+        );
+        denylist.integrator = ctx.accounts.integrator.key();
+        denylist.recipients.push(recipient);
+
+        msg!(
+            "Recipient {} denied by integrator {}",
+            recipient,
+            ctx.accounts.integrator.app_id
+        );
+        Ok(())
+    }
+
+    /// Remove `recipient` from the integrator's denylist.
+    pub fn remove_denied_recipient(ctx: Context<UpdateDenylist>, recipient: Pubkey) -> Result<()> {
+        let denylist = &mut ctx.accounts.denylist;
+        let position = denylist
+            .recipients
+            .iter()
+            .position(|r| *r == recipient)
+            .ok_or(ObscuraError::InvalidDenylist)?;
+        denylist.recipients.remove(position);
+
+        msg!(
+            "Recipient {} removed from integrator {} denylist",
+            recipient,
+            ctx.accounts.integrator.app_id
+        );
+        Ok(())
+    }
+
+    /// Set the compliance program called before outbound transfers from
+    /// the integrator's vaults (integrator authority only; default =
+    /// none).  See `compliance` for the hook interface.
+    pub fn set_compliance_program(
+        ctx: Context<UpdateDenylist>,
+        compliance_program: Pubkey,
+    ) -> Result<()> {
+        require_keys_neq!(
+            compliance_program,
+            crate::ID,
+            ObscuraError::InvalidComplianceProgram
+        );
+
+        let denylist = &mut ctx.accounts.denylist;
+        denylist.integrator = ctx.accounts.integrator.key();
+        denylist.compliance_program = compliance_program;
+
+        msg!(
+            "Integrator {} compliance program set to {}",
+            ctx.accounts.integrator.app_id,
+            compliance_program
+        );
+        Ok(())
+    }
+
+    // -----------------------------------------------------------------------
+    // Validator scoring
+    // -----------------------------------------------------------------------
+
+    /// Record a keeper observation of a registered validator.
+    ///
+    /// Observations are folded into exponentially weighted averages, from
+    /// which a 0..=`MAX_VALIDATOR_SCORE` score is derived.  Clients read the
+    /// `ValidatorScore` account when choosing where to delegate.
+    pub fn submit_validator_observation(
+        ctx: Context<SubmitValidatorObservation>,
+        commit_latency_ms: u32,
+        uptime_bps: u16,
+    ) -> Result<()> {
+        require!(
+            uptime_bps <= MAX_VALIDATOR_SCORE,
+            ObscuraError::InvalidObservation
+        );
+
+        let score = &mut ctx.accounts.validator_score;
+        if score.observations == 0 {
+            score.validator = ctx.accounts.validator_info.validator;
+            score.avg_commit_latency_ms = commit_latency_ms;
+            score.uptime_bps = uptime_bps;
+        } else {
+            // EWMA with alpha = 1/8
+            score.avg_commit_latency_ms =
+                ((score.avg_commit_latency_ms as u64 * 7 + commit_latency_ms as u64) / 8) as u32;
+            score.uptime_bps = ((score.uptime_bps as u32 * 7 + uptime_bps as u32) / 8) as u16;
+        }
+        score.observations = score.observations.saturating_add(1);
+        score.last_observed_at = Clock::get()?.unix_timestamp;
+
+        // Uptime scaled down proportionally once latency exceeds the target.
+        let latency = score.avg_commit_latency_ms.max(SCORE_LATENCY_TARGET_MS);
+        score.score =
+            (score.uptime_bps as u64 * SCORE_LATENCY_TARGET_MS as u64 / latency as u64) as u16;
+
+        msg!(
+            "Validator {} observed: latency={}ms uptime={}bps score={}",
+            score.validator,
+            score.avg_commit_latency_ms,
+            score.uptime_bps,
+            score.score
+        );
+        Ok(())
+    }
+
+    /// Require delegation targets to have at least `min_score` (0 disables).
+    pub fn set_min_validator_score(ctx: Context<SetVaultPolicy>, min_score: u16) -> Result<()> {
+        require_valid_min_score(min_score)?;
+
+        let vault = &mut ctx.accounts.vault;
+        require!(!vault.is_delegated, ObscuraError::AccountDelegated);
+        vault.min_validator_score = min_score;
+        vault.last_activity = Clock::get()?.unix_timestamp;
+
+        msg!(
+            "Vault {} minimum validator score set to {}",
+            vault.vault_id,
+            min_score
+        );
+        Ok(())
+    }
+
+    // -----------------------------------------------------------------------
+    // Savings goals
+    // -----------------------------------------------------------------------
+
+    /// Lock `amount` of the vault's balance until `unlock_at`.
+    ///
+    /// Locked lamports stay in the vault but cannot be spent by any
+    /// instruction; `release_goal` unlocks them after `unlock_at`, and
+    /// `break_goal` unlocks them early for a `GOAL_BREAK_FEE_BPS` fee paid
+    /// to the safety fund.
+    pub fn create_goal(
+        ctx: Context<CreateGoal>,
+        goal_id: u64,
+        amount: u64,
+        unlock_at: i64,
+    ) -> Result<()> {
+        let vault = &mut ctx.accounts.vault;
+        require!(!vault.is_delegated, ObscuraError::AccountDelegated);
+        require!(
+            amount > 0 && vault.spendable() >= amount,
+            ObscuraError::InsufficientBalance
+        );
+        let now = Clock::get()?.unix_timestamp;
+        require!(unlock_at > now, ObscuraError::InvalidGoal);
+
+        vault.locked_balance += amount;
+        vault.last_activity = now;
+
+        let goal = &mut ctx.accounts.goal;
+        goal.vault = vault.key();
+        goal.goal_id = goal_id;
+        goal.amount = amount;
+        goal.unlock_at = unlock_at;
+        goal.created_at = now;
+
+        msg!(
+            "Savings goal {} on vault {}: {} lamports until {}",
+            goal_id,
+            vault.vault_id,
+            amount,
+            unlock_at
+        );
+        Ok(())
+    }
+
+    /// Unlock a matured savings goal.
+    pub fn release_goal(ctx: Context<CloseGoal>) -> Result<()> {
+        let now = Clock::get()?.unix_timestamp;
+        let goal = &ctx.accounts.goal;
+        require!(now >= goal.unlock_at, ObscuraError::GoalLocked);
+
+        let vault = &mut ctx.accounts.vault;
+        require!(!vault.is_delegated, ObscuraError::AccountDelegated);
+        vault.locked_balance = vault.locked_balance.saturating_sub(goal.amount);
+        vault.last_activity = now;
+
+        msg!(
+            "Savings goal {} released on vault {}",
+            goal.goal_id,
+            vault.vault_id
+        );
+        Ok(())
+    }
+
+    /// Break a savings goal before it matures, paying the early-break fee
+    /// to the safety fund.
+    pub fn break_goal(ctx: Context<BreakGoal>) -> Result<()> {
+        let now = Clock::get()?.unix_timestamp;
+        let goal = &ctx.accounts.goal;
+        require!(now < goal.unlock_at, ObscuraError::InvalidGoal);
+
+        let fee =
+            (goal.amount as u128 * GOAL_BREAK_FEE_BPS as u128 / BPS_DENOMINATOR as u128) as u64;
+        let vault = &mut ctx.accounts.vault;
+        require!(!vault.is_delegated, ObscuraError::AccountDelegated);
+        vault.locked_balance = vault.locked_balance.saturating_sub(goal.amount);
+        vault.debit(fee)?;
+        vault.last_activity = now;
+
+        **vault.to_account_info().try_borrow_mut_lamports()? -= fee;
+        **ctx
+            .accounts
+            .safety_fund
+            .to_account_info()
+            .try_borrow_mut_lamports()? += fee;
+        let fund = &mut ctx.accounts.safety_fund;
+        fund.total_received = fund.total_received.saturating_add(fee);
+
+        msg!(
+            "Savings goal {} broken on vault {}, fee={}",
+            goal.goal_id,
+            vault.vault_id,
+            fee
+        );
+        Ok(())
+    }
+
+    // -----------------------------------------------------------------------
+    // Receive-only mode
+    // -----------------------------------------------------------------------
+
+    /// Put the vault in receive-only mode.  Deposits and incoming
+    /// transfers still work; everything that moves funds out fails until
+    /// the mode is lifted with `request_receive_only_unlock` followed by
+    /// `lift_receive_only` after `RECEIVE_ONLY_UNLOCK_DELAY`.
+    pub fn enable_receive_only(ctx: Context<SetVaultPolicy>) -> Result<()> {
+        let vault = &mut ctx.accounts.vault;
+        require!(!vault.is_delegated, ObscuraError::AccountDelegated);
+        vault.receive_only = true;
+        vault.receive_only_unlock_at = 0;
+        vault.last_activity = Clock::get()?.unix_timestamp;
+
+        msg!("Vault {} is now receive-only", vault.vault_id);
+        Ok(())
+    }
+
+    /// Start the unlock timelock for a receive-only vault.
+    pub fn request_receive_only_unlock(ctx: Context<SetVaultPolicy>) -> Result<()> {
+        let vault = &mut ctx.accounts.vault;
+        require!(!vault.is_delegated, ObscuraError::AccountDelegated);
+        require!(vault.receive_only, ObscuraError::NotReceiveOnly);
+
+        let now = Clock::get()?.unix_timestamp;
+        vault.receive_only_unlock_at = now + RECEIVE_ONLY_UNLOCK_DELAY;
+        vault.last_activity = now;
+
+        msg!(
+            "Vault {} receive-only unlock requested, effective at {}",
+            vault.vault_id,
+            vault.receive_only_unlock_at
+        );
+        Ok(())
+    }
+
+    /// Lift receive-only mode once the requested unlock has matured.
+    pub fn lift_receive_only(ctx: Context<SetVaultPolicy>) -> Result<()> {
+        let vault = &mut ctx.accounts.vault;
+        require!(!vault.is_delegated, ObscuraError::AccountDelegated);
+        require!(vault.receive_only, ObscuraError::NotReceiveOnly);
+
+        let now = Clock::get()?.unix_timestamp;
+        require!(
+            vault.receive_only_unlock_at != 0 && now >= vault.receive_only_unlock_at,
+            ObscuraError::ReceiveOnlyTimelockActive
+        );
+        vault.receive_only = false;
+        vault.receive_only_unlock_at = 0;
+        vault.last_activity = now;
+
+        msg!("Vault {} receive-only mode lifted", vault.vault_id);
+        Ok(())
+    }
+
+    // -----------------------------------------------------------------------
+    // Emergency freeze
+    // -----------------------------------------------------------------------
+
+    /// Designate the key that may freeze the vault and must sign to
+    /// unfreeze it (owner only; `Pubkey::default()` removes it).  Pick a
+    /// key held apart from the owner's device, so losing the device can't
+    /// also lift the freeze.
+    pub fn set_freeze_guardian(ctx: Context<SetVaultPolicy>, guardian: Pubkey) -> Result<()> {
+        let vault = &mut ctx.accounts.vault;
+        require!(!vault.is_delegated, ObscuraError::AccountDelegated);
+        require!(!vault.frozen, ObscuraError::VaultFrozen);
+        vault.freeze_guardian = guardian;
+        vault.last_activity = Clock::get()?.unix_timestamp;
+
+        msg!(
+            "Vault {} freeze guardian set to {}",
+            vault.vault_id,
+            guardian
+        );
+        Ok(())
+    }
+
+    /// Freeze the vault at once (owner or freeze guardian): every
+    /// outbound instruction fails until `unfreeze_vault`.
+    ///
+    /// Works on a delegated vault too; send it to the ER the vault is
+    /// delegated to.
+    pub fn freeze_vault(ctx: Context<FreezeVault>) -> Result<()> {
+        let vault = &mut ctx.accounts.vault;
+        let authority = ctx.accounts.authority.key();
+        require!(
+            authority == vault.owner
+                || (vault.freeze_guardian != Pubkey::default()
+                    && authority == vault.freeze_guardian),
+            ObscuraError::Unauthorized
+        );
+        vault.frozen = true;
+
+        msg!("Vault {} frozen by {}", vault.vault_id, authority);
+        Ok(())
+    }
+
+    /// Lift a freeze.  Signed by the freeze guardian when one is set,
+    /// otherwise by the owner.
+    pub fn unfreeze_vault(ctx: Context<FreezeVault>) -> Result<()> {
+        let vault = &mut ctx.accounts.vault;
+        require!(vault.frozen, ObscuraError::VaultNotFrozen);
+        let unfreezer = if vault.freeze_guardian == Pubkey::default() {
+            vault.owner
+        } else {
+            vault.freeze_guardian
+        };
+        require_keys_eq!(
+            ctx.accounts.authority.key(),
+            unfreezer,
+            ObscuraError::Unauthorized
+        );
+        vault.frozen = false;
+
+        msg!("Vault {} unfrozen", vault.vault_id);
+        Ok(())
+    }
+
+    // -----------------------------------------------------------------------
+    // Co-signer
+    // -----------------------------------------------------------------------
+
+    /// Require `cosigner` (e.g. a server-side 2FA key) to sign every
+    /// owner-initiated outbound transfer alongside the owner;
+    /// `Pubkey::default()` removes the requirement.
+    ///
+    /// Changing or removing an existing co-signer needs its signature as
+    /// `current_cosigner`, so a stolen owner key alone can't turn 2FA off.
+    pub fn set_cosigner(ctx: Context<SetCosigner>, cosigner: Pubkey) -> Result<()> {
+        let current = ctx.accounts.current_cosigner.as_ref().map(|c| c.key());
+        let vault = &mut ctx.accounts.vault;
+        require!(!vault.is_delegated, ObscuraError::AccountDelegated);
+        vault.require_cosigned(current)?;
+        require!(cosigner != vault.owner, ObscuraError::InvalidCosigner);
+        vault.cosigner = cosigner;
+        vault.last_activity = Clock::get()?.unix_timestamp;
+
+        msg!("Vault {} co-signer set to {}", vault.vault_id, cosigner);
+        Ok(())
+    }
+
+    // -----------------------------------------------------------------------
+    // Velocity policy
+    // -----------------------------------------------------------------------
+
+    /// Configure velocity-based step-up for outbound transfers.
+    ///
+    /// `factor_pct` is the allowed daily outflow as a percentage of the
+    /// vault's norm (0 disables the policy).  With a `cosigner`, anomalous
+    /// transfers must be cosigned; without one they need a matured
+    /// `request_velocity_override`.
+    pub fn set_velocity_policy(
+        ctx: Context<SetVaultPolicy>,
+        factor_pct: u16,
+        cosigner: Option<Pubkey>,
+    ) -> Result<()> {
+        let vault = &mut ctx.accounts.vault;
+        require!(!vault.is_delegated, ObscuraError::AccountDelegated);
+        vault.velocity.configure(factor_pct, cosigner)?;
+        vault.last_activity = Clock::get()?.unix_timestamp;
+
+        msg!(
+            "Vault {} velocity policy set to {}%",
+            vault.vault_id,
+            factor_pct
+        );
+        Ok(())
+    }
+
+    /// Cap cumulative lamport outflow (transfers, withdrawals, moves to
+    /// other vaults, and escrow for streams, scheduled and conditional
+    /// transfers) per Solana epoch; 0 removes the cap.
+    ///
+    /// A lower cap applies immediately.  Raising or removing it only
+    /// takes effect after `SPENDING_LIMIT_RAISE_DELAY`, so a compromised
+    /// key cannot lift the cap and drain the vault in one go.
+    pub fn set_spending_limit(ctx: Context<SetVaultPolicy>, amount_per_epoch: u64) -> Result<()> {
+        let vault = &mut ctx.accounts.vault;
+        require!(!vault.is_delegated, ObscuraError::AccountDelegated);
+
+        let now = Clock::get()?.unix_timestamp;
+        let effective_at = vault.spending_limit.set(amount_per_epoch, now);
+        vault.last_activity = now;
+
+        msg!(
+            "Vault {} spending limit set to {} per epoch, effective at {}",
+            vault.vault_id,
+            amount_per_epoch,
+            effective_at
+        );
+        Ok(())
+    }
+
+    /// Cap the outflow of one token sub-balance (`withdraw_spl`,
+    /// `private_token_transfer`) per Solana epoch, in the mint's base
+    /// units; 0 removes the cap.  Same delays as `set_spending_limit`.
+    pub fn set_token_spending_limit(
+        ctx: Context<SetTokenSpendingLimit>,
+        amount_per_epoch: u64,
+    ) -> Result<()> {
+        require!(
+            !ctx.accounts.vault.is_delegated,
+            ObscuraError::AccountDelegated
+        );
+
+        let now = Clock::get()?.unix_timestamp;
+        let token_balance = &mut ctx.accounts.token_balance;
+        let effective_at = token_balance.spending_limit.set(amount_per_epoch, now);
+
+        msg!(
+            "Vault {} spending limit for mint {} set to {} per epoch, effective at {}",
+            ctx.accounts.vault.vault_id,
+            token_balance.mint,
+            amount_per_epoch,
+            effective_at
+        );
+        Ok(())
+    }
+
+    /// Cap `withdraw` at `daily_cap` lamports per rolling 24 hours; 0
+    /// removes the cap.
+    ///
+    /// A lower cap applies immediately.  Raising or removing it needs a
+    /// matured `request_withdrawal_override`, which it consumes.
+    pub fn set_withdrawal_limit(ctx: Context<SetVaultPolicy>, daily_cap: u64) -> Result<()> {
+        let vault = &mut ctx.accounts.vault;
+        require!(!vault.is_delegated, ObscuraError::AccountDelegated);
+
+        let now = Clock::get()?.unix_timestamp;
+        let threshold = vault.withdrawal_limit.timelock_threshold;
+        vault
+            .withdrawal_limit
+            .configure(daily_cap, threshold, now)?;
+        vault.last_activity = now;
+
+        msg!(
+            "Vault {} withdrawal limit set to {} per 24h",
+            vault.vault_id,
+            daily_cap
+        );
+        Ok(())
+    }
+
+    /// Request a time-delayed override of the withdrawal limit.  It takes
+    /// effect after `WITHDRAWAL_OVERRIDE_DELAY` and lasts as long again,
+    /// lifting the cap for withdrawals and allowing it to be raised.
+    pub fn request_withdrawal_override(ctx: Context<SetVaultPolicy>) -> Result<()> {
+        let vault = &mut ctx.accounts.vault;
+        require!(!vault.is_delegated, ObscuraError::AccountDelegated);
+
+        let now = Clock::get()?.unix_timestamp;
+        vault.withdrawal_limit.override_at = now + WITHDRAWAL_OVERRIDE_DELAY;
+        vault.last_activity = now;
+
+        msg!(
+            "Vault {} withdrawal override requested, effective at {}",
+            vault.vault_id,
+            vault.withdrawal_limit.override_at
+        );
+        Ok(())
+    }
+
+    /// Require withdrawals over `threshold` lamports to be queued for
+    /// `WITHDRAWAL_TIMELOCK_DELAY`; 0 removes the timelock.
+    ///
+    /// A lower threshold applies immediately.  Raising or removing it
+    /// needs a matured `request_withdrawal_override`, which it consumes.
+    pub fn set_withdrawal_timelock(ctx: Context<SetVaultPolicy>, threshold: u64) -> Result<()> {
+        let vault = &mut ctx.accounts.vault;
+        require!(!vault.is_delegated, ObscuraError::AccountDelegated);
+
+        let now = Clock::get()?.unix_timestamp;
+        let daily_cap = vault.withdrawal_limit.daily_cap;
+        vault
+            .withdrawal_limit
+            .configure(daily_cap, threshold, now)?;
+        vault.last_activity = now;
+
+        msg!(
+            "Vault {} withdrawal timelock threshold set to {}",
+            vault.vault_id,
+            threshold
+        );
+        Ok(())
+    }
+
+    /// Execute a queued withdrawal once its delay has passed (owner only).
+    ///
+    /// The vault's outflow limits apply as for a direct `withdraw`.
+    pub fn execute_pending_withdrawal(ctx: Context<ExecutePendingWithdrawal>) -> Result<()> {
+        let cosigner = ctx.accounts.cosigner.as_ref().map(|c| c.key());
+        let vault = &mut ctx.accounts.vault;
+        let amount = ctx.accounts.pending_withdrawal.amount;
+
+        require!(!vault.is_delegated, ObscuraError::AccountDelegated);
+        require!(
+            Clock::get()?.unix_timestamp >= ctx.accounts.pending_withdrawal.executable_at,
+            ObscuraError::WithdrawalTimelocked
+        );
+        require!(vault.balance >= amount, ObscuraError::InsufficientBalance);
+        vault.require_outbound_allowed()?;
+        vault.require_cosigned(cosigner)?;
+        vault.require_acknowledged_upgrade()?;
+        vault.require_allowed_recipient(
+            ctx.accounts.recipient_allowlist.as_deref(),
+            &ctx.accounts.owner.key(),
+        )?;
+        require_compliant(
+            vault,
+            ctx.accounts.denylist.as_ref(),
+            ctx.accounts.compliance_program.as_ref(),
+            ctx.remaining_accounts,
+            &ctx.accounts.owner.key(),
+            amount,
+        )?;
+
+        pay_out_withdrawal(
+            vault,
+            &ctx.accounts.owner.to_account_info(),
+            ctx.accounts.history_view.as_mut(),
+            amount,
+        )?;
+
+        msg!(
+            "Executed queued withdrawal of {} lamports from vault {}",
+            amount,
+            vault.vault_id
+        );
+        Ok(())
+    }
+
+    /// Cancel a queued withdrawal (owner only), e.g. one queued with a
+    /// compromised key.
+    pub fn cancel_pending_withdrawal(ctx: Context<CancelPendingWithdrawal>) -> Result<()> {
+        msg!(
+            "Cancelled queued withdrawal of {} lamports from vault {}",
+            ctx.accounts.pending_withdrawal.amount,
+            ctx.accounts.vault.vault_id
+        );
+        Ok(())
+    }
+
+    // -----------------------------------------------------------------------
+    // Recipient allowlist
+    // -----------------------------------------------------------------------
+
+    /// Turn recipient allowlist mode on or off.
+    ///
+    /// While on, every outbound path (transfers, withdrawals, moves to
+    /// other vaults, streams, scheduled, conditional and recurring
+    /// payments) rejects destinations not on the vault's
+    /// `RecipientAllowlist`.  Withdrawals pay the owner, so the owner's
+    /// own key must be listed for them to keep working.
+    pub fn set_allowlist_enabled(ctx: Context<SetVaultPolicy>, enabled: bool) -> Result<()> {
+        let vault = &mut ctx.accounts.vault;
+        require!(!vault.is_delegated, ObscuraError::AccountDelegated);
+        vault.allowlist_enabled = enabled;
+        vault.last_activity = Clock::get()?.unix_timestamp;
+
+        msg!("Vault {} allowlist mode: {}", vault.vault_id, enabled);
+        Ok(())
+    }
+
+    /// Add `recipient` to the vault's allowlist, creating it if needed.
+    pub fn add_allowed_recipient(
+        ctx: Context<UpdateRecipientAllowlist>,
+        recipient: Pubkey,
+    ) -> Result<()> {
+        let allowlist = &mut ctx.accounts.recipient_allowlist;
+        require!(
+            !allowlist.recipients.contains(&recipient)
+                && allowlist.recipients.len() < MAX_ALLOWED_RECIPIENTS,
+            ObscuraError::InvalidRecipientAllowlist
+        );
+        allowlist.vault = ctx.accounts.vault.key();
+        allowlist.recipients.push(recipient);
+
+        msg!(
+            "Recipient {} allowed on vault {}",
+            recipient,
+            ctx.accounts.vault.vault_id
+        );
+        Ok(())
+    }
+
+    /// Remove `recipient` from the vault's allowlist.
+    pub fn remove_allowed_recipient(
+        ctx: Context<UpdateRecipientAllowlist>,
+        recipient: Pubkey,
+    ) -> Result<()> {
+        let allowlist = &mut ctx.accounts.recipient_allowlist;
+        let position = allowlist
+            .recipients
+            .iter()
+            .position(|r| *r == recipient)
+            .ok_or(ObscuraError::InvalidRecipientAllowlist)?;
+        allowlist.recipients.remove(position);
+
+        msg!(
+            "Recipient {} removed from vault {}",
+            recipient,
+            ctx.accounts.vault.vault_id
+        );
+        Ok(())
+    }
+
+    /// Request a time-delayed override of the velocity limit.  It takes
+    /// effect after `VELOCITY_OVERRIDE_DELAY` and lasts as long again.
+    pub fn request_velocity_override(ctx: Context<SetVaultPolicy>) -> Result<()> {
+        let vault = &mut ctx.accounts.vault;
+        require!(!vault.is_delegated, ObscuraError::AccountDelegated);
+
+        let now = Clock::get()?.unix_timestamp;
+        vault.velocity.override_at = now + VELOCITY_OVERRIDE_DELAY;
+        vault.last_activity = now;
+
+        msg!(
+            "Vault {} velocity override requested, effective at {}",
+            vault.vault_id,
+            vault.velocity.override_at
+        );
+        Ok(())
+    }
+
+    /// Set the round-up savings rule: every outgoing transfer is rounded
+    /// up to a multiple of `increment` and the difference is moved into
+    /// `savings_vault` in the same instruction.  `increment = 0` disables
+    /// the rule.
+    pub fn set_round_up_rule(ctx: Context<SetRoundUpRule>, increment: u64) -> Result<()> {
+        let savings_vault = ctx.accounts.savings_vault.as_ref().map(|v| v.key());
+        require!(
+            increment == 0 || savings_vault.is_some(),
+            ObscuraError::InvalidRoundUpRule
+        );
+
+        let vault = &mut ctx.accounts.vault;
+        require!(!vault.is_delegated, ObscuraError::AccountDelegated);
+        require!(
+            savings_vault.is_none() || savings_vault != Some(vault.split.split_vault),
+            ObscuraError::SameVault
+        );
+        vault.round_up = RoundUpRule {
+            increment,
+            savings_vault: if increment == 0 {
+                Pubkey::default()
+            } else {
+                savings_vault.unwrap_or_default()
+            },
+        };
+        vault.last_activity = Clock::get()?.unix_timestamp;
+
+        msg!(
+            "Vault {} round-up set to {} lamports into {}",
+            vault.vault_id,
+            increment,
+            vault.round_up.savings_vault
+        );
+        Ok(())
+    }
+
+    /// Set the split rule: `bps` of every outgoing transfer (or, with
+    /// `inbound`, of every deposit) goes to `split_vault` in the same
+    /// instruction, e.g. 1% to a charity vault.  `bps = 0` disables it.
+    pub fn set_split_rule(ctx: Context<SetSplitRule>, bps: u16, inbound: bool) -> Result<()> {
+        let split_vault = ctx.accounts.split_vault.as_ref().map(|v| v.key());
+        require!(
+            bps <= BPS_DENOMINATOR && (bps == 0 || split_vault.is_some()),
+            ObscuraError::InvalidSplitRule
+        );
+
+        let vault = &mut ctx.accounts.vault;
+        require!(!vault.is_delegated, ObscuraError::AccountDelegated);
+        require!(
+            split_vault.is_none() || split_vault != Some(vault.round_up.savings_vault),
+            ObscuraError::SameVault
+        );
+        vault.split = if bps == 0 {
+            SplitRule::default()
+        } else {
+            SplitRule {
+                bps,
+                split_vault: split_vault.unwrap_or_default(),
+                inbound,
+            }
+        };
+        vault.last_activity = Clock::get()?.unix_timestamp;
+
+        msg!(
+            "Vault {} split set to {} bps into {}",
+            vault.vault_id,
+            bps,
+            vault.split.split_vault
+        );
+        Ok(())
+    }
+
+    // -----------------------------------------------------------------------
+    // Fee payment from vault
+    // -----------------------------------------------------------------------
+
+    /// Allow transaction fees to be paid from the vault, up to `cap`
+    /// lamports per transaction (0 disables it).
+    pub fn set_fee_payment_cap(ctx: Context<SetVaultPolicy>, cap: u64) -> Result<()> {
+        let vault = &mut ctx.accounts.vault;
+        require!(!vault.is_delegated, ObscuraError::AccountDelegated);
+        vault.fee_payment_cap = cap;
+        vault.last_activity = Clock::get()?.unix_timestamp;
+
+        msg!("Vault {} fee payment cap set to {}", vault.vault_id, cap);
+        Ok(())
+    }
+
+    /// Reimburse a relayer that paid the network and priority fees of an
+    /// owner-signed transaction, from the vault's tracked balance.
+    ///
+    /// The relayer is the transaction's fee payer and adds this
+    /// instruction; the owner only signs, so their wallet never needs
+    /// loose SOL.  Fees are not an outbound transfer, so receive-only
+    /// vaults can still pay them, but frozen ones cannot.
+    ///
+    /// The cap holds per transaction: the instruction must run at the top
+    /// level, once per vault.  Fees need the co-signer and count against
+    /// the spending limit and withdrawal cap like any other outflow.
+    pub fn pay_fees_from_vault(ctx: Context<PayFeesFromVault>, amount: u64) -> Result<()> {
+        let cosigner = ctx.accounts.cosigner.as_ref().map(|c| c.key());
+        let vault = &mut ctx.accounts.vault;
+        require!(!vault.is_delegated, ObscuraError::AccountDelegated);
+        require!(!vault.frozen, ObscuraError::VaultFrozen);
+        vault.require_cosigned(cosigner)?;
+        require!(
+            amount <= vault.fee_payment_cap,
+            ObscuraError::FeePaymentCapExceeded
+        );
+        require_single_fee_payment(&ctx.accounts.sysvar_instructions, &vault.key())?;
+
+        let clock = Clock::get()?;
+        let now = clock.unix_timestamp;
+        vault.spending_limit.record(clock.epoch, now, amount)?;
+        vault.withdrawal_limit.record(now, amount)?;
+        vault.debit(amount)?;
+        vault.last_activity = now;
+
+        **vault.to_account_info().try_borrow_mut_lamports()? -= amount;
+        **ctx.accounts.fee_payer.try_borrow_mut_lamports()? += amount;
+
+        msg!(
+            "Vault {} paid {} lamports of fees to {}",
+            vault.vault_id,
+            amount,
+            ctx.accounts.fee_payer.key()
+        );
+        Ok(())
+    }
+
+    /// Open a reimbursement ledger for a relayer that sponsors this
+    /// vault's operations, owing it at most `cap` lamports at a time.
+    pub fn open_relayer_ledger(
+        ctx: Context<OpenRelayerLedger>,
+        relayer: Pubkey,
+        cap: u64,
+    ) -> Result<()> {
+        let ledger = &mut ctx.accounts.relayer_ledger;
+        ledger.vault = ctx.accounts.vault.key();
+        ledger.relayer = relayer;
+        ledger.cap = cap;
+
+        msg!(
+            "Relayer ledger opened for {} on vault {}: cap={}",
+            relayer,
+            ctx.accounts.vault.vault_id,
+            cap
+        );
+        Ok(())
+    }
+
+    /// Record the fees a relayer fronted for one sponsored, owner-signed
+    /// operation.  Nothing moves until the relayer settles and claims.
+    pub fn accrue_relayer_fee(ctx: Context<AccrueRelayerFee>, amount: u64) -> Result<()> {
+        let ledger = &mut ctx.accounts.relayer_ledger;
+        let owed = ledger
+            .owed
+            .checked_add(amount)
+            .ok_or(ObscuraError::MathOverflow)?;
+        require!(
+            owed.saturating_add(ledger.pending) <= ledger.cap,
+            ObscuraError::RelayerCapExceeded
+        );
+        ledger.owed = owed;
+        ledger.sponsored_ops = ledger.sponsored_ops.saturating_add(1);
+
+        msg!("Relayer {} owed {} lamports", ledger.relayer, ledger.owed);
+        Ok(())
+    }
+
+    /// Move accrued fees into settlement once the vault is committed back
+    /// to L1, starting the owner's dispute window.
+    pub fn settle_relayer_fees(ctx: Context<RelayerClaim>) -> Result<()> {
+        require!(
+            !ctx.accounts.vault.is_delegated,
+            ObscuraError::AccountDelegated
+        );
+
+        let ledger = &mut ctx.accounts.relayer_ledger;
+        require!(ledger.owed > 0, ObscuraError::NoRelayerFees);
+
+        ledger.pending = ledger
+            .pending
+            .checked_add(ledger.owed)
+            .ok_or(ObscuraError::MathOverflow)?;
+        ledger.owed = 0;
+        ledger.claimable_at = Clock::get()?.unix_timestamp + RELAYER_DISPUTE_WINDOW;
+
+        msg!(
+            "Relayer {} settling {} lamports, claimable at {}",
+            ledger.relayer,
+            ledger.pending,
+            ledger.claimable_at
+        );
+        Ok(())
+    }
+
+    /// Reject fees under settlement before the dispute window closes.
+    pub fn dispute_relayer_fees(ctx: Context<DisputeRelayerFees>) -> Result<()> {
+        let ledger = &mut ctx.accounts.relayer_ledger;
+        require!(ledger.pending > 0, ObscuraError::NoRelayerFees);
+        require!(
+            Clock::get()?.unix_timestamp < ledger.claimable_at,
+            ObscuraError::DisputeWindowClosed
+        );
+
+        ledger.disputed = ledger.disputed.saturating_add(ledger.pending);
+        ledger.pending = 0;
+        ledger.claimable_at = 0;
+
+        msg!(
+            "Vault {} disputed relayer {} fees",
+            ctx.accounts.vault.vault_id,
+            ledger.relayer
+        );
+        Ok(())
+    }
+
+    /// Pay out undisputed fees to the relayer from the vault balance.
+    /// Waits, like any other outbound transfer, while the vault is frozen
+    /// or receive-only.
+    pub fn claim_relayer_fees(ctx: Context<RelayerClaim>) -> Result<()> {
+        let ledger = &mut ctx.accounts.relayer_ledger;
+        let vault = &mut ctx.accounts.vault;
+        require!(!vault.is_delegated, ObscuraError::AccountDelegated);
+        vault.require_outbound_allowed()?;
+        require!(ledger.pending > 0, ObscuraError::NoRelayerFees);
+        require!(
+            Clock::get()?.unix_timestamp >= ledger.claimable_at,
+            ObscuraError::DisputeWindowActive
+        );
+
+        let amount = ledger.pending;
+        vault.debit(amount)?;
+        ledger.pending = 0;
+        ledger.claimable_at = 0;
+
+        **vault.to_account_info().try_borrow_mut_lamports()? -= amount;
+        **ctx.accounts.relayer.try_borrow_mut_lamports()? += amount;
+
+        msg!(
+            "Relayer {} claimed {} lamports from vault {}",
+            ledger.relayer,
+            amount,
+            vault.vault_id
+        );
+        Ok(())
+    }
+
+    // -----------------------------------------------------------------------
+    // Upgrade acknowledgment
+    // -----------------------------------------------------------------------
+
+    /// Opt into (or out of) strict upgrade mode.
+    ///
+    /// Passing the running `PROGRAM_VERSION` acknowledges it and enables
+    /// strict mode: after every later upgrade, withdrawals, transfers,
+    /// delegation and authority grants fail until the owner acknowledges
+    /// the new release again.  Passing 0 disables strict mode.
+    pub fn acknowledge_program_version(ctx: Context<SetVaultPolicy>, version: u32) -> Result<()> {
+        require!(
+            version == 0 || version == PROGRAM_VERSION,
+            ObscuraError::ProgramVersionMismatch
+        );
+
+        let vault = &mut ctx.accounts.vault;
+        require!(!vault.is_delegated, ObscuraError::AccountDelegated);
+        vault.min_accepted_program_version = version;
+        vault.last_activity = Clock::get()?.unix_timestamp;
+
+        msg!(
+            "Vault {} accepts program version {}",
+            vault.vault_id,
+            version
+        );
+        Ok(())
+    }
+
+    /// Grow a vault created under an older `VaultState` layout to
+    /// `VAULT_STATE_SIZE` (permissionless; `payer` funds the extra rent).
+    ///
+    /// `VaultState` fields are only ever appended, so an older vault's data
+    /// is a prefix of the current layout and the appended fields start
+    /// zeroed, i.e. unset.  Until it is migrated, such a vault fails to
+    /// deserialize in every other instruction.
+    pub fn migrate_vault(ctx: Context<MigrateVault>) -> Result<()> {
+        let vault = ctx.accounts.vault.to_account_info();
+        let old_len = vault.data_len();
+        require!(
+            old_len < VAULT_STATE_SIZE,
+            ObscuraError::VaultAlreadyMigrated
+        );
+
+        let vault_id = {
+            let data = vault.try_borrow_data()?;
+            require!(
+                old_len >= 56
+                    && data[..8] == <VaultState as anchor_lang::Discriminator>::DISCRIMINATOR,
+                ObscuraError::InvalidVaultLayout
+            );
+            u64::from_le_bytes(data[40..48].try_into().unwrap())
+        };
+        let (expected, _) =
+            Pubkey::find_program_address(&[VAULT_SEED, &vault_id.to_le_bytes()], &crate::ID);
+        require_keys_eq!(vault.key(), expected, ObscuraError::InvalidVaultLayout);
+
+        // Only the rent for the added bytes: the vault's lamports above
+        // its old rent-exempt minimum back its tracked balance.
+        let rent = Rent::get()?;
+        let top_up = rent
+            .minimum_balance(VAULT_STATE_SIZE)
+            .saturating_sub(rent.minimum_balance(old_len));
+        if top_up > 0 {
+            anchor_lang::system_program::transfer(
+                CpiContext::new(
+                    ctx.accounts.system_program.to_account_info(),
+                    anchor_lang::system_program::Transfer {
+                        from: ctx.accounts.payer.to_account_info(),
+                        to: vault.clone(),
+                    },
+                ),
+                top_up,
+            )?;
+        }
+        vault.realloc(VAULT_STATE_SIZE, true)?;
+
+        msg!(
+            "Vault {} migrated: {} -> {} bytes",
+            vault_id,
+            old_len,
+            VAULT_STATE_SIZE
+        );
+        Ok(())
+    }
+
+    // -----------------------------------------------------------------------
+    // SPL tokens
+    // -----------------------------------------------------------------------
+
+    /// Deposit SPL or Token-2022 tokens into the vault's associated
+    /// token account.
+    ///
+    /// The per-mint balance lives in a `VaultTokenBalance` sub-balance PDA, which
+    /// is delegated and committed independently of the vault so token
+    /// transfers can run inside the ER/PER like lamport transfers.  The
+    /// ledger is credited with what actually arrived, so mints with a
+    /// transfer fee are tracked correctly.  Transfer-hook accounts go in
+    /// `remaining_accounts`.
+    pub fn deposit_spl<'info>(
+        ctx: Context<'_, '_, 'info, 'info, DepositSpl<'info>>,
+        amount: u64,
+    ) -> Result<()> {
+        require!(
+            !ctx.accounts.vault.is_delegated,
+            ObscuraError::AccountDelegated
+        );
+        token::require_supported_mint(&ctx.accounts.mint.to_account_info())?;
+
+        let before = ctx.accounts.vault_token_account.amount;
+        token::transfer_checked(
+            ctx.accounts.token_program.to_account_info(),
+            ctx.accounts.depositor_token_account.to_account_info(),
+            ctx.accounts.mint.to_account_info(),
+            ctx.accounts.vault_token_account.to_account_info(),
+            ctx.accounts.depositor.to_account_info(),
+            ctx.remaining_accounts,
+            amount,
+            ctx.accounts.mint.decimals,
+            &[],
+        )?;
+        ctx.accounts.vault_token_account.reload()?;
+        let received = ctx
+            .accounts
+            .vault_token_account
+            .amount
+            .checked_sub(before)
+            .ok_or(ObscuraError::MathOverflow)?;
+
+        let token_balance = &mut ctx.accounts.token_balance;
+        if token_balance.vault == Pubkey::default() {
+            token_balance.vault = ctx.accounts.vault.key();
+            token_balance.mint = ctx.accounts.mint.key();
+            ctx.accounts.vault.token_balances = ctx
+                .accounts
+                .vault
+                .token_balances
+                .checked_add(1)
+                .ok_or(ObscuraError::MathOverflow)?;
+        }
+        token_balance.credit(received)?;
+
+        let vault = &mut ctx.accounts.vault;
+        vault.last_activity = Clock::get()?.unix_timestamp;
+
+        msg!(
+            "Deposited {} of mint {} into vault {}",
+            received,
+            ctx.accounts.mint.key(),
+            vault.vault_id
+        );
+        Ok(())
+    }
+
+    /// Withdraw SPL or Token-2022 tokens from the vault's associated
+    /// token account.  Transfer-hook and compliance-hook accounts go in
+    /// `remaining_accounts`.
+    pub fn withdraw_spl<'info>(
+        ctx: Context<'_, '_, 'info, 'info, WithdrawSpl<'info>>,
+        amount: u64,
+    ) -> Result<()> {
+        let vault = &ctx.accounts.vault;
+        require!(!vault.is_delegated, ObscuraError::AccountDelegated);
+        vault.require_outbound_allowed()?;
+        vault.require_cosigned(ctx.accounts.cosigner.as_ref().map(|c| c.key()))?;
+        vault.require_acknowledged_upgrade()?;
+        vault.require_allowed_recipient(
+            ctx.accounts.recipient_allowlist.as_deref(),
+            &ctx.accounts.owner_token_account.owner,
+        )?;
+        require_compliant(
+            vault,
+            ctx.accounts.denylist.as_ref(),
+            ctx.accounts.compliance_program.as_ref(),
+            ctx.remaining_accounts,
+            &ctx.accounts.owner_token_account.owner,
+            amount,
+        )?;
+
+        let clock = Clock::get()?;
+        let token_balance = &mut ctx.accounts.token_balance;
+        token_balance
+            .spending_limit
+            .record(clock.epoch, clock.unix_timestamp, amount)?;
+        token_balance.debit(amount)?;
+
+        let vault_id = vault.vault_id.to_le_bytes();
+        let bump = [ctx.bumps.vault];
+        let signer_seeds: &[&[&[u8]]] = &[&[VAULT_SEED, &vault_id, &bump]];
+        token::transfer_checked(
+            ctx.accounts.token_program.to_account_info(),
+            ctx.accounts.vault_token_account.to_account_info(),
+            ctx.accounts.mint.to_account_info(),
+            ctx.accounts.owner_token_account.to_account_info(),
+            ctx.accounts.vault.to_account_info(),
+            ctx.remaining_accounts,
+            amount,
+            ctx.accounts.mint.decimals,
+            signer_seeds,
+        )?;
+
+        let vault = &mut ctx.accounts.vault;
+        vault.last_activity = clock.unix_timestamp;
+
+        msg!(
+            "Withdrew {} of mint {} from vault {}",
+            amount,
+            ctx.accounts.mint.key(),
+            vault.vault_id
+        );
+        Ok(())
+    }
+
+    /// Deposit from a wrapped SOL account, unwrapping it on the way.
+    ///
+    /// The depositor's wSOL account is closed into their wallet and
+    /// `amount` lamports then move into the vault, so wSOL and native SOL
+    /// count as one balance and any remainder stays in the wallet as SOL.
+    pub fn deposit_wsol(ctx: Context<DepositWsol>, amount: u64) -> Result<()> {
+        close_account(CpiContext::new(
+            ctx.accounts.token_program.to_account_info(),
+            CloseAccount {
+                account: ctx.accounts.depositor_wsol.to_account_info(),
+                destination: ctx.accounts.depositor.to_account_info(),
+                authority: ctx.accounts.depositor.to_account_info(),
+            },
+        ))?;
+
+        let vault = &mut ctx.accounts.vault;
+        let ix = anchor_lang::solana_program::system_instruction::transfer(
+            &ctx.accounts.depositor.key(),
+            &vault.key(),
+            amount,
+        );
+        anchor_lang::solana_program::program::invoke(
+            &ix,
+            &[
+                ctx.accounts.depositor.to_account_info(),
+                vault.to_account_info(),
+                ctx.accounts.system_program.to_account_info(),
+            ],
+        )?;
+
+        vault.credit(amount)?;
+        vault.last_activity = Clock::get()?.unix_timestamp;
+
+        msg!(
+            "Deposited {} lamports (from wSOL) into vault {}",
+            amount,
+            vault.vault_id
+        );
+        Ok(())
+    }
+
+    /// Withdraw lamports straight into the owner's wrapped SOL account.
+    ///
+    /// Timelocked like `withdraw`: passing `pending_withdrawal` queues the
+    /// withdrawal, which `execute_pending_withdrawal` later pays out as
+    /// SOL.
+    pub fn withdraw_wsol(ctx: Context<WithdrawWsol>, amount: u64) -> Result<()> {
+        let cosigner = ctx.accounts.cosigner.as_ref().map(|c| c.key());
+        let vault = &mut ctx.accounts.vault;
+
+        require!(!vault.is_delegated, ObscuraError::AccountDelegated);
+        require!(vault.balance >= amount, ObscuraError::InsufficientBalance);
+        vault.require_outbound_allowed()?;
+        vault.require_cosigned(cosigner)?;
+        vault.require_acknowledged_upgrade()?;
+        vault.require_allowed_recipient(
+            ctx.accounts.recipient_allowlist.as_deref(),
+            &ctx.accounts.owner.key(),
+        )?;
+        require_compliant(
+            vault,
+            ctx.accounts.denylist.as_ref(),
+            ctx.accounts.compliance_program.as_ref(),
+            ctx.remaining_accounts,
+            &ctx.accounts.owner.key(),
+            amount,
+        )?;
+
+        if let Some(pending) = ctx.accounts.pending_withdrawal.as_mut() {
+            return queue_withdrawal(vault, pending, amount);
+        }
+        require!(
+            !vault.withdrawal_limit.requires_timelock(amount),
+            ObscuraError::WithdrawalTimelocked
+        );
+
+        pay_out_withdrawal(
+            vault,
+            &ctx.accounts.owner_wsol.to_account_info(),
+            ctx.accounts.history_view.as_mut(),
+            amount,
+        )?;
+        sync_native(CpiContext::new(
+            ctx.accounts.token_program.to_account_info(),
+            SyncNative {
+                account: ctx.accounts.owner_wsol.to_account_info(),
+            },
+        ))?;
+
+        msg!(
+            "Withdrew {} lamports (as wSOL) from vault {}",
+            amount,
+            ctx.accounts.vault.vault_id
+        );
+        Ok(())
+    }
+
+    /// Close an empty sub-balance and the vault's token account for that
+    /// mint, returning their rent to the owner.
+    pub fn close_token_balance(ctx: Context<CloseTokenBalance>) -> Result<()> {
+        let vault = &ctx.accounts.vault;
+        require!(!vault.is_delegated, ObscuraError::AccountDelegated);
+        require!(
+            ctx.accounts.token_balance.amount == 0 && ctx.accounts.vault_token_account.amount == 0,
+            ObscuraError::TokenBalanceNotEmpty
+        );
+
+        let vault_id = vault.vault_id.to_le_bytes();
+        let bump = [ctx.bumps.vault];
+        let signer_seeds: &[&[&[u8]]] = &[&[VAULT_SEED, &vault_id, &bump]];
+        close_account(CpiContext::new_with_signer(
+            ctx.accounts.token_program.to_account_info(),
+            CloseAccount {
+                account: ctx.accounts.vault_token_account.to_account_info(),
+                destination: ctx.accounts.owner.to_account_info(),
+                authority: ctx.accounts.vault.to_account_info(),
+            },
+            signer_seeds,
+        ))?;
+
+        let vault = &mut ctx.accounts.vault;
+        vault.token_balances = vault.token_balances.saturating_sub(1);
+        vault.last_activity = Clock::get()?.unix_timestamp;
+
+        msg!(
+            "Closed sub-balance for mint {} on vault {}",
+            ctx.accounts.mint.key(),
+            vault.vault_id
+        );
+        Ok(())
+    }
+
+    /// Delegate a vault's per-mint token ledger to an ER validator.
+    ///
+    /// As for `delegate_vault`, only registered, live validators are
+    /// accepted, and the ledger remembers the delegation SDK version its
+    /// validator speaks.
+    pub fn delegate_token_balance(
+        ctx: Context<DelegateTokenBalance>,
+        validator: Pubkey,
+    ) -> Result<()> {
+        let validator_info = &ctx.accounts.validator_info;
+        require!(!validator_info.is_defunct, ObscuraError::ValidatorDefunct);
+        let sdk_version = validator_info.sdk_version;
+        require!(
+            delegation::accepts_new_delegations(sdk_version),
+            ObscuraError::UnsupportedSdkVersion
+        );
+
+        let vault_key = ctx.accounts.vault.key();
+        let mint_key = ctx.accounts.token_balance.mint;
+        ctx.accounts.token_balance.delegation_sdk_version = sdk_version;
+        ctx.accounts.token_balance.exit(&crate::ID)?;
+
+        msg!(
+            "Delegating token ledger {} of vault {} to validator {}",
+            mint_key,
+            ctx.accounts.vault.vault_id,
+            validator
+        );
+
+        ctx.accounts.delegate_token_balance(
+            &ctx.accounts.owner,
+            &[TOKEN_BALANCE_SEED, vault_key.as_ref(), mint_key.as_ref()],
+            validator,
+        )?;
+
+        Ok(())
+    }
+
+    /// Execute a private token transfer inside the ER/PER, then commit the
+    /// token ledger back to L1 and undelegate it.
+    pub fn private_token_transfer(
+        ctx: Context<PrivateTokenTransfer>,
+        amount: u64,
+        recipient: Pubkey,
+    ) -> Result<()> {
+        ctx.accounts.vault.require_outbound_allowed()?;
+        ctx.accounts
+            .vault
+            .require_cosigned(ctx.accounts.cosigner.as_ref().map(|c| c.key()))?;
+        ctx.accounts.vault.require_acknowledged_upgrade()?;
+        ctx.accounts
+            .vault
+            .require_allowed_recipient(ctx.accounts.recipient_allowlist.as_deref(), &recipient)?;
+        require_compliant(
+            &ctx.accounts.vault,
+            ctx.accounts.denylist.as_ref(),
+            ctx.accounts.compliance_program.as_ref(),
+            ctx.remaining_accounts,
+            &recipient,
+            amount,
+        )?;
+
+        let clock = Clock::get()?;
+        let token_balance = &mut ctx.accounts.token_balance;
+        token_balance
+            .spending_limit
+            .record(clock.epoch, clock.unix_timestamp, amount)?;
+        token_balance.debit(amount)?;
+        token_balance.nonce = token_balance
+            .nonce
+            .checked_add(1)
+            .ok_or(ObscuraError::MathOverflow)?;
+
+        msg!(
+            "Private token transfer: {} of mint {} to {}, nonce={}",
+            amount,
+            token_balance.mint,
+            recipient,
+            token_balance.nonce
+        );
+
+        // Committed below, so nothing is left for `take_dirty` to commit.
+        ctx.accounts.token_balance.dirty = false;
+        let sdk_version = ctx.accounts.token_balance.delegation_sdk_version;
+        ctx.accounts.token_balance.exit(&crate::ID)?;
+        delegation::commit_and_undelegate(
+            sdk_version,
+            &ctx.accounts.owner,
+            vec![&ctx.accounts.token_balance.to_account_info()],
+            &ctx.accounts.magic_context,
+            &ctx.accounts.magic_program,
+        )?;
+
+        Ok(())
+    }
+
+    // -----------------------------------------------------------------------
+    // Limit orders
+    // -----------------------------------------------------------------------
+
+    /// Place a limit order selling `amount` of the input mint for at least
+    /// `min_output` of the output mint, funded from the vault's token
+    /// ledger.
+    ///
+    /// `adapter_data` and the remaining accounts form the DEX adapter
+    /// instruction, which runs with the vault PDA as signer.  Exactly
+    /// `amount` must leave the vault's input token account; the only
+    /// other vault token account the adapter may see is the output one,
+    /// which must not lose anything.  The amount counts against the input
+    /// ledger's spending limit.
+    pub fn place_limit_order<'info>(
+        ctx: Context<'_, '_, 'info, 'info, PlaceLimitOrder<'info>>,
+        order_id: u64,
+        amount: u64,
+        min_output: u64,
+        adapter_data: Vec<u8>,
+    ) -> Result<()> {
+        ctx.accounts.config.require_feature(FEATURE_LIMIT_ORDERS)?;
+        require!(
+            amount > 0 && min_output > 0,
+            ObscuraError::InvalidLimitOrder
+        );
+
+        let vault = &ctx.accounts.vault;
+        require!(!vault.is_delegated, ObscuraError::AccountDelegated);
+        vault.require_outbound_allowed()?;
+        vault.require_cosigned(ctx.accounts.cosigner.as_ref().map(|c| c.key()))?;
+        vault.require_acknowledged_upgrade()?;
+
+        let clock = Clock::get()?;
+        let now = clock.unix_timestamp;
+        let input_balance = &mut ctx.accounts.input_balance;
+        input_balance
+            .spending_limit
+            .record(clock.epoch, now, amount)?;
+        input_balance.debit(amount)?;
+
+        let input_key = ctx.accounts.vault_input_account.key();
+        let output_key = get_associated_token_address_with_program_id(
+            &vault.key(),
+            &ctx.accounts.output_mint.key(),
+            &ctx.accounts.token_program.key(),
+        );
+        let mut untouched = dex::vault_token_balances(
+            ctx.remaining_accounts,
+            &vault.key(),
+            &[input_key, output_key],
+        )?;
+        untouched.retain(|(key, _)| *key != input_key);
+
+        let before = ctx.accounts.vault_input_account.amount;
+        let vault_id = vault.vault_id.to_le_bytes();
+        let bump = [ctx.bumps.vault];
+        let signer_seeds: &[&[&[u8]]] = &[&[VAULT_SEED, &vault_id, &bump]];
+        dex::invoke_adapter(
+            &ctx.accounts.dex_adapter.to_account_info(),
+            &vault.key(),
+            ctx.remaining_accounts,
+            adapter_data,
+            signer_seeds,
+        )?;
+        dex::require_no_outflow(ctx.remaining_accounts, &vault.key(), &untouched)?;
+        ctx.accounts.vault_input_account.reload()?;
+        let escrowed = before.saturating_sub(ctx.accounts.vault_input_account.amount);
+        require!(escrowed == amount, ObscuraError::DexAdapterMismatch);
+
+        let order = &mut ctx.accounts.order;
+        order.vault = ctx.accounts.vault.key();
+        order.order_id = order_id;
+        order.input_mint = ctx.accounts.input_mint.key();
+        order.output_mint = ctx.accounts.output_mint.key();
+        order.amount = amount;
+        order.min_output = min_output;
+        order.returned = 0;
+        order.received = 0;
+        order.placed_at = now;
+
+        let vault = &mut ctx.accounts.vault;
+        vault.last_activity = now;
+
+        msg!(
+            "Limit order {} placed on vault {}: {} for >= {}",
+            order_id,
+            vault.vault_id,
+            amount,
+            min_output
+        );
+        Ok(())
+    }
+
+    /// Settle a limit order through the DEX adapter, crediting fills and
+    /// returned input to the vault's token ledgers.
+    ///
+    /// With `close`, the order account is closed once the fills received
+    /// meet the limit price for the input consumed.  Not feature-gated, so
+    /// open orders can always be unwound.
+    ///
+    /// As for `place_limit_order`, the adapter may only see the order's
+    /// input and output token accounts of the vault, and neither may lose
+    /// anything.
+    pub fn settle_limit_order<'info>(
+        ctx: Context<'_, '_, 'info, 'info, SettleLimitOrder<'info>>,
+        adapter_data: Vec<u8>,
+        close: bool,
+    ) -> Result<()> {
+        let vault_key = ctx.accounts.vault.key();
+        let untouched = dex::vault_token_balances(
+            ctx.remaining_accounts,
+            &vault_key,
+            &[
+                ctx.accounts.vault_input_account.key(),
+                ctx.accounts.vault_output_account.key(),
+            ],
+        )?;
+
+        let input_before = ctx.accounts.vault_input_account.amount;
+        let output_before = ctx.accounts.vault_output_account.amount;
+        let vault_id = ctx.accounts.vault.vault_id.to_le_bytes();
+        let bump = [ctx.bumps.vault];
+        let signer_seeds: &[&[&[u8]]] = &[&[VAULT_SEED, &vault_id, &bump]];
+        dex::invoke_adapter(
+            &ctx.accounts.dex_adapter.to_account_info(),
+            &vault_key,
+            ctx.remaining_accounts,
+            adapter_data,
+            signer_seeds,
+        )?;
+        dex::require_no_outflow(ctx.remaining_accounts, &vault_key, &untouched)?;
+        ctx.accounts.vault_input_account.reload()?;
+        ctx.accounts.vault_output_account.reload()?;
+        let returned = ctx
+            .accounts
+            .vault_input_account
+            .amount
+            .checked_sub(input_before)
+            .ok_or(ObscuraError::DexAdapterMismatch)?;
+        let received = ctx
+            .accounts
+            .vault_output_account
+            .amount
+            .checked_sub(output_before)
+            .ok_or(ObscuraError::DexAdapterMismatch)?;
+
+        let order = &mut ctx.accounts.order;
+        order.returned = order
+            .returned
+            .checked_add(returned)
+            .filter(|total| *total <= order.amount)
+            .ok_or(ObscuraError::DexAdapterMismatch)?;
+        order.received = order
+            .received
+            .checked_add(received)
+            .ok_or(ObscuraError::MathOverflow)?;
+
+        ctx.accounts.input_balance.credit(returned)?;
+        let output_balance = &mut ctx.accounts.output_balance;
+        if output_balance.vault == Pubkey::default() {
+            output_balance.vault = ctx.accounts.vault.key();
+            output_balance.mint = ctx.accounts.output_mint.key();
+            ctx.accounts.vault.token_balances = ctx
+                .accounts
+                .vault
+                .token_balances
+                .checked_add(1)
+                .ok_or(ObscuraError::MathOverflow)?;
+        }
+        output_balance.credit(received)?;
+
+        let vault = &mut ctx.accounts.vault;
+        vault.last_activity = Clock::get()?.unix_timestamp;
+
+        msg!(
+            "Limit order {} settled: {} returned, {} received",
+            ctx.accounts.order.order_id,
+            returned,
+            received
+        );
+
+        if close {
+            require!(
+                ctx.accounts.order.meets_limit(),
+                ObscuraError::LimitPriceViolated
+            );
+            ctx.accounts
+                .order
+                .close(ctx.accounts.owner.to_account_info())?;
+        }
+        Ok(())
+    }
+
+    // -----------------------------------------------------------------------
+    // Conditional transfers
+    // -----------------------------------------------------------------------
+
+    /// Escrow `amount` lamports from the vault for `recipient`, released
+    /// by `execute_conditional_transfer` once `condition` holds.
+    pub fn create_conditional_transfer(
+        ctx: Context<CreateConditionalTransfer>,
+        transfer_id: u64,
+        amount: u64,
+        recipient: Pubkey,
+        condition: TransferCondition,
+    ) -> Result<()> {
+        require!(amount > 0, ObscuraError::InvalidConditionalTransfer);
+        let cosigner = ctx.accounts.cosigner.as_ref().map(|c| c.key());
+        let vault = &mut ctx.accounts.vault;
+        require!(!vault.is_delegated, ObscuraError::AccountDelegated);
+        vault.require_outbound_allowed()?;
+        vault.require_cosigned(cosigner)?;
+        vault.require_acknowledged_upgrade()?;
+        vault.require_allowed_recipient(ctx.accounts.recipient_allowlist.as_deref(), &recipient)?;
+        require_compliant(
+            vault,
+            ctx.accounts.denylist.as_ref(),
+            ctx.accounts.compliance_program.as_ref(),
+            ctx.remaining_accounts,
+            &recipient,
+            amount,
+        )?;
+
+        let clock = Clock::get()?;
+        let now = clock.unix_timestamp;
+        vault.spending_limit.record(clock.epoch, now, amount)?;
+        vault.debit(amount)?;
+        vault.billing.accrue(now, amount)?;
+        vault.last_activity = now;
+        **vault.to_account_info().try_borrow_mut_lamports()? -= amount;
+        **ctx
+            .accounts
+            .conditional_transfer
+            .to_account_info()
+            .try_borrow_mut_lamports()? += amount;
+
+        let conditional_transfer = &mut ctx.accounts.conditional_transfer;
+        conditional_transfer.vault = vault.key();
+        conditional_transfer.owner = vault.owner;
+        conditional_transfer.transfer_id = transfer_id;
+        conditional_transfer.amount = amount;
+        conditional_transfer.recipient = recipient;
+        conditional_transfer.condition = condition;
+        conditional_transfer.created_at = now;
+
+        msg!(
+            "Conditional transfer {} created: {} lamports to {}",
+            transfer_id,
+            amount,
+            recipient
+        );
+        Ok(())
+    }
+
+    /// Release a conditional transfer to its recipient (permissionless
+    /// crank).  The condition is checked on-chain against the Pyth price
+    /// update, the clock, or the attestor's signature.
+    pub fn execute_conditional_transfer<'info>(
+        ctx: Context<'_, '_, 'info, 'info, ExecuteConditionalTransfer<'info>>,
+    ) -> Result<()> {
+        let conditional_transfer = &ctx.accounts.conditional_transfer;
+        let clock = Clock::get()?;
+        let met = match conditional_transfer.condition {
+            TransferCondition::PriceAbove { feed_id, price } => {
+                let price_update = ctx
+                    .accounts
+                    .price_update
+                    .as_ref()
+                    .ok_or(ObscuraError::MissingPriceUpdate)?;
+                price_update
+                    .get_price_no_older_than(&clock, PRICE_MAX_AGE, &feed_id)?
+                    .price
+                    > price
+            }
+            TransferCondition::After { unix_timestamp } => clock.unix_timestamp >= unix_timestamp,
+            TransferCondition::Attestation { attestor } => ctx
+                .accounts
+                .attestor
+                .as_ref()
+                .is_some_and(|signer| signer.key() == attestor),
+        };
+        require!(met, ObscuraError::ConditionNotMet);
+
+        let amount = conditional_transfer.amount;
+        **conditional_transfer
+            .to_account_info()
+            .try_borrow_mut_lamports()? -= amount;
+        **ctx.accounts.recipient.try_borrow_mut_lamports()? += amount;
+
+        if let Some(rating_ticket) = ctx.accounts.rating_ticket.as_mut() {
+            rating_ticket.completed_at = clock.unix_timestamp;
+        }
+
+        if !ctx.remaining_accounts.is_empty() {
+            receipt::mint_receipts(
+                ctx.remaining_accounts,
+                [
+                    &ctx.accounts.owner.to_account_info(),
+                    &ctx.accounts.recipient.to_account_info(),
+                ],
+                conditional_transfer.reference(),
+            )?;
+        }
+
+        msg!(
+            "Conditional transfer {} executed: {} lamports to {}",
+            conditional_transfer.transfer_id,
+            amount,
+            conditional_transfer.recipient
+        );
+        Ok(())
+    }
+
+    /// Cancel a pending conditional transfer, returning the escrow to the
+    /// vault (owner only).
+    pub fn cancel_conditional_transfer(ctx: Context<CancelConditionalTransfer>) -> Result<()> {
+        let amount = ctx.accounts.conditional_transfer.amount;
+        **ctx
+            .accounts
+            .conditional_transfer
+            .to_account_info()
+            .try_borrow_mut_lamports()? -= amount;
+        **ctx
+            .accounts
+            .vault
+            .to_account_info()
+            .try_borrow_mut_lamports()? += amount;
+
+        let vault = &mut ctx.accounts.vault;
+        vault.credit(amount)?;
+        vault.last_activity = Clock::get()?.unix_timestamp;
+
+        msg!(
+            "Conditional transfer {} cancelled, {} lamports returned",
+            ctx.accounts.conditional_transfer.transfer_id,
+            amount
+        );
+        Ok(())
+    }
+
+    /// Open the evidence locker of an arbitrated conditional transfer
+    /// (one with an `Attestation` condition, whose attestor arbitrates).
+    /// Either party may open it and pays its rent.
+    pub fn open_evidence_locker(ctx: Context<OpenEvidenceLocker>) -> Result<()> {
+        let conditional_transfer = &ctx.accounts.conditional_transfer;
+        let TransferCondition::Attestation { attestor } = conditional_transfer.condition else {
+            return err!(ObscuraError::NotArbitrated);
+        };
+        let party = ctx.accounts.party.key();
+        require!(
+            conditional_transfer.is_party(&party),
+            ObscuraError::Unauthorized
+        );
+
+        let locker = &mut ctx.accounts.evidence_locker;
+        locker.conditional_transfer = conditional_transfer.key();
+        locker.arbiter = attestor;
+        locker.opened_by = party;
+        locker.entries = Vec::new();
+
+        msg!(
+            "Evidence locker opened for conditional transfer {}",
+            conditional_transfer.transfer_id
+        );
+        Ok(())
+    }
+
+    /// Append an evidence blob, encrypted by the client to the arbiter's
+    /// key, on behalf of either party.
+    pub fn append_evidence(ctx: Context<AppendEvidence>, blob: Vec<u8>) -> Result<()> {
+        let party = ctx.accounts.party.key();
+        require!(
+            ctx.accounts.conditional_transfer.is_party(&party),
+            ObscuraError::Unauthorized
+        );
+        require!(
+            !blob.is_empty() && blob.len() <= MAX_EVIDENCE_LEN,
+            ObscuraError::InvalidEvidence
+        );
+
+        let locker = &mut ctx.accounts.evidence_locker;
+        require!(
+            locker.entries.len() < MAX_EVIDENCE_ENTRIES,
+            ObscuraError::EvidenceLockerFull
+        );
+        locker.entries.push(EvidenceEntry {
+            author: party,
+            submitted_at: Clock::get()?.unix_timestamp,
+            blob,
+        });
+
+        msg!("Evidence {} appended by {}", locker.entries.len(), party);
+        Ok(())
+    }
+
+    /// Finalize a resolved dispute by closing its evidence locker
+    /// (arbiter only), refunding the rent to whoever opened it.
+    pub fn close_evidence_locker(ctx: Context<CloseEvidenceLocker>) -> Result<()> {
+        msg!(
+            "Evidence locker closed with {} entries",
+            ctx.accounts.evidence_locker.entries.len()
+        );
+        Ok(())
+    }
+
+    /// Open the rating ticket of a pending conditional transfer, so its
+    /// parties can rate each other once it completes.  Either party may
+    /// open it and pays its rent; `execute_conditional_transfer` must be
+    /// passed the ticket for it to count.
+    pub fn open_rating_ticket(ctx: Context<OpenRatingTicket>) -> Result<()> {
+        let conditional_transfer = &ctx.accounts.conditional_transfer;
+        let party = ctx.accounts.party.key();
+        require!(
+            conditional_transfer.is_party(&party),
+            ObscuraError::Unauthorized
+        );
+
+        let ticket = &mut ctx.accounts.rating_ticket;
+        ticket.conditional_transfer = conditional_transfer.key();
+        ticket.reference = conditional_transfer.reference();
+        ticket.owner = conditional_transfer.owner;
+        ticket.recipient = conditional_transfer.recipient;
+        ticket.opened_by = party;
+        ticket.completed_at = 0;
+        ticket.owner_rated = false;
+        ticket.recipient_rated = false;
+
+        msg!(
+            "Rating ticket opened for conditional transfer {}",
+            conditional_transfer.transfer_id
+        );
+        Ok(())
+    }
+
+    /// Rate the other party of a completed escrow, once, within
+    /// `RATING_WINDOW` of its completion.  The rating is minted into the
+    /// attestation tree with the receipt accounts (see `receipt`) as the
+    /// remaining accounts.
+    pub fn rate_counterparty<'info>(
+        ctx: Context<'_, '_, 'info, 'info, RateCounterparty<'info>>,
+        rating: Rating,
+    ) -> Result<()> {
+        if let Rating::Public { score } = rating {
+            require!(
+                (1..=MAX_RATING_SCORE).contains(&score),
+                ObscuraError::InvalidRating
+            );
+        }
+        let now = Clock::get()?.unix_timestamp;
+        let rater = ctx.accounts.rater.key();
+        let ticket = &mut ctx.accounts.rating_ticket;
+        require!(
+            ticket.completed_at != 0 && now < ticket.completed_at + RATING_WINDOW,
+            ObscuraError::RatingClosed
+        );
+        let subject = if rater == ticket.owner {
+            require!(!ticket.owner_rated, ObscuraError::AlreadyRated);
+            ticket.owner_rated = true;
+            ticket.recipient
+        } else if rater == ticket.recipient {
+            require!(!ticket.recipient_rated, ObscuraError::AlreadyRated);
+            ticket.recipient_rated = true;
+            ticket.owner
+        } else {
+            return err!(ObscuraError::Unauthorized);
+        };
+
+        attestation::mint_attestation(
+            ctx.remaining_accounts,
+            &rater,
+            &subject,
+            &ticket.reference,
+            &rating,
+        )?;
+
+        msg!("Counterparty {} rated by {}", subject, rater);
+        Ok(())
+    }
+
+    /// Close a rating ticket, refunding its rent to whoever opened it
+    /// (permissionless): once both parties have rated or `RATING_WINDOW`
+    /// has passed, or once its escrow is gone without completing it.
+    pub fn close_rating_ticket(ctx: Context<CloseRatingTicket>) -> Result<()> {
+        let ticket = &ctx.accounts.rating_ticket;
+        let now = Clock::get()?.unix_timestamp;
+        let done = if ticket.completed_at != 0 {
+            (ticket.owner_rated && ticket.recipient_rated)
+                || now >= ticket.completed_at + RATING_WINDOW
+        } else {
+            ctx.accounts.conditional_transfer.data_is_empty()
+        };
+        require!(done, ObscuraError::RatingTicketOpen);
+
+        msg!("Rating ticket closed for {}", ticket.conditional_transfer);
+        Ok(())
+    }
+
+    // -----------------------------------------------------------------------
+    // Scheduled transfers
+    // -----------------------------------------------------------------------
+
+    /// Escrow `amount` lamports from the vault for `recipient`, released
+    /// by `execute_scheduled_transfer` once `execute_after` has passed.
+    /// `SCHEDULED_TRANSFER_TIP` is escrowed on top for the cranker.
+    pub fn schedule_transfer(
+        ctx: Context<ScheduleTransfer>,
+        transfer_id: u64,
+        recipient: Pubkey,
+        amount: u64,
+        execute_after: i64,
+    ) -> Result<()> {
+        let now = Clock::get()?.unix_timestamp;
+        require!(
+            amount > 0 && recipient != Pubkey::default() && execute_after > now,
+            ObscuraError::InvalidScheduledTransfer
+        );
+        let cosigner = ctx.accounts.cosigner.as_ref().map(|c| c.key());
+        let vault = &mut ctx.accounts.vault;
+        require!(!vault.is_delegated, ObscuraError::AccountDelegated);
+        vault.require_outbound_allowed()?;
+        vault.require_cosigned(cosigner)?;
+        vault.require_acknowledged_upgrade()?;
+        vault.require_allowed_recipient(ctx.accounts.recipient_allowlist.as_deref(), &recipient)?;
+        require_compliant(
+            vault,
+            ctx.accounts.denylist.as_ref(),
+            ctx.accounts.compliance_program.as_ref(),
+            ctx.remaining_accounts,
+            &recipient,
+            amount,
+        )?;
+
+        let escrowed = amount
+            .checked_add(SCHEDULED_TRANSFER_TIP)
+            .ok_or(ObscuraError::MathOverflow)?;
+        vault
+            .spending_limit
+            .record(Clock::get()?.epoch, now, escrowed)?;
+        vault.debit(escrowed)?;
+        vault.billing.accrue(now, amount)?;
+        vault.last_activity = now;
+        **vault.to_account_info().try_borrow_mut_lamports()? -= escrowed;
+        **ctx
+            .accounts
+            .scheduled_transfer
+            .to_account_info()
+            .try_borrow_mut_lamports()? += escrowed;
+
+        ctx.accounts
+            .scheduled_transfer
+            .set_inner(ScheduledTransfer {
+                vault: vault.key(),
+                owner: vault.owner,
+                transfer_id,
+                recipient,
+                amount,
+                tip: SCHEDULED_TRANSFER_TIP,
+                execute_after,
+                created_at: now,
+            });
+
+        msg!(
+            "Transfer {} scheduled: {} lamports to {} after {}",
+            transfer_id,
+            amount,
+            recipient,
+            execute_after
+        );
+        Ok(())
+    }
+
+    /// Pay a due scheduled transfer to its recipient (permissionless
+    /// crank).  The cranker receives the escrowed tip, diminished while it
+    /// is inside its `CRANK_COOLDOWN`; the rent and any withheld tip go
+    /// back to the owner.
+    pub fn execute_scheduled_transfer(ctx: Context<ExecuteScheduledTransfer>) -> Result<()> {
+        let now = Clock::get()?.unix_timestamp;
+        let scheduled_transfer = &ctx.accounts.scheduled_transfer;
+        require!(
+            now >= scheduled_transfer.execute_after,
+            ObscuraError::ScheduledTransferNotDue
+        );
+
+        let cranker_stats = &mut ctx.accounts.cranker_stats;
+        cranker_stats.cranker = ctx.accounts.cranker.key();
+        let amount = scheduled_transfer.amount;
+        let tip = cranker_stats.record(now, scheduled_transfer.tip);
+        **scheduled_transfer
+            .to_account_info()
+            .try_borrow_mut_lamports()? -= amount + tip;
+        **ctx.accounts.recipient.try_borrow_mut_lamports()? += amount;
+        **ctx.accounts.cranker.try_borrow_mut_lamports()? += tip;
+
+        msg!(
+            "Scheduled transfer {} executed: {} lamports to {}, {} tip to {}",
+            scheduled_transfer.transfer_id,
+            amount,
+            scheduled_transfer.recipient,
+            tip,
+            ctx.accounts.cranker.key()
+        );
+        Ok(())
+    }
+
+    /// Cancel a pending scheduled transfer, returning the amount and tip
+    /// to the vault (owner only).
+    pub fn cancel_scheduled_transfer(ctx: Context<CancelScheduledTransfer>) -> Result<()> {
+        let scheduled_transfer = &ctx.accounts.scheduled_transfer;
+        let escrowed = scheduled_transfer.amount + scheduled_transfer.tip;
+        **scheduled_transfer
+            .to_account_info()
+            .try_borrow_mut_lamports()? -= escrowed;
+        **ctx
+            .accounts
+            .vault
+            .to_account_info()
+            .try_borrow_mut_lamports()? += escrowed;
+
+        let vault = &mut ctx.accounts.vault;
+        vault.credit(escrowed)?;
+        vault.last_activity = Clock::get()?.unix_timestamp;
+
+        msg!(
+            "Scheduled transfer {} cancelled, {} lamports returned",
+            ctx.accounts.scheduled_transfer.transfer_id,
+            escrowed
+        );
+        Ok(())
+    }
+
+    // -----------------------------------------------------------------------
+    // Subscriptions
+    // -----------------------------------------------------------------------
+
+    /// Let `recipient` (e.g. a merchant) be paid `amount` lamports from
+    /// the vault every `interval` seconds, starting at `first_due`.
+    /// Nothing is escrowed; each payment is taken by `process_subscription`.
+    pub fn create_subscription(
+        ctx: Context<CreateSubscription>,
+        subscription_id: u64,
+        recipient: Pubkey,
+        amount: u64,
+        interval: i64,
+        first_due: i64,
+    ) -> Result<()> {
+        require!(
+            amount > 0 && recipient != Pubkey::default() && interval >= MIN_SUBSCRIPTION_INTERVAL,
+            ObscuraError::InvalidSubscription
+        );
+        let cosigner = ctx.accounts.cosigner.as_ref().map(|c| c.key());
+        let vault = &mut ctx.accounts.vault;
+        vault.require_allowed_recipient(ctx.accounts.recipient_allowlist.as_deref(), &recipient)?;
+        vault.require_cosigned(cosigner)?;
+        require_compliant(
+            vault,
+            ctx.accounts.denylist.as_ref(),
+            ctx.accounts.compliance_program.as_ref(),
+            ctx.remaining_accounts,
+            &recipient,
+            amount,
+        )?;
+        let now = Clock::get()?.unix_timestamp;
+        vault.last_activity = now;
+
+        ctx.accounts.subscription.set_inner(Subscription {
+            vault: vault.key(),
+            owner: vault.owner,
+            subscription_id,
+            recipient,
+            amount,
+            interval,
+            next_due: first_due,
+            payments: 0,
+            created_at: now,
+        });
+
+        msg!(
+            "Subscription {} created: {} lamports to {} every {}s from {}",
+            subscription_id,
+            amount,
+            recipient,
+            interval,
+            first_due
+        );
+        Ok(())
+    }
+
+    /// Pay a due subscription period from the vault (permissionless
+    /// crank).  One call pays one period, so missed periods are caught up
+    /// one at a time.  The vault must be on L1.
+    ///
+    /// Every payment runs the vault's allowlist and compliance checks
+    /// against the recipient again, so a recipient denied after the
+    /// subscription was created stops being paid.  Compliance-hook
+    /// accounts go in `remaining_accounts`.
+    pub fn process_subscription(ctx: Context<ProcessSubscription>) -> Result<()> {
+        let clock = Clock::get()?;
+        let now = clock.unix_timestamp;
+        let subscription = &mut ctx.accounts.subscription;
+        require!(
+            now >= subscription.next_due,
+            ObscuraError::SubscriptionNotDue
+        );
+
+        let amount = subscription.amount;
+        let vault = &mut ctx.accounts.vault;
+        require!(!vault.is_delegated, ObscuraError::AccountDelegated);
+        vault.require_outbound_allowed()?;
+        vault.require_acknowledged_upgrade()?;
+        vault.require_allowed_recipient(
+            ctx.accounts.recipient_allowlist.as_deref(),
+            &subscription.recipient,
+        )?;
+        require_compliant(
+            vault,
+            ctx.accounts.denylist.as_ref(),
+            ctx.accounts.compliance_program.as_ref(),
+            ctx.remaining_accounts,
+            &subscription.recipient,
+            amount,
+        )?;
+        vault.spending_limit.record(clock.epoch, now, amount)?;
+        vault.withdrawal_limit.record(now, amount)?;
+        vault.debit(amount)?;
+        vault.billing.accrue(now, amount)?;
+
+        **vault.to_account_info().try_borrow_mut_lamports()? -= amount;
+        **ctx.accounts.recipient.try_borrow_mut_lamports()? += amount;
+
+        subscription.next_due = subscription
+            .next_due
+            .checked_add(subscription.interval)
+            .ok_or(ObscuraError::MathOverflow)?;
+        subscription.payments = subscription.payments.saturating_add(1);
+
+        msg!(
+            "Subscription {} paid: {} lamports from vault {} to {}, next due {}",
+            subscription.subscription_id,
+            amount,
+            vault.vault_id,
+            subscription.recipient,
+            subscription.next_due
+        );
+        Ok(())
+    }
+
+    /// End a subscription (vault owner or recipient).  The rent goes back
+    /// to the owner.
+    pub fn cancel_subscription(ctx: Context<CancelSubscription>) -> Result<()> {
+        let subscription = &ctx.accounts.subscription;
+        let authority = ctx.accounts.authority.key();
+        require!(
+            authority == subscription.owner || authority == subscription.recipient,
+            ObscuraError::Unauthorized
+        );
+
+        msg!(
+            "Subscription {} cancelled by {} after {} payments",
+            subscription.subscription_id,
+            authority,
+            subscription.payments
+        );
+        Ok(())
+    }
+
+    // -----------------------------------------------------------------------
+    // Payment streams
+    // -----------------------------------------------------------------------
+
+    /// Stream `rate_per_second` lamports from the vault to
+    /// `recipient_vault` between `start` and `end` (payroll-style).  The
+    /// whole amount is escrowed in the stream up front; the recipient
+    /// claims whatever has vested with `withdraw_from_stream`.
+    pub fn create_stream(
+        ctx: Context<CreateStream>,
+        stream_id: u64,
+        recipient_vault: Pubkey,
+        rate_per_second: u64,
+        start: i64,
+        end: i64,
+    ) -> Result<()> {
+        let cosigner = ctx.accounts.cosigner.as_ref().map(|c| c.key());
+        let now = Clock::get()?.unix_timestamp;
+        let vault = &mut ctx.accounts.vault;
+        require!(
+            rate_per_second > 0
+                && end > start
+                && end > now
+                && recipient_vault != Pubkey::default()
+                && recipient_vault != vault.key(),
+            ObscuraError::InvalidStream
+        );
+        require!(!vault.is_delegated, ObscuraError::AccountDelegated);
+        vault.require_outbound_allowed()?;
+        vault.require_cosigned(cosigner)?;
+        vault.require_acknowledged_upgrade()?;
+        vault.require_allowed_recipient(
+            ctx.accounts.recipient_allowlist.as_deref(),
+            &recipient_vault,
+        )?;
+
+        let stream = &mut ctx.accounts.stream;
+        stream.vault = vault.key();
+        stream.owner = vault.owner;
+        stream.stream_id = stream_id;
+        stream.recipient_vault = recipient_vault;
+        stream.rate_per_second = rate_per_second;
+        stream.start = start;
+        stream.end = end;
+        stream.withdrawn = 0;
+        stream.created_at = now;
+        let total = stream.total().ok_or(ObscuraError::InvalidStream)?;
+        require_compliant(
+            vault,
+            ctx.accounts.denylist.as_ref(),
+            ctx.accounts.compliance_program.as_ref(),
+            ctx.remaining_accounts,
+            &recipient_vault,
+            total,
+        )?;
+
+        vault
+            .spending_limit
+            .record(Clock::get()?.epoch, now, total)?;
+        vault.debit(total)?;
+        vault.billing.accrue(now, total)?;
+        vault.last_activity = now;
+        **vault.to_account_info().try_borrow_mut_lamports()? -= total;
+        **stream.to_account_info().try_borrow_mut_lamports()? += total;
+
+        msg!(
+            "Stream {} created: {} lamports/s to vault {} from {} to {}",
+            stream_id,
+            rate_per_second,
+            recipient_vault,
+            start,
+            end
+        );
+        Ok(())
+    }
+
+    /// Delegate a stream to the recipient vault's validator (recipient
+    /// vault owner).
+    ///
+    /// Call this before `delegate_vault` on the recipient vault (in the
+    /// same transaction), so `withdraw_from_stream` runs inside the ER
+    /// next to the vault it credits.
+    pub fn delegate_stream(ctx: Context<DelegateStream>, validator: Pubkey) -> Result<()> {
+        let stream = &ctx.accounts.stream;
+        let vault_key = stream.vault;
+        let stream_id = stream.stream_id.to_le_bytes();
+
+        msg!(
+            "Delegating stream {} of vault {} to validator {}",
+            stream.stream_id,
+            vault_key,
+            validator
+        );
+
+        ctx.accounts.delegate_stream(
+            &ctx.accounts.owner,
+            &[STREAM_SEED, vault_key.as_ref(), &stream_id],
+            validator,
+        )?;
+
+        Ok(())
+    }
+
+    /// Pay everything vested so far to the recipient vault (recipient
+    /// vault owner).  Works on L1, or inside the ER when the stream and
+    /// the recipient vault are delegated to the same validator.
+    pub fn withdraw_from_stream(ctx: Context<WithdrawFromStream>) -> Result<()> {
+        let now = Clock::get()?.unix_timestamp;
+        let stream = &mut ctx.accounts.stream;
+        let amount = stream.withdrawable(now);
+        require!(amount > 0, ObscuraError::NothingVested);
+
+        stream.withdrawn = stream
+            .withdrawn
+            .checked_add(amount)
+            .ok_or(ObscuraError::MathOverflow)?;
+        **stream.to_account_info().try_borrow_mut_lamports()? -= amount;
+        **ctx
+            .accounts
+            .recipient_vault
+            .to_account_info()
+            .try_borrow_mut_lamports()? += amount;
+
+        let recipient_vault = &mut ctx.accounts.recipient_vault;
+        recipient_vault.credit(amount)?;
+        recipient_vault.last_activity = now;
+
+        msg!(
+            "Stream {} withdrawal: {} lamports to vault {} ({} withdrawn)",
+            stream.stream_id,
+            amount,
+            recipient_vault.vault_id,
+            stream.withdrawn
+        );
+        Ok(())
+    }
+
+    /// Commit a delegated stream and hand it back to L1 (paying vault
+    /// owner or recipient vault owner).
+    pub fn undelegate_stream(ctx: Context<UndelegateStream>) -> Result<()> {
+        let authority = ctx.accounts.authority.key();
+        require!(
+            authority == ctx.accounts.stream.owner
+                || authority == ctx.accounts.recipient_vault.owner,
+            ObscuraError::Unauthorized
+        );
+
+        msg!("Undelegating stream {}", ctx.accounts.stream.stream_id);
+
+        ctx.accounts.commit_and_undelegate_stream()?;
+
+        Ok(())
+    }
+
+    /// Stop a stream (paying vault owner, L1).  The unvested remainder
+    /// goes back to the vault and the stream ends now; what has already
+    /// vested stays claimable.  The stream account is closed once nothing
+    /// is left for the recipient, so call this again after their last
+    /// withdrawal to get the rent back.
+    pub fn close_stream(ctx: Context<CloseStream>) -> Result<()> {
+        let now = Clock::get()?.unix_timestamp;
+        let stream = &mut ctx.accounts.stream;
+        let total = stream.total().ok_or(ObscuraError::MathOverflow)?;
+        stream.end = stream.end.min(now.max(stream.start));
+        let refund = total - stream.total().ok_or(ObscuraError::MathOverflow)?;
+
+        if refund > 0 {
+            **stream.to_account_info().try_borrow_mut_lamports()? -= refund;
+            **ctx
+                .accounts
+                .vault
+                .to_account_info()
+                .try_borrow_mut_lamports()? += refund;
+        }
+        let vault = &mut ctx.accounts.vault;
+        vault.credit(refund)?;
+        vault.last_activity = now;
+
+        msg!(
+            "Stream {} stopped, {} lamports returned",
+            stream.stream_id,
+            refund
+        );
+        if stream.withdrawable(now) == 0 {
+            ctx.accounts
+                .stream
+                .close(ctx.accounts.owner.to_account_info())?;
+        }
+        Ok(())
+    }
+
+    // -----------------------------------------------------------------------
+    // NFT custody
+    // -----------------------------------------------------------------------
+
+    /// Move a Metaplex NFT into the vault's associated token account.
+    ///
+    /// Goes through Token Metadata's `TransferV1`, so programmable NFTs
+    /// work too: pass both token records (and the authorization rules
+    /// accounts if the pNFT has a rule set); leave them out for regular
+    /// NFTs.
+    pub fn deposit_nft(ctx: Context<DepositNft>) -> Result<()> {
+        require!(
+            !ctx.accounts.vault.is_delegated,
+            ObscuraError::AccountDelegated
+        );
+        let a = &ctx.accounts;
+
+        TransferV1CpiBuilder::new(&a.token_metadata_program)
+            .token(&a.depositor_token_account)
+            .token_owner(&a.depositor)
+            .destination_token(&a.vault_token_account)
+            .destination_owner(&a.vault.to_account_info())
+            .mint(&a.mint.to_account_info())
+            .metadata(&a.metadata)
+            .edition(Some(&a.edition))
+            .token_record(a.owner_token_record.as_ref().map(|r| r.as_ref()))
+            .destination_token_record(a.vault_token_record.as_ref().map(|r| r.as_ref()))
+            .authority(&a.depositor)
+            .payer(&a.depositor)
+            .system_program(&a.system_program)
+            .sysvar_instructions(&a.sysvar_instructions)
+            .spl_token_program(&a.token_program)
+            .spl_ata_program(&a.associated_token_program)
+            .authorization_rules_program(a.authorization_rules_program.as_ref().map(|r| r.as_ref()))
+            .authorization_rules(a.authorization_rules.as_ref().map(|r| r.as_ref()))
+            .amount(1)
+            .invoke()?;
+
+        let record = &mut ctx.accounts.nft_record;
+        record.vault = ctx.accounts.vault.key();
+        record.mint = ctx.accounts.mint.key();
+        record.deposited_at = Clock::get()?.unix_timestamp;
+
+        let vault = &mut ctx.accounts.vault;
+        vault.last_activity = record.deposited_at;
+
+        msg!(
+            "NFT {} deposited into vault {}",
+            record.mint,
+            vault.vault_id
+        );
+        Ok(())
+    }
+
+    /// Move a custodied NFT back to the owner and close its record.
+    pub fn withdraw_nft(ctx: Context<WithdrawNft>) -> Result<()> {
+        let vault = &ctx.accounts.vault;
+        require!(!vault.is_delegated, ObscuraError::AccountDelegated);
+        vault.require_outbound_allowed()?;
+        vault.require_cosigned(ctx.accounts.cosigner.as_ref().map(|c| c.key()))?;
+        vault.require_acknowledged_upgrade()?;
+
+        let vault_id = vault.vault_id.to_le_bytes();
+        let bump = [ctx.bumps.vault];
+        let signer_seeds: &[&[&[u8]]] = &[&[VAULT_SEED, &vault_id, &bump]];
+        let a = &ctx.accounts;
+        let vault_info = a.vault.to_account_info();
+
+        TransferV1CpiBuilder::new(&a.token_metadata_program)
+            .token(&a.vault_token_account)
+            .token_owner(&vault_info)
+            .destination_token(&a.owner_token_account)
+            .destination_owner(&a.owner)
+            .mint(&a.mint.to_account_info())
+            .metadata(&a.metadata)
+            .edition(Some(&a.edition))
+            .token_record(a.vault_token_record.as_ref().map(|r| r.as_ref()))
+            .destination_token_record(a.owner_token_record.as_ref().map(|r| r.as_ref()))
+            .authority(&vault_info)
+            .payer(&a.owner)
+            .system_program(&a.system_program)
+            .sysvar_instructions(&a.sysvar_instructions)
+            .spl_token_program(&a.token_program)
+            .spl_ata_program(&a.associated_token_program)
+            .authorization_rules_program(a.authorization_rules_program.as_ref().map(|r| r.as_ref()))
+            .authorization_rules(a.authorization_rules.as_ref().map(|r| r.as_ref()))
+            .amount(1)
+            .invoke_signed(signer_seeds)?;
+
+        let vault = &mut ctx.accounts.vault;
+        vault.last_activity = Clock::get()?.unix_timestamp;
+
+        msg!(
+            "NFT {} withdrawn from vault {}",
+            ctx.accounts.mint.key(),
+            vault.vault_id
+        );
+        Ok(())
+    }
+
+    /// Transfer a compressed NFT (Bubblegum) to the vault PDA as leaf
+    /// owner.  The Merkle proof goes in `remaining_accounts`.
+    pub fn deposit_cnft<'info>(
+        ctx: Context<'_, '_, 'info, 'info, DepositCnft<'info>>,
+        root: [u8; 32],
+        data_hash: [u8; 32],
+        creator_hash: [u8; 32],
+        nonce: u64,
+        index: u32,
+    ) -> Result<()> {
+        require!(
+            !ctx.accounts.vault.is_delegated,
+            ObscuraError::AccountDelegated
+        );
+        let a = &ctx.accounts;
+        let proof: Vec<_> = ctx
+            .remaining_accounts
+            .iter()
+            .map(|account| (account, false, false))
+            .collect();
+
+        TransferCpiBuilder::new(&a.bubblegum_program)
+            .tree_config(&a.tree_config)
+            .leaf_owner(&a.depositor, true)
+            .leaf_delegate(&a.leaf_delegate, false)
+            .new_leaf_owner(&a.vault.to_account_info())
+            .merkle_tree(&a.merkle_tree)
+            .log_wrapper(&a.log_wrapper)
+            .compression_program(&a.compression_program)
+            .system_program(&a.system_program)
+            .root(root)
+            .data_hash(data_hash)
+            .creator_hash(creator_hash)
+            .nonce(nonce)
+            .index(index)
+            .add_remaining_accounts(&proof)
+            .invoke()?;
+
+        let record = &mut ctx.accounts.cnft_record;
+        record.vault = ctx.accounts.vault.key();
+        record.asset_id = get_asset_id(&ctx.accounts.merkle_tree.key(), nonce);
+        record.merkle_tree = ctx.accounts.merkle_tree.key();
+        record.nonce = nonce;
+        record.deposited_at = Clock::get()?.unix_timestamp;
+
+        let vault = &mut ctx.accounts.vault;
+        vault.last_activity = record.deposited_at;
+
+        msg!(
+            "cNFT {} deposited into vault {}",
+            record.asset_id,
+            vault.vault_id
+        );
+        Ok(())
+    }
+
+    /// Transfer a custodied compressed NFT back to the owner, signing as
+    /// leaf owner with the vault PDA.  The proof goes in
+    /// `remaining_accounts`.
+    pub fn withdraw_cnft<'info>(
+        ctx: Context<'_, '_, 'info, 'info, WithdrawCnft<'info>>,
+        root: [u8; 32],
+        data_hash: [u8; 32],
+        creator_hash: [u8; 32],
+        nonce: u64,
+        index: u32,
+    ) -> Result<()> {
+        let vault = &ctx.accounts.vault;
+        require!(!vault.is_delegated, ObscuraError::AccountDelegated);
+        vault.require_outbound_allowed()?;
+        vault.require_cosigned(ctx.accounts.cosigner.as_ref().map(|c| c.key()))?;
+        vault.require_acknowledged_upgrade()?;
+
+        let vault_id = vault.vault_id.to_le_bytes();
+        let bump = [ctx.bumps.vault];
+        let signer_seeds: &[&[&[u8]]] = &[&[VAULT_SEED, &vault_id, &bump]];
+        let a = &ctx.accounts;
+        let vault_info = a.vault.to_account_info();
+        let proof: Vec<_> = ctx
+            .remaining_accounts
+            .iter()
+            .map(|account| (account, false, false))
+            .collect();
+
+        TransferCpiBuilder::new(&a.bubblegum_program)
+            .tree_config(&a.tree_config)
+            .leaf_owner(&vault_info, true)
+            .leaf_delegate(&vault_info, false)
+            .new_leaf_owner(&a.owner)
+            .merkle_tree(&a.merkle_tree)
+            .log_wrapper(&a.log_wrapper)
+            .compression_program(&a.compression_program)
+            .system_program(&a.system_program)
+            .root(root)
+            .data_hash(data_hash)
+            .creator_hash(creator_hash)
+            .nonce(nonce)
+            .index(index)
+            .add_remaining_accounts(&proof)
+            .invoke_signed(signer_seeds)?;
+
+        let vault = &mut ctx.accounts.vault;
+        vault.last_activity = Clock::get()?.unix_timestamp;
+
+        msg!(
+            "cNFT {} withdrawn from vault {}",
+            ctx.accounts.cnft_record.asset_id,
+            vault.vault_id
+        );
+        Ok(())
+    }
+
+    // -----------------------------------------------------------------------
+    // Social recovery
+    // -----------------------------------------------------------------------
+
+    /// Add a recovery guardian and set how many guardians must agree to
+    /// rotate the owner key (owner only).  Cancels any pending recovery.
+    pub fn add_guardian(ctx: Context<AddGuardian>, guardian: Pubkey, threshold: u8) -> Result<()> {
+        let guardian_set = &mut ctx.accounts.guardian_set;
+        guardian_set.vault = ctx.accounts.vault.key();
+        require!(
+            guardian != Pubkey::default()
+                && guardian != ctx.accounts.owner.key()
+                && !guardian_set.guardians.contains(&guardian)
+                && guardian_set.guardians.len() < MAX_GUARDIANS,
+            ObscuraError::InvalidGuardianSet
+        );
+        guardian_set.guardians.push(guardian);
+        guardian_set.set_threshold(threshold)?;
+
+        msg!(
+            "Guardian {} added to vault {} ({}-of-{})",
+            guardian,
+            ctx.accounts.vault.vault_id,
+            threshold,
+            guardian_set.guardians.len()
+        );
+        Ok(())
+    }
+
+    /// Remove a guardian and set the new threshold (owner only).
+    /// Cancels any pending recovery.
+    pub fn remove_guardian(
+        ctx: Context<UpdateGuardians>,
+        guardian: Pubkey,
+        threshold: u8,
+    ) -> Result<()> {
+        let guardian_set = &mut ctx.accounts.guardian_set;
+        let index = guardian_set
+            .guardians
+            .iter()
+            .position(|g| *g == guardian)
+            .ok_or(ObscuraError::InvalidGuardianSet)?;
+        guardian_set.guardians.remove(index);
+        guardian_set.set_threshold(threshold)?;
+
+        msg!(
+            "Guardian {} removed from vault {} ({}-of-{})",
+            guardian,
+            ctx.accounts.vault.vault_id,
+            threshold,
+            guardian_set.guardians.len()
+        );
+        Ok(())
+    }
+
+    /// Start rotating the owner key to `new_owner` (guardians only).
+    /// Counts as the initiating guardian's approval.
+    pub fn initiate_recovery(ctx: Context<GuardianAction>, new_owner: Pubkey) -> Result<()> {
+        require!(
+            new_owner != Pubkey::default(),
+            ObscuraError::InvalidGuardianSet
+        );
+        let now = Clock::get()?.unix_timestamp;
+        let guardian_set = &mut ctx.accounts.guardian_set;
+        let index = guardian_set.require_guardian(&ctx.accounts.guardian.key())?;
+        let recovery = &guardian_set.recovery;
+        require!(
+            recovery.new_owner == Pubkey::default()
+                || (recovery.ready_at == 0
+                    && now >= recovery.initiated_at + RECOVERY_CHALLENGE_PERIOD),
+            ObscuraError::RecoveryPending
+        );
+
+        guardian_set.recovery = RecoveryRequest {
+            new_owner,
+            approvals: 0,
+            initiated_at: now,
+            ready_at: 0,
+        };
+        guardian_set.approve(index, now);
+
+        msg!(
+            "Recovery of vault {} to {} initiated by {}",
+            ctx.accounts.vault.vault_id,
+            new_owner,
+            ctx.accounts.guardian.key()
+        );
+        Ok(())
+    }
+
+    /// Approve the pending recovery (guardians only).  Once `threshold`
+    /// guardians agree, the challenge period starts.
+    pub fn approve_recovery(ctx: Context<GuardianAction>) -> Result<()> {
+        let guardian_set = &mut ctx.accounts.guardian_set;
+        let index = guardian_set.require_guardian(&ctx.accounts.guardian.key())?;
+        require!(
+            guardian_set.recovery.new_owner != Pubkey::default(),
+            ObscuraError::NoRecovery
+        );
+        require!(
+            guardian_set.recovery.approvals & (1 << index) == 0,
+            ObscuraError::AlreadyApproved
+        );
+        guardian_set.approve(index, Clock::get()?.unix_timestamp);
+
+        msg!(
+            "Recovery of vault {} approved by {} ({}/{})",
+            ctx.accounts.vault.vault_id,
+            ctx.accounts.guardian.key(),
+            guardian_set.recovery.approvals.count_ones(),
+            guardian_set.threshold
+        );
+        Ok(())
+    }
+
+    /// Cancel the pending recovery (owner only).
+    pub fn cancel_recovery(ctx: Context<UpdateGuardians>) -> Result<()> {
+        let guardian_set = &mut ctx.accounts.guardian_set;
+        require!(
+            guardian_set.recovery.new_owner != Pubkey::default(),
+            ObscuraError::NoRecovery
+        );
+        guardian_set.recovery = RecoveryRequest::default();
+
+        msg!(
+            "Recovery of vault {} cancelled",
+            ctx.accounts.vault.vault_id
+        );
+        Ok(())
+    }
+
+    /// Rotate the owner key once the recovery has been approved and its
+    /// `RECOVERY_CHALLENGE_PERIOD` has passed.  Permissionless.
+    ///
+    /// Only the owner changes; policies, permissions and guardians stay
+    /// in place for the new key to review.
+    pub fn finalize_recovery(ctx: Context<FinalizeRecovery>) -> Result<()> {
+        let now = Clock::get()?.unix_timestamp;
+        let guardian_set = &mut ctx.accounts.guardian_set;
+        let recovery = guardian_set.recovery;
+        require!(
+            recovery.new_owner != Pubkey::default(),
+            ObscuraError::NoRecovery
+        );
+        require!(
+            recovery.ready_at != 0 && now >= recovery.ready_at + RECOVERY_CHALLENGE_PERIOD,
+            ObscuraError::RecoveryNotReady
+        );
+
+        let vault = &mut ctx.accounts.vault;
+        require!(!vault.is_delegated, ObscuraError::AccountDelegated);
+        let old_owner = vault.owner;
+        vault.owner = recovery.new_owner;
+        vault.last_activity = now;
+        guardian_set.recovery = RecoveryRequest::default();
+
+        msg!(
+            "Vault {} recovered: {} -> {}",
+            vault.vault_id,
+            old_owner,
+            recovery.new_owner
+        );
+        Ok(())
+    }
+
+    // -----------------------------------------------------------------------
+    // Inheritance
+    // -----------------------------------------------------------------------
+
+    /// Name a beneficiary who may take over the vault with
+    /// `claim_inherited_vault` once it has been inactive for
+    /// `inactivity_period` seconds (owner only).  `Pubkey::default()`
+    /// removes the beneficiary.
+    ///
+    /// Any owner activity, including `heartbeat`, resets the clock.
+    pub fn set_beneficiary(
+        ctx: Context<SetVaultPolicy>,
+        beneficiary: Pubkey,
+        inactivity_period: i64,
+    ) -> Result<()> {
+        let vault = &mut ctx.accounts.vault;
+        require!(!vault.is_delegated, ObscuraError::AccountDelegated);
+        if beneficiary == Pubkey::default() {
+            vault.inheritance_period = 0;
+        } else {
+            require!(
+                beneficiary != vault.owner && inactivity_period >= MIN_INHERITANCE_PERIOD,
+                ObscuraError::InvalidBeneficiary
+            );
+            vault.inheritance_period = inactivity_period;
+        }
+        vault.beneficiary = beneficiary;
+        vault.last_activity = Clock::get()?.unix_timestamp;
+
+        msg!(
+            "Vault {} beneficiary set to {} after {}s of inactivity",
+            vault.vault_id,
+            beneficiary,
+            vault.inheritance_period
+        );
+        Ok(())
+    }
+
+    /// Take ownership of an inactive vault (beneficiary only).
+    ///
+    /// As with `finalize_recovery`, only the owner changes; policies,
+    /// permissions and guardians stay in place for the new owner to
+    /// review.  A delegated vault must first be undelegated, e.g. by the
+    /// inactivity sweep.
+    pub fn claim_inherited_vault(ctx: Context<ClaimInheritedVault>) -> Result<()> {
+        let now = Clock::get()?.unix_timestamp;
+        let vault = &mut ctx.accounts.vault;
+        require!(!vault.is_delegated, ObscuraError::AccountDelegated);
+        require!(
+            now >= vault.last_activity.saturating_add(vault.inheritance_period),
+            ObscuraError::VaultStillActive
+        );
+
+        let old_owner = vault.owner;
+        vault.owner = vault.beneficiary;
+        vault.beneficiary = Pubkey::default();
+        vault.inheritance_period = 0;
+        vault.last_activity = now;
+
+        msg!(
+            "Vault {} inherited: {} -> {}",
+            vault.vault_id,
+            old_owner,
+            vault.owner
+        );
+        Ok(())
+    }
+
+    // -----------------------------------------------------------------------
+    // Multisig ownership
+    // -----------------------------------------------------------------------
+
+    /// Hand the vault over to an M-of-N signer set (owner only).
+    ///
+    /// The vault's owner becomes the `VaultOwners` PDA.  From then on any
+    /// owner instruction is run through `propose_action` /
+    /// `approve_action` / `execute_action`, which invokes this program
+    /// with the PDA as signer once `threshold` signers have approved.
+    /// Instructions where the owner pays rent cannot be run this way.
+    /// Withdrawals pay the PDA; `sweep_vault_owners` moves them on.
+    pub fn convert_to_multisig(
+        ctx: Context<ConvertToMultisig>,
+        signers: Vec<Pubkey>,
+        threshold: u8,
+    ) -> Result<()> {
+        let vault = &mut ctx.accounts.vault;
+        require!(!vault.is_delegated, ObscuraError::AccountDelegated);
+
+        let vault_owners = &mut ctx.accounts.vault_owners;
+        vault_owners.vault = vault.key();
+        vault_owners.action_count = 0;
+        vault_owners.owners_epoch = 0;
+        vault_owners.set_signers(signers, threshold)?;
+        vault.owner = vault_owners.key();
+        vault.last_activity = Clock::get()?.unix_timestamp;
+
+        msg!(
+            "Vault {} converted to {}-of-{} multisig",
+            vault.vault_id,
+            threshold,
+            vault_owners.signers.len()
+        );
+        Ok(())
+    }
+
+    /// Replace the signer set and threshold.  Only callable through
+    /// `execute_action`; pending actions approved under the old set
+    /// become void.
+    pub fn set_vault_owners(
+        ctx: Context<SetVaultOwners>,
+        signers: Vec<Pubkey>,
+        threshold: u8,
+    ) -> Result<()> {
+        let vault_owners = &mut ctx.accounts.vault_owners;
+        vault_owners.set_signers(signers, threshold)?;
+        vault_owners.owners_epoch = vault_owners
+            .owners_epoch
+            .checked_add(1)
+            .ok_or(ObscuraError::MathOverflow)?;
+
+        msg!(
+            "Vault {} owners set to {}-of-{}",
+            vault_owners.vault,
+            threshold,
+            vault_owners.signers.len()
+        );
+        Ok(())
+    }
+
+    /// Move every lamport the `VaultOwners` PDA holds above its rent
+    /// (e.g. withdrawals from the vault) to `destination`.  Only callable
+    /// through `execute_action`.
+    pub fn sweep_vault_owners(ctx: Context<SweepVaultOwners>) -> Result<()> {
+        let info = ctx.accounts.vault_owners.to_account_info();
+        let amount = info
+            .lamports()
+            .saturating_sub(Rent::get()?.minimum_balance(info.data_len()));
+        require!(amount > 0, ObscuraError::InsufficientBalance);
+
+        **info.try_borrow_mut_lamports()? -= amount;
+        **ctx.accounts.destination.try_borrow_mut_lamports()? += amount;
+
+        msg!(
+            "Swept {} lamports from the owners of vault {} to {}",
+            amount,
+            ctx.accounts.vault_owners.vault,
+            ctx.accounts.destination.key()
+        );
+        Ok(())
+    }
+
+    /// Propose an instruction of this program to run with the
+    /// `VaultOwners` PDA as signer (signers only).  Counts as the
+    /// proposer's approval.
+    pub fn propose_action(
+        ctx: Context<ProposeAction>,
+        data: Vec<u8>,
+        accounts: Vec<ActionAccount>,
+    ) -> Result<()> {
+        require!(
+            data.len() <= MAX_ACTION_DATA_LEN && accounts.len() <= MAX_ACTION_ACCOUNTS,
+            ObscuraError::InvalidAction
+        );
+        let vault_owners = &mut ctx.accounts.vault_owners;
+        let index = vault_owners.require_signer(&ctx.accounts.proposer.key())?;
+
+        let action_id = vault_owners.action_count;
+        vault_owners.action_count = action_id.checked_add(1).ok_or(ObscuraError::MathOverflow)?;
+        ctx.accounts.action.set_inner(MultisigAction {
+            vault_owners: vault_owners.key(),
+            action_id,
+            proposer: ctx.accounts.proposer.key(),
+            owners_epoch: vault_owners.owners_epoch,
+            data,
+            accounts,
+            approvals: 1 << index,
+            created_at: Clock::get()?.unix_timestamp,
+        });
+
+        msg!(
+            "Action {} proposed on vault {} by {}",
+            action_id,
+            vault_owners.vault,
+            ctx.accounts.proposer.key()
+        );
+        Ok(())
+    }
+
+    /// Approve a pending action (signers only).
+    pub fn approve_action(ctx: Context<ApproveAction>) -> Result<()> {
+        let vault_owners = &ctx.accounts.vault_owners;
+        let index = vault_owners.require_signer(&ctx.accounts.signer.key())?;
+
+        let action = &mut ctx.accounts.action;
+        require!(
+            action.owners_epoch == vault_owners.owners_epoch,
+            ObscuraError::InvalidAction
+        );
+        require!(
+            action.approvals & (1 << index) == 0,
+            ObscuraError::AlreadyApproved
+        );
+        action.approvals |= 1 << index;
+
+        msg!(
+            "Action {} on vault {} approved by {} ({}/{})",
+            action.action_id,
+            vault_owners.vault,
+            ctx.accounts.signer.key(),
+            action.approvals.count_ones(),
+            vault_owners.threshold
+        );
+        Ok(())
+    }
+
+    /// Run an action once `threshold` signers have approved it (signers
+    /// only), closing it and refunding the proposer.
+    ///
+    /// The action's accounts, in order, are passed as remaining accounts;
+    /// the `VaultOwners` PDA signs wherever it appears.
+    pub fn execute_action<'info>(
+        ctx: Context<'_, '_, 'info, 'info, ExecuteAction<'info>>,
+    ) -> Result<()> {
+        let vault_owners = &ctx.accounts.vault_owners;
+        vault_owners.require_signer(&ctx.accounts.executor.key())?;
+
+        let action = &ctx.accounts.action;
+        require!(
+            action.owners_epoch == vault_owners.owners_epoch,
+            ObscuraError::InvalidAction
+        );
+        require!(
+            action.approvals.count_ones() >= vault_owners.threshold as u32,
+            ObscuraError::ThresholdNotMet
+        );
+        require!(
+            ctx.remaining_accounts.len() == action.accounts.len()
+                && ctx
+                    .remaining_accounts
+                    .iter()
+                    .zip(&action.accounts)
+                    .all(|(info, meta)| info.key() == meta.pubkey),
+            ObscuraError::InvalidAction
+        );
+
+        let owners_key = vault_owners.key();
+        let metas = action
+            .accounts
+            .iter()
+            .map(
+                |meta| anchor_lang::solana_program::instruction::AccountMeta {
+                    pubkey: meta.pubkey,
+                    is_signer: meta.is_signer || meta.pubkey == owners_key,
+                    is_writable: meta.is_writable,
+                },
+            )
+            .collect();
+        let data = action.data.clone();
+        let action_id = action.action_id;
+        let vault = vault_owners.vault;
+        let mut infos = ctx.remaining_accounts.to_vec();
+        infos.push(ctx.accounts.obscura_program.to_account_info());
+
+        // Persist the spent approvals before the CPI so the action cannot
+        // be executed again from inside it.
+        ctx.accounts.action.approvals = 0;
+        ctx.accounts.action.exit(&crate::ID)?;
+
+        let bump = [ctx.bumps.vault_owners];
+        anchor_lang::solana_program::program::invoke_signed(
+            &anchor_lang::solana_program::instruction::Instruction {
+                program_id: crate::ID,
+                accounts: metas,
+                data,
+            },
+            &infos,
+            &[&[VAULT_OWNERS_SEED, vault.as_ref(), &bump]],
+        )?;
+
+        msg!("Action {} executed on vault {}", action_id, vault);
+        Ok(())
+    }
+
+    /// Withdraw a pending action (its proposer only), refunding the rent.
+    pub fn cancel_action(ctx: Context<CancelAction>) -> Result<()> {
+        msg!(
+            "Action {} cancelled on vault {}",
+            ctx.accounts.action.action_id,
+            ctx.accounts.vault_owners.vault
+        );
+        Ok(())
+    }
+
+    // -----------------------------------------------------------------------
+    // Payment intents
+    // -----------------------------------------------------------------------
+
+    /// Pay a payment intent from the vault into the recipient's vault
+    /// (owner only, both vaults on L1).  See `docs/PAYMENT_INTENTS.md`.
+    ///
+    /// The paying wallet checks the requester's envelope signature before
+    /// building this instruction; on chain the intent's version, expiry
+    /// and recipient alias are enforced, and an `IntentReceipt` keyed by
+    /// (recipient vault, reference) makes each request payable once.
+    ///
+    /// `remaining_accounts` holds the receipt accounts and the recipient
+    /// owner when minting receipts, followed by any compliance-hook
+    /// accounts; a hook that needs accounts therefore needs receipts too.
+    pub fn fulfill_intent<'info>(
+        ctx: Context<'_, '_, 'info, 'info, FulfillIntent<'info>>,
+        intent: PaymentIntent,
+    ) -> Result<()> {
+        require!(
+            intent.version == PAYMENT_INTENT_VERSION
+                && intent.amount > 0
+                && intent.asset == Pubkey::default(),
+            ObscuraError::InvalidPaymentIntent
+        );
+        let clock = Clock::get()?;
+        let now = clock.unix_timestamp;
+        require!(now <= intent.expires_at, ObscuraError::PaymentIntentExpired);
+
+        let recipient_vault_key = ctx.accounts.recipient_vault.key();
+        let resolved = match intent.recipient {
+            RecipientAlias::Vault(vault) => vault == recipient_vault_key,
+            RecipientAlias::Contact(contact_hash) => {
+                ctx.accounts.contact_claim.as_ref().is_some_and(|claim| {
+                    claim.contact_hash == contact_hash && claim.vault == recipient_vault_key
+                })
+            }
+        };
+        require!(resolved, ObscuraError::InvalidPaymentIntent);
+
+        let amount = intent.amount;
+        let cosigner = ctx.accounts.cosigner.as_ref().map(|c| c.key());
+        let vault = &mut ctx.accounts.vault;
+        let recipient_vault = &mut ctx.accounts.recipient_vault;
+        require!(
+            !vault.is_delegated && !recipient_vault.is_delegated,
+            ObscuraError::AccountDelegated
+        );
+        require!(vault.balance >= amount, ObscuraError::InsufficientBalance);
+        vault.require_outbound_allowed()?;
+        vault.require_cosigned(cosigner)?;
+        vault.require_acknowledged_upgrade()?;
+        vault.require_allowed_recipient(
+            ctx.accounts.recipient_allowlist.as_deref(),
+            &recipient_vault_key,
+        )?;
+        require_compliant(
+            vault,
+            ctx.accounts.denylist.as_ref(),
+            ctx.accounts.compliance_program.as_ref(),
+            ctx.remaining_accounts
+                .get(receipt::ACCOUNTS + 1..)
+                .unwrap_or(&[]),
+            &recipient_vault_key,
+            amount,
+        )?;
+
+        vault.spending_limit.record(clock.epoch, now, amount)?;
+        vault.debit(amount)?;
+        recipient_vault.credit(amount)?;
+        vault.billing.accrue(now, amount)?;
+        vault.last_activity = now;
+
+        **vault.to_account_info().try_borrow_mut_lamports()? -= amount;
+        **recipient_vault
+            .to_account_info()
+            .try_borrow_mut_lamports()? += amount;
+
+        ctx.accounts.receipt.set_inner(IntentReceipt {
+            payer_vault: vault.key(),
+            recipient_vault: recipient_vault_key,
+            reference: intent.reference,
+            amount,
+            paid_at: now,
+        });
+
+        if !ctx.remaining_accounts.is_empty() {
+            let recipient_owner = ctx
+                .remaining_accounts
+                .get(receipt::ACCOUNTS)
+                .ok_or(ObscuraError::InvalidReceiptAccounts)?;
+            require_keys_eq!(
+                recipient_owner.key(),
+                recipient_vault.owner,
+                ObscuraError::InvalidReceiptAccounts
+            );
+            receipt::mint_receipts(
+                ctx.remaining_accounts,
+                [&ctx.accounts.owner.to_account_info(), recipient_owner],
+                intent.reference,
+            )?;
+        }
+
+        msg!(
+            "Payment intent fulfilled: {} lamports from vault {} to vault {}",
+            amount,
+            vault.vault_id,
+            recipient_vault.vault_id
+        );
+        Ok(())
+    }
+
+    // -----------------------------------------------------------------------
+    // Shared expense groups
+    // -----------------------------------------------------------------------
+
+    /// Create an expense group between the signer's vault and `members`.
+    ///
+    /// Every other member must `join_group` before expenses can be
+    /// logged: joining is consent to `settle_group` later moving the
+    /// member's share out of their vault.
+    pub fn create_group(
+        ctx: Context<CreateGroup>,
+        group_id: u64,
+        members: Vec<Pubkey>,
+    ) -> Result<()> {
+        let creator_vault = ctx.accounts.vault.key();
+        let mut vaults = vec![creator_vault];
+        vaults.extend(members);
+        require!(
+            vaults.len() >= 2
+                && vaults.len() <= MAX_GROUP_MEMBERS
+                && vaults
+                    .iter()
+                    .enumerate()
+                    .all(|(i, v)| *v != Pubkey::default() && !vaults[..i].contains(v)),
+            ObscuraError::InvalidGroup
+        );
+
+        let now = Clock::get()?.unix_timestamp;
+        ctx.accounts.group.set_inner(Group {
+            creator: ctx.accounts.owner.key(),
+            group_id,
+            members: vaults
+                .iter()
+                .map(|vault| GroupMember {
+                    vault: *vault,
+                    joined: *vault == creator_vault,
+                    net: 0,
+                })
+                .collect(),
+            expense_count: 0,
+            created_at: now,
+            settled_at: 0,
+        });
+
+        msg!(
+            "Group {} created by vault {} with {} members",
+            group_id,
+            ctx.accounts.vault.vault_id,
+            vaults.len()
+        );
+        Ok(())
+    }
+
+    /// Join a group the signer's vault was listed in.
+    pub fn join_group(ctx: Context<GroupMemberAction>) -> Result<()> {
+        let group = &mut ctx.accounts.group;
+        let index = group.member_index(&ctx.accounts.vault.key())?;
+        group.members[index].joined = true;
+
+        msg!(
+            "Vault {} joined group {}",
+            ctx.accounts.vault.vault_id,
+            group.group_id
+        );
+        Ok(())
+    }
+
+    /// Log an expense of `amount` lamports paid by the signer's vault and
+    /// shared equally by the members in the `participants` bitmap (the
+    /// lowest-indexed participant absorbs any rounding remainder).
+    ///
+    /// Only balances change; nothing moves until `settle_group`.  Works
+    /// on L1 or inside the ER, wherever the group currently lives.
+    pub fn log_expense(
+        ctx: Context<GroupMemberAction>,
+        amount: u64,
+        participants: u8,
+    ) -> Result<()> {
+        let group = &mut ctx.accounts.group;
+        let payer = group.member_index(&ctx.accounts.vault.key())?;
+        group.log_expense(payer, amount, participants)?;
+
+        msg!(
+            "Group {} expense #{}: {} lamports paid by vault {}, participants={:#b}",
+            group.group_id,
+            group.expense_count,
+            amount,
+            ctx.accounts.vault.vault_id,
+            participants
+        );
+        Ok(())
+    }
+
+    /// Delegate the group to the ER validator its member vaults live on,
+    /// so it can be settled there (any joined member).
+    pub fn delegate_group(ctx: Context<DelegateGroup>, validator: Pubkey) -> Result<()> {
+        let group = &ctx.accounts.group;
+        let index = group.member_index(&ctx.accounts.vault.key())?;
+        require!(group.members[index].joined, ObscuraError::InvalidGroup);
+        let creator = group.creator;
+        let group_id = group.group_id.to_le_bytes();
+
+        msg!(
+            "Delegating group {} to validator {}",
+            group.group_id,
+            validator
+        );
+
+        ctx.accounts.delegate_group(
+            &ctx.accounts.owner,
+            &[GROUP_SEED, creator.as_ref(), &group_id],
+            validator,
+        )?;
+
+        Ok(())
+    }
+
+    /// Settle every open balance in the group with at most
+    /// `members - 1` vault-to-vault transfers, inside the ER (any member).
+    ///
+    /// All member vaults are passed as writable `remaining_accounts`, in
+    /// member order, and must be delegated, followed by each member's
+    /// recipient allowlist (any account for a vault not in allowlist
+    /// mode), then each member's integrator denylist (any account for a
+    /// vault without an integrator), then any compliance-hook accounts.
+    /// Debtor vaults are subject to their receive-only mode, allowlist,
+    /// integrator compliance checks and spending limit as for any
+    /// outflow.
+    /// Balances are zeroed and the group and vaults committed to L1; all
+    /// stay delegated.
+    pub fn settle_group<'info>(
+        ctx: Context<'_, '_, 'info, 'info, SettleGroup<'info>>,
+    ) -> Result<()> {
+        let group = &mut ctx.accounts.group;
+        let members = group.members.len();
+        require!(
+            ctx.remaining_accounts.len() >= 3 * members,
+            ObscuraError::InvalidGroup
+        );
+        let (vault_infos, rest) = ctx.remaining_accounts.split_at(members);
+        let (allowlist_infos, rest) = rest.split_at(members);
+        let (denylist_infos, hook_accounts) = rest.split_at(members);
+
+        let mut vaults = Vec::with_capacity(members);
+        let mut allowlists = Vec::with_capacity(members);
+        let mut denylists = Vec::with_capacity(members);
+        for (((member, info), allowlist_info), denylist_info) in group
+            .members
+            .iter()
+            .zip(vault_infos)
+            .zip(allowlist_infos)
+            .zip(denylist_infos)
+        {
+            require_keys_eq!(info.key(), member.vault, ObscuraError::InvalidGroup);
+            let vault = Account::<VaultState>::try_from(info)?;
+            require!(vault.is_delegated, ObscuraError::NotDelegated);
+            let allowlist = if vault.allowlist_enabled {
+                let allowlist = Account::<RecipientAllowlist>::try_from(allowlist_info)?;
+                require_keys_eq!(allowlist.vault, vault.key(), ObscuraError::InvalidGroup);
+                Some(allowlist)
+            } else {
+                None
+            };
+            if vault.integrator != Pubkey::default() {
+                let (denylist, _) = Pubkey::find_program_address(
+                    &[DENYLIST_SEED, vault.integrator.as_ref()],
+                    &crate::ID,
+                );
+                require_keys_eq!(denylist_info.key(), denylist, ObscuraError::InvalidGroup);
+            }
+            vaults.push(vault);
+            allowlists.push(allowlist);
+            denylists.push(UncheckedAccount::try_from(denylist_info));
+        }
+        let signer = ctx.accounts.member.key();
+        require!(
+            vaults.iter().any(|vault| vault.owner == signer),
+            ObscuraError::Unauthorized
+        );
+
+        let clock = Clock::get()?;
+        let now = clock.unix_timestamp;
+        let transfers = group.settlement();
+        for &(debtor, creditor, amount) in &transfers {
+            let to_key = vaults[creditor].key();
+            let from = &mut vaults[debtor];
+            from.require_outbound_allowed()?;
+            from.require_allowed_recipient(allowlists[debtor].as_deref(), &to_key)?;
+            require_compliant(
+                from,
+                Some(&denylists[debtor]),
+                ctx.accounts.compliance_program.as_ref(),
+                hook_accounts,
+                &to_key,
+                amount,
+            )?;
+            from.spending_limit.record(clock.epoch, now, amount)?;
+            from.debit(amount)?;
+            **from.to_account_info().try_borrow_mut_lamports()? -= amount;
+
+            let to = &mut vaults[creditor];
+            to.credit(amount)?;
+            **to.to_account_info().try_borrow_mut_lamports()? += amount;
+
+            msg!(
+                "Group {} settlement: {} lamports from vault {} to vault {}",
+                group.group_id,
+                amount,
+                group.members[debtor].vault,
+                group.members[creditor].vault
+            );
+        }
+        for member in group.members.iter_mut() {
+            member.net = 0;
+        }
+        group.settled_at = now;
+
+        ctx.accounts.group.exit(&crate::ID)?;
+        let mut accounts = vec![ctx.accounts.group.to_account_info()];
+        for vault in &vaults {
+            vault.exit(&crate::ID)?;
+            accounts.push(vault.to_account_info());
+        }
+        commit_accounts(
+            &ctx.accounts.member,
+            accounts.iter().collect(),
+            &ctx.accounts.magic_context,
+            &ctx.accounts.magic_program,
+        )?;
+
+        Ok(())
+    }
+
+    // -----------------------------------------------------------------------
+    // Point of sale
+    // -----------------------------------------------------------------------
+
+    /// Open a point-of-sale session on the merchant's vault for one shift.
+    ///
+    /// `operator` (typically the till's key) records incoming payments
+    /// for `duration` seconds; the owner closes the session with
+    /// `close_pos_session` at the end of the day.
+    pub fn open_pos_session(
+        ctx: Context<OpenPosSession>,
+        session_id: u64,
+        operator: Pubkey,
+        duration: i64,
+    ) -> Result<()> {
+        require!(
+            operator != Pubkey::default() && duration > 0 && duration <= MAX_POS_SESSION_DURATION,
+            ObscuraError::InvalidPosSession
+        );
+
+        let now = Clock::get()?.unix_timestamp;
+        ctx.accounts.pos_session.set_inner(PosSession {
+            vault: ctx.accounts.vault.key(),
+            session_id,
+            operator,
+            opened_at: now,
+            expires_at: now + duration,
+            payment_count: 0,
+            total: 0,
+            references_digest: [0; 32],
+        });
+
+        msg!(
+            "POS session {} opened on vault {} until {}",
+            session_id,
+            ctx.accounts.vault.vault_id,
+            now + duration
+        );
+        Ok(())
+    }
+
+    /// Record an incoming payment of `amount` identified by `reference`
+    /// (e.g. an invoice id or the hash of the payer's transaction
+    /// signature).  Operator only, while the session is open.
+    pub fn record_pos_payment(
+        ctx: Context<RecordPosPayment>,
+        reference: [u8; 32],
+        amount: u64,
+    ) -> Result<()> {
+        let session = &mut ctx.accounts.pos_session;
+        session.record(reference, amount, Clock::get()?.unix_timestamp)?;
+
+        msg!(
+            "POS session {} payment {}: {} lamports",
+            session.session_id,
+            session.payment_count,
+            amount
+        );
+        Ok(())
+    }
+
+    /// Close a session (owner only), emitting its `PosSessionClosed`
+    /// summary for reconciliation and returning the rent.
+    pub fn close_pos_session(ctx: Context<ClosePosSession>) -> Result<()> {
+        let session = &ctx.accounts.pos_session;
+        emit!(PosSessionClosed {
+            vault: session.vault,
+            session_id: session.session_id,
+            operator: session.operator,
+            opened_at: session.opened_at,
+            closed_at: Clock::get()?.unix_timestamp,
+            payment_count: session.payment_count,
+            total: session.total,
+            references_digest: session.references_digest,
+        });
+
+        msg!(
+            "POS session {} closed: {} payments, {} lamports",
+            session.session_id,
+            session.payment_count,
+            session.total
+        );
+        Ok(())
+    }
+
+    // -----------------------------------------------------------------------
+    // Support tooling
+    // -----------------------------------------------------------------------
+
+    /// Report the health of up to `MAX_INSPECTED_VAULTS` vaults, passed as
+    /// remaining accounts, in one call.
+    ///
+    /// Read-only; meant to be simulated by support tooling, which reads
+    /// the report from the return data.  On L1 a delegated vault shows
+    /// its last committed state; simulating against the ER as well and
+    /// comparing `nonce` gives the operations not yet committed.
+    pub fn inspect_vaults<'info>(
+        ctx: Context<'_, '_, 'info, 'info, InspectVaults>,
+    ) -> Result<Vec<VaultHealth>> {
+        require!(
+            !ctx.remaining_accounts.is_empty()
+                && ctx.remaining_accounts.len() <= MAX_INSPECTED_VAULTS,
+            ObscuraError::InvalidInspection
+        );
+
+        ctx.remaining_accounts
+            .iter()
+            .map(VaultHealth::inspect)
+            .collect()
+    }
+}
+
+// ---------------------------------------------------------------------------
+// Accounts (instruction contexts)
+// ---------------------------------------------------------------------------
+
+#[derive(Accounts)]
+#[instruction(vault_id: u64)]
+pub struct CreateVault<'info> {
+    #[account(
+        init,
+        payer = owner,
+        space = VAULT_STATE_SIZE,
+        seeds = [VAULT_SEED, &vault_id.to_le_bytes()],
+        bump,
+    )]
+    pub vault: Account<'info, VaultState>,
+
+    #[account(mut)]
+    pub owner: Signer<'info>,
+
+    /// Owner's vault index; the new vault is appended when present
+    #[account(
+        mut,
+        seeds = [OWNER_INDEX_SEED, owner.key().as_ref()],
+        bump,
+    )]
+    pub owner_index: Option<Account<'info, OwnerIndex>>,
+
+    /// App the vault is created through; earns a share of its fees
+    pub integrator: Option<Account<'info, Integrator>>,
+
+    pub system_program: Program<'info, System>,
+}
+
+impl<'info> CreateVault<'info> {
+    /// The attributed integrator (default = none).
+    fn integrator_key(&self) -> Pubkey {
+        self.integrator
+            .as_ref()
+            .map_or(Pubkey::default(), |integrator| integrator.key())
+    }
+}
+
+#[derive(Accounts)]
+pub struct InitOwnerIndex<'info> {
+    #[account(
+        init,
+        payer = owner,
+        space = 8 + OwnerIndex::INIT_SPACE,
+        seeds = [OWNER_INDEX_SEED, owner.key().as_ref()],
+        bump,
+    )]
+    pub owner_index: Account<'info, OwnerIndex>,
+
+    #[account(mut)]
+    pub owner: Signer<'info>,
+
+    pub system_program: Program<'info, System>,
+}
+
+#[derive(Accounts)]
+pub struct IndexVault<'info> {
+    #[account(
+        mut,
+        seeds = [OWNER_INDEX_SEED, owner.key().as_ref()],
+        bump,
+    )]
+    pub owner_index: Account<'info, OwnerIndex>,
+
+    #[account(has_one = owner @ ObscuraError::Unauthorized)]
+    pub vault: Account<'info, VaultState>,
+
+    pub owner: Signer<'info>,
+}
+
+#[derive(Accounts)]
+pub struct UpdateOwnerIndex<'info> {
+    #[account(
+        mut,
+        seeds = [OWNER_INDEX_SEED, owner.key().as_ref()],
+        bump,
+    )]
+    pub owner_index: Account<'info, OwnerIndex>,
+
+    pub owner: Signer<'info>,
+}
+
+#[derive(Accounts)]
+pub struct ClaimFullCustody<'info> {
+    #[account(
+        mut,
+        seeds = [VAULT_SEED, &vault.vault_id.to_le_bytes()],
+        bump,
+    )]
+    pub vault: Account<'info, VaultState>,
+
+    pub claimant: Signer<'info>,
+
+    pub new_owner: Signer<'info>,
+}
+
+#[derive(Accounts)]
+#[instruction(contact_hash: [u8; 32])]
+pub struct RegisterContactClaim<'info> {
+    #[account(
+        init,
+        payer = owner,
+        space = 8 + ContactClaim::INIT_SPACE,
+        seeds = [CONTACT_SEED, contact_hash.as_ref()],
+        bump,
+    )]
+    pub contact_claim: Account<'info, ContactClaim>,
+
+    #[account(
+        has_one = owner @ ObscuraError::Unauthorized,
+        constraint = vault.custody_claimant == contact_claim.key() @ ObscuraError::InvalidClaimant,
+    )]
+    pub vault: Account<'info, VaultState>,
+
+    #[account(mut)]
+    pub owner: Signer<'info>,
+
+    pub system_program: Program<'info, System>,
+}
+
+#[derive(Accounts)]
+pub struct ClaimContactVault<'info> {
+    #[account(
+        mut,
+        close = new_owner,
+        seeds = [CONTACT_SEED, contact_claim.contact_hash.as_ref()],
+        bump,
+        has_one = vault,
+    )]
+    pub contact_claim: Account<'info, ContactClaim>,
+
+    #[account(
+        mut,
+        seeds = [VAULT_SEED, &vault.vault_id.to_le_bytes()],
+        bump,
+    )]
+    pub vault: Account<'info, VaultState>,
+
+    #[account(
+        seeds = [CONFIG_SEED],
+        bump,
+        has_one = claim_attestor @ ObscuraError::Unauthorized,
+    )]
+    pub config: Account<'info, ProgramConfig>,
+
+    pub claim_attestor: Signer<'info>,
+
+    #[account(mut)]
+    pub new_owner: Signer<'info>,
+}
+
+#[derive(Accounts)]
+#[instruction(goal_id: u64)]
+pub struct CreateGoal<'info> {
+    #[account(
+        init,
+        payer = owner,
+        space = 8 + SavingsGoal::INIT_SPACE,
+        seeds = [GOAL_SEED, vault.key().as_ref(), &goal_id.to_le_bytes()],
+        bump,
+    )]
+    pub goal: Account<'info, SavingsGoal>,
+
+    #[account(
+        mut,
+        seeds = [VAULT_SEED, &vault.vault_id.to_le_bytes()],
+        bump,
+        has_one = owner @ ObscuraError::Unauthorized,
+    )]
+    pub vault: Account<'info, VaultState>,
+
+    #[account(mut)]
+    pub owner: Signer<'info>,
+
+    pub system_program: Program<'info, System>,
+}
+
+#[derive(Accounts)]
+pub struct CloseGoal<'info> {
+    #[account(
+        mut,
+        close = owner,
+        seeds = [GOAL_SEED, vault.key().as_ref(), &goal.goal_id.to_le_bytes()],
+        bump,
+        has_one = vault,
+    )]
+    pub goal: Account<'info, SavingsGoal>,
+
+    #[account(
+        mut,
+        seeds = [VAULT_SEED, &vault.vault_id.to_le_bytes()],
+        bump,
+        has_one = owner @ ObscuraError::Unauthorized,
+    )]
+    pub vault: Account<'info, VaultState>,
+
+    #[account(mut)]
+    pub owner: Signer<'info>,
+}
+
+#[derive(Accounts)]
+pub struct BreakGoal<'info> {
+    #[account(
+        mut,
+        close = owner,
+        seeds = [GOAL_SEED, vault.key().as_ref(), &goal.goal_id.to_le_bytes()],
+        bump,
+        has_one = vault,
+    )]
+    pub goal: Account<'info, SavingsGoal>,
+
+    #[account(
+        mut,
+        seeds = [VAULT_SEED, &vault.vault_id.to_le_bytes()],
+        bump,
+        has_one = owner @ ObscuraError::Unauthorized,
+    )]
+    pub vault: Account<'info, VaultState>,
+
+    #[account(mut, seeds = [SAFETY_FUND_SEED], bump)]
+    pub safety_fund: Account<'info, SafetyFund>,
+
+    #[account(mut)]
+    pub owner: Signer<'info>,
+}
+
+#[derive(Accounts)]
+#[instruction(change_id: u64)]
+pub struct QueueConfigChange<'info> {
+    #[account(
+        seeds = [CONFIG_SEED],
+        bump,
+        has_one = governance @ ObscuraError::Unauthorized,
+    )]
+    pub config: Account<'info, ProgramConfig>,
+
+    #[account(
+        init,
+        payer = governance,
+        space = 8 + QueuedConfigChange::INIT_SPACE,
+        seeds = [CONFIG_CHANGE_SEED, &change_id.to_le_bytes()],
+        bump,
+    )]
+    pub config_change: Account<'info, QueuedConfigChange>,
+
+    #[account(mut)]
+    pub governance: Signer<'info>,
+
+    pub system_program: Program<'info, System>,
+}
+
+#[derive(Accounts)]
+pub struct VoteOnConfigChange<'info> {
+    #[account(
+        seeds = [VAULT_SEED, &vault.vault_id.to_le_bytes()],
+        bump,
+        has_one = owner @ ObscuraError::Unauthorized,
+    )]
+    pub vault: Account<'info, VaultState>,
+
+    #[account(
+        mut,
+        seeds = [CONFIG_CHANGE_SEED, &config_change.change_id.to_le_bytes()],
+        bump,
+    )]
+    pub config_change: Account<'info, QueuedConfigChange>,
+
+    #[account(
+        init,
+        payer = owner,
+        space = 8 + VoteRecord::INIT_SPACE,
+        seeds = [VOTE_RECORD_SEED, config_change.key().as_ref(), vault.key().as_ref()],
+        bump,
+    )]
+    pub vote_record: Account<'info, VoteRecord>,
+
+    #[account(mut)]
+    pub owner: Signer<'info>,
+
+    pub system_program: Program<'info, System>,
+}
+
+#[derive(Accounts)]
+pub struct ExecuteConfigChange<'info> {
+    #[account(
+        mut,
+        seeds = [CONFIG_SEED],
+        bump,
+        has_one = governance,
+    )]
+    pub config: Account<'info, ProgramConfig>,
+
+    #[account(
+        mut,
+        close = governance,
+        seeds = [CONFIG_CHANGE_SEED, &config_change.change_id.to_le_bytes()],
+        bump,
+    )]
+    pub config_change: Account<'info, QueuedConfigChange>,
+
+    /// CHECK: rent refund destination, checked against the config
+    #[account(mut)]
+    pub governance: UncheckedAccount<'info>,
+}
+
+#[derive(Accounts)]
+pub struct CancelConfigChange<'info> {
+    #[account(
+        seeds = [CONFIG_SEED],
+        bump,
+        has_one = governance @ ObscuraError::Unauthorized,
+    )]
+    pub config: Account<'info, ProgramConfig>,
+
+    #[account(
+        mut,
+        close = governance,
+        seeds = [CONFIG_CHANGE_SEED, &config_change.change_id.to_le_bytes()],
+        bump,
+    )]
+    pub config_change: Account<'info, QueuedConfigChange>,
+
+    #[account(mut)]
+    pub governance: Signer<'info>,
+}
+
+#[derive(Accounts)]
+pub struct CloseVoteRecord<'info> {
+    #[account(
+        mut,
+        close = voter,
+        seeds = [VOTE_RECORD_SEED, config_change.key().as_ref(), vote_record.vault.as_ref()],
+        bump,
+        has_one = config_change,
+        has_one = voter,
+    )]
+    pub vote_record: Account<'info, VoteRecord>,
+
+    /// CHECK: the voted-on change, checked against the record; only its
+    /// emptiness is read
+    pub config_change: UncheckedAccount<'info>,
+
+    /// CHECK: rent refund destination, checked against the record
+    #[account(mut)]
+    pub voter: UncheckedAccount<'info>,
+}
+
+#[derive(Accounts)]
+pub struct InitSafetyFund<'info> {
+    #[account(
+        init,
+        payer = governance,
+        space = 8 + SafetyFund::INIT_SPACE,
+        seeds = [SAFETY_FUND_SEED],
+        bump,
+    )]
+    pub safety_fund: Account<'info, SafetyFund>,
+
+    #[account(
+        seeds = [CONFIG_SEED],
+        bump,
+        has_one = governance @ ObscuraError::Unauthorized,
+    )]
+    pub config: Account<'info, ProgramConfig>,
+
+    #[account(mut)]
+    pub governance: Signer<'info>,
+
+    pub system_program: Program<'info, System>,
+}
+
+#[derive(Accounts)]
+pub struct SetVaultMetadata<'info> {
+    #[account(
+        init_if_needed,
+        payer = owner,
+        space = 8 + VaultMetadata::INIT_SPACE,
+        seeds = [METADATA_SEED, vault.key().as_ref()],
+        bump,
+    )]
+    pub metadata: Account<'info, VaultMetadata>,
+
+    #[account(has_one = owner @ ObscuraError::Unauthorized)]
+    pub vault: Account<'info, VaultState>,
+
+    #[account(mut)]
+    pub owner: Signer<'info>,
+
+    pub system_program: Program<'info, System>,
+}
+
+/// Delegate a vault to an ER validator.
+///
+/// The `#[delegate]` attribute from `ephemeral-rollups-sdk` automatically
+/// adds the delegation program accounts and generates a
+/// `delegate_vault(&self, ...)` helper on the context.
+#[delegate]
+#[derive(Accounts)]
+#[instruction(validator: Pubkey)]
+pub struct DelegateVault<'info> {
+    #[account(
+        mut,
+        seeds = [VAULT_SEED, &vault.vault_id.to_le_bytes()],
+        bump,
+        del,  // marks this account for delegation
+    )]
+    pub vault: Account<'info, VaultState>,
+
+    #[account(mut)]
+    pub owner: Signer<'info>,
+
+    /// Score of `validator`; required when the vault has a
+    /// `min_validator_score` policy
+    #[account(
+        seeds = [VALIDATOR_SCORE_SEED, validator.as_ref()],
+        bump,
+    )]
+    pub validator_score: Option<Account<'info, ValidatorScore>>,
+
+    /// Registry entry for `validator`, used to pick the delegation SDK version
+    #[account(
+        seeds = [VALIDATOR_SEED, validator.as_ref()],
+        bump,
+    )]
+    pub validator_info: Account<'info, ValidatorInfo>,
+}
+
+/// Private transfer within the ephemeral rollup, then commit + undelegate.
+///
+/// The `#[commit]` attribute auto-adds `magic_context` and `magic_program`
+/// accounts and generates `commit_and_undelegate_vault(&self)`.
+#[commit]
+#[derive(Accounts)]
+pub struct PrivateTransfer<'info> {
+    #[account(
+        mut,
+        seeds = [VAULT_SEED, &vault.vault_id.to_le_bytes()],
+        bump,
+        com,  // marks this account for commit
+    )]
+    pub vault: Account<'info, VaultState>,
+
+    /// Vault owner, a key holding a transfer-scoped permission, or a
+    /// session key allowing `IX_PRIVATE_TRANSFER`
+    #[account(mut)]
+    pub owner: Signer<'info>,
+
+    /// Delegated history view; committed together with the vault
+    #[account(
+        mut,
+        seeds = [HISTORY_SEED, vault.key().as_ref()],
+        bump,
+    )]
+    pub history_view: Option<Account<'info, HistoryView>>,
+
+    /// Co-signer or velocity step-up cosigner, when the vault requires
+    /// one
+    pub cosigner: Option<Signer<'info>>,
+
+    /// Transfer-scoped permission, when the signer is not the owner
+    #[account(
+        seeds = [PERMISSION_SEED, vault.key().as_ref(), owner.key().as_ref()],
+        bump,
+    )]
+    pub permission: Option<Account<'info, PermissionState>>,
+
+    /// Session key, when the signer is one
+    #[account(
+        seeds = [SESSION_KEY_SEED, vault.key().as_ref(), owner.key().as_ref()],
+        bump,
+    )]
+    pub session_key: Option<Account<'info, SessionKey>>,
+
+    /// Recipient allowlist; required while allowlist mode is on
+    #[account(
+        seeds = [RECIPIENT_ALLOWLIST_SEED, vault.key().as_ref()],
+        bump,
+    )]
+    pub recipient_allowlist: Option<Account<'info, RecipientAllowlist>>,
+
+    /// CHECK: the vault's integrator denylist, possibly uninitialized;
+    /// required for vaults created through an integrator
+    #[account(
+        seeds = [DENYLIST_SEED, vault.integrator.as_ref()],
+        bump,
+    )]
+    pub denylist: Option<UncheckedAccount<'info>>,
+
+    /// CHECK: checked against `Denylist::compliance_program`
+    #[account(executable)]
+    pub compliance_program: Option<UncheckedAccount<'info>>,
+
+    /// Round-up savings vault; required while the vault's rule is active
+    #[account(
+        mut,
+        seeds = [VAULT_SEED, &savings_vault.vault_id.to_le_bytes()],
+        bump,
+        constraint = savings_vault.key() == vault.round_up.savings_vault
+            @ ObscuraError::InvalidSavingsVault,
+    )]
+    pub savings_vault: Option<Account<'info, VaultState>>,
+
+    /// Split-rule vault; required while the vault's split rule is active
+    #[account(
+        mut,
+        seeds = [VAULT_SEED, &split_vault.vault_id.to_le_bytes()],
+        bump,
+        constraint = split_vault.key() == vault.split.split_vault
+            @ ObscuraError::InvalidSplitVault,
+    )]
+    pub split_vault: Option<Account<'info, VaultState>>,
+}
+
+#[derive(Accounts)]
+pub struct ExecuteIntent<'info> {
+    #[account(
+        mut,
+        seeds = [VAULT_SEED, &vault.vault_id.to_le_bytes()],
+        bump,
+        has_one = owner @ ObscuraError::Unauthorized,
+    )]
+    pub vault: Account<'info, VaultState>,
+
+    pub owner: Signer<'info>,
+
+    /// Co-signer or velocity step-up cosigner, when the vault requires
+    /// one
+    pub cosigner: Option<Signer<'info>>,
+
+    /// Delegated permission PDA; required by `GrantPermission` legs
+    #[account(
+        mut,
+        seeds = [PERMISSION_SEED, vault.key().as_ref(), permission.permitted.as_ref()],
+        bump,
+    )]
+    pub permission: Option<Account<'info, PermissionState>>,
+
+    /// Recipient allowlist; required while allowlist mode is on
+    #[account(
+        seeds = [RECIPIENT_ALLOWLIST_SEED, vault.key().as_ref()],
+        bump,
+    )]
+    pub recipient_allowlist: Option<Account<'info, RecipientAllowlist>>,
+
+    /// CHECK: the vault's integrator denylist, possibly uninitialized;
+    /// required for vaults created through an integrator
+    #[account(
+        seeds = [DENYLIST_SEED, vault.integrator.as_ref()],
+        bump,
+    )]
+    pub denylist: Option<UncheckedAccount<'info>>,
+
+    /// CHECK: checked against `Denylist::compliance_program`
+    #[account(executable)]
+    pub compliance_program: Option<UncheckedAccount<'info>>,
+
+    /// Round-up savings vault; required while the vault's rule is active
+    #[account(
+        mut,
+        seeds = [VAULT_SEED, &savings_vault.vault_id.to_le_bytes()],
+        bump,
+        constraint = savings_vault.key() == vault.round_up.savings_vault
+            @ ObscuraError::InvalidSavingsVault,
+    )]
+    pub savings_vault: Option<Account<'info, VaultState>>,
+
+    /// Split-rule vault; required while the vault's split rule is active
+    #[account(
+        mut,
+        seeds = [VAULT_SEED, &split_vault.vault_id.to_le_bytes()],
+        bump,
+        constraint = split_vault.key() == vault.split.split_vault
+            @ ObscuraError::InvalidSplitVault,
+    )]
+    pub split_vault: Option<Account<'info, VaultState>>,
+}
+
+/// Commit vault state to L1 without undelegating.
+#[commit]
+#[derive(Accounts)]
+pub struct CommitState<'info> {
+    #[account(
+        mut,
+        seeds = [VAULT_SEED, &vault.vault_id.to_le_bytes()],
+        bump,
+        com,
+    )]
+    pub vault: Account<'info, VaultState>,
+
+    /// Vault owner, or a delegate holding a `DelegatedAuthority`
+    #[account(mut)]
+    pub owner: Signer<'info>,
+
+    #[account(
+        seeds = [AUTHORITY_SEED, vault.key().as_ref(), owner.key().as_ref()],
+        bump,
+    )]
+    pub delegated_authority: Option<Account<'info, DelegatedAuthority>>,
+}
+
+/// Undelegate — commit + undelegate in one step.
+#[commit]
+#[derive(Accounts)]
+pub struct UndelegateVault<'info> {
+    #[account(
+        mut,
+        seeds = [VAULT_SEED, &vault.vault_id.to_le_bytes()],
+        bump,
+        com,
+    )]
+    pub vault: Account<'info, VaultState>,
+
+    /// Vault owner, or a delegate holding a `DelegatedAuthority`
+    #[account(mut)]
+    pub owner: Signer<'info>,
+
+    #[account(
+        seeds = [AUTHORITY_SEED, vault.key().as_ref(), owner.key().as_ref()],
+        bump,
+    )]
+    pub delegated_authority: Option<Account<'info, DelegatedAuthority>>,
+}
+
+#[derive(Accounts)]
+pub struct CheckBaseSlot<'info> {
+    #[account(seeds = [VAULT_SEED, &vault.vault_id.to_le_bytes()], bump)]
+    pub vault: Account<'info, VaultState>,
+}
+
+#[derive(Accounts)]
+pub struct Deposit<'info> {
+    #[account(
+        mut,
+        seeds = [VAULT_SEED, &vault.vault_id.to_le_bytes()],
+        bump,
+    )]
+    pub vault: Account<'info, VaultState>,
+
+    #[account(mut)]
+    pub depositor: Signer<'info>,
+
+    #[account(
+        mut,
+        seeds = [HISTORY_SEED, vault.key().as_ref()],
+        bump,
+    )]
+    pub history_view: Option<Account<'info, HistoryView>>,
+
+    /// Split-rule vault; required while an inbound split rule is active
+    #[account(
+        mut,
+        seeds = [VAULT_SEED, &split_vault.vault_id.to_le_bytes()],
+        bump,
+        constraint = split_vault.key() == vault.split.split_vault
+            @ ObscuraError::InvalidSplitVault,
+    )]
+    pub split_vault: Option<Account<'info, VaultState>>,
+
+    pub system_program: Program<'info, System>,
+}
+
+#[derive(Accounts)]
+pub struct Withdraw<'info> {
+    #[account(
+        mut,
+        seeds = [VAULT_SEED, &vault.vault_id.to_le_bytes()],
+        bump,
+        constraint = vault.owner == owner.key() @ ObscuraError::Unauthorized,
+    )]
+    pub vault: Account<'info, VaultState>,
+
+    #[account(mut)]
+    pub owner: Signer<'info>,
+
+    /// Co-signer, when the vault requires one
+    pub cosigner: Option<Signer<'info>>,
+
+    #[account(
+        mut,
+        seeds = [HISTORY_SEED, vault.key().as_ref()],
+        bump,
+    )]
+    pub history_view: Option<Account<'info, HistoryView>>,
+
+    #[account(
+        init,
+        payer = owner,
+        space = 8 + PendingWithdrawal::INIT_SPACE,
+        seeds = [PENDING_WITHDRAWAL_SEED, vault.key().as_ref()],
+        bump,
+    )]
+    pub pending_withdrawal: Option<Account<'info, PendingWithdrawal>>,
+
+    /// Recipient allowlist; required while allowlist mode is on
+    #[account(
+        seeds = [RECIPIENT_ALLOWLIST_SEED, vault.key().as_ref()],
+        bump,
+    )]
+    pub recipient_allowlist: Option<Account<'info, RecipientAllowlist>>,
+
+    /// CHECK: the vault's integrator denylist, possibly uninitialized;
+    /// required for vaults created through an integrator
+    #[account(
+        seeds = [DENYLIST_SEED, vault.integrator.as_ref()],
+        bump,
+    )]
+    pub denylist: Option<UncheckedAccount<'info>>,
+
+    /// CHECK: checked against `Denylist::compliance_program`
+    #[account(executable)]
+    pub compliance_program: Option<UncheckedAccount<'info>>,
+
+    pub system_program: Program<'info, System>,
+}
+
+#[derive(Accounts)]
+pub struct ExecutePendingWithdrawal<'info> {
+    #[account(
+        mut,
+        seeds = [VAULT_SEED, &vault.vault_id.to_le_bytes()],
+        bump,
+        has_one = owner @ ObscuraError::Unauthorized,
+    )]
+    pub vault: Account<'info, VaultState>,
+
+    #[account(
+        mut,
+        close = owner,
+        seeds = [PENDING_WITHDRAWAL_SEED, vault.key().as_ref()],
+        bump,
+    )]
+    pub pending_withdrawal: Account<'info, PendingWithdrawal>,
+
+    #[account(mut)]
+    pub owner: Signer<'info>,
+
+    /// Co-signer, when the vault requires one
+    pub cosigner: Option<Signer<'info>>,
+
+    #[account(
+        mut,
+        seeds = [HISTORY_SEED, vault.key().as_ref()],
+        bump,
+    )]
+    pub history_view: Option<Account<'info, HistoryView>>,
+
+    /// Recipient allowlist; required while allowlist mode is on
+    #[account(
+        seeds = [RECIPIENT_ALLOWLIST_SEED, vault.key().as_ref()],
+        bump,
+    )]
+    pub recipient_allowlist: Option<Account<'info, RecipientAllowlist>>,
+
+    /// CHECK: the vault's integrator denylist, possibly uninitialized;
+    /// required for vaults created through an integrator
+    #[account(
+        seeds = [DENYLIST_SEED, vault.integrator.as_ref()],
+        bump,
+    )]
+    pub denylist: Option<UncheckedAccount<'info>>,
+
+    /// CHECK: checked against `Denylist::compliance_program`
+    #[account(executable)]
+    pub compliance_program: Option<UncheckedAccount<'info>>,
+}
+
+#[derive(Accounts)]
+pub struct CancelPendingWithdrawal<'info> {
+    #[account(
+        seeds = [VAULT_SEED, &vault.vault_id.to_le_bytes()],
+        bump,
+        has_one = owner @ ObscuraError::Unauthorized,
+    )]
+    pub vault: Account<'info, VaultState>,
+
+    #[account(
+        mut,
+        close = owner,
+        seeds = [PENDING_WITHDRAWAL_SEED, vault.key().as_ref()],
+        bump,
+    )]
+    pub pending_withdrawal: Account<'info, PendingWithdrawal>,
+
+    #[account(mut)]
+    pub owner: Signer<'info>,
+}
+
+#[derive(Accounts)]
+pub struct UpdateRecipientAllowlist<'info> {
+    #[account(
+        init_if_needed,
+        payer = owner,
+        space = 8 + RecipientAllowlist::INIT_SPACE,
+        seeds = [RECIPIENT_ALLOWLIST_SEED, vault.key().as_ref()],
+        bump,
+    )]
+    pub recipient_allowlist: Account<'info, RecipientAllowlist>,
+
+    #[account(
+        seeds = [VAULT_SEED, &vault.vault_id.to_le_bytes()],
+        bump,
+        has_one = owner @ ObscuraError::Unauthorized,
+    )]
+    pub vault: Account<'info, VaultState>,
+
+    #[account(mut)]
+    pub owner: Signer<'info>,
+
+    pub system_program: Program<'info, System>,
+}
+
+#[derive(Accounts)]
+pub struct UpdateDenylist<'info> {
+    #[account(
+        init_if_needed,
+        payer = authority,
+        space = 8 + Denylist::INIT_SPACE,
+        seeds = [DENYLIST_SEED, integrator.key().as_ref()],
+        bump,
+    )]
+    pub denylist: Account<'info, Denylist>,
+
+    #[account(
+        seeds = [INTEGRATOR_SEED, &integrator.app_id.to_le_bytes()],
+        bump,
+        has_one = authority @ ObscuraError::Unauthorized,
+    )]
+    pub integrator: Account<'info, Integrator>,
+
+    #[account(mut)]
+    pub authority: Signer<'info>,
+
+    pub system_program: Program<'info, System>,
+}
+
+#[derive(Accounts)]
+pub struct PayFeesFromVault<'info> {
+    #[account(
+        mut,
+        seeds = [VAULT_SEED, &vault.vault_id.to_le_bytes()],
+        bump,
+        has_one = owner @ ObscuraError::Unauthorized,
+    )]
+    pub vault: Account<'info, VaultState>,
+
+    pub owner: Signer<'info>,
+
+    /// Co-signer, when the vault requires one
+    pub cosigner: Option<Signer<'info>>,
+
+    #[account(mut)]
+    pub fee_payer: Signer<'info>,
+
+    /// CHECK: address-checked
+    #[account(address = anchor_lang::solana_program::sysvar::instructions::ID)]
+    pub sysvar_instructions: UncheckedAccount<'info>,
+}
+
+#[derive(Accounts)]
+#[instruction(relayer: Pubkey)]
+pub struct OpenRelayerLedger<'info> {
+    #[account(
+        init,
+        payer = owner,
+        space = 8 + RelayerLedger::INIT_SPACE,
+        seeds = [RELAYER_LEDGER_SEED, vault.key().as_ref(), relayer.as_ref()],
+        bump,
+    )]
+    pub relayer_ledger: Account<'info, RelayerLedger>,
+
+    #[account(has_one = owner @ ObscuraError::Unauthorized)]
+    pub vault: Account<'info, VaultState>,
+
+    #[account(mut)]
+    pub owner: Signer<'info>,
+
+    pub system_program: Program<'info, System>,
+}
+
+#[derive(Accounts)]
+pub struct AccrueRelayerFee<'info> {
+    #[account(
+        mut,
+        seeds = [RELAYER_LEDGER_SEED, vault.key().as_ref(), relayer.key().as_ref()],
+        bump,
+        has_one = vault,
+        has_one = relayer @ ObscuraError::Unauthorized,
+    )]
+    pub relayer_ledger: Account<'info, RelayerLedger>,
+
+    #[account(has_one = owner @ ObscuraError::Unauthorized)]
+    pub vault: Account<'info, VaultState>,
+
+    pub owner: Signer<'info>,
+
+    pub relayer: Signer<'info>,
+}
+
+#[derive(Accounts)]
+pub struct RelayerClaim<'info> {
+    #[account(
+        mut,
+        seeds = [RELAYER_LEDGER_SEED, vault.key().as_ref(), relayer.key().as_ref()],
+        bump,
+        has_one = vault,
+        has_one = relayer @ ObscuraError::Unauthorized,
+    )]
+    pub relayer_ledger: Account<'info, RelayerLedger>,
+
+    #[account(
+        mut,
+        seeds = [VAULT_SEED, &vault.vault_id.to_le_bytes()],
+        bump,
+    )]
+    pub vault: Account<'info, VaultState>,
+
+    #[account(mut)]
+    pub relayer: Signer<'info>,
+}
+
+#[derive(Accounts)]
+pub struct DisputeRelayerFees<'info> {
+    #[account(
+        mut,
+        seeds = [RELAYER_LEDGER_SEED, vault.key().as_ref(), relayer_ledger.relayer.as_ref()],
+        bump,
+        has_one = vault,
+    )]
+    pub relayer_ledger: Account<'info, RelayerLedger>,
+
+    #[account(has_one = owner @ ObscuraError::Unauthorized)]
+    pub vault: Account<'info, VaultState>,
+
+    pub owner: Signer<'info>,
+}
+
+#[derive(Accounts)]
+pub struct InternalTransfer<'info> {
+    #[account(
+        mut,
+        seeds = [VAULT_SEED, &from_vault.vault_id.to_le_bytes()],
+        bump,
+        constraint = from_vault.owner == owner.key() @ ObscuraError::Unauthorized,
+    )]
+    pub from_vault: Account<'info, VaultState>,
+
+    #[account(
+        mut,
+        seeds = [VAULT_SEED, &to_vault.vault_id.to_le_bytes()],
+        bump,
+        constraint = to_vault.key() != from_vault.key() @ ObscuraError::SameVault,
+    )]
+    pub to_vault: Account<'info, VaultState>,
+
+    pub owner: Signer<'info>,
+
+    /// Co-signer or velocity step-up cosigner, when `from_vault` requires
+    /// one
+    pub cosigner: Option<Signer<'info>>,
+
+    /// Recipient allowlist of `from_vault`; required while allowlist mode
+    /// is on
+    #[account(
+        seeds = [RECIPIENT_ALLOWLIST_SEED, from_vault.key().as_ref()],
+        bump,
+    )]
+    pub recipient_allowlist: Option<Account<'info, RecipientAllowlist>>,
+
+    /// CHECK: `from_vault`'s integrator denylist, possibly uninitialized;
+    /// required for vaults created through an integrator
+    #[account(
+        seeds = [DENYLIST_SEED, from_vault.integrator.as_ref()],
+        bump,
+    )]
+    pub denylist: Option<UncheckedAccount<'info>>,
+
+    /// CHECK: checked against `Denylist::compliance_program`
+    #[account(executable)]
+    pub compliance_program: Option<UncheckedAccount<'info>>,
+}
+
+#[derive(Accounts)]
+#[instruction(intent: PaymentIntent)]
+pub struct FulfillIntent<'info> {
+    #[account(
+        init,
+        payer = owner,
+        space = 8 + IntentReceipt::INIT_SPACE,
+        seeds = [
+            INTENT_RECEIPT_SEED,
+            recipient_vault.key().as_ref(),
+            intent.reference.as_ref(),
+        ],
+        bump,
+    )]
+    pub receipt: Account<'info, IntentReceipt>,
+
+    #[account(
+        mut,
+        seeds = [VAULT_SEED, &vault.vault_id.to_le_bytes()],
+        bump,
+        has_one = owner @ ObscuraError::Unauthorized,
+    )]
+    pub vault: Account<'info, VaultState>,
+
+    #[account(
+        mut,
+        seeds = [VAULT_SEED, &recipient_vault.vault_id.to_le_bytes()],
+        bump,
+        constraint = recipient_vault.key() != vault.key() @ ObscuraError::SameVault,
+    )]
+    pub recipient_vault: Account<'info, VaultState>,
+
+    /// Resolves a `RecipientAlias::Contact` recipient
+    #[account(
+        seeds = [CONTACT_SEED, contact_claim.contact_hash.as_ref()],
+        bump,
+    )]
+    pub contact_claim: Option<Account<'info, ContactClaim>>,
+
+    #[account(mut)]
+    pub owner: Signer<'info>,
+
+    /// Co-signer, when the vault requires one
+    pub cosigner: Option<Signer<'info>>,
+
+    /// Recipient allowlist; required while allowlist mode is on
+    #[account(
+        seeds = [RECIPIENT_ALLOWLIST_SEED, vault.key().as_ref()],
+        bump,
+    )]
+    pub recipient_allowlist: Option<Account<'info, RecipientAllowlist>>,
+
+    /// CHECK: the vault's integrator denylist, possibly uninitialized;
+    /// required for vaults created through an integrator
+    #[account(
+        seeds = [DENYLIST_SEED, vault.integrator.as_ref()],
+        bump,
+    )]
+    pub denylist: Option<UncheckedAccount<'info>>,
+
+    /// CHECK: checked against `Denylist::compliance_program`
+    #[account(executable)]
+    pub compliance_program: Option<UncheckedAccount<'info>>,
+
+    pub system_program: Program<'info, System>,
+}
+
+#[derive(Accounts)]
+pub struct RebalanceOwnVaults<'info> {
+    #[account(
+        mut,
+        seeds = [VAULT_SEED, &from_vault.vault_id.to_le_bytes()],
+        bump,
+        constraint = from_vault.owner == owner.key() @ ObscuraError::Unauthorized,
+    )]
+    pub from_vault: Account<'info, VaultState>,
+
+    #[account(
+        mut,
+        seeds = [VAULT_SEED, &to_vault.vault_id.to_le_bytes()],
+        bump,
+        constraint = to_vault.owner == owner.key() @ ObscuraError::Unauthorized,
+        constraint = to_vault.key() != from_vault.key() @ ObscuraError::SameVault,
+    )]
+    pub to_vault: Account<'info, VaultState>,
+
+    pub owner: Signer<'info>,
+
+    /// Co-signer or velocity step-up cosigner, when `from_vault` requires
+    /// one
+    pub cosigner: Option<Signer<'info>>,
+
+    /// Recipient allowlist of `from_vault`; required while allowlist mode
+    /// is on
+    #[account(
+        seeds = [RECIPIENT_ALLOWLIST_SEED, from_vault.key().as_ref()],
+        bump,
+    )]
+    pub recipient_allowlist: Option<Account<'info, RecipientAllowlist>>,
+
+    /// Recipient allowlist of `to_vault`; required while `from_vault` is
+    /// in allowlist mode
+    #[account(
+        seeds = [RECIPIENT_ALLOWLIST_SEED, to_vault.key().as_ref()],
+        bump,
+    )]
+    pub to_recipient_allowlist: Option<Account<'info, RecipientAllowlist>>,
+
+    /// CHECK: `from_vault`'s integrator denylist, possibly uninitialized;
+    /// required for vaults created through an integrator
+    #[account(
+        seeds = [DENYLIST_SEED, from_vault.integrator.as_ref()],
+        bump,
+    )]
+    pub denylist: Option<UncheckedAccount<'info>>,
+
+    /// CHECK: checked against `Denylist::compliance_program`
+    #[account(executable)]
+    pub compliance_program: Option<UncheckedAccount<'info>>,
+}
+
+#[derive(Accounts)]
+#[instruction(permitted_pubkey: Pubkey)]
+pub struct CreatePermission<'info> {
+    #[account(
+        init,
+        payer = owner,
+        space = PERMISSION_STATE_SIZE,
+        seeds = [PERMISSION_SEED, vault.key().as_ref(), permitted_pubkey.as_ref()],
+        bump,
+    )]
+    pub permission: Account<'info, PermissionState>,
+
+    #[account(seeds = [VAULT_SEED, &vault.vault_id.to_le_bytes()], bump)]
+    pub vault: Account<'info, VaultState>,
+
+    /// Vault owner, or its permission manager for read-only grants
+    #[account(mut)]
+    pub owner: Signer<'info>,
+
+    /// CHECK: Access Control permission PDA, checked in `access_control`
+    #[account(mut)]
+    pub acl_permission: UncheckedAccount<'info>,
+
+    /// CHECK: the MagicBlock Access Control program
+    #[account(address = access_control::ID)]
+    pub access_control_program: UncheckedAccount<'info>,
+
+    pub system_program: Program<'info, System>,
+}
+
+#[derive(Accounts)]
+#[instruction(device: Pubkey)]
+pub struct RegisterDevice<'info> {
+    #[account(
+        init,
+        payer = owner,
+        space = 8 + DeviceKey::INIT_SPACE,
+        seeds = [DEVICE_SEED, vault.key().as_ref(), device.as_ref()],
+        bump,
+    )]
+    pub device_key: Account<'info, DeviceKey>,
+
+    #[account(has_one = owner @ ObscuraError::Unauthorized)]
+    pub vault: Account<'info, VaultState>,
+
+    #[account(mut)]
+    pub owner: Signer<'info>,
+
+    pub system_program: Program<'info, System>,
+}
+
+#[derive(Accounts)]
+pub struct RemoveDevice<'info> {
+    #[account(
+        mut,
+        close = owner,
+        seeds = [DEVICE_SEED, vault.key().as_ref(), device_key.device.as_ref()],
+        bump,
+    )]
+    pub device_key: Account<'info, DeviceKey>,
+
+    #[account(has_one = owner @ ObscuraError::Unauthorized)]
+    pub vault: Account<'info, VaultState>,
+
+    #[account(mut)]
+    pub owner: Signer<'info>,
+}
+
+#[derive(Accounts)]
+pub struct Heartbeat<'info> {
+    #[account(
+        mut,
+        seeds = [VAULT_SEED, &vault.vault_id.to_le_bytes()],
+        bump,
+    )]
+    pub vault: Account<'info, VaultState>,
+
+    /// Owner or registered device key
+    pub authority: Signer<'info>,
+
+    /// Required when `authority` is a device key rather than the owner
+    #[account(
+        seeds = [DEVICE_SEED, vault.key().as_ref(), authority.key().as_ref()],
+        bump,
+    )]
+    pub device_key: Option<Account<'info, DeviceKey>>,
+
+    /// Required when `authority` is a delegated authority
+    #[account(
+        seeds = [AUTHORITY_SEED, vault.key().as_ref(), authority.key().as_ref()],
+        bump,
+    )]
+    pub delegated_authority: Option<Account<'info, DelegatedAuthority>>,
+}
+
+#[derive(Accounts)]
+pub struct PostSessionHandoff<'info> {
+    #[account(
+        init,
+        payer = from_device,
+        space = 8 + SessionHandoff::INIT_SPACE,
+        seeds = [HANDOFF_SEED, vault.key().as_ref(), to_device_key.device.as_ref()],
+        bump,
+    )]
+    pub handoff: Account<'info, SessionHandoff>,
+
+    pub vault: Account<'info, VaultState>,
+
+    /// The posting device
+    #[account(mut)]
+    pub from_device: Signer<'info>,
+
+    #[account(
+        seeds = [DEVICE_SEED, vault.key().as_ref(), from_device.key().as_ref()],
+        bump,
+    )]
+    pub from_device_key: Account<'info, DeviceKey>,
+
+    /// Registration of the device picking the session up
+    #[account(
+        seeds = [DEVICE_SEED, vault.key().as_ref(), to_device_key.device.as_ref()],
+        bump,
+        constraint = to_device_key.device != from_device.key() @ ObscuraError::InvalidSessionBundle,
+    )]
+    pub to_device_key: Account<'info, DeviceKey>,
+
+    pub system_program: Program<'info, System>,
+}
+
+#[derive(Accounts)]
+pub struct AcknowledgeSessionHandoff<'info> {
+    #[account(
+        mut,
+        close = from_device,
+        seeds = [HANDOFF_SEED, vault.key().as_ref(), to_device.key().as_ref()],
+        bump,
+        has_one = vault,
+        has_one = from_device,
+    )]
+    pub handoff: Account<'info, SessionHandoff>,
+
+    pub vault: Account<'info, VaultState>,
+
+    /// The target device
+    pub to_device: Signer<'info>,
+
+    #[account(
+        seeds = [DEVICE_SEED, vault.key().as_ref(), to_device.key().as_ref()],
+        bump,
+    )]
+    pub to_device_key: Account<'info, DeviceKey>,
+
+    /// CHECK: rent refund destination, checked against `from_device`
+    #[account(mut)]
+    pub from_device: UncheckedAccount<'info>,
+}
+
+#[derive(Accounts)]
+pub struct CloseSessionHandoff<'info> {
+    #[account(
+        mut,
+        close = from_device,
+        seeds = [HANDOFF_SEED, handoff.vault.as_ref(), handoff.to_device.as_ref()],
+        bump,
+        has_one = from_device,
+    )]
+    pub handoff: Account<'info, SessionHandoff>,
+
+    /// CHECK: rent refund destination, checked against `from_device`
+    #[account(mut)]
+    pub from_device: UncheckedAccount<'info>,
+
+    /// The posting device, or anyone once the handoff has expired
+    pub closer: Signer<'info>,
+}
+
+#[derive(Accounts)]
+#[instruction(delegate: Pubkey)]
+pub struct GrantDelegatedAuthority<'info> {
+    #[account(
+        init_if_needed,
+        payer = owner,
+        space = 8 + DelegatedAuthority::INIT_SPACE,
+        seeds = [AUTHORITY_SEED, vault.key().as_ref(), delegate.as_ref()],
+        bump,
+    )]
+    pub delegated_authority: Account<'info, DelegatedAuthority>,
+
+    #[account(has_one = owner @ ObscuraError::Unauthorized)]
+    pub vault: Account<'info, VaultState>,
+
+    #[account(mut)]
+    pub owner: Signer<'info>,
+
+    pub system_program: Program<'info, System>,
+}
+
+#[derive(Accounts)]
+pub struct RevokeDelegatedAuthority<'info> {
+    #[account(
+        mut,
+        close = owner,
+        seeds = [AUTHORITY_SEED, vault.key().as_ref(), delegated_authority.delegate.as_ref()],
+        bump,
+    )]
+    pub delegated_authority: Account<'info, DelegatedAuthority>,
+
+    #[account(has_one = owner @ ObscuraError::Unauthorized)]
+    pub vault: Account<'info, VaultState>,
+
+    #[account(mut)]
+    pub owner: Signer<'info>,
+}
+
+#[derive(Accounts)]
+#[instruction(key: Pubkey)]
+pub struct CreateSessionKey<'info> {
+    #[account(
+        init_if_needed,
+        payer = owner,
+        space = 8 + SessionKey::INIT_SPACE,
+        seeds = [SESSION_KEY_SEED, vault.key().as_ref(), key.as_ref()],
+        bump,
+    )]
+    pub session_key: Account<'info, SessionKey>,
+
+    #[account(has_one = owner @ ObscuraError::Unauthorized)]
+    pub vault: Account<'info, VaultState>,
+
+    #[account(mut)]
+    pub owner: Signer<'info>,
+
+    pub system_program: Program<'info, System>,
+}
+
+#[derive(Accounts)]
+pub struct RevokeSessionKey<'info> {
+    #[account(
+        mut,
+        close = owner,
+        seeds = [SESSION_KEY_SEED, vault.key().as_ref(), session_key.key.as_ref()],
+        bump,
+    )]
+    pub session_key: Account<'info, SessionKey>,
+
+    #[account(has_one = owner @ ObscuraError::Unauthorized)]
+    pub vault: Account<'info, VaultState>,
+
+    #[account(mut)]
+    pub owner: Signer<'info>,
+}
+
+#[derive(Accounts)]
+#[instruction(capability_id: u64, service: Pubkey)]
+pub struct MintCapability<'info> {
+    #[account(
+        init,
+        payer = owner,
+        space = 8 + Capability::INIT_SPACE,
+        seeds = [
+            CAPABILITY_SEED,
+            vault.key().as_ref(),
+            service.as_ref(),
+            &capability_id.to_le_bytes(),
+        ],
+        bump,
+    )]
+    pub capability: Account<'info, Capability>,
+
+    #[account(has_one = owner @ ObscuraError::Unauthorized)]
+    pub vault: Account<'info, VaultState>,
+
+    #[account(mut)]
+    pub owner: Signer<'info>,
+
+    /// Co-signer, when the vault requires one
+    pub cosigner: Option<Signer<'info>>,
+
+    pub system_program: Program<'info, System>,
+}
+
+#[derive(Accounts)]
+pub struct ExecuteCapability<'info> {
+    #[account(
+        mut,
+        close = owner,
+        seeds = [
+            CAPABILITY_SEED,
+            vault.key().as_ref(),
+            service.key().as_ref(),
+            &capability.capability_id.to_le_bytes(),
+        ],
+        bump,
+        has_one = vault,
+        has_one = service,
+    )]
+    pub capability: Account<'info, Capability>,
+
+    #[account(
+        mut,
+        seeds = [VAULT_SEED, &vault.vault_id.to_le_bytes()],
+        bump,
+        has_one = owner @ ObscuraError::Unauthorized,
+    )]
+    pub vault: Account<'info, VaultState>,
+
+    pub service: Signer<'info>,
+
+    /// CHECK: rent refund destination, checked against the vault owner
+    #[account(mut)]
+    pub owner: UncheckedAccount<'info>,
+
+    /// CHECK: `Transfer` destination, checked against `capability.object`
+    #[account(mut)]
+    pub recipient: Option<UncheckedAccount<'info>>,
+
+    /// `TopUpVault` destination, checked against `capability.object`
+    #[account(
+        mut,
+        seeds = [VAULT_SEED, &recipient_vault.vault_id.to_le_bytes()],
+        bump,
+        constraint = !recipient_vault.is_delegated @ ObscuraError::AccountDelegated,
+    )]
+    pub recipient_vault: Option<Account<'info, VaultState>>,
+
+    /// Recipient allowlist; required while allowlist mode is on
+    #[account(
+        seeds = [RECIPIENT_ALLOWLIST_SEED, vault.key().as_ref()],
+        bump,
+    )]
+    pub recipient_allowlist: Option<Account<'info, RecipientAllowlist>>,
+
+    /// CHECK: the vault's integrator denylist, possibly uninitialized;
+    /// required for vaults created through an integrator
+    #[account(
+        seeds = [DENYLIST_SEED, vault.integrator.as_ref()],
+        bump,
+    )]
+    pub denylist: Option<UncheckedAccount<'info>>,
+
+    /// CHECK: checked against `Denylist::compliance_program`
+    #[account(executable)]
+    pub compliance_program: Option<UncheckedAccount<'info>>,
+}
+
+#[derive(Accounts)]
+pub struct RevokeCapability<'info> {
+    #[account(
+        mut,
+        close = owner,
+        has_one = vault,
+    )]
+    pub capability: Account<'info, Capability>,
+
+    #[account(has_one = owner @ ObscuraError::Unauthorized)]
+    pub vault: Account<'info, VaultState>,
+
+    #[account(mut)]
+    pub owner: Signer<'info>,
+}
+
+#[derive(Accounts)]
+pub struct InitHistoryView<'info> {
+    #[account(
+        init,
+        payer = owner,
+        space = 8 + HistoryView::INIT_SPACE,
+        seeds = [HISTORY_SEED, vault.key().as_ref()],
+        bump,
+    )]
+    pub history_view: Account<'info, HistoryView>,
+
+    #[account(has_one = owner @ ObscuraError::Unauthorized)]
+    pub vault: Account<'info, VaultState>,
+
+    #[account(mut)]
+    pub owner: Signer<'info>,
+
+    pub system_program: Program<'info, System>,
+}
+
+/// Delegate a vault's history view to an ER validator.
+#[delegate]
+#[derive(Accounts)]
+pub struct DelegateHistoryView<'info> {
+    #[account(
+        mut,
+        seeds = [HISTORY_SEED, vault.key().as_ref()],
+        bump,
+        del,
+    )]
+    pub history_view: Account<'info, HistoryView>,
+
+    #[account(has_one = owner @ ObscuraError::Unauthorized)]
+    pub vault: Account<'info, VaultState>,
+
+    #[account(mut)]
+    pub owner: Signer<'info>,
+}
+
+#[derive(Accounts)]
+pub struct CreatePermissionsBatch<'info> {
+    #[account(seeds = [VAULT_SEED, &vault.vault_id.to_le_bytes()], bump)]
+    pub vault: Account<'info, VaultState>,
+
+    /// Vault owner, or its permission manager for read-only grants
+    #[account(mut)]
+    pub owner: Signer<'info>,
+
+    /// CHECK: the MagicBlock Access Control program
+    #[account(address = access_control::ID)]
+    pub access_control_program: UncheckedAccount<'info>,
+
+    pub system_program: Program<'info, System>,
+}
+
+/// Owner-only update of an existing permission.
+#[derive(Accounts)]
+pub struct UpdatePermission<'info> {
+    #[account(
+        mut,
+        seeds = [PERMISSION_SEED, vault.key().as_ref(), permission.permitted.as_ref()],
+        bump,
+        has_one = vault,
+    )]
+    pub permission: Account<'info, PermissionState>,
+
+    #[account(
+        seeds = [VAULT_SEED, &vault.vault_id.to_le_bytes()],
+        bump,
+        has_one = owner @ ObscuraError::Unauthorized,
+    )]
+    pub vault: Account<'info, VaultState>,
+
+    /// Vault owner; pays the Access Control permission's rent on restore
+    #[account(mut)]
+    pub owner: Signer<'info>,
+
+    /// CHECK: Access Control permission PDA, checked in `access_control`
+    #[account(mut)]
+    pub acl_permission: UncheckedAccount<'info>,
+
+    /// CHECK: the MagicBlock Access Control program
+    #[account(address = access_control::ID)]
+    pub access_control_program: UncheckedAccount<'info>,
+
+    pub system_program: Program<'info, System>,
+}
+
+impl<'info> UpdatePermission<'info> {
+    /// Create (`active`) or close the Access Control permission mirroring
+    /// this grant.
+    fn sync_acl(&self, vault_bump: u8, active: bool) -> Result<()> {
+        let vault_id = self.vault.vault_id.to_le_bytes();
+        let accounts = access_control::AclAccounts {
+            program: &self.access_control_program,
+            permission: &self.acl_permission,
+            vault: &self.vault.to_account_info(),
+            payer: &self.owner,
+            system_program: &self.system_program,
+        };
+        let seeds: &[&[&[u8]]] = &[&[VAULT_SEED, &vault_id, &[vault_bump]]];
+        if active {
+            access_control::grant(&accounts, self.permission.permitted, seeds)
+        } else {
+            access_control::revoke(&accounts, self.permission.permitted, seeds)
+        }
+    }
+}
+
+/// Revocation by the owner or permission manager; closes the permission
+/// PDA.
+#[derive(Accounts)]
+pub struct RevokePermission<'info> {
+    #[account(
+        mut,
+        close = granted_by,
+        seeds = [PERMISSION_SEED, vault.key().as_ref(), permission.permitted.as_ref()],
+        bump,
+        has_one = vault,
+        has_one = granted_by,
+    )]
+    pub permission: Account<'info, PermissionState>,
+
+    #[account(seeds = [VAULT_SEED, &vault.vault_id.to_le_bytes()], bump)]
+    pub vault: Account<'info, VaultState>,
+
+    /// Vault owner, or its permission manager for read-only permissions
+    pub owner: Signer<'info>,
+
+    /// CHECK: rent refund destination, checked against `granted_by`
+    #[account(mut)]
+    pub granted_by: UncheckedAccount<'info>,
+
+    /// CHECK: Access Control permission PDA, checked in `access_control`
+    #[account(mut)]
+    pub acl_permission: UncheckedAccount<'info>,
+
+    /// CHECK: the MagicBlock Access Control program
+    #[account(address = access_control::ID)]
+    pub access_control_program: UncheckedAccount<'info>,
+
+    pub system_program: Program<'info, System>,
+}
+
+/// Permissionless cleanup of an expired permission.
+#[derive(Accounts)]
+pub struct CloseExpiredPermission<'info> {
+    #[account(
+        mut,
+        close = granted_by,
+        seeds = [PERMISSION_SEED, vault.key().as_ref(), permission.permitted.as_ref()],
+        bump,
+        has_one = vault,
+        has_one = granted_by,
+    )]
+    pub permission: Account<'info, PermissionState>,
+
+    #[account(seeds = [VAULT_SEED, &vault.vault_id.to_le_bytes()], bump)]
+    pub vault: Account<'info, VaultState>,
+
+    /// CHECK: rent refund destination, checked against `granted_by`
+    #[account(mut)]
+    pub granted_by: UncheckedAccount<'info>,
+
+    /// CHECK: Access Control permission PDA, checked in `access_control`
+    #[account(mut)]
+    pub acl_permission: UncheckedAccount<'info>,
+
+    /// CHECK: the MagicBlock Access Control program
+    #[account(address = access_control::ID)]
+    pub access_control_program: UncheckedAccount<'info>,
+
+    pub system_program: Program<'info, System>,
+}
+
+#[derive(Accounts)]
+pub struct InitializeConfig<'info> {
+    #[account(
+        init,
+        payer = authority,
+        space = 8 + ProgramConfig::INIT_SPACE,
+        seeds = [CONFIG_SEED],
+        bump,
+    )]
+    pub config: Account<'info, ProgramConfig>,
+
+    #[account(
+        constraint = program.programdata_address()? == Some(program_data.key()),
+    )]
+    pub program: Program<'info, crate::program::ObscuraPer>,
+
+    #[account(
+        constraint = program_data.upgrade_authority_address == Some(authority.key())
+            @ ObscuraError::Unauthorized,
+    )]
+    pub program_data: Account<'info, ProgramData>,
+
+    #[account(mut)]
+    pub authority: Signer<'info>,
+
+    pub system_program: Program<'info, System>,
+}
+
+#[derive(Accounts)]
+pub struct PublishProgramVersion<'info> {
+    #[account(
+        init_if_needed,
+        payer = governance,
+        space = 8 + ProgramVersionInfo::INIT_SPACE,
+        seeds = [VERSION_SEED],
+        bump,
+    )]
+    pub version_info: Account<'info, ProgramVersionInfo>,
+
+    #[account(
+        seeds = [CONFIG_SEED],
+        bump,
+        has_one = governance @ ObscuraError::Unauthorized,
+    )]
+    pub config: Account<'info, ProgramConfig>,
+
+    #[account(
+        constraint = program.programdata_address()? == Some(program_data.key()),
+    )]
+    pub program: Program<'info, crate::program::ObscuraPer>,
+
+    pub program_data: Account<'info, ProgramData>,
+
+    #[account(mut)]
+    pub governance: Signer<'info>,
+
+    pub system_program: Program<'info, System>,
+}
+
+#[derive(Accounts)]
+#[instruction(validator: Pubkey)]
+pub struct RegisterValidator<'info> {
+    #[account(
+        init,
+        payer = governance,
+        space = 8 + ValidatorInfo::INIT_SPACE,
+        seeds = [VALIDATOR_SEED, validator.as_ref()],
+        bump,
+    )]
+    pub validator_info: Account<'info, ValidatorInfo>,
+
+    #[account(
+        seeds = [CONFIG_SEED],
+        bump,
+        has_one = governance @ ObscuraError::Unauthorized,
+    )]
+    pub config: Account<'info, ProgramConfig>,
+
+    #[account(mut)]
+    pub governance: Signer<'info>,
+
+    pub system_program: Program<'info, System>,
+}
+
+/// Governance update of the program config.
+#[derive(Accounts)]
+pub struct UpdateConfig<'info> {
+    #[account(
+        mut,
+        seeds = [CONFIG_SEED],
+        bump,
+        has_one = governance @ ObscuraError::Unauthorized,
+    )]
+    pub config: Account<'info, ProgramConfig>,
+
+    pub governance: Signer<'info>,
+}
+
+/// Governance update of an existing validator registry entry.
+#[derive(Accounts)]
+pub struct UpdateValidatorInfo<'info> {
+    #[account(
+        mut,
+        seeds = [VALIDATOR_SEED, validator_info.validator.as_ref()],
+        bump,
+    )]
+    pub validator_info: Account<'info, ValidatorInfo>,
+
+    #[account(
+        seeds = [CONFIG_SEED],
+        bump,
+        has_one = governance @ ObscuraError::Unauthorized,
+    )]
+    pub config: Account<'info, ProgramConfig>,
+
+    pub governance: Signer<'info>,
+}
+
+/// Operator update of its own validator registry entry.
+#[derive(Accounts)]
+pub struct SetRateCard<'info> {
+    #[account(
+        mut,
+        seeds = [VALIDATOR_SEED, validator.key().as_ref()],
+        bump,
+    )]
+    pub validator_info: Account<'info, ValidatorInfo>,
+
+    pub validator: Signer<'info>,
+}
+
+#[derive(Accounts)]
+pub struct SettleOperatorFees<'info> {
+    #[account(
+        mut,
+        seeds = [VAULT_SEED, &vault.vault_id.to_le_bytes()],
+        bump,
+    )]
+    pub vault: Account<'info, VaultState>,
+
+    /// CHECK: the rate card's payout account the vault accepted
+    #[account(mut, address = vault.operator_fees.payout)]
+    pub payout: UncheckedAccount<'info>,
+}
+
+#[derive(Accounts)]
+pub struct AdminForceSettle<'info> {
+    #[account(
+        mut,
+        seeds = [VAULT_SEED, &vault.vault_id.to_le_bytes()],
+        bump,
+    )]
+    pub vault: Account<'info, VaultState>,
+
+    #[account(
+        seeds = [VALIDATOR_SEED, vault.delegate_validator.as_ref()],
+        bump,
+    )]
+    pub validator_info: Account<'info, ValidatorInfo>,
+
+    #[account(
+        seeds = [CONFIG_SEED],
+        bump,
+        has_one = governance @ ObscuraError::Unauthorized,
+    )]
+    pub config: Account<'info, ProgramConfig>,
+
+    pub governance: Signer<'info>,
+}
+
+#[derive(Accounts)]
+pub struct CheckUndelegateEligibility<'info> {
+    #[account(seeds = [VAULT_SEED, &vault.vault_id.to_le_bytes()], bump)]
+    pub vault: Account<'info, VaultState>,
+
+    #[account(seeds = [CONFIG_SEED], bump)]
+    pub config: Account<'info, ProgramConfig>,
+}
+
+/// Keeper sweep of an inactive vault — commit + undelegate.
+#[commit]
+#[derive(Accounts)]
+pub struct SweepInactiveVault<'info> {
+    #[account(
+        mut,
+        seeds = [VAULT_SEED, &vault.vault_id.to_le_bytes()],
+        bump,
+        com,
+    )]
+    pub vault: Account<'info, VaultState>,
+
+    #[account(
+        seeds = [CONFIG_SEED],
+        bump,
+        has_one = keeper @ ObscuraError::Unauthorized,
+    )]
+    pub config: Account<'info, ProgramConfig>,
+
+    #[account(mut)]
+    pub keeper: Signer<'info>,
+}
+
+#[derive(Accounts)]
+pub struct SetInvoicedBilling<'info> {
+    #[account(
+        mut,
+        seeds = [VAULT_SEED, &vault.vault_id.to_le_bytes()],
+        bump,
+        has_one = owner @ ObscuraError::Unauthorized,
+    )]
+    pub vault: Account<'info, VaultState>,
+
+    pub owner: Signer<'info>,
+
+    #[account(
+        seeds = [CONFIG_SEED],
+        bump,
+        has_one = governance @ ObscuraError::Unauthorized,
+    )]
+    pub config: Account<'info, ProgramConfig>,
+
+    pub governance: Signer<'info>,
+}
+
+#[derive(Accounts)]
+#[instruction(period: u32)]
+pub struct CloseFeeStatement<'info> {
+    #[account(
+        init,
+        payer = governance,
+        space = 8 + FeeStatement::INIT_SPACE,
+        seeds = [FEE_STATEMENT_SEED, vault.key().as_ref(), &period.to_le_bytes()],
+        bump,
+    )]
+    pub fee_statement: Account<'info, FeeStatement>,
+
+    #[account(
+        mut,
+        seeds = [VAULT_SEED, &vault.vault_id.to_le_bytes()],
+        bump,
+    )]
+    pub vault: Account<'info, VaultState>,
+
+    #[account(
+        seeds = [CONFIG_SEED],
+        bump,
+        has_one = governance @ ObscuraError::Unauthorized,
+    )]
+    pub config: Account<'info, ProgramConfig>,
+
+    #[account(mut)]
+    pub governance: Signer<'info>,
+
+    /// The vault's integrator; required when the vault has one
+    #[account(
+        mut,
+        constraint = integrator.key() == vault.integrator @ ObscuraError::InvalidIntegrator,
+    )]
+    pub integrator: Option<Account<'info, Integrator>>,
+
+    pub system_program: Program<'info, System>,
+}
+
+#[derive(Accounts)]
+#[instruction(app_id: u64)]
+pub struct RegisterIntegrator<'info> {
+    #[account(
+        init,
+        payer = authority,
+        space = 8 + Integrator::INIT_SPACE,
+        seeds = [INTEGRATOR_SEED, &app_id.to_le_bytes()],
+        bump,
+    )]
+    pub integrator: Account<'info, Integrator>,
+
+    /// Vault receiving the integrator's settlements
+    #[account(
+        seeds = [VAULT_SEED, &payout_vault.vault_id.to_le_bytes()],
+        bump,
+        constraint = payout_vault.owner == authority.key() @ ObscuraError::Unauthorized,
+    )]
+    pub payout_vault: Account<'info, VaultState>,
+
+    #[account(mut)]
+    pub authority: Signer<'info>,
+
+    #[account(
+        seeds = [CONFIG_SEED],
+        bump,
+        has_one = governance @ ObscuraError::Unauthorized,
+    )]
+    pub config: Account<'info, ProgramConfig>,
+
+    pub governance: Signer<'info>,
+
+    pub system_program: Program<'info, System>,
+}
+
+#[derive(Accounts)]
+pub struct UpdateIntegrator<'info> {
+    #[account(
+        mut,
+        seeds = [INTEGRATOR_SEED, &integrator.app_id.to_le_bytes()],
+        bump,
+    )]
+    pub integrator: Account<'info, Integrator>,
+
+    #[account(
+        seeds = [CONFIG_SEED],
+        bump,
+        has_one = governance @ ObscuraError::Unauthorized,
+    )]
+    pub config: Account<'info, ProgramConfig>,
+
+    pub governance: Signer<'info>,
+}
+
+#[derive(Accounts)]
+pub struct SettleIntegratorFees<'info> {
+    #[account(
+        mut,
+        seeds = [INTEGRATOR_SEED, &integrator.app_id.to_le_bytes()],
+        bump,
+        has_one = payout_vault @ ObscuraError::InvalidIntegrator,
+    )]
+    pub integrator: Account<'info, Integrator>,
+
+    #[account(
+        mut,
+        seeds = [VAULT_SEED, &payout_vault.vault_id.to_le_bytes()],
+        bump,
+    )]
+    pub payout_vault: Account<'info, VaultState>,
+
+    #[account(
+        seeds = [CONFIG_SEED],
+        bump,
+        has_one = governance @ ObscuraError::Unauthorized,
+    )]
+    pub config: Account<'info, ProgramConfig>,
+
+    #[account(mut)]
+    pub governance: Signer<'info>,
+
+    pub system_program: Program<'info, System>,
+}
+
+#[derive(Accounts)]
+pub struct SubmitValidatorObservation<'info> {
+    #[account(
+        init_if_needed,
+        payer = keeper,
+        space = 8 + ValidatorScore::INIT_SPACE,
+        seeds = [VALIDATOR_SCORE_SEED, validator_info.validator.as_ref()],
+        bump,
+    )]
+    pub validator_score: Account<'info, ValidatorScore>,
+
+    #[account(
+        seeds = [VALIDATOR_SEED, validator_info.validator.as_ref()],
+        bump,
+    )]
+    pub validator_info: Account<'info, ValidatorInfo>,
+
+    #[account(
+        seeds = [CONFIG_SEED],
+        bump,
+        has_one = keeper @ ObscuraError::Unauthorized,
+    )]
+    pub config: Account<'info, ProgramConfig>,
+
+    #[account(mut)]
+    pub keeper: Signer<'info>,
+
+    pub system_program: Program<'info, System>,
+}
+
+#[derive(Accounts)]
+pub struct MigrateVault<'info> {
+    /// CHECK: a vault in an older layout, which `Account<VaultState>` can't
+    /// load; discriminator and PDA are checked in the handler.  Owned by
+    /// this program, so a delegated vault must be undelegated first.
+    #[account(mut, owner = crate::ID @ ObscuraError::AccountDelegated)]
+    pub vault: UncheckedAccount<'info>,
+
+    #[account(mut)]
+    pub payer: Signer<'info>,
+
+    pub system_program: Program<'info, System>,
+}
+
+/// Owner-only update of a vault's policy fields.
+#[derive(Accounts)]
+pub struct SetVaultPolicy<'info> {
+    #[account(
+        mut,
+        seeds = [VAULT_SEED, &vault.vault_id.to_le_bytes()],
+        bump,
+        has_one = owner @ ObscuraError::Unauthorized,
+    )]
+    pub vault: Account<'info, VaultState>,
+
+    pub owner: Signer<'info>,
+}
+
+#[derive(Accounts)]
+pub struct SetTokenSpendingLimit<'info> {
+    #[account(
+        seeds = [VAULT_SEED, &vault.vault_id.to_le_bytes()],
+        bump,
+        has_one = owner @ ObscuraError::Unauthorized,
+    )]
+    pub vault: Account<'info, VaultState>,
+
+    #[account(
+        mut,
+        seeds = [TOKEN_BALANCE_SEED, vault.key().as_ref(), token_balance.mint.as_ref()],
+        bump,
+    )]
+    pub token_balance: Account<'info, VaultTokenBalance>,
+
+    pub owner: Signer<'info>,
+}
+
+#[derive(Accounts)]
+pub struct SetCosigner<'info> {
+    #[account(
+        mut,
+        seeds = [VAULT_SEED, &vault.vault_id.to_le_bytes()],
+        bump,
+        has_one = owner @ ObscuraError::Unauthorized,
+    )]
+    pub vault: Account<'info, VaultState>,
+
+    pub owner: Signer<'info>,
+
+    /// The co-signer being replaced, if one is set
+    pub current_cosigner: Option<Signer<'info>>,
+}
+
+#[derive(Accounts)]
+pub struct FreezeVault<'info> {
+    #[account(
+        mut,
+        seeds = [VAULT_SEED, &vault.vault_id.to_le_bytes()],
+        bump,
+    )]
+    pub vault: Account<'info, VaultState>,
+
+    /// The owner or the vault's freeze guardian
+    pub authority: Signer<'info>,
+}
+
+#[derive(Accounts)]
+pub struct ExportPolicy<'info> {
+    #[account(seeds = [VAULT_SEED, &vault.vault_id.to_le_bytes()], bump)]
+    pub vault: Account<'info, VaultState>,
+}
+
+#[derive(Accounts)]
+pub struct SetRoundUpRule<'info> {
+    #[account(
+        mut,
+        seeds = [VAULT_SEED, &vault.vault_id.to_le_bytes()],
+        bump,
+        has_one = owner @ ObscuraError::Unauthorized,
+    )]
+    pub vault: Account<'info, VaultState>,
+
+    /// Vault that receives the round-ups; omit when disabling the rule
+    #[account(
+        seeds = [VAULT_SEED, &savings_vault.vault_id.to_le_bytes()],
+        bump,
+        constraint = savings_vault.owner == owner.key() @ ObscuraError::Unauthorized,
+        constraint = savings_vault.key() != vault.key() @ ObscuraError::SameVault,
+    )]
+    pub savings_vault: Option<Account<'info, VaultState>>,
+
+    pub owner: Signer<'info>,
+}
+
+#[derive(Accounts)]
+pub struct SetSplitRule<'info> {
+    #[account(
+        mut,
+        seeds = [VAULT_SEED, &vault.vault_id.to_le_bytes()],
+        bump,
+        has_one = owner @ ObscuraError::Unauthorized,
+    )]
+    pub vault: Account<'info, VaultState>,
+
+    /// Vault that receives the split (any owner); omit when disabling
+    #[account(
+        seeds = [VAULT_SEED, &split_vault.vault_id.to_le_bytes()],
+        bump,
+        constraint = split_vault.key() != vault.key() @ ObscuraError::SameVault,
+    )]
+    pub split_vault: Option<Account<'info, VaultState>>,
+
+    pub owner: Signer<'info>,
+}
+
+#[derive(Accounts)]
+pub struct DepositSpl<'info> {
+    #[account(
+        mut,
+        seeds = [VAULT_SEED, &vault.vault_id.to_le_bytes()],
+        bump,
+    )]
+    pub vault: Account<'info, VaultState>,
+
+    #[account(
+        init_if_needed,
+        payer = depositor,
+        space = 8 + VaultTokenBalance::INIT_SPACE,
+        seeds = [TOKEN_BALANCE_SEED, vault.key().as_ref(), mint.key().as_ref()],
+        bump,
+    )]
+    pub token_balance: Account<'info, VaultTokenBalance>,
+
+    #[account(mint::token_program = token_program)]
+    pub mint: InterfaceAccount<'info, Mint>,
+
+    #[account(
+        init_if_needed,
+        payer = depositor,
+        associated_token::mint = mint,
+        associated_token::authority = vault,
+        associated_token::token_program = token_program,
+    )]
+    pub vault_token_account: InterfaceAccount<'info, TokenAccount>,
+
+    #[account(
+        mut,
+        token::mint = mint,
+        token::authority = depositor,
+        token::token_program = token_program,
+    )]
+    pub depositor_token_account: InterfaceAccount<'info, TokenAccount>,
+
+    #[account(mut)]
+    pub depositor: Signer<'info>,
+
+    pub token_program: Interface<'info, TokenInterface>,
+    pub associated_token_program: Program<'info, AssociatedToken>,
+    pub system_program: Program<'info, System>,
+}
+
+#[derive(Accounts)]
+pub struct WithdrawSpl<'info> {
+    #[account(
+        mut,
+        seeds = [VAULT_SEED, &vault.vault_id.to_le_bytes()],
+        bump,
+        has_one = owner @ ObscuraError::Unauthorized,
+    )]
+    pub vault: Account<'info, VaultState>,
+
+    #[account(
+        mut,
+        seeds = [TOKEN_BALANCE_SEED, vault.key().as_ref(), mint.key().as_ref()],
+        bump,
+    )]
+    pub token_balance: Account<'info, VaultTokenBalance>,
+
+    #[account(mint::token_program = token_program)]
+    pub mint: InterfaceAccount<'info, Mint>,
+
+    #[account(
+        mut,
+        associated_token::mint = mint,
+        associated_token::authority = vault,
+        associated_token::token_program = token_program,
+    )]
+    pub vault_token_account: InterfaceAccount<'info, TokenAccount>,
+
+    #[account(
+        mut,
+        token::mint = mint,
+        token::token_program = token_program,
+    )]
+    pub owner_token_account: InterfaceAccount<'info, TokenAccount>,
+
+    pub owner: Signer<'info>,
+
+    /// Co-signer, when the vault requires one
+    pub cosigner: Option<Signer<'info>>,
+
+    /// Recipient allowlist; required while allowlist mode is on
+    #[account(
+        seeds = [RECIPIENT_ALLOWLIST_SEED, vault.key().as_ref()],
+        bump,
+    )]
+    pub recipient_allowlist: Option<Account<'info, RecipientAllowlist>>,
+
+    /// CHECK: the vault's integrator denylist, possibly uninitialized;
+    /// required for vaults created through an integrator
+    #[account(
+        seeds = [DENYLIST_SEED, vault.integrator.as_ref()],
+        bump,
+    )]
+    pub denylist: Option<UncheckedAccount<'info>>,
+
+    /// CHECK: checked against `Denylist::compliance_program`
+    #[account(executable)]
+    pub compliance_program: Option<UncheckedAccount<'info>>,
+
+    pub token_program: Interface<'info, TokenInterface>,
+}
+
+#[derive(Accounts)]
+pub struct DepositWsol<'info> {
+    #[account(
+        mut,
+        seeds = [VAULT_SEED, &vault.vault_id.to_le_bytes()],
+        bump,
+    )]
+    pub vault: Account<'info, VaultState>,
+
+    #[account(
+        mut,
+        token::authority = depositor,
+        token::token_program = token_program,
+        constraint = depositor_wsol.mint == native_mint::ID @ ObscuraError::UnsupportedMint,
+    )]
+    pub depositor_wsol: InterfaceAccount<'info, TokenAccount>,
+
+    #[account(mut)]
+    pub depositor: Signer<'info>,
+
+    #[account(address = anchor_spl::token::ID)]
+    pub token_program: Interface<'info, TokenInterface>,
+    pub system_program: Program<'info, System>,
+}
+
+#[derive(Accounts)]
+pub struct WithdrawWsol<'info> {
+    #[account(
+        mut,
+        seeds = [VAULT_SEED, &vault.vault_id.to_le_bytes()],
+        bump,
+        has_one = owner @ ObscuraError::Unauthorized,
+    )]
+    pub vault: Account<'info, VaultState>,
+
+    #[account(
+        mut,
+        token::authority = owner,
+        token::token_program = token_program,
+        constraint = owner_wsol.mint == native_mint::ID @ ObscuraError::UnsupportedMint,
+    )]
+    pub owner_wsol: InterfaceAccount<'info, TokenAccount>,
+
+    #[account(mut)]
+    pub owner: Signer<'info>,
+
+    /// Co-signer, when the vault requires one
+    pub cosigner: Option<Signer<'info>>,
+
+    #[account(
+        mut,
+        seeds = [HISTORY_SEED, vault.key().as_ref()],
+        bump,
+    )]
+    pub history_view: Option<Account<'info, HistoryView>>,
+
+    #[account(
+        init,
+        payer = owner,
+        space = 8 + PendingWithdrawal::INIT_SPACE,
+        seeds = [PENDING_WITHDRAWAL_SEED, vault.key().as_ref()],
+        bump,
+    )]
+    pub pending_withdrawal: Option<Account<'info, PendingWithdrawal>>,
+
+    /// Recipient allowlist; required while allowlist mode is on
+    #[account(
+        seeds = [RECIPIENT_ALLOWLIST_SEED, vault.key().as_ref()],
+        bump,
+    )]
+    pub recipient_allowlist: Option<Account<'info, RecipientAllowlist>>,
+
+    /// CHECK: the vault's integrator denylist, possibly uninitialized;
+    /// required for vaults created through an integrator
+    #[account(
+        seeds = [DENYLIST_SEED, vault.integrator.as_ref()],
+        bump,
+    )]
+    pub denylist: Option<UncheckedAccount<'info>>,
+
+    /// CHECK: checked against `Denylist::compliance_program`
+    #[account(executable)]
+    pub compliance_program: Option<UncheckedAccount<'info>>,
+
+    #[account(address = anchor_spl::token::ID)]
+    pub token_program: Interface<'info, TokenInterface>,
+
+    pub system_program: Program<'info, System>,
+}
+
+#[derive(Accounts)]
+#[instruction(transfer_id: u64)]
+pub struct CreateConditionalTransfer<'info> {
+    #[account(
+        mut,
+        seeds = [VAULT_SEED, &vault.vault_id.to_le_bytes()],
+        bump,
+        has_one = owner @ ObscuraError::Unauthorized,
+    )]
+    pub vault: Account<'info, VaultState>,
+
+    #[account(
+        init,
+        payer = owner,
+        space = 8 + ConditionalTransfer::INIT_SPACE,
+        seeds = [
+            CONDITIONAL_TRANSFER_SEED,
+            vault.key().as_ref(),
+            &transfer_id.to_le_bytes(),
+        ],
+        bump,
+    )]
+    pub conditional_transfer: Account<'info, ConditionalTransfer>,
+
+    #[account(mut)]
+    pub owner: Signer<'info>,
+
+    /// Co-signer, when the vault requires one
+    pub cosigner: Option<Signer<'info>>,
+
+    /// Recipient allowlist; required while allowlist mode is on
+    #[account(
+        seeds = [RECIPIENT_ALLOWLIST_SEED, vault.key().as_ref()],
+        bump,
+    )]
+    pub recipient_allowlist: Option<Account<'info, RecipientAllowlist>>,
+
+    /// CHECK: the vault's integrator denylist, possibly uninitialized;
+    /// required for vaults created through an integrator
+    #[account(
+        seeds = [DENYLIST_SEED, vault.integrator.as_ref()],
+        bump,
+    )]
+    pub denylist: Option<UncheckedAccount<'info>>,
+
+    /// CHECK: checked against `Denylist::compliance_program`
+    #[account(executable)]
+    pub compliance_program: Option<UncheckedAccount<'info>>,
+
+    pub system_program: Program<'info, System>,
+}
+
+#[derive(Accounts)]
+pub struct ExecuteConditionalTransfer<'info> {
+    #[account(
+        mut,
+        close = owner,
+        seeds = [
+            CONDITIONAL_TRANSFER_SEED,
+            conditional_transfer.vault.as_ref(),
+            &conditional_transfer.transfer_id.to_le_bytes(),
+        ],
+        bump,
+        has_one = owner,
+        has_one = recipient,
+    )]
+    pub conditional_transfer: Account<'info, ConditionalTransfer>,
+
+    /// CHECK: payee, checked against the conditional transfer
+    #[account(mut)]
+    pub recipient: UncheckedAccount<'info>,
+
+    /// CHECK: rent refund destination, checked against the conditional
+    /// transfer
+    #[account(mut)]
+    pub owner: UncheckedAccount<'info>,
+
+    /// Pyth price update, for price conditions
+    pub price_update: Option<Account<'info, PriceUpdateV2>>,
+
+    /// Attestor co-signature, for attestation conditions
+    pub attestor: Option<Signer<'info>>,
+
+    /// The escrow's rating ticket, marked completed if passed
+    #[account(
+        mut,
+        seeds = [RATING_TICKET_SEED, conditional_transfer.key().as_ref()],
+        bump,
+    )]
+    pub rating_ticket: Option<Account<'info, RatingTicket>>,
+}
+
+#[derive(Accounts)]
+pub struct CancelConditionalTransfer<'info> {
+    #[account(
+        mut,
+        seeds = [VAULT_SEED, &vault.vault_id.to_le_bytes()],
+        bump,
+        has_one = owner @ ObscuraError::Unauthorized,
+    )]
+    pub vault: Account<'info, VaultState>,
+
+    #[account(
+        mut,
+        close = owner,
+        seeds = [
+            CONDITIONAL_TRANSFER_SEED,
+            vault.key().as_ref(),
+            &conditional_transfer.transfer_id.to_le_bytes(),
+        ],
+        bump,
+        has_one = vault,
+    )]
+    pub conditional_transfer: Account<'info, ConditionalTransfer>,
+
+    #[account(mut)]
+    pub owner: Signer<'info>,
+}
+
+#[derive(Accounts)]
+pub struct OpenEvidenceLocker<'info> {
+    #[account(
+        seeds = [
+            CONDITIONAL_TRANSFER_SEED,
+            conditional_transfer.vault.as_ref(),
+            &conditional_transfer.transfer_id.to_le_bytes(),
+        ],
+        bump,
+    )]
+    pub conditional_transfer: Account<'info, ConditionalTransfer>,
+
+    #[account(
+        init,
+        payer = party,
+        space = 8 + EvidenceLocker::INIT_SPACE,
+        seeds = [EVIDENCE_SEED, conditional_transfer.key().as_ref()],
+        bump,
+    )]
+    pub evidence_locker: Account<'info, EvidenceLocker>,
+
+    /// Vault owner or recipient of the conditional transfer
+    #[account(mut)]
+    pub party: Signer<'info>,
+
+    pub system_program: Program<'info, System>,
+}
+
+#[derive(Accounts)]
+pub struct OpenRatingTicket<'info> {
+    #[account(
+        seeds = [
+            CONDITIONAL_TRANSFER_SEED,
+            conditional_transfer.vault.as_ref(),
+            &conditional_transfer.transfer_id.to_le_bytes(),
+        ],
+        bump,
+    )]
+    pub conditional_transfer: Account<'info, ConditionalTransfer>,
+
+    #[account(
+        init,
+        payer = party,
+        space = 8 + RatingTicket::INIT_SPACE,
+        seeds = [RATING_TICKET_SEED, conditional_transfer.key().as_ref()],
+        bump,
+    )]
+    pub rating_ticket: Account<'info, RatingTicket>,
+
+    /// Vault owner or recipient of the conditional transfer
+    #[account(mut)]
+    pub party: Signer<'info>,
+
+    pub system_program: Program<'info, System>,
+}
+
+#[derive(Accounts)]
+pub struct RateCounterparty<'info> {
+    #[account(
+        mut,
+        seeds = [RATING_TICKET_SEED, rating_ticket.conditional_transfer.as_ref()],
+        bump,
+    )]
+    pub rating_ticket: Account<'info, RatingTicket>,
+
+    /// Vault owner or recipient of the completed escrow
+    pub rater: Signer<'info>,
+}
+
+#[derive(Accounts)]
+pub struct CloseRatingTicket<'info> {
+    #[account(
+        mut,
+        close = opened_by,
+        seeds = [RATING_TICKET_SEED, rating_ticket.conditional_transfer.as_ref()],
+        bump,
+        has_one = opened_by,
+        has_one = conditional_transfer,
+    )]
+    pub rating_ticket: Account<'info, RatingTicket>,
+
+    /// CHECK: the escrow, checked against the ticket; only its emptiness
+    /// is read
+    pub conditional_transfer: UncheckedAccount<'info>,
+
+    /// CHECK: rent refund destination, checked against the ticket
+    #[account(mut)]
+    pub opened_by: UncheckedAccount<'info>,
+}
+
+#[derive(Accounts)]
+pub struct AppendEvidence<'info> {
+    #[account(
+        seeds = [
+            CONDITIONAL_TRANSFER_SEED,
+            conditional_transfer.vault.as_ref(),
+            &conditional_transfer.transfer_id.to_le_bytes(),
+        ],
+        bump,
+    )]
+    pub conditional_transfer: Account<'info, ConditionalTransfer>,
+
+    #[account(
+        mut,
+        seeds = [EVIDENCE_SEED, conditional_transfer.key().as_ref()],
+        bump,
+        has_one = conditional_transfer,
+    )]
+    pub evidence_locker: Account<'info, EvidenceLocker>,
+
+    /// Vault owner or recipient of the conditional transfer
+    pub party: Signer<'info>,
+}
+
+#[derive(Accounts)]
+pub struct CloseEvidenceLocker<'info> {
+    #[account(
+        mut,
+        close = opened_by,
+        seeds = [EVIDENCE_SEED, evidence_locker.conditional_transfer.as_ref()],
+        bump,
+        has_one = arbiter @ ObscuraError::Unauthorized,
+        has_one = opened_by,
+    )]
+    pub evidence_locker: Account<'info, EvidenceLocker>,
+
+    pub arbiter: Signer<'info>,
+
+    /// CHECK: rent refund destination, checked against `opened_by`
+    #[account(mut)]
+    pub opened_by: UncheckedAccount<'info>,
+}
+
+#[derive(Accounts)]
+#[instruction(transfer_id: u64)]
+pub struct ScheduleTransfer<'info> {
+    #[account(
+        mut,
+        seeds = [VAULT_SEED, &vault.vault_id.to_le_bytes()],
+        bump,
+        has_one = owner @ ObscuraError::Unauthorized,
+    )]
+    pub vault: Account<'info, VaultState>,
+
+    #[account(
+        init,
+        payer = owner,
+        space = 8 + ScheduledTransfer::INIT_SPACE,
+        seeds = [
+            SCHEDULED_TRANSFER_SEED,
+            vault.key().as_ref(),
+            &transfer_id.to_le_bytes(),
+        ],
+        bump,
+    )]
+    pub scheduled_transfer: Account<'info, ScheduledTransfer>,
+
+    #[account(mut)]
+    pub owner: Signer<'info>,
+
+    /// Co-signer, when the vault requires one
+    pub cosigner: Option<Signer<'info>>,
+
+    /// Recipient allowlist; required while allowlist mode is on
+    #[account(
+        seeds = [RECIPIENT_ALLOWLIST_SEED, vault.key().as_ref()],
+        bump,
+    )]
+    pub recipient_allowlist: Option<Account<'info, RecipientAllowlist>>,
+
+    /// CHECK: the vault's integrator denylist, possibly uninitialized;
+    /// required for vaults created through an integrator
+    #[account(
+        seeds = [DENYLIST_SEED, vault.integrator.as_ref()],
+        bump,
+    )]
+    pub denylist: Option<UncheckedAccount<'info>>,
+
+    /// CHECK: checked against `Denylist::compliance_program`
+    #[account(executable)]
+    pub compliance_program: Option<UncheckedAccount<'info>>,
+
+    pub system_program: Program<'info, System>,
+}
+
+#[derive(Accounts)]
+pub struct ExecuteScheduledTransfer<'info> {
+    #[account(
+        mut,
+        close = owner,
+        seeds = [
+            SCHEDULED_TRANSFER_SEED,
+            scheduled_transfer.vault.as_ref(),
+            &scheduled_transfer.transfer_id.to_le_bytes(),
+        ],
+        bump,
+        has_one = owner,
+        has_one = recipient,
+    )]
+    pub scheduled_transfer: Account<'info, ScheduledTransfer>,
+
+    /// CHECK: payee, checked against the scheduled transfer
+    #[account(mut)]
+    pub recipient: UncheckedAccount<'info>,
+
+    /// CHECK: rent refund destination, checked against the scheduled
+    /// transfer
+    #[account(mut)]
+    pub owner: UncheckedAccount<'info>,
+
+    /// Whoever cranks the transfer; receives the tip
+    #[account(mut)]
+    pub cranker: Signer<'info>,
+
+    #[account(
+        init_if_needed,
+        payer = cranker,
+        space = 8 + CrankerStats::INIT_SPACE,
+        seeds = [CRANKER_SEED, cranker.key().as_ref()],
+        bump,
+    )]
+    pub cranker_stats: Account<'info, CrankerStats>,
+
+    pub system_program: Program<'info, System>,
+}
+
+#[derive(Accounts)]
+pub struct CancelScheduledTransfer<'info> {
+    #[account(
+        mut,
+        seeds = [VAULT_SEED, &vault.vault_id.to_le_bytes()],
+        bump,
+        has_one = owner @ ObscuraError::Unauthorized,
+    )]
+    pub vault: Account<'info, VaultState>,
+
+    #[account(
+        mut,
+        close = owner,
+        seeds = [
+            SCHEDULED_TRANSFER_SEED,
+            vault.key().as_ref(),
+            &scheduled_transfer.transfer_id.to_le_bytes(),
+        ],
+        bump,
+        has_one = vault,
+    )]
+    pub scheduled_transfer: Account<'info, ScheduledTransfer>,
+
+    #[account(mut)]
+    pub owner: Signer<'info>,
+}
+
+#[derive(Accounts)]
+#[instruction(subscription_id: u64)]
+pub struct CreateSubscription<'info> {
+    #[account(
+        mut,
+        seeds = [VAULT_SEED, &vault.vault_id.to_le_bytes()],
+        bump,
+        has_one = owner @ ObscuraError::Unauthorized,
+    )]
+    pub vault: Account<'info, VaultState>,
+
+    #[account(
+        init,
+        payer = owner,
+        space = 8 + Subscription::INIT_SPACE,
+        seeds = [
+            SUBSCRIPTION_SEED,
+            vault.key().as_ref(),
+            &subscription_id.to_le_bytes(),
+        ],
+        bump,
+    )]
+    pub subscription: Account<'info, Subscription>,
+
+    #[account(mut)]
+    pub owner: Signer<'info>,
+
+    /// Co-signer, when the vault requires one
+    pub cosigner: Option<Signer<'info>>,
+
+    /// Recipient allowlist; required while allowlist mode is on
+    #[account(
+        seeds = [RECIPIENT_ALLOWLIST_SEED, vault.key().as_ref()],
+        bump,
+    )]
+    pub recipient_allowlist: Option<Account<'info, RecipientAllowlist>>,
+
+    /// CHECK: the vault's integrator denylist, possibly uninitialized;
+    /// required for vaults created through an integrator
+    #[account(
+        seeds = [DENYLIST_SEED, vault.integrator.as_ref()],
+        bump,
+    )]
+    pub denylist: Option<UncheckedAccount<'info>>,
+
+    /// CHECK: checked against `Denylist::compliance_program`
+    #[account(executable)]
+    pub compliance_program: Option<UncheckedAccount<'info>>,
+
+    pub system_program: Program<'info, System>,
+}
+
+#[derive(Accounts)]
+pub struct ProcessSubscription<'info> {
+    #[account(
+        mut,
+        seeds = [VAULT_SEED, &vault.vault_id.to_le_bytes()],
+        bump,
+    )]
+    pub vault: Account<'info, VaultState>,
+
+    #[account(
+        mut,
+        seeds = [
+            SUBSCRIPTION_SEED,
+            vault.key().as_ref(),
+            &subscription.subscription_id.to_le_bytes(),
+        ],
+        bump,
+        has_one = vault,
+        has_one = recipient,
+    )]
+    pub subscription: Account<'info, Subscription>,
+
+    /// CHECK: payee, checked against the subscription
+    #[account(mut)]
+    pub recipient: UncheckedAccount<'info>,
+
+    /// Recipient allowlist; required while allowlist mode is on
+    #[account(
+        seeds = [RECIPIENT_ALLOWLIST_SEED, vault.key().as_ref()],
+        bump,
+    )]
+    pub recipient_allowlist: Option<Account<'info, RecipientAllowlist>>,
+
+    /// CHECK: the vault's integrator denylist, possibly uninitialized;
+    /// required for vaults created through an integrator
+    #[account(
+        seeds = [DENYLIST_SEED, vault.integrator.as_ref()],
+        bump,
+    )]
+    pub denylist: Option<UncheckedAccount<'info>>,
+
+    /// CHECK: checked against `Denylist::compliance_program`
+    #[account(executable)]
+    pub compliance_program: Option<UncheckedAccount<'info>>,
+}
+
+#[derive(Accounts)]
+pub struct CancelSubscription<'info> {
+    #[account(
+        mut,
+        close = owner,
+        seeds = [
+            SUBSCRIPTION_SEED,
+            subscription.vault.as_ref(),
+            &subscription.subscription_id.to_le_bytes(),
+        ],
+        bump,
+        has_one = owner,
+    )]
+    pub subscription: Account<'info, Subscription>,
+
+    /// CHECK: rent refund destination, checked against the subscription
+    #[account(mut)]
+    pub owner: UncheckedAccount<'info>,
+
+    /// Vault owner or recipient
+    pub authority: Signer<'info>,
+}
+
+#[derive(Accounts)]
+#[instruction(stream_id: u64)]
+pub struct CreateStream<'info> {
+    #[account(
+        mut,
+        seeds = [VAULT_SEED, &vault.vault_id.to_le_bytes()],
+        bump,
+        has_one = owner @ ObscuraError::Unauthorized,
+    )]
+    pub vault: Account<'info, VaultState>,
+
+    #[account(
+        init,
+        payer = owner,
+        space = 8 + Stream::INIT_SPACE,
+        seeds = [STREAM_SEED, vault.key().as_ref(), &stream_id.to_le_bytes()],
+        bump,
+    )]
+    pub stream: Account<'info, Stream>,
+
+    #[account(mut)]
+    pub owner: Signer<'info>,
+
+    /// Co-signer, when the vault requires one
+    pub cosigner: Option<Signer<'info>>,
+
+    /// Recipient allowlist; required while allowlist mode is on
+    #[account(
+        seeds = [RECIPIENT_ALLOWLIST_SEED, vault.key().as_ref()],
+        bump,
+    )]
+    pub recipient_allowlist: Option<Account<'info, RecipientAllowlist>>,
+
+    /// CHECK: the vault's integrator denylist, possibly uninitialized;
+    /// required for vaults created through an integrator
+    #[account(
+        seeds = [DENYLIST_SEED, vault.integrator.as_ref()],
+        bump,
+    )]
+    pub denylist: Option<UncheckedAccount<'info>>,
+
+    /// CHECK: checked against `Denylist::compliance_program`
+    #[account(executable)]
+    pub compliance_program: Option<UncheckedAccount<'info>>,
+
+    pub system_program: Program<'info, System>,
+}
+
+/// Delegate a payment stream to an ER validator.
+#[delegate]
+#[derive(Accounts)]
+pub struct DelegateStream<'info> {
+    #[account(
+        mut,
+        seeds = [
+            STREAM_SEED,
+            stream.vault.as_ref(),
+            &stream.stream_id.to_le_bytes(),
+        ],
+        bump,
+        has_one = recipient_vault,
+        del,
+    )]
+    pub stream: Account<'info, Stream>,
+
+    #[account(has_one = owner @ ObscuraError::Unauthorized)]
+    pub recipient_vault: Account<'info, VaultState>,
+
+    /// Recipient vault owner
+    #[account(mut)]
+    pub owner: Signer<'info>,
+}
+
+#[derive(Accounts)]
+pub struct WithdrawFromStream<'info> {
+    #[account(
+        mut,
+        seeds = [
+            STREAM_SEED,
+            stream.vault.as_ref(),
+            &stream.stream_id.to_le_bytes(),
+        ],
+        bump,
+        has_one = recipient_vault,
+    )]
+    pub stream: Account<'info, Stream>,
+
+    #[account(
+        mut,
+        seeds = [VAULT_SEED, &recipient_vault.vault_id.to_le_bytes()],
+        bump,
+        has_one = owner @ ObscuraError::Unauthorized,
+    )]
+    pub recipient_vault: Account<'info, VaultState>,
+
+    /// Recipient vault owner
+    pub owner: Signer<'info>,
+}
+
+/// Commit + undelegate a payment stream.
+#[commit]
+#[derive(Accounts)]
+pub struct UndelegateStream<'info> {
+    #[account(
+        mut,
+        seeds = [
+            STREAM_SEED,
+            stream.vault.as_ref(),
+            &stream.stream_id.to_le_bytes(),
+        ],
+        bump,
+        has_one = recipient_vault,
+        com,
+    )]
+    pub stream: Account<'info, Stream>,
+
+    pub recipient_vault: Account<'info, VaultState>,
+
+    /// Paying vault owner or recipient vault owner
+    #[account(mut)]
+    pub authority: Signer<'info>,
+}
+
+#[derive(Accounts)]
+pub struct CloseStream<'info> {
+    #[account(
+        mut,
+        seeds = [VAULT_SEED, &vault.vault_id.to_le_bytes()],
+        bump,
+        has_one = owner @ ObscuraError::Unauthorized,
+    )]
+    pub vault: Account<'info, VaultState>,
+
+    #[account(
+        mut,
+        seeds = [
+            STREAM_SEED,
+            vault.key().as_ref(),
+            &stream.stream_id.to_le_bytes(),
+        ],
+        bump,
+        has_one = vault,
+    )]
+    pub stream: Account<'info, Stream>,
+
+    #[account(mut)]
+    pub owner: Signer<'info>,
+}
+
+#[derive(Accounts)]
+#[instruction(order_id: u64)]
+pub struct PlaceLimitOrder<'info> {
+    #[account(seeds = [CONFIG_SEED], bump)]
+    pub config: Account<'info, ProgramConfig>,
+
+    #[account(
+        mut,
+        seeds = [VAULT_SEED, &vault.vault_id.to_le_bytes()],
+        bump,
+        has_one = owner @ ObscuraError::Unauthorized,
+    )]
+    pub vault: Account<'info, VaultState>,
+
+    #[account(
+        init,
+        payer = owner,
+        space = 8 + LimitOrder::INIT_SPACE,
+        seeds = [LIMIT_ORDER_SEED, vault.key().as_ref(), &order_id.to_le_bytes()],
+        bump,
+    )]
+    pub order: Account<'info, LimitOrder>,
+
+    #[account(mint::token_program = token_program)]
+    pub input_mint: InterfaceAccount<'info, Mint>,
+
+    #[account(
+        mint::token_program = token_program,
+        constraint = output_mint.key() != input_mint.key() @ ObscuraError::InvalidLimitOrder,
+    )]
+    pub output_mint: InterfaceAccount<'info, Mint>,
+
+    #[account(
+        mut,
+        seeds = [TOKEN_BALANCE_SEED, vault.key().as_ref(), input_mint.key().as_ref()],
+        bump,
+    )]
+    pub input_balance: Account<'info, VaultTokenBalance>,
+
+    #[account(
+        mut,
+        associated_token::mint = input_mint,
+        associated_token::authority = vault,
+        associated_token::token_program = token_program,
+    )]
+    pub vault_input_account: InterfaceAccount<'info, TokenAccount>,
+
+    /// CHECK: the governance-configured DEX adapter program
+    #[account(executable, address = config.dex_adapter @ ObscuraError::InvalidDexAdapter)]
+    pub dex_adapter: UncheckedAccount<'info>,
+
+    #[account(mut)]
+    pub owner: Signer<'info>,
+
+    /// Co-signer, when the vault requires one
+    pub cosigner: Option<Signer<'info>>,
+
+    pub token_program: Interface<'info, TokenInterface>,
+    pub system_program: Program<'info, System>,
+}
+
+#[derive(Accounts)]
+pub struct SettleLimitOrder<'info> {
+    #[account(seeds = [CONFIG_SEED], bump)]
+    pub config: Account<'info, ProgramConfig>,
+
+    #[account(
+        mut,
+        seeds = [VAULT_SEED, &vault.vault_id.to_le_bytes()],
+        bump,
+        has_one = owner @ ObscuraError::Unauthorized,
+    )]
+    pub vault: Account<'info, VaultState>,
+
+    #[account(
+        mut,
+        seeds = [LIMIT_ORDER_SEED, vault.key().as_ref(), &order.order_id.to_le_bytes()],
+        bump,
+        has_one = vault,
+    )]
+    pub order: Account<'info, LimitOrder>,
+
+    #[account(address = order.input_mint)]
+    pub input_mint: InterfaceAccount<'info, Mint>,
+
+    #[account(address = order.output_mint)]
+    pub output_mint: InterfaceAccount<'info, Mint>,
+
+    #[account(
+        mut,
+        seeds = [TOKEN_BALANCE_SEED, vault.key().as_ref(), input_mint.key().as_ref()],
+        bump,
+    )]
+    pub input_balance: Account<'info, VaultTokenBalance>,
+
+    #[account(
+        init_if_needed,
+        payer = owner,
+        space = 8 + VaultTokenBalance::INIT_SPACE,
+        seeds = [TOKEN_BALANCE_SEED, vault.key().as_ref(), output_mint.key().as_ref()],
+        bump,
+    )]
+    pub output_balance: Account<'info, VaultTokenBalance>,
+
+    #[account(
+        mut,
+        associated_token::mint = input_mint,
+        associated_token::authority = vault,
+        associated_token::token_program = token_program,
+    )]
+    pub vault_input_account: InterfaceAccount<'info, TokenAccount>,
+
+    #[account(
+        init_if_needed,
+        payer = owner,
+        associated_token::mint = output_mint,
+        associated_token::authority = vault,
+        associated_token::token_program = token_program,
+    )]
+    pub vault_output_account: InterfaceAccount<'info, TokenAccount>,
+
+    /// CHECK: the governance-configured DEX adapter program
+    #[account(executable, address = config.dex_adapter @ ObscuraError::InvalidDexAdapter)]
+    pub dex_adapter: UncheckedAccount<'info>,
+
+    #[account(mut)]
+    pub owner: Signer<'info>,
+
+    pub token_program: Interface<'info, TokenInterface>,
+    pub associated_token_program: Program<'info, AssociatedToken>,
+    pub system_program: Program<'info, System>,
+}
+
+#[derive(Accounts)]
+pub struct CloseTokenBalance<'info> {
+    #[account(
+        mut,
+        seeds = [VAULT_SEED, &vault.vault_id.to_le_bytes()],
+        bump,
+        has_one = owner @ ObscuraError::Unauthorized,
+    )]
+    pub vault: Account<'info, VaultState>,
+
+    #[account(
+        mut,
+        close = owner,
+        seeds = [TOKEN_BALANCE_SEED, vault.key().as_ref(), mint.key().as_ref()],
+        bump,
+    )]
+    pub token_balance: Account<'info, VaultTokenBalance>,
+
+    #[account(mint::token_program = token_program)]
+    pub mint: InterfaceAccount<'info, Mint>,
+
+    #[account(
+        mut,
+        associated_token::mint = mint,
+        associated_token::authority = vault,
+        associated_token::token_program = token_program,
+    )]
+    pub vault_token_account: InterfaceAccount<'info, TokenAccount>,
+
+    #[account(mut)]
+    pub owner: Signer<'info>,
+
+    pub token_program: Interface<'info, TokenInterface>,
+}
+
+/// Delegate a vault's per-mint token ledger to an ER validator.
+#[delegate]
+#[derive(Accounts)]
+#[instruction(validator: Pubkey)]
+pub struct DelegateTokenBalance<'info> {
+    #[account(
+        mut,
+        seeds = [TOKEN_BALANCE_SEED, vault.key().as_ref(), token_balance.mint.as_ref()],
+        bump,
+        del,
+    )]
+    pub token_balance: Account<'info, VaultTokenBalance>,
+
+    #[account(has_one = owner @ ObscuraError::Unauthorized)]
+    pub vault: Account<'info, VaultState>,
+
+    #[account(mut)]
+    pub owner: Signer<'info>,
+
+    /// Registry entry for `validator`, used to pick the delegation SDK version
+    #[account(
+        seeds = [VALIDATOR_SEED, validator.as_ref()],
+        bump,
+    )]
+    pub validator_info: Account<'info, ValidatorInfo>,
+}
+
+/// Private token transfer inside the ER/PER; commits the token ledger.
+#[commit]
+#[derive(Accounts)]
+pub struct PrivateTokenTransfer<'info> {
+    #[account(
+        mut,
+        seeds = [TOKEN_BALANCE_SEED, vault.key().as_ref(), token_balance.mint.as_ref()],
+        bump,
+        com,
+    )]
+    pub token_balance: Account<'info, VaultTokenBalance>,
+
+    #[account(has_one = owner @ ObscuraError::Unauthorized)]
+    pub vault: Account<'info, VaultState>,
+
+    #[account(mut)]
+    pub owner: Signer<'info>,
+
+    /// Co-signer, when the vault requires one
+    pub cosigner: Option<Signer<'info>>,
+
+    /// Recipient allowlist; required while allowlist mode is on
+    #[account(
+        seeds = [RECIPIENT_ALLOWLIST_SEED, vault.key().as_ref()],
+        bump,
+    )]
+    pub recipient_allowlist: Option<Account<'info, RecipientAllowlist>>,
+
+    /// CHECK: the vault's integrator denylist, possibly uninitialized;
+    /// required for vaults created through an integrator
+    #[account(
+        seeds = [DENYLIST_SEED, vault.integrator.as_ref()],
+        bump,
+    )]
+    pub denylist: Option<UncheckedAccount<'info>>,
+
+    /// CHECK: checked against `Denylist::compliance_program`
+    #[account(executable)]
+    pub compliance_program: Option<UncheckedAccount<'info>>,
+}
+
+#[derive(Accounts)]
+pub struct DepositNft<'info> {
+    #[account(
+        mut,
+        seeds = [VAULT_SEED, &vault.vault_id.to_le_bytes()],
+        bump,
+    )]
+    pub vault: Account<'info, VaultState>,
+
+    #[account(
+        init,
+        payer = depositor,
+        space = 8 + NftRecord::INIT_SPACE,
+        seeds = [NFT_SEED, vault.key().as_ref(), mint.key().as_ref()],
+        bump,
+    )]
+    pub nft_record: Account<'info, NftRecord>,
+
+    #[account(
+        constraint = mint.decimals == 0 && mint.supply == 1 @ ObscuraError::UnsupportedMint,
+    )]
+    pub mint: InterfaceAccount<'info, Mint>,
+
+    /// CHECK: Metaplex metadata; validated by Token Metadata
+    #[account(mut)]
+    pub metadata: UncheckedAccount<'info>,
+
+    /// CHECK: Metaplex master edition; validated by Token Metadata
+    pub edition: UncheckedAccount<'info>,
+
+    /// CHECK: depositor's token account; validated by Token Metadata
+    #[account(mut)]
+    pub depositor_token_account: UncheckedAccount<'info>,
+
+    /// CHECK: vault's associated token account, created by Token Metadata
+    #[account(mut)]
+    pub vault_token_account: UncheckedAccount<'info>,
+
+    /// CHECK: pNFT token record of the depositor's token account
+    #[account(mut)]
+    pub owner_token_record: Option<UncheckedAccount<'info>>,
+
+    /// CHECK: pNFT token record of the vault's token account
+    #[account(mut)]
+    pub vault_token_record: Option<UncheckedAccount<'info>>,
+
+    /// CHECK: pNFT rule set program, if the NFT has a rule set
+    pub authorization_rules_program: Option<UncheckedAccount<'info>>,
+
+    /// CHECK: pNFT rule set, if the NFT has one
+    pub authorization_rules: Option<UncheckedAccount<'info>>,
+
+    #[account(mut)]
+    pub depositor: Signer<'info>,
+
+    /// CHECK: address-checked
+    #[account(address = mpl_token_metadata::ID)]
+    pub token_metadata_program: UncheckedAccount<'info>,
+
+    /// CHECK: address-checked
+    #[account(address = anchor_lang::solana_program::sysvar::instructions::ID)]
+    pub sysvar_instructions: UncheckedAccount<'info>,
+
+    pub token_program: Interface<'info, TokenInterface>,
+    pub associated_token_program: Program<'info, AssociatedToken>,
+    pub system_program: Program<'info, System>,
+}
+
+#[derive(Accounts)]
+pub struct WithdrawNft<'info> {
+    #[account(
+        mut,
+        seeds = [VAULT_SEED, &vault.vault_id.to_le_bytes()],
+        bump,
+        has_one = owner @ ObscuraError::Unauthorized,
+    )]
+    pub vault: Account<'info, VaultState>,
+
+    #[account(
+        mut,
+        close = owner,
+        seeds = [NFT_SEED, vault.key().as_ref(), mint.key().as_ref()],
+        bump,
+    )]
+    pub nft_record: Account<'info, NftRecord>,
+
+    pub mint: InterfaceAccount<'info, Mint>,
+
+    /// CHECK: Metaplex metadata; validated by Token Metadata
+    #[account(mut)]
+    pub metadata: UncheckedAccount<'info>,
+
+    /// CHECK: Metaplex master edition; validated by Token Metadata
+    pub edition: UncheckedAccount<'info>,
+
+    /// CHECK: vault's token account; validated by Token Metadata
+    #[account(mut)]
+    pub vault_token_account: UncheckedAccount<'info>,
+
+    /// CHECK: owner's associated token account, created by Token Metadata
+    #[account(mut)]
+    pub owner_token_account: UncheckedAccount<'info>,
+
+    /// CHECK: pNFT token record of the vault's token account
+    #[account(mut)]
+    pub vault_token_record: Option<UncheckedAccount<'info>>,
+
+    /// CHECK: pNFT token record of the owner's token account
+    #[account(mut)]
+    pub owner_token_record: Option<UncheckedAccount<'info>>,
+
+    /// CHECK: pNFT rule set program, if the NFT has a rule set
+    pub authorization_rules_program: Option<UncheckedAccount<'info>>,
+
+    /// CHECK: pNFT rule set, if the NFT has one
+    pub authorization_rules: Option<UncheckedAccount<'info>>,
+
+    #[account(mut)]
+    pub owner: Signer<'info>,
+
+    /// Co-signer, when the vault requires one
+    pub cosigner: Option<Signer<'info>>,
+
+    /// CHECK: address-checked
+    #[account(address = mpl_token_metadata::ID)]
+    pub token_metadata_program: UncheckedAccount<'info>,
+
+    /// CHECK: address-checked
+    #[account(address = anchor_lang::solana_program::sysvar::instructions::ID)]
+    pub sysvar_instructions: UncheckedAccount<'info>,
+
+    pub token_program: Interface<'info, TokenInterface>,
+    pub associated_token_program: Program<'info, AssociatedToken>,
+    pub system_program: Program<'info, System>,
+}
+
+#[derive(Accounts)]
+#[instruction(root: [u8; 32], data_hash: [u8; 32], creator_hash: [u8; 32], nonce: u64)]
+pub struct DepositCnft<'info> {
+    #[account(
+        mut,
+        seeds = [VAULT_SEED, &vault.vault_id.to_le_bytes()],
+        bump,
+    )]
+    pub vault: Account<'info, VaultState>,
+
+    #[account(
+        init,
+        payer = depositor,
+        space = 8 + CnftRecord::INIT_SPACE,
+        seeds = [CNFT_SEED, vault.key().as_ref(), merkle_tree.key().as_ref(), &nonce.to_le_bytes()],
+        bump,
+    )]
+    pub cnft_record: Account<'info, CnftRecord>,
+
+    /// CHECK: Bubblegum tree config; validated by Bubblegum
+    pub tree_config: UncheckedAccount<'info>,
+
+    /// CHECK: concurrent Merkle tree; validated by Bubblegum
+    #[account(mut)]
+    pub merkle_tree: UncheckedAccount<'info>,
+
+    /// CHECK: current leaf delegate (the depositor if none is set)
+    pub leaf_delegate: UncheckedAccount<'info>,
+
+    #[account(mut)]
+    pub depositor: Signer<'info>,
+
+    /// CHECK: address-checked
+    #[account(address = mpl_bubblegum::ID)]
+    pub bubblegum_program: UncheckedAccount<'info>,
+
+    /// CHECK: noop log wrapper; validated by Bubblegum
+    pub log_wrapper: UncheckedAccount<'info>,
+
+    /// CHECK: account compression program; validated by Bubblegum
+    pub compression_program: UncheckedAccount<'info>,
+
+    pub system_program: Program<'info, System>,
+}
+
+#[derive(Accounts)]
+#[instruction(root: [u8; 32], data_hash: [u8; 32], creator_hash: [u8; 32], nonce: u64)]
+pub struct WithdrawCnft<'info> {
+    #[account(
+        mut,
+        seeds = [VAULT_SEED, &vault.vault_id.to_le_bytes()],
+        bump,
+        has_one = owner @ ObscuraError::Unauthorized,
+    )]
+    pub vault: Account<'info, VaultState>,
+
+    #[account(
+        mut,
+        close = owner,
+        seeds = [CNFT_SEED, vault.key().as_ref(), merkle_tree.key().as_ref(), &nonce.to_le_bytes()],
+        bump,
+    )]
+    pub cnft_record: Account<'info, CnftRecord>,
+
+    /// CHECK: Bubblegum tree config; validated by Bubblegum
+    pub tree_config: UncheckedAccount<'info>,
+
+    /// CHECK: concurrent Merkle tree; validated by Bubblegum
+    #[account(mut)]
+    pub merkle_tree: UncheckedAccount<'info>,
+
+    #[account(mut)]
+    pub owner: Signer<'info>,
+
+    /// Co-signer, when the vault requires one
+    pub cosigner: Option<Signer<'info>>,
+
+    /// CHECK: address-checked
+    #[account(address = mpl_bubblegum::ID)]
+    pub bubblegum_program: UncheckedAccount<'info>,
+
+    /// CHECK: noop log wrapper; validated by Bubblegum
+    pub log_wrapper: UncheckedAccount<'info>,
+
+    /// CHECK: account compression program; validated by Bubblegum
+    pub compression_program: UncheckedAccount<'info>,
+
+    pub system_program: Program<'info, System>,
+}
+
+#[derive(Accounts)]
+pub struct AddGuardian<'info> {
+    #[account(
+        init_if_needed,
+        payer = owner,
+        space = 8 + GuardianSet::INIT_SPACE,
+        seeds = [GUARDIAN_SEED, vault.key().as_ref()],
+        bump,
+    )]
+    pub guardian_set: Account<'info, GuardianSet>,
+
+    #[account(has_one = owner @ ObscuraError::Unauthorized)]
+    pub vault: Account<'info, VaultState>,
+
+    #[account(mut)]
+    pub owner: Signer<'info>,
+
+    pub system_program: Program<'info, System>,
+}
+
+#[derive(Accounts)]
+pub struct UpdateGuardians<'info> {
+    #[account(
+        mut,
+        seeds = [GUARDIAN_SEED, vault.key().as_ref()],
+        bump,
+    )]
+    pub guardian_set: Account<'info, GuardianSet>,
+
+    #[account(has_one = owner @ ObscuraError::Unauthorized)]
+    pub vault: Account<'info, VaultState>,
+
+    pub owner: Signer<'info>,
+}
+
+#[derive(Accounts)]
+pub struct GuardianAction<'info> {
+    #[account(
+        mut,
+        seeds = [GUARDIAN_SEED, vault.key().as_ref()],
+        bump,
+    )]
+    pub guardian_set: Account<'info, GuardianSet>,
+
+    pub vault: Account<'info, VaultState>,
+
+    pub guardian: Signer<'info>,
+}
+
+#[derive(Accounts)]
+pub struct FinalizeRecovery<'info> {
+    #[account(
+        mut,
+        seeds = [GUARDIAN_SEED, vault.key().as_ref()],
+        bump,
+    )]
+    pub guardian_set: Account<'info, GuardianSet>,
+
+    #[account(
+        mut,
+        seeds = [VAULT_SEED, &vault.vault_id.to_le_bytes()],
+        bump,
+    )]
+    pub vault: Account<'info, VaultState>,
+}
+
+#[derive(Accounts)]
+pub struct ClaimInheritedVault<'info> {
+    #[account(
+        mut,
+        seeds = [VAULT_SEED, &vault.vault_id.to_le_bytes()],
+        bump,
+        has_one = beneficiary @ ObscuraError::Unauthorized,
+    )]
+    pub vault: Account<'info, VaultState>,
+
+    pub beneficiary: Signer<'info>,
+}
+
+#[derive(Accounts)]
+pub struct ConvertToMultisig<'info> {
+    #[account(
+        init,
+        payer = owner,
+        space = 8 + VaultOwners::INIT_SPACE,
+        seeds = [VAULT_OWNERS_SEED, vault.key().as_ref()],
+        bump,
+    )]
+    pub vault_owners: Account<'info, VaultOwners>,
+
+    #[account(
+        mut,
+        seeds = [VAULT_SEED, &vault.vault_id.to_le_bytes()],
+        bump,
+        has_one = owner @ ObscuraError::Unauthorized,
+    )]
+    pub vault: Account<'info, VaultState>,
+
+    #[account(mut)]
+    pub owner: Signer<'info>,
+
+    pub system_program: Program<'info, System>,
+}
+
+#[derive(Accounts)]
+pub struct SetVaultOwners<'info> {
+    /// Signs only through `execute_action`
+    #[account(
+        mut,
+        seeds = [VAULT_OWNERS_SEED, vault_owners.vault.as_ref()],
+        bump,
+        constraint = vault_owners.to_account_info().is_signer @ ObscuraError::Unauthorized,
+    )]
+    pub vault_owners: Account<'info, VaultOwners>,
+}
+
+#[derive(Accounts)]
+pub struct SweepVaultOwners<'info> {
+    /// Signs only through `execute_action`
+    #[account(
+        mut,
+        seeds = [VAULT_OWNERS_SEED, vault_owners.vault.as_ref()],
+        bump,
+        constraint = vault_owners.to_account_info().is_signer @ ObscuraError::Unauthorized,
+    )]
+    pub vault_owners: Account<'info, VaultOwners>,
+
+    /// CHECK: any account; named in the approved action
+    #[account(mut)]
+    pub destination: UncheckedAccount<'info>,
+}
+
+#[derive(Accounts)]
+pub struct ProposeAction<'info> {
+    #[account(
+        init,
+        payer = proposer,
+        space = 8 + MultisigAction::INIT_SPACE,
+        seeds = [
+            ACTION_SEED,
+            vault_owners.key().as_ref(),
+            &vault_owners.action_count.to_le_bytes(),
+        ],
+        bump,
+    )]
+    pub action: Account<'info, MultisigAction>,
+
+    #[account(
+        mut,
+        seeds = [VAULT_OWNERS_SEED, vault_owners.vault.as_ref()],
+        bump,
+    )]
+    pub vault_owners: Account<'info, VaultOwners>,
+
+    #[account(mut)]
+    pub proposer: Signer<'info>,
+
+    pub system_program: Program<'info, System>,
+}
+
+#[derive(Accounts)]
+pub struct ApproveAction<'info> {
+    #[account(mut, has_one = vault_owners)]
+    pub action: Account<'info, MultisigAction>,
+
+    #[account(
+        seeds = [VAULT_OWNERS_SEED, vault_owners.vault.as_ref()],
+        bump,
+    )]
+    pub vault_owners: Account<'info, VaultOwners>,
+
+    pub signer: Signer<'info>,
+}
+
+#[derive(Accounts)]
+pub struct ExecuteAction<'info> {
+    #[account(
+        mut,
+        close = proposer,
+        has_one = vault_owners,
+        has_one = proposer,
+    )]
+    pub action: Account<'info, MultisigAction>,
+
+    #[account(
+        seeds = [VAULT_OWNERS_SEED, vault_owners.vault.as_ref()],
+        bump,
+    )]
+    pub vault_owners: Account<'info, VaultOwners>,
+
+    pub executor: Signer<'info>,
+
+    /// CHECK: rent refund destination, checked against `proposer`
+    #[account(mut)]
+    pub proposer: UncheckedAccount<'info>,
+
+    /// CHECK: this program, invoked with the `VaultOwners` PDA as signer
+    #[account(address = crate::ID)]
+    pub obscura_program: UncheckedAccount<'info>,
+}
+
+#[derive(Accounts)]
+pub struct CancelAction<'info> {
+    #[account(
+        mut,
+        close = proposer,
+        has_one = vault_owners,
+        has_one = proposer,
+    )]
+    pub action: Account<'info, MultisigAction>,
+
+    pub vault_owners: Account<'info, VaultOwners>,
+
+    #[account(mut)]
+    pub proposer: Signer<'info>,
+}
+
+#[derive(Accounts)]
+#[instruction(group_id: u64)]
+pub struct CreateGroup<'info> {
+    #[account(
+        init,
+        payer = owner,
+        space = 8 + Group::INIT_SPACE,
+        seeds = [GROUP_SEED, owner.key().as_ref(), &group_id.to_le_bytes()],
+        bump,
+    )]
+    pub group: Account<'info, Group>,
+
+    #[account(
+        seeds = [VAULT_SEED, &vault.vault_id.to_le_bytes()],
+        bump,
+        has_one = owner @ ObscuraError::Unauthorized,
+    )]
+    pub vault: Account<'info, VaultState>,
+
+    #[account(mut)]
+    pub owner: Signer<'info>,
+
+    pub system_program: Program<'info, System>,
+}
+
+/// A member vault's owner acting on a group.
+#[derive(Accounts)]
+pub struct GroupMemberAction<'info> {
+    #[account(
+        mut,
+        seeds = [GROUP_SEED, group.creator.as_ref(), &group.group_id.to_le_bytes()],
+        bump,
+    )]
+    pub group: Account<'info, Group>,
+
+    #[account(
+        seeds = [VAULT_SEED, &vault.vault_id.to_le_bytes()],
+        bump,
+        has_one = owner @ ObscuraError::Unauthorized,
+    )]
+    pub vault: Account<'info, VaultState>,
+
+    pub owner: Signer<'info>,
+}
+
+/// Delegate an expense group to an ER validator.
+#[delegate]
+#[derive(Accounts)]
+pub struct DelegateGroup<'info> {
+    #[account(
+        mut,
+        seeds = [GROUP_SEED, group.creator.as_ref(), &group.group_id.to_le_bytes()],
+        bump,
+        del,
+    )]
+    pub group: Account<'info, Group>,
+
+    #[account(
+        seeds = [VAULT_SEED, &vault.vault_id.to_le_bytes()],
+        bump,
+        has_one = owner @ ObscuraError::Unauthorized,
+    )]
+    pub vault: Account<'info, VaultState>,
+
+    #[account(mut)]
+    pub owner: Signer<'info>,
+}
+
+/// Settle a group inside the ER; commits the group and member vaults.
+#[commit]
+#[derive(Accounts)]
+pub struct SettleGroup<'info> {
+    #[account(
+        mut,
+        seeds = [GROUP_SEED, group.creator.as_ref(), &group.group_id.to_le_bytes()],
+        bump,
+        com,
+    )]
+    pub group: Account<'info, Group>,
+
+    /// Owner of one of the member vaults
+    #[account(mut)]
+    pub member: Signer<'info>,
+
+    /// CHECK: checked against `Denylist::compliance_program`
+    #[account(executable)]
+    pub compliance_program: Option<UncheckedAccount<'info>>,
+}
+
+#[derive(Accounts)]
+#[instruction(session_id: u64)]
+pub struct OpenPosSession<'info> {
+    #[account(
+        init,
+        payer = owner,
+        space = 8 + PosSession::INIT_SPACE,
+        seeds = [POS_SESSION_SEED, vault.key().as_ref(), &session_id.to_le_bytes()],
+        bump,
+    )]
+    pub pos_session: Account<'info, PosSession>,
+
+    #[account(
+        seeds = [VAULT_SEED, &vault.vault_id.to_le_bytes()],
+        bump,
+        has_one = owner @ ObscuraError::Unauthorized,
+    )]
+    pub vault: Account<'info, VaultState>,
+
+    #[account(mut)]
+    pub owner: Signer<'info>,
+
+    pub system_program: Program<'info, System>,
+}
+
+#[derive(Accounts)]
+pub struct RecordPosPayment<'info> {
+    #[account(
+        mut,
+        seeds = [
+            POS_SESSION_SEED,
+            pos_session.vault.as_ref(),
+            &pos_session.session_id.to_le_bytes(),
+        ],
+        bump,
+        has_one = operator @ ObscuraError::Unauthorized,
+    )]
+    pub pos_session: Account<'info, PosSession>,
+
+    pub operator: Signer<'info>,
+}
+
+#[derive(Accounts)]
+pub struct ClosePosSession<'info> {
+    #[account(
+        mut,
+        close = owner,
+        seeds = [
+            POS_SESSION_SEED,
+            vault.key().as_ref(),
+            &pos_session.session_id.to_le_bytes(),
+        ],
+        bump,
+    )]
+    pub pos_session: Account<'info, PosSession>,
+
+    #[account(
+        seeds = [VAULT_SEED, &vault.vault_id.to_le_bytes()],
+        bump,
+        has_one = owner @ ObscuraError::Unauthorized,
+    )]
+    pub vault: Account<'info, VaultState>,
+
+    #[account(mut)]
+    pub owner: Signer<'info>,
+}
+
+#[derive(Accounts)]
+pub struct InspectVaults {}
+
+// ---------------------------------------------------------------------------
+// State
+// ---------------------------------------------------------------------------
+
+/// On-chain vault state managed by Obscura.
+///
+/// When delegated to an ER validator, mutations happen with sub-second
+/// finality.  When delegated to the TEE validator, the account data is
+/// only visible to permitted pubkeys.
+#[account]
+#[derive(InitSpace, Default)]
+pub struct VaultState {
+    /// Wallet owner
+    pub owner: Pubkey,
+    /// Unique vault identifier
+    pub vault_id: u64,
+    /// SOL balance in lamports
+    pub balance: u64,
+    /// Whether the account is currently delegated to an ER validator
+    pub is_delegated: bool,
+    /// Pubkey of the ER validator (default = not delegated)
+    pub delegate_validator: Pubkey,
+    /// Unix timestamp of creation
+    pub created_at: i64,
+    /// Unix timestamp of last activity
+    pub last_activity: i64,
+    /// Monotonically increasing nonce for replay protection
+    pub nonce: u64,
+    /// Whether delegated to TEE validator (Private ER)
+    pub is_private: bool,
+    /// Minimum `ValidatorScore` required to delegate (0 = no policy)
+    pub min_validator_score: u16,
+    /// Delegation SDK version the vault was last delegated under
+    pub delegation_sdk_version: u8,
+    /// Invoiced-billing fee ledger
+    pub billing: FeeLedger,
+    /// UTC day (`unix_timestamp / 86_400`) of the last rebalance out
+    pub rebalance_day: u32,
+    /// Rebalances out of this vault during `rebalance_day`
+    pub rebalances_today: u8,
+    /// Receive-only (cold storage) mode: all outbound instructions fail
+    pub receive_only: bool,
+    /// When a requested unlock takes effect (0 = none requested)
+    pub receive_only_unlock_at: i64,
+    /// Outflow velocity tracking and step-up policy
+    pub velocity: VelocityPolicy,
+    /// Strict upgrade mode: last `PROGRAM_VERSION` the owner acknowledged
+    /// (0 = not strict)
+    pub min_accepted_program_version: u32,
+    /// Most lamports `pay_fees_from_vault` may take per transaction
+    /// (0 = disabled)
+    pub fee_payment_cap: u64,
+    /// Number of open `VaultTokenBalance` sub-balances
+    pub token_balances: u16,
+    /// Custodial vaults: key that can claim full custody (default =
+    /// self-custodial)
+    pub custody_claimant: Pubkey,
+    /// Part of `balance` locked in `SavingsGoal`s
+    pub locked_balance: u64,
+    /// Round-up savings rule applied to outgoing transfers
+    pub round_up: RoundUpRule,
+    /// Percentage split of outgoing transfers or deposits
+    pub split: SplitRule,
+    /// Key allowed to grant and revoke read-only permissions (default =
+    /// none)
+    pub permission_manager: Pubkey,
+    /// `Integrator` the vault was created through (default = none)
+    pub integrator: Pubkey,
+    /// Per-epoch outflow cap
+    pub spending_limit: SpendingLimit,
+    /// Rolling 24h cap on `withdraw`
+    pub withdrawal_limit: WithdrawalLimit,
+    /// L1 slot from which L1 state is current: set when the vault is
+    /// delegated (earlier reads are stale) or force-settled
+    pub last_settled_slot: u64,
+    /// sha256 of the policy document last applied with `apply_policy`
+    /// (zero = none).  Individual setters leave it in place; compare
+    /// against a fresh `export_policy` to detect drift.
+    pub policy_hash: [u8; 32],
+    /// Whether `private_transfer` and `withdraw` may only pay recipients
+    /// on the vault's `RecipientAllowlist`
+    pub allowlist_enabled: bool,
+    /// Emergency freeze: all outbound instructions fail
+    pub frozen: bool,
+    /// Key that may freeze the vault alongside the owner and, when set,
+    /// is the only key that may unfreeze it (default = none)
+    pub freeze_guardian: Pubkey,
+    /// Key that may claim the vault once it has been inactive for
+    /// `inheritance_period` (default = none)
+    pub beneficiary: Pubkey,
+    /// Seconds without `last_activity` before `beneficiary` may claim
+    pub inheritance_period: i64,
+    /// Second key (e.g. a server-side 2FA key) that must sign
+    /// `private_transfer` and `withdraw` alongside the owner (default =
+    /// none).  Passed as the same `cosigner` account as the velocity
+    /// step-up cosigner.
+    pub cosigner: Pubkey,
+    /// Fees owed to the ER operator under the rate card accepted at
+    /// delegation
+    pub operator_fees: OperatorFees,
+}
+
+impl VaultState {
+    /// Add `amount` to the tracked balance.
+    pub fn credit(&mut self, amount: u64) -> Result<()> {
+        self.balance = self
+            .balance
+            .checked_add(amount)
+            .ok_or(ObscuraError::MathOverflow)?;
+        Ok(())
+    }
+
+    /// In strict upgrade mode, fail until the owner has acknowledged the
+    /// running `PROGRAM_VERSION`.
+    pub fn require_acknowledged_upgrade(&self) -> Result<()> {
+        require!(
+            self.min_accepted_program_version == 0
+                || self.min_accepted_program_version >= PROGRAM_VERSION,
+            ObscuraError::UpgradeNotAcknowledged
+        );
+        Ok(())
+    }
+
+    /// One-way handover of a custodial vault to `new_owner`, resetting
+    /// the operator-set payment policies.
+    pub fn hand_over_custody(&mut self, new_owner: Pubkey) {
+        self.owner = new_owner;
+        self.custody_claimant = Pubkey::default();
+        self.velocity.cosigner = Pubkey::default();
+        self.fee_payment_cap = 0;
+        self.permission_manager = Pubkey::default();
+        self.spending_limit = SpendingLimit::default();
+        self.withdrawal_limit = WithdrawalLimit::default();
+        self.policy_hash = [0; 32];
+        self.allowlist_enabled = false;
+        self.freeze_guardian = Pubkey::default();
+        self.beneficiary = Pubkey::default();
+        self.inheritance_period = 0;
+        self.cosigner = Pubkey::default();
+    }
+
+    /// The policy fields `export_policy` and `apply_policy` cover.
+    pub fn policy(&self) -> VaultPolicy {
+        let optional = |key: Pubkey| (key != Pubkey::default()).then_some(key);
+        VaultPolicy {
+            min_validator_score: self.min_validator_score,
+            velocity_factor_pct: self.velocity.factor_pct,
+            velocity_cosigner: optional(self.velocity.cosigner),
+            fee_payment_cap: self.fee_payment_cap,
+            spending_limit_per_epoch: self.spending_limit.target(),
+            withdrawal_daily_cap: self.withdrawal_limit.daily_cap,
+            withdrawal_timelock_threshold: self.withdrawal_limit.timelock_threshold,
+            permission_manager: optional(self.permission_manager),
+        }
+    }
+
+    /// `HEALTH_*` bits describing the vault's active policies.
+    pub fn policy_flags(&self) -> u16 {
+        [
+            (self.receive_only, HEALTH_RECEIVE_ONLY),
+            (self.receive_only_unlock_at != 0, HEALTH_UNLOCK_PENDING),
+            (
+                self.min_accepted_program_version != 0,
+                HEALTH_STRICT_UPGRADE,
+            ),
+            (
+                self.min_accepted_program_version != 0
+                    && self.min_accepted_program_version < PROGRAM_VERSION,
+                HEALTH_UPGRADE_UNACKNOWLEDGED,
+            ),
+            (self.velocity.factor_pct != 0, HEALTH_VELOCITY_POLICY),
+            (self.custody_claimant != Pubkey::default(), HEALTH_CUSTODIAL),
+            (self.billing.fee_bps != 0, HEALTH_INVOICED),
+            (
+                self.spending_limit.amount_per_epoch != 0,
+                HEALTH_SPENDING_LIMIT,
+            ),
+            (
+                self.withdrawal_limit.daily_cap != 0,
+                HEALTH_WITHDRAWAL_LIMIT,
+            ),
+            (
+                self.withdrawal_limit.timelock_threshold != 0,
+                HEALTH_WITHDRAWAL_TIMELOCK,
+            ),
+            (self.allowlist_enabled, HEALTH_RECIPIENT_ALLOWLIST),
+            (self.frozen, HEALTH_FROZEN),
+            (self.beneficiary != Pubkey::default(), HEALTH_BENEFICIARY),
+            (self.cosigner != Pubkey::default(), HEALTH_COSIGNER),
+        ]
+        .iter()
+        .filter(|(set, _)| *set)
+        .fold(0, |flags, (_, bit)| flags | bit)
+    }
+
+    /// Whether the vault is delegated and its owner has been inactive
+    /// for at least `threshold` seconds (never, if `threshold` is 0).
+    pub fn inactive_for(&self, unix_timestamp: i64, threshold: i64) -> bool {
+        self.is_delegated && threshold != 0 && unix_timestamp >= self.last_activity + threshold
+    }
+
+    /// In allowlist mode, fail unless `recipient` is on `allowlist`.
+    pub fn require_allowed_recipient(
+        &self,
+        allowlist: Option<&RecipientAllowlist>,
+        recipient: &Pubkey,
+    ) -> Result<()> {
+        require!(
+            !self.allowlist_enabled || allowlist.is_some_and(|a| a.recipients.contains(recipient)),
+            ObscuraError::RecipientNotAllowed
+        );
+        Ok(())
+    }
+
+    /// When a co-signer is set, fail unless `cosigner` is it.
+    pub fn require_cosigned(&self, cosigner: Option<Pubkey>) -> Result<()> {
+        require!(
+            self.cosigner == Pubkey::default() || cosigner == Some(self.cosigner),
+            ObscuraError::CosignerRequired
+        );
+        Ok(())
+    }
+
+    /// Fail if the vault is frozen or in receive-only mode.
+    pub fn require_outbound_allowed(&self) -> Result<()> {
+        require!(!self.frozen, ObscuraError::VaultFrozen);
+        require!(!self.receive_only, ObscuraError::ReceiveOnly);
+        Ok(())
+    }
+
+    /// Balance not locked in savings goals or owed to the ER operator.
+    pub fn spendable(&self) -> u64 {
+        self.balance
+            .saturating_sub(self.locked_balance)
+            .saturating_sub(self.operator_fees.owed)
+    }
+
+    /// Remove `amount` from the tracked balance.
+    ///
+    /// Leaves the vault untouched on failure.
+    pub fn debit(&mut self, amount: u64) -> Result<()> {
+        require!(
+            self.spendable() >= amount,
+            ObscuraError::InsufficientBalance
+        );
+        self.balance -= amount;
+        Ok(())
+    }
+
+    /// Debit an outgoing transfer and advance the replay-protection nonce.
+    pub fn record_transfer(&mut self, amount: u64) -> Result<()> {
+        let nonce = self
+            .nonce
+            .checked_add(1)
+            .ok_or(ObscuraError::MathOverflow)?;
+        self.debit(amount)?;
+        self.nonce = nonce;
+        Ok(())
+    }
+}
+
+/// Round-up savings rule: outgoing transfers are rounded up to a multiple
+/// of `increment` and the difference is skimmed into `savings_vault`.
+#[derive(AnchorSerialize, AnchorDeserialize, Clone, Copy, Default, InitSpace)]
+pub struct RoundUpRule {
+    /// Round-up increment in lamports (0 = disabled)
+    pub increment: u64,
+    /// Vault that receives the round-ups
+    pub savings_vault: Pubkey,
+}
+
+impl RoundUpRule {
+    /// Lamports needed to round `amount` up to the next `increment`.
+    pub fn round_up(&self, amount: u64) -> u64 {
+        if self.increment == 0 {
+            return 0;
+        }
+        (self.increment - amount % self.increment) % self.increment
+    }
+}
+
+/// Skim the round-up on an outgoing `amount` from `vault` into its
+/// savings vault, returning the lamports moved.
+///
+/// Capped at the spendable balance left after the transfer, so a
+/// round-up never blocks the payment itself.
+pub fn skim_round_up<'info>(
+    vault: &mut Account<'info, VaultState>,
+    savings_vault: Option<&mut Account<'info, VaultState>>,
+    amount: u64,
+) -> Result<u64> {
+    let skim = vault.round_up.round_up(amount).min(vault.spendable());
+    if skim == 0 {
+        return Ok(0);
+    }
+
+    let savings_vault = savings_vault.ok_or(ObscuraError::InvalidSavingsVault)?;
+    move_to_vault(vault, savings_vault, skim)?;
+    Ok(skim)
+}
+
+/// Percentage split of a vault's outgoing transfers or deposits into a
+/// second vault.
+#[derive(AnchorSerialize, AnchorDeserialize, Clone, Copy, Default, InitSpace)]
+pub struct SplitRule {
+    /// Share in basis points (0 = disabled)
+    pub bps: u16,
+    /// Vault that receives the share
+    pub split_vault: Pubkey,
+    /// Split deposits instead of outgoing transfers
+    pub inbound: bool,
+}
+
+impl SplitRule {
+    /// Share of a `direction` flow of `amount` owed to the split vault.
+    pub fn share(&self, direction: FlowDirection, amount: u64) -> u64 {
+        if self.inbound != (direction == FlowDirection::Inflow) {
+            return 0;
+        }
+        (amount as u128 * self.bps as u128 / BPS_DENOMINATOR as u128) as u64
+    }
+}
+
+/// Move the split-rule share of a `direction` flow of `amount` from
+/// `vault` into its split vault, returning the lamports moved.
+pub fn apply_split<'info>(
+    vault: &mut Account<'info, VaultState>,
+    split_vault: Option<&mut Account<'info, VaultState>>,
+    direction: FlowDirection,
+    amount: u64,
+) -> Result<u64> {
+    let share = vault.split.share(direction, amount);
+    if share == 0 {
+        return Ok(0);
+    }
+
+    let split_vault = split_vault.ok_or(ObscuraError::InvalidSplitVault)?;
+    move_to_vault(vault, split_vault, share)?;
+    Ok(share)
+}
+
+/// Move `amount` of tracked balance and its lamports from `from` to `to`.
+/// Both vaults must be on the same layer (both delegated or both on L1).
+fn move_to_vault(
+    from: &mut Account<VaultState>,
+    to: &mut Account<VaultState>,
+    amount: u64,
+) -> Result<()> {
+    if from.is_delegated {
+        require!(to.is_delegated, ObscuraError::NotDelegated);
+    } else {
+        require!(!to.is_delegated, ObscuraError::AccountDelegated);
+    }
+    from.debit(amount)?;
+    to.credit(amount)?;
+
+    **from.to_account_info().try_borrow_mut_lamports()? -= amount;
+    **to.to_account_info().try_borrow_mut_lamports()? += amount;
+    Ok(())
+}
+
+/// ER operator prices, published on its `ValidatorInfo` (all zero =
+/// free).
+#[derive(AnchorSerialize, AnchorDeserialize, Clone, Copy, Default, InitSpace)]
+pub struct RateCard {
+    /// Lamports per commit of a delegated vault
+    pub commit_fee: u64,
+    /// Lamports per `RATE_CARD_PERIOD` a vault stays delegated, charged
+    /// pro rata
+    pub monthly_delegation_fee: u64,
+    /// Account `settle_operator_fees` pays
+    pub payout: Pubkey,
+}
+
+/// A vault's fees under the `RateCard` it accepted when delegating.
+///
+/// Fees accrue in the ER on each commit and are held back from the
+/// spendable balance until `settle_operator_fees` pays them on L1.
+#[derive(AnchorSerialize, AnchorDeserialize, Clone, Copy, Default, InitSpace)]
+pub struct OperatorFees {
+    /// Account the fees are owed to
+    pub payout: Pubkey,
+    /// Accepted lamports per commit
+    pub commit_fee: u64,
+    /// Accepted lamports per `RATE_CARD_PERIOD` delegated
+    pub monthly_delegation_fee: u64,
+    /// Delegation fee has been accrued up to this timestamp
+    pub accrued_until: i64,
+    /// Lamports owed and not yet settled
+    pub owed: u64,
+}
+
+impl OperatorFees {
+    /// Accept `rate_card` for a delegation starting at `unix_timestamp`.
+    pub fn start(&mut self, rate_card: &RateCard, unix_timestamp: i64) {
+        self.payout = rate_card.payout;
+        self.commit_fee = rate_card.commit_fee;
+        self.monthly_delegation_fee = rate_card.monthly_delegation_fee;
+        self.accrued_until = unix_timestamp;
+    }
+
+    /// Accrue the delegation fee up to `unix_timestamp` plus `commits`
+    /// commit fees.
+    pub fn accrue(&mut self, unix_timestamp: i64, commits: u64) -> Result<()> {
+        let elapsed = unix_timestamp.saturating_sub(self.accrued_until).max(0) as u128;
+        let delegation_fee =
+            self.monthly_delegation_fee as u128 * elapsed / RATE_CARD_PERIOD as u128;
+        let fees = (self.commit_fee as u128 * commits as u128)
+            .checked_add(delegation_fee)
+            .and_then(|fees| u64::try_from(fees).ok())
+            .ok_or(ObscuraError::MathOverflow)?;
+        self.owed = self
+            .owed
+            .checked_add(fees)
+            .ok_or(ObscuraError::MathOverflow)?;
+        self.accrued_until = self.accrued_until.max(unix_timestamp);
+        Ok(())
+    }
+
+    /// Accrue the final commit and stop charging at undelegation.  `owed`
+    /// and `payout` stay until settled.
+    pub fn stop(&mut self, unix_timestamp: i64) -> Result<()> {
+        self.accrue(unix_timestamp, 1)?;
+        self.clear_rates();
+        Ok(())
+    }
+
+    /// Stop charging without accruing anything further, e.g. when the
+    /// vault is force-settled off a defunct validator.
+    pub fn clear_rates(&mut self) {
+        self.commit_fee = 0;
+        self.monthly_delegation_fee = 0;
+    }
+}
+
+/// Per-vault protocol fee accrual for invoiced billing.
+///
+/// Keeps the open calendar month plus the oldest finished month awaiting
+/// close-out, so transfers never block on the operator's close-out run.
+#[derive(AnchorSerialize, AnchorDeserialize, Clone, Copy, Default, InitSpace)]
+pub struct FeeLedger {
+    /// Invoiced fee rate in basis points (0 = not on invoiced billing)
+    pub fee_bps: u16,
+    /// Month (see `month_index`) currently accruing
+    pub open_period: u32,
+    /// Fees accrued in `open_period`
+    pub open_fees: u64,
+    /// Fee-bearing operations in `open_period`
+    pub open_ops: u32,
+    /// Finished month awaiting `close_fee_statement`
+    pub pending_period: u32,
+    /// Fees accrued in `pending_period` (plus any missed months)
+    pub pending_fees: u64,
+    /// Fee-bearing operations in `pending_period`
+    pub pending_ops: u32,
+}
+
+impl FeeLedger {
+    /// Record the fee owed on an outflow of `amount` at `unix_timestamp`.
+    pub fn accrue(&mut self, unix_timestamp: i64, amount: u64) -> Result<u64> {
+        if self.fee_bps == 0 {
+            return Ok(0);
+        }
+        self.roll(unix_timestamp);
+
+        let fee = (amount as u128 * self.fee_bps as u128 / BPS_DENOMINATOR as u128) as u64;
+        self.open_fees = self
+            .open_fees
+            .checked_add(fee)
+            .ok_or(ObscuraError::MathOverflow)?;
+        self.open_ops = self.open_ops.saturating_add(1);
+        Ok(fee)
+    }
+
+    /// Move the open month to pending once the calendar month has changed.
+    pub fn roll(&mut self, unix_timestamp: i64) {
+        let month = month_index(unix_timestamp);
+        if month == self.open_period {
+            return;
+        }
+        if self.open_ops > 0 {
+            if self.pending_ops == 0 {
+                self.pending_period = self.open_period;
+            }
+            self.pending_fees = self.pending_fees.saturating_add(self.open_fees);
+            self.pending_ops = self.pending_ops.saturating_add(self.open_ops);
+        }
+        self.open_period = month;
+        self.open_fees = 0;
+        self.open_ops = 0;
+    }
+}
+
+/// Cumulative outflow cap per Solana epoch.
+///
+/// Outflow is tracked even without a cap, so a newly set cap counts what
+/// already left during the current epoch.
+#[derive(AnchorSerialize, AnchorDeserialize, Clone, Copy, Default, InitSpace)]
+pub struct SpendingLimit {
+    /// Most lamports that may leave per epoch (0 = no limit)
+    pub amount_per_epoch: u64,
+    /// Epoch being accumulated
+    pub epoch: u64,
+    /// Outflow so far during `epoch`
+    pub spent: u64,
+    /// Raised (or removed, 0) limit waiting for `pending_at`
+    pub pending_amount: u64,
+    /// When `pending_amount` takes effect (0 = nothing pending)
+    pub pending_at: i64,
+}
+
+impl SpendingLimit {
+    /// Set the per-epoch cap, returning when it takes effect: at once
+    /// when lowered, after `SPENDING_LIMIT_RAISE_DELAY` when raised or
+    /// removed.
+    pub fn set(&mut self, amount_per_epoch: u64, unix_timestamp: i64) -> i64 {
+        self.apply_pending(unix_timestamp);
+        if amount_per_epoch != 0
+            && (self.amount_per_epoch == 0 || amount_per_epoch <= self.amount_per_epoch)
+        {
+            self.amount_per_epoch = amount_per_epoch;
+            self.pending_at = 0;
+            unix_timestamp
+        } else {
+            self.pending_amount = amount_per_epoch;
+            self.pending_at = unix_timestamp + SPENDING_LIMIT_RAISE_DELAY;
+            self.pending_at
+        }
+    }
+
+    /// The cap in force once any pending raise has matured.
+    pub fn target(&self) -> u64 {
+        if self.pending_at != 0 {
+            self.pending_amount
+        } else {
+            self.amount_per_epoch
+        }
+    }
+
+    /// Whether this cap, once pending raises mature, is no looser than
+    /// `other`'s.
+    pub fn at_least_as_strict(&self, other: &Self) -> bool {
+        other.target() == 0 || (self.target() != 0 && self.target() <= other.target())
+    }
+
+    /// Adopt a pending raise once its delay has passed.
+    pub fn apply_pending(&mut self, unix_timestamp: i64) {
+        if self.pending_at != 0 && unix_timestamp >= self.pending_at {
+            self.amount_per_epoch = self.pending_amount;
+            self.pending_amount = 0;
+            self.pending_at = 0;
+        }
+    }
+
+    /// Record an outflow of `amount` in `epoch`, failing past the limit.
+    pub fn record(&mut self, epoch: u64, unix_timestamp: i64, amount: u64) -> Result<()> {
+        self.apply_pending(unix_timestamp);
+        if epoch != self.epoch {
+            self.epoch = epoch;
+            self.spent = 0;
+        }
+        let spent = self
+            .spent
+            .checked_add(amount)
+            .ok_or(ObscuraError::MathOverflow)?;
+        require!(
+            self.amount_per_epoch == 0 || spent <= self.amount_per_epoch,
+            ObscuraError::SpendingLimitExceeded
+        );
+        self.spent = spent;
+        Ok(())
+    }
+}
+
+/// Withdrawal cap over a rolling 24-hour window.
+///
+/// The window opens with the first withdrawal after the previous one
+/// ended and lasts `WITHDRAWAL_WINDOW`.
+#[derive(AnchorSerialize, AnchorDeserialize, Clone, Copy, Default, InitSpace)]
+pub struct WithdrawalLimit {
+    /// Most lamports `withdraw` may take per window (0 = no limit)
+    pub daily_cap: u64,
+    /// Start of the current window
+    pub window_start: i64,
+    /// Withdrawn so far in the current window
+    pub withdrawn_today: u64,
+    /// When a requested override takes effect (0 = none requested)
+    pub override_at: i64,
+    /// Withdrawals over this must be queued as a `PendingWithdrawal`
+    /// (0 = no timelock)
+    pub timelock_threshold: u64,
+}
+
+impl WithdrawalLimit {
+    /// Set the cap and timelock threshold.  Lowering either applies at
+    /// once; raising or removing either needs a matured override, which
+    /// it consumes.
+    pub fn configure(
+        &mut self,
+        daily_cap: u64,
+        timelock_threshold: u64,
+        unix_timestamp: i64,
+    ) -> Result<()> {
+        let loosens = |current: u64, new: u64| current != 0 && (new == 0 || new > current);
+        if loosens(self.daily_cap, daily_cap)
+            || loosens(self.timelock_threshold, timelock_threshold)
+        {
+            require!(
+                self.override_active(unix_timestamp),
+                ObscuraError::WithdrawalOverrideRequired
+            );
+            self.override_at = 0;
+        }
+        self.daily_cap = daily_cap;
+        self.timelock_threshold = timelock_threshold;
+        Ok(())
+    }
+
+    /// Whether this cap and threshold are each no looser than `other`'s.
+    pub fn at_least_as_strict(&self, other: &Self) -> bool {
+        let stricter = |mine: u64, theirs: u64| theirs == 0 || (mine != 0 && mine <= theirs);
+        stricter(self.daily_cap, other.daily_cap)
+            && stricter(self.timelock_threshold, other.timelock_threshold)
+    }
+
+    /// Whether withdrawing `amount` must go through a `PendingWithdrawal`.
+    pub fn requires_timelock(&self, amount: u64) -> bool {
+        self.timelock_threshold != 0 && amount > self.timelock_threshold
+    }
+
+    /// Whether a requested override has matured and not yet lapsed.
+    pub fn override_active(&self, unix_timestamp: i64) -> bool {
+        self.override_at != 0
+            && unix_timestamp >= self.override_at
+            && unix_timestamp < self.override_at + WITHDRAWAL_OVERRIDE_DELAY
+    }
+
+    /// Record a withdrawal of `amount`, failing past the cap unless an
+    /// override is active.
+    pub fn record(&mut self, unix_timestamp: i64, amount: u64) -> Result<()> {
+        if unix_timestamp >= self.window_start + WITHDRAWAL_WINDOW {
+            self.window_start = unix_timestamp;
+            self.withdrawn_today = 0;
+        }
+        let withdrawn = self
+            .withdrawn_today
+            .checked_add(amount)
+            .ok_or(ObscuraError::MathOverflow)?;
+        require!(
+            self.daily_cap == 0
+                || withdrawn <= self.daily_cap
+                || self.override_active(unix_timestamp),
+            ObscuraError::WithdrawalLimitExceeded
+        );
+        self.withdrawn_today = withdrawn;
+        Ok(())
+    }
+}
+
+/// A withdrawal over the vault's timelock threshold, executable by the
+/// owner from `executable_at` and cancellable until then.
+#[account]
+#[derive(InitSpace)]
+pub struct PendingWithdrawal {
+    /// Vault the withdrawal is from
+    pub vault: Pubkey,
+    /// Lamports to withdraw
+    pub amount: u64,
+    /// When the withdrawal was queued
+    pub requested_at: i64,
+    /// Earliest time it can be executed
+    pub executable_at: i64,
+}
+
+/// Destinations a vault in allowlist mode may pay.
+#[account]
+#[derive(InitSpace)]
+pub struct RecipientAllowlist {
+    /// The vault the allowlist applies to
+    pub vault: Pubkey,
+    /// Allowed recipients
+    #[max_len(MAX_ALLOWED_RECIPIENTS)]
+    pub recipients: Vec<Pubkey>,
+}
+
+/// Recipients an integrator blocks for every vault created through it,
+/// and its optional compliance hook.
+#[account]
+#[derive(InitSpace)]
+pub struct Denylist {
+    /// The integrator maintaining the list
+    pub integrator: Pubkey,
+    /// Program whose `check_transfer` vets outbound transfers (default =
+    /// none)
+    pub compliance_program: Pubkey,
+    /// Denied recipients
+    #[max_len(MAX_DENIED_RECIPIENTS)]
+    pub recipients: Vec<Pubkey>,
+}
+
+/// Rolling outflow velocity and the step-up policy applied when a day's
+/// outflow exceeds the vault's norm.
+///
+/// The norm is an EWMA (alpha 1/8) of daily outflow, updated inside the
+/// ER as transfers happen.  Once a day's outflow passes
+/// `factor_pct`% of the norm, further transfers need either `cosigner`'s
+/// signature or, when no cosigner is set, a matured override from
+/// `request_velocity_override`.
+#[derive(AnchorSerialize, AnchorDeserialize, Clone, Copy, Default, InitSpace)]
+pub struct VelocityPolicy {
+    /// Allowed outflow as a percentage of the norm (0 = no policy)
+    pub factor_pct: u16,
+    /// Required cosigner for step-up (default = use the override delay)
+    pub cosigner: Pubkey,
+    /// EWMA of daily outflow in lamports
+    pub avg_daily_outflow: u64,
+    /// UTC day (`unix_timestamp / 86_400`) being accumulated
+    pub day: u32,
+    /// Outflow so far during `day`
+    pub day_outflow: u64,
+    /// When a requested override takes effect (0 = none requested)
+    pub override_at: i64,
+}
+
+impl VelocityPolicy {
+    /// Set the step-up factor and cosigner, clearing any requested
+    /// override.
+    pub fn configure(&mut self, factor_pct: u16, cosigner: Option<Pubkey>) -> Result<()> {
+        require!(
+            factor_pct == 0 || factor_pct >= 100,
+            ObscuraError::InvalidVelocityPolicy
+        );
+        self.factor_pct = factor_pct;
+        self.cosigner = cosigner.unwrap_or_default();
+        self.override_at = 0;
+        Ok(())
+    }
+
+    /// Fold an outflow of `amount` into the metric and enforce the policy.
+    pub fn observe(
+        &mut self,
+        unix_timestamp: i64,
+        amount: u64,
+        cosigner: Option<Pubkey>,
+    ) -> Result<()> {
+        let day = (unix_timestamp / 86_400) as u32;
+        if day != self.day {
+            if self.day != 0 {
+                // Close the previous day, then decay over any idle days
+                // (bounded: after ~30 idle days the norm is effectively 0).
+                self.avg_daily_outflow =
+                    self.avg_daily_outflow - self.avg_daily_outflow / 8 + self.day_outflow / 8;
+                let idle = day.saturating_sub(self.day + 1).min(30);
+                for _ in 0..idle {
+                    self.avg_daily_outflow -= self.avg_daily_outflow / 8;
+                }
+            }
+            self.day = day;
+            self.day_outflow = 0;
+        }
+        self.day_outflow = self.day_outflow.saturating_add(amount);
+
+        // No norm yet (new vault) means nothing to compare against.
+        if self.factor_pct == 0 || self.avg_daily_outflow == 0 {
+            return Ok(());
+        }
+        let limit = self.avg_daily_outflow as u128 * self.factor_pct as u128 / 100;
+        if (self.day_outflow as u128) <= limit {
+            return Ok(());
+        }
+
+        if self.cosigner != Pubkey::default() {
+            require!(
+                cosigner == Some(self.cosigner),
+                ObscuraError::VelocityStepUpRequired
+            );
+        } else {
+            require!(
+                self.override_at != 0
+                    && unix_timestamp >= self.override_at
+                    && unix_timestamp < self.override_at + VELOCITY_OVERRIDE_DELAY,
+                ObscuraError::VelocityStepUpRequired
+            );
+        }
+        Ok(())
+    }
+}
+
+/// Escrowed transfer released once its condition holds.
+#[account]
+#[derive(InitSpace)]
+pub struct ConditionalTransfer {
+    /// The funding vault
+    pub vault: Pubkey,
+    /// Vault owner at creation; receives the rent back
+    pub owner: Pubkey,
+    /// Vault-scoped transfer identifier
+    pub transfer_id: u64,
+    /// Escrowed lamports (on top of rent)
+    pub amount: u64,
+    /// Payee
+    pub recipient: Pubkey,
+    /// Release condition
+    pub condition: TransferCondition,
+    /// Unix timestamp of creation
+    pub created_at: i64,
+}
+
+impl ConditionalTransfer {
+    /// Whether `key` is one of the two parties (vault owner or recipient).
+    pub fn is_party(&self, key: &Pubkey) -> bool {
+        *key == self.owner || *key == self.recipient
+    }
+
+    /// Reference hash carried by this escrow's receipt cNFTs.
+    pub fn reference(&self) -> [u8; 32] {
+        hashv(&[
+            self.vault.as_ref(),
+            &self.transfer_id.to_le_bytes(),
+            &self.created_at.to_le_bytes(),
+        ])
+        .to_bytes()
+    }
+}
+
+/// Time-locked transfer out of a vault, escrowed until a crank executes
+/// it after `execute_after`.
+#[account]
+#[derive(InitSpace)]
+pub struct ScheduledTransfer {
+    /// The funding vault
+    pub vault: Pubkey,
+    /// Vault owner at creation; receives the rent back
+    pub owner: Pubkey,
+    /// Vault-scoped transfer identifier
+    pub transfer_id: u64,
+    /// Payee
+    pub recipient: Pubkey,
+    /// Escrowed lamports for the recipient (on top of rent and tip)
+    pub amount: u64,
+    /// Escrowed lamports for the cranker
+    pub tip: u64,
+    /// Unix timestamp from which the transfer can be executed
+    pub execute_after: i64,
+    /// Unix timestamp of creation
+    pub created_at: i64,
+}
+
+/// Per-caller crank history, used to diminish the tips of a caller that
+/// cranks in rapid succession.
+#[account]
+#[derive(InitSpace)]
+pub struct CrankerStats {
+    /// The crank caller
+    pub cranker: Pubkey,
+    /// Unix timestamp of the caller's last tipped crank
+    pub last_crank_at: i64,
+    /// Consecutive cranks each within `CRANK_COOLDOWN` of the previous
+    pub streak: u8,
+}
+
+impl CrankerStats {
+    /// Record a crank at `now` and return the share of `tip` it earns:
+    /// the full tip once the cooldown has passed, halved for every
+    /// consecutive crank inside it.
+    pub fn record(&mut self, now: i64, tip: u64) -> u64 {
+        if self.last_crank_at != 0 && now - self.last_crank_at < CRANK_COOLDOWN {
+            self.streak = self.streak.saturating_add(1);
+        } else {
+            self.streak = 0;
+        }
+        self.last_crank_at = now;
+        tip.checked_shr(u32::from(self.streak)).unwrap_or(0)
+    }
+}
+
+/// Recurring pull payment from a vault to a recipient.
+#[account]
+#[derive(InitSpace)]
+pub struct Subscription {
+    /// The paying vault
+    pub vault: Pubkey,
+    /// Vault owner at creation; receives the rent back
+    pub owner: Pubkey,
+    /// Vault-scoped subscription identifier
+    pub subscription_id: u64,
+    /// Payee
+    pub recipient: Pubkey,
+    /// Lamports per period
+    pub amount: u64,
+    /// Seconds between payments
+    pub interval: i64,
+    /// Unix timestamp from which the next payment can be processed
+    pub next_due: i64,
+    /// Payments made so far
+    pub payments: u64,
+    /// Unix timestamp of creation
+    pub created_at: i64,
+}
+
+/// Per-second payment stream from one vault to another.  Holds the
+/// unwithdrawn part of the streamed amount.
+#[account]
+#[derive(InitSpace)]
+pub struct Stream {
+    /// The paying vault
+    pub vault: Pubkey,
+    /// Paying vault owner at creation; receives the rent back
+    pub owner: Pubkey,
+    /// Vault-scoped stream identifier
+    pub stream_id: u64,
+    /// The vault the stream pays into
+    pub recipient_vault: Pubkey,
+    /// Lamports vesting per second
+    pub rate_per_second: u64,
+    /// Unix timestamp vesting starts at
+    pub start: i64,
+    /// Unix timestamp vesting stops at
+    pub end: i64,
+    /// Lamports withdrawn so far
+    pub withdrawn: u64,
+    /// Unix timestamp of creation
+    pub created_at: i64,
+}
+
+impl Stream {
+    /// Lamports the stream pays out in full (`None` on overflow).
+    pub fn total(&self) -> Option<u64> {
+        let duration = u64::try_from(self.end.checked_sub(self.start)?).ok()?;
+        self.rate_per_second.checked_mul(duration)
+    }
+
+    /// Lamports vested by `now`.
+    pub fn vested(&self, now: i64) -> u64 {
+        let elapsed = now.clamp(self.start, self.end.max(self.start)) - self.start;
+        self.rate_per_second.saturating_mul(elapsed as u64)
+    }
+
+    /// Lamports vested by `now` and not yet withdrawn.
+    pub fn withdrawable(&self, now: i64) -> u64 {
+        self.vested(now).saturating_sub(self.withdrawn)
+    }
+}
+
+/// Guardians able to rotate a vault's owner key, N-of-M.
+#[account]
+#[derive(InitSpace)]
+pub struct GuardianSet {
+    /// The protected vault
+    pub vault: Pubkey,
+    /// Guardian keys; an approval is one bit per index
+    #[max_len(MAX_GUARDIANS)]
+    pub guardians: Vec<Pubkey>,
+    /// Guardians needed to approve a recovery
+    pub threshold: u8,
+    /// Pending recovery (`new_owner` default = none)
+    pub recovery: RecoveryRequest,
+}
+
+impl GuardianSet {
+    /// Set the threshold after a guardian change, cancelling any pending
+    /// recovery since its approvals index the old list.
+    pub fn set_threshold(&mut self, threshold: u8) -> Result<()> {
+        require!(
+            threshold > 0 && threshold as usize <= self.guardians.len(),
+            ObscuraError::InvalidGuardianSet
+        );
+        self.threshold = threshold;
+        self.recovery = RecoveryRequest::default();
+        Ok(())
+    }
+
+    /// Index of `key` in the guardian list, failing if it is not one.
+    pub fn require_guardian(&self, key: &Pubkey) -> Result<usize> {
+        self.guardians
+            .iter()
+            .position(|guardian| guardian == key)
+            .ok_or_else(|| error!(ObscuraError::Unauthorized))
+    }
+
+    /// Record guardian `index`'s approval, starting the challenge period
+    /// once the threshold is reached.
+    pub fn approve(&mut self, index: usize, unix_timestamp: i64) {
+        let recovery = &mut self.recovery;
+        recovery.approvals |= 1 << index;
+        if recovery.ready_at == 0 && recovery.approvals.count_ones() >= self.threshold as u32 {
+            recovery.ready_at = unix_timestamp;
+        }
+    }
+}
+
+/// Owner rotation proposed by the guardians.
+#[derive(AnchorSerialize, AnchorDeserialize, Clone, Copy, Default, InitSpace)]
+pub struct RecoveryRequest {
+    /// Proposed owner key (default = no recovery pending)
+    pub new_owner: Pubkey,
+    /// Approval bitmap over `GuardianSet::guardians` indices
+    pub approvals: u8,
+    /// When the recovery was initiated
+    pub initiated_at: i64,
+    /// When the threshold was reached (0 = not yet)
+    pub ready_at: i64,
+}
+
+/// M-of-N signer set owning a vault (`VaultState::owner` is this PDA).
+#[account]
+#[derive(InitSpace)]
+pub struct VaultOwners {
+    /// The owned vault
+    pub vault: Pubkey,
+    /// Signers; an approval is one bit per index
+    #[max_len(MAX_VAULT_OWNERS)]
+    pub signers: Vec<Pubkey>,
+    /// Approvals needed to execute an action
+    pub threshold: u8,
+    /// Actions proposed so far (next action id)
+    pub action_count: u64,
+    /// Bumped whenever the signer set changes, voiding older actions
+    pub owners_epoch: u32,
+}
+
+impl VaultOwners {
+    /// Validate and install a new signer set.
+    pub fn set_signers(&mut self, signers: Vec<Pubkey>, threshold: u8) -> Result<()> {
+        let unique = signers
+            .iter()
+            .enumerate()
+            .all(|(i, key)| *key != Pubkey::default() && !signers[..i].contains(key));
+        require!(
+            unique
+                && signers.len() <= MAX_VAULT_OWNERS
+                && threshold > 0
+                && threshold as usize <= signers.len(),
+            ObscuraError::InvalidMultisig
+        );
+        self.signers = signers;
+        self.threshold = threshold;
+        Ok(())
+    }
+
+    /// Index of `key` in the signer set, failing if it is not a signer.
+    pub fn require_signer(&self, key: &Pubkey) -> Result<usize> {
+        self.signers
+            .iter()
+            .position(|signer| signer == key)
+            .ok_or_else(|| error!(ObscuraError::Unauthorized))
+    }
+}
+
+/// Pending instruction awaiting `VaultOwners` approvals.
+#[account]
+#[derive(InitSpace)]
+pub struct MultisigAction {
+    /// The signer set deciding on it
+    pub vault_owners: Pubkey,
+    /// Sequential id within the signer set
+    pub action_id: u64,
+    /// Who proposed it (receives the rent back)
+    pub proposer: Pubkey,
+    /// `VaultOwners::owners_epoch` at proposal time
+    pub owners_epoch: u32,
+    /// Instruction data for this program
+    #[max_len(MAX_ACTION_DATA_LEN)]
+    pub data: Vec<u8>,
+    /// Instruction accounts, in order
+    #[max_len(MAX_ACTION_ACCOUNTS)]
+    pub accounts: Vec<ActionAccount>,
+    /// Approval bitmap over `VaultOwners::signers` indices
+    pub approvals: u16,
+    /// When the action was proposed
+    pub created_at: i64,
+}
+
+/// Shared expense ledger between member vaults, settled in SOL.
+#[account]
+#[derive(InitSpace)]
+pub struct Group {
+    /// Owner who created the group (part of the PDA seeds)
+    pub creator: Pubkey,
+    /// Creator-chosen id
+    pub group_id: u64,
+    /// Member vaults; the creator's vault first
+    #[max_len(MAX_GROUP_MEMBERS)]
+    pub members: Vec<GroupMember>,
+    /// Expenses logged so far
+    pub expense_count: u32,
+    /// When the group was created
+    pub created_at: i64,
+    /// Last `settle_group` (0 = never)
+    pub settled_at: i64,
+}
+
+/// One vault's standing in a `Group`.
+#[derive(AnchorSerialize, AnchorDeserialize, Clone, InitSpace)]
+pub struct GroupMember {
+    /// The member vault
+    pub vault: Pubkey,
+    /// Whether its owner has joined
+    pub joined: bool,
+    /// Net lamports owed to the vault (negative = it owes)
+    pub net: i64,
+}
+
+impl Group {
+    /// Index of `vault` among the members.
+    pub fn member_index(&self, vault: &Pubkey) -> Result<usize> {
+        self.members
+            .iter()
+            .position(|m| m.vault == *vault)
+            .ok_or_else(|| error!(ObscuraError::InvalidGroup))
+    }
+
+    /// Record `amount` paid by member `payer` and owed equally by the
+    /// members in the `participants` bitmap.
+    pub fn log_expense(&mut self, payer: usize, amount: u64, participants: u8) -> Result<()> {
+        let count = participants.count_ones() as u64;
+        require!(
+            amount > 0 && count > 0 && (participants as usize) >> self.members.len() == 0,
+            ObscuraError::InvalidGroup
+        );
+        require!(
+            self.members.iter().all(|m| m.joined),
+            ObscuraError::GroupNotJoined
+        );
+
+        let amount_signed = i64::try_from(amount).map_err(|_| ObscuraError::MathOverflow)?;
+        let share = amount / count;
+        let mut remainder = amount % count;
+        let mut nets: Vec<i64> = self.members.iter().map(|m| m.net).collect();
+        nets[payer] = nets[payer]
+            .checked_add(amount_signed)
+            .ok_or(ObscuraError::MathOverflow)?;
+        for (index, net) in nets.iter_mut().enumerate() {
+            if participants & (1 << index) == 0 {
+                continue;
+            }
+            let owed = (share + std::mem::take(&mut remainder)) as i64;
+            *net = net.checked_sub(owed).ok_or(ObscuraError::MathOverflow)?;
+        }
+
+        for (member, net) in self.members.iter_mut().zip(nets) {
+            member.net = net;
+        }
+        self.expense_count = self.expense_count.saturating_add(1);
+        Ok(())
+    }
+
+    /// Transfers `(debtor, creditor, amount)` that clear every balance.
+    ///
+    /// Greedily pays the largest creditor from the largest debtor; each
+    /// transfer clears at least one member, so there are at most
+    /// `members - 1`.
+    pub fn settlement(&self) -> Vec<(usize, usize, u64)> {
+        let mut nets: Vec<i64> = self.members.iter().map(|m| m.net).collect();
+        let mut transfers = Vec::new();
+        loop {
+            let (debtor, debt) = extreme(&nets, |a, b| a < b);
+            let (creditor, credit) = extreme(&nets, |a, b| a > b);
+            if debt >= 0 || credit <= 0 {
+                return transfers;
+            }
+            let amount = credit.min(-debt);
+            nets[debtor] += amount;
+            nets[creditor] -= amount;
+            transfers.push((debtor, creditor, amount as u64));
+        }
+    }
+}
+
+/// Index and value of the first element of `values` that no other beats
+/// under `better`.
+fn extreme(values: &[i64], better: impl Fn(i64, i64) -> bool) -> (usize, i64) {
+    values
+        .iter()
+        .copied()
+        .enumerate()
+        .fold((0, values[0]), |best, (i, v)| {
+            if better(v, best.1) {
+                (i, v)
+            } else {
+                best
+            }
+        })
+}
+
+/// A merchant's point-of-sale shift: running totals of the payments the
+/// till recorded.
+///
+/// Payment references are folded into `references_digest` rather than
+/// stored, so a session has a fixed size however busy the shift; the
+/// till keeps the list and reconciliation replays it against the digest.
+#[account]
+#[derive(InitSpace)]
+pub struct PosSession {
+    /// The merchant's vault
+    pub vault: Pubkey,
+    /// Merchant-chosen id (part of the PDA seeds)
+    pub session_id: u64,
+    /// Key allowed to record payments
+    pub operator: Pubkey,
+    /// When the session was opened
+    pub opened_at: i64,
+    /// After this, no further payments are recorded
+    pub expires_at: i64,
+    /// Payments recorded
+    pub payment_count: u32,
+    /// Lamports recorded
+    pub total: u64,
+    /// `sha256(prev || reference || amount_le)` over the recorded
+    /// payments, starting from zero
+    pub references_digest: [u8; 32],
+}
+
+impl PosSession {
+    /// Fold a payment into the totals and the reference digest.
+    pub fn record(&mut self, reference: [u8; 32], amount: u64, now: i64) -> Result<()> {
+        require!(now < self.expires_at, ObscuraError::PosSessionExpired);
+        require!(amount > 0, ObscuraError::InvalidPosSession);
+        self.total = self
+            .total
+            .checked_add(amount)
+            .ok_or(ObscuraError::MathOverflow)?;
+        self.payment_count = self
+            .payment_count
+            .checked_add(1)
+            .ok_or(ObscuraError::MathOverflow)?;
+        self.references_digest =
+            hashv(&[&self.references_digest, &reference, &amount.to_le_bytes()]).to_bytes();
+        Ok(())
+    }
+}
+
+/// End-of-shift summary of a `PosSession`, emitted by
+/// `close_pos_session` in a transaction signed by the merchant.
+#[event]
+pub struct PosSessionClosed {
+    /// The merchant's vault
+    pub vault: Pubkey,
+    /// The session's id
+    pub session_id: u64,
+    /// The till key that recorded payments
+    pub operator: Pubkey,
+    /// When the session was opened
+    pub opened_at: i64,
+    /// When it was closed
+    pub closed_at: i64,
+    /// Payments recorded
+    pub payment_count: u32,
+    /// Lamports recorded
+    pub total: u64,
+    /// Final reference digest (see `PosSession::references_digest`)
+    pub references_digest: [u8; 32],
+}
+
+/// One account of a `MultisigAction`'s instruction.
+#[derive(AnchorSerialize, AnchorDeserialize, Clone, InitSpace)]
+pub struct ActionAccount {
+    /// The account
+    pub pubkey: Pubkey,
+    /// Whether it signs (the `VaultOwners` PDA always does)
+    pub is_signer: bool,
+    /// Whether it is writable
+    pub is_writable: bool,
+}
+
+/// Dispute evidence for an arbitrated `ConditionalTransfer`.
+///
+/// Blobs are encrypted client-side to the arbiter's key; the program only
+/// checks who may append them.
+#[account]
+#[derive(InitSpace)]
+pub struct EvidenceLocker {
+    /// The disputed conditional transfer
+    pub conditional_transfer: Pubkey,
+    /// Arbiter (the transfer's attestor); closes the locker on resolution
+    pub arbiter: Pubkey,
+    /// Party that opened the locker and paid its rent
+    pub opened_by: Pubkey,
+    /// Evidence, in submission order
+    #[max_len(MAX_EVIDENCE_ENTRIES)]
+    pub entries: Vec<EvidenceEntry>,
+}
+
+/// Lets the parties of one escrow rate each other once it completes.
+#[account]
+#[derive(InitSpace)]
+pub struct RatingTicket {
+    /// The escrow (`ConditionalTransfer`) rated
+    pub conditional_transfer: Pubkey,
+    /// The escrow's reference, as on its receipts
+    pub reference: [u8; 32],
+    /// Vault owner at the escrow's creation
+    pub owner: Pubkey,
+    /// Payee
+    pub recipient: Pubkey,
+    /// Party that opened the ticket and paid its rent
+    pub opened_by: Pubkey,
+    /// Unix timestamp the escrow was executed (0 = not yet)
+    pub completed_at: i64,
+    /// Whether the owner has rated the recipient
+    pub owner_rated: bool,
+    /// Whether the recipient has rated the owner
+    pub recipient_rated: bool,
+}
+
+/// A counterparty rating (see `attestation`).
+#[derive(AnchorSerialize, AnchorDeserialize, Clone, Copy)]
+pub enum Rating {
+    /// Score from 1 to `MAX_RATING_SCORE`, readable by anyone
+    Public { score: u8 },
+    /// SHA-256 of the score byte and a 32-byte salt, opened off-chain to
+    /// keys holding `SCOPE_REPUTATION` on the rater's vault
+    Sealed { commitment: [u8; 32] },
+}
+
+/// One encrypted evidence blob in an `EvidenceLocker`.
+#[derive(AnchorSerialize, AnchorDeserialize, Clone, InitSpace)]
+pub struct EvidenceEntry {
+    /// Submitting party
+    pub author: Pubkey,
+    /// Unix timestamp of submission
+    pub submitted_at: i64,
+    /// Evidence encrypted to the arbiter's key
+    #[max_len(MAX_EVIDENCE_LEN)]
+    pub blob: Vec<u8>,
+}
+
+/// Release condition of a `ConditionalTransfer`.
+#[derive(AnchorSerialize, AnchorDeserialize, Clone, Copy, InitSpace)]
+pub enum TransferCondition {
+    /// Pyth price for `feed_id` strictly above `price` (in the feed's
+    /// exponent)
+    PriceAbove { feed_id: [u8; 32], price: i64 },
+    /// Clock at or past `unix_timestamp`
+    After { unix_timestamp: i64 },
+    /// `attestor` co-signs the execution
+    Attestation { attestor: Pubkey },
+}
+
+/// Vault-funded limit order resting on the DEX adapter.
+#[account]
+#[derive(InitSpace)]
+pub struct LimitOrder {
+    /// The funding vault
+    pub vault: Pubkey,
+    /// Vault-scoped order identifier
+    pub order_id: u64,
+    /// Mint sold
+    pub input_mint: Pubkey,
+    /// Mint bought
+    pub output_mint: Pubkey,
+    /// Input escrowed with the DEX when the order was placed
+    pub amount: u64,
+    /// Minimum output for the full `amount` (the limit price)
+    pub min_output: u64,
+    /// Input returned unfilled so far
+    pub returned: u64,
+    /// Output received from fills so far
+    pub received: u64,
+    /// Unix timestamp of placement
+    pub placed_at: i64,
+}
+
+impl LimitOrder {
+    /// Whether the output received covers the input consumed at the
+    /// limit price.
+    pub fn meets_limit(&self) -> bool {
+        let consumed = (self.amount - self.returned) as u128;
+        self.received as u128 * self.amount as u128 >= consumed * self.min_output as u128
+    }
+}
+
+/// Per-mint sub-balance of a vault.
+///
+/// Tokens sit in the vault's associated token account on L1; this PDA
+/// tracks the vault's spendable amount and is what gets delegated to the
+/// ER for private token transfers.  A vault's sub-balances can be
+/// enumerated with a `getProgramAccounts` memcmp on `vault` (offset 8);
+/// `VaultState::token_balances` says how many to expect.
+#[account]
+#[derive(InitSpace)]
+pub struct VaultTokenBalance {
+    /// The owning vault
+    pub vault: Pubkey,
+    /// Token mint
+    pub mint: Pubkey,
+    /// Spendable amount in base units
+    pub amount: u64,
+    /// Monotonically increasing nonce for private token transfers
+    pub nonce: u64,
+    /// Changed since last committed from the ER
+    pub dirty: bool,
+    /// Per-epoch outflow cap, in base units
+    pub spending_limit: SpendingLimit,
+    /// Delegation SDK version the ledger was last delegated under
+    pub delegation_sdk_version: u8,
+}
+
+impl VaultTokenBalance {
+    pub fn credit(&mut self, amount: u64) -> Result<()> {
+        self.amount = self
+            .amount
+            .checked_add(amount)
+            .ok_or(ObscuraError::MathOverflow)?;
+        self.dirty = true;
+        Ok(())
+    }
+
+    pub fn debit(&mut self, amount: u64) -> Result<()> {
+        require!(self.amount >= amount, ObscuraError::InsufficientBalance);
+        self.amount -= amount;
+        self.dirty = true;
+        Ok(())
+    }
+}
+
+impl Companion for VaultTokenBalance {
+    fn vault(&self) -> Pubkey {
+        self.vault
+    }
+
+    fn dirty_mut(&mut self) -> &mut bool {
+        &mut self.dirty
+    }
+}
+
+/// Lamports a vault owes one relayer for sponsored operations.
+///
+/// Fees accrue in `owed`, move to `pending` when the relayer settles,
+/// and can be claimed from the vault once `RELAYER_DISPUTE_WINDOW` has
+/// passed without the owner disputing them.
+#[account]
+#[derive(InitSpace)]
+pub struct RelayerLedger {
+    /// The sponsored vault
+    pub vault: Pubkey,
+    /// The relayer (fee payer) being reimbursed
+    pub relayer: Pubkey,
+    /// Most lamports that may be owed + pending at once
+    pub cap: u64,
+    /// Fees accrued since the last settlement
+    pub owed: u64,
+    /// Fees under settlement
+    pub pending: u64,
+    /// When `pending` becomes claimable
+    pub claimable_at: i64,
+    /// Total fees the owner has disputed
+    pub disputed: u64,
+    /// Number of sponsored operations recorded
+    pub sponsored_ops: u64,
+}
+
+/// Registry entry mapping a salted contact-identifier hash to the
+/// custodial vault waiting for that contact.
+#[account]
+#[derive(InitSpace)]
+pub struct ContactClaim {
+    /// `sha256(salt || normalized phone number or email)`
+    pub contact_hash: [u8; 32],
+    /// The claimable vault
+    pub vault: Pubkey,
+    /// When the entry was registered
+    pub created_at: i64,
+}
+
+/// Proof that a payment intent was paid, keyed by (recipient vault,
+/// reference) so the same request can't be paid twice.
+#[account]
+#[derive(InitSpace)]
+pub struct IntentReceipt {
+    /// The paying vault
+    pub payer_vault: Pubkey,
+    /// The vault paid into
+    pub recipient_vault: Pubkey,
+    /// The intent's reference
+    pub reference: [u8; 32],
+    /// Lamports paid
+    pub amount: u64,
+    /// When it was paid
+    pub paid_at: i64,
+}
+
+/// Time-locked savings goal: part of a vault's balance that cannot be
+/// spent before `unlock_at`.
+#[account]
+#[derive(InitSpace)]
+pub struct SavingsGoal {
+    /// The vault holding the locked funds
+    pub vault: Pubkey,
+    /// Owner-chosen goal identifier
+    pub goal_id: u64,
+    /// Locked lamports
+    pub amount: u64,
+    /// When the goal can be released without a fee
+    pub unlock_at: i64,
+    /// When the goal was created
+    pub created_at: i64,
+}
+
+/// Protocol safety fund; holds early-break fees as lamports.
+#[account]
+#[derive(InitSpace)]
+pub struct SafetyFund {
+    /// Total fees received
+    pub total_received: u64,
+}
+
+/// Vault ids owned by one wallet, for single-fetch enumeration.
+#[account]
+#[derive(InitSpace)]
+pub struct OwnerIndex {
+    /// The indexed wallet
+    pub owner: Pubkey,
+    /// Vault ids, in insertion order
+    #[max_len(MAX_INDEXED_VAULTS)]
+    pub vault_ids: Vec<u64>,
+}
+
+impl OwnerIndex {
+    pub fn insert(&mut self, vault_id: u64) -> Result<()> {
+        if self.vault_ids.contains(&vault_id) {
+            return Ok(());
+        }
+        require!(
+            self.vault_ids.len() < MAX_INDEXED_VAULTS,
+            ObscuraError::OwnerIndexFull
+        );
+        self.vault_ids.push(vault_id);
+        Ok(())
+    }
+
+    pub fn remove(&mut self, vault_id: u64) {
+        self.vault_ids.retain(|id| *id != vault_id);
+    }
+}
+
+/// Owner-set vault metadata.
+#[account]
+#[derive(InitSpace)]
+pub struct VaultMetadata {
+    /// The described vault
+    pub vault: Pubkey,
+    /// Short plaintext label, e.g. "Savings"
+    #[max_len(MAX_LABEL_LEN)]
+    pub label: String,
+    /// Memo encrypted to the owner; opaque to the program
+    #[max_len(MAX_ENCRYPTED_MEMO_LEN)]
+    pub encrypted_memo: Vec<u8>,
+    /// When the metadata was last changed
+    pub updated_at: i64,
+}
+
+/// Marks an NFT as held by a vault.
+#[account]
+#[derive(InitSpace)]
+pub struct NftRecord {
+    /// The custodying vault
+    pub vault: Pubkey,
+    /// NFT mint
+    pub mint: Pubkey,
+    /// When the NFT was deposited
+    pub deposited_at: i64,
+}
+
+/// Marks a compressed NFT leaf as owned by a vault.
+#[account]
+#[derive(InitSpace)]
+pub struct CnftRecord {
+    /// The custodying vault
+    pub vault: Pubkey,
+    /// Bubblegum asset id
+    pub asset_id: Pubkey,
+    /// Tree holding the leaf
+    pub merkle_tree: Pubkey,
+    /// Leaf nonce
+    pub nonce: u64,
+    /// When the cNFT was deposited
+    pub deposited_at: i64,
+}
+
+/// Permission entry for PER access control.
+///
+/// Grants a specific pubkey the `SCOPE_*` rights in `scopes`: reading
+/// vault state when the vault is delegated to a TEE validator,
+/// initiating transfers, or administration.
+#[account]
+#[derive(InitSpace)]
+pub struct PermissionState {
+    /// The vault this permission applies to
+    pub vault: Pubkey,
+    /// The pubkey that is permitted to read the vault
+    pub permitted: Pubkey,
+    /// Who granted the permission
+    pub granted_by: Pubkey,
+    /// When the permission was granted
+    pub granted_at: i64,
+    /// Whether the permission is temporarily suspended
+    pub suspended: bool,
+    /// When the current suspension started (0 = not suspended)
+    pub suspended_at: i64,
+    /// When the permission lapses (0 = never)
+    pub expires_at: i64,
+    /// Granted `SCOPE_*` bits
+    pub scopes: u8,
+}
+
+impl PermissionState {
+    /// A fresh grant of `scopes` on `vault` to `permitted`, validating
+    /// the scopes and expiry.
+    pub fn grant(
+        vault: Pubkey,
+        permitted: Pubkey,
+        granted_by: Pubkey,
+        scopes: u8,
+        expires_at: Option<i64>,
+        now: i64,
+    ) -> Result<Self> {
+        require!(
+            scopes != 0 && scopes & !SCOPE_ALL == 0,
+            ObscuraError::InvalidScopes
+        );
+        require!(
+            !expires_at.is_some_and(|at| at <= now),
+            ObscuraError::InvalidExpiry
+        );
+        Ok(Self {
+            vault,
+            permitted,
+            granted_by,
+            granted_at: now,
+            suspended: false,
+            suspended_at: 0,
+            expires_at: expires_at.unwrap_or(0),
+            scopes,
+        })
+    }
+
+    /// Whether the permission has lapsed at `now`.
+    pub fn is_expired(&self, now: i64) -> bool {
+        self.expires_at != 0 && now >= self.expires_at
+    }
+
+    /// Whether the permission currently grants access.
+    pub fn is_active(&self, now: i64) -> bool {
+        !self.suspended && !self.is_expired(now)
+    }
+
+    /// Whether the grant is mirrored by an Access Control permission:
+    /// read-scoped and not suspended.
+    pub fn has_acl_entry(&self) -> bool {
+        self.scopes & SCOPE_READ != 0 && !self.suspended
+    }
+
+    /// Whether the permission currently grants every bit in `scope`.
+    pub fn allows(&self, scope: u8, now: i64) -> bool {
+        self.is_active(now) && self.scopes & scope == scope
+    }
+}
+
+/// A device key registered to a vault.
+#[account]
+#[derive(InitSpace)]
+pub struct DeviceKey {
+    /// The vault the device belongs to
+    pub vault: Pubkey,
+    /// The device's signing key
+    pub device: Pubkey,
+    /// When the device was registered
+    pub registered_at: i64,
+}
+
+/// Encrypted session bundle posted by one registered device for another
+/// to pick up.
+#[account]
+#[derive(InitSpace)]
+pub struct SessionHandoff {
+    /// The vault both devices belong to
+    pub vault: Pubkey,
+    /// The posting device (receives the rent back)
+    pub from_device: Pubkey,
+    /// The device the bundle is encrypted to
+    pub to_device: Pubkey,
+    /// Session bundle, encrypted to `to_device`
+    #[max_len(MAX_SESSION_BUNDLE_LEN)]
+    pub bundle: Vec<u8>,
+    /// When the handoff was posted
+    pub posted_at: i64,
+    /// When the handoff can no longer be acknowledged
+    pub expires_at: i64,
+}
+
+/// A payment request, as carried in a signed payment intent envelope
+/// (see `docs/PAYMENT_INTENTS.md`).  Its Borsh encoding is what the
+/// requester signs.
+#[derive(AnchorSerialize, AnchorDeserialize, Clone, Copy)]
+pub struct PaymentIntent {
+    /// Envelope version (`PAYMENT_INTENT_VERSION`)
+    pub version: u8,
+    /// Amount in the asset's base units
+    pub amount: u64,
+    /// Asset mint (default = SOL, the only asset `fulfill_intent` pays)
+    pub asset: Pubkey,
+    /// Who is paid
+    pub recipient: RecipientAlias,
+    /// Unix timestamp after which the request can't be paid
+    pub expires_at: i64,
+    /// Requester-chosen reference, e.g. an invoice id hash
+    pub reference: [u8; 32],
+}
+
+/// The recipient of a `PaymentIntent`.
+#[derive(AnchorSerialize, AnchorDeserialize, Clone, Copy, PartialEq, Eq)]
+pub enum RecipientAlias {
+    /// A vault PDA
+    Vault(Pubkey),
+    /// A contact hash registered with `register_contact_claim`
+    Contact([u8; 32]),
+}
+
+/// One recipient of a `private_transfer_batch`.
+#[derive(AnchorSerialize, AnchorDeserialize, Clone, Copy)]
+pub struct BatchTransfer {
+    pub recipient: Pubkey,
+    pub amount: u64,
+}
+
+/// One leg of an `execute_intent` call.
+///
+/// Only operations on accounts that already exist are supported: new
+/// accounts (e.g. permission PDAs) can't be allocated inside the ER.
+#[derive(AnchorSerialize, AnchorDeserialize, Clone)]
+pub enum IntentOp {
+    /// Private transfer, as in `private_transfer` but without commit
+    Transfer { amount: u64, recipient: Pubkey },
+    /// Free-form memo logged alongside the other legs
+    Memo { memo: String },
+    /// Replace the grant in the existing `permission` PDA of `permitted`,
+    /// as `create_permission` would; read scope is not supported
+    GrantPermission {
+        permitted: Pubkey,
+        scopes: u8,
+        expires_at: Option<i64>,
+    },
+}
+
+/// Scoped authority letting a secondary key call a whitelist of
+/// instructions on a vault.
+#[account]
+#[derive(InitSpace)]
+pub struct DelegatedAuthority {
+    /// The vault the authority applies to
+    pub vault: Pubkey,
+    /// The secondary key
+    pub delegate: Pubkey,
+    /// Bitmask of `IX_*` instructions the delegate may call
+    pub allowed_instructions: u64,
+    /// When the authority was (last) granted
+    pub granted_at: i64,
+}
+
+impl DelegatedAuthority {
+    /// Whether the delegate may call the instruction identified by `ix`.
+    pub fn allows(&self, ix: u64) -> bool {
+        self.allowed_instructions & ix == ix
+    }
+}
+
+/// Short-lived key the owner lets sign selected instructions, so the
+/// mobile app need not hold the main key for every ER transaction.
+#[account]
+#[derive(InitSpace)]
+pub struct SessionKey {
+    /// The vault the session key applies to
+    pub vault: Pubkey,
+    /// The session key itself
+    pub key: Pubkey,
+    /// When the session key lapses
+    pub expires_at: i64,
+    /// Most lamports a single call may move
+    pub max_amount: u64,
+    /// Bitmask of `IX_*` instructions the key may sign
+    pub allowed_instructions: u64,
+    /// When the session key was (last) created
+    pub created_at: i64,
+}
+
+impl SessionKey {
+    /// Fail unless the key may sign `ix` moving `amount` at `now`.
+    pub fn authorize(&self, ix: u64, amount: u64, now: i64) -> Result<()> {
+        require!(
+            self.allowed_instructions & ix == ix,
+            ObscuraError::Unauthorized
+        );
+        require!(now < self.expires_at, ObscuraError::SessionKeyExpired);
+        require!(
+            amount <= self.max_amount,
+            ObscuraError::SessionKeyAmountExceeded
+        );
+        Ok(())
+    }
+}
+
+/// Short-lived, one-time capability minted by the owner for a service.
+#[account]
+#[derive(InitSpace)]
+pub struct Capability {
+    /// The vault the capability draws on
+    pub vault: Pubkey,
+    /// Key allowed to execute it
+    pub service: Pubkey,
+    /// Owner-chosen id, distinguishing capabilities for the same service
+    pub capability_id: u64,
+    /// What the service may do
+    pub verb: CapabilityVerb,
+    /// What it may do it to (see `CapabilityVerb`)
+    pub object: Pubkey,
+    /// Most lamports the execution may move
+    pub max_amount: u64,
+    /// When the capability lapses
+    pub expires_at: i64,
+    /// When the capability was minted
+    pub created_at: i64,
+}
+
+/// A vault's full policy, as carried by a policy document.
+#[derive(AnchorSerialize, AnchorDeserialize, Clone, PartialEq, Eq)]
+pub struct VaultPolicy {
+    /// Lowest validator score the vault may be delegated to
+    pub min_validator_score: u16,
+    /// Velocity step-up factor in percent (0 = off)
+    pub velocity_factor_pct: u16,
+    /// Cosigner for transfers over the velocity threshold
+    pub velocity_cosigner: Option<Pubkey>,
+    /// Cap on fees paid from the vault per transaction (0 = off)
+    pub fee_payment_cap: u64,
+    /// Per-epoch outflow cap (0 = off)
+    pub spending_limit_per_epoch: u64,
+    /// Rolling 24h withdrawal cap (0 = off)
+    pub withdrawal_daily_cap: u64,
+    /// Withdrawals over this are timelocked (0 = off)
+    pub withdrawal_timelock_threshold: u64,
+    /// Key allowed to manage read-only permissions
+    pub permission_manager: Option<Pubkey>,
+}
+
+/// Action a `Capability` authorizes.
+#[derive(AnchorSerialize, AnchorDeserialize, Clone, Copy, PartialEq, Eq, InitSpace)]
+pub enum CapabilityVerb {
+    /// Send lamports to the `object` account
+    Transfer,
+    /// Deposit lamports into the `object` vault, crediting its ledger
+    TopUpVault,
+}
+
+/// Reject validator score floors above `MAX_VALIDATOR_SCORE`.
+pub fn require_valid_min_score(min_score: u16) -> Result<()> {
+    require!(
+        min_score <= MAX_VALIDATOR_SCORE,
+        ObscuraError::InvalidObservation
+    );
+    Ok(())
+}
+
+/// Queue a withdrawal of `amount` in `pending`, executable after
+/// `WITHDRAWAL_TIMELOCK_DELAY`.
+fn queue_withdrawal(
+    vault: &mut Account<VaultState>,
+    pending: &mut Account<PendingWithdrawal>,
+    amount: u64,
+) -> Result<()> {
+    let now = Clock::get()?.unix_timestamp;
+    pending.vault = vault.key();
+    pending.amount = amount;
+    pending.requested_at = now;
+    pending.executable_at = now + WITHDRAWAL_TIMELOCK_DELAY;
+    vault.last_activity = now;
+
+    msg!(
+        "Withdrawal of {} lamports from vault {} queued until {}",
+        amount,
+        vault.vault_id,
+        pending.executable_at
+    );
+    Ok(())
+}
+
+/// Move `amount` from the vault to its owner (or the owner's wSOL
+/// account), enforcing the outflow limits and recording it in the ledger.
+fn pay_out_withdrawal<'info>(
+    vault: &mut Account<'info, VaultState>,
+    owner: &AccountInfo<'info>,
+    history_view: Option<&mut Account<'info, HistoryView>>,
+    amount: u64,
+) -> Result<()> {
+    let clock = Clock::get()?;
+    let now = clock.unix_timestamp;
+    vault.spending_limit.record(clock.epoch, now, amount)?;
+    vault.withdrawal_limit.record(now, amount)?;
+
+    **vault.to_account_info().try_borrow_mut_lamports()? -= amount;
+    **owner.try_borrow_mut_lamports()? += amount;
+
+    vault.debit(amount)?;
+    vault.billing.accrue(now, amount)?;
+    vault.last_activity = now;
+    if let Some(history_view) = history_view {
+        history_view.record(now, FlowDirection::Outflow, amount);
+    }
+    Ok(())
+}
+
+/// Delegated account belonging to a vault whose changes are tracked, so
+/// `commit_vault_state` only commits it when needed.
+pub trait Companion {
+    /// The vault the account belongs to
+    fn vault(&self) -> Pubkey;
+    /// Set whenever the account changes; cleared when it is committed
+    fn dirty_mut(&mut self) -> &mut bool;
+}
+
+/// Load `info` as a `T` companion of `vault` and clear its dirty flag.
+///
+/// Returns `None` if `info` is not a `T`, otherwise whether it was dirty
+/// (and so needs committing).
+fn take_dirty<'info, T>(info: &'info AccountInfo<'info>, vault: &Pubkey) -> Result<Option<bool>>
+where
+    T: AccountSerialize + AccountDeserialize + Owner + Clone + Companion,
+{
+    let Ok(mut account) = Account::<T>::try_from(info) else {
+        return Ok(None);
+    };
+    require_keys_eq!(account.vault(), *vault, ObscuraError::InvalidCompanion);
+    let dirty = std::mem::take(account.dirty_mut());
+    if dirty {
+        account.exit(&crate::ID)?;
+    }
+    Ok(Some(dirty))
+}
+
+/// Apply `transfers` out of a delegated vault inside the ER, then commit
+/// the vault to L1 and undelegate it, once for the whole list.
+fn private_transfers(ctx: Context<PrivateTransfer>, transfers: &[BatchTransfer]) -> Result<()> {
+    let total = transfers
+        .iter()
+        .try_fold(0u64, |total, transfer| total.checked_add(transfer.amount))
+        .ok_or(ObscuraError::MathOverflow)?;
+    let cosigner = ctx.accounts.cosigner.as_ref().map(|c| c.key());
+    let vault = &mut ctx.accounts.vault;
+
+    require!(vault.is_delegated, ObscuraError::NotDelegated);
+    require!(vault.balance >= total, ObscuraError::InsufficientBalance);
+    vault.require_outbound_allowed()?;
+    vault.require_acknowledged_upgrade()?;
+    vault.require_cosigned(cosigner)?;
+
+    // Execute transfer logic
+    let clock = Clock::get()?;
+    let now = clock.unix_timestamp;
+    match ctx.accounts.session_key.as_deref() {
+        Some(session_key) => session_key.authorize(IX_PRIVATE_TRANSFER, total, now)?,
+        None => require_owner_or_permitted(
+            vault,
+            &ctx.accounts.owner.key(),
+            ctx.accounts.permission.as_deref(),
+            SCOPE_TRANSFER,
+            now,
+        )?,
+    }
+    vault.operator_fees.stop(now)?;
+    for &BatchTransfer { recipient, amount } in transfers {
+        vault.require_allowed_recipient(ctx.accounts.recipient_allowlist.as_deref(), &recipient)?;
+        require_compliant(
+            vault,
+            ctx.accounts.denylist.as_ref(),
+            ctx.accounts.compliance_program.as_ref(),
+            ctx.remaining_accounts,
+            &recipient,
+            amount,
+        )?;
+        vault.velocity.observe(now, amount, cosigner)?;
+        vault.spending_limit.record(clock.epoch, now, amount)?;
+        vault.record_transfer(amount)?;
+        vault.billing.accrue(now, amount)?;
+        let skimmed = skim_round_up(vault, ctx.accounts.savings_vault.as_mut(), amount)?;
+        let split = apply_split(
+            vault,
+            ctx.accounts.split_vault.as_mut(),
+            FlowDirection::Outflow,
+            amount,
+        )?;
+        if let Some(history_view) = ctx.accounts.history_view.as_mut() {
+            history_view.record(now, FlowDirection::Outflow, amount);
+        }
+
+        msg!(
+            "Private transfer: {} lamports to {}, nonce={}, round-up={}, split={}",
+            amount,
+            recipient,
+            vault.nonce,
+            skimmed,
+            split
+        );
+    }
+    vault.last_activity = now;
+
+    // Savings and split vaults stay delegated; commit their new
+    // balances without undelegating them.
+    // Each is committed through the delegation program it was delegated
+    // under.
+    for side_vault in [
+        ctx.accounts.savings_vault.as_mut(),
+        ctx.accounts.split_vault.as_mut(),
+    ]
+    .into_iter()
+    .flatten()
+    {
+        side_vault.exit(&crate::ID)?;
+        delegation::commit(
+            side_vault.delegation_sdk_version,
+            &ctx.accounts.owner,
+            vec![&side_vault.to_account_info()],
+            &ctx.accounts.magic_context,
+            &ctx.accounts.magic_program,
+        )?;
+    }
+
+    // Commit state back to L1 and undelegate in one step.
+    // The `#[commit]` macro on `PrivateTransfer` wires up the
+    // `magic_context` and `magic_program` accounts automatically.
+    let sdk_version = ctx.accounts.vault.delegation_sdk_version;
+    ctx.accounts.vault.exit(&crate::ID)?;
+    let vault_info = ctx.accounts.vault.to_account_info();
+    let mut accounts = vec![vault_info];
+    if let Some(history_view) = ctx.accounts.history_view.as_mut() {
+        history_view.dirty = false;
+        history_view.exit(&crate::ID)?;
+        accounts.push(history_view.to_account_info());
+    }
+    delegation::commit_and_undelegate(
+        sdk_version,
+        &ctx.accounts.owner,
+        accounts.iter().collect(),
+        &ctx.accounts.magic_context,
+        &ctx.accounts.magic_program,
+    )?;
+
+    Ok(())
+}
+
+/// Run the vault's integrator compliance checks on an outbound transfer
+/// of `amount` to `recipient`: the integrator's denylist, then its
+/// compliance program with `hook_accounts`.
+///
+/// Vaults not created through an integrator, or whose integrator keeps no
+/// denylist, pass without either account.
+fn require_compliant<'info>(
+    vault: &Account<'info, VaultState>,
+    denylist: Option<&UncheckedAccount<'info>>,
+    compliance_program: Option<&UncheckedAccount<'info>>,
+    hook_accounts: &[AccountInfo<'info>],
+    recipient: &Pubkey,
+    amount: u64,
+) -> Result<()> {
+    if vault.integrator == Pubkey::default() {
+        return Ok(());
+    }
+    let info = denylist.ok_or(ObscuraError::InvalidDenylist)?;
+    if info.data_is_empty() {
+        return Ok(());
+    }
+    require_keys_eq!(*info.owner, crate::ID, ObscuraError::InvalidDenylist);
+    let denylist = Denylist::try_deserialize(&mut &info.try_borrow_data()?[..])?;
+    require!(
+        !denylist.recipients.contains(recipient),
+        ObscuraError::RecipientDenied
+    );
+
+    if denylist.compliance_program != Pubkey::default() {
+        let program = compliance_program.ok_or(ObscuraError::InvalidComplianceProgram)?;
+        require_keys_eq!(
+            program.key(),
+            denylist.compliance_program,
+            ObscuraError::InvalidComplianceProgram
+        );
+        compliance::check_transfer(program, hook_accounts, vault.key(), *recipient, amount)?;
+    }
+    Ok(())
+}
+
+/// Fail unless the current instruction is the transaction's only
+/// top-level `pay_fees_from_vault` for `vault`, so `fee_payment_cap`
+/// bounds what one transaction can take.
+fn require_single_fee_payment(instructions: &AccountInfo, vault: &Pubkey) -> Result<()> {
+    require!(
+        get_stack_height() == TRANSACTION_LEVEL_STACK_HEIGHT,
+        ObscuraError::FeePaymentRepeated
+    );
+    let mut payments = 0;
+    let mut index = 0;
+    while let Ok(ix) = load_instruction_at_checked(index, instructions) {
+        if ix.program_id == crate::ID
+            && ix
+                .data
+                .starts_with(&instruction::PayFeesFromVault::DISCRIMINATOR)
+            && ix
+                .accounts
+                .first()
+                .is_some_and(|meta| meta.pubkey == *vault)
+        {
+            payments += 1;
+        }
+        index += 1;
+    }
+    require!(payments == 1, ObscuraError::FeePaymentRepeated);
+    Ok(())
+}
+
+/// Require `signer` to be the vault owner or a delegate allowed to call `ix`.
+pub fn require_owner_or_delegate(
+    vault: &VaultState,
+    signer: &Pubkey,
+    delegated_authority: Option<&DelegatedAuthority>,
+    ix: u64,
+) -> Result<()> {
+    if *signer == vault.owner {
+        return Ok(());
+    }
+    let allowed = delegated_authority.is_some_and(|a| a.delegate == *signer && a.allows(ix));
+    require!(allowed, ObscuraError::Unauthorized);
+    Ok(())
+}
+
+/// Require `signer` to be the vault owner, or its permission manager
+/// acting on read-only `scopes`.
+pub fn require_permission_authority(vault: &VaultState, signer: &Pubkey, scopes: u8) -> Result<()> {
+    if *signer == vault.owner {
+        return Ok(());
+    }
+    require!(
+        vault.permission_manager != Pubkey::default()
+            && *signer == vault.permission_manager
+            && scopes == SCOPE_READ,
+        ObscuraError::Unauthorized
+    );
+    Ok(())
+}
+
+/// Require `signer` to be the vault owner or hold an active permission
+/// covering `scope`.
+pub fn require_owner_or_permitted(
+    vault: &VaultState,
+    signer: &Pubkey,
+    permission: Option<&PermissionState>,
+    scope: u8,
+    now: i64,
+) -> Result<()> {
+    if *signer == vault.owner {
+        return Ok(());
+    }
+    let allowed = permission.is_some_and(|p| p.permitted == *signer && p.allows(scope, now));
+    require!(allowed, ObscuraError::Unauthorized);
+    Ok(())
+}
+
+/// Coarse, read-only view of a vault's activity for compliance viewers.
+///
+/// Holds a ring of `HISTORY_MONTHS` calendar months, each with counts of
+/// inflows and outflows per amount bucket.  Amounts, counterparties and
+/// exact times are never recorded.
+#[account]
+#[derive(InitSpace)]
+pub struct HistoryView {
+    /// The vault this view summarises
+    pub vault: Pubkey,
+    /// Monthly buckets, indexed by `month % HISTORY_MONTHS`
+    pub months: [HistoryMonth; HISTORY_MONTHS],
+    /// Changed since last committed from the ER
+    pub dirty: bool,
+}
+
+impl Companion for HistoryView {
+    fn vault(&self) -> Pubkey {
+        self.vault
+    }
+
+    fn dirty_mut(&mut self) -> &mut bool {
+        &mut self.dirty
+    }
+}
+
+/// Support snapshot of one vault, returned by `inspect_vaults`.
+#[derive(AnchorSerialize, AnchorDeserialize, Clone)]
+pub struct VaultHealth {
+    /// The vault PDA
+    pub vault: Pubkey,
+    /// Unique vault identifier
+    pub vault_id: u64,
+    /// Wallet owner
+    pub owner: Pubkey,
+    /// Whether the vault is delegated to an ER validator
+    pub is_delegated: bool,
+    /// Whether the inspected account is currently owned by the delegation
+    /// program (false while delegated means the delegation is stuck)
+    pub owned_by_delegation: bool,
+    /// Pubkey of the ER validator (default = not delegated)
+    pub delegate_validator: Pubkey,
+    /// Whether delegated to a TEE validator (Private ER)
+    pub is_private: bool,
+    /// Replay nonce; compare with the ER copy for uncommitted operations
+    pub nonce: u64,
+    /// Unix timestamp of last activity
+    pub last_activity: i64,
+    /// SOL balance in lamports
+    pub balance: u64,
+    /// Part of `balance` that can leave the vault
+    pub spendable: u64,
+    /// Most lamports `pay_fees_from_vault` may take per transaction
+    pub fee_payment_cap: u64,
+    /// Invoiced fees not yet on a closed statement
+    pub unbilled_fees: u64,
+    /// `HEALTH_*` bits
+    pub policy_flags: u16,
+}
+
+impl VaultHealth {
+    /// Snapshot a vault account, which may be owned by this program or,
+    /// while delegated, by the delegation program.
+    pub fn inspect(account: &AccountInfo) -> Result<Self> {
+        let vault = VaultState::try_deserialize(&mut &account.try_borrow_data()?[..])?;
+        let (address, _) =
+            Pubkey::find_program_address(&[VAULT_SEED, &vault.vault_id.to_le_bytes()], &crate::ID);
+        require_keys_eq!(account.key(), address, ObscuraError::InvalidInspection);
+
+        let owned_by_delegation = *account.owner != crate::ID;
+        if owned_by_delegation {
+            require_keys_eq!(
+                *account.owner,
+                delegation::delegation_program_id(vault.delegation_sdk_version)?,
+                ObscuraError::InvalidInspection
+            );
+        }
+
+        Ok(Self {
+            vault: address,
+            vault_id: vault.vault_id,
+            owner: vault.owner,
+            is_delegated: vault.is_delegated,
+            owned_by_delegation,
+            delegate_validator: vault.delegate_validator,
+            is_private: vault.is_private,
+            nonce: vault.nonce,
+            last_activity: vault.last_activity,
+            balance: vault.balance,
+            spendable: vault.spendable(),
+            fee_payment_cap: vault.fee_payment_cap,
+            unbilled_fees: vault
+                .billing
+                .open_fees
+                .saturating_add(vault.billing.pending_fees),
+            policy_flags: vault.policy_flags(),
+        })
+    }
+}
+
+/// One calendar month of a `HistoryView`.
+#[derive(AnchorSerialize, AnchorDeserialize, Clone, Copy, Default, InitSpace)]
+pub struct HistoryMonth {
+    /// Calendar month as `year * 12 + (month - 1)`; 0 = unused
+    pub month: u32,
+    /// Inflow counts per amount bucket
+    pub inflows: [u32; HISTORY_AMOUNT_BUCKETS],
+    /// Outflow counts per amount bucket
+    pub outflows: [u32; HISTORY_AMOUNT_BUCKETS],
+}
+
+/// Direction of a flow recorded in a `HistoryView`.
+#[derive(Clone, Copy, PartialEq, Eq)]
+pub enum FlowDirection {
+    Inflow,
+    Outflow,
+}
+
+impl HistoryView {
+    /// Count one flow of `amount` lamports at `unix_timestamp`.
+    pub fn record(&mut self, unix_timestamp: i64, direction: FlowDirection, amount: u64) {
+        let month = month_index(unix_timestamp);
+        let slot = &mut self.months[month as usize % HISTORY_MONTHS];
+        if slot.month != month {
+            *slot = HistoryMonth {
+                month,
+                ..HistoryMonth::default()
+            };
+        }
+
+        let bucket = amount_bucket(amount);
+        let counts = match direction {
+            FlowDirection::Inflow => &mut slot.inflows,
+            FlowDirection::Outflow => &mut slot.outflows,
+        };
+        counts[bucket] = counts[bucket].saturating_add(1);
+        self.dirty = true;
+    }
+}
 
-    #[account(mut)]
-    pub owner: Signer<'info>,
+/// Amount bucket: < 0.01 SOL, < 0.1 SOL, < 1 SOL, < 10 SOL, >= 10 SOL.
+pub fn amount_bucket(amount: u64) -> usize {
+    let mut bucket = 0;
+    let mut bound = 10_000_000u64;
+    while bucket < HISTORY_AMOUNT_BUCKETS - 1 && amount >= bound {
+        bucket += 1;
+        bound = bound.saturating_mul(10);
+    }
+    bucket
 }
 
-/// Undelegate — commit + undelegate in one step.
-#[commit]
-#[derive(Accounts)]
-pub struct UndelegateVault<'info> {
-    #[account(
-        mut,
-        seeds = [VAULT_SEED, &vault.vault_id.to_le_bytes()],
-        bump,
-        com,
-    )]
-    pub vault: Account<'info, VaultState>,
+/// Calendar month of a unix timestamp as `year * 12 + (month - 1)`.
+pub fn month_index(unix_timestamp: i64) -> u32 {
+    // Civil-from-days (H. Hinnant), days counted from 0000-03-01.
+    let z = unix_timestamp.div_euclid(86_400) + 719_468;
+    let era = z.div_euclid(146_097);
+    let doe = z - era * 146_097;
+    let yoe = (doe - doe / 1_460 + doe / 36_524 - doe / 146_096) / 365;
+    let doy = doe - (365 * yoe + yoe / 4 - yoe / 100);
+    let mp = (5 * doy + 2) / 153;
+    let month = if mp < 10 { mp + 3 } else { mp - 9 };
+    let year = yoe + era * 400 + i64::from(month <= 2);
+    (year * 12 + month - 1) as u32
+}
 
-    #[account(mut)]
-    pub owner: Signer<'info>,
+/// Closed-out monthly protocol fee statement for one vault.
+#[account]
+#[derive(InitSpace)]
+pub struct FeeStatement {
+    /// The billed vault
+    pub vault: Pubkey,
+    /// Billed month (see `month_index`)
+    pub period: u32,
+    /// Fee rate in effect at close-out
+    pub fee_bps: u16,
+    /// Total fees owed for the period, in lamports
+    pub fees: u64,
+    /// Number of fee-bearing operations
+    pub operations: u32,
+    /// When the statement was closed
+    pub closed_at: i64,
+    /// Integrator credited with a share (default = none)
+    pub integrator: Pubkey,
+    /// Integrator's share of `fees`, in lamports
+    pub integrator_share: u64,
 }
 
-#[derive(Accounts)]
-pub struct Deposit<'info> {
-    #[account(
-        mut,
-        seeds = [VAULT_SEED, &vault.vault_id.to_le_bytes()],
-        bump,
-    )]
-    pub vault: Account<'info, VaultState>,
+/// Third-party app embedding Obscura, earning a share of the protocol
+/// fees billed to the vaults created through it.
+#[account]
+#[derive(InitSpace)]
+pub struct Integrator {
+    /// Per-app identifier
+    pub app_id: u64,
+    /// The app's key
+    pub authority: Pubkey,
+    /// Vault receiving monthly settlements
+    pub payout_vault: Pubkey,
+    /// Share of billed fees, in basis points
+    pub share_bps: u16,
+    /// Share accrued from closed statements, awaiting settlement
+    pub accrued: u64,
+    /// Month (see `month_index`) of the last settlement
+    pub settled_period: u32,
+    /// Total lamports paid out
+    pub total_paid: u64,
+    /// Unix timestamp of registration
+    pub registered_at: i64,
+}
 
-    #[account(mut)]
-    pub depositor: Signer<'info>,
+impl Integrator {
+    /// Accrue the integrator's share of `fees`, returning it.
+    pub fn accrue(&mut self, fees: u64) -> Result<u64> {
+        let share = (fees as u128 * self.share_bps as u128 / BPS_DENOMINATOR as u128) as u64;
+        self.accrued = self
+            .accrued
+            .checked_add(share)
+            .ok_or(ObscuraError::MathOverflow)?;
+        Ok(share)
+    }
+}
 
-    pub system_program: Program<'info, System>,
+/// A `ProgramConfig` parameter change, as queued with
+/// `queue_config_change`.  Each variant sets the field its `set_*`
+/// instruction sets.
+#[derive(AnchorSerialize, AnchorDeserialize, Clone, Copy, InitSpace)]
+pub enum ConfigChange {
+    /// `set_keeper`
+    Keeper { keeper: Pubkey },
+    /// `set_claim_attestor`
+    ClaimAttestor { claim_attestor: Pubkey },
+    /// `set_dex_adapter`
+    DexAdapter { dex_adapter: Pubkey },
+    /// `set_inactivity_undelegate_after`
+    InactivityUndelegateAfter { inactivity_undelegate_after: i64 },
+    /// `set_receipt_tree`
+    ReceiptTree { receipt_tree: Pubkey },
+    /// `set_attestation_tree`
+    AttestationTree { attestation_tree: Pubkey },
+    /// `set_feature_flags`
+    FeatureFlags { feature_flags: u64 },
 }
 
-#[derive(Accounts)]
-pub struct Withdraw<'info> {
-    #[account(
-        mut,
-        seeds = [VAULT_SEED, &vault.vault_id.to_le_bytes()],
-        bump,
-        constraint = vault.owner == owner.key() @ ObscuraError::Unauthorized,
-    )]
-    pub vault: Account<'info, VaultState>,
+impl ConfigChange {
+    /// Fail with the error its `set_*` instruction would.
+    pub fn validate(&self) -> Result<()> {
+        if let ConfigChange::InactivityUndelegateAfter {
+            inactivity_undelegate_after,
+        } = *self
+        {
+            require!(
+                inactivity_undelegate_after == 0
+                    || inactivity_undelegate_after >= MIN_INACTIVITY_UNDELEGATE_AFTER,
+                ObscuraError::InvalidInactivityThreshold
+            );
+        }
+        Ok(())
+    }
+}
 
-    #[account(mut)]
-    pub owner: Signer<'info>,
+/// A config change waiting out `CONFIG_CHANGE_DELAY`, with its vote tally.
+#[account]
+#[derive(InitSpace)]
+pub struct QueuedConfigChange {
+    /// Governance-chosen identifier (PDA seed)
+    pub change_id: u64,
+    /// The change applied on execution
+    pub change: ConfigChange,
+    /// Unix timestamp it was queued; vault ages are measured at it
+    pub queued_at: i64,
+    /// Unix timestamp from which it can be executed and votes close
+    pub executable_at: i64,
+    /// Total weight of votes for it
+    pub weight_for: u64,
+    /// Total weight of votes against it
+    pub weight_against: u64,
+    /// Number of vaults that voted
+    pub voters: u32,
+}
 
-    pub system_program: Program<'info, System>,
+impl QueuedConfigChange {
+    /// Whether the tally blocks execution.
+    pub fn is_vetoed(&self) -> bool {
+        self.weight_against >= VETO_WEIGHT && self.weight_against > self.weight_for
+    }
 }
 
-#[derive(Accounts)]
-#[instruction(permitted_pubkey: Pubkey)]
-pub struct CreatePermission<'info> {
-    #[account(
-        init,
-        payer = owner,
-        space = 8 + PermissionState::INIT_SPACE,
-        seeds = [PERMISSION_SEED, vault.key().as_ref(), permitted_pubkey.as_ref()],
-        bump,
-    )]
-    pub permission: Account<'info, PermissionState>,
+/// One vault's vote on a queued config change.
+#[account]
+#[derive(InitSpace)]
+pub struct VoteRecord {
+    /// The `QueuedConfigChange` voted on
+    pub config_change: Pubkey,
+    /// Vault voting
+    pub vault: Pubkey,
+    /// Vault owner who cast the vote and paid its rent
+    pub voter: Pubkey,
+    /// Whether the vote is for the change
+    pub support: bool,
+    /// Vote weight (vault-days)
+    pub weight: u64,
+}
 
-    #[account(
-        constraint = vault.owner == owner.key() @ ObscuraError::Unauthorized,
-    )]
-    pub vault: Account<'info, VaultState>,
+/// Vote weight of a vault created at `created_at` on a change queued at
+/// `queued_at`: its age in whole days then, up to `MAX_VOTE_WEIGHT`.
+pub fn vote_weight(created_at: i64, queued_at: i64) -> u64 {
+    let days = queued_at.saturating_sub(created_at).max(0) / (24 * 60 * 60);
+    (days as u64).min(MAX_VOTE_WEIGHT)
+}
 
-    #[account(mut)]
-    pub owner: Signer<'info>,
+/// Deployed program version, published after each upgrade.
+#[account]
+#[derive(InitSpace)]
+pub struct ProgramVersionInfo {
+    /// Semantic version of the deployed release
+    pub major: u16,
+    pub minor: u16,
+    pub patch: u16,
+    /// `PROGRAM_VERSION` of the deployed binary
+    pub release: u32,
+    /// Slot the program was last deployed at (from its ProgramData)
+    pub upgrade_slot: u64,
+    /// Hash of the release's capability set, for client feature gating
+    pub features_hash: [u8; 32],
+    /// When this entry was published
+    pub published_at: i64,
+}
 
-    pub system_program: Program<'info, System>,
+/// Singleton program configuration.
+#[account]
+#[derive(InitSpace)]
+pub struct ProgramConfig {
+    /// Governance authority allowed to manage the validator registry
+    pub governance: Pubkey,
+    /// Keeper allowed to submit validator observations
+    pub keeper: Pubkey,
+    /// Enabled experimental features (`FEATURE_*` bits)
+    pub feature_flags: u64,
+    /// Operator key attesting contact ownership for contact claims
+    pub claim_attestor: Pubkey,
+    /// DEX adapter program that limit orders are routed through
+    pub dex_adapter: Pubkey,
+    /// Owner inactivity (seconds) after which the keeper may undelegate a
+    /// vault with `sweep_inactive_vault` (0 = disabled)
+    pub inactivity_undelegate_after: i64,
+    /// Bubblegum tree receipt cNFTs are minted into (default = disabled)
+    pub receipt_tree: Pubkey,
+    /// Bubblegum tree counterparty ratings are minted into (default =
+    /// disabled)
+    pub attestation_tree: Pubkey,
 }
 
-// ---------------------------------------------------------------------------
-// State
-// ---------------------------------------------------------------------------
+impl ProgramConfig {
+    /// Apply a validated config change.
+    pub fn apply(&mut self, change: &ConfigChange) {
+        match *change {
+            ConfigChange::Keeper { keeper } => self.keeper = keeper,
+            ConfigChange::ClaimAttestor { claim_attestor } => self.claim_attestor = claim_attestor,
+            ConfigChange::DexAdapter { dex_adapter } => self.dex_adapter = dex_adapter,
+            ConfigChange::InactivityUndelegateAfter {
+                inactivity_undelegate_after,
+            } => self.inactivity_undelegate_after = inactivity_undelegate_after,
+            ConfigChange::ReceiptTree { receipt_tree } => self.receipt_tree = receipt_tree,
+            ConfigChange::AttestationTree { attestation_tree } => {
+                self.attestation_tree = attestation_tree
+            }
+            ConfigChange::FeatureFlags { feature_flags } => self.feature_flags = feature_flags,
+        }
+    }
 
-/// On-chain vault state managed by Obscura.
+    /// Whether every bit in `feature` is enabled on this cluster.
+    pub fn is_enabled(&self, feature: u64) -> bool {
+        self.feature_flags & feature == feature
+    }
+
+    /// Fail with `FeatureDisabled` unless `feature` is enabled.
+    pub fn require_feature(&self, feature: u64) -> Result<()> {
+        require!(self.is_enabled(feature), ObscuraError::FeatureDisabled);
+        Ok(())
+    }
+}
+
+/// Validator registry entry.
 ///
-/// When delegated to an ER validator, mutations happen with sub-second
-/// finality.  When delegated to the TEE validator, the account data is
-/// only visible to permitted pubkeys.
+/// Tracks whether a validator is still considered live.  Once marked
+/// defunct, vaults stranded on it become eligible for force settlement
+/// after `FORCE_SETTLE_DELAY`.
 #[account]
 #[derive(InitSpace)]
-pub struct VaultState {
-    /// Wallet owner
-    pub owner: Pubkey,
-    /// Unique vault identifier
-    pub vault_id: u64,
-    /// SOL balance in lamports
-    pub balance: u64,
-    /// Whether the account is currently delegated to an ER validator
-    pub is_delegated: bool,
-    /// Pubkey of the ER validator (default = not delegated)
-    pub delegate_validator: Pubkey,
-    /// Unix timestamp of creation
-    pub created_at: i64,
-    /// Unix timestamp of last activity
-    pub last_activity: i64,
-    /// Monotonically increasing nonce for replay protection
-    pub nonce: u64,
-    /// Whether delegated to TEE validator (Private ER)
-    pub is_private: bool,
+pub struct ValidatorInfo {
+    /// The ER / TEE validator pubkey
+    pub validator: Pubkey,
+    /// Delegation SDK version the validator speaks
+    pub sdk_version: u8,
+    /// Whether governance has marked the validator as defunct
+    pub is_defunct: bool,
+    /// Unix timestamp at which the validator was marked defunct
+    pub defunct_at: i64,
+    /// Unix timestamp of registration
+    pub registered_at: i64,
+    /// Operator prices for vaults delegating to this validator
+    pub rate_card: RateCard,
 }
 
-/// Permission entry for PER access control.
-///
-/// Grants a specific pubkey the ability to read vault state when the
-/// vault is delegated to a TEE validator.
+/// Rolling performance score for a registered validator.
 #[account]
 #[derive(InitSpace)]
-pub struct PermissionState {
-    /// The vault this permission applies to
-    pub vault: Pubkey,
-    /// The pubkey that is permitted to read the vault
-    pub permitted: Pubkey,
-    /// Who granted the permission
-    pub granted_by: Pubkey,
-    /// When the permission was granted
-    pub granted_at: i64,
+pub struct ValidatorScore {
+    /// The validator being scored
+    pub validator: Pubkey,
+    /// Derived score, 0..=`MAX_VALIDATOR_SCORE`
+    pub score: u16,
+    /// Exponentially weighted average commit latency in milliseconds
+    pub avg_commit_latency_ms: u32,
+    /// Exponentially weighted average uptime in basis points
+    pub uptime_bps: u16,
+    /// Number of observations folded in
+    pub observations: u64,
+    /// Unix timestamp of the latest observation
+    pub last_observed_at: i64,
 }
 
 // ---------------------------------------------------------------------------
@@ -474,4 +12677,385 @@ pub enum ObscuraError {
 
     #[msg("Vault is not in private mode")]
     NotPrivate,
+
+    #[msg("Validator has already been marked defunct")]
+    ValidatorDefunct,
+
+    #[msg("Validator has not been marked defunct")]
+    ValidatorNotDefunct,
+
+    #[msg("The public force-settlement delay has not elapsed yet")]
+    ForceSettleDelayActive,
+
+    #[msg("Validator score is below the vault's minimum")]
+    ValidatorScoreTooLow,
+
+    #[msg("Observation values are out of range")]
+    InvalidObservation,
+
+    #[msg("Unsupported delegation SDK version")]
+    UnsupportedSdkVersion,
+
+    #[msg("Arithmetic overflow")]
+    MathOverflow,
+
+    #[msg("This feature is not enabled on this cluster")]
+    FeatureDisabled,
+
+    #[msg("Permission is suspended")]
+    PermissionSuspended,
+
+    #[msg("Permission is not suspended")]
+    PermissionNotSuspended,
+
+    #[msg("Instruction whitelist is empty or contains non-delegable instructions")]
+    InvalidInstructionScope,
+
+    #[msg("Intent is empty, too long, or has an oversized memo")]
+    InvalidIntent,
+
+    #[msg("Fee rate exceeds 100%")]
+    InvalidFeeRate,
+
+    #[msg("No finished billing period to close for this vault")]
+    NoFeesToClose,
+
+    #[msg("Source and destination vault are the same")]
+    SameVault,
+
+    #[msg("Daily rebalance limit reached for this vault")]
+    RebalanceLimitExceeded,
+
+    #[msg("Vault is in receive-only mode")]
+    ReceiveOnly,
+
+    #[msg("Vault is not in receive-only mode")]
+    NotReceiveOnly,
+
+    #[msg("Receive-only unlock has not matured yet")]
+    ReceiveOnlyTimelockActive,
+
+    #[msg("Transfer exceeds the vault's velocity norm")]
+    VelocityStepUpRequired,
+
+    #[msg("Velocity factor must be 0 or at least 100%")]
+    InvalidVelocityPolicy,
+
+    #[msg("Program upgrade not yet acknowledged for this strict-mode vault")]
+    UpgradeNotAcknowledged,
+
+    #[msg("Version does not match the running program")]
+    ProgramVersionMismatch,
+
+    #[msg("Mint uses a Token-2022 extension the vault cannot custody")]
+    UnsupportedMint,
+
+    #[msg("Published version must be newer than the current one")]
+    VersionNotIncreasing,
+
+    #[msg("Fee payment exceeds the vault's per-transaction cap")]
+    FeePaymentCapExceeded,
+
+    #[msg("Token sub-balance is not empty")]
+    TokenBalanceNotEmpty,
+
+    #[msg("Dispute window for these relayer fees has closed")]
+    DisputeWindowClosed,
+
+    #[msg("Relayer fees are still inside the dispute window")]
+    DisputeWindowActive,
+
+    #[msg("No relayer fees to settle, dispute or claim")]
+    NoRelayerFees,
+
+    #[msg("Relayer ledger cap exceeded")]
+    RelayerCapExceeded,
+
+    #[msg("Custodial vaults need a claimant key")]
+    InvalidClaimant,
+
+    #[msg("Vault is not custodial")]
+    NotCustodial,
+
+    #[msg("Invalid savings goal timing")]
+    InvalidGoal,
+
+    #[msg("Savings goal is still locked")]
+    GoalLocked,
+
+    #[msg("Vault label or memo too long")]
+    MetadataTooLong,
+
+    #[msg("Owner index is full")]
+    OwnerIndexFull,
+
+    #[msg("Invalid round-up rule")]
+    InvalidRoundUpRule,
+
+    #[msg("Savings vault does not match the round-up rule")]
+    InvalidSavingsVault,
+
+    #[msg("Invalid split rule")]
+    InvalidSplitRule,
+
+    #[msg("Split vault does not match the split rule")]
+    InvalidSplitVault,
+
+    #[msg("Invalid limit order")]
+    InvalidLimitOrder,
+
+    #[msg("Not the configured DEX adapter")]
+    InvalidDexAdapter,
+
+    #[msg("DEX adapter moved unexpected amounts")]
+    DexAdapterMismatch,
+
+    #[msg("Fills do not meet the limit price")]
+    LimitPriceViolated,
+
+    #[msg("Expiry must be in the future")]
+    InvalidExpiry,
+
+    #[msg("Permission has not expired")]
+    PermissionNotExpired,
+
+    #[msg("Invalid conditional transfer")]
+    InvalidConditionalTransfer,
+
+    #[msg("Transfer condition is not met yet")]
+    ConditionNotMet,
+
+    #[msg("Price condition needs a Pyth price update")]
+    MissingPriceUpdate,
+
+    #[msg("Invalid permission scopes")]
+    InvalidScopes,
+
+    #[msg("Invalid permission batch")]
+    InvalidPermissionBatch,
+
+    #[msg("Conditional transfer has no arbiter")]
+    NotArbitrated,
+
+    #[msg("Invalid evidence blob")]
+    InvalidEvidence,
+
+    #[msg("Evidence locker is full")]
+    EvidenceLockerFull,
+
+    #[msg("Access Control permission account mismatch")]
+    InvalidAclPermission,
+
+    #[msg("Integrator account missing or does not match")]
+    InvalidIntegrator,
+
+    #[msg("No integrator fees to settle this month")]
+    NoIntegratorFees,
+
+    #[msg("Not an inspectable vault account")]
+    InvalidInspection,
+
+    #[msg("Transfer exceeds the vault's per-epoch spending limit")]
+    SpendingLimitExceeded,
+
+    #[msg("Session bundle empty, too long or addressed to the posting device")]
+    InvalidSessionBundle,
+
+    #[msg("Session handoff has expired")]
+    SessionHandoffExpired,
+
+    #[msg("Session handoff has not expired yet")]
+    SessionHandoffActive,
+
+    #[msg("Withdrawal exceeds the vault's 24h withdrawal limit")]
+    WithdrawalLimitExceeded,
+
+    #[msg("Raising the withdrawal limit needs a matured override")]
+    WithdrawalOverrideRequired,
+
+    #[msg("Invalid capability or capability destination")]
+    InvalidCapability,
+
+    #[msg("Capability has expired")]
+    CapabilityExpired,
+
+    #[msg("Amount is zero or exceeds the capability's max_amount")]
+    CapabilityAmountExceeded,
+
+    #[msg("Invalid multisig signer set or threshold")]
+    InvalidMultisig,
+
+    #[msg("Action too large, stale or not matching its accounts")]
+    InvalidAction,
+
+    #[msg("Signer already approved this action")]
+    AlreadyApproved,
+
+    #[msg("Action does not have enough approvals")]
+    ThresholdNotMet,
+
+    #[msg("Invalid guardian, guardian set or threshold")]
+    InvalidGuardianSet,
+
+    #[msg("A recovery is already pending")]
+    RecoveryPending,
+
+    #[msg("No recovery is pending")]
+    NoRecovery,
+
+    #[msg("Recovery not yet approved or still in its challenge period")]
+    RecoveryNotReady,
+
+    #[msg("Transaction was built against vault state older than its last settlement")]
+    StaleBaseSlot,
+
+    #[msg("Withdrawal is over the timelock threshold or still in its delay")]
+    WithdrawalTimelocked,
+
+    #[msg("Inactivity threshold is below the minimum")]
+    InvalidInactivityThreshold,
+
+    #[msg("Vault owner has not been inactive long enough to sweep")]
+    VaultNotInactive,
+
+    #[msg("Invalid session key parameters")]
+    InvalidSessionKey,
+
+    #[msg("Session key has expired")]
+    SessionKeyExpired,
+
+    #[msg("Amount exceeds the session key limit")]
+    SessionKeyAmountExceeded,
+
+    #[msg("Account is not a companion of this vault")]
+    InvalidCompanion,
+
+    #[msg("Recipient is not on the vault allowlist")]
+    RecipientNotAllowed,
+
+    #[msg("Recipient already listed, not listed, or allowlist full")]
+    InvalidRecipientAllowlist,
+
+    #[msg("Invalid expense group, member or participants")]
+    InvalidGroup,
+
+    #[msg("Not every group member has joined")]
+    GroupNotJoined,
+
+    #[msg("Recipient is on the integrator denylist")]
+    RecipientDenied,
+
+    #[msg("Invalid denylist")]
+    InvalidDenylist,
+
+    #[msg("Invalid compliance program")]
+    InvalidComplianceProgram,
+
+    #[msg("Vault is frozen")]
+    VaultFrozen,
+
+    #[msg("Vault is not frozen")]
+    VaultNotFrozen,
+
+    #[msg("Invalid point-of-sale session or payment")]
+    InvalidPosSession,
+
+    #[msg("Point-of-sale session has expired")]
+    PosSessionExpired,
+
+    #[msg("Invalid beneficiary or inheritance period")]
+    InvalidBeneficiary,
+
+    #[msg("Vault has not been inactive for its inheritance period")]
+    VaultStillActive,
+
+    #[msg("Invalid payment intent")]
+    InvalidPaymentIntent,
+
+    #[msg("Payment intent has expired")]
+    PaymentIntentExpired,
+
+    #[msg("Vault co-signer signature required")]
+    CosignerRequired,
+
+    #[msg("Invalid co-signer")]
+    InvalidCosigner,
+
+    #[msg("Invalid receipt mint accounts")]
+    InvalidReceiptAccounts,
+
+    #[msg("Invalid scheduled transfer")]
+    InvalidScheduledTransfer,
+
+    #[msg("Scheduled transfer is not due yet")]
+    ScheduledTransferNotDue,
+
+    #[msg("Rate card charges fees without a payout account")]
+    InvalidRateCard,
+
+    #[msg("Operator fees from an earlier delegation are unsettled")]
+    OperatorFeesOutstanding,
+
+    #[msg("No operator fees to settle")]
+    NoOperatorFees,
+
+    #[msg("Invalid subscription")]
+    InvalidSubscription,
+
+    #[msg("Subscription payment is not due yet")]
+    SubscriptionNotDue,
+
+    #[msg("Invalid payment stream")]
+    InvalidStream,
+
+    #[msg("Nothing has vested since the last withdrawal")]
+    NothingVested,
+
+    #[msg("Invalid transfer batch")]
+    InvalidTransferBatch,
+
+    #[msg("The validator score has no recent observation")]
+    StaleValidatorScore,
+
+    #[msg("Vault already has the current layout")]
+    VaultAlreadyMigrated,
+
+    #[msg("Account is not a vault in a known layout")]
+    InvalidVaultLayout,
+
+    #[msg("Destination vault has a weaker outflow policy than the source")]
+    WeakerDestinationPolicy,
+
+    #[msg("DEX adapter accounts include a vault token account outside the order")]
+    DexAccountNotAllowed,
+
+    #[msg("Fees can be paid from a vault once per transaction")]
+    FeePaymentRepeated,
+
+    #[msg("Rating score out of range")]
+    InvalidRating,
+
+    #[msg("Counterparty already rated")]
+    AlreadyRated,
+
+    #[msg("Escrow is not completed or its rating window has passed")]
+    RatingClosed,
+
+    #[msg("Rating ticket is still open")]
+    RatingTicketOpen,
+
+    #[msg("Vault is too new to vote on this config change")]
+    NotEligibleToVote,
+
+    #[msg("Voting on this config change has closed")]
+    VotingClosed,
+
+    #[msg("Config change is still in its delay")]
+    ConfigChangeTimelocked,
+
+    #[msg("Config change was vetoed by vault owners")]
+    ConfigChangeVetoed,
+
+    #[msg("Config change is still queued")]
+    ConfigChangeOpen,
 }
@@ -0,0 +1,147 @@
+//! Receipt cNFTs.
+//!
+//! When an escrow (`execute_conditional_transfer`) or an invoice
+//! (`fulfill_intent`) completes, the program can mint a compressed
+//! receipt NFT to both parties.  A receipt carries nothing but the
+//! reference hash, in its URI, so it is a portable proof of purchase that
+//! shows up in any wallet without revealing the amount.
+//!
+//! Receipts are minted into the governance-configured
+//! `ProgramConfig::receipt_tree`, whose Bubblegum tree delegate must be
+//! the `RECEIPT_AUTHORITY_SEED` PDA.  Minting is opt-in per instruction:
+//! pass these `ACCOUNTS` as the first remaining accounts, in order:
+//!
+//! 0. the `ProgramConfig`
+//! 1. the Bubblegum tree config (writable)
+//! 2. the receipt merkle tree (writable)
+//! 3. the receipt authority PDA (writable; pays and signs as tree delegate)
+//! 4. the Bubblegum program
+//! 5. the SPL noop log wrapper
+//! 6. the SPL account compression program
+//! 7. the system program
+//!
+//! Bubblegum v1 leaves can't be made non-transferable, so receipts are
+//! soulbound by convention only: wallets should treat a receipt as proof
+//! only while it is still held by the address it was minted to.
+
+use anchor_lang::prelude::*;
+use mpl_bubblegum::instructions::MintV1CpiBuilder;
+use mpl_bubblegum::types::{MetadataArgs, TokenProgramVersion, TokenStandard};
+
+use crate::{ObscuraError, ProgramConfig, RECEIPT_AUTHORITY_SEED};
+
+/// Number of remaining accounts a receipt mint takes.
+pub const ACCOUNTS: usize = 8;
+
+/// Receipt NFT name.
+pub const RECEIPT_NAME: &str = "Obscura Receipt";
+
+/// Receipt NFT symbol.
+pub const RECEIPT_SYMBOL: &str = "OBSR";
+
+/// Receipt URI prefix; the hex reference follows.
+pub const RECEIPT_URI_PREFIX: &str = "obscura:receipt:";
+
+/// Receipt URI for `reference`.
+pub fn receipt_uri(reference: &[u8; 32]) -> String {
+    let mut uri = String::with_capacity(RECEIPT_URI_PREFIX.len() + 64);
+    uri.push_str(RECEIPT_URI_PREFIX);
+    for byte in reference {
+        uri.push_str(&format!("{:02x}", byte));
+    }
+    uri
+}
+
+/// Mint a receipt for `reference` to each of `parties` using the first
+/// `ACCOUNTS` of `accounts`.
+pub fn mint_receipts<'info>(
+    accounts: &[AccountInfo<'info>],
+    parties: [&AccountInfo<'info>; 2],
+    reference: [u8; 32],
+) -> Result<()> {
+    let metadata = MetadataArgs {
+        name: RECEIPT_NAME.to_string(),
+        symbol: RECEIPT_SYMBOL.to_string(),
+        uri: receipt_uri(&reference),
+        seller_fee_basis_points: 0,
+        primary_sale_happened: true,
+        is_mutable: false,
+        edition_nonce: None,
+        token_standard: Some(TokenStandard::NonFungible),
+        collection: None,
+        uses: None,
+        token_program_version: TokenProgramVersion::Original,
+        creators: vec![],
+    };
+    mint_leaves(accounts, |config| config.receipt_tree, &parties, metadata)
+}
+
+/// Mint a leaf with `metadata` to each of `owners` (to the authority PDA
+/// itself if there are none) into the tree `tree` selects from the
+/// `ProgramConfig`, using the first `ACCOUNTS` of `accounts` laid out as
+/// above.
+pub(crate) fn mint_leaves<'info>(
+    accounts: &[AccountInfo<'info>],
+    tree: fn(&ProgramConfig) -> Pubkey,
+    owners: &[&AccountInfo<'info>],
+    metadata: MetadataArgs,
+) -> Result<()> {
+    require!(
+        accounts.len() >= ACCOUNTS,
+        ObscuraError::InvalidReceiptAccounts
+    );
+    let config = &accounts[0];
+    let tree_config = &accounts[1];
+    let merkle_tree = &accounts[2];
+    let authority = &accounts[3];
+    let bubblegum_program = &accounts[4];
+    let log_wrapper = &accounts[5];
+    let compression_program = &accounts[6];
+    let system_program = &accounts[7];
+
+    require_keys_eq!(
+        *config.owner,
+        crate::ID,
+        ObscuraError::InvalidReceiptAccounts
+    );
+    let expected_tree = tree(&ProgramConfig::try_deserialize(
+        &mut &config.try_borrow_data()?[..],
+    )?);
+    require!(
+        expected_tree != Pubkey::default() && merkle_tree.key() == expected_tree,
+        ObscuraError::InvalidReceiptAccounts
+    );
+    require_keys_eq!(
+        bubblegum_program.key(),
+        mpl_bubblegum::ID,
+        ObscuraError::InvalidReceiptAccounts
+    );
+    let (authority_key, bump) = Pubkey::find_program_address(&[RECEIPT_AUTHORITY_SEED], &crate::ID);
+    require_keys_eq!(
+        authority.key(),
+        authority_key,
+        ObscuraError::InvalidReceiptAccounts
+    );
+
+    let authority_only = [authority];
+    let owners = if owners.is_empty() {
+        &authority_only[..]
+    } else {
+        owners
+    };
+    for owner in owners {
+        MintV1CpiBuilder::new(bubblegum_program)
+            .tree_config(tree_config)
+            .leaf_owner(owner)
+            .leaf_delegate(owner)
+            .merkle_tree(merkle_tree)
+            .payer(authority)
+            .tree_creator_or_delegate(authority)
+            .log_wrapper(log_wrapper)
+            .compression_program(compression_program)
+            .system_program(system_program)
+            .metadata(metadata.clone())
+            .invoke_signed(&[&[RECEIPT_AUTHORITY_SEED, &[bump]]])?;
+    }
+    Ok(())
+}
@@ -0,0 +1,94 @@
+//! Token program adapter.
+//!
+//! Vault token paths accept mints from both SPL Token and Token-2022.
+//! Token-2022 transfers go through `invoke_transfer_checked`, which
+//! appends the transfer-hook program and its extra accounts (passed by
+//! the client as remaining accounts) to the CPI, so hooked mints work
+//! without a separate code path.
+//!
+//! Confidential-transfer mints are supported through their public
+//! balance: tokens move in and out of the vault's token account in the
+//! clear and the vault ledger tracks them like any other mint.
+
+use anchor_lang::prelude::*;
+use anchor_spl::token_2022::spl_token_2022::{
+    self,
+    extension::{BaseStateWithExtensions, ExtensionType, StateWithExtensions},
+    onchain,
+};
+use anchor_spl::token_interface::{self, TransferChecked};
+
+use crate::ObscuraError;
+
+/// Token-2022 extensions that break the vault's custody model.
+///
+/// A permanent delegate can move tokens out of the vault's token account
+/// behind the ledger's back, and non-transferable tokens could never be
+/// withdrawn again.
+const UNSUPPORTED_EXTENSIONS: [ExtensionType; 2] = [
+    ExtensionType::PermanentDelegate,
+    ExtensionType::NonTransferable,
+];
+
+/// Reject mints whose extensions the vault cannot custody safely.
+pub fn require_supported_mint(mint: &AccountInfo) -> Result<()> {
+    if *mint.owner != spl_token_2022::ID {
+        return Ok(());
+    }
+
+    let data = mint.try_borrow_data()?;
+    let state = StateWithExtensions::<spl_token_2022::state::Mint>::unpack(&data)?;
+    let extensions = state.get_extension_types()?;
+    require!(
+        !extensions
+            .iter()
+            .any(|ext| UNSUPPORTED_EXTENSIONS.contains(ext)),
+        ObscuraError::UnsupportedMint
+    );
+    Ok(())
+}
+
+/// `transfer_checked` for either token program, forwarding transfer-hook
+/// accounts on Token-2022 mints.
+#[allow(clippy::too_many_arguments)]
+pub fn transfer_checked<'info>(
+    token_program: AccountInfo<'info>,
+    from: AccountInfo<'info>,
+    mint: AccountInfo<'info>,
+    to: AccountInfo<'info>,
+    authority: AccountInfo<'info>,
+    hook_accounts: &[AccountInfo<'info>],
+    amount: u64,
+    decimals: u8,
+    signer_seeds: &[&[&[u8]]],
+) -> Result<()> {
+    if token_program.key() == spl_token_2022::ID {
+        onchain::invoke_transfer_checked(
+            token_program.key,
+            from,
+            mint,
+            to,
+            authority,
+            hook_accounts,
+            amount,
+            decimals,
+            signer_seeds,
+        )?;
+        return Ok(());
+    }
+
+    token_interface::transfer_checked(
+        CpiContext::new_with_signer(
+            token_program,
+            TransferChecked {
+                from,
+                mint,
+                to,
+                authority,
+            },
+            signer_seeds,
+        ),
+        amount,
+        decimals,
+    )
+}
@@ -0,0 +1,46 @@
+//! Crank tip cooldown.
+//!
+//! A caller cranking again within `CRANK_COOLDOWN` of its previous crank
+//! earns half the tip, compounding, until it waits out the cooldown.
+
+use anchor_lang::prelude::Pubkey;
+use obscura_per::{CrankerStats, CRANK_COOLDOWN, SCHEDULED_TRANSFER_TIP};
+
+fn stats() -> CrankerStats {
+    CrankerStats {
+        cranker: Pubkey::new_unique(),
+        last_crank_at: 0,
+        streak: 0,
+    }
+}
+
+#[test]
+fn first_crank_earns_the_full_tip() {
+    let mut stats = stats();
+    assert_eq!(
+        stats.record(1_000, SCHEDULED_TRANSFER_TIP),
+        SCHEDULED_TRANSFER_TIP
+    );
+}
+
+#[test]
+fn rapid_cranks_halve_the_tip() {
+    let mut stats = stats();
+    stats.record(1_000, SCHEDULED_TRANSFER_TIP);
+    assert_eq!(stats.record(1_001, SCHEDULED_TRANSFER_TIP), 5_000);
+    assert_eq!(stats.record(1_002, SCHEDULED_TRANSFER_TIP), 2_500);
+
+    stats.streak = u8::MAX - 1;
+    assert_eq!(stats.record(1_003, SCHEDULED_TRANSFER_TIP), 0);
+}
+
+#[test]
+fn waiting_out_the_cooldown_restores_the_tip() {
+    let mut stats = stats();
+    stats.record(1_000, SCHEDULED_TRANSFER_TIP);
+    stats.record(1_001, SCHEDULED_TRANSFER_TIP);
+    assert_eq!(
+        stats.record(1_001 + CRANK_COOLDOWN, SCHEDULED_TRANSFER_TIP),
+        SCHEDULED_TRANSFER_TIP
+    );
+}
@@ -0,0 +1,75 @@
+//! Property tests for shared expense group settlement.
+//!
+//! Random expenses are logged through `Group::log_expense`, then the
+//! transfers from `Group::settlement` are applied to a model of the
+//! member balances.  We assert that:
+//!
+//! * logged balances always sum to zero (nothing owed out of thin air),
+//! * settlement clears every balance exactly,
+//! * settlement needs at most `members - 1` transfers, each positive and
+//!   from a debtor to a creditor.
+
+use anchor_lang::prelude::Pubkey;
+use obscura_per::{Group, GroupMember, MAX_GROUP_MEMBERS};
+use proptest::prelude::*;
+
+fn group(members: usize) -> Group {
+    Group {
+        creator: Pubkey::new_unique(),
+        group_id: 1,
+        members: (0..members)
+            .map(|_| GroupMember {
+                vault: Pubkey::new_unique(),
+                joined: true,
+                net: 0,
+            })
+            .collect(),
+        expense_count: 0,
+        created_at: 0,
+        settled_at: 0,
+    }
+}
+
+/// (payer index, amount, participants bitmap), reduced modulo the
+/// member count when applied.
+fn expense() -> impl Strategy<Value = (usize, u64, u8)> {
+    (0..MAX_GROUP_MEMBERS, 1..=10_000_000_000u64, 1..=u8::MAX)
+}
+
+proptest! {
+    #[test]
+    fn settlement_clears_balances(
+        members in 2..=MAX_GROUP_MEMBERS,
+        expenses in prop::collection::vec(expense(), 1..32),
+    ) {
+        let mut group = group(members);
+        let mask = ((1u16 << members) - 1) as u8;
+        for (payer, amount, participants) in expenses {
+            let participants = participants & mask;
+            if participants == 0 {
+                continue;
+            }
+            group.log_expense(payer % members, amount, participants).unwrap();
+            prop_assert_eq!(group.members.iter().map(|m| m.net as i128).sum::<i128>(), 0);
+        }
+
+        let mut nets: Vec<i128> = group.members.iter().map(|m| m.net as i128).collect();
+        let transfers = group.settlement();
+        prop_assert!(transfers.len() < members);
+        for (debtor, creditor, amount) in transfers {
+            prop_assert!(amount > 0);
+            prop_assert!(nets[debtor] < 0 && nets[creditor] > 0);
+            nets[debtor] += amount as i128;
+            nets[creditor] -= amount as i128;
+        }
+        prop_assert!(nets.iter().all(|net| *net == 0));
+    }
+
+    #[test]
+    fn unjoined_groups_reject_expenses(members in 2..=MAX_GROUP_MEMBERS, amount in 1..=u64::MAX >> 1) {
+        let mut group = group(members);
+        group.members[members - 1].joined = false;
+        prop_assert!(group.log_expense(0, amount, 1).is_err());
+        prop_assert!(group.members.iter().all(|m| m.net == 0));
+    }
+}
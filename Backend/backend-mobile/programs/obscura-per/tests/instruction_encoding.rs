@@ -0,0 +1,2723 @@
+//! Golden encodings for every instruction (data + account metas).
+//!
+//! Mobile clients ship with hand-written encoders, so an accidental
+//! argument or account reordering in the program bricks them in the
+//! field.  Each test renders one instruction with fixed inputs and
+//! compares it against `tests/fixtures/instruction_encoding/<name>.golden`.
+//!
+//! After an intentional encoding change, regenerate the fixtures with
+//! `UPDATE_GOLDEN=1 cargo test --test instruction_encoding` and review
+//! the diff like any other API change.
+//!
+//! Contexts extended by the ER SDK's `#[delegate]` / `#[commit]` macros
+//! get extra, SDK-owned accounts, so only their instruction data is pinned.
+
+use std::fs;
+use std::path::PathBuf;
+
+use anchor_lang::prelude::*;
+use anchor_lang::{system_program, InstructionData, ToAccountMetas};
+use obscura_per::{
+    accounts, instruction, ActionAccount, BatchTransfer, CapabilityVerb, ConfigChange, IntentOp,
+    PaymentIntent, RateCard, Rating, RecipientAlias, TransferCondition, VaultPolicy,
+    IX_PRIVATE_TRANSFER, PAYMENT_INTENT_VERSION, SCOPE_READ, SCOPE_TRANSFER,
+};
+
+fn key(n: u8) -> Pubkey {
+    Pubkey::new_from_array([n; 32])
+}
+
+fn render(data: &[u8], metas: &[AccountMeta]) -> String {
+    let mut out = String::new();
+    out.push_str("data ");
+    for byte in data {
+        out.push_str(&format!("{:02x}", byte));
+    }
+    out.push('\n');
+    for meta in metas {
+        out.push_str(&format!(
+            "meta {} {} {}\n",
+            meta.pubkey, meta.is_signer, meta.is_writable
+        ));
+    }
+    out
+}
+
+fn check_rendered(name: &str, actual: String) {
+    let path = PathBuf::from(env!("CARGO_MANIFEST_DIR"))
+        .join("tests/fixtures/instruction_encoding")
+        .join(format!("{}.golden", name));
+
+    if std::env::var_os("UPDATE_GOLDEN").is_some() {
+        fs::create_dir_all(path.parent().unwrap()).unwrap();
+        fs::write(&path, actual).unwrap();
+        return;
+    }
+
+    let expected = fs::read_to_string(&path).unwrap_or_else(|_| {
+        panic!(
+            "missing golden {}; run with UPDATE_GOLDEN=1",
+            path.display()
+        )
+    });
+    assert_eq!(
+        actual, expected,
+        "encoding of `{}` changed; if intentional, regenerate with UPDATE_GOLDEN=1",
+        name
+    );
+}
+
+fn check(name: &str, ix: impl InstructionData, accounts: impl ToAccountMetas) {
+    check_rendered(name, render(&ix.data(), &accounts.to_account_metas(None)));
+}
+
+fn check_data(name: &str, ix: impl InstructionData) {
+    check_rendered(name, render(&ix.data(), &[]));
+}
+
+// ---------------------------------------------------------------------------
+// Vault lifecycle
+// ---------------------------------------------------------------------------
+
+#[test]
+fn create_vault() {
+    check(
+        "create_vault",
+        instruction::CreateVault { vault_id: 7 },
+        accounts::CreateVault {
+            vault: key(1),
+            owner: key(2),
+            owner_index: Some(key(52)),
+            integrator: Some(key(68)),
+            system_program: system_program::ID,
+        },
+    );
+}
+
+#[test]
+fn create_custodial_vault() {
+    check(
+        "create_custodial_vault",
+        instruction::CreateCustodialVault {
+            vault_id: 7,
+            claimant: key(45),
+        },
+        accounts::CreateVault {
+            vault: key(1),
+            owner: key(46),
+            owner_index: Some(key(52)),
+            integrator: Some(key(68)),
+            system_program: system_program::ID,
+        },
+    );
+}
+
+#[test]
+fn claim_full_custody() {
+    check(
+        "claim_full_custody",
+        instruction::ClaimFullCustody {},
+        accounts::ClaimFullCustody {
+            vault: key(1),
+            claimant: key(45),
+            new_owner: key(2),
+        },
+    );
+}
+
+#[test]
+fn register_contact_claim() {
+    check(
+        "register_contact_claim",
+        instruction::RegisterContactClaim {
+            contact_hash: [0xcd; 32],
+        },
+        accounts::RegisterContactClaim {
+            contact_claim: key(47),
+            vault: key(1),
+            owner: key(46),
+            system_program: system_program::ID,
+        },
+    );
+}
+
+#[test]
+fn claim_contact_vault() {
+    check(
+        "claim_contact_vault",
+        instruction::ClaimContactVault {},
+        accounts::ClaimContactVault {
+            contact_claim: key(47),
+            vault: key(1),
+            config: key(7),
+            claim_attestor: key(48),
+            new_owner: key(2),
+        },
+    );
+}
+
+#[test]
+fn delegate_vault() {
+    check_data(
+        "delegate_vault",
+        instruction::DelegateVault { validator: key(3) },
+    );
+}
+
+#[test]
+fn private_transfer() {
+    check_data(
+        "private_transfer",
+        instruction::PrivateTransfer {
+            amount: 1_000,
+            recipient: key(4),
+        },
+    );
+}
+
+#[test]
+fn private_transfer_batch() {
+    check_data(
+        "private_transfer_batch",
+        instruction::PrivateTransferBatch {
+            transfers: vec![
+                BatchTransfer {
+                    recipient: key(4),
+                    amount: 1_000,
+                },
+                BatchTransfer {
+                    recipient: key(5),
+                    amount: 2_500,
+                },
+            ],
+        },
+    );
+}
+
+#[test]
+fn execute_intent() {
+    check(
+        "execute_intent",
+        instruction::ExecuteIntent {
+            ops: vec![
+                IntentOp::Transfer {
+                    amount: 1_000,
+                    recipient: key(4),
+                },
+                IntentOp::Memo {
+                    memo: "invoice-42".to_string(),
+                },
+                IntentOp::GrantPermission {
+                    permitted: key(63),
+                    scopes: SCOPE_TRANSFER,
+                    expires_at: None,
+                },
+            ],
+        },
+        accounts::ExecuteIntent {
+            vault: key(1),
+            owner: key(2),
+            cosigner: Some(key(25)),
+            permission: Some(key(5)),
+            recipient_allowlist: Some(key(86)),
+            denylist: Some(key(88)),
+            compliance_program: Some(key(89)),
+            savings_vault: Some(key(53)),
+            split_vault: Some(key(54)),
+        },
+    );
+}
+
+#[test]
+fn commit_vault_state() {
+    check_data("commit_vault_state", instruction::CommitVaultState {});
+}
+
+#[test]
+fn undelegate_vault() {
+    check_data("undelegate_vault", instruction::UndelegateVault {});
+}
+
+#[test]
+fn check_base_slot() {
+    check(
+        "check_base_slot",
+        instruction::CheckBaseSlot {
+            base_slot: 312_000_000,
+        },
+        accounts::CheckBaseSlot { vault: key(1) },
+    );
+}
+
+#[test]
+fn deposit() {
+    check(
+        "deposit",
+        instruction::Deposit { amount: 5_000 },
+        accounts::Deposit {
+            vault: key(1),
+            depositor: key(2),
+            history_view: Some(key(13)),
+            split_vault: Some(key(54)),
+            system_program: system_program::ID,
+        },
+    );
+}
+
+#[test]
+fn withdraw() {
+    check(
+        "withdraw",
+        instruction::Withdraw { amount: 2_500 },
+        accounts::Withdraw {
+            vault: key(1),
+            owner: key(2),
+            cosigner: Some(key(25)),
+            history_view: Some(key(13)),
+            pending_withdrawal: Some(key(83)),
+            recipient_allowlist: Some(key(86)),
+            denylist: Some(key(88)),
+            compliance_program: Some(key(89)),
+            system_program: system_program::ID,
+        },
+    );
+}
+
+#[test]
+fn rebalance_own_vaults() {
+    check(
+        "rebalance_own_vaults",
+        instruction::RebalanceOwnVaults { amount: 4_000 },
+        accounts::RebalanceOwnVaults {
+            from_vault: key(1),
+            to_vault: key(18),
+            owner: key(2),
+            cosigner: Some(key(25)),
+            recipient_allowlist: Some(key(86)),
+            to_recipient_allowlist: Some(key(103)),
+            denylist: Some(key(88)),
+            compliance_program: Some(key(89)),
+        },
+    );
+}
+
+#[test]
+fn internal_transfer() {
+    check(
+        "internal_transfer",
+        instruction::InternalTransfer { amount: 3_000 },
+        accounts::InternalTransfer {
+            from_vault: key(1),
+            to_vault: key(18),
+            owner: key(2),
+            cosigner: Some(key(25)),
+            recipient_allowlist: Some(key(86)),
+            denylist: Some(key(88)),
+            compliance_program: Some(key(89)),
+        },
+    );
+}
+
+#[test]
+fn create_permission() {
+    check(
+        "create_permission",
+        instruction::CreatePermission {
+            permitted_pubkey: key(4),
+            scopes: SCOPE_READ | SCOPE_TRANSFER,
+            expires_at: Some(1_767_225_600),
+        },
+        accounts::CreatePermission {
+            permission: key(5),
+            vault: key(1),
+            owner: key(2),
+            acl_permission: key(66),
+            access_control_program: key(67),
+            system_program: system_program::ID,
+        },
+    );
+}
+
+#[test]
+fn create_permissions_batch() {
+    check(
+        "create_permissions_batch",
+        instruction::CreatePermissionsBatch {
+            permitted: vec![key(4), key(63)],
+            scopes: SCOPE_READ,
+            expires_at: None,
+        },
+        accounts::CreatePermissionsBatch {
+            vault: key(1),
+            owner: key(2),
+            access_control_program: key(67),
+            system_program: system_program::ID,
+        },
+    );
+}
+
+#[test]
+fn suspend_permission() {
+    check(
+        "suspend_permission",
+        instruction::SuspendPermission {},
+        accounts::UpdatePermission {
+            permission: key(5),
+            vault: key(1),
+            owner: key(2),
+            acl_permission: key(66),
+            access_control_program: key(67),
+            system_program: system_program::ID,
+        },
+    );
+}
+
+#[test]
+fn restore_permission() {
+    check(
+        "restore_permission",
+        instruction::RestorePermission {},
+        accounts::UpdatePermission {
+            permission: key(5),
+            vault: key(1),
+            owner: key(2),
+            acl_permission: key(66),
+            access_control_program: key(67),
+            system_program: system_program::ID,
+        },
+    );
+}
+
+#[test]
+fn revoke_permission() {
+    check(
+        "revoke_permission",
+        instruction::RevokePermission {},
+        accounts::RevokePermission {
+            permission: key(5),
+            vault: key(1),
+            owner: key(64),
+            granted_by: key(2),
+            acl_permission: key(66),
+            access_control_program: key(67),
+            system_program: system_program::ID,
+        },
+    );
+}
+
+#[test]
+fn set_permission_manager() {
+    check(
+        "set_permission_manager",
+        instruction::SetPermissionManager {
+            permission_manager: Some(key(64)),
+        },
+        accounts::SetVaultPolicy {
+            vault: key(1),
+            owner: key(2),
+        },
+    );
+}
+
+#[test]
+fn export_policy() {
+    check(
+        "export_policy",
+        instruction::ExportPolicy {},
+        accounts::ExportPolicy { vault: key(1) },
+    );
+}
+
+#[test]
+fn apply_policy() {
+    check(
+        "apply_policy",
+        instruction::ApplyPolicy {
+            policy: VaultPolicy {
+                min_validator_score: 7_000,
+                velocity_factor_pct: 300,
+                velocity_cosigner: Some(key(25)),
+                fee_payment_cap: 50_000,
+                spending_limit_per_epoch: 5_000_000_000,
+                withdrawal_daily_cap: 2_000_000_000,
+                withdrawal_timelock_threshold: 10_000_000_000,
+                permission_manager: Some(key(64)),
+            },
+            document_hash: [0xcd; 32],
+        },
+        accounts::SetVaultPolicy {
+            vault: key(1),
+            owner: key(2),
+        },
+    );
+}
+
+#[test]
+fn close_expired_permission() {
+    check(
+        "close_expired_permission",
+        instruction::CloseExpiredPermission {},
+        accounts::CloseExpiredPermission {
+            permission: key(5),
+            vault: key(1),
+            granted_by: key(2),
+            acl_permission: key(66),
+            access_control_program: key(67),
+            system_program: system_program::ID,
+        },
+    );
+}
+
+#[test]
+fn init_owner_index() {
+    check(
+        "init_owner_index",
+        instruction::InitOwnerIndex {},
+        accounts::InitOwnerIndex {
+            owner_index: key(52),
+            owner: key(2),
+            system_program: system_program::ID,
+        },
+    );
+}
+
+#[test]
+fn index_vault() {
+    check(
+        "index_vault",
+        instruction::IndexVault {},
+        accounts::IndexVault {
+            owner_index: key(52),
+            vault: key(1),
+            owner: key(2),
+        },
+    );
+}
+
+#[test]
+fn unindex_vault() {
+    check(
+        "unindex_vault",
+        instruction::UnindexVault { vault_id: 7 },
+        accounts::UpdateOwnerIndex {
+            owner_index: key(52),
+            owner: key(2),
+        },
+    );
+}
+
+#[test]
+fn set_vault_metadata() {
+    check(
+        "set_vault_metadata",
+        instruction::SetVaultMetadata {
+            label: "Savings".to_string(),
+            encrypted_memo: vec![0xde, 0xad, 0xbe, 0xef],
+        },
+        accounts::SetVaultMetadata {
+            metadata: key(51),
+            vault: key(1),
+            owner: key(2),
+            system_program: system_program::ID,
+        },
+    );
+}
+
+// ---------------------------------------------------------------------------
+// Delegated authorities
+// ---------------------------------------------------------------------------
+
+#[test]
+fn grant_delegated_authority() {
+    check(
+        "grant_delegated_authority",
+        instruction::GrantDelegatedAuthority {
+            delegate: key(10),
+            allowed_instructions: 0b11,
+        },
+        accounts::GrantDelegatedAuthority {
+            delegated_authority: key(16),
+            vault: key(1),
+            owner: key(2),
+            system_program: system_program::ID,
+        },
+    );
+}
+
+#[test]
+fn revoke_delegated_authority() {
+    check(
+        "revoke_delegated_authority",
+        instruction::RevokeDelegatedAuthority {},
+        accounts::RevokeDelegatedAuthority {
+            delegated_authority: key(16),
+            vault: key(1),
+            owner: key(2),
+        },
+    );
+}
+
+#[test]
+fn create_session_key() {
+    check(
+        "create_session_key",
+        instruction::CreateSessionKey {
+            key: key(84),
+            expires_at: 1_767_225_600,
+            max_amount: 100_000_000,
+            allowed_instructions: IX_PRIVATE_TRANSFER,
+        },
+        accounts::CreateSessionKey {
+            session_key: key(85),
+            vault: key(1),
+            owner: key(2),
+            system_program: system_program::ID,
+        },
+    );
+}
+
+#[test]
+fn revoke_session_key() {
+    check(
+        "revoke_session_key",
+        instruction::RevokeSessionKey {},
+        accounts::RevokeSessionKey {
+            session_key: key(85),
+            vault: key(1),
+            owner: key(2),
+        },
+    );
+}
+
+#[test]
+fn mint_capability() {
+    check(
+        "mint_capability",
+        instruction::MintCapability {
+            capability_id: 3,
+            service: key(72),
+            verb: CapabilityVerb::TopUpVault,
+            object: key(18),
+            max_amount: 50_000_000,
+            expires_at: 1_767_225_600,
+        },
+        accounts::MintCapability {
+            capability: key(73),
+            vault: key(1),
+            owner: key(2),
+            cosigner: Some(key(25)),
+            system_program: system_program::ID,
+        },
+    );
+}
+
+#[test]
+fn execute_capability() {
+    check(
+        "execute_capability",
+        instruction::ExecuteCapability { amount: 40_000_000 },
+        accounts::ExecuteCapability {
+            capability: key(73),
+            vault: key(1),
+            service: key(72),
+            owner: key(2),
+            recipient: Some(key(74)),
+            recipient_vault: Some(key(18)),
+            recipient_allowlist: Some(key(86)),
+            denylist: Some(key(88)),
+            compliance_program: Some(key(89)),
+        },
+    );
+}
+
+#[test]
+fn revoke_capability() {
+    check(
+        "revoke_capability",
+        instruction::RevokeCapability {},
+        accounts::RevokeCapability {
+            capability: key(73),
+            vault: key(1),
+            owner: key(2),
+        },
+    );
+}
+
+// ---------------------------------------------------------------------------
+// Devices & liveness
+// ---------------------------------------------------------------------------
+
+#[test]
+fn register_device() {
+    check(
+        "register_device",
+        instruction::RegisterDevice { device: key(14) },
+        accounts::RegisterDevice {
+            device_key: key(15),
+            vault: key(1),
+            owner: key(2),
+            system_program: system_program::ID,
+        },
+    );
+}
+
+#[test]
+fn remove_device() {
+    check(
+        "remove_device",
+        instruction::RemoveDevice {},
+        accounts::RemoveDevice {
+            device_key: key(15),
+            vault: key(1),
+            owner: key(2),
+        },
+    );
+}
+
+#[test]
+fn heartbeat() {
+    check(
+        "heartbeat",
+        instruction::Heartbeat {},
+        accounts::Heartbeat {
+            vault: key(1),
+            authority: key(14),
+            device_key: Some(key(15)),
+            delegated_authority: Some(key(16)),
+        },
+    );
+}
+
+#[test]
+fn post_session_handoff() {
+    check(
+        "post_session_handoff",
+        instruction::PostSessionHandoff {
+            bundle: vec![0x5e, 0x55, 0x10, 0x4e],
+        },
+        accounts::PostSessionHandoff {
+            handoff: key(71),
+            vault: key(1),
+            from_device: key(14),
+            from_device_key: key(15),
+            to_device_key: key(70),
+            system_program: system_program::ID,
+        },
+    );
+}
+
+#[test]
+fn acknowledge_session_handoff() {
+    check(
+        "acknowledge_session_handoff",
+        instruction::AcknowledgeSessionHandoff {},
+        accounts::AcknowledgeSessionHandoff {
+            handoff: key(71),
+            vault: key(1),
+            to_device: key(69),
+            to_device_key: key(70),
+            from_device: key(14),
+        },
+    );
+}
+
+#[test]
+fn close_session_handoff() {
+    check(
+        "close_session_handoff",
+        instruction::CloseSessionHandoff {},
+        accounts::CloseSessionHandoff {
+            handoff: key(71),
+            from_device: key(14),
+            closer: key(14),
+        },
+    );
+}
+
+// ---------------------------------------------------------------------------
+// History view
+// ---------------------------------------------------------------------------
+
+#[test]
+fn init_history_view() {
+    check(
+        "init_history_view",
+        instruction::InitHistoryView {},
+        accounts::InitHistoryView {
+            history_view: key(13),
+            vault: key(1),
+            owner: key(2),
+            system_program: system_program::ID,
+        },
+    );
+}
+
+#[test]
+fn delegate_history_view() {
+    check_data(
+        "delegate_history_view",
+        instruction::DelegateHistoryView { validator: key(3) },
+    );
+}
+
+// ---------------------------------------------------------------------------
+// Governance
+// ---------------------------------------------------------------------------
+
+#[test]
+fn initialize_config() {
+    check(
+        "initialize_config",
+        instruction::InitializeConfig { governance: key(6) },
+        accounts::InitializeConfig {
+            config: key(7),
+            program: key(8),
+            program_data: key(9),
+            authority: key(2),
+            system_program: system_program::ID,
+        },
+    );
+}
+
+#[test]
+fn set_keeper() {
+    check(
+        "set_keeper",
+        instruction::SetKeeper { keeper: key(10) },
+        accounts::UpdateConfig {
+            config: key(7),
+            governance: key(6),
+        },
+    );
+}
+
+#[test]
+fn set_dex_adapter() {
+    check(
+        "set_dex_adapter",
+        instruction::SetDexAdapter {
+            dex_adapter: key(55),
+        },
+        accounts::UpdateConfig {
+            config: key(7),
+            governance: key(6),
+        },
+    );
+}
+
+#[test]
+fn set_inactivity_undelegate_after() {
+    check(
+        "set_inactivity_undelegate_after",
+        instruction::SetInactivityUndelegateAfter {
+            inactivity_undelegate_after: 30 * 24 * 60 * 60,
+        },
+        accounts::UpdateConfig {
+            config: key(7),
+            governance: key(6),
+        },
+    );
+}
+
+#[test]
+fn set_receipt_tree() {
+    check(
+        "set_receipt_tree",
+        instruction::SetReceiptTree {
+            receipt_tree: key(96),
+        },
+        accounts::UpdateConfig {
+            config: key(7),
+            governance: key(6),
+        },
+    );
+}
+
+#[test]
+fn set_attestation_tree() {
+    check(
+        "set_attestation_tree",
+        instruction::SetAttestationTree {
+            attestation_tree: key(106),
+        },
+        accounts::UpdateConfig {
+            config: key(7),
+            governance: key(6),
+        },
+    );
+}
+
+#[test]
+fn set_claim_attestor() {
+    check(
+        "set_claim_attestor",
+        instruction::SetClaimAttestor {
+            claim_attestor: key(48),
+        },
+        accounts::UpdateConfig {
+            config: key(7),
+            governance: key(6),
+        },
+    );
+}
+
+#[test]
+fn queue_config_change() {
+    check(
+        "queue_config_change",
+        instruction::QueueConfigChange {
+            change_id: 4,
+            change: ConfigChange::FeatureFlags { feature_flags: 5 },
+        },
+        accounts::QueueConfigChange {
+            config: key(7),
+            config_change: key(107),
+            governance: key(6),
+            system_program: system_program::ID,
+        },
+    );
+}
+
+#[test]
+fn vote_on_config_change() {
+    check(
+        "vote_on_config_change",
+        instruction::VoteOnConfigChange { support: true },
+        accounts::VoteOnConfigChange {
+            vault: key(1),
+            config_change: key(107),
+            vote_record: key(108),
+            owner: key(2),
+            system_program: system_program::ID,
+        },
+    );
+}
+
+#[test]
+fn execute_config_change() {
+    check(
+        "execute_config_change",
+        instruction::ExecuteConfigChange {},
+        accounts::ExecuteConfigChange {
+            config: key(7),
+            config_change: key(107),
+            governance: key(6),
+        },
+    );
+}
+
+#[test]
+fn cancel_config_change() {
+    check(
+        "cancel_config_change",
+        instruction::CancelConfigChange {},
+        accounts::CancelConfigChange {
+            config: key(7),
+            config_change: key(107),
+            governance: key(6),
+        },
+    );
+}
+
+#[test]
+fn close_vote_record() {
+    check(
+        "close_vote_record",
+        instruction::CloseVoteRecord {},
+        accounts::CloseVoteRecord {
+            vote_record: key(108),
+            config_change: key(107),
+            voter: key(2),
+        },
+    );
+}
+
+#[test]
+fn init_safety_fund() {
+    check(
+        "init_safety_fund",
+        instruction::InitSafetyFund {},
+        accounts::InitSafetyFund {
+            safety_fund: key(50),
+            config: key(7),
+            governance: key(6),
+            system_program: system_program::ID,
+        },
+    );
+}
+
+#[test]
+fn set_feature_flags() {
+    check(
+        "set_feature_flags",
+        instruction::SetFeatureFlags {
+            feature_flags: 0b101,
+        },
+        accounts::UpdateConfig {
+            config: key(7),
+            governance: key(6),
+        },
+    );
+}
+
+#[test]
+fn publish_program_version() {
+    check(
+        "publish_program_version",
+        instruction::PublishProgramVersion {
+            major: 1,
+            minor: 2,
+            patch: 3,
+            features_hash: [0xab; 32],
+        },
+        accounts::PublishProgramVersion {
+            version_info: key(26),
+            config: key(7),
+            program: key(8),
+            program_data: key(9),
+            governance: key(6),
+            system_program: system_program::ID,
+        },
+    );
+}
+
+#[test]
+fn register_validator() {
+    check(
+        "register_validator",
+        instruction::RegisterValidator {
+            validator: key(3),
+            sdk_version: 1,
+        },
+        accounts::RegisterValidator {
+            validator_info: key(11),
+            config: key(7),
+            governance: key(6),
+            system_program: system_program::ID,
+        },
+    );
+}
+
+#[test]
+fn set_validator_sdk_version() {
+    check(
+        "set_validator_sdk_version",
+        instruction::SetValidatorSdkVersion { sdk_version: 1 },
+        accounts::UpdateValidatorInfo {
+            validator_info: key(11),
+            config: key(7),
+            governance: key(6),
+        },
+    );
+}
+
+#[test]
+fn set_rate_card() {
+    check(
+        "set_rate_card",
+        instruction::SetRateCard {
+            rate_card: RateCard {
+                commit_fee: 5_000,
+                monthly_delegation_fee: 1_000_000_000,
+                payout: key(99),
+            },
+        },
+        accounts::SetRateCard {
+            validator_info: key(11),
+            validator: key(3),
+        },
+    );
+}
+
+#[test]
+fn settle_operator_fees() {
+    check(
+        "settle_operator_fees",
+        instruction::SettleOperatorFees {},
+        accounts::SettleOperatorFees {
+            vault: key(1),
+            payout: key(99),
+        },
+    );
+}
+
+#[test]
+fn mark_validator_defunct() {
+    check(
+        "mark_validator_defunct",
+        instruction::MarkValidatorDefunct {},
+        accounts::UpdateValidatorInfo {
+            validator_info: key(11),
+            config: key(7),
+            governance: key(6),
+        },
+    );
+}
+
+#[test]
+fn admin_force_settle() {
+    check(
+        "admin_force_settle",
+        instruction::AdminForceSettle {},
+        accounts::AdminForceSettle {
+            vault: key(1),
+            validator_info: key(11),
+            config: key(7),
+            governance: key(6),
+        },
+    );
+}
+
+#[test]
+fn check_undelegate_eligibility() {
+    check(
+        "check_undelegate_eligibility",
+        instruction::CheckUndelegateEligibility {},
+        accounts::CheckUndelegateEligibility {
+            vault: key(1),
+            config: key(7),
+        },
+    );
+}
+
+#[test]
+fn sweep_inactive_vault() {
+    check_data("sweep_inactive_vault", instruction::SweepInactiveVault {});
+}
+
+// ---------------------------------------------------------------------------
+// Invoiced billing
+// ---------------------------------------------------------------------------
+
+#[test]
+fn set_invoiced_billing() {
+    check(
+        "set_invoiced_billing",
+        instruction::SetInvoicedBilling { fee_bps: 25 },
+        accounts::SetInvoicedBilling {
+            vault: key(1),
+            owner: key(2),
+            config: key(7),
+            governance: key(6),
+        },
+    );
+}
+
+#[test]
+fn close_fee_statement() {
+    check(
+        "close_fee_statement",
+        instruction::CloseFeeStatement { period: 24_321 },
+        accounts::CloseFeeStatement {
+            fee_statement: key(17),
+            vault: key(1),
+            config: key(7),
+            governance: key(6),
+            integrator: Some(key(68)),
+            system_program: system_program::ID,
+        },
+    );
+}
+
+#[test]
+fn register_integrator() {
+    check(
+        "register_integrator",
+        instruction::RegisterIntegrator {
+            app_id: 9,
+            share_bps: 2_000,
+        },
+        accounts::RegisterIntegrator {
+            integrator: key(68),
+            payout_vault: key(1),
+            authority: key(2),
+            config: key(7),
+            governance: key(6),
+            system_program: system_program::ID,
+        },
+    );
+}
+
+#[test]
+fn set_integrator_share() {
+    check(
+        "set_integrator_share",
+        instruction::SetIntegratorShare { share_bps: 1_500 },
+        accounts::UpdateIntegrator {
+            integrator: key(68),
+            config: key(7),
+            governance: key(6),
+        },
+    );
+}
+
+#[test]
+fn settle_integrator_fees() {
+    check(
+        "settle_integrator_fees",
+        instruction::SettleIntegratorFees {},
+        accounts::SettleIntegratorFees {
+            integrator: key(68),
+            payout_vault: key(1),
+            config: key(7),
+            governance: key(6),
+            system_program: system_program::ID,
+        },
+    );
+}
+
+#[test]
+fn add_denied_recipient() {
+    check(
+        "add_denied_recipient",
+        instruction::AddDeniedRecipient { recipient: key(4) },
+        accounts::UpdateDenylist {
+            denylist: key(88),
+            integrator: key(68),
+            authority: key(2),
+            system_program: system_program::ID,
+        },
+    );
+}
+
+#[test]
+fn remove_denied_recipient() {
+    check(
+        "remove_denied_recipient",
+        instruction::RemoveDeniedRecipient { recipient: key(4) },
+        accounts::UpdateDenylist {
+            denylist: key(88),
+            integrator: key(68),
+            authority: key(2),
+            system_program: system_program::ID,
+        },
+    );
+}
+
+#[test]
+fn set_compliance_program() {
+    check(
+        "set_compliance_program",
+        instruction::SetComplianceProgram {
+            compliance_program: key(89),
+        },
+        accounts::UpdateDenylist {
+            denylist: key(88),
+            integrator: key(68),
+            authority: key(2),
+            system_program: system_program::ID,
+        },
+    );
+}
+
+// ---------------------------------------------------------------------------
+// Validator scoring
+// ---------------------------------------------------------------------------
+
+#[test]
+fn submit_validator_observation() {
+    check(
+        "submit_validator_observation",
+        instruction::SubmitValidatorObservation {
+            commit_latency_ms: 80,
+            uptime_bps: 9_950,
+        },
+        accounts::SubmitValidatorObservation {
+            validator_score: key(12),
+            validator_info: key(11),
+            config: key(7),
+            keeper: key(10),
+            system_program: system_program::ID,
+        },
+    );
+}
+
+#[test]
+fn set_min_validator_score() {
+    check(
+        "set_min_validator_score",
+        instruction::SetMinValidatorScore { min_score: 8_000 },
+        accounts::SetVaultPolicy {
+            vault: key(1),
+            owner: key(2),
+        },
+    );
+}
+
+// ---------------------------------------------------------------------------
+// Savings goals
+// ---------------------------------------------------------------------------
+
+#[test]
+fn create_goal() {
+    check(
+        "create_goal",
+        instruction::CreateGoal {
+            goal_id: 1,
+            amount: 100_000,
+            unlock_at: 1_800_000_000,
+        },
+        accounts::CreateGoal {
+            goal: key(49),
+            vault: key(1),
+            owner: key(2),
+            system_program: system_program::ID,
+        },
+    );
+}
+
+#[test]
+fn release_goal() {
+    check(
+        "release_goal",
+        instruction::ReleaseGoal {},
+        accounts::CloseGoal {
+            goal: key(49),
+            vault: key(1),
+            owner: key(2),
+        },
+    );
+}
+
+#[test]
+fn break_goal() {
+    check(
+        "break_goal",
+        instruction::BreakGoal {},
+        accounts::BreakGoal {
+            goal: key(49),
+            vault: key(1),
+            safety_fund: key(50),
+            owner: key(2),
+        },
+    );
+}
+
+// ---------------------------------------------------------------------------
+// Receive-only mode
+// ---------------------------------------------------------------------------
+
+#[test]
+fn enable_receive_only() {
+    check(
+        "enable_receive_only",
+        instruction::EnableReceiveOnly {},
+        accounts::SetVaultPolicy {
+            vault: key(1),
+            owner: key(2),
+        },
+    );
+}
+
+#[test]
+fn request_receive_only_unlock() {
+    check(
+        "request_receive_only_unlock",
+        instruction::RequestReceiveOnlyUnlock {},
+        accounts::SetVaultPolicy {
+            vault: key(1),
+            owner: key(2),
+        },
+    );
+}
+
+#[test]
+fn lift_receive_only() {
+    check(
+        "lift_receive_only",
+        instruction::LiftReceiveOnly {},
+        accounts::SetVaultPolicy {
+            vault: key(1),
+            owner: key(2),
+        },
+    );
+}
+
+// ---------------------------------------------------------------------------
+// Emergency freeze
+// ---------------------------------------------------------------------------
+
+#[test]
+fn set_freeze_guardian() {
+    check(
+        "set_freeze_guardian",
+        instruction::SetFreezeGuardian { guardian: key(90) },
+        accounts::SetVaultPolicy {
+            vault: key(1),
+            owner: key(2),
+        },
+    );
+}
+
+#[test]
+fn freeze_vault() {
+    check(
+        "freeze_vault",
+        instruction::FreezeVault {},
+        accounts::FreezeVault {
+            vault: key(1),
+            authority: key(90),
+        },
+    );
+}
+
+#[test]
+fn unfreeze_vault() {
+    check(
+        "unfreeze_vault",
+        instruction::UnfreezeVault {},
+        accounts::FreezeVault {
+            vault: key(1),
+            authority: key(90),
+        },
+    );
+}
+
+// ---------------------------------------------------------------------------
+// Co-signer
+// ---------------------------------------------------------------------------
+
+#[test]
+fn set_cosigner() {
+    check(
+        "set_cosigner",
+        instruction::SetCosigner { cosigner: key(25) },
+        accounts::SetCosigner {
+            vault: key(1),
+            owner: key(2),
+            current_cosigner: Some(key(95)),
+        },
+    );
+}
+
+// ---------------------------------------------------------------------------
+// Velocity policy
+// ---------------------------------------------------------------------------
+
+#[test]
+fn set_velocity_policy() {
+    check(
+        "set_velocity_policy",
+        instruction::SetVelocityPolicy {
+            factor_pct: 300,
+            cosigner: Some(key(25)),
+        },
+        accounts::SetVaultPolicy {
+            vault: key(1),
+            owner: key(2),
+        },
+    );
+}
+
+#[test]
+fn request_velocity_override() {
+    check(
+        "request_velocity_override",
+        instruction::RequestVelocityOverride {},
+        accounts::SetVaultPolicy {
+            vault: key(1),
+            owner: key(2),
+        },
+    );
+}
+
+#[test]
+fn set_spending_limit() {
+    check(
+        "set_spending_limit",
+        instruction::SetSpendingLimit {
+            amount_per_epoch: 5_000_000_000,
+        },
+        accounts::SetVaultPolicy {
+            vault: key(1),
+            owner: key(2),
+        },
+    );
+}
+
+#[test]
+fn set_token_spending_limit() {
+    check(
+        "set_token_spending_limit",
+        instruction::SetTokenSpendingLimit {
+            amount_per_epoch: 1_000_000,
+        },
+        accounts::SetTokenSpendingLimit {
+            vault: key(1),
+            token_balance: key(19),
+            owner: key(2),
+        },
+    );
+}
+
+#[test]
+fn set_withdrawal_limit() {
+    check(
+        "set_withdrawal_limit",
+        instruction::SetWithdrawalLimit {
+            daily_cap: 2_000_000_000,
+        },
+        accounts::SetVaultPolicy {
+            vault: key(1),
+            owner: key(2),
+        },
+    );
+}
+
+#[test]
+fn request_withdrawal_override() {
+    check(
+        "request_withdrawal_override",
+        instruction::RequestWithdrawalOverride {},
+        accounts::SetVaultPolicy {
+            vault: key(1),
+            owner: key(2),
+        },
+    );
+}
+
+#[test]
+fn set_withdrawal_timelock() {
+    check(
+        "set_withdrawal_timelock",
+        instruction::SetWithdrawalTimelock {
+            threshold: 10_000_000_000,
+        },
+        accounts::SetVaultPolicy {
+            vault: key(1),
+            owner: key(2),
+        },
+    );
+}
+
+#[test]
+fn execute_pending_withdrawal() {
+    check(
+        "execute_pending_withdrawal",
+        instruction::ExecutePendingWithdrawal {},
+        accounts::ExecutePendingWithdrawal {
+            vault: key(1),
+            pending_withdrawal: key(83),
+            owner: key(2),
+            cosigner: Some(key(25)),
+            history_view: Some(key(13)),
+            recipient_allowlist: Some(key(86)),
+            denylist: Some(key(88)),
+            compliance_program: Some(key(89)),
+        },
+    );
+}
+
+#[test]
+fn cancel_pending_withdrawal() {
+    check(
+        "cancel_pending_withdrawal",
+        instruction::CancelPendingWithdrawal {},
+        accounts::CancelPendingWithdrawal {
+            vault: key(1),
+            pending_withdrawal: key(83),
+            owner: key(2),
+        },
+    );
+}
+
+#[test]
+fn set_allowlist_enabled() {
+    check(
+        "set_allowlist_enabled",
+        instruction::SetAllowlistEnabled { enabled: true },
+        accounts::SetVaultPolicy {
+            vault: key(1),
+            owner: key(2),
+        },
+    );
+}
+
+#[test]
+fn add_allowed_recipient() {
+    check(
+        "add_allowed_recipient",
+        instruction::AddAllowedRecipient { recipient: key(4) },
+        accounts::UpdateRecipientAllowlist {
+            recipient_allowlist: key(86),
+            vault: key(1),
+            owner: key(2),
+            system_program: system_program::ID,
+        },
+    );
+}
+
+#[test]
+fn remove_allowed_recipient() {
+    check(
+        "remove_allowed_recipient",
+        instruction::RemoveAllowedRecipient { recipient: key(4) },
+        accounts::UpdateRecipientAllowlist {
+            recipient_allowlist: key(86),
+            vault: key(1),
+            owner: key(2),
+            system_program: system_program::ID,
+        },
+    );
+}
+
+#[test]
+fn set_round_up_rule() {
+    check(
+        "set_round_up_rule",
+        instruction::SetRoundUpRule {
+            increment: 1_000_000,
+        },
+        accounts::SetRoundUpRule {
+            vault: key(1),
+            savings_vault: Some(key(53)),
+            owner: key(2),
+        },
+    );
+}
+
+#[test]
+fn set_split_rule() {
+    check(
+        "set_split_rule",
+        instruction::SetSplitRule {
+            bps: 100,
+            inbound: false,
+        },
+        accounts::SetSplitRule {
+            vault: key(1),
+            split_vault: Some(key(54)),
+            owner: key(2),
+        },
+    );
+}
+
+// ---------------------------------------------------------------------------
+// Fee payment from vault
+// ---------------------------------------------------------------------------
+
+#[test]
+fn set_fee_payment_cap() {
+    check(
+        "set_fee_payment_cap",
+        instruction::SetFeePaymentCap { cap: 50_000 },
+        accounts::SetVaultPolicy {
+            vault: key(1),
+            owner: key(2),
+        },
+    );
+}
+
+#[test]
+fn pay_fees_from_vault() {
+    check(
+        "pay_fees_from_vault",
+        instruction::PayFeesFromVault { amount: 15_000 },
+        accounts::PayFeesFromVault {
+            vault: key(1),
+            owner: key(2),
+            cosigner: Some(key(25)),
+            fee_payer: key(43),
+            sysvar_instructions: key(36),
+        },
+    );
+}
+
+#[test]
+fn open_relayer_ledger() {
+    check(
+        "open_relayer_ledger",
+        instruction::OpenRelayerLedger {
+            relayer: key(43),
+            cap: 1_000_000,
+        },
+        accounts::OpenRelayerLedger {
+            relayer_ledger: key(44),
+            vault: key(1),
+            owner: key(2),
+            system_program: system_program::ID,
+        },
+    );
+}
+
+#[test]
+fn accrue_relayer_fee() {
+    check(
+        "accrue_relayer_fee",
+        instruction::AccrueRelayerFee { amount: 5_000 },
+        accounts::AccrueRelayerFee {
+            relayer_ledger: key(44),
+            vault: key(1),
+            owner: key(2),
+            relayer: key(43),
+        },
+    );
+}
+
+#[test]
+fn settle_relayer_fees() {
+    check(
+        "settle_relayer_fees",
+        instruction::SettleRelayerFees {},
+        accounts::RelayerClaim {
+            relayer_ledger: key(44),
+            vault: key(1),
+            relayer: key(43),
+        },
+    );
+}
+
+#[test]
+fn dispute_relayer_fees() {
+    check(
+        "dispute_relayer_fees",
+        instruction::DisputeRelayerFees {},
+        accounts::DisputeRelayerFees {
+            relayer_ledger: key(44),
+            vault: key(1),
+            owner: key(2),
+        },
+    );
+}
+
+#[test]
+fn claim_relayer_fees() {
+    check(
+        "claim_relayer_fees",
+        instruction::ClaimRelayerFees {},
+        accounts::RelayerClaim {
+            relayer_ledger: key(44),
+            vault: key(1),
+            relayer: key(43),
+        },
+    );
+}
+
+// ---------------------------------------------------------------------------
+// Upgrade acknowledgment
+// ---------------------------------------------------------------------------
+
+#[test]
+fn acknowledge_program_version() {
+    check(
+        "acknowledge_program_version",
+        instruction::AcknowledgeProgramVersion { version: 1 },
+        accounts::SetVaultPolicy {
+            vault: key(1),
+            owner: key(2),
+        },
+    );
+}
+
+#[test]
+fn migrate_vault() {
+    check(
+        "migrate_vault",
+        instruction::MigrateVault {},
+        accounts::MigrateVault {
+            vault: key(1),
+            payer: key(2),
+            system_program: system_program::ID,
+        },
+    );
+}
+
+// ---------------------------------------------------------------------------
+// SPL tokens
+// ---------------------------------------------------------------------------
+
+#[test]
+fn deposit_spl() {
+    check(
+        "deposit_spl",
+        instruction::DepositSpl { amount: 1_000_000 },
+        accounts::DepositSpl {
+            vault: key(1),
+            token_balance: key(19),
+            mint: key(20),
+            vault_token_account: key(21),
+            depositor_token_account: key(22),
+            depositor: key(2),
+            token_program: key(23),
+            associated_token_program: key(24),
+            system_program: system_program::ID,
+        },
+    );
+}
+
+#[test]
+fn withdraw_spl() {
+    check(
+        "withdraw_spl",
+        instruction::WithdrawSpl { amount: 500_000 },
+        accounts::WithdrawSpl {
+            vault: key(1),
+            token_balance: key(19),
+            mint: key(20),
+            vault_token_account: key(21),
+            owner_token_account: key(22),
+            owner: key(2),
+            cosigner: Some(key(25)),
+            recipient_allowlist: Some(key(86)),
+            denylist: Some(key(88)),
+            compliance_program: Some(key(89)),
+            token_program: key(23),
+        },
+    );
+}
+
+#[test]
+fn deposit_wsol() {
+    check(
+        "deposit_wsol",
+        instruction::DepositWsol { amount: 7_500 },
+        accounts::DepositWsol {
+            vault: key(1),
+            depositor_wsol: key(27),
+            depositor: key(2),
+            token_program: key(23),
+            system_program: system_program::ID,
+        },
+    );
+}
+
+#[test]
+fn withdraw_wsol() {
+    check(
+        "withdraw_wsol",
+        instruction::WithdrawWsol { amount: 2_500 },
+        accounts::WithdrawWsol {
+            vault: key(1),
+            owner_wsol: key(27),
+            owner: key(2),
+            cosigner: Some(key(25)),
+            history_view: Some(key(13)),
+            pending_withdrawal: Some(key(83)),
+            recipient_allowlist: Some(key(86)),
+            denylist: Some(key(88)),
+            compliance_program: Some(key(89)),
+            token_program: key(23),
+            system_program: system_program::ID,
+        },
+    );
+}
+
+#[test]
+fn close_token_balance() {
+    check(
+        "close_token_balance",
+        instruction::CloseTokenBalance {},
+        accounts::CloseTokenBalance {
+            vault: key(1),
+            token_balance: key(19),
+            mint: key(20),
+            vault_token_account: key(21),
+            owner: key(2),
+            token_program: key(23),
+        },
+    );
+}
+
+#[test]
+fn delegate_token_balance() {
+    check_data(
+        "delegate_token_balance",
+        instruction::DelegateTokenBalance { validator: key(3) },
+    );
+}
+
+#[test]
+fn private_token_transfer() {
+    check_data(
+        "private_token_transfer",
+        instruction::PrivateTokenTransfer {
+            amount: 250_000,
+            recipient: key(4),
+        },
+    );
+}
+
+// ---------------------------------------------------------------------------
+// Limit orders
+// ---------------------------------------------------------------------------
+
+#[test]
+fn place_limit_order() {
+    check(
+        "place_limit_order",
+        instruction::PlaceLimitOrder {
+            order_id: 3,
+            amount: 1_000_000,
+            min_output: 250_000,
+            adapter_data: vec![0xde, 0xad, 0xbe, 0xef],
+        },
+        accounts::PlaceLimitOrder {
+            config: key(7),
+            vault: key(1),
+            order: key(56),
+            input_mint: key(20),
+            output_mint: key(57),
+            input_balance: key(19),
+            vault_input_account: key(21),
+            dex_adapter: key(55),
+            owner: key(2),
+            cosigner: Some(key(25)),
+            token_program: key(23),
+            system_program: system_program::ID,
+        },
+    );
+}
+
+#[test]
+fn settle_limit_order() {
+    check(
+        "settle_limit_order",
+        instruction::SettleLimitOrder {
+            adapter_data: vec![0xca, 0xfe],
+            close: true,
+        },
+        accounts::SettleLimitOrder {
+            config: key(7),
+            vault: key(1),
+            order: key(56),
+            input_mint: key(20),
+            output_mint: key(57),
+            input_balance: key(19),
+            output_balance: key(58),
+            vault_input_account: key(21),
+            vault_output_account: key(59),
+            dex_adapter: key(55),
+            owner: key(2),
+            token_program: key(23),
+            associated_token_program: key(24),
+            system_program: system_program::ID,
+        },
+    );
+}
+
+// ---------------------------------------------------------------------------
+// Conditional transfers
+// ---------------------------------------------------------------------------
+
+#[test]
+fn create_conditional_transfer() {
+    check(
+        "create_conditional_transfer",
+        instruction::CreateConditionalTransfer {
+            transfer_id: 9,
+            amount: 2_000_000,
+            recipient: key(4),
+            condition: TransferCondition::PriceAbove {
+                feed_id: [0xab; 32],
+                price: 15_000_000_000,
+            },
+        },
+        accounts::CreateConditionalTransfer {
+            vault: key(1),
+            conditional_transfer: key(60),
+            owner: key(2),
+            cosigner: Some(key(25)),
+            recipient_allowlist: Some(key(86)),
+            denylist: Some(key(88)),
+            compliance_program: Some(key(89)),
+            system_program: system_program::ID,
+        },
+    );
+}
+
+#[test]
+fn execute_conditional_transfer() {
+    check(
+        "execute_conditional_transfer",
+        instruction::ExecuteConditionalTransfer {},
+        accounts::ExecuteConditionalTransfer {
+            conditional_transfer: key(60),
+            recipient: key(4),
+            owner: key(2),
+            price_update: Some(key(61)),
+            attestor: Some(key(62)),
+            rating_ticket: Some(key(105)),
+        },
+    );
+}
+
+#[test]
+fn cancel_conditional_transfer() {
+    check(
+        "cancel_conditional_transfer",
+        instruction::CancelConditionalTransfer {},
+        accounts::CancelConditionalTransfer {
+            vault: key(1),
+            conditional_transfer: key(60),
+            owner: key(2),
+        },
+    );
+}
+
+#[test]
+fn open_evidence_locker() {
+    check(
+        "open_evidence_locker",
+        instruction::OpenEvidenceLocker {},
+        accounts::OpenEvidenceLocker {
+            conditional_transfer: key(60),
+            evidence_locker: key(65),
+            party: key(4),
+            system_program: system_program::ID,
+        },
+    );
+}
+
+#[test]
+fn append_evidence() {
+    check(
+        "append_evidence",
+        instruction::AppendEvidence {
+            blob: vec![0x5e, 0xa1, 0xed],
+        },
+        accounts::AppendEvidence {
+            conditional_transfer: key(60),
+            evidence_locker: key(65),
+            party: key(4),
+        },
+    );
+}
+
+#[test]
+fn close_evidence_locker() {
+    check(
+        "close_evidence_locker",
+        instruction::CloseEvidenceLocker {},
+        accounts::CloseEvidenceLocker {
+            evidence_locker: key(65),
+            arbiter: key(62),
+            opened_by: key(4),
+        },
+    );
+}
+
+#[test]
+fn open_rating_ticket() {
+    check(
+        "open_rating_ticket",
+        instruction::OpenRatingTicket {},
+        accounts::OpenRatingTicket {
+            conditional_transfer: key(60),
+            rating_ticket: key(105),
+            party: key(4),
+            system_program: system_program::ID,
+        },
+    );
+}
+
+#[test]
+fn rate_counterparty() {
+    check(
+        "rate_counterparty",
+        instruction::RateCounterparty {
+            rating: Rating::Public { score: 4 },
+        },
+        accounts::RateCounterparty {
+            rating_ticket: key(105),
+            rater: key(4),
+        },
+    );
+}
+
+#[test]
+fn close_rating_ticket() {
+    check(
+        "close_rating_ticket",
+        instruction::CloseRatingTicket {},
+        accounts::CloseRatingTicket {
+            rating_ticket: key(105),
+            conditional_transfer: key(60),
+            opened_by: key(4),
+        },
+    );
+}
+
+// ---------------------------------------------------------------------------
+// Scheduled transfers
+// ---------------------------------------------------------------------------
+
+#[test]
+fn schedule_transfer() {
+    check(
+        "schedule_transfer",
+        instruction::ScheduleTransfer {
+            transfer_id: 10,
+            recipient: key(4),
+            amount: 2_000_000,
+            execute_after: 1_900_000_000,
+        },
+        accounts::ScheduleTransfer {
+            vault: key(1),
+            scheduled_transfer: key(97),
+            owner: key(2),
+            cosigner: Some(key(25)),
+            recipient_allowlist: Some(key(86)),
+            denylist: Some(key(88)),
+            compliance_program: Some(key(89)),
+            system_program: system_program::ID,
+        },
+    );
+}
+
+#[test]
+fn execute_scheduled_transfer() {
+    check(
+        "execute_scheduled_transfer",
+        instruction::ExecuteScheduledTransfer {},
+        accounts::ExecuteScheduledTransfer {
+            scheduled_transfer: key(97),
+            recipient: key(4),
+            owner: key(2),
+            cranker: key(98),
+            cranker_stats: key(104),
+            system_program: system_program::ID,
+        },
+    );
+}
+
+#[test]
+fn cancel_scheduled_transfer() {
+    check(
+        "cancel_scheduled_transfer",
+        instruction::CancelScheduledTransfer {},
+        accounts::CancelScheduledTransfer {
+            vault: key(1),
+            scheduled_transfer: key(97),
+            owner: key(2),
+        },
+    );
+}
+
+// ---------------------------------------------------------------------------
+// Subscriptions
+// ---------------------------------------------------------------------------
+
+#[test]
+fn create_subscription() {
+    check(
+        "create_subscription",
+        instruction::CreateSubscription {
+            subscription_id: 3,
+            recipient: key(4),
+            amount: 1_000_000,
+            interval: 30 * 24 * 60 * 60,
+            first_due: 1_900_000_000,
+        },
+        accounts::CreateSubscription {
+            vault: key(1),
+            subscription: key(100),
+            owner: key(2),
+            cosigner: Some(key(25)),
+            recipient_allowlist: Some(key(86)),
+            denylist: Some(key(88)),
+            compliance_program: Some(key(89)),
+            system_program: system_program::ID,
+        },
+    );
+}
+
+#[test]
+fn process_subscription() {
+    check(
+        "process_subscription",
+        instruction::ProcessSubscription {},
+        accounts::ProcessSubscription {
+            vault: key(1),
+            subscription: key(100),
+            recipient: key(4),
+            recipient_allowlist: Some(key(86)),
+            denylist: Some(key(88)),
+            compliance_program: Some(key(89)),
+        },
+    );
+}
+
+#[test]
+fn cancel_subscription() {
+    check(
+        "cancel_subscription",
+        instruction::CancelSubscription {},
+        accounts::CancelSubscription {
+            subscription: key(100),
+            owner: key(2),
+            authority: key(4),
+        },
+    );
+}
+
+// ---------------------------------------------------------------------------
+// Payment streams
+// ---------------------------------------------------------------------------
+
+#[test]
+fn create_stream() {
+    check(
+        "create_stream",
+        instruction::CreateStream {
+            stream_id: 5,
+            recipient_vault: key(102),
+            rate_per_second: 1_000,
+            start: 1_900_000_000,
+            end: 1_902_592_000,
+        },
+        accounts::CreateStream {
+            vault: key(1),
+            stream: key(101),
+            owner: key(2),
+            cosigner: Some(key(25)),
+            recipient_allowlist: Some(key(86)),
+            denylist: Some(key(88)),
+            compliance_program: Some(key(89)),
+            system_program: system_program::ID,
+        },
+    );
+}
+
+#[test]
+fn withdraw_from_stream() {
+    check(
+        "withdraw_from_stream",
+        instruction::WithdrawFromStream {},
+        accounts::WithdrawFromStream {
+            stream: key(101),
+            recipient_vault: key(102),
+            owner: key(4),
+        },
+    );
+}
+
+#[test]
+fn close_stream() {
+    check(
+        "close_stream",
+        instruction::CloseStream {},
+        accounts::CloseStream {
+            vault: key(1),
+            stream: key(101),
+            owner: key(2),
+        },
+    );
+}
+
+#[test]
+fn delegate_stream() {
+    check_data(
+        "delegate_stream",
+        instruction::DelegateStream { validator: key(3) },
+    );
+}
+
+#[test]
+fn undelegate_stream() {
+    check_data("undelegate_stream", instruction::UndelegateStream {});
+}
+
+// ---------------------------------------------------------------------------
+// NFT custody
+// ---------------------------------------------------------------------------
+
+#[test]
+fn deposit_nft() {
+    check(
+        "deposit_nft",
+        instruction::DepositNft {},
+        accounts::DepositNft {
+            vault: key(1),
+            nft_record: key(28),
+            mint: key(20),
+            metadata: key(29),
+            edition: key(30),
+            depositor_token_account: key(22),
+            vault_token_account: key(21),
+            owner_token_record: Some(key(31)),
+            vault_token_record: Some(key(32)),
+            authorization_rules_program: Some(key(33)),
+            authorization_rules: Some(key(34)),
+            depositor: key(2),
+            token_metadata_program: key(35),
+            sysvar_instructions: key(36),
+            token_program: key(23),
+            associated_token_program: key(24),
+            system_program: system_program::ID,
+        },
+    );
+}
+
+#[test]
+fn withdraw_nft() {
+    check(
+        "withdraw_nft",
+        instruction::WithdrawNft {},
+        accounts::WithdrawNft {
+            vault: key(1),
+            nft_record: key(28),
+            mint: key(20),
+            metadata: key(29),
+            edition: key(30),
+            vault_token_account: key(21),
+            owner_token_account: key(22),
+            vault_token_record: Some(key(32)),
+            owner_token_record: Some(key(31)),
+            authorization_rules_program: Some(key(33)),
+            authorization_rules: Some(key(34)),
+            owner: key(2),
+            cosigner: Some(key(25)),
+            token_metadata_program: key(35),
+            sysvar_instructions: key(36),
+            token_program: key(23),
+            associated_token_program: key(24),
+            system_program: system_program::ID,
+        },
+    );
+}
+
+#[test]
+fn deposit_cnft() {
+    check(
+        "deposit_cnft",
+        instruction::DepositCnft {
+            root: [1; 32],
+            data_hash: [2; 32],
+            creator_hash: [3; 32],
+            nonce: 42,
+            index: 42,
+        },
+        accounts::DepositCnft {
+            vault: key(1),
+            cnft_record: key(37),
+            tree_config: key(38),
+            merkle_tree: key(39),
+            leaf_delegate: key(2),
+            depositor: key(2),
+            bubblegum_program: key(40),
+            log_wrapper: key(41),
+            compression_program: key(42),
+            system_program: system_program::ID,
+        },
+    );
+}
+
+#[test]
+fn withdraw_cnft() {
+    check(
+        "withdraw_cnft",
+        instruction::WithdrawCnft {
+            root: [1; 32],
+            data_hash: [2; 32],
+            creator_hash: [3; 32],
+            nonce: 42,
+            index: 42,
+        },
+        accounts::WithdrawCnft {
+            vault: key(1),
+            cnft_record: key(37),
+            tree_config: key(38),
+            merkle_tree: key(39),
+            owner: key(2),
+            cosigner: Some(key(25)),
+            bubblegum_program: key(40),
+            log_wrapper: key(41),
+            compression_program: key(42),
+            system_program: system_program::ID,
+        },
+    );
+}
+
+// ---------------------------------------------------------------------------
+// Social recovery
+// ---------------------------------------------------------------------------
+
+#[test]
+fn add_guardian() {
+    check(
+        "add_guardian",
+        instruction::AddGuardian {
+            guardian: key(81),
+            threshold: 2,
+        },
+        accounts::AddGuardian {
+            guardian_set: key(80),
+            vault: key(1),
+            owner: key(2),
+            system_program: system_program::ID,
+        },
+    );
+}
+
+#[test]
+fn remove_guardian() {
+    check(
+        "remove_guardian",
+        instruction::RemoveGuardian {
+            guardian: key(81),
+            threshold: 1,
+        },
+        accounts::UpdateGuardians {
+            guardian_set: key(80),
+            vault: key(1),
+            owner: key(2),
+        },
+    );
+}
+
+#[test]
+fn initiate_recovery() {
+    check(
+        "initiate_recovery",
+        instruction::InitiateRecovery { new_owner: key(82) },
+        accounts::GuardianAction {
+            guardian_set: key(80),
+            vault: key(1),
+            guardian: key(81),
+        },
+    );
+}
+
+#[test]
+fn approve_recovery() {
+    check(
+        "approve_recovery",
+        instruction::ApproveRecovery {},
+        accounts::GuardianAction {
+            guardian_set: key(80),
+            vault: key(1),
+            guardian: key(81),
+        },
+    );
+}
+
+#[test]
+fn cancel_recovery() {
+    check(
+        "cancel_recovery",
+        instruction::CancelRecovery {},
+        accounts::UpdateGuardians {
+            guardian_set: key(80),
+            vault: key(1),
+            owner: key(2),
+        },
+    );
+}
+
+#[test]
+fn finalize_recovery() {
+    check(
+        "finalize_recovery",
+        instruction::FinalizeRecovery {},
+        accounts::FinalizeRecovery {
+            guardian_set: key(80),
+            vault: key(1),
+        },
+    );
+}
+
+// ---------------------------------------------------------------------------
+// Inheritance
+// ---------------------------------------------------------------------------
+
+#[test]
+fn set_beneficiary() {
+    check(
+        "set_beneficiary",
+        instruction::SetBeneficiary {
+            beneficiary: key(93),
+            inactivity_period: 15_552_000,
+        },
+        accounts::SetVaultPolicy {
+            vault: key(1),
+            owner: key(2),
+        },
+    );
+}
+
+#[test]
+fn claim_inherited_vault() {
+    check(
+        "claim_inherited_vault",
+        instruction::ClaimInheritedVault {},
+        accounts::ClaimInheritedVault {
+            vault: key(1),
+            beneficiary: key(93),
+        },
+    );
+}
+
+// ---------------------------------------------------------------------------
+// Multisig ownership
+// ---------------------------------------------------------------------------
+
+#[test]
+fn convert_to_multisig() {
+    check(
+        "convert_to_multisig",
+        instruction::ConvertToMultisig {
+            signers: vec![key(2), key(77), key(78)],
+            threshold: 2,
+        },
+        accounts::ConvertToMultisig {
+            vault_owners: key(75),
+            vault: key(1),
+            owner: key(2),
+            system_program: system_program::ID,
+        },
+    );
+}
+
+#[test]
+fn set_vault_owners() {
+    check(
+        "set_vault_owners",
+        instruction::SetVaultOwners {
+            signers: vec![key(2), key(77), key(78)],
+            threshold: 3,
+        },
+        accounts::SetVaultOwners {
+            vault_owners: key(75),
+        },
+    );
+}
+
+#[test]
+fn sweep_vault_owners() {
+    check(
+        "sweep_vault_owners",
+        instruction::SweepVaultOwners {},
+        accounts::SweepVaultOwners {
+            vault_owners: key(75),
+            destination: key(4),
+        },
+    );
+}
+
+#[test]
+fn propose_action() {
+    check(
+        "propose_action",
+        instruction::ProposeAction {
+            data: vec![0xaa, 0xbb],
+            accounts: vec![
+                ActionAccount {
+                    pubkey: key(1),
+                    is_signer: false,
+                    is_writable: true,
+                },
+                ActionAccount {
+                    pubkey: key(75),
+                    is_signer: true,
+                    is_writable: false,
+                },
+            ],
+        },
+        accounts::ProposeAction {
+            action: key(76),
+            vault_owners: key(75),
+            proposer: key(77),
+            system_program: system_program::ID,
+        },
+    );
+}
+
+#[test]
+fn approve_action() {
+    check(
+        "approve_action",
+        instruction::ApproveAction {},
+        accounts::ApproveAction {
+            action: key(76),
+            vault_owners: key(75),
+            signer: key(78),
+        },
+    );
+}
+
+#[test]
+fn execute_action() {
+    check(
+        "execute_action",
+        instruction::ExecuteAction {},
+        accounts::ExecuteAction {
+            action: key(76),
+            vault_owners: key(75),
+            executor: key(78),
+            proposer: key(77),
+            obscura_program: key(79),
+        },
+    );
+}
+
+#[test]
+fn cancel_action() {
+    check(
+        "cancel_action",
+        instruction::CancelAction {},
+        accounts::CancelAction {
+            action: key(76),
+            vault_owners: key(75),
+            proposer: key(77),
+        },
+    );
+}
+
+// ---------------------------------------------------------------------------
+// Payment intents
+// ---------------------------------------------------------------------------
+
+#[test]
+fn fulfill_intent() {
+    check(
+        "fulfill_intent",
+        instruction::FulfillIntent {
+            intent: PaymentIntent {
+                version: PAYMENT_INTENT_VERSION,
+                amount: 250_000,
+                asset: Pubkey::default(),
+                recipient: RecipientAlias::Contact([0xcd; 32]),
+                expires_at: 1_767_225_600,
+                reference: [0xab; 32],
+            },
+        },
+        accounts::FulfillIntent {
+            receipt: key(94),
+            vault: key(1),
+            recipient_vault: key(18),
+            contact_claim: Some(key(47)),
+            owner: key(2),
+            cosigner: Some(key(25)),
+            recipient_allowlist: Some(key(86)),
+            denylist: Some(key(88)),
+            compliance_program: Some(key(89)),
+            system_program: system_program::ID,
+        },
+    );
+}
+
+// ---------------------------------------------------------------------------
+// Shared expense groups
+// ---------------------------------------------------------------------------
+
+#[test]
+fn create_group() {
+    check(
+        "create_group",
+        instruction::CreateGroup {
+            group_id: 1,
+            members: vec![key(18), key(4)],
+        },
+        accounts::CreateGroup {
+            group: key(87),
+            vault: key(1),
+            owner: key(2),
+            system_program: system_program::ID,
+        },
+    );
+}
+
+#[test]
+fn join_group() {
+    check(
+        "join_group",
+        instruction::JoinGroup {},
+        accounts::GroupMemberAction {
+            group: key(87),
+            vault: key(18),
+            owner: key(2),
+        },
+    );
+}
+
+#[test]
+fn log_expense() {
+    check(
+        "log_expense",
+        instruction::LogExpense {
+            amount: 90_000_000,
+            participants: 0b111,
+        },
+        accounts::GroupMemberAction {
+            group: key(87),
+            vault: key(1),
+            owner: key(2),
+        },
+    );
+}
+
+#[test]
+fn delegate_group() {
+    check_data(
+        "delegate_group",
+        instruction::DelegateGroup { validator: key(3) },
+    );
+}
+
+#[test]
+fn settle_group() {
+    check_data("settle_group", instruction::SettleGroup {});
+}
+
+// ---------------------------------------------------------------------------
+// Point of sale
+// ---------------------------------------------------------------------------
+
+#[test]
+fn open_pos_session() {
+    check(
+        "open_pos_session",
+        instruction::OpenPosSession {
+            session_id: 1,
+            operator: key(92),
+            duration: 28_800,
+        },
+        accounts::OpenPosSession {
+            pos_session: key(91),
+            vault: key(1),
+            owner: key(2),
+            system_program: system_program::ID,
+        },
+    );
+}
+
+#[test]
+fn record_pos_payment() {
+    check(
+        "record_pos_payment",
+        instruction::RecordPosPayment {
+            reference: [0xab; 32],
+            amount: 150_000,
+        },
+        accounts::RecordPosPayment {
+            pos_session: key(91),
+            operator: key(92),
+        },
+    );
+}
+
+#[test]
+fn close_pos_session() {
+    check(
+        "close_pos_session",
+        instruction::ClosePosSession {},
+        accounts::ClosePosSession {
+            pos_session: key(91),
+            vault: key(1),
+            owner: key(2),
+        },
+    );
+}
+
+// ---------------------------------------------------------------------------
+// Support tooling
+// ---------------------------------------------------------------------------
+
+#[test]
+fn inspect_vaults() {
+    check(
+        "inspect_vaults",
+        instruction::InspectVaults {},
+        accounts::InspectVaults {},
+    );
+}
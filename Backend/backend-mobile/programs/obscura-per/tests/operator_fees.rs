@@ -0,0 +1,39 @@
+//! Operator fee accrual under an accepted rate card.
+//!
+//! The delegation fee is charged pro rata per second, commits at the
+//! flat rate, and nothing accrues once the delegation has stopped.
+
+use anchor_lang::prelude::Pubkey;
+use obscura_per::{OperatorFees, RateCard, RATE_CARD_PERIOD};
+
+fn card() -> RateCard {
+    RateCard {
+        commit_fee: 5_000,
+        monthly_delegation_fee: 3_000_000,
+        payout: Pubkey::new_unique(),
+    }
+}
+
+#[test]
+fn accrues_commits_and_delegation_time() {
+    let mut fees = OperatorFees::default();
+    fees.start(&card(), 1_000);
+
+    fees.accrue(1_000 + RATE_CARD_PERIOD / 3, 2).unwrap();
+    assert_eq!(fees.owed, 1_000_000 + 2 * 5_000);
+
+    fees.stop(1_000 + RATE_CARD_PERIOD).unwrap();
+    assert_eq!(fees.owed, 3_000_000 + 3 * 5_000);
+}
+
+#[test]
+fn stopped_delegations_accrue_nothing() {
+    let mut fees = OperatorFees::default();
+    fees.start(&card(), 0);
+    fees.stop(0).unwrap();
+    let owed = fees.owed;
+
+    fees.accrue(RATE_CARD_PERIOD, 4).unwrap();
+    assert_eq!(fees.owed, owed);
+    assert_ne!(fees.payout, Pubkey::default());
+}
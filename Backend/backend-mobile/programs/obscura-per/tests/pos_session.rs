@@ -0,0 +1,46 @@
+//! Point-of-sale session bookkeeping.
+//!
+//! The reference digest is what end-of-day reconciliation replays off
+//! chain, so its construction is pinned here alongside the totals and
+//! the expiry cut-off.
+
+use anchor_lang::prelude::Pubkey;
+use anchor_lang::solana_program::hash::hashv;
+use obscura_per::PosSession;
+
+fn session(expires_at: i64) -> PosSession {
+    PosSession {
+        vault: Pubkey::new_unique(),
+        session_id: 1,
+        operator: Pubkey::new_unique(),
+        opened_at: 0,
+        expires_at,
+        payment_count: 0,
+        total: 0,
+        references_digest: [0; 32],
+    }
+}
+
+#[test]
+fn digest_chains_references_and_amounts() {
+    let mut pos = session(100);
+    let payments = [([1u8; 32], 150_000u64), ([2; 32], 42), ([1; 32], 7)];
+    let mut digest = [0u8; 32];
+    for (reference, amount) in payments {
+        pos.record(reference, amount, 10).unwrap();
+        digest = hashv(&[&digest, &reference, &amount.to_le_bytes()]).to_bytes();
+    }
+
+    assert_eq!(pos.references_digest, digest);
+    assert_eq!(pos.payment_count, 3);
+    assert_eq!(pos.total, 150_049);
+}
+
+#[test]
+fn rejects_zero_amounts_and_expired_sessions() {
+    let mut pos = session(100);
+    assert!(pos.record([1; 32], 0, 10).is_err());
+    assert!(pos.record([1; 32], 5, 100).is_err());
+    assert_eq!(pos.payment_count, 0);
+    assert_eq!(pos.references_digest, [0; 32]);
+}
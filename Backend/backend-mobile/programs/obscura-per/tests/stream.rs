@@ -0,0 +1,40 @@
+//! Payment stream vesting.
+//!
+//! A stream vests linearly between `start` and `end`, and a withdrawal
+//! only ever pays what has vested since the last one.
+
+use anchor_lang::prelude::Pubkey;
+use obscura_per::Stream;
+
+fn stream() -> Stream {
+    Stream {
+        vault: Pubkey::new_unique(),
+        owner: Pubkey::new_unique(),
+        stream_id: 1,
+        recipient_vault: Pubkey::new_unique(),
+        rate_per_second: 1_000,
+        start: 1_000,
+        end: 2_000,
+        withdrawn: 0,
+        created_at: 900,
+    }
+}
+
+#[test]
+fn vests_linearly_within_bounds() {
+    let stream = stream();
+    assert_eq!(stream.total(), Some(1_000_000));
+    assert_eq!(stream.vested(500), 0);
+    assert_eq!(stream.vested(1_250), 250_000);
+    assert_eq!(stream.vested(5_000), 1_000_000);
+}
+
+#[test]
+fn withdrawals_pay_only_the_new_part() {
+    let mut stream = stream();
+    stream.withdrawn = stream.withdrawable(1_400);
+    assert_eq!(stream.withdrawn, 400_000);
+
+    assert_eq!(stream.withdrawable(1_400), 0);
+    assert_eq!(stream.withdrawable(1_500), 100_000);
+}
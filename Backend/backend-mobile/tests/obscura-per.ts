@@ -30,6 +30,9 @@ describe("obscura-per", () => {
   const DELEGATION_PROGRAM = new PublicKey(
     "DELeGGvXpWV2fqJUhqcF5ZSYMS4JTLjteaAMARRSaeSh"
   );
+  const ACCESS_CONTROL_PROGRAM = new PublicKey(
+    "ACLseoPoyC3cBqoUtkbjZ4aDrkurZW86v19pXz2XQnp1"
+  );
 
   // Devnet validators from official MagicBlock docs
   const VALIDATORS = {
@@ -47,6 +50,17 @@ describe("obscura-per", () => {
     );
   }
 
+  // Helper: derive Access Control permission PDA
+  function getAclPermissionPDA(
+    vault: PublicKey,
+    permitted: PublicKey
+  ): [PublicKey, number] {
+    return PublicKey.findProgramAddressSync(
+      [Buffer.from("permission"), vault.toBuffer(), permitted.toBuffer()],
+      ACCESS_CONTROL_PROGRAM
+    );
+  }
+
   // Helper: derive permission PDA
   function getPermissionPDA(
     vault: PublicKey,
@@ -160,11 +174,13 @@ describe("obscura-per", () => {
     const [permissionPDA] = getPermissionPDA(vaultPDA, permittedUser);
 
     const tx = await program.methods
-      .createPermission(permittedUser)
+      .createPermission(permittedUser, 1, null) // SCOPE_READ
       .accounts({
         permission: permissionPDA,
         vault: vaultPDA,
         owner: owner.publicKey,
+        aclPermission: getAclPermissionPDA(vaultPDA, permittedUser)[0],
+        accessControlProgram: ACCESS_CONTROL_PROGRAM,
         systemProgram: SystemProgram.programId,
       })
       .rpc();
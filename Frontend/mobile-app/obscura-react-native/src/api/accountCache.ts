@@ -0,0 +1,146 @@
+/**
+ * Account cache
+ * LRU cache of account data, each entry tagged with the slot it was read
+ * at.  Misses are fetched together in one getMultipleAccountsInfo call,
+ * so a screen of vault tiles costs one request rather than one each, and
+ * every cached account holds an account subscription that replaces the
+ * entry whenever the account changes, so entries never go stale while
+ * cached.  Evicting an entry drops its subscription.
+ */
+
+import {
+  AccountInfo,
+  Commitment,
+  Connection,
+  PublicKey,
+} from '@solana/web3.js';
+
+const DEFAULT_CAPACITY = 128;
+/** getMultipleAccountsInfo limit */
+const MAX_BATCH = 100;
+
+export interface CachedAccount {
+  /** null if the account does not exist */
+  account: AccountInfo<Buffer> | null;
+  /** Slot the data was observed at */
+  slot: number;
+}
+
+interface Entry extends CachedAccount {
+  subscription: number;
+}
+
+export class AccountCache {
+  /** Least recently used first (Map keeps insertion order) */
+  private entries = new Map<string, Entry>();
+  private inflight = new Map<string, Promise<CachedAccount>>();
+
+  constructor(
+    private connection: Connection,
+    private capacity = DEFAULT_CAPACITY,
+    private commitment: Commitment = 'confirmed'
+  ) {}
+
+  /** Cached entry for `pubkey` no older than `minSlot`, without fetching. */
+  peek(pubkey: PublicKey, minSlot = 0): CachedAccount | null {
+    const key = pubkey.toBase58();
+    const entry = this.entries.get(key);
+    if (!entry || entry.slot < minSlot) {
+      return null;
+    }
+    this.entries.delete(key);
+    this.entries.set(key, entry);
+    return { account: entry.account, slot: entry.slot };
+  }
+
+  async get(pubkey: PublicKey, minSlot = 0): Promise<CachedAccount> {
+    return (await this.getMultiple([pubkey], minSlot))[0];
+  }
+
+  /**
+   * Accounts for `pubkeys`, in order, each observed at `minSlot` or
+   * later.  Only missing or older entries are fetched, in batches.
+   */
+  async getMultiple(
+    pubkeys: PublicKey[],
+    minSlot = 0
+  ): Promise<CachedAccount[]> {
+    const misses = pubkeys.filter(
+      (pubkey, i) =>
+        !this.peek(pubkey, minSlot) &&
+        !this.inflight.has(pubkey.toBase58()) &&
+        pubkeys.findIndex((p) => p.equals(pubkey)) === i
+    );
+    for (let i = 0; i < misses.length; i += MAX_BATCH) {
+      const batch = misses.slice(i, i + MAX_BATCH);
+      const request = this.connection.getMultipleAccountsInfoAndContext(batch, {
+        commitment: this.commitment,
+        minContextSlot: minSlot || undefined,
+      });
+      batch.forEach((pubkey, j) => {
+        const key = pubkey.toBase58();
+        const fetched = request.then(({ context, value }) => {
+          this.store(pubkey, { account: value[j], slot: context.slot });
+          return { account: value[j], slot: context.slot };
+        });
+        this.inflight.set(key, fetched);
+        fetched
+          .catch(() => undefined)
+          .then(() => this.inflight.delete(key));
+      });
+    }
+
+    return Promise.all(
+      pubkeys.map(
+        (pubkey) =>
+          this.peek(pubkey, minSlot) ?? this.inflight.get(pubkey.toBase58())!
+      )
+    );
+  }
+
+  /** Keep the newer of the cached and `observed` data for `pubkey`. */
+  private store(pubkey: PublicKey, observed: CachedAccount) {
+    const key = pubkey.toBase58();
+    const existing = this.entries.get(key);
+    if (existing) {
+      this.entries.delete(key);
+      if (existing.slot > observed.slot) {
+        this.entries.set(key, existing);
+        return;
+      }
+      this.entries.set(key, { ...observed, subscription: existing.subscription });
+      return;
+    }
+
+    const subscription = this.connection.onAccountChange(
+      pubkey,
+      (account, context) => {
+        if (this.entries.has(key)) {
+          this.store(pubkey, { account, slot: context.slot });
+        }
+      },
+      { commitment: this.commitment }
+    );
+    this.entries.set(key, { ...observed, subscription });
+    while (this.entries.size > this.capacity) {
+      const [oldest] = this.entries.keys();
+      this.invalidate(new PublicKey(oldest));
+    }
+  }
+
+  /** Drop `pubkey` from the cache, so the next read fetches it. */
+  invalidate(pubkey: PublicKey) {
+    const key = pubkey.toBase58();
+    const entry = this.entries.get(key);
+    if (entry) {
+      this.entries.delete(key);
+      this.connection.removeAccountChangeListener(entry.subscription);
+    }
+  }
+
+  clear() {
+    for (const key of [...this.entries.keys()]) {
+      this.invalidate(new PublicKey(key));
+    }
+  }
+}
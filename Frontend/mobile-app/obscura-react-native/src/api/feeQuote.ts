@@ -0,0 +1,155 @@
+/**
+ * Fee quotes
+ * What an operation will cost on top of its amount, signed by obscurad's
+ * quote key (POST /v1/quotes, see the backend README "Payment Daemon").
+ * Show `totalCost` only after `verifyFeeQuote` accepts the quote: it may
+ * reach the app through a backend that is not trusted with the total.
+ */
+
+import { PublicKey } from '@solana/web3.js';
+import { ed25519 } from '@noble/curves/ed25519';
+import bs58 from 'bs58';
+
+export const FEE_QUOTE_VERSION = 1;
+
+const SIGNING_PREFIX = 'obscura-fee-quote:';
+
+export type QuoteOperation =
+  | 'private_transfer'
+  | 'withdraw'
+  | 'deposit'
+  | 'create_vault';
+
+export interface FeeQuoteRequest {
+  operation: QuoteOperation;
+  /** Required except for create_vault */
+  vaultId?: string;
+  /** Lamports */
+  amount: bigint;
+}
+
+/** As returned by the daemon */
+export interface SignedFeeQuote {
+  /** Quote JSON, exactly as signed */
+  quote: string;
+  /** Base58 Ed25519 signature */
+  signature: string;
+  /** Base58 quote key */
+  signer: string;
+}
+
+export interface FeeQuote {
+  id: string;
+  operation: QuoteOperation;
+  vaultId: string | null;
+  /** All amounts in lamports */
+  amount: bigint;
+  protocolFee: bigint;
+  networkFee: bigint;
+  priorityFee: bigint;
+  /** Rent of accounts the operation creates */
+  rent: bigint;
+  /** Sum of the fees and rent */
+  totalCost: bigint;
+  /** Unix timestamps (seconds) */
+  quotedAt: number;
+  expiresAt: number;
+}
+
+function lamports(value: unknown, field: string): bigint {
+  if (typeof value !== 'string' || !/^\d{1,20}$/.test(value)) {
+    throw new Error(`Fee quote ${field} is malformed`);
+  }
+  return BigInt(value);
+}
+
+/**
+ * The quote in `signed`, if `quoteKey` signed it and it hasn't expired.
+ * `now` is a unix timestamp in seconds.
+ */
+export function verifyFeeQuote(
+  signed: SignedFeeQuote,
+  quoteKey: PublicKey,
+  now: number = Math.floor(Date.now() / 1000)
+): FeeQuote {
+  if (signed.signer !== quoteKey.toBase58()) {
+    throw new Error('Fee quote is not from the expected quote key');
+  }
+  const message = new TextEncoder().encode(SIGNING_PREFIX + signed.quote);
+  let valid = false;
+  try {
+    valid = ed25519.verify(
+      bs58.decode(signed.signature),
+      message,
+      quoteKey.toBytes()
+    );
+  } catch {
+    valid = false;
+  }
+  if (!valid) {
+    throw new Error('Fee quote signature is invalid');
+  }
+
+  const raw = JSON.parse(signed.quote);
+  if (raw.version !== FEE_QUOTE_VERSION) {
+    throw new Error(`Unsupported fee quote version ${raw.version}`);
+  }
+  const quote: FeeQuote = {
+    id: String(raw.id),
+    operation: raw.operation,
+    vaultId: raw.vaultId ?? null,
+    amount: lamports(raw.amount, 'amount'),
+    protocolFee: lamports(raw.protocolFee, 'protocolFee'),
+    networkFee: lamports(raw.networkFee, 'networkFee'),
+    priorityFee: lamports(raw.priorityFee, 'priorityFee'),
+    rent: lamports(raw.rent, 'rent'),
+    totalCost: lamports(raw.totalCost, 'totalCost'),
+    quotedAt: Number(raw.quotedAt),
+    expiresAt: Number(raw.expiresAt),
+  };
+  const parts =
+    quote.protocolFee + quote.networkFee + quote.priorityFee + quote.rent;
+  if (quote.totalCost !== parts) {
+    throw new Error('Fee quote total does not match its parts');
+  }
+  if (!(now < quote.expiresAt)) {
+    throw new Error('Fee quote has expired');
+  }
+  return quote;
+}
+
+/**
+ * Ask obscurad (or a backend relaying to it) for a quote and verify it.
+ * `headers` carries the API key when the daemon has tenants.
+ */
+export async function fetchFeeQuote(
+  endpoint: string,
+  request: FeeQuoteRequest,
+  quoteKey: PublicKey,
+  headers: Record<string, string> = {}
+): Promise<FeeQuote> {
+  const response = await fetch(`${endpoint.replace(/\/+$/, '')}/v1/quotes`, {
+    method: 'POST',
+    headers: { 'Content-Type': 'application/json', ...headers },
+    body: JSON.stringify({
+      operation: request.operation,
+      vaultId: request.vaultId,
+      amount: request.amount.toString(),
+    }),
+  });
+  if (!response.ok) {
+    const error = await response
+      .json()
+      .catch(() => ({ error: `HTTP ${response.status}` }));
+    throw new Error(error.error || `HTTP ${response.status}`);
+  }
+  const quote = verifyFeeQuote(await response.json(), quoteKey);
+  if (
+    quote.operation !== request.operation ||
+    quote.amount !== request.amount ||
+    (request.vaultId !== undefined && quote.vaultId !== request.vaultId)
+  ) {
+    throw new Error('Fee quote is for a different operation');
+  }
+  return quote;
+}
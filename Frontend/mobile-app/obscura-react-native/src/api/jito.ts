@@ -0,0 +1,108 @@
+/**
+ * Jito bundles
+ * Sends a settlement or exit transaction (withdraw, undelegate on L1)
+ * to a Jito block engine as a bundle instead of through RPC, so nobody
+ * sees it before it lands and it can't be front-run.  The bundle pays a
+ * tip to one of the block engine's tip accounts, as a transfer inside
+ * the transaction, so the tip is only paid if the transaction lands.
+ */
+
+import {
+  Connection,
+  PublicKey,
+  SystemProgram,
+  TransactionInstruction,
+  VersionedTransaction,
+} from '@solana/web3.js';
+import bs58 from 'bs58';
+
+export interface JitoConfig {
+  /** Block engine, e.g. https://mainnet.block-engine.jito.wtf */
+  blockEngineUrl: string;
+  /** Lamports tipped per bundle */
+  tipLamports: number;
+}
+
+/** Build and sign the transaction, with `tip` as its last instruction. */
+export type BuildWithTip = (
+  tip: TransactionInstruction,
+  recentBlockhash: string
+) => Promise<VersionedTransaction>;
+
+async function call(url: string, method: string, params: unknown[]) {
+  const response = await fetch(`${url.replace(/\/+$/, '')}/api/v1/bundles`, {
+    method: 'POST',
+    headers: { 'Content-Type': 'application/json' },
+    body: JSON.stringify({ jsonrpc: '2.0', id: 1, method, params }),
+  });
+  const body = await response.json().catch(() => ({}));
+  if (!response.ok || body.error) {
+    throw new Error(
+      `Jito ${method}: ${body.error?.message ?? `HTTP ${response.status}`}`
+    );
+  }
+  return body.result;
+}
+
+/** A tip from `payer` to a random tip account of the block engine. */
+export async function tipInstruction(
+  config: JitoConfig,
+  payer: PublicKey
+): Promise<TransactionInstruction> {
+  const accounts: string[] = await call(
+    config.blockEngineUrl,
+    'getTipAccounts',
+    []
+  );
+  if (accounts.length === 0) {
+    throw new Error('Jito block engine returned no tip accounts');
+  }
+  return SystemProgram.transfer({
+    fromPubkey: payer,
+    toPubkey: new PublicKey(
+      accounts[Math.floor(Math.random() * accounts.length)]
+    ),
+    lamports: config.tipLamports,
+  });
+}
+
+/** Send signed transactions, tip included, as one bundle; its ID. */
+export async function sendBundle(
+  config: JitoConfig,
+  transactions: VersionedTransaction[]
+): Promise<string> {
+  const encoded = transactions.map((tx) =>
+    Buffer.from(tx.serialize()).toString('base64')
+  );
+  return call(config.blockEngineUrl, 'sendBundle', [
+    encoded,
+    { encoding: 'base64' },
+  ]);
+}
+
+/**
+ * Build a transaction with a tip, send it as a bundle and wait until it
+ * is confirmed on `connection` (L1); its signature.  Throws if it
+ * failed or its blockhash expired without it landing.
+ */
+export async function sendWithJito(
+  connection: Connection,
+  config: JitoConfig,
+  payer: PublicKey,
+  build: BuildWithTip
+): Promise<string> {
+  const tip = await tipInstruction(config, payer);
+  const { blockhash, lastValidBlockHeight } =
+    await connection.getLatestBlockhash('confirmed');
+  const tx = await build(tip, blockhash);
+  const signature = bs58.encode(tx.signatures[0]);
+  await sendBundle(config, [tx]);
+  const { value } = await connection.confirmTransaction(
+    { signature, blockhash, lastValidBlockHeight },
+    'confirmed'
+  );
+  if (value.err) {
+    throw new Error(`${signature} failed: ${JSON.stringify(value.err)}`);
+  }
+  return signature;
+}
@@ -0,0 +1,164 @@
+/**
+ * Payment intent envelopes
+ * Signed payment requests any Obscura vault can pay with `fulfill_intent`.
+ * Format: Backend/backend-mobile/docs/PAYMENT_INTENTS.md
+ */
+
+import { PublicKey } from '@solana/web3.js';
+import { ed25519 } from '@noble/curves/ed25519';
+import bs58 from 'bs58';
+
+export const PAYMENT_INTENT_VERSION = 1;
+export const PAYMENT_REQUEST_PREFIX = 'obscura:pay?e=';
+
+const SIGNING_PREFIX = new TextEncoder().encode('obscura-payment-intent:');
+const INTENT_LENGTH = 114;
+const ENVELOPE_LENGTH = INTENT_LENGTH + 32 + 64;
+
+export type RecipientAlias =
+  | { kind: 'vault'; vault: PublicKey }
+  | { kind: 'contact'; contactHash: Uint8Array };
+
+export interface PaymentIntent {
+  version: number;
+  amount: bigint;
+  /** Mint; null = SOL */
+  asset: PublicKey | null;
+  recipient: RecipientAlias;
+  /** Unix timestamp (seconds) */
+  expiresAt: number;
+  reference: Uint8Array;
+}
+
+export interface PaymentIntentEnvelope {
+  intent: PaymentIntent;
+  requester: PublicKey;
+  signature: Uint8Array;
+}
+
+function bytes32(value: Uint8Array, field: string): Uint8Array {
+  if (value.length !== 32) {
+    throw new Error(`${field} must be 32 bytes`);
+  }
+  return value;
+}
+
+/** Borsh encoding of the program's `PaymentIntent`. */
+export function serializeIntent(intent: PaymentIntent): Uint8Array {
+  const out = new Uint8Array(INTENT_LENGTH);
+  const view = new DataView(out.buffer);
+  view.setUint8(0, intent.version);
+  view.setBigUint64(1, intent.amount, true);
+  out.set(intent.asset ? intent.asset.toBytes() : new Uint8Array(32), 9);
+  if (intent.recipient.kind === 'vault') {
+    out[41] = 0;
+    out.set(intent.recipient.vault.toBytes(), 42);
+  } else {
+    out[41] = 1;
+    out.set(bytes32(intent.recipient.contactHash, 'contactHash'), 42);
+  }
+  view.setBigInt64(74, BigInt(intent.expiresAt), true);
+  out.set(bytes32(intent.reference, 'reference'), 82);
+  return out;
+}
+
+export function deserializeIntent(data: Uint8Array): PaymentIntent {
+  if (data.length !== INTENT_LENGTH) {
+    throw new Error('Malformed payment intent');
+  }
+  const view = new DataView(data.buffer, data.byteOffset, data.byteLength);
+  const asset = data.slice(9, 41);
+  const alias = data.slice(42, 74);
+  let recipient: RecipientAlias;
+  if (data[41] === 0) {
+    recipient = { kind: 'vault', vault: new PublicKey(alias) };
+  } else if (data[41] === 1) {
+    recipient = { kind: 'contact', contactHash: alias };
+  } else {
+    throw new Error('Unknown recipient alias');
+  }
+  return {
+    version: view.getUint8(0),
+    amount: view.getBigUint64(1, true),
+    asset: asset.every((b) => b === 0) ? null : new PublicKey(asset),
+    recipient,
+    expiresAt: Number(view.getBigInt64(74, true)),
+    reference: data.slice(82, 114),
+  };
+}
+
+/** The bytes the requester signs. */
+export function signingMessage(intent: PaymentIntent): Uint8Array {
+  const body = serializeIntent(intent);
+  const message = new Uint8Array(SIGNING_PREFIX.length + body.length);
+  message.set(SIGNING_PREFIX);
+  message.set(body, SIGNING_PREFIX.length);
+  return message;
+}
+
+/**
+ * Sign an intent with the requester's wallet (e.g. the wallet adapter's
+ * signMessage).
+ */
+export async function signIntent(
+  intent: PaymentIntent,
+  requester: PublicKey,
+  signMessage: (message: Uint8Array) => Promise<Uint8Array>
+): Promise<PaymentIntentEnvelope> {
+  const signature = await signMessage(signingMessage(intent));
+  return { intent, requester, signature };
+}
+
+export function verifyEnvelope(envelope: PaymentIntentEnvelope): boolean {
+  try {
+    return ed25519.verify(
+      envelope.signature,
+      signingMessage(envelope.intent),
+      envelope.requester.toBytes()
+    );
+  } catch {
+    return false;
+  }
+}
+
+/** QR / link payload for an envelope. */
+export function encodePaymentRequest(envelope: PaymentIntentEnvelope): string {
+  const out = new Uint8Array(ENVELOPE_LENGTH);
+  out.set(serializeIntent(envelope.intent));
+  out.set(envelope.requester.toBytes(), INTENT_LENGTH);
+  out.set(envelope.signature, INTENT_LENGTH + 32);
+  return PAYMENT_REQUEST_PREFIX + bs58.encode(out);
+}
+
+/**
+ * Decode a scanned payment request.  Throws unless it is a well-formed,
+ * correctly signed, unexpired request of a supported version.
+ */
+export function decodePaymentRequest(
+  payload: string,
+  now: number = Math.floor(Date.now() / 1000)
+): PaymentIntentEnvelope {
+  if (!payload.startsWith(PAYMENT_REQUEST_PREFIX)) {
+    throw new Error('Not an Obscura payment request');
+  }
+  const data = bs58.decode(payload.slice(PAYMENT_REQUEST_PREFIX.length));
+  if (data.length !== ENVELOPE_LENGTH) {
+    throw new Error('Malformed payment request');
+  }
+
+  const envelope: PaymentIntentEnvelope = {
+    intent: deserializeIntent(data.slice(0, INTENT_LENGTH)),
+    requester: new PublicKey(data.slice(INTENT_LENGTH, INTENT_LENGTH + 32)),
+    signature: data.slice(INTENT_LENGTH + 32),
+  };
+  if (envelope.intent.version !== PAYMENT_INTENT_VERSION) {
+    throw new Error(`Unsupported payment request version ${envelope.intent.version}`);
+  }
+  if (!verifyEnvelope(envelope)) {
+    throw new Error('Payment request signature is invalid');
+  }
+  if (now > envelope.intent.expiresAt) {
+    throw new Error('Payment request has expired');
+  }
+  return envelope;
+}
@@ -0,0 +1,147 @@
+/**
+ * Program error table
+ * Generated from the obscura_per IDL by `npm run gen-errors` in
+ * Backend/backend-mobile; do not edit by hand.
+ */
+
+export interface ProgramErrorEntry {
+  name: string;
+  msg: string;
+}
+
+export const PROGRAM_ERRORS: Record<number, ProgramErrorEntry> = {
+  6000: { name: "NotDelegated", msg: "The account is not currently delegated to an ER validator" },
+  6001: { name: "InsufficientBalance", msg: "Insufficient balance for this operation" },
+  6002: { name: "Unauthorized", msg: "You are not authorized to perform this action" },
+  6003: { name: "AccountDelegated", msg: "The account is currently delegated — undelegate first" },
+  6004: { name: "InvalidValidator", msg: "Invalid validator pubkey" },
+  6005: { name: "PermissionExists", msg: "Permission already exists for this pubkey" },
+  6006: { name: "NotPrivate", msg: "Vault is not in private mode" },
+  6007: { name: "ValidatorDefunct", msg: "Validator has already been marked defunct" },
+  6008: { name: "ValidatorNotDefunct", msg: "Validator has not been marked defunct" },
+  6009: { name: "ForceSettleDelayActive", msg: "The public force-settlement delay has not elapsed yet" },
+  6010: { name: "ValidatorScoreTooLow", msg: "Validator score is below the vault's minimum" },
+  6011: { name: "InvalidObservation", msg: "Observation values are out of range" },
+  6012: { name: "UnsupportedSdkVersion", msg: "Unsupported delegation SDK version" },
+  6013: { name: "MathOverflow", msg: "Arithmetic overflow" },
+  6014: { name: "FeatureDisabled", msg: "This feature is not enabled on this cluster" },
+  6015: { name: "PermissionSuspended", msg: "Permission is suspended" },
+  6016: { name: "PermissionNotSuspended", msg: "Permission is not suspended" },
+  6017: { name: "InvalidInstructionScope", msg: "Instruction whitelist is empty or contains non-delegable instructions" },
+  6018: { name: "InvalidIntent", msg: "Intent is empty, too long, or has an oversized memo" },
+  6019: { name: "InvalidFeeRate", msg: "Fee rate exceeds 100%" },
+  6020: { name: "NoFeesToClose", msg: "No finished billing period to close for this vault" },
+  6021: { name: "SameVault", msg: "Source and destination vault are the same" },
+  6022: { name: "RebalanceLimitExceeded", msg: "Daily rebalance limit reached for this vault" },
+  6023: { name: "ReceiveOnly", msg: "Vault is in receive-only mode" },
+  6024: { name: "NotReceiveOnly", msg: "Vault is not in receive-only mode" },
+  6025: { name: "ReceiveOnlyTimelockActive", msg: "Receive-only unlock has not matured yet" },
+  6026: { name: "VelocityStepUpRequired", msg: "Transfer exceeds the vault's velocity norm" },
+  6027: { name: "InvalidVelocityPolicy", msg: "Velocity factor must be 0 or at least 100%" },
+  6028: { name: "UpgradeNotAcknowledged", msg: "Program upgrade not yet acknowledged for this strict-mode vault" },
+  6029: { name: "ProgramVersionMismatch", msg: "Version does not match the running program" },
+  6030: { name: "UnsupportedMint", msg: "Mint uses a Token-2022 extension the vault cannot custody" },
+  6031: { name: "VersionNotIncreasing", msg: "Published version must be newer than the current one" },
+  6032: { name: "FeePaymentCapExceeded", msg: "Fee payment exceeds the vault's per-transaction cap" },
+  6033: { name: "TokenBalanceNotEmpty", msg: "Token sub-balance is not empty" },
+  6034: { name: "DisputeWindowClosed", msg: "Dispute window for these relayer fees has closed" },
+  6035: { name: "DisputeWindowActive", msg: "Relayer fees are still inside the dispute window" },
+  6036: { name: "NoRelayerFees", msg: "No relayer fees to settle, dispute or claim" },
+  6037: { name: "RelayerCapExceeded", msg: "Relayer ledger cap exceeded" },
+  6038: { name: "InvalidClaimant", msg: "Custodial vaults need a claimant key" },
+  6039: { name: "NotCustodial", msg: "Vault is not custodial" },
+  6040: { name: "InvalidGoal", msg: "Invalid savings goal timing" },
+  6041: { name: "GoalLocked", msg: "Savings goal is still locked" },
+  6042: { name: "MetadataTooLong", msg: "Vault label or memo too long" },
+  6043: { name: "OwnerIndexFull", msg: "Owner index is full" },
+  6044: { name: "InvalidRoundUpRule", msg: "Invalid round-up rule" },
+  6045: { name: "InvalidSavingsVault", msg: "Savings vault does not match the round-up rule" },
+  6046: { name: "InvalidSplitRule", msg: "Invalid split rule" },
+  6047: { name: "InvalidSplitVault", msg: "Split vault does not match the split rule" },
+  6048: { name: "InvalidLimitOrder", msg: "Invalid limit order" },
+  6049: { name: "InvalidDexAdapter", msg: "Not the configured DEX adapter" },
+  6050: { name: "DexAdapterMismatch", msg: "DEX adapter moved unexpected amounts" },
+  6051: { name: "LimitPriceViolated", msg: "Fills do not meet the limit price" },
+  6052: { name: "InvalidExpiry", msg: "Expiry must be in the future" },
+  6053: { name: "PermissionNotExpired", msg: "Permission has not expired" },
+  6054: { name: "InvalidConditionalTransfer", msg: "Invalid conditional transfer" },
+  6055: { name: "ConditionNotMet", msg: "Transfer condition is not met yet" },
+  6056: { name: "MissingPriceUpdate", msg: "Price condition needs a Pyth price update" },
+  6057: { name: "InvalidScopes", msg: "Invalid permission scopes" },
+  6058: { name: "InvalidPermissionBatch", msg: "Invalid permission batch" },
+  6059: { name: "NotArbitrated", msg: "Conditional transfer has no arbiter" },
+  6060: { name: "InvalidEvidence", msg: "Invalid evidence blob" },
+  6061: { name: "EvidenceLockerFull", msg: "Evidence locker is full" },
+  6062: { name: "InvalidAclPermission", msg: "Access Control permission account mismatch" },
+  6063: { name: "InvalidIntegrator", msg: "Integrator account missing or does not match" },
+  6064: { name: "NoIntegratorFees", msg: "No integrator fees to settle this month" },
+  6065: { name: "InvalidInspection", msg: "Not an inspectable vault account" },
+  6066: { name: "SpendingLimitExceeded", msg: "Transfer exceeds the vault's per-epoch spending limit" },
+  6067: { name: "InvalidSessionBundle", msg: "Session bundle empty, too long or addressed to the posting device" },
+  6068: { name: "SessionHandoffExpired", msg: "Session handoff has expired" },
+  6069: { name: "SessionHandoffActive", msg: "Session handoff has not expired yet" },
+  6070: { name: "WithdrawalLimitExceeded", msg: "Withdrawal exceeds the vault's 24h withdrawal limit" },
+  6071: { name: "WithdrawalOverrideRequired", msg: "Raising the withdrawal limit needs a matured override" },
+  6072: { name: "InvalidCapability", msg: "Invalid capability or capability destination" },
+  6073: { name: "CapabilityExpired", msg: "Capability has expired" },
+  6074: { name: "CapabilityAmountExceeded", msg: "Amount is zero or exceeds the capability's max_amount" },
+  6075: { name: "InvalidMultisig", msg: "Invalid multisig signer set or threshold" },
+  6076: { name: "InvalidAction", msg: "Action too large, stale or not matching its accounts" },
+  6077: { name: "AlreadyApproved", msg: "Signer already approved this action" },
+  6078: { name: "ThresholdNotMet", msg: "Action does not have enough approvals" },
+  6079: { name: "InvalidGuardianSet", msg: "Invalid guardian, guardian set or threshold" },
+  6080: { name: "RecoveryPending", msg: "A recovery is already pending" },
+  6081: { name: "NoRecovery", msg: "No recovery is pending" },
+  6082: { name: "RecoveryNotReady", msg: "Recovery not yet approved or still in its challenge period" },
+  6083: { name: "StaleBaseSlot", msg: "Transaction was built against vault state older than its last settlement" },
+  6084: { name: "WithdrawalTimelocked", msg: "Withdrawal is over the timelock threshold or still in its delay" },
+  6085: { name: "InvalidInactivityThreshold", msg: "Inactivity threshold is below the minimum" },
+  6086: { name: "VaultNotInactive", msg: "Vault owner has not been inactive long enough to sweep" },
+  6087: { name: "InvalidSessionKey", msg: "Invalid session key parameters" },
+  6088: { name: "SessionKeyExpired", msg: "Session key has expired" },
+  6089: { name: "SessionKeyAmountExceeded", msg: "Amount exceeds the session key limit" },
+  6090: { name: "InvalidCompanion", msg: "Account is not a companion of this vault" },
+  6091: { name: "RecipientNotAllowed", msg: "Recipient is not on the vault allowlist" },
+  6092: { name: "InvalidRecipientAllowlist", msg: "Recipient already listed, not listed, or allowlist full" },
+  6093: { name: "InvalidGroup", msg: "Invalid expense group, member or participants" },
+  6094: { name: "GroupNotJoined", msg: "Not every group member has joined" },
+  6095: { name: "RecipientDenied", msg: "Recipient is on the integrator denylist" },
+  6096: { name: "InvalidDenylist", msg: "Invalid denylist" },
+  6097: { name: "InvalidComplianceProgram", msg: "Invalid compliance program" },
+  6098: { name: "VaultFrozen", msg: "Vault is frozen" },
+  6099: { name: "VaultNotFrozen", msg: "Vault is not frozen" },
+  6100: { name: "InvalidPosSession", msg: "Invalid point-of-sale session or payment" },
+  6101: { name: "PosSessionExpired", msg: "Point-of-sale session has expired" },
+  6102: { name: "InvalidBeneficiary", msg: "Invalid beneficiary or inheritance period" },
+  6103: { name: "VaultStillActive", msg: "Vault has not been inactive for its inheritance period" },
+  6104: { name: "InvalidPaymentIntent", msg: "Invalid payment intent" },
+  6105: { name: "PaymentIntentExpired", msg: "Payment intent has expired" },
+  6106: { name: "CosignerRequired", msg: "Vault co-signer signature required" },
+  6107: { name: "InvalidCosigner", msg: "Invalid co-signer" },
+  6108: { name: "InvalidReceiptAccounts", msg: "Invalid receipt mint accounts" },
+  6109: { name: "InvalidScheduledTransfer", msg: "Invalid scheduled transfer" },
+  6110: { name: "ScheduledTransferNotDue", msg: "Scheduled transfer is not due yet" },
+  6111: { name: "InvalidRateCard", msg: "Rate card charges fees without a payout account" },
+  6112: { name: "OperatorFeesOutstanding", msg: "Operator fees from an earlier delegation are unsettled" },
+  6113: { name: "NoOperatorFees", msg: "No operator fees to settle" },
+  6114: { name: "InvalidSubscription", msg: "Invalid subscription" },
+  6115: { name: "SubscriptionNotDue", msg: "Subscription payment is not due yet" },
+  6116: { name: "InvalidStream", msg: "Invalid payment stream" },
+  6117: { name: "NothingVested", msg: "Nothing has vested since the last withdrawal" },
+  6118: { name: "InvalidTransferBatch", msg: "Invalid transfer batch" },
+  6119: { name: "StaleValidatorScore", msg: "The validator score has no recent observation" },
+  6120: { name: "VaultAlreadyMigrated", msg: "Vault already has the current layout" },
+  6121: { name: "InvalidVaultLayout", msg: "Account is not a vault in a known layout" },
+  6122: { name: "WeakerDestinationPolicy", msg: "Destination vault has a weaker outflow policy than the source" },
+  6123: { name: "DexAccountNotAllowed", msg: "DEX adapter accounts include a vault token account outside the order" },
+  6124: { name: "FeePaymentRepeated", msg: "Fees can be paid from a vault once per transaction" },
+  6125: { name: "InvalidRating", msg: "Rating score out of range" },
+  6126: { name: "AlreadyRated", msg: "Counterparty already rated" },
+  6127: { name: "RatingClosed", msg: "Escrow is not completed or its rating window has passed" },
+  6128: { name: "RatingTicketOpen", msg: "Rating ticket is still open" },
+  6129: { name: "NotEligibleToVote", msg: "Vault is too new to vote on this config change" },
+  6130: { name: "VotingClosed", msg: "Voting on this config change has closed" },
+  6131: { name: "ConfigChangeTimelocked", msg: "Config change is still in its delay" },
+  6132: { name: "ConfigChangeVetoed", msg: "Config change was vetoed by vault owners" },
+  6133: { name: "ConfigChangeOpen", msg: "Config change is still queued" },
+};
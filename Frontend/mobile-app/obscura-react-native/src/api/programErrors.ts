@@ -0,0 +1,186 @@
+/**
+ * Program errors
+ * Decodes the program's custom error codes into the `ObscuraError` variant
+ * and message (from the IDL-generated table) plus a hint on what the user
+ * should do about it.
+ * Regenerate the table with `npm run gen-errors` in Backend/backend-mobile.
+ */
+
+import { PROGRAM_ERRORS } from './programErrors.generated';
+
+export interface DecodedProgramError {
+  code: string;
+  number: number;
+  message: string;
+  remediation?: string;
+}
+
+/** What to do about each error, keyed by `ObscuraError` variant. */
+export const REMEDIATION: Record<string, string> = {
+  NotDelegated: 'Call delegate_vault first',
+  InsufficientBalance: 'Deposit more SOL or lower the amount',
+  Unauthorized: 'Sign with the vault owner (or the authorized key)',
+  AccountDelegated: 'Call undelegate_vault and wait for settlement',
+  InvalidValidator: 'Pass the validator the vault is delegated to',
+  PermissionExists: 'The permission is already granted; nothing to do',
+  NotPrivate: 'Delegate the vault to the TEE validator',
+  ValidatorDefunct: 'The validator is already marked defunct',
+  ValidatorNotDefunct: 'Mark the validator defunct first',
+  ForceSettleDelayActive: 'Retry after the public delay has elapsed',
+  ValidatorScoreTooLow: "Pick a higher-scoring validator or lower the vault's minimum score",
+  InvalidObservation: 'Check the value ranges and resubmit',
+  UnsupportedSdkVersion: 'Use a validator on the current delegation SDK version',
+  MathOverflow: 'Lower the amount; the result does not fit in a u64',
+  FeatureDisabled: 'Ask governance to enable the feature flag',
+  PermissionSuspended: 'Restore the permission with restore_permission first',
+  PermissionNotSuspended: 'Nothing to restore; the permission is already active',
+  InvalidInstructionScope: 'Use a non-empty combination of the IX_* constants',
+  InvalidIntent: 'Send between 1 and MAX_INTENT_OPS ops with memos up to MAX_MEMO_LEN bytes',
+  InvalidFeeRate: 'Use a fee rate of at most BPS_DENOMINATOR basis points',
+  NoFeesToClose: "Close the vault's pending_period once its month has ended",
+  SameVault: 'Pass two different vaults to internal_transfer',
+  RebalanceLimitExceeded: 'Wait until the next UTC day or use internal_transfer',
+  ReceiveOnly: 'Lift receive-only mode (request_receive_only_unlock, then lift_receive_only) before sending',
+  NotReceiveOnly: 'Only call this on a receive-only vault',
+  ReceiveOnlyTimelockActive: 'Call request_receive_only_unlock and wait RECEIVE_ONLY_UNLOCK_DELAY',
+  VelocityStepUpRequired: 'Have the policy cosigner sign, or request_velocity_override and wait VELOCITY_OVERRIDE_DELAY',
+  InvalidVelocityPolicy: 'Pass 0 to disable, or a factor_pct of 100 or more',
+  UpgradeNotAcknowledged: 'Review the release, then call acknowledge_program_version with PROGRAM_VERSION',
+  ProgramVersionMismatch: 'Pass the deployed PROGRAM_VERSION, or 0 to leave strict mode',
+  UnsupportedMint: 'Permanent-delegate and non-transferable mints cannot be deposited',
+  VersionNotIncreasing: 'Bump the semver before calling publish_program_version',
+  FeePaymentCapExceeded: 'Raise the cap with set_fee_payment_cap or lower the priority fee',
+  TokenBalanceNotEmpty: 'Withdraw the remaining tokens before closing the sub-balance',
+  DisputeWindowClosed: 'Disputes must be raised within RELAYER_DISPUTE_WINDOW of settlement',
+  DisputeWindowActive: 'Wait until claimable_at before claiming',
+  NoRelayerFees: 'Accrue or settle fees first',
+  RelayerCapExceeded: 'Settle and claim outstanding fees, or have the owner reopen the ledger with a higher cap',
+  InvalidClaimant: "Generate a claim key on the user's device and pass its pubkey",
+  NotCustodial: 'Only custodial vaults can be claimed',
+  InvalidGoal: 'Use an unlock time in the future; matured goals are released, not broken',
+  GoalLocked: 'Wait until unlock_at, or use break_goal and pay the early-break fee',
+  MetadataTooLong: 'Keep the label within MAX_LABEL_LEN and the memo within MAX_ENCRYPTED_MEMO_LEN bytes',
+  OwnerIndexFull: 'Unindex a vault first; an index lists at most MAX_INDEXED_VAULTS vaults',
+  InvalidRoundUpRule: 'Pass a savings vault with a non-zero increment',
+  InvalidSavingsVault: "Pass the vault's round_up.savings_vault as savings_vault",
+  InvalidSplitRule: 'Use at most BPS_DENOMINATOR bps and pass a split vault with a non-zero share',
+  InvalidSplitVault: "Pass the vault's split.split_vault as split_vault",
+  InvalidLimitOrder: 'Use non-zero amounts and two different mints',
+  InvalidDexAdapter: 'Pass the program set with set_dex_adapter',
+  DexAdapterMismatch: 'Check the adapter instruction; it must escrow exactly the order amount and only pay funds back in',
+  LimitPriceViolated: 'Settle the remaining fills before closing the order',
+  InvalidExpiry: 'Pass a future unix timestamp, or None for no expiry',
+  PermissionNotExpired: 'Wait until expires_at, or have the owner call revoke_permission',
+  InvalidConditionalTransfer: 'Escrow a non-zero amount',
+  ConditionNotMet: 'Retry once the price, time or attestation condition holds',
+  MissingPriceUpdate: "Pass a fresh PriceUpdateV2 account for the condition's feed as price_update",
+  InvalidScopes: 'Pass a non-empty combination of SCOPE_READ, SCOPE_TRANSFER, SCOPE_ADMIN and SCOPE_REPUTATION',
+  InvalidPermissionBatch: 'Pass 1..=MAX_PERMISSION_BATCH keys with their permission PDAs, in order, as remaining accounts',
+  NotArbitrated: 'Only transfers with an Attestation condition have an evidence locker',
+  InvalidEvidence: 'Submit 1..=MAX_EVIDENCE_LEN encrypted bytes',
+  EvidenceLockerFull: 'Ask the arbiter to resolve with the evidence already submitted',
+  InvalidAclPermission: 'Pass the ACL permission PDA derived from the vault and permitted key',
+  InvalidIntegrator: 'Pass the Integrator the vault was created through',
+  NoIntegratorFees: 'Wait for the next closed fee statement or month',
+  InvalidInspection: 'Pass 1 to MAX_INSPECTED_VAULTS vault PDAs as remaining accounts',
+  SpendingLimitExceeded: 'Wait for the next epoch or raise the limit with set_spending_limit',
+  InvalidSessionBundle: 'Post a 1 to MAX_SESSION_BUNDLE_LEN byte bundle for another registered device',
+  SessionHandoffExpired: 'Ask the other device to post the session again',
+  SessionHandoffActive: 'Wait for expires_at, or close it from the posting device',
+  WithdrawalLimitExceeded: 'Wait for the window to roll over or use request_withdrawal_override',
+  WithdrawalOverrideRequired: 'Call request_withdrawal_override and wait WITHDRAWAL_OVERRIDE_DELAY',
+  InvalidCapability: 'Mint with max_amount > 0 and expiry within MAX_CAPABILITY_TTL; pass the destination matching verb and object',
+  CapabilityExpired: 'Ask the owner to mint a new capability',
+  CapabilityAmountExceeded: 'Execute with an amount up to max_amount',
+  InvalidMultisig: 'Use 1 to MAX_VAULT_OWNERS distinct signers and 1 <= threshold <= signers',
+  InvalidAction: "Re-propose under the current signer set and pass the action's accounts in order",
+  AlreadyApproved: 'Nothing to do; wait for the other signers',
+  ThresholdNotMet: 'Collect approvals from threshold signers first',
+  InvalidGuardianSet: 'Use up to MAX_GUARDIANS distinct guardians other than the owner and 1 <= threshold <= guardians',
+  RecoveryPending: 'Approve it, or wait until it can be replaced',
+  NoRecovery: 'Call initiate_recovery first',
+  RecoveryNotReady: 'Collect threshold approvals and wait RECOVERY_CHALLENGE_PERIOD',
+  StaleBaseSlot: 'Re-read the vault and rebuild the transaction with a current base slot',
+  WithdrawalTimelocked: 'Queue it with pending_withdrawal and execute after WITHDRAWAL_TIMELOCK_DELAY',
+  InvalidInactivityThreshold: 'Use 0 or at least MIN_INACTIVITY_UNDELEGATE_AFTER',
+  VaultNotInactive: 'Check check_undelegate_eligibility before sweeping',
+  InvalidSessionKey: 'Use a non-owner key, IX_SESSIONABLE instructions and an expiry within MAX_SESSION_KEY_TTL',
+  SessionKeyExpired: 'Create a new session key with the main key',
+  SessionKeyAmountExceeded: 'Sign with the main key or create a session key with a higher max_amount',
+  InvalidCompanion: "Pass only the vault's HistoryView and VaultTokenBalance accounts",
+  RecipientNotAllowed: 'Add the recipient with add_allowed_recipient or pass the recipient_allowlist account',
+  InvalidRecipientAllowlist: 'Check the allowlist; it holds at most MAX_ALLOWED_RECIPIENTS',
+  InvalidGroup: 'Use 2..=MAX_GROUP_MEMBERS distinct vaults and a participants bitmap over member indices',
+  GroupNotJoined: 'Ask the remaining members to call join_group',
+  RecipientDenied: "Choose another recipient; the vault's integrator blocks this one",
+  InvalidDenylist: 'Pass the integrator denylist account; it holds at most MAX_DENIED_RECIPIENTS',
+  InvalidComplianceProgram: 'Pass the program set with set_compliance_program',
+  VaultFrozen: 'Unfreeze with unfreeze_vault, signed by the freeze guardian if one is set',
+  VaultNotFrozen: 'Only a frozen vault can be unfrozen',
+  InvalidPosSession: 'Use a non-default operator, a duration up to MAX_POS_SESSION_DURATION and a non-zero amount',
+  PosSessionExpired: 'Close it with close_pos_session and open a new session',
+  InvalidBeneficiary: 'Name a key other than the owner with an inactivity period of at least MIN_INHERITANCE_PERIOD',
+  VaultStillActive: "Wait until last_activity is older than the vault's inheritance_period",
+  InvalidPaymentIntent: 'Check the intent version, a non-zero SOL amount and that the recipient accounts match its alias',
+  PaymentIntentExpired: 'Ask the requester for a new payment request',
+  CosignerRequired: "Have the vault's co-signer sign as the cosigner account",
+  InvalidCosigner: 'Pick a co-signer other than the owner',
+  InvalidReceiptAccounts: 'Pass the receipt accounts in order, with the configured receipt tree',
+  InvalidScheduledTransfer: 'Schedule a non-zero amount to a recipient at a future time',
+  ScheduledTransferNotDue: 'Retry once execute_after has passed',
+  InvalidRateCard: 'Set a payout account, or publish an all-zero rate card',
+  OperatorFeesOutstanding: 'Call settle_operator_fees before delegating again',
+  NoOperatorFees: 'Nothing is owed, or the vault has no unlocked balance to pay from',
+  InvalidSubscription: 'Use a non-zero amount, a recipient and an interval of at least MIN_SUBSCRIPTION_INTERVAL',
+  SubscriptionNotDue: 'Retry once next_due has passed',
+  InvalidStream: 'Use a non-zero rate, an end after both start and now, and another vault as recipient',
+  NothingVested: 'Retry once more of the stream has vested',
+  InvalidTransferBatch: 'Send between 1 and MAX_TRANSFER_BATCH transfers',
+  StaleValidatorScore: 'Wait for the keeper to submit a fresh observation, or pick another validator',
+  VaultAlreadyMigrated: 'Nothing to migrate; the vault can be used as is.',
+  InvalidVaultLayout: 'Pass the vault PDA derived from its vault_id.',
+  WeakerDestinationPolicy: "Give the destination vault at least the source vault's spending limit, withdrawal limits, cosigner and allowlist before moving funds into it.",
+  DexAccountNotAllowed: "Pass only the order's input and output token accounts of the vault to the DEX adapter.",
+  FeePaymentRepeated: 'Pay the whole fee in a single pay_fees_from_vault instruction at the top level of the transaction.',
+  InvalidRating: 'Use a public score from 1 to MAX_RATING_SCORE',
+  AlreadyRated: 'Each party rates the other once per escrow',
+  RatingClosed: 'Rate within RATING_WINDOW of the escrow executing with its ticket',
+  RatingTicketOpen: 'Wait until both parties rate, RATING_WINDOW passes or the escrow is gone',
+  NotEligibleToVote: 'Only vaults at least a day old when the change was queued can vote',
+  VotingClosed: 'Votes are only accepted until the change becomes executable',
+  ConfigChangeTimelocked: 'Wait until executable_at, CONFIG_CHANGE_DELAY after it was queued',
+  ConfigChangeVetoed: 'Governance can cancel it with cancel_config_change',
+  ConfigChangeOpen: 'Close vote records once the change is executed or cancelled',
+};
+
+/** Decode a raw `custom program error` code returned by the program. */
+export function decodeProgramError(code: number): DecodedProgramError | null {
+  const entry = PROGRAM_ERRORS[code];
+  if (!entry) {
+    return null;
+  }
+  return {
+    code: entry.name,
+    number: code,
+    message: entry.msg,
+    remediation: REMEDIATION[entry.name],
+  };
+}
+
+/**
+ * Pull the custom error code out of a transaction error, as returned in
+ * `value.err` by simulateTransaction / getSignatureStatuses, or from the
+ * `custom program error: 0x...` text of a thrown send error.
+ */
+export function programErrorCode(err: unknown): number | null {
+  if (err && typeof err === 'object' && 'InstructionError' in err) {
+    const [, detail] = (err as { InstructionError: [number, unknown] }).InstructionError;
+    if (detail && typeof detail === 'object' && 'Custom' in detail) {
+      return Number((detail as { Custom: number }).Custom);
+    }
+    return null;
+  }
+  const text = err instanceof Error ? err.message : String(err);
+  const match = text.match(/custom program error: (0x[0-9a-f]+)/i);
+  return match ? parseInt(match[1], 16) : null;
+}
@@ -0,0 +1,191 @@
+/**
+ * RPC failover
+ * A pool of RPC endpoints for one chain (L1, or one ER validator) with
+ * background health checks.  Its `Connection` sends every JSON-RPC
+ * request to a healthy endpoint picked by weight and retries on the next
+ * endpoint when one errors, rate-limits or times out, so a single RPC
+ * outage no longer takes the app down.
+ * Endpoints: Backend/backend-mobile/clusters.toml
+ */
+
+import { Commitment, Connection } from '@solana/web3.js';
+
+export interface RpcEndpoint {
+  url: string;
+  /**
+   * Share of traffic among healthy endpoints; 0 marks a fallback, only
+   * used while no weighted endpoint is healthy.
+   */
+  weight: number;
+}
+
+export interface EndpointStatus {
+  endpoint: RpcEndpoint;
+  healthy: boolean;
+  /** Consecutive failed requests or health checks */
+  failures: number;
+  /** Slot at the last health check */
+  slot: number | null;
+  /** Too far behind the other endpoints at the last health check */
+  lagging: boolean;
+}
+
+const REQUEST_TIMEOUT_MS = 8000;
+const HEALTH_CHECK_INTERVAL_MS = 15000;
+/** Consecutive failures before an endpoint leaves the rotation */
+const FAILURE_THRESHOLD = 2;
+/** Slots behind the most advanced endpoint before one counts as stale */
+const MAX_SLOT_LAG = 50;
+
+async function fetchWithTimeout(
+  url: string,
+  init: RequestInit | undefined,
+  timeoutMs: number
+): Promise<Response> {
+  const controller = new AbortController();
+  const timer = setTimeout(() => controller.abort(), timeoutMs);
+  try {
+    return await fetch(url, { ...init, signal: controller.signal });
+  } finally {
+    clearTimeout(timer);
+  }
+}
+
+export class RpcPool {
+  private statuses: EndpointStatus[];
+  private timer: ReturnType<typeof setInterval> | null = null;
+
+  constructor(
+    endpoints: RpcEndpoint[],
+    private timeoutMs = REQUEST_TIMEOUT_MS
+  ) {
+    if (endpoints.length === 0) {
+      throw new Error('RpcPool needs at least one endpoint');
+    }
+    this.statuses = endpoints.map((endpoint) => ({
+      endpoint,
+      healthy: true,
+      failures: 0,
+      slot: null,
+      lagging: false,
+    }));
+  }
+
+  status(): EndpointStatus[] {
+    return this.statuses.map((status) => ({ ...status }));
+  }
+
+  /**
+   * Endpoints in the order to try them: healthy weighted ones in a
+   * weighted random order, then healthy fallbacks, then the unhealthy
+   * ones as a last resort.
+   */
+  private candidates(): EndpointStatus[] {
+    const usable = (s: EndpointStatus) => s.healthy && !s.lagging;
+    const weighted = this.statuses
+      .filter((s) => usable(s) && s.endpoint.weight > 0)
+      .map((s) => ({ s, key: Math.random() ** (1 / s.endpoint.weight) }))
+      .sort((a, b) => b.key - a.key)
+      .map(({ s }) => s);
+    const fallbacks = this.statuses.filter(
+      (s) => usable(s) && s.endpoint.weight === 0
+    );
+    const unhealthy = this.statuses.filter((s) => !usable(s));
+    return [...weighted, ...fallbacks, ...unhealthy];
+  }
+
+  private report(status: EndpointStatus, ok: boolean) {
+    status.failures = ok ? 0 : status.failures + 1;
+    status.healthy = ok || status.failures < FAILURE_THRESHOLD;
+  }
+
+  /** `fetch` for web3.js: the request goes to the pool, not `input`. */
+  fetch = async (_input: unknown, init?: RequestInit): Promise<Response> => {
+    let lastError: unknown = null;
+    for (const status of this.candidates()) {
+      try {
+        const response = await fetchWithTimeout(
+          status.endpoint.url,
+          init,
+          this.timeoutMs
+        );
+        if (response.status === 429 || response.status >= 500) {
+          this.report(status, false);
+          lastError = new Error(
+            `${status.endpoint.url}: HTTP ${response.status}`
+          );
+          continue;
+        }
+        this.report(status, true);
+        return response;
+      } catch (err) {
+        this.report(status, false);
+        lastError = err;
+      }
+    }
+    throw lastError;
+  };
+
+  /** Poll every endpoint's slot and take failing or stale ones out. */
+  async checkHealth(): Promise<void> {
+    await Promise.all(
+      this.statuses.map(async (status) => {
+        try {
+          const response = await fetchWithTimeout(
+            status.endpoint.url,
+            {
+              method: 'POST',
+              headers: { 'Content-Type': 'application/json' },
+              body: JSON.stringify({
+                jsonrpc: '2.0',
+                id: 1,
+                method: 'getSlot',
+                params: [{ commitment: 'confirmed' }],
+              }),
+            },
+            this.timeoutMs
+          );
+          const { result } = await response.json();
+          if (!response.ok || typeof result !== 'number') {
+            throw new Error(`HTTP ${response.status}`);
+          }
+          status.slot = result;
+          this.report(status, true);
+        } catch {
+          status.slot = null;
+          this.report(status, false);
+        }
+      })
+    );
+
+    const best = Math.max(...this.statuses.map((s) => s.slot ?? 0));
+    for (const status of this.statuses) {
+      status.lagging =
+        status.slot !== null && best - status.slot > MAX_SLOT_LAG;
+    }
+  }
+
+  start(intervalMs = HEALTH_CHECK_INTERVAL_MS) {
+    if (this.timer) return;
+    this.checkHealth();
+    this.timer = setInterval(() => this.checkHealth(), intervalMs);
+  }
+
+  stop() {
+    if (this.timer) {
+      clearInterval(this.timer);
+      this.timer = null;
+    }
+  }
+
+  /**
+   * A Connection whose requests go through the pool.  Subscriptions use
+   * the first endpoint's websocket.
+   */
+  connection(commitment: Commitment = 'confirmed'): Connection {
+    return new Connection(this.statuses[0].endpoint.url, {
+      commitment,
+      fetch: this.fetch as typeof fetch,
+    });
+  }
+}
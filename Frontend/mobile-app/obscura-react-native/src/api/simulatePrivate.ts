@@ -0,0 +1,191 @@
+/**
+ * Private simulation
+ * Dry-runs a proposed vault transaction on the TEE validator, against the
+ * current private state, so the app can show the exact outcome before
+ * the user signs.  The query only ever goes to the TEE endpoint, behind
+ * an auth token held by a key with a permission on the vault; nothing
+ * is sent to public RPC.
+ * TEE endpoint: the private validator's `er_rpc_url` in
+ * Backend/backend-mobile/clusters.toml
+ */
+
+import {
+  Connection,
+  PublicKey,
+  TransactionInstruction,
+  TransactionMessage,
+  VersionedTransaction,
+} from '@solana/web3.js';
+import bs58 from 'bs58';
+
+// Any well-formed blockhash; the validator replaces it when simulating.
+const PLACEHOLDER_BLOCKHASH = '11111111111111111111111111111111';
+const VAULT_PREFIX_LENGTH = 113;
+
+export interface PrivateSession {
+  rpcUrl: string;
+  publicKey: PublicKey;
+  token: string;
+  /** Unix timestamp (ms) */
+  expiresAt: number;
+}
+
+/** Leading, fixed-layout fields of the program's `VaultState`. */
+export interface VaultSnapshot {
+  owner: PublicKey;
+  vaultId: bigint;
+  balance: bigint;
+  isDelegated: boolean;
+  nonce: bigint;
+}
+
+/** A program error, as named in the program's `ObscuraError`. */
+export interface ProgramError {
+  code: string;
+  number: number;
+  message: string;
+}
+
+export type PrivateSimulation =
+  | {
+      ok: true;
+      before: VaultSnapshot;
+      after: VaultSnapshot;
+      unitsConsumed?: number;
+      logs: string[];
+    }
+  | {
+      ok: false;
+      before: VaultSnapshot;
+      /** Program error, or the raw transaction error */
+      error: ProgramError | string;
+      logs: string[];
+    };
+
+/**
+ * Get a TEE auth token for `publicKey` by signing the validator's
+ * challenge (e.g. with the wallet adapter's signMessage).  The TEE only
+ * shows a vault's private state to keys permitted on it.
+ */
+export async function openPrivateSession(
+  rpcUrl: string,
+  publicKey: PublicKey,
+  signMessage: (message: Uint8Array) => Promise<Uint8Array>
+): Promise<PrivateSession> {
+  const challengeResponse = await fetch(
+    `${rpcUrl}/auth/challenge?pubkey=${publicKey.toBase58()}`
+  );
+  const { challenge, error } = await challengeResponse.json();
+  if (!challengeResponse.ok || typeof challenge !== 'string') {
+    throw new Error(error || `TEE challenge failed: HTTP ${challengeResponse.status}`);
+  }
+
+  const signature = await signMessage(new TextEncoder().encode(challenge));
+  const loginResponse = await fetch(`${rpcUrl}/auth/login`, {
+    method: 'POST',
+    headers: { 'Content-Type': 'application/json' },
+    body: JSON.stringify({
+      pubkey: publicKey.toBase58(),
+      challenge,
+      signature: bs58.encode(signature),
+    }),
+  });
+  const login = await loginResponse.json();
+  if (!loginResponse.ok || typeof login.token !== 'string') {
+    throw new Error(login.error || `TEE login failed: HTTP ${loginResponse.status}`);
+  }
+  return {
+    rpcUrl,
+    publicKey,
+    token: login.token,
+    expiresAt: login.expiresAt ?? Date.now() + 60 * 60 * 1000,
+  };
+}
+
+/** Connection to the TEE that reads what `session`'s key may see. */
+export function sessionConnection(session: PrivateSession): Connection {
+  if (Date.now() >= session.expiresAt) {
+    throw new Error('TEE session has expired');
+  }
+  const token = encodeURIComponent(session.token);
+  return new Connection(`${session.rpcUrl}?token=${token}`, 'confirmed');
+}
+
+export function decodeVaultSnapshot(data: Uint8Array): VaultSnapshot {
+  if (data.length < VAULT_PREFIX_LENGTH) {
+    throw new Error('Malformed vault account');
+  }
+  const view = new DataView(data.buffer, data.byteOffset, data.byteLength);
+  return {
+    owner: new PublicKey(data.slice(8, 40)),
+    vaultId: view.getBigUint64(40, true),
+    balance: view.getBigUint64(48, true),
+    isDelegated: data[56] === 1,
+    nonce: view.getBigUint64(105, true),
+  };
+}
+
+/** The Anchor error a failed simulation logged, if any. */
+export function parseProgramError(logs: string[]): ProgramError | null {
+  for (const line of logs) {
+    const match = line.match(
+      /Error Code: (\w+)\. Error Number: (\d+)\. Error Message: (.*)\.$/
+    );
+    if (match) {
+      return { code: match[1], number: Number(match[2]), message: match[3] };
+    }
+  }
+  return null;
+}
+
+/**
+ * Simulate `instructions` (e.g. a `private_transfer`) on the TEE and
+ * report `vault` before and after.  Signatures are not checked, so the
+ * user is only asked to sign once they accept the outcome.
+ */
+export async function simulatePrivate(
+  session: PrivateSession,
+  vault: PublicKey,
+  instructions: TransactionInstruction[],
+  feePayer: PublicKey = session.publicKey
+): Promise<PrivateSimulation> {
+  const connection = sessionConnection(session);
+  const account = await connection.getAccountInfo(vault);
+  if (!account) {
+    throw new Error('Vault is not visible to this key on the TEE');
+  }
+  const before = decodeVaultSnapshot(account.data);
+
+  const message = new TransactionMessage({
+    payerKey: feePayer,
+    recentBlockhash: PLACEHOLDER_BLOCKHASH,
+    instructions,
+  }).compileToV0Message();
+  const transaction = new VersionedTransaction(message);
+  const { value } = await connection.simulateTransaction(transaction, {
+    sigVerify: false,
+    replaceRecentBlockhash: true,
+    accounts: { encoding: 'base64', addresses: [vault.toBase58()] },
+  });
+  const logs = value.logs ?? [];
+
+  if (value.err) {
+    return {
+      ok: false,
+      before,
+      error: parseProgramError(logs) ?? JSON.stringify(value.err),
+      logs,
+    };
+  }
+  const after = value.accounts?.[0];
+  if (!after) {
+    throw new Error('TEE returned no post-simulation vault state');
+  }
+  return {
+    ok: true,
+    before,
+    after: decodeVaultSnapshot(Buffer.from(after.data[0], 'base64')),
+    unitsConsumed: value.unitsConsumed,
+    logs,
+  };
+}
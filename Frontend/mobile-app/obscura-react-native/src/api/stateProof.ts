@@ -0,0 +1,183 @@
+/**
+ * Vault state proofs
+ * Bundles a vault's committed L1 state with the evidence for it: the
+ * signature and slot of the last transaction that touched the vault, and
+ * the account hash each of a quorum of independent RPC endpoints
+ * reported at finalized commitment.  A third party checks the bundle
+ * with `verifyVaultProof` instead of trusting the user's RPC.
+ */
+
+import { Connection, PublicKey } from '@solana/web3.js';
+import * as Crypto from 'expo-crypto';
+import { decodeVaultSnapshot, VaultSnapshot } from './simulatePrivate';
+
+export const VAULT_PROOF_VERSION = 1;
+
+export interface Attestation {
+  endpoint: string;
+  /** Slot the endpoint served the account at */
+  slot: number;
+  /** Hex SHA-256 of the account, see `accountHash` */
+  accountHash: string;
+}
+
+export interface VaultProof {
+  version: number;
+  vault: string;
+  /** Account owner: the program, or the delegation program if delegated */
+  owner: string;
+  lamports: number;
+  /** Account data, base64 */
+  data: string;
+  accountHash: string;
+  /** Last finalized transaction that touched the vault, and its slot */
+  signature: string;
+  slot: number;
+  attestations: Attestation[];
+}
+
+function u64(value: number): Uint8Array {
+  const out = new Uint8Array(8);
+  new DataView(out.buffer).setBigUint64(0, BigInt(value), true);
+  return out;
+}
+
+/** SHA-256 over address, owner, lamports (u64 LE) and data. */
+export async function accountHash(
+  vault: PublicKey,
+  owner: PublicKey,
+  lamports: number,
+  data: Uint8Array
+): Promise<string> {
+  const parts = [vault.toBytes(), owner.toBytes(), u64(lamports), data];
+  const message = new Uint8Array(
+    parts.reduce((length, part) => length + part.length, 0)
+  );
+  let offset = 0;
+  for (const part of parts) {
+    message.set(part, offset);
+    offset += part.length;
+  }
+  const digest = await Crypto.digest(
+    Crypto.CryptoDigestAlgorithm.SHA256,
+    message
+  );
+  return Buffer.from(digest).toString('hex');
+}
+
+/**
+ * Read `vault` from every endpoint in `rpcUrls` and bundle its state once
+ * at least `quorum` of them agree on it.
+ */
+export async function exportVaultProof(
+  vault: PublicKey,
+  rpcUrls: string[],
+  quorum = Math.floor((rpcUrls.length * 2) / 3) + 1
+): Promise<VaultProof> {
+  if (quorum > rpcUrls.length) {
+    throw new Error(`Quorum of ${quorum} needs as many endpoints`);
+  }
+
+  const readings = await Promise.all(
+    rpcUrls.map(async (endpoint) => {
+      try {
+        const connection = new Connection(endpoint, 'finalized');
+        const [{ context, value }, [latest]] = await Promise.all([
+          connection.getAccountInfoAndContext(vault),
+          connection.getSignaturesForAddress(vault, { limit: 1 }),
+        ]);
+        if (!value || !latest) {
+          return null;
+        }
+        return {
+          endpoint,
+          slot: context.slot,
+          account: value,
+          accountHash: await accountHash(
+            vault,
+            value.owner,
+            value.lamports,
+            value.data
+          ),
+          signature: latest.signature,
+          signatureSlot: latest.slot,
+        };
+      } catch {
+        return null;
+      }
+    })
+  );
+
+  // Endpoints agree if they report the same state and last transaction.
+  const groups = new Map<string, typeof readings>();
+  for (const reading of readings) {
+    if (!reading) continue;
+    const key = `${reading.accountHash}:${reading.signature}`;
+    groups.set(key, [...(groups.get(key) ?? []), reading]);
+  }
+  const agreeing = [...groups.values()].sort((a, b) => b.length - a.length)[0];
+  if (!agreeing || agreeing.length < quorum) {
+    throw new Error(
+      `Only ${agreeing?.length ?? 0} of ${rpcUrls.length} endpoints agree ` +
+        `on the vault state; ${quorum} needed`
+    );
+  }
+
+  const { account, accountHash: hash, signature, signatureSlot } =
+    agreeing[0]!;
+  return {
+    version: VAULT_PROOF_VERSION,
+    vault: vault.toBase58(),
+    owner: account.owner.toBase58(),
+    lamports: account.lamports,
+    data: Buffer.from(account.data).toString('base64'),
+    accountHash: hash,
+    signature,
+    slot: signatureSlot,
+    attestations: agreeing.map((reading) => ({
+      endpoint: reading!.endpoint,
+      slot: reading!.slot,
+      accountHash: reading!.accountHash,
+    })),
+  };
+}
+
+/**
+ * Check a proof bundle: the data matches its hash, and at least
+ * `minAttestations` endpoints attested to that hash no earlier than the
+ * last transaction's slot.  Returns the vault state it proves.
+ * RPC responses are not signed, so a verifier who doubts the exporter
+ * can re-query any attesting endpoint for `signature`.
+ */
+export async function verifyVaultProof(
+  proof: VaultProof,
+  minAttestations = 2
+): Promise<VaultSnapshot> {
+  if (proof.version !== VAULT_PROOF_VERSION) {
+    throw new Error(`Unsupported proof version ${proof.version}`);
+  }
+  const data = Buffer.from(proof.data, 'base64');
+  const hash = await accountHash(
+    new PublicKey(proof.vault),
+    new PublicKey(proof.owner),
+    proof.lamports,
+    data
+  );
+  if (hash !== proof.accountHash) {
+    throw new Error('Proof data does not match its account hash');
+  }
+
+  const endpoints = new Set<string>();
+  for (const attestation of proof.attestations) {
+    if (attestation.accountHash !== hash || attestation.slot < proof.slot) {
+      throw new Error(`Attestation from ${attestation.endpoint} disagrees`);
+    }
+    endpoints.add(attestation.endpoint);
+  }
+  if (endpoints.size < minAttestations) {
+    throw new Error(
+      `Proof has ${endpoints.size} attestations; ${minAttestations} needed`
+    );
+  }
+  return decodeVaultSnapshot(data);
+}
@@ -0,0 +1,474 @@
+/**
+ * Statement export
+ * Rebuilds a vault's history from its program transactions and writes it
+ * as CSV, OFX or ISO 20022 camt.053, for import into accounting software
+ * (QuickBooks, Xero).  A private vault's transfers only exist on its TEE
+ * validator, so its history is read through a TEE session opened with
+ * the owner's key (`openPrivateSession`, then `sessionConnection`);
+ * nothing about it goes to public RPC.  Each entry carries the payment
+ * intent reference where there is one, and a category the caller can
+ * remap.
+ */
+
+import {
+  Connection,
+  PublicKey,
+  VersionedTransactionResponse,
+} from '@solana/web3.js';
+import { deserializeIntent } from './paymentIntent';
+
+const LAMPORTS_PER_SOL = BigInt(1_000_000_000);
+const PAGE_SIZE = 1000;
+const INTENT_LENGTH = 114;
+
+export type Category =
+  | 'deposit'
+  | 'withdrawal'
+  | 'transfer_out'
+  | 'transfer_in'
+  | 'invoice_payment'
+  | 'invoice_received';
+
+export interface StatementEntry {
+  /** Unique per entry: the signature, and the leg of a batch */
+  id: string;
+  signature: string;
+  /** Unix timestamp (seconds) */
+  date: number;
+  direction: 'credit' | 'debit';
+  /** Lamports */
+  amount: bigint;
+  counterparty: string | null;
+  /** Hex payment intent reference, if the entry paid an intent */
+  reference: string | null;
+  category: Category;
+  /** Caller's category (e.g. an account code); `category` by default */
+  accountingCategory: string;
+}
+
+export interface Statement {
+  vault: string;
+  /** Three-letter code written to OFX and camt.053 */
+  currency: string;
+  /** Unix timestamps (seconds) */
+  from: number;
+  to: number;
+  /** Lamports */
+  openingBalance: bigint;
+  closingBalance: bigint;
+  entries: StatementEntry[];
+}
+
+export interface StatementOptions {
+  /** Unix timestamps (seconds), inclusive */
+  from: number;
+  to: number;
+  /** The vault's balance now (lamports), e.g. from `decodeVaultSnapshot` */
+  currentBalance: bigint;
+  currency?: string;
+  /** Remap an entry's category, e.g. by counterparty */
+  categorize?: (entry: StatementEntry) => string;
+}
+
+// sha256("global:<instruction>")[0..8], as joined by `discriminator`
+const DEPOSIT = '242,35,198,137,82,225,242,182';
+const WITHDRAW = '183,18,70,156,148,109,161,34';
+const EXECUTE_PENDING_WITHDRAWAL = '157,170,148,251,213,40,60,228';
+const PRIVATE_TRANSFER = '107,20,177,94,33,119,16,110';
+const PRIVATE_TRANSFER_BATCH = '51,125,73,141,149,223,88,150';
+const INTERNAL_TRANSFER = '56,217,60,137,252,221,185,114';
+const FULFILL_INTENT = '236,191,7,151,169,132,84,160';
+
+function discriminator(data: Uint8Array): string {
+  return Array.from(data.slice(0, 8)).join(',');
+}
+
+type Leg = Omit<
+  StatementEntry,
+  'id' | 'signature' | 'date' | 'accountingCategory'
+>;
+
+function hex(bytes: Uint8Array): string {
+  return Array.from(bytes, (b) => b.toString(16).padStart(2, '0')).join('');
+}
+
+/** The legs of one program instruction that touch `vault`. */
+function decodeLegs(
+  vault: string,
+  data: Uint8Array,
+  accounts: string[],
+  programId: string,
+  lamportDelta: () => bigint
+): Leg[] {
+  const view = new DataView(data.buffer, data.byteOffset, data.byteLength);
+  const u64 = (offset: number) => view.getBigUint64(offset, true);
+  const key = (offset: number) =>
+    new PublicKey(data.slice(offset, offset + 32)).toBase58();
+  const leg = (
+    direction: Leg['direction'],
+    amount: bigint,
+    counterparty: string | null,
+    category: Category,
+    reference: string | null = null
+  ): Leg => ({ direction, amount, counterparty, reference, category });
+
+  switch (discriminator(data)) {
+    case DEPOSIT:
+      if (accounts[0] !== vault) return [];
+      return [leg('credit', u64(8), accounts[1], 'deposit')];
+    case WITHDRAW:
+      // Over the timelock threshold it is only queued (pending_withdrawal
+      // passed); the funds move in execute_pending_withdrawal.
+      if (accounts[0] !== vault || accounts[4] !== programId) return [];
+      return [leg('debit', u64(8), accounts[1], 'withdrawal')];
+    case EXECUTE_PENDING_WITHDRAWAL:
+      if (accounts[0] !== vault) return [];
+      return [leg('debit', lamportDelta(), accounts[2], 'withdrawal')];
+    case PRIVATE_TRANSFER:
+      if (accounts[0] !== vault) return [];
+      return [leg('debit', u64(8), key(16), 'transfer_out')];
+    case PRIVATE_TRANSFER_BATCH: {
+      if (accounts[0] !== vault) return [];
+      const legs: Leg[] = [];
+      const count = view.getUint32(8, true);
+      for (let i = 0; i < count; i++) {
+        const offset = 12 + i * 40;
+        legs.push(leg('debit', u64(offset + 32), key(offset), 'transfer_out'));
+      }
+      return legs;
+    }
+    case INTERNAL_TRANSFER:
+      if (accounts[0] === vault) {
+        return [leg('debit', u64(8), accounts[1], 'transfer_out')];
+      }
+      if (accounts[1] === vault) {
+        return [leg('credit', u64(8), accounts[0], 'transfer_in')];
+      }
+      return [];
+    case FULFILL_INTENT: {
+      const intent = deserializeIntent(data.slice(8, 8 + INTENT_LENGTH));
+      const reference = hex(intent.reference);
+      if (accounts[1] === vault) {
+        return [
+          leg(
+            'debit',
+            intent.amount,
+            accounts[2],
+            'invoice_payment',
+            reference
+          ),
+        ];
+      }
+      if (accounts[2] === vault) {
+        return [
+          leg(
+            'credit',
+            intent.amount,
+            accounts[1],
+            'invoice_received',
+            reference
+          ),
+        ];
+      }
+      return [];
+    }
+    default:
+      return [];
+  }
+}
+
+/** Entries `tx` makes in `vault`'s statement, if it succeeded. */
+function entriesOf(
+  vault: string,
+  programId: string,
+  tx: VersionedTransactionResponse
+): Omit<StatementEntry, 'accountingCategory'>[] {
+  if (!tx.meta || tx.meta.err) return [];
+  if (tx.blockTime == null) {
+    throw new Error(`Transaction ${tx.transaction.signatures[0]} has no time`);
+  }
+  const message = tx.transaction.message;
+  const keys = message.getAccountKeys({
+    accountKeysFromLookups: tx.meta.loadedAddresses,
+  });
+  const meta = tx.meta;
+  const signature = tx.transaction.signatures[0];
+  const entries: Omit<StatementEntry, 'accountingCategory'>[] = [];
+  for (const ix of message.compiledInstructions) {
+    if (keys.get(ix.programIdIndex)?.toBase58() !== programId) continue;
+    const accounts = ix.accountKeyIndexes.map((i) => keys.get(i)!.toBase58());
+    const lamportDelta = () => {
+      const i = ix.accountKeyIndexes[0];
+      return BigInt(meta.preBalances[i] - meta.postBalances[i]);
+    };
+    for (const leg of decodeLegs(
+      vault,
+      ix.data,
+      accounts,
+      programId,
+      lamportDelta
+    )) {
+      entries.push({
+        ...leg,
+        id: `${signature}:${entries.length}`,
+        signature,
+        date: tx.blockTime,
+      });
+    }
+  }
+  return entries;
+}
+
+/**
+ * Build `vault`'s statement for a period from every connection in
+ * `sources`: L1, and the vault's ER or TEE session (see
+ * `openPrivateSession`) for what happened while it was delegated.
+ */
+export async function buildStatement(
+  vault: PublicKey,
+  programId: PublicKey,
+  sources: Connection[],
+  options: StatementOptions
+): Promise<Statement> {
+  const address = vault.toBase58();
+  const program = programId.toBase58();
+  const seen = new Set<string>();
+  const all: Omit<StatementEntry, 'accountingCategory'>[] = [];
+
+  for (const connection of sources) {
+    let before: string | undefined;
+    for (;;) {
+      const page = await connection.getSignaturesForAddress(vault, {
+        before,
+        limit: PAGE_SIZE,
+      });
+      for (const info of page) {
+        if (info.err || seen.has(info.signature)) continue;
+        // Older entries aren't needed: balances are walked back from now.
+        if (info.blockTime != null && info.blockTime < options.from) continue;
+        seen.add(info.signature);
+        const tx = await connection.getTransaction(info.signature, {
+          maxSupportedTransactionVersion: 0,
+        });
+        if (tx) all.push(...entriesOf(address, program, tx));
+      }
+      const last = page[page.length - 1];
+      if (
+        page.length < PAGE_SIZE ||
+        (last.blockTime != null && last.blockTime < options.from)
+      ) {
+        break;
+      }
+      before = last.signature;
+    }
+  }
+
+  // Walk back from the current balance to the period's ends.
+  const net = (entries: typeof all) =>
+    entries.reduce(
+      (sum, e) => (e.direction === 'credit' ? sum + e.amount : sum - e.amount),
+      BigInt(0)
+    );
+  const closingBalance =
+    options.currentBalance - net(all.filter((e) => e.date > options.to));
+  const inPeriod = all
+    .filter((e) => e.date <= options.to)
+    .sort((a, b) => a.date - b.date || a.id.localeCompare(b.id));
+  const entries = inPeriod.map((e) => {
+    const entry: StatementEntry = { ...e, accountingCategory: e.category };
+    entry.accountingCategory = options.categorize?.(entry) ?? e.category;
+    return entry;
+  });
+  return {
+    vault: address,
+    currency: options.currency ?? 'SOL',
+    from: options.from,
+    to: options.to,
+    openingBalance: closingBalance - net(inPeriod),
+    closingBalance,
+    entries,
+  };
+}
+
+/** Lamports as a decimal SOL amount. */
+function sol(lamports: bigint): string {
+  const sign = lamports < 0 ? '-' : '';
+  const abs = lamports < 0 ? -lamports : lamports;
+  const fraction = (abs % LAMPORTS_PER_SOL).toString().padStart(9, '0');
+  return `${sign}${abs / LAMPORTS_PER_SOL}.${fraction}`;
+}
+
+function isoDate(date: number): string {
+  return new Date(date * 1000).toISOString().slice(0, 10);
+}
+
+function isoDateTime(date: number): string {
+  return new Date(date * 1000).toISOString().slice(0, 19);
+}
+
+function description(entry: StatementEntry): string {
+  const what = entry.category.replace(/_/g, ' ');
+  return entry.counterparty ? `${what} ${entry.counterparty}` : what;
+}
+
+function csvField(value: string): string {
+  return /[",\n\r]/.test(value) ? `"${value.replace(/"/g, '""')}"` : value;
+}
+
+/** One row per entry, amounts in SOL. */
+export function toCsv(statement: Statement): string {
+  const rows = [
+    [
+      'Date',
+      'Reference',
+      'Description',
+      'Category',
+      'Counterparty',
+      'Debit',
+      'Credit',
+      'Signature',
+    ],
+    ...statement.entries.map((e) => [
+      isoDate(e.date),
+      e.reference ?? '',
+      description(e),
+      e.accountingCategory,
+      e.counterparty ?? '',
+      e.direction === 'debit' ? sol(e.amount) : '',
+      e.direction === 'credit' ? sol(e.amount) : '',
+      e.signature,
+    ]),
+  ];
+  return rows.map((row) => row.map(csvField).join(',')).join('\r\n') + '\r\n';
+}
+
+function xml(value: string): string {
+  return value
+    .replace(/&/g, '&amp;')
+    .replace(/</g, '&lt;')
+    .replace(/>/g, '&gt;')
+    .replace(/"/g, '&quot;');
+}
+
+function ofxDate(date: number): string {
+  return isoDateTime(date).replace(/[-T:]/g, '');
+}
+
+/** OFX 2.2 bank statement, the vault as the account. */
+export function toOfx(statement: Statement): string {
+  const transactions = statement.entries.map((e) => {
+    const signed = e.direction === 'credit' ? e.amount : -e.amount;
+    return [
+      '<STMTTRN>',
+      `<TRNTYPE>${e.direction === 'credit' ? 'CREDIT' : 'DEBIT'}</TRNTYPE>`,
+      `<DTPOSTED>${ofxDate(e.date)}</DTPOSTED>`,
+      `<TRNAMT>${sol(signed)}</TRNAMT>`,
+      `<FITID>${xml(e.id)}</FITID>`,
+      e.reference ? `<REFNUM>${e.reference.slice(0, 32)}</REFNUM>` : '',
+      `<NAME>${xml((e.counterparty ?? e.category).slice(0, 32))}</NAME>`,
+      `<MEMO>${xml(`${e.accountingCategory}: ${description(e)}`)}</MEMO>`,
+      '</STMTTRN>',
+    ]
+      .filter(Boolean)
+      .join('\n');
+  });
+  return [
+    '<?xml version="1.0" encoding="UTF-8" standalone="no"?>',
+    '<?OFX OFXHEADER="200" VERSION="220" SECURITY="NONE" ' +
+      'OLDFILEUID="NONE" NEWFILEUID="NONE"?>',
+    '<OFX>',
+    '<SIGNONMSGSRSV1><SONRS>',
+    '<STATUS><CODE>0</CODE><SEVERITY>INFO</SEVERITY></STATUS>',
+    `<DTSERVER>${ofxDate(Math.floor(Date.now() / 1000))}</DTSERVER>`,
+    '<LANGUAGE>ENG</LANGUAGE>',
+    '</SONRS></SIGNONMSGSRSV1>',
+    '<BANKMSGSRSV1><STMTTRNRS>',
+    '<TRNUID>0</TRNUID>',
+    '<STATUS><CODE>0</CODE><SEVERITY>INFO</SEVERITY></STATUS>',
+    '<STMTRS>',
+    `<CURDEF>${xml(statement.currency)}</CURDEF>`,
+    '<BANKACCTFROM>',
+    '<BANKID>OBSCURA</BANKID>',
+    `<ACCTID>${statement.vault}</ACCTID>`,
+    '<ACCTTYPE>CHECKING</ACCTTYPE>',
+    '</BANKACCTFROM>',
+    '<BANKTRANLIST>',
+    `<DTSTART>${ofxDate(statement.from)}</DTSTART>`,
+    `<DTEND>${ofxDate(statement.to)}</DTEND>`,
+    ...transactions,
+    '</BANKTRANLIST>',
+    '<LEDGERBAL>',
+    `<BALAMT>${sol(statement.closingBalance)}</BALAMT>`,
+    `<DTASOF>${ofxDate(statement.to)}</DTASOF>`,
+    '</LEDGERBAL>',
+    '</STMTRS>',
+    '</STMTTRNRS></BANKMSGSRSV1>',
+    '</OFX>',
+    '',
+  ].join('\n');
+}
+
+/** ISO 20022 camt.053.001.02 statement, the vault as the account. */
+export function toCamt053(statement: Statement): string {
+  const { currency } = statement;
+  const now = isoDateTime(Math.floor(Date.now() / 1000));
+  const id = `${statement.vault.slice(0, 16)}-${statement.from}`;
+  const balance = (code: string, amount: bigint, date: number) =>
+    [
+      '<Bal>',
+      `<Tp><CdOrPrtry><Cd>${code}</Cd></CdOrPrtry></Tp>`,
+      `<Amt Ccy="${xml(currency)}">${sol(amount < 0 ? -amount : amount)}</Amt>`,
+      `<CdtDbtInd>${amount < 0 ? 'DBIT' : 'CRDT'}</CdtDbtInd>`,
+      `<Dt><Dt>${isoDate(date)}</Dt></Dt>`,
+      '</Bal>',
+    ].join('\n');
+  const entries = statement.entries.map((e) =>
+    [
+      '<Ntry>',
+      `<NtryRef>${xml(e.id)}</NtryRef>`,
+      `<Amt Ccy="${xml(currency)}">${sol(e.amount)}</Amt>`,
+      `<CdtDbtInd>${e.direction === 'credit' ? 'CRDT' : 'DBIT'}</CdtDbtInd>`,
+      '<Sts>BOOK</Sts>',
+      `<BookgDt><Dt>${isoDate(e.date)}</Dt></BookgDt>`,
+      `<ValDt><Dt>${isoDate(e.date)}</Dt></ValDt>`,
+      `<AcctSvcrRef>${e.signature}</AcctSvcrRef>`,
+      '<BkTxCd><Prtry>',
+      `<Cd>${xml(e.accountingCategory)}</Cd>`,
+      '<Issr>OBSCURA</Issr>',
+      '</Prtry></BkTxCd>',
+      '<NtryDtls><TxDtls>',
+      '<Refs>',
+      `<AcctSvcrRef>${e.signature}</AcctSvcrRef>`,
+      `<EndToEndId>${e.reference ?? 'NOTPROVIDED'}</EndToEndId>`,
+      '</Refs>',
+      `<RmtInf><Ustrd>${xml(description(e).slice(0, 140))}</Ustrd></RmtInf>`,
+      '</TxDtls></NtryDtls>',
+      '</Ntry>',
+    ].join('\n')
+  );
+  return [
+    '<?xml version="1.0" encoding="UTF-8"?>',
+    '<Document xmlns="urn:iso:std:iso:20022:tech:xsd:camt.053.001.02">',
+    '<BkToCstmrStmt>',
+    '<GrpHdr>',
+    `<MsgId>${id}</MsgId>`,
+    `<CreDtTm>${now}</CreDtTm>`,
+    '</GrpHdr>',
+    '<Stmt>',
+    `<Id>${id}</Id>`,
+    `<CreDtTm>${now}</CreDtTm>`,
+    '<FrToDt>',
+    `<FrDtTm>${isoDateTime(statement.from)}</FrDtTm>`,
+    `<ToDtTm>${isoDateTime(statement.to)}</ToDtTm>`,
+    '</FrToDt>',
+    `<Acct><Id><Othr><Id>${statement.vault}</Id></Othr></Id>`,
+    `<Ccy>${xml(currency)}</Ccy></Acct>`,
+    balance('OPBD', statement.openingBalance, statement.from),
+    balance('CLBD', statement.closingBalance, statement.to),
+    ...entries,
+    '</Stmt>',
+    '</BkToCstmrStmt>',
+    '</Document>',
+    '',
+  ].join('\n');
+}
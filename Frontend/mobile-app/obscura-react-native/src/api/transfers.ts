@@ -0,0 +1,255 @@
+/**
+ * Transfer tracking
+ * Drives each outgoing vault transfer through Created → Submitted →
+ * Confirmed → Settled, persisted in AsyncStorage so a pending transfer
+ * survives an app restart.  A submission whose blockhash expires before
+ * it lands is rebuilt and resubmitted.  The vault's `nonce`, which the
+ * program advances once per transfer, tells whether an earlier
+ * submission already landed, so a transfer is never applied twice.
+ */
+
+import AsyncStorage from '@react-native-async-storage/async-storage';
+import { Connection, PublicKey, VersionedTransaction } from '@solana/web3.js';
+import { decodeProgramError, programErrorCode } from './programErrors';
+import { decodeVaultSnapshot } from './simulatePrivate';
+
+const STORAGE_KEY = 'obscura.transfers';
+const POLL_INTERVAL_MS = 2000;
+/** Submissions (fresh blockhashes) before giving up */
+const MAX_SUBMISSIONS = 5;
+
+export type TransferState =
+  | 'created'
+  | 'submitted'
+  | 'confirmed'
+  | 'settled'
+  | 'failed';
+
+export interface TrackedTransfer {
+  id: string;
+  vault: string;
+  /** Vault nonce once this transfer has been applied (decimal string) */
+  expectedNonce: string;
+  state: TransferState;
+  /** Every signature submitted for this transfer, latest last */
+  signatures: string[];
+  /** Block height after which the latest submission can no longer land */
+  lastValidBlockHeight: number | null;
+  error?: string;
+  /** Unix timestamps (ms) */
+  createdAt: number;
+  updatedAt: number;
+}
+
+export interface TransferEndpoints {
+  /** Where the transfer executes: the ER (a TEE session for private vaults) */
+  execution: Connection;
+  /** L1, where the committed vault shows the transfer settled */
+  settlement: Connection;
+}
+
+/** Build and sign the transfer against `recentBlockhash`. */
+export type BuildTransfer = (
+  recentBlockhash: string
+) => Promise<VersionedTransaction>;
+
+const sleep = (ms: number) => new Promise((resolve) => setTimeout(resolve, ms));
+
+function describeError(err: unknown): string {
+  const code = programErrorCode(err);
+  const decoded = code === null ? null : decodeProgramError(code);
+  if (decoded) {
+    return `${decoded.code}: ${decoded.remediation ?? decoded.message}`;
+  }
+  return err instanceof Error ? err.message : JSON.stringify(err);
+}
+
+export class TransferTracker {
+  constructor(
+    private endpoints: TransferEndpoints,
+    private onChange?: (transfer: TrackedTransfer) => void
+  ) {}
+
+  async list(): Promise<TrackedTransfer[]> {
+    const stored = await AsyncStorage.getItem(STORAGE_KEY);
+    return stored ? JSON.parse(stored) : [];
+  }
+
+  private async update(
+    transfer: TrackedTransfer,
+    changes: Partial<TrackedTransfer>
+  ): Promise<TrackedTransfer> {
+    const updated = { ...transfer, ...changes, updatedAt: Date.now() };
+    const transfers = (await this.list()).filter((t) => t.id !== transfer.id);
+    transfers.push(updated);
+    await AsyncStorage.setItem(STORAGE_KEY, JSON.stringify(transfers));
+    this.onChange?.(updated);
+    return updated;
+  }
+
+  /** Current vault nonce on `connection`, or null if the vault is absent. */
+  private async nonce(connection: Connection, vault: string) {
+    const account = await connection.getAccountInfo(new PublicKey(vault));
+    return account ? decodeVaultSnapshot(account.data).nonce : null;
+  }
+
+  private async applied(connection: Connection, transfer: TrackedTransfer) {
+    const nonce = await this.nonce(connection, transfer.vault);
+    return nonce !== null && nonce >= BigInt(transfer.expectedNonce);
+  }
+
+  /** Whether the transfer shows on the ER or, once committed, on L1. */
+  private async landed(transfer: TrackedTransfer) {
+    return (
+      (await this.applied(this.endpoints.settlement, transfer)) ||
+      (await this.applied(this.endpoints.execution, transfer))
+    );
+  }
+
+  /**
+   * Start tracking a transfer out of `vault`.  One transfer per vault is
+   * tracked at a time, since each expects the next nonce.
+   */
+  async create(vault: PublicKey): Promise<TrackedTransfer> {
+    const pending = (await this.list()).find(
+      (t) =>
+        t.vault === vault.toBase58() &&
+        t.state !== 'settled' &&
+        t.state !== 'failed'
+    );
+    if (pending) {
+      throw new Error('Another transfer from this vault is still pending');
+    }
+    const nonce = await this.nonce(this.endpoints.execution, vault.toBase58());
+    if (nonce === null) {
+      throw new Error('Vault not found');
+    }
+    const now = Date.now();
+    return this.update(
+      {
+        id: `${vault.toBase58()}:${nonce + BigInt(1)}`,
+        vault: vault.toBase58(),
+        expectedNonce: (nonce + BigInt(1)).toString(),
+        state: 'created',
+        signatures: [],
+        lastValidBlockHeight: null,
+        createdAt: now,
+        updatedAt: now,
+      },
+      {}
+    );
+  }
+
+  /** Drive `transfer` until it is settled on L1 or has failed. */
+  async run(
+    transfer: TrackedTransfer,
+    build: BuildTransfer
+  ): Promise<TrackedTransfer> {
+    const { execution, settlement } = this.endpoints;
+    for (;;) {
+      switch (transfer.state) {
+        case 'created': {
+          // An earlier submission may have landed after all.
+          if (await this.landed(transfer)) {
+            transfer = await this.update(transfer, { state: 'confirmed' });
+            break;
+          }
+          if (transfer.signatures.length >= MAX_SUBMISSIONS) {
+            return this.update(transfer, {
+              state: 'failed',
+              error: `Not confirmed after ${MAX_SUBMISSIONS} submissions`,
+            });
+          }
+          const { blockhash, lastValidBlockHeight } =
+            await execution.getLatestBlockhash('confirmed');
+          let signature: string;
+          try {
+            // No RPC-side retries: resubmission is ours to decide.
+            signature = await execution.sendTransaction(await build(blockhash), {
+              maxRetries: 0,
+            });
+          } catch (err) {
+            if (describeError(err).includes('Blockhash not found')) {
+              await sleep(POLL_INTERVAL_MS);
+              break;
+            }
+            return this.update(transfer, {
+              state: 'failed',
+              error: describeError(err),
+            });
+          }
+          transfer = await this.update(transfer, {
+            state: 'submitted',
+            signatures: [...transfer.signatures, signature],
+            lastValidBlockHeight,
+          });
+          break;
+        }
+
+        case 'submitted': {
+          const signature = transfer.signatures[transfer.signatures.length - 1];
+          const { value } = await execution.getSignatureStatuses([signature]);
+          const status = value[0];
+          if (status?.err) {
+            return this.update(transfer, {
+              state: 'failed',
+              error: describeError(status.err),
+            });
+          }
+          if (
+            status?.confirmationStatus === 'confirmed' ||
+            status?.confirmationStatus === 'finalized'
+          ) {
+            transfer = await this.update(transfer, { state: 'confirmed' });
+            break;
+          }
+          const height = await execution.getBlockHeight('confirmed');
+          if (height > (transfer.lastValidBlockHeight ?? 0)) {
+            // Expired: rebuild with a fresh blockhash.
+            transfer = await this.update(transfer, { state: 'created' });
+            break;
+          }
+          await sleep(POLL_INTERVAL_MS);
+          break;
+        }
+
+        case 'confirmed': {
+          if (await this.applied(settlement, transfer)) {
+            return this.update(transfer, { state: 'settled' });
+          }
+          // A confirmed submission can still be rolled back by a reorg.
+          if (!(await this.landed(transfer))) {
+            transfer = await this.update(transfer, { state: 'created' });
+            break;
+          }
+          await sleep(POLL_INTERVAL_MS);
+          break;
+        }
+
+        case 'settled':
+        case 'failed':
+          return transfer;
+      }
+    }
+  }
+
+  /**
+   * Continue every unfinished transfer, e.g. on app start.  `builderFor`
+   * returns how to rebuild a transfer, or undefined to leave it for now.
+   */
+  async resume(
+    builderFor: (transfer: TrackedTransfer) => BuildTransfer | undefined
+  ): Promise<TrackedTransfer[]> {
+    const unfinished = (await this.list()).filter(
+      (t) => t.state !== 'settled' && t.state !== 'failed'
+    );
+    const resumed: TrackedTransfer[] = [];
+    for (const transfer of unfinished) {
+      const build = builderFor(transfer);
+      if (build) {
+        resumed.push(await this.run(transfer, build));
+      }
+    }
+    return resumed;
+  }
+}
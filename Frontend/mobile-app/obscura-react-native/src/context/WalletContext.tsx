@@ -17,6 +17,7 @@ import {
   LAMPORTS_PER_SOL,
 } from '@solana/web3.js';
 import bs58 from 'bs58';
+import { RpcEndpoint, RpcPool } from '../api/rpcFailover';
 
 export type ChainType = 'solana' | 'ethereum' | 'polygon' | 'arbitrum';
 
@@ -50,9 +51,13 @@ const initialState: WalletState = {
   walletType: null,
 };
 
-// Solana connection
-const SOLANA_RPC = clusterApiUrl('devnet');
-const connection = new Connection(SOLANA_RPC);
+// Solana connection; add fallbacks with weight 0
+const SOLANA_RPC_ENDPOINTS: RpcEndpoint[] = [
+  { url: clusterApiUrl('devnet'), weight: 1 },
+];
+const rpcPool = new RpcPool(SOLANA_RPC_ENDPOINTS);
+rpcPool.start();
+const connection = rpcPool.connection();
 
 // App identity for Mobile Wallet Adapter
 const APP_IDENTITY = {